@@ -0,0 +1,242 @@
+//! Custom Render Pass Example
+//!
+//! This example shows how to:
+//! - Register a render extension from a plugin
+//! - Build a pipeline and bind group layout from `RenderSetup`
+//! - Add a render node that draws after tonemapping
+//!
+//! Run with: `cargo run --example custom_pass`
+
+use anyhow::{Result, anyhow};
+use bytemuck::{Pod, Zeroable};
+use resonance::prelude::*;
+use resonance::renderer::{
+    RenderContext, RenderExtension, RenderExtensions, RenderGraph, RenderNode, RenderSetup,
+};
+use wgpu::util::DeviceExt;
+
+fn main() {
+    Resonance::new()
+        .with_log_level(log::LevelFilter::Info)
+        .add_plugin(DefaultPlugins)
+        .add_plugin(CrosshairPlugin)
+        .add_system(Stage::Startup, setup_scene)
+        .run();
+}
+
+fn setup_scene(world: &mut World) {
+    world.spawn((
+        Transform::from_xyz(0.0, 2.0, 5.0),
+        Camera::perspective(16.0 / 9.0),
+    ));
+}
+
+/// Draws a crosshair in the middle of the window.
+#[derive(Default)]
+struct CrosshairPlugin;
+
+impl Plugin for CrosshairPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine
+            .world
+            .get_resource_or_init::<RenderExtensions>()
+            .add(CrosshairExtension);
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+struct CrosshairExtension;
+
+impl RenderExtension for CrosshairExtension {
+    fn name(&self) -> &str {
+        "crosshair"
+    }
+
+    fn setup(&self, setup: &RenderSetup, world: &mut World, graph: &mut RenderGraph) {
+        world.insert_resource(CrosshairPipeline::new(setup));
+        graph.add_node(Box::new(CrosshairNode));
+    }
+}
+
+const CROSSHAIR_SHADER: &str = r#"
+struct Crosshair {
+    color: vec4<f32>,
+    // Half length and half thickness in pixels, then the surface size
+    size: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> crosshair: Crosshair;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A horizontal and a vertical bar, two triangles each
+    var corners = array<vec2<f32>, 6>(
+        vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0),
+        vec2(-1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0),
+    );
+    var half_extent = crosshair.size.xy;
+    if index >= 6u {
+        half_extent = half_extent.yx;
+    }
+    let pixels = corners[index % 6u] * half_extent;
+    return vec4(pixels * 2.0 / crosshair.size.zw, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return crosshair.color;
+}
+"#;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CrosshairUniform {
+    color: [f32; 4],
+    size: [f32; 4],
+}
+
+#[derive(Resource)]
+struct CrosshairPipeline {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl CrosshairPipeline {
+    fn new(setup: &RenderSetup) -> Self {
+        let device = setup.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Crosshair Shader"),
+            source: wgpu::ShaderSource::Wgsl(CROSSHAIR_SHADER.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Crosshair Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Crosshair Uniform Buffer"),
+            contents: bytemuck::bytes_of(&CrosshairUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Crosshair Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Crosshair Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Drawn after tonemapping, so it targets the surface format without depth or MSAA
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Crosshair Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: setup.surface_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+}
+
+struct CrosshairNode;
+
+impl RenderNode for CrosshairNode {
+    fn name(&self) -> &str {
+        "crosshair"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &["tonemap"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Result<()> {
+        let pipeline = world
+            .get_resource::<CrosshairPipeline>()
+            .ok_or_else(|| anyhow!("CrosshairPipeline missing"))?;
+
+        let uniform = CrosshairUniform {
+            color: [1.0, 1.0, 1.0, 0.8],
+            size: [
+                10.0,
+                1.0,
+                context.surface_config.width as f32,
+                context.surface_config.height as f32,
+            ],
+        };
+        context
+            .queue
+            .write_buffer(&pipeline.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Crosshair Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: context.surface_view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &pipeline.bind_group, &[]);
+        pass.draw(0..12, 0..1);
+
+        Ok(())
+    }
+}
@@ -44,7 +44,7 @@ fn setup_scene(world: &mut World) {
 fn rotate_cubes(mut query: Query<(&mut Transform, &RotatingCube)>, time: Res<Time>) {
     for (mut transform, cube) in query.iter_mut() {
         // Rotate around Y axis based on time and cube's speed
-        let rotation_speed = cube.speed * time.delta();
+        let rotation_speed = cube.speed * time.delta_seconds();
         transform.rotation = Quat::from_rotation_y(rotation_speed) * transform.rotation;
     }
 }
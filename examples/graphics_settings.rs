@@ -13,12 +13,7 @@ fn main() {
     Resonance::new()
         .with_log_level(log::LevelFilter::Info)
         // Configure window settings
-        .with_resource(WindowConfig {
-            width: 1920,
-            height: 1080,
-            title: "Graphics Settings Example".to_string(),
-            mode: WindowMode::Windowed,
-        })
+        .with_resource(WindowConfig::new(1920, 1080, "Graphics Settings Example"))
         // Configure graphics settings (MSAA x4, VSync enabled)
         .with_graphics_settings(GraphicsSettings::new(MsaaSampleCount::X4, true))
         .add_plugin(DefaultPlugins)
@@ -30,7 +25,7 @@ fn setup_scene(world: &mut World) {
     // Spawn a camera
     world.spawn((
         Transform::from_xyz(0.0, 2.0, 5.0),
-        Camera::perspective(16.0 / 9.0, 60.0),
+        Camera::new(60.0_f32.to_radians(), 16.0 / 9.0, 0.1, 10000.0),
     ));
 
     println!("Scene setup complete!");
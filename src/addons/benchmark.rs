@@ -0,0 +1,440 @@
+//! Scripted performance capture.
+//!
+//! [`BenchmarkRecorder`] captures the main camera path and keyboard/mouse input
+//! while playing. A [`BenchmarkRun`] plays a recording back, collects frame
+//! times, draw counts and memory usage, writes a [`BenchmarkReport`] as JSON and
+//! exits, so renderer changes can be compared on the same content.
+//!
+//! The recording names the scene it was captured in. When the [`Scenes`] resource is
+//! present, a run swaps the loaded levels for that scene before playback starts; its
+//! warmup frames cover the load.
+
+use super::scene::Scenes;
+use super::sequencer::{TransformKeyframe, TransformTrack};
+use crate::app::{Plugin, Resonance, Stage};
+pub use crate::core::Distribution;
+use crate::core::math::*;
+use crate::core::time::Time;
+use crate::core::{AppExit, MemoryTracker};
+use crate::input::{Input, KeyCode};
+use crate::renderer::Camera;
+use crate::renderer::components::IndirectDrawData;
+use crate::transform::Transform;
+use bevy_ecs::message::Messages;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Input state for one recorded frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputSample {
+    pub time: f32,
+    pub keys_down: Vec<KeyCode>,
+    pub mouse_delta: Vec2,
+}
+
+/// A camera path and input stream captured in a scene.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkRecording {
+    /// [`Scenes`] name of the scene the recording was captured in. Loaded for playback and
+    /// reported back in the results.
+    pub scene: String,
+    pub duration: f32,
+    pub camera_path: TransformTrack,
+    pub inputs: Vec<InputSample>,
+}
+
+impl BenchmarkRecording {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path.as_ref(), contents)?;
+        Ok(())
+    }
+
+    /// Last input sample at or before `time`.
+    pub fn input_at(&self, time: f32) -> Option<&InputSample> {
+        let index = self.inputs.partition_point(|sample| sample.time <= time);
+        index.checked_sub(1).map(|index| &self.inputs[index])
+    }
+}
+
+/// Insert to start recording. Remove it and call [`BenchmarkRecorder::finish`] to save.
+#[derive(Resource, Debug, Clone)]
+pub struct BenchmarkRecorder {
+    pub recording: BenchmarkRecording,
+    /// Seconds between camera keyframes.
+    pub keyframe_interval: f32,
+    elapsed: f32,
+    next_keyframe: f32,
+}
+
+impl BenchmarkRecorder {
+    pub fn new(scene: impl Into<String>) -> Self {
+        Self {
+            recording: BenchmarkRecording {
+                scene: scene.into(),
+                ..Default::default()
+            },
+            keyframe_interval: 0.1,
+            elapsed: 0.0,
+            next_keyframe: 0.0,
+        }
+    }
+
+    pub fn finish(mut self, path: impl AsRef<Path>) -> anyhow::Result<BenchmarkRecording> {
+        self.recording.duration = self.elapsed;
+        self.recording.save(path)?;
+        Ok(self.recording)
+    }
+}
+
+/// Machine-readable result of a [`BenchmarkRun`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub scene: String,
+    pub frames: usize,
+    pub duration_seconds: f64,
+    pub average_fps: f64,
+    pub frame_time_ms: Distribution,
+    pub draw_batches: Distribution,
+    pub draw_commands: Distribution,
+    pub peak_gpu_memory_bytes: u64,
+    pub peak_process_memory_bytes: u64,
+}
+
+/// Plays back a [`BenchmarkRecording`] and writes a report to `output` when it ends.
+#[derive(Resource, Debug, Clone)]
+pub struct BenchmarkRun {
+    pub recording: BenchmarkRecording,
+    pub output: PathBuf,
+    /// Frames at the start that are played but not measured, to skip shader compilation and uploads.
+    pub warmup_frames: usize,
+    /// Exit the engine once the report is written.
+    pub exit_when_done: bool,
+    elapsed: f32,
+    frame: usize,
+    frame_times_ms: Vec<f64>,
+    batches: Vec<f64>,
+    commands: Vec<f64>,
+    peak_gpu_memory: u64,
+    peak_process_memory: u64,
+    scene_requested: bool,
+}
+
+impl BenchmarkRun {
+    pub fn new(recording: BenchmarkRecording, output: impl Into<PathBuf>) -> Self {
+        Self {
+            recording,
+            output: output.into(),
+            warmup_frames: 30,
+            exit_when_done: true,
+            elapsed: 0.0,
+            frame: 0,
+            frame_times_ms: Vec::new(),
+            batches: Vec::new(),
+            commands: Vec::new(),
+            peak_gpu_memory: 0,
+            peak_process_memory: 0,
+            scene_requested: false,
+        }
+    }
+
+    /// Reads `--benchmark <recording.ron>` and optional `--benchmark-report <report.json>`
+    /// from the command line.
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let value_of = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+        };
+
+        let recording_path = value_of("--benchmark")?;
+        let recording = match BenchmarkRecording::load(recording_path) {
+            Ok(recording) => recording,
+            Err(e) => {
+                log::error!(
+                    "Failed to load benchmark recording {}: {}",
+                    recording_path,
+                    e
+                );
+                return None;
+            }
+        };
+        let output = value_of("--benchmark-report")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("benchmark_report.json"));
+
+        Some(Self::new(recording, output))
+    }
+
+    pub fn report(&self) -> BenchmarkReport {
+        let duration_seconds = self.frame_times_ms.iter().sum::<f64>() / 1000.0;
+        BenchmarkReport {
+            scene: self.recording.scene.clone(),
+            frames: self.frame_times_ms.len(),
+            duration_seconds,
+            average_fps: if duration_seconds > 0.0 {
+                self.frame_times_ms.len() as f64 / duration_seconds
+            } else {
+                0.0
+            },
+            frame_time_ms: Distribution::from_samples(&self.frame_times_ms),
+            draw_batches: Distribution::from_samples(&self.batches),
+            draw_commands: Distribution::from_samples(&self.commands),
+            peak_gpu_memory_bytes: self.peak_gpu_memory,
+            peak_process_memory_bytes: self.peak_process_memory,
+        }
+    }
+}
+
+/// Records with [`BenchmarkRecorder`] and plays back [`BenchmarkRun`]s.
+///
+/// Picks up a run from the command line with [`BenchmarkRun::from_args`].
+#[derive(Default)]
+pub struct BenchmarkPlugin;
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<Messages<AppExit>>();
+
+        if let Some(run) = BenchmarkRun::from_args() {
+            log::info!(
+                "Benchmark mode: playing {:.1}s recording of '{}'",
+                run.recording.duration,
+                run.recording.scene
+            );
+            engine.world.insert_resource(run);
+        }
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            schedule.add_systems(benchmark_playback_system);
+        }
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
+            schedule.add_systems((benchmark_record_system, benchmark_measure_system));
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::input::InputPlugin>(),
+            "resonance::input::InputPlugin",
+        )]
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+pub fn benchmark_record_system(
+    time: Res<Time>,
+    input: Option<Res<Input>>,
+    recorder: Option<ResMut<BenchmarkRecorder>>,
+    cameras: Query<&Transform, With<Camera>>,
+) {
+    let Some(mut recorder) = recorder else { return };
+    let elapsed = recorder.elapsed;
+
+    if let Some(input) = input {
+        let mut keys_down: Vec<KeyCode> = input.keyboard.pressed_keys().collect();
+        keys_down.sort();
+        recorder.recording.inputs.push(InputSample {
+            time: elapsed,
+            keys_down,
+            mouse_delta: input.mouse.delta(),
+        });
+    }
+
+    if elapsed >= recorder.next_keyframe {
+        if let Some(transform) = cameras.iter().next() {
+            recorder.recording.camera_path.target = "camera".to_string();
+            recorder
+                .recording
+                .camera_path
+                .keyframes
+                .push(TransformKeyframe::from_transform(elapsed, transform));
+        }
+        recorder.next_keyframe = elapsed + recorder.keyframe_interval;
+    }
+
+    recorder.elapsed += time.delta().as_secs_f32();
+}
+
+pub fn benchmark_playback_system(
+    run: Option<ResMut<BenchmarkRun>>,
+    scenes: Option<ResMut<Scenes>>,
+    input: Option<ResMut<Input>>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+) {
+    let Some(mut run) = run else { return };
+    let time = run.elapsed;
+
+    if !run.scene_requested {
+        run.scene_requested = true;
+        if let Some(mut scenes) = scenes {
+            load_benchmark_scene(&mut scenes, &run.recording.scene);
+        }
+    }
+
+    let pose = run.recording.camera_path.sample(time);
+    if let (Some(pose), Some(mut transform)) = (pose, cameras.iter_mut().next()) {
+        *transform = pose;
+    }
+
+    let (Some(mut input), Some(sample)) = (input, run.recording.input_at(time)) else {
+        return;
+    };
+
+    let held: Vec<KeyCode> = input.keyboard.pressed_keys().collect();
+    for key in held {
+        if !sample.keys_down.contains(&key) {
+            input.keyboard.release(key);
+        }
+    }
+    for key in &sample.keys_down {
+        input.keyboard.press(*key);
+    }
+    // Mouse deltas are per recorded frame, so look speed follows the playback
+    // frame rate. The camera path above is authoritative for benchmark views.
+    input
+        .mouse
+        .add_motion_delta(sample.mouse_delta.x, sample.mouse_delta.y);
+}
+
+/// Replaces the loaded levels with `scene`, unless it is already loaded.
+fn load_benchmark_scene(scenes: &mut Scenes, scene: &str) {
+    if scene.is_empty() || scenes.loaded().iter().any(|loaded| loaded.name == scene) {
+        return;
+    }
+    scenes.unload_levels();
+    if scenes.load(scene).is_none() {
+        log::warn!(
+            "Benchmark scene '{}' is not registered, playing in the current scene",
+            scene
+        );
+    }
+}
+
+pub fn benchmark_measure_system(
+    time: Res<Time>,
+    run: Option<ResMut<BenchmarkRun>>,
+    memory: Option<Res<MemoryTracker>>,
+    indirect: Option<Res<IndirectDrawData>>,
+    mut exit: Option<ResMut<Messages<AppExit>>>,
+) {
+    let Some(mut run) = run else { return };
+
+    let frame_time = time.delta();
+    run.frame += 1;
+
+    if run.frame > run.warmup_frames {
+        run.frame_times_ms.push(frame_time.as_secs_f64() * 1000.0);
+
+        let (batches, commands) = indirect
+            .map(|indirect| {
+                let commands: u32 = indirect.batches.iter().map(|batch| batch.draw_count).sum();
+                (indirect.batches.len() as f64, commands as f64)
+            })
+            .unwrap_or_default();
+        run.batches.push(batches);
+        run.commands.push(commands);
+
+        if let Some(memory) = memory {
+            run.peak_gpu_memory = run.peak_gpu_memory.max(memory.gpu.total());
+            run.peak_process_memory = run.peak_process_memory.max(memory.process.process_bytes);
+        }
+
+        // The path plays in real time so every run covers the same content
+        run.elapsed += frame_time.as_secs_f32();
+    }
+
+    if run.elapsed < run.recording.duration {
+        return;
+    }
+
+    let report = run.report();
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => match std::fs::write(&run.output, json) {
+            Ok(()) => log::info!(
+                "Benchmark finished: {} frames, p50 {:.2}ms, p99 {:.2}ms, report written to {}",
+                report.frames,
+                report.frame_time_ms.p50,
+                report.frame_time_ms.p99,
+                run.output.display()
+            ),
+            Err(e) => log::error!("Failed to write benchmark report: {}", e),
+        },
+        Err(e) => log::error!("Failed to serialize benchmark report: {}", e),
+    }
+
+    // Keep measuring only once
+    run.recording.duration = f32::INFINITY;
+    if let Some(exit) = exit.as_mut().filter(|_| run.exit_when_done) {
+        exit.write(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addons::scene::{SceneLoaded, SceneUnloaded, scene_system};
+
+    #[test]
+    fn test_input_at() {
+        let recording = BenchmarkRecording {
+            inputs: vec![
+                InputSample {
+                    time: 0.0,
+                    keys_down: vec![],
+                    mouse_delta: Vec2::ZERO,
+                },
+                InputSample {
+                    time: 0.5,
+                    keys_down: vec![KeyCode::KeyW],
+                    mouse_delta: Vec2::ZERO,
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(recording.input_at(0.2).unwrap().keys_down.is_empty());
+        assert_eq!(
+            recording.input_at(0.7).unwrap().keys_down,
+            vec![KeyCode::KeyW]
+        );
+        assert!(recording.input_at(-1.0).is_none());
+    }
+
+    #[test]
+    fn test_run_loads_recorded_scene() {
+        let mut world = World::new();
+        world.init_resource::<Messages<SceneLoaded>>();
+        world.init_resource::<Messages<SceneUnloaded>>();
+        let mut scenes = Scenes::default();
+        scenes.register("menu", |_| {});
+        scenes.register("arena", |_| {});
+        scenes.load("menu");
+        world.insert_resource(scenes);
+        scene_system(&mut world);
+
+        load_benchmark_scene(&mut world.resource_mut::<Scenes>(), "arena");
+        scene_system(&mut world);
+        let loaded: Vec<&str> = world
+            .resource::<Scenes>()
+            .loaded()
+            .iter()
+            .map(|scene| scene.name.as_str())
+            .collect();
+        assert_eq!(loaded, ["arena"]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Switching between camera shots with a cut or a blend.
+//!
+//! Only one entity renders: the one with a [`CameraDirector`], which copies the pose and
+//! field of view of the active [`VirtualCamera`] shot every frame. Switching shots either
+//! cuts, or blends from wherever the director currently is, so a switch in the middle of a
+//! blend continues smoothly. Put the [`AudioListener`](crate::audio::AudioListener) on the
+//! director too, and the listener always hears from where the picture is taken:
+//!
+//! ```ignore
+//! fn setup(mut commands: Commands) {
+//!     let gameplay = commands
+//!         .spawn((Transform::from_xyz(0.0, 2.0, 5.0), VirtualCamera::default()))
+//!         .id();
+//!     commands.spawn((
+//!         Transform::new(),
+//!         Camera::perspective(16.0 / 9.0),
+//!         AudioListener::new(),
+//!         CameraDirector::new(gameplay),
+//!     ));
+//! }
+//!
+//! fn on_death(mut directors: Query<&mut CameraDirector>, death_cam: Res<DeathCam>) {
+//!     for mut director in &mut directors {
+//!         director.switch_to(death_cam.0, CameraTransition::Blend { duration: 1.5 });
+//!     }
+//! }
+//! ```
+//!
+//! On a cut the director is tagged with [`CameraCut`] for one frame, which zeroes its motion
+//! vectors and discards the TAA history, and its [`AudioVelocity`] is zeroed so the jump isn't
+//! heard as Doppler shift.
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::audio::AudioVelocity;
+use crate::core::math::*;
+use crate::core::time::Time;
+use crate::renderer::{Camera, CameraCut, Renderer};
+use crate::transform::Transform;
+use bevy_ecs::prelude::*;
+
+/// A shot the director can switch to. Its [`Transform`] is the camera pose; it isn't
+/// rendered itself.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VirtualCamera {
+    /// Vertical field of view in radians.
+    pub fov: f32,
+}
+
+impl Default for VirtualCamera {
+    fn default() -> Self {
+        Self {
+            fov: 45.0_f32.to_radians(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraTransition {
+    Cut,
+    /// Eases from the current pose to the new shot over `duration` seconds.
+    Blend {
+        duration: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Blend {
+    from_position: Vec3,
+    from_rotation: Quat,
+    from_fov: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Drives the rendered camera from the active [`VirtualCamera`].
+#[derive(Component, Debug, Clone)]
+pub struct CameraDirector {
+    active: Entity,
+    pending: Option<CameraTransition>,
+    blend: Option<Blend>,
+}
+
+impl CameraDirector {
+    /// Starts on `shot`, cutting to it on the first frame.
+    pub fn new(shot: Entity) -> Self {
+        Self {
+            active: shot,
+            pending: Some(CameraTransition::Cut),
+            blend: None,
+        }
+    }
+
+    /// Makes `shot` active, applied by [`camera_director_system`] this or next frame.
+    pub fn switch_to(&mut self, shot: Entity, transition: CameraTransition) {
+        self.active = shot;
+        self.pending = Some(transition);
+    }
+
+    pub fn active(&self) -> Entity {
+        self.active
+    }
+
+    pub fn is_blending(&self) -> bool {
+        self.blend.is_some()
+    }
+}
+
+#[derive(Default)]
+pub struct CameraBlendPlugin;
+
+impl Plugin for CameraBlendPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        use crate::addons::camera_controller::{
+            first_person_camera_system, third_person_camera_system,
+        };
+        use crate::audio::systems::{apply_doppler_effect, update_spatial_audio};
+
+        // After shots are moved by controllers, and before audio reads the listener
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
+            schedule.add_systems(
+                camera_director_system
+                    .after(first_person_camera_system)
+                    .after(third_person_camera_system)
+                    .before(update_spatial_audio)
+                    .before(apply_doppler_effect),
+            );
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::transform::TransformPlugin>(),
+            "resonance::transform::TransformPlugin",
+        )]
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+pub fn camera_director_system(
+    mut commands: Commands,
+    time: Option<Res<Time>>,
+    mut renderer: Option<ResMut<Renderer>>,
+    cut_last_frame: Query<Entity, With<CameraCut>>,
+    shots: Query<(&Transform, &VirtualCamera), Without<CameraDirector>>,
+    mut directors: Query<(
+        Entity,
+        &mut CameraDirector,
+        &mut Transform,
+        &mut Camera,
+        Option<&mut AudioVelocity>,
+    )>,
+) {
+    for entity in &cut_last_frame {
+        commands.entity(entity).remove::<CameraCut>();
+    }
+
+    let delta = time.map(|time| time.delta_seconds()).unwrap_or(0.0);
+
+    for (entity, mut director, mut transform, mut camera, velocity) in &mut directors {
+        let Ok((shot, virtual_camera)) = shots.get(director.active) else {
+            continue;
+        };
+
+        let cut = match director.pending.take() {
+            Some(CameraTransition::Cut) => {
+                director.blend = None;
+                true
+            }
+            Some(CameraTransition::Blend { duration }) if duration > 0.0 => {
+                director.blend = Some(Blend {
+                    from_position: transform.position,
+                    from_rotation: transform.rotation,
+                    from_fov: camera.fov,
+                    elapsed: 0.0,
+                    duration,
+                });
+                false
+            }
+            Some(CameraTransition::Blend { .. }) => {
+                director.blend = None;
+                false
+            }
+            None => false,
+        };
+
+        let previous_position = transform.position;
+        let (position, rotation, fov) = match &mut director.blend {
+            Some(blend) => {
+                blend.elapsed += delta;
+                let t = blend_weight(blend.elapsed / blend.duration);
+                (
+                    blend.from_position.lerp(shot.position, t),
+                    blend.from_rotation.slerp(shot.rotation, t),
+                    blend.from_fov + (virtual_camera.fov - blend.from_fov) * t,
+                )
+            }
+            None => (shot.position, shot.rotation, virtual_camera.fov),
+        };
+        if director
+            .blend
+            .is_some_and(|blend| blend.elapsed >= blend.duration)
+        {
+            director.blend = None;
+        }
+
+        transform.position = position;
+        transform.rotation = rotation;
+        camera.fov = fov;
+
+        if let Some(mut velocity) = velocity {
+            velocity.velocity = if cut || delta <= 0.0 {
+                Vec3::ZERO
+            } else {
+                (position - previous_position) / delta
+            };
+        }
+
+        if cut {
+            commands.entity(entity).insert(CameraCut);
+            if let Some(renderer) = renderer.as_mut() {
+                renderer.reset_taa_history();
+            }
+        }
+    }
+}
+
+/// Smoothstep, so blends start and end without a jolt.
+fn blend_weight(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_weight_eases() {
+        assert_eq!(blend_weight(0.0), 0.0);
+        assert_eq!(blend_weight(0.5), 0.5);
+        assert_eq!(blend_weight(1.0), 1.0);
+        assert_eq!(blend_weight(2.0), 1.0);
+        assert!(blend_weight(0.1) < 0.1);
+    }
+}
@@ -0,0 +1,389 @@
+use super::FlyCam;
+use crate::app::{Plugin, Resonance, Stage};
+use crate::core::math::*;
+use crate::input::{Input, KeyCode};
+use crate::renderer::Aabb;
+use crate::transform::{GlobalTransform, Parent, Transform};
+use crate::window::Window;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const MAX_PITCH: f32 = 89.0_f32 * std::f32::consts::PI / 180.0;
+
+/// Shortest boom a collision may leave, so the camera stays in front of the near plane.
+const MIN_BOOM_LENGTH: f32 = 0.05;
+
+type ControlledCamera = Or<(With<FirstPersonCamera>, With<ThirdPersonOrbitCamera>)>;
+type ColliderQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Aabb, &'static GlobalTransform),
+    Without<ThirdPersonOrbitCamera>,
+>;
+
+/// Look settings shared by [`FirstPersonCamera`] and [`ThirdPersonOrbitCamera`].
+///
+/// Serializable so it can live in a game's settings file.
+#[derive(Resource, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraControllerSettings {
+    pub sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+}
+
+impl CameraControllerSettings {
+    fn look_delta(&self, mouse_delta: Vec2) -> Vec2 {
+        let x = if self.invert_x {
+            mouse_delta.x
+        } else {
+            -mouse_delta.x
+        };
+        let y = if self.invert_y {
+            mouse_delta.y
+        } else {
+            -mouse_delta.y
+        };
+        Vec2::new(x, y) * self.sensitivity * 0.01
+    }
+}
+
+impl Default for CameraControllerSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.2,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+/// Mouse-look camera, optionally attached to a target entity (e.g. the player).
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FirstPersonCamera {
+    /// Entity whose position the camera follows. `None` leaves the position untouched.
+    pub target: Option<Entity>,
+    /// Offset from the target's origin, usually the eye height.
+    pub eye_offset: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl FirstPersonCamera {
+    pub fn new(target: Entity, eye_height: f32) -> Self {
+        Self {
+            target: Some(target),
+            eye_offset: Vec3::new(0.0, eye_height, 0.0),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for FirstPersonCamera {
+    fn default() -> Self {
+        Self {
+            target: None,
+            eye_offset: Vec3::new(0.0, 1.7, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+}
+
+/// Camera orbiting a target on a boom that shortens when geometry is in the way.
+///
+/// Collision is tested against the world-space [`Aabb`] of every mesh entity outside the
+/// target's hierarchy, so the target's own child meshes don't block the boom. Geometry
+/// closer than `min_distance` still pulls the camera in; `min_distance` only limits zooming.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ThirdPersonOrbitCamera {
+    pub target: Entity,
+    /// Offset from the target's origin to the orbit pivot.
+    pub pivot_offset: Vec3,
+    /// Desired boom length.
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub zoom_speed: f32,
+    /// Gap kept between the camera and whatever blocked the boom.
+    pub collision_margin: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Boom length after collision, updated every frame.
+    pub current_distance: f32,
+}
+
+impl ThirdPersonOrbitCamera {
+    pub fn new(target: Entity, distance: f32) -> Self {
+        Self {
+            target,
+            pivot_offset: Vec3::new(0.0, 1.5, 0.0),
+            distance,
+            min_distance: 1.0,
+            max_distance: 20.0,
+            zoom_speed: 1.0,
+            collision_margin: 0.2,
+            yaw: 0.0,
+            pitch: -0.3,
+            current_distance: distance,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<CameraControllerSettings>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
+            schedule.add_systems((
+                cursor_grab_system,
+                first_person_camera_system,
+                third_person_camera_system,
+            ));
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![
+            (
+                std::any::TypeId::of::<crate::input::InputPlugin>(),
+                "resonance::input::InputPlugin",
+            ),
+            (
+                std::any::TypeId::of::<crate::transform::TransformPlugin>(),
+                "resonance::transform::TransformPlugin",
+            ),
+        ]
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+/// Whether mouse look is active. Escape toggles it, like [`FlyCam`].
+///
+/// A [`FlyCam`] in the world grabs the cursor itself, so this resource is left alone
+/// while one exists.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CameraCursorGrab {
+    pub active: bool,
+}
+
+fn cursor_grab_system(
+    mut commands: Commands,
+    input: Option<Res<Input>>,
+    window: Option<Res<Window>>,
+    grab: Option<ResMut<CameraCursorGrab>>,
+    cameras: Query<(), ControlledCamera>,
+    flycams: Query<(), With<FlyCam>>,
+) {
+    if cameras.is_empty() || !flycams.is_empty() {
+        return;
+    }
+
+    let Some(mut grab) = grab else {
+        if let Some(window) = window.as_ref() {
+            window.set_cursor_visible(false);
+            let _ = window.set_cursor_grab(true);
+        }
+        commands.insert_resource(CameraCursorGrab { active: true });
+        return;
+    };
+
+    let Some(input) = input else { return };
+
+    if input.keyboard.just_pressed(KeyCode::Escape) {
+        grab.active = !grab.active;
+
+        if let Some(window) = window.as_ref() {
+            window.set_cursor_visible(!grab.active);
+            let _ = window.set_cursor_grab(grab.active);
+        }
+    }
+}
+
+pub fn first_person_camera_system(
+    input: Option<Res<Input>>,
+    settings: Option<Res<CameraControllerSettings>>,
+    grab: Option<Res<CameraCursorGrab>>,
+    targets: Query<&GlobalTransform, Without<FirstPersonCamera>>,
+    mut cameras: Query<(&mut Transform, &mut FirstPersonCamera)>,
+) {
+    let Some(input) = input else { return };
+    let settings = settings.map(|s| *s).unwrap_or_default();
+    let active = grab.map(|g| g.active).unwrap_or(false);
+
+    let look = if active {
+        settings.look_delta(input.mouse.delta())
+    } else {
+        Vec2::ZERO
+    };
+
+    for (mut transform, mut camera) in cameras.iter_mut() {
+        camera.yaw += look.x;
+        camera.pitch = (camera.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, camera.yaw, camera.pitch, 0.0);
+
+        if let Some(target_transform) = camera.target.and_then(|target| targets.get(target).ok()) {
+            transform.position = target_transform.position() + camera.eye_offset;
+        }
+    }
+}
+
+pub fn third_person_camera_system(
+    input: Option<Res<Input>>,
+    settings: Option<Res<CameraControllerSettings>>,
+    grab: Option<Res<CameraCursorGrab>>,
+    targets: Query<&GlobalTransform, Without<ThirdPersonOrbitCamera>>,
+    colliders: ColliderQuery,
+    parents: Query<&Parent>,
+    mut cameras: Query<(&mut Transform, &mut ThirdPersonOrbitCamera)>,
+) {
+    let Some(input) = input else { return };
+    let settings = settings.map(|s| *s).unwrap_or_default();
+    let active = grab.map(|g| g.active).unwrap_or(false);
+
+    let look = if active {
+        settings.look_delta(input.mouse.delta())
+    } else {
+        Vec2::ZERO
+    };
+    let scroll = input.mouse.scroll_delta();
+
+    for (mut transform, mut camera) in cameras.iter_mut() {
+        let Ok(target_transform) = targets.get(camera.target) else {
+            continue;
+        };
+
+        camera.yaw += look.x;
+        camera.pitch = (camera.pitch + look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        camera.distance = (camera.distance - scroll * camera.zoom_speed)
+            .clamp(camera.min_distance, camera.max_distance);
+
+        let rotation = Quat::from_euler(EulerRot::YXZ, camera.yaw, camera.pitch, 0.0);
+        let pivot = target_transform.position() + camera.pivot_offset;
+        let boom_direction = rotation * Vec3::Z;
+
+        let boom_end = pivot + boom_direction * camera.distance;
+        let blockers = colliders
+            .iter()
+            .filter(|(entity, ..)| !in_hierarchy(*entity, camera.target, &parents))
+            .filter(|(_, aabb, global_transform)| {
+                // Cheap bounding sphere test before transforming the box
+                let matrix = global_transform.matrix();
+                let center = matrix.transform_point3((aabb.min + aabb.max) * 0.5);
+                let scale = matrix
+                    .x_axis
+                    .length()
+                    .max(matrix.y_axis.length())
+                    .max(matrix.z_axis.length());
+                let radius = (aabb.max - aabb.min).length() * 0.5 * scale + camera.collision_margin;
+                distance_to_segment(center, pivot, boom_end) <= radius
+            })
+            .map(|(_, aabb, global_transform)| aabb.transform(global_transform.matrix()));
+        camera.current_distance = boom_length(
+            pivot,
+            boom_direction,
+            camera.distance,
+            camera.collision_margin,
+            blockers,
+        );
+
+        transform.rotation = rotation;
+        transform.position = pivot + boom_direction * camera.current_distance;
+    }
+}
+
+/// Length of a boom from `pivot` along the normalized `direction`, shortened to stop
+/// `margin` before the first box it hits.
+///
+/// Boxes containing the pivot (e.g. the ground under the target) don't block the boom.
+pub fn boom_length(
+    pivot: Vec3,
+    direction: Vec3,
+    desired: f32,
+    margin: f32,
+    boxes: impl IntoIterator<Item = Aabb>,
+) -> f32 {
+    boxes
+        .into_iter()
+        .filter_map(|aabb| aabb.intersect_ray(pivot, direction))
+        .filter(|&hit| hit > 0.0)
+        .fold(desired, |distance, hit| distance.min(hit - margin))
+        .max(MIN_BOOM_LENGTH)
+}
+
+/// Whether `entity` is `root` or one of its descendants.
+fn in_hierarchy(mut entity: Entity, root: Entity, parents: &Query<&Parent>) -> bool {
+    loop {
+        if entity == root {
+            return true;
+        }
+        match parents.get(entity) {
+            Ok(parent) => entity = parent.get(),
+            Err(_) => return false,
+        }
+    }
+}
+
+fn distance_to_segment(point: Vec3, start: Vec3, end: Vec3) -> f32 {
+    let segment = end - start;
+    let t =
+        ((point - start).dot(segment) / segment.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boom_stops_before_walls() {
+        let wall = |z: f32| Aabb {
+            min: Vec3::new(-5.0, -5.0, z),
+            max: Vec3::new(5.0, 5.0, z + 1.0),
+        };
+
+        assert_eq!(
+            boom_length(Vec3::ZERO, Vec3::Z, 5.0, 0.2, [wall(10.0)]),
+            5.0
+        );
+        assert!(
+            (boom_length(Vec3::ZERO, Vec3::Z, 5.0, 0.2, [wall(10.0), wall(3.0)]) - 2.8).abs()
+                < 1e-5
+        );
+        // A wall closer than any sensible minimum zoom still pulls the camera in
+        assert_eq!(
+            boom_length(Vec3::ZERO, Vec3::Z, 5.0, 0.2, [wall(0.1)]),
+            MIN_BOOM_LENGTH
+        );
+        // The ground box around the pivot doesn't count
+        assert_eq!(
+            boom_length(Vec3::ZERO, Vec3::Z, 5.0, 0.2, [wall(-1.0)]),
+            5.0
+        );
+    }
+
+    #[test]
+    fn test_target_hierarchy_is_excluded() {
+        let mut world = World::new();
+        let target = world.spawn_empty().id();
+        let child = world.spawn(Parent::new(target)).id();
+        let grandchild = world.spawn(Parent::new(child)).id();
+        let other = world.spawn_empty().id();
+
+        let mut state = bevy_ecs::system::SystemState::<Query<&Parent>>::new(&mut world);
+        let parents = state.get(&world);
+        assert!(in_hierarchy(target, target, &parents));
+        assert!(in_hierarchy(grandchild, target, &parents));
+        assert!(!in_hierarchy(other, target, &parents));
+        assert!(!in_hierarchy(target, child, &parents));
+    }
+}
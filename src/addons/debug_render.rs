@@ -1,26 +1,31 @@
-/// Debug rendering utilities for visualizing game state
-///
-/// Provides simple debug rendering capabilities for AABBs, lines, and other
-/// debug visualizations. Useful for debugging physics, culling, and spatial issues.
-///
-/// # Example
-/// ```no_run
-/// use resonance::prelude::*;
-/// use resonance::addons::debug_render::*;
-///
-/// fn debug_system(mut debug: ResMut<DebugRenderer>) {
-///     // Draw a red AABB
-///     debug.draw_aabb(
-///         Vec3::ZERO,
-///         Vec3::new(1.0, 1.0, 1.0),
-///         Vec3::new(1.0, 0.0, 0.0)
-///     );
-/// }
-/// ```
+//! Debug rendering utilities for visualizing game state
+//!
+//! Provides simple debug rendering capabilities for AABBs, lines, and other
+//! debug visualizations. Useful for debugging physics, culling, and spatial issues.
+//!
+//! Lines are handed to the renderer's [`Gizmos`] each frame; new code can draw
+//! through `ResMut<Gizmos>` directly.
+//!
+//! # Example
+//! ```no_run
+//! use resonance::prelude::*;
+//! use resonance::addons::debug_render::*;
+//!
+//! fn debug_system(mut debug: ResMut<DebugRenderer>) {
+//!     // Draw a red AABB
+//!     debug.draw_aabb(
+//!         Vec3::ZERO,
+//!         Vec3::new(1.0, 1.0, 1.0),
+//!         Vec3::new(1.0, 0.0, 0.0)
+//!     );
+//! }
+//! ```
 
 use bevy_ecs::prelude::*;
 use glam::Vec3;
 
+use crate::renderer::Gizmos;
+
 /// Debug line to be rendered
 #[derive(Clone, Debug)]
 pub struct DebugLine {
@@ -31,8 +36,7 @@ pub struct DebugLine {
 
 /// Resource for managing debug rendering
 ///
-/// Collects debug primitives each frame and renders them as wireframes.
-/// All debug primitives are cleared at the end of each frame.
+/// Collects debug primitives each frame and forwards them to [`Gizmos`] for rendering.
 #[derive(Resource, Default)]
 pub struct DebugRenderer {
     lines: Vec<DebugLine>,
@@ -85,12 +89,19 @@ impl DebugRenderer {
     }
 
     /// Draws a camera frustum for visualization
-    pub fn draw_frustum(&mut self, _frustum: &crate::renderer::camera::Frustum, _color: Vec3) {
-        // Simplified frustum visualization
-        // In a full implementation, would extract frustum corners and draw all edges
-        if self.enabled {
-            // This is a stub - full implementation would require frustum corner extraction
-            log::warn!("Frustum visualization not yet fully implemented");
+    pub fn draw_frustum(&mut self, frustum: &crate::renderer::camera::Frustum, color: Vec3) {
+        if !self.enabled {
+            return;
+        }
+        let Some(corners) = frustum.corners() else {
+            return;
+        };
+
+        for i in 0..4 {
+            let next = (i + 1) % 4;
+            self.draw_line(corners[i], corners[next], color);
+            self.draw_line(corners[i + 4], corners[next + 4], color);
+            self.draw_line(corners[i], corners[i + 4], color);
         }
     }
 
@@ -105,9 +116,11 @@ impl DebugRenderer {
     }
 }
 
-/// System that clears debug rendering each frame
-fn clear_debug_renderer(mut debug: ResMut<DebugRenderer>) {
-    debug.clear();
+/// System that hands this frame's debug lines to the renderer
+fn forward_debug_lines(mut debug: ResMut<DebugRenderer>, mut gizmos: ResMut<Gizmos>) {
+    for line in debug.lines.drain(..) {
+        gizmos.line(line.from, line.to, line.color);
+    }
 }
 
 /// Plugin that adds debug rendering capabilities
@@ -117,11 +130,12 @@ pub struct DebugRenderPlugin;
 impl crate::app::Plugin for DebugRenderPlugin {
     fn build(&self, engine: &mut crate::app::Resonance) {
         engine.world.insert_resource(DebugRenderer::new());
+        engine.world.init_resource::<Gizmos>();
 
-        // Clear debug primitives at the end of each frame
+        // Lines drawn during Update are forwarded before rendering
         use crate::app::Stage;
-        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
-            schedule.add_systems(clear_debug_renderer);
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PostUpdate) {
+            schedule.add_systems(forward_debug_lines);
         }
     }
 
@@ -0,0 +1,276 @@
+//! Opt-in hardware and startup report for triaging player performance reports.
+//!
+//! [`DiagnosticsPlugin`] collects the GPU and driver, CPU, memory, monitors and startup
+//! timings into a [`DiagnosticsReport`] once the first frame has rendered, and writes it as
+//! JSON. Nothing leaves the machine unless the game sets an upload callback, typically only
+//! after the player agreed to send it:
+//!
+//! ```ignore
+//! Resonance::new()
+//!     .add_plugin(DefaultPlugins)
+//!     .add_plugin(
+//!         DiagnosticsPlugin::new("diagnostics.json")
+//!             .with_upload(|report| crash_reporter::send("diagnostics", &report.to_json())),
+//!     )
+//!     .run();
+//! ```
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::renderer::Renderer;
+use crate::window::Window;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuInfo {
+    pub name: String,
+    /// PCI vendor id, e.g. 0x10de for NVIDIA.
+    pub vendor: u32,
+    pub device: u32,
+    pub device_type: String,
+    pub backend: String,
+    pub driver: String,
+    pub driver_info: String,
+}
+
+impl GpuInfo {
+    pub fn from_adapter(info: &wgpu::AdapterInfo) -> Self {
+        Self {
+            name: info.name.clone(),
+            vendor: info.vendor,
+            device: info.device,
+            device_type: format!("{:?}", info.device_type),
+            backend: format!("{:?}", info.backend),
+            driver: info.driver.clone(),
+            driver_info: info.driver_info.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub refresh_rate_hz: Option<f32>,
+    pub primary: bool,
+    /// The window is on this monitor.
+    pub current: bool,
+}
+
+/// Milliseconds from the plugin being added until each milestone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StartupTimings {
+    /// The GPU device and pipelines were created. `None` without a renderer.
+    pub renderer_ready_ms: Option<f64>,
+    pub first_frame_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub engine_version: String,
+    /// RFC 3339 local time.
+    pub generated_at: String,
+    pub os: String,
+    pub cpu_brand: String,
+    pub cpu_logical_cores: usize,
+    pub total_memory_bytes: u64,
+    pub available_memory_bytes: u64,
+    /// `None` on servers and other runs without a renderer.
+    pub gpu: Option<GpuInfo>,
+    pub monitors: Vec<MonitorInfo>,
+    pub startup: StartupTimings,
+}
+
+impl DiagnosticsReport {
+    /// Engine, OS, CPU and memory. GPU, monitors and timings are left empty.
+    pub fn collect_system() -> Self {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        system.refresh_cpu_all();
+
+        Self {
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: chrono::Local::now().to_rfc3339(),
+            os: sysinfo::System::long_os_version().unwrap_or_else(|| std::env::consts::OS.into()),
+            cpu_brand: system
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().trim().to_string())
+                .unwrap_or_default(),
+            cpu_logical_cores: std::thread::available_parallelism()
+                .map(|cores| cores.get())
+                .unwrap_or(system.cpus().len()),
+            total_memory_bytes: system.total_memory(),
+            available_memory_bytes: system.available_memory(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path.as_ref(), self.to_json())?;
+        Ok(())
+    }
+}
+
+type UploadFn = Arc<dyn Fn(&DiagnosticsReport) + Send + Sync>;
+
+/// Writes a [`DiagnosticsReport`] after the first frame. Not part of
+/// [`DefaultPlugins`](crate::app::DefaultPlugins); games add it to opt in.
+#[derive(Clone)]
+pub struct DiagnosticsPlugin {
+    output: Option<PathBuf>,
+    upload: Option<UploadFn>,
+}
+
+impl DiagnosticsPlugin {
+    pub fn new(output: impl Into<PathBuf>) -> Self {
+        Self {
+            output: Some(output.into()),
+            upload: None,
+        }
+    }
+
+    /// Collects the report into [`Diagnostics`] without writing a file.
+    pub fn in_memory() -> Self {
+        Self {
+            output: None,
+            upload: None,
+        }
+    }
+
+    /// Called with the report once it's collected, e.g. to send it to a crash reporting
+    /// service. The engine has no uploader of its own.
+    pub fn with_upload(
+        mut self,
+        upload: impl Fn(&DiagnosticsReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.upload = Some(Arc::new(upload));
+        self
+    }
+}
+
+impl Default for DiagnosticsPlugin {
+    /// Writes `diagnostics.json`.
+    fn default() -> Self {
+        Self::new("diagnostics.json")
+    }
+}
+
+/// State of the [`DiagnosticsPlugin`]; `report` is set once collected.
+#[derive(Resource)]
+pub struct Diagnostics {
+    pub report: Option<DiagnosticsReport>,
+    output: Option<PathBuf>,
+    upload: Option<UploadFn>,
+    started: Instant,
+    renderer_ready: Option<Duration>,
+}
+
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.insert_resource(Diagnostics {
+            report: None,
+            output: self.output.clone(),
+            upload: self.upload.clone(),
+            started: Instant::now(),
+            renderer_ready: None,
+        });
+
+        // After the frame has been rendered
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
+            schedule.add_systems(diagnostics_system);
+        }
+    }
+}
+
+pub fn diagnostics_system(
+    mut diagnostics: ResMut<Diagnostics>,
+    renderer: Option<Res<Renderer>>,
+    window: Option<Res<Window>>,
+) {
+    if diagnostics.report.is_some() {
+        return;
+    }
+    if renderer.is_some() && diagnostics.renderer_ready.is_none() {
+        diagnostics.renderer_ready = Some(diagnostics.started.elapsed());
+    }
+    // A window without a renderer hasn't drawn its first frame yet
+    if window.is_some() && renderer.is_none() {
+        return;
+    }
+
+    let mut report = DiagnosticsReport::collect_system();
+    report.gpu = renderer
+        .as_ref()
+        .map(|renderer| GpuInfo::from_adapter(renderer.adapter_info()));
+    if let Some(window) = &window {
+        report.monitors = monitors(&window.window);
+    }
+    report.startup = StartupTimings {
+        renderer_ready_ms: diagnostics
+            .renderer_ready
+            .map(|elapsed| elapsed.as_secs_f64() * 1000.0),
+        first_frame_ms: diagnostics.started.elapsed().as_secs_f64() * 1000.0,
+    };
+
+    if let Some(output) = &diagnostics.output {
+        match report.save(output) {
+            Ok(()) => log::info!("Wrote diagnostics report to {}", output.display()),
+            Err(e) => log::warn!(
+                "Failed to write diagnostics report to {}: {}",
+                output.display(),
+                e
+            ),
+        }
+    }
+    if let Some(upload) = &diagnostics.upload {
+        upload(&report);
+    }
+    diagnostics.report = Some(report);
+}
+
+fn monitors(window: &winit::window::Window) -> Vec<MonitorInfo> {
+    let primary = window.primary_monitor();
+    let current = window.current_monitor();
+    window
+        .available_monitors()
+        .map(|monitor| {
+            let size = monitor.size();
+            MonitorInfo {
+                name: monitor.name(),
+                width: size.width,
+                height: size.height,
+                scale_factor: monitor.scale_factor(),
+                refresh_rate_hz: monitor
+                    .refresh_rate_millihertz()
+                    .map(|millihertz| millihertz as f32 / 1000.0),
+                primary: primary.as_ref() == Some(&monitor),
+                current: current.as_ref() == Some(&monitor),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_report_round_trips() {
+        let report = DiagnosticsReport::collect_system();
+        assert!(report.cpu_logical_cores > 0);
+        assert!(report.gpu.is_none());
+
+        let parsed: DiagnosticsReport = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,526 @@
+//! Data-driven NPC conversations.
+//!
+//! A dialog is a graph of [`DialogNode`]s loaded from a RON `.dialog` file.
+//! Inserting a [`DialogSession`] on a participant starts the conversation;
+//! the participant picks choices by writing [`DialogChoiceRequest`]s and the UI
+//! follows along by reading [`DialogEvent`]s. Choices are validated against the
+//! current node and the participant's [`DialogVariables`] before they are
+//! applied, so the session can be run authoritatively on the server.
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::assets::cache::CachePolicy;
+use crate::assets::loader::{AssetLoader, LoadError};
+use crate::assets::{AssetHandle, Assets};
+use bevy_ecs::message::{MessageReader, Messages};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Requirement on the participant's [`DialogVariables`] for a choice to be offered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DialogCondition {
+    HasFlag(String),
+    MissingFlag(String),
+    StatAtLeast(String, i64),
+    StatBelow(String, i64),
+    QuestStage(String, String),
+}
+
+/// Side effect of entering a node or picking a choice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DialogEffect {
+    SetFlag(String),
+    ClearFlag(String),
+    AddStat(String, i64),
+    SetQuestStage(String, String),
+    /// Runs the [`DialogHooks`] entry with this name.
+    Hook(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogChoice {
+    pub text: String,
+    /// Node to go to, `None` ends the conversation.
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub conditions: Vec<DialogCondition>,
+    #[serde(default)]
+    pub effects: Vec<DialogEffect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogNode {
+    pub id: String,
+    #[serde(default)]
+    pub speaker: String,
+    pub text: String,
+    /// Nodes without choices continue to `next` on choice `0`.
+    #[serde(default)]
+    pub choices: Vec<DialogChoice>,
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub on_enter: Vec<DialogEffect>,
+}
+
+impl DialogNode {
+    /// Indices of the choices whose conditions all hold.
+    pub fn available_choices(&self, variables: &DialogVariables) -> Vec<usize> {
+        self.choices
+            .iter()
+            .enumerate()
+            .filter(|(_, choice)| choice.conditions.iter().all(|c| variables.check(c)))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// A conversation graph. Load with [`DialogLoader`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DialogAsset {
+    pub start: String,
+    pub nodes: Vec<DialogNode>,
+}
+
+impl DialogAsset {
+    pub fn node(&self, id: &str) -> Option<&DialogNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Checks that node ids are unique and every reference points at an existing node.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if !ids.insert(node.id.as_str()) {
+                return Err(format!("duplicate node '{}'", node.id));
+            }
+        }
+
+        if !ids.contains(self.start.as_str()) {
+            return Err(format!("start node '{}' does not exist", self.start));
+        }
+
+        for node in &self.nodes {
+            let targets = node
+                .choices
+                .iter()
+                .filter_map(|choice| choice.next.as_deref())
+                .chain(node.next.as_deref());
+
+            for target in targets {
+                if !ids.contains(target) {
+                    return Err(format!(
+                        "node '{}' references missing node '{}'",
+                        node.id, target
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads [`DialogAsset`]s from RON files with the `.dialog` extension.
+pub struct DialogLoader;
+
+impl AssetLoader for DialogLoader {
+    type Asset = DialogAsset;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| LoadError::LoadFailed(format!("Failed to read dialog file: {}", e)))?;
+
+        let dialog: DialogAsset = ron::from_str(&contents)
+            .map_err(|e| LoadError::LoadFailed(format!("Invalid dialog file: {}", e)))?;
+
+        dialog.validate().map_err(|e| {
+            LoadError::LoadFailed(format!("Invalid dialog {}: {}", path.display(), e))
+        })?;
+
+        Ok(dialog)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialog"]
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::Strong
+    }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(DialogAsset::default())
+    }
+}
+
+/// Quest and stat state that dialog conditions read and effects write.
+#[derive(Component, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DialogVariables {
+    pub flags: HashSet<String>,
+    pub stats: HashMap<String, i64>,
+    pub quests: HashMap<String, String>,
+}
+
+impl DialogVariables {
+    pub fn stat(&self, name: &str) -> i64 {
+        self.stats.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn check(&self, condition: &DialogCondition) -> bool {
+        match condition {
+            DialogCondition::HasFlag(flag) => self.flags.contains(flag),
+            DialogCondition::MissingFlag(flag) => !self.flags.contains(flag),
+            DialogCondition::StatAtLeast(stat, value) => self.stat(stat) >= *value,
+            DialogCondition::StatBelow(stat, value) => self.stat(stat) < *value,
+            DialogCondition::QuestStage(quest, stage) => self
+                .quests
+                .get(quest)
+                .is_some_and(|current| current == stage),
+        }
+    }
+
+    /// Applies variable effects. [`DialogEffect::Hook`] is ignored here.
+    pub fn apply(&mut self, effect: &DialogEffect) {
+        match effect {
+            DialogEffect::SetFlag(flag) => {
+                self.flags.insert(flag.clone());
+            }
+            DialogEffect::ClearFlag(flag) => {
+                self.flags.remove(flag);
+            }
+            DialogEffect::AddStat(stat, amount) => {
+                *self.stats.entry(stat.clone()).or_default() += amount;
+            }
+            DialogEffect::SetQuestStage(quest, stage) => {
+                self.quests.insert(quest.clone(), stage.clone());
+            }
+            DialogEffect::Hook(_) => {}
+        }
+    }
+}
+
+/// Called with the participant and the NPC they are talking to.
+pub type DialogHookFn = Arc<dyn Fn(&mut World, Entity, Option<Entity>) + Send + Sync>;
+
+/// Named game code that dialogs can trigger with [`DialogEffect::Hook`].
+#[derive(Resource, Default, Clone)]
+pub struct DialogHooks {
+    hooks: HashMap<String, DialogHookFn>,
+}
+
+impl DialogHooks {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        hook: impl Fn(&mut World, Entity, Option<Entity>) + Send + Sync + 'static,
+    ) {
+        self.hooks.insert(name.into(), Arc::new(hook));
+    }
+
+    pub fn get(&self, name: &str) -> Option<DialogHookFn> {
+        self.hooks.get(name).cloned()
+    }
+}
+
+/// Active conversation of the entity it is attached to. Removed when the dialog ends.
+#[derive(Component, Debug, Clone)]
+pub struct DialogSession {
+    pub dialog: AssetHandle<DialogAsset>,
+    /// NPC being talked to.
+    pub speaker: Option<Entity>,
+    /// Current node, `None` until the session has started.
+    pub current: Option<String>,
+}
+
+impl DialogSession {
+    pub fn new(dialog: AssetHandle<DialogAsset>, speaker: Option<Entity>) -> Self {
+        Self {
+            dialog,
+            speaker,
+            current: None,
+        }
+    }
+}
+
+/// Sent by the participant's client to pick a choice of the current node.
+#[derive(Message, Debug, Clone)]
+pub struct DialogChoiceRequest {
+    pub participant: Entity,
+    pub choice: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogChoiceView {
+    /// Index to send back in [`DialogChoiceRequest::choice`].
+    pub index: usize,
+    pub text: String,
+}
+
+/// Dialog state changes for the UI.
+#[derive(Message, Debug, Clone)]
+pub enum DialogEvent {
+    Line {
+        participant: Entity,
+        speaker: Option<Entity>,
+        node: String,
+        speaker_name: String,
+        text: String,
+        /// Empty when the node continues on choice `0`.
+        choices: Vec<DialogChoiceView>,
+    },
+    ChoiceRejected {
+        participant: Entity,
+        choice: usize,
+        reason: String,
+    },
+    Ended {
+        participant: Entity,
+    },
+}
+
+#[derive(Default)]
+pub struct DialogPlugin;
+
+impl Plugin for DialogPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<DialogHooks>();
+        engine
+            .world
+            .init_resource::<Messages<DialogChoiceRequest>>();
+        engine.world.init_resource::<Messages<DialogEvent>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
+            schedule.add_systems(dialog_system);
+        }
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        true
+    }
+}
+
+/// Starts pending sessions and applies choice requests.
+///
+/// Requests are read with a [`MessageReader`] so other readers, such as UI or analytics,
+/// still see them.
+pub fn dialog_system(
+    world: &mut World,
+    reader: &mut SystemState<MessageReader<DialogChoiceRequest>>,
+) {
+    let requests: Vec<DialogChoiceRequest> = reader.get_mut(world).read().cloned().collect();
+
+    let pending: Vec<Entity> = world
+        .query::<(Entity, &DialogSession)>()
+        .iter(world)
+        .filter(|(_, session)| session.current.is_none())
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for participant in pending {
+        // Wait for the asset to finish loading
+        let Some(dialog) = resolve_dialog(world, participant) else {
+            continue;
+        };
+        let start = dialog.start.clone();
+        enter_node(world, participant, &dialog, Some(start));
+    }
+
+    for request in requests {
+        handle_choice(world, request);
+    }
+}
+
+fn resolve_dialog(world: &World, participant: Entity) -> Option<Arc<DialogAsset>> {
+    let handle = &world.get::<DialogSession>(participant)?.dialog;
+
+    match world.get_resource::<Assets>() {
+        Some(assets) if assets.is_loading::<DialogAsset>(handle.id) => None,
+        Some(assets) => Some(
+            assets
+                .get::<DialogAsset>(handle.id)
+                .unwrap_or(handle.asset.clone()),
+        ),
+        None => Some(handle.asset.clone()),
+    }
+}
+
+fn handle_choice(world: &mut World, request: DialogChoiceRequest) {
+    let participant = request.participant;
+    let reject = |world: &mut World, reason: &str| {
+        world.write_message(DialogEvent::ChoiceRejected {
+            participant,
+            choice: request.choice,
+            reason: reason.to_string(),
+        });
+    };
+
+    let Some(current) = world
+        .get::<DialogSession>(participant)
+        .and_then(|session| session.current.clone())
+    else {
+        return reject(world, "no active dialog");
+    };
+    let Some(dialog) = resolve_dialog(world, participant) else {
+        return reject(world, "dialog is not loaded");
+    };
+    let Some(node) = dialog.node(&current) else {
+        return reject(world, "current node no longer exists");
+    };
+
+    let (next, effects) = if node.choices.is_empty() {
+        if request.choice != 0 {
+            return reject(world, "node has no choices");
+        }
+        (node.next.clone(), Vec::new())
+    } else {
+        let variables = world
+            .get::<DialogVariables>(participant)
+            .cloned()
+            .unwrap_or_default();
+        if !node.available_choices(&variables).contains(&request.choice) {
+            return reject(world, "choice is not available");
+        }
+        let choice = &node.choices[request.choice];
+        (choice.next.clone(), choice.effects.clone())
+    };
+
+    apply_effects(world, participant, &effects);
+    enter_node(world, participant, &dialog, next);
+}
+
+fn enter_node(world: &mut World, participant: Entity, dialog: &DialogAsset, id: Option<String>) {
+    let Some(node) = id.as_deref().and_then(|id| dialog.node(id)) else {
+        if let Some(id) = id {
+            log::warn!("Dialog node '{}' does not exist, ending dialog", id);
+        }
+        world.entity_mut(participant).remove::<DialogSession>();
+        world.write_message(DialogEvent::Ended { participant });
+        return;
+    };
+
+    let Some(mut session) = world.get_mut::<DialogSession>(participant) else {
+        return;
+    };
+    session.current = Some(node.id.clone());
+    let speaker = session.speaker;
+
+    apply_effects(world, participant, &node.on_enter);
+
+    let variables = world
+        .get::<DialogVariables>(participant)
+        .cloned()
+        .unwrap_or_default();
+    let choices = node
+        .available_choices(&variables)
+        .into_iter()
+        .map(|index| DialogChoiceView {
+            index,
+            text: node.choices[index].text.clone(),
+        })
+        .collect();
+
+    world.write_message(DialogEvent::Line {
+        participant,
+        speaker,
+        node: node.id.clone(),
+        speaker_name: node.speaker.clone(),
+        text: node.text.clone(),
+        choices,
+    });
+}
+
+fn apply_effects(world: &mut World, participant: Entity, effects: &[DialogEffect]) {
+    if effects.is_empty() {
+        return;
+    }
+
+    let speaker = world
+        .get::<DialogSession>(participant)
+        .and_then(|session| session.speaker);
+
+    for effect in effects {
+        if let DialogEffect::Hook(name) = effect {
+            let Some(hook) = world
+                .get_resource::<DialogHooks>()
+                .and_then(|hooks| hooks.get(name))
+            else {
+                log::warn!("Dialog hook '{}' is not registered", name);
+                continue;
+            };
+            hook(world, participant, speaker);
+            continue;
+        }
+
+        let Ok(mut entity) = world.get_entity_mut(participant) else {
+            return;
+        };
+        if !entity.contains::<DialogVariables>() {
+            entity.insert(DialogVariables::default());
+        }
+        if let Some(mut variables) = entity.get_mut::<DialogVariables>() {
+            variables.apply(effect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIALOG: &str = r#"(
+        start: "greet",
+        nodes: [
+            (
+                id: "greet",
+                speaker: "Smith",
+                text: "Need something forged?",
+                choices: [
+                    (text: "Buy a sword", next: Some("sword"), conditions: [StatAtLeast("gold", 10)]),
+                    (text: "About the missing hammer...", next: Some("hammer"), conditions: [QuestStage("hammer", "started")]),
+                    (text: "Bye"),
+                ],
+            ),
+            (id: "sword", text: "Here you go.", on_enter: [AddStat("gold", -10), SetFlag("has_sword")]),
+            (id: "hammer", text: "You found it!"),
+        ],
+    )"#;
+
+    #[test]
+    fn test_dialog_parse_and_conditions() {
+        let dialog: DialogAsset = ron::from_str(DIALOG).unwrap();
+        assert!(dialog.validate().is_ok());
+
+        let greet = dialog.node("greet").unwrap();
+        let mut variables = DialogVariables::default();
+        assert_eq!(greet.available_choices(&variables), vec![2]);
+
+        variables.apply(&DialogEffect::AddStat("gold".into(), 15));
+        variables.apply(&DialogEffect::SetQuestStage(
+            "hammer".into(),
+            "started".into(),
+        ));
+        assert_eq!(greet.available_choices(&variables), vec![0, 1, 2]);
+
+        for effect in &dialog.node("sword").unwrap().on_enter {
+            variables.apply(effect);
+        }
+        assert_eq!(variables.stat("gold"), 5);
+        assert!(variables.check(&DialogCondition::HasFlag("has_sword".into())));
+    }
+
+    #[test]
+    fn test_dialog_validate_missing_node() {
+        let mut dialog: DialogAsset = ron::from_str(DIALOG).unwrap();
+        dialog.nodes.retain(|node| node.id != "hammer");
+        assert!(dialog.validate().is_err());
+    }
+}
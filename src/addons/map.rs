@@ -0,0 +1,287 @@
+use crate::app::{Plugin, Resonance, Stage};
+use crate::core::math::*;
+use crate::renderer::Aabb;
+use crate::renderer::capture;
+use crate::transform::GlobalTransform;
+use anyhow::{Context, Result};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Largest image side the exporter will render, matching wgpu's default texture limit.
+const MAX_MAP_RESOLUTION: u32 = 8192;
+
+/// Point of interest shown on maps and the minimap.
+///
+/// Serializable so it can be stored alongside the rest of an entity's data.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapMarker {
+    /// Icon identifier, interpreted by the UI (e.g. `"icons/shop.png"`).
+    pub icon: String,
+    pub label: String,
+    /// Free-form grouping such as `"quest"` or `"vendor"`, used for filtering.
+    pub category: String,
+}
+
+impl MapMarker {
+    pub fn new(
+        icon: impl Into<String>,
+        label: impl Into<String>,
+        category: impl Into<String>,
+    ) -> Self {
+        Self {
+            icon: icon.into(),
+            label: label.into(),
+            category: category.into(),
+        }
+    }
+}
+
+/// A marker resolved to world and map coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapMarkerRecord {
+    pub label: String,
+    pub icon: String,
+    pub category: String,
+    pub position: [f32; 3],
+    /// Normalized position on the map image, `(0, 0)` is the top-left corner.
+    pub map_uv: [f32; 2],
+}
+
+/// World-space area covered by a map. The map looks down the -Y axis with -Z at the top.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MapBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl MapBounds {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    /// Bounds enclosing the world-space [`Aabb`] of every entity, or `None` if there are none.
+    pub fn from_world(world: &mut World) -> Option<Self> {
+        world
+            .query::<(&Aabb, &GlobalTransform)>()
+            .iter(world)
+            .map(|(aabb, transform)| aabb.transform(transform.matrix()))
+            .reduce(|a, b| Aabb::new(a.min.min(b.min), a.max.max(b.max)))
+            .map(|aabb| Self::new(aabb.min, aabb.max))
+    }
+
+    /// Size of the covered area on the XZ plane.
+    pub fn extent(&self) -> Vec2 {
+        Vec2::new(self.max.x - self.min.x, self.max.z - self.min.z)
+    }
+
+    pub fn to_uv(&self, position: Vec3) -> Vec2 {
+        let extent = self.extent().max(Vec2::splat(f32::EPSILON));
+        Vec2::new(
+            (position.x - self.min.x) / extent.x,
+            (position.z - self.min.z) / extent.y,
+        )
+    }
+
+    /// Image size for a map whose longest side is `resolution` pixels.
+    pub fn image_size(&self, resolution: u32) -> (u32, u32) {
+        let resolution = resolution.clamp(1, MAX_MAP_RESOLUTION);
+        let extent = self.extent().max(Vec2::splat(f32::EPSILON));
+        let scale = resolution as f32 / extent.x.max(extent.y);
+        (
+            ((extent.x * scale).round() as u32).max(1),
+            ((extent.y * scale).round() as u32).max(1),
+        )
+    }
+
+    /// Top-down orthographic view-projection covering the bounds.
+    pub fn view_projection(&self) -> Mat4 {
+        let center = (self.min + self.max) * 0.5;
+        let half = self.extent() * 0.5;
+        let eye = Vec3::new(center.x, self.max.y + 1.0, center.z);
+        let depth = self.max.y - self.min.y + 2.0;
+
+        let view = Mat4::look_at_rh(eye, Vec3::new(center.x, self.min.y, center.z), Vec3::NEG_Z);
+        let projection = Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, 0.0, depth);
+        projection * view
+    }
+}
+
+/// Contents of the exported marker JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapData {
+    pub bounds: MapBounds,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub markers: Vec<MapMarkerRecord>,
+}
+
+/// Returns every [`MapMarker`] inside `bounds`, sorted by category then label.
+pub fn collect_markers(world: &mut World, bounds: &MapBounds) -> Vec<MapMarkerRecord> {
+    let mut markers: Vec<MapMarkerRecord> = world
+        .query::<(&MapMarker, &GlobalTransform)>()
+        .iter(world)
+        .filter_map(|(marker, transform)| {
+            let position = transform.position();
+            let uv = bounds.to_uv(position);
+            if !(0.0..=1.0).contains(&uv.x) || !(0.0..=1.0).contains(&uv.y) {
+                return None;
+            }
+
+            Some(MapMarkerRecord {
+                label: marker.label.clone(),
+                icon: marker.icon.clone(),
+                category: marker.category.clone(),
+                position: position.to_array(),
+                map_uv: uv.to_array(),
+            })
+        })
+        .collect();
+
+    markers.sort_by(|a, b| (&a.category, &a.label).cmp(&(&b.category, &b.label)));
+    markers
+}
+
+/// Renders a top-down map to `<path>.png` and writes its markers to `<path>.json`.
+///
+/// Call from an exclusive system or use [`MapExportRequest`]. `bounds` defaults to
+/// everything with an [`Aabb`].
+pub fn export_map(
+    world: &mut World,
+    path: impl AsRef<Path>,
+    resolution: u32,
+    bounds: Option<MapBounds>,
+) -> Result<MapData> {
+    let path = path.as_ref();
+    let bounds = bounds
+        .or_else(|| MapBounds::from_world(world))
+        .context("No map bounds given and no entities with an Aabb to derive them from")?;
+
+    let (width, height) = bounds.image_size(resolution);
+    let pixels = capture::render_offscreen(
+        world,
+        bounds.view_projection(),
+        width,
+        height,
+        wgpu::Color::TRANSPARENT,
+    )
+    .context("Renderer is not initialized")?;
+
+    let image = image::RgbaImage::from_raw(width, height, pixels)
+        .context("Rendered map has an unexpected size")?;
+    let image_path = path.with_extension("png");
+    image
+        .save(&image_path)
+        .with_context(|| format!("Failed to write {}", image_path.display()))?;
+
+    let data = MapData {
+        bounds,
+        image_width: width,
+        image_height: height,
+        markers: collect_markers(world, &bounds),
+    };
+
+    let json_path = path.with_extension("json");
+    std::fs::write(&json_path, serde_json::to_string_pretty(&data)?)
+        .with_context(|| format!("Failed to write {}", json_path.display()))?;
+
+    log::info!(
+        "Exported map {} ({}x{}, {} markers)",
+        image_path.display(),
+        width,
+        height,
+        data.markers.len()
+    );
+
+    Ok(data)
+}
+
+/// Insert to export a map at the end of the current frame. Removed once handled.
+#[derive(Resource, Debug, Clone)]
+pub struct MapExportRequest {
+    /// Output path without extension.
+    pub path: PathBuf,
+    /// Pixel length of the longest side of the image.
+    pub resolution: u32,
+    pub bounds: Option<MapBounds>,
+}
+
+impl MapExportRequest {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            resolution: 2048,
+            bounds: None,
+        }
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: MapBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+}
+
+/// Handles [`MapExportRequest`]s.
+#[derive(Default)]
+pub struct MapExportPlugin;
+
+impl Plugin for MapExportPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
+            schedule.add_systems(map_export_system);
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::renderer::RenderPlugin>(),
+            "resonance::renderer::RenderPlugin",
+        )]
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+pub fn map_export_system(world: &mut World) {
+    let Some(request) = world.remove_resource::<MapExportRequest>() else {
+        return;
+    };
+
+    if let Err(e) = export_map(world, &request.path, request.resolution, request.bounds) {
+        log::error!("Map export failed: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_bounds_uv_and_size() {
+        let bounds = MapBounds::new(Vec3::new(-10.0, 0.0, -5.0), Vec3::new(10.0, 4.0, 5.0));
+
+        assert_eq!(bounds.to_uv(Vec3::new(-10.0, 2.0, -5.0)), Vec2::ZERO);
+        assert_eq!(bounds.to_uv(Vec3::new(0.0, 0.0, 0.0)), Vec2::splat(0.5));
+        assert_eq!(bounds.image_size(1024), (1024, 512));
+
+        // Top-left of the image is (min.x, min.z)
+        let clip = bounds
+            .view_projection()
+            .project_point3(Vec3::new(-10.0, 2.0, -5.0));
+        assert!((clip.x + 1.0).abs() < 1e-4 && (clip.y - 1.0).abs() < 1e-4);
+    }
+}
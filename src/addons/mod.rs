@@ -1,7 +1,47 @@
+pub mod benchmark;
+pub mod camera_blend;
+pub mod camera_controller;
 pub mod debug_render;
+pub mod diagnostics;
+pub mod dialog;
 pub mod flycam;
+pub mod map;
+pub mod preview;
+pub mod scene;
+pub mod sequencer;
+pub mod spawner;
 pub mod wireframe;
 
+pub use benchmark::{
+    BenchmarkPlugin, BenchmarkRecorder, BenchmarkRecording, BenchmarkReport, BenchmarkRun,
+};
+pub use camera_blend::{
+    CameraBlendPlugin, CameraDirector, CameraTransition, VirtualCamera, camera_director_system,
+};
+pub use camera_controller::{
+    CameraControllerPlugin, CameraControllerSettings, FirstPersonCamera, ThirdPersonOrbitCamera,
+};
 pub use debug_render::{DebugRenderPlugin, DebugRenderer};
+pub use diagnostics::{
+    Diagnostics, DiagnosticsPlugin, DiagnosticsReport, GpuInfo, MonitorInfo, StartupTimings,
+};
+pub use dialog::{
+    DialogAsset, DialogChoiceRequest, DialogEvent, DialogHooks, DialogLoader, DialogPlugin,
+    DialogSession, DialogVariables,
+};
 pub use flycam::{FlyCam, flycam_system};
-pub use wireframe::{WireframePlugin, WireframeState};
+pub use map::{MapBounds, MapData, MapExportPlugin, MapExportRequest, MapMarker, MapMarkerRecord};
+pub use preview::{AssetPreviews, PreviewKey, PreviewPlugin, PreviewReady};
+pub use scene::{
+    LoadedScene, SceneBuilder, SceneHandle, SceneLoaded, SceneMember, ScenePlugin, SceneUnloaded,
+    Scenes,
+};
+pub use sequencer::{
+    SequenceEvent, SequenceFinished, SequencePlayer, SequencerPlugin, TimelineAsset, TimelineLoader,
+};
+pub use spawner::{
+    PopulationLimits, SpawnObserver, SpawnPoint, SpawnTemplates, Spawned, SpawnerPlugin,
+};
+pub use wireframe::{
+    ExtractedWireframeOverlays, WireframeOverlay, WireframePlugin, WireframeState,
+};
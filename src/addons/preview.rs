@@ -0,0 +1,224 @@
+//! Thumbnails of mesh assets for asset browsers and inventory icons.
+//!
+//! Ask [`AssetPreviews`] for a preview of a [`Mesh`]; it is rendered offscreen
+//! with [`capture::render_instances_offscreen`] at the end of a later frame, cached
+//! by asset id and size, and announced with a [`PreviewReady`] message.
+//!
+//! ```ignore
+//! fn inventory_icon(mut previews: ResMut<AssetPreviews>, item: Query<&Mesh, With<Item>>) {
+//!     for mesh in &item {
+//!         if let Some(pixels) = previews.request(mesh, 128) {
+//!             // 128x128 RGBA8, transparent background
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::assets::handle::AssetId;
+use crate::core::math::*;
+use crate::renderer::capture;
+use crate::renderer::systems::draw::utils::storage;
+use crate::renderer::{Aabb, GpuMeshCache, Mesh};
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Vertical field of view of the preview camera.
+const PREVIEW_FOV: f32 = std::f32::consts::FRAC_PI_6;
+
+/// Identifies one cached preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PreviewKey {
+    pub mesh: AssetId,
+    pub mesh_index: usize,
+    /// Width and height in pixels.
+    pub size: u32,
+}
+
+impl PreviewKey {
+    pub fn new(mesh: &Mesh, size: u32) -> Self {
+        Self {
+            mesh: mesh.handle.id,
+            mesh_index: mesh.mesh_index,
+            size: size.max(1),
+        }
+    }
+}
+
+/// A preview finished rendering and is now in the cache.
+#[derive(Message, Clone)]
+pub struct PreviewReady {
+    pub key: PreviewKey,
+    /// Tonemapped sRGB RGBA8 pixels, row by row from the top-left.
+    pub pixels: Arc<Vec<u8>>,
+}
+
+/// Cache and request queue for mesh previews.
+#[derive(Resource)]
+pub struct AssetPreviews {
+    cache: HashMap<PreviewKey, Arc<Vec<u8>>>,
+    pending: Vec<(PreviewKey, Mesh)>,
+    /// Previews rendered per frame at most. Each one waits for the GPU, so keep this small.
+    pub per_frame: usize,
+}
+
+impl Default for AssetPreviews {
+    fn default() -> Self {
+        Self {
+            cache: HashMap::new(),
+            pending: Vec::new(),
+            per_frame: 2,
+        }
+    }
+}
+
+impl AssetPreviews {
+    /// Cached preview of `mesh`, queueing it for rendering if there is none yet.
+    pub fn request(&mut self, mesh: &Mesh, size: u32) -> Option<Arc<Vec<u8>>> {
+        let key = PreviewKey::new(mesh, size);
+        if let Some(pixels) = self.cache.get(&key) {
+            return Some(Arc::clone(pixels));
+        }
+        if !self.pending.iter().any(|(pending, _)| *pending == key) {
+            self.pending.push((key, mesh.clone()));
+        }
+        None
+    }
+
+    pub fn get(&self, key: &PreviewKey) -> Option<Arc<Vec<u8>>> {
+        self.cache.get(key).cloned()
+    }
+
+    /// Drops every cached size of an asset, e.g. after it was hot-reloaded.
+    pub fn invalidate(&mut self, mesh: AssetId) {
+        self.cache.retain(|key, _| key.mesh != mesh);
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Camera looking at `aabb` from the front-right and above, fitting it in a square image.
+pub fn preview_view_projection(aabb: &Aabb) -> Mat4 {
+    let center = (aabb.min + aabb.max) * 0.5;
+    let radius = ((aabb.max - aabb.min).length() * 0.5).max(0.01);
+    // Distance at which the bounding sphere touches the edges of the view
+    let distance = radius / (PREVIEW_FOV * 0.5).sin();
+    let eye = center + Vec3::new(1.0, 0.8, 1.0).normalize() * distance;
+
+    let view = Mat4::look_at_rh(eye, center, Vec3::Y);
+    let projection = Mat4::perspective_rh(
+        PREVIEW_FOV,
+        1.0,
+        distance - radius * 1.01,
+        distance + radius * 1.01,
+    );
+    projection * view
+}
+
+/// Renders queued [`AssetPreviews`] requests.
+#[derive(Default)]
+pub struct PreviewPlugin;
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<AssetPreviews>();
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<PreviewReady>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
+            schedule.add_systems(render_previews);
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::renderer::RenderPlugin>(),
+            "resonance::renderer::RenderPlugin",
+        )]
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+pub fn render_previews(world: &mut World) {
+    let Some(mut previews) = world.remove_resource::<AssetPreviews>() else {
+        return;
+    };
+
+    let mut rendered = 0;
+    let mut index = 0;
+    while index < previews.pending.len() && rendered < previews.per_frame {
+        let (key, mesh) = &previews.pending[index];
+        // Wait until the mesh has been uploaded by the renderer
+        let uploaded = world
+            .get_resource::<GpuMeshCache>()
+            .is_some_and(|cache| cache.contains(&key.mesh));
+        let Some(mesh_data) = mesh.handle.asset.get(key.mesh_index).filter(|_| uploaded) else {
+            index += 1;
+            continue;
+        };
+
+        let aabb = Aabb::from_positions(&mesh_data.positions);
+        let instance = (
+            key.mesh,
+            storage::compute_model_uniform(&GlobalTransform::default(), Vec3::ONE),
+        );
+        let Some(pixels) = capture::render_instances_offscreen(
+            world,
+            &[instance],
+            preview_view_projection(&aabb),
+            key.size,
+            key.size,
+            wgpu::Color::TRANSPARENT,
+        ) else {
+            break;
+        };
+
+        let (key, _) = previews.pending.remove(index);
+        let pixels = Arc::new(pixels);
+        previews.cache.insert(key, Arc::clone(&pixels));
+        world.write_message(PreviewReady { key, pixels });
+        rendered += 1;
+    }
+
+    world.insert_resource(previews);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_frames_bounds() {
+        let aabb = Aabb::new(Vec3::new(-1.0, 0.0, -3.0), Vec3::new(1.0, 4.0, 3.0));
+        let view_proj = preview_view_projection(&aabb);
+
+        let center = view_proj.project_point3((aabb.min + aabb.max) * 0.5);
+        assert!(center.x.abs() < 1e-4 && center.y.abs() < 1e-4);
+
+        for x in [aabb.min.x, aabb.max.x] {
+            for y in [aabb.min.y, aabb.max.y] {
+                for z in [aabb.min.z, aabb.max.z] {
+                    let ndc = view_proj.project_point3(Vec3::new(x, y, z));
+                    assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0);
+                    assert!((0.0..=1.0).contains(&ndc.z));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Additive scene loading.
+//!
+//! A scene is a named builder registered with [`Scenes::register`]. Any number of scenes
+//! can be loaded at once, and every entity a scene spawns is tagged with [`SceneMember`],
+//! so [`Scenes::unload_scene`] removes exactly that scene's entities. A typical layout is a
+//! persistent "core" scene holding the player, UI and managers, plus level scenes that are
+//! swapped with [`Scenes::unload_levels`]:
+//!
+//! ```ignore
+//! fn setup(mut scenes: ResMut<Scenes>) {
+//!     scenes.register("core", |scene| {
+//!         scene.spawn((Transform::new(), Player));
+//!     });
+//!     scenes.register("forest", |scene| {
+//!         let camp = scene.spawn(Transform::from_xyz(10.0, 0.0, 4.0));
+//!         scene.spawn_child(camp, Transform::from_xyz(0.0, 1.0, 0.0));
+//!     });
+//!
+//!     scenes.load_persistent("core");
+//!     scenes.load("forest");
+//! }
+//! ```
+//!
+//! Loads and unloads are queued and applied by [`scene_system`] at the start of the next
+//! frame. Entities spawned later by gameplay code are not members unless they are given a
+//! [`SceneMember`] too.
+//!
+//! Scenes built from saved data carry entity references from the world they were saved in.
+//! Spawning those entities with [`SceneBuilder::spawn_mapped`] records which entity each
+//! saved one became, and once the builder returns, references in components registered with
+//! [`EntityRefRegistry`](crate::core::EntityRefRegistry) are remapped to the new entities.
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::core::{EntityMap, remap_entities};
+use crate::transform::{Children, Parent};
+use bevy_ecs::message::Messages;
+use bevy_ecs::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Identifies one loaded instance of a scene. Loading the same scene twice yields two handles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SceneHandle(pub u32);
+
+/// Marks an entity as belonging to a loaded scene.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneMember(pub SceneHandle);
+
+/// Spawns a scene's entities through a [`SceneBuilder`].
+pub type SceneFn = Arc<dyn Fn(&mut SceneBuilder) + Send + Sync>;
+
+/// Spawns entities tagged with the scene being loaded.
+pub struct SceneBuilder<'w> {
+    world: &'w mut World,
+    scene: SceneHandle,
+    spawned: Vec<Entity>,
+    entity_map: EntityMap,
+}
+
+impl SceneBuilder<'_> {
+    pub fn scene(&self) -> SceneHandle {
+        self.scene
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = self.world.spawn((bundle, SceneMember(self.scene))).id();
+        self.spawned.push(entity);
+        entity
+    }
+
+    /// Spawns the entity saved as `source`. References to `source` in this scene's
+    /// components are remapped to the new entity after the scene is built.
+    pub fn spawn_mapped(&mut self, source: Entity, bundle: impl Bundle) -> Entity {
+        let entity = self.spawn(bundle);
+        self.entity_map.insert(source, entity);
+        entity
+    }
+
+    /// Saved entities spawned so far and the entities they became.
+    pub fn entity_map(&self) -> &EntityMap {
+        &self.entity_map
+    }
+
+    /// Spawns an entity parented to `parent`.
+    pub fn spawn_child(&mut self, parent: Entity, bundle: impl Bundle) -> Entity {
+        let child = self
+            .world
+            .spawn((bundle, SceneMember(self.scene), Parent(parent)))
+            .id();
+        self.spawned.push(child);
+        match self.world.get_mut::<Children>(parent) {
+            Some(mut children) => children.add(child),
+            None => {
+                self.world
+                    .entity_mut(parent)
+                    .insert(Children::with_children(vec![child]));
+            }
+        }
+        child
+    }
+
+    /// The world, for resources and for entities that shouldn't belong to the scene.
+    pub fn world(&mut self) -> &mut World {
+        self.world
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadedScene {
+    pub handle: SceneHandle,
+    pub name: String,
+    /// Kept by [`Scenes::unload_levels`].
+    pub persistent: bool,
+}
+
+/// Registered scene builders and the scenes currently loaded.
+#[derive(Resource, Default)]
+pub struct Scenes {
+    templates: HashMap<String, SceneFn>,
+    loaded: Vec<LoadedScene>,
+    pending_loads: Vec<LoadedScene>,
+    pending_unloads: Vec<SceneHandle>,
+    next_handle: u32,
+}
+
+impl Scenes {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        build: impl Fn(&mut SceneBuilder) + Send + Sync + 'static,
+    ) {
+        self.templates.insert(name.into(), Arc::new(build));
+    }
+
+    /// Queues `name` to be loaded alongside the scenes already loaded. Returns `None` if
+    /// no scene with that name is registered.
+    pub fn load(&mut self, name: &str) -> Option<SceneHandle> {
+        self.queue_load(name, false)
+    }
+
+    /// Like [`load`](Self::load), but the scene survives [`unload_levels`](Self::unload_levels).
+    pub fn load_persistent(&mut self, name: &str) -> Option<SceneHandle> {
+        self.queue_load(name, true)
+    }
+
+    fn queue_load(&mut self, name: &str, persistent: bool) -> Option<SceneHandle> {
+        if !self.templates.contains_key(name) {
+            log::warn!("Scene '{}' is not registered", name);
+            return None;
+        }
+
+        let handle = SceneHandle(self.next_handle);
+        self.next_handle += 1;
+        self.pending_loads.push(LoadedScene {
+            handle,
+            name: name.to_string(),
+            persistent,
+        });
+        Some(handle)
+    }
+
+    /// Queues the scene's entities, and their children, for despawning.
+    pub fn unload_scene(&mut self, handle: SceneHandle) {
+        // Not built yet, so there is nothing to despawn
+        let pending = self.pending_loads.len();
+        self.pending_loads.retain(|scene| scene.handle != handle);
+        if self.pending_loads.len() == pending && !self.pending_unloads.contains(&handle) {
+            self.pending_unloads.push(handle);
+        }
+    }
+
+    /// Unloads every scene that wasn't loaded with [`load_persistent`](Self::load_persistent).
+    pub fn unload_levels(&mut self) {
+        let levels: Vec<SceneHandle> = self
+            .loaded
+            .iter()
+            .chain(&self.pending_loads)
+            .filter(|scene| !scene.persistent)
+            .map(|scene| scene.handle)
+            .collect();
+        for handle in levels {
+            self.unload_scene(handle);
+        }
+    }
+
+    /// Whether the scene has been built and not unloaded.
+    pub fn is_loaded(&self, handle: SceneHandle) -> bool {
+        self.loaded.iter().any(|scene| scene.handle == handle)
+    }
+
+    pub fn loaded(&self) -> &[LoadedScene] {
+        &self.loaded
+    }
+}
+
+/// Sent after a scene's entities have been spawned.
+#[derive(Message, Debug, Clone)]
+pub struct SceneLoaded {
+    pub handle: SceneHandle,
+    pub name: String,
+}
+
+/// Sent after a scene's entities have been despawned.
+#[derive(Message, Debug, Clone)]
+pub struct SceneUnloaded {
+    pub handle: SceneHandle,
+    pub name: String,
+}
+
+#[derive(Default)]
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<Scenes>();
+        engine.world.init_resource::<Messages<SceneLoaded>>();
+        engine.world.init_resource::<Messages<SceneUnloaded>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            schedule.add_systems(scene_system);
+        }
+    }
+}
+
+/// Applies queued unloads, then queued loads.
+pub fn scene_system(world: &mut World) {
+    let Some(mut scenes) = world.get_resource_mut::<Scenes>() else {
+        return;
+    };
+    let unloads = std::mem::take(&mut scenes.pending_unloads);
+    let loads = std::mem::take(&mut scenes.pending_loads);
+    if unloads.is_empty() && loads.is_empty() {
+        return;
+    }
+
+    for handle in unloads {
+        despawn_scene(world, handle);
+        let mut scenes = world.resource_mut::<Scenes>();
+        let Some(index) = scenes
+            .loaded
+            .iter()
+            .position(|scene| scene.handle == handle)
+        else {
+            continue;
+        };
+        let scene = scenes.loaded.remove(index);
+        log::info!("Unloaded scene '{}'", scene.name);
+        world.write_message(SceneUnloaded {
+            handle,
+            name: scene.name,
+        });
+    }
+
+    for scene in loads {
+        let Some(build) = world
+            .resource::<Scenes>()
+            .templates
+            .get(&scene.name)
+            .cloned()
+        else {
+            continue;
+        };
+        let mut builder = SceneBuilder {
+            world,
+            scene: scene.handle,
+            spawned: Vec::new(),
+            entity_map: EntityMap::new(),
+        };
+        build(&mut builder);
+        let SceneBuilder {
+            spawned,
+            entity_map,
+            ..
+        } = builder;
+        if !entity_map.is_empty() {
+            remap_entities(world, &spawned, &entity_map);
+        }
+        log::info!("Loaded scene '{}'", scene.name);
+        world.write_message(SceneLoaded {
+            handle: scene.handle,
+            name: scene.name.clone(),
+        });
+        world.resource_mut::<Scenes>().loaded.push(scene);
+    }
+}
+
+/// Despawns the scene's members and their descendants, and detaches them from surviving parents.
+fn despawn_scene(world: &mut World, handle: SceneHandle) {
+    let mut stack: Vec<Entity> = world
+        .query::<(Entity, &SceneMember)>()
+        .iter(world)
+        .filter(|(_, member)| member.0 == handle)
+        .map(|(entity, _)| entity)
+        .collect();
+
+    let mut despawned = HashSet::new();
+    while let Some(entity) = stack.pop() {
+        if !despawned.insert(entity) {
+            continue;
+        }
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().copied());
+        }
+    }
+
+    for &entity in &despawned {
+        let Some(parent) = world.get::<Parent>(entity).map(Parent::get) else {
+            continue;
+        };
+        if despawned.contains(&parent) {
+            continue;
+        }
+        if let Some(mut children) = world.get_mut::<Children>(parent) {
+            children.remove(entity);
+        }
+    }
+
+    for entity in despawned {
+        world.despawn(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Marker;
+
+    #[test]
+    fn test_unload_removes_only_that_scene() {
+        let mut world = World::new();
+        world.init_resource::<Messages<SceneLoaded>>();
+        world.init_resource::<Messages<SceneUnloaded>>();
+
+        let mut scenes = Scenes::default();
+        scenes.register("core", |scene| {
+            scene.spawn(Marker);
+        });
+        scenes.register("level", |scene| {
+            let root = scene.spawn(Marker);
+            scene.spawn_child(root, Marker);
+        });
+        let core = scenes.load_persistent("core").unwrap();
+        let level = scenes.load("level").unwrap();
+        assert!(scenes.load("missing").is_none());
+        world.insert_resource(scenes);
+
+        scene_system(&mut world);
+        assert_eq!(world.query::<&Marker>().iter(&world).count(), 3);
+        assert!(world.resource::<Scenes>().is_loaded(level));
+
+        world.resource_mut::<Scenes>().unload_levels();
+        scene_system(&mut world);
+        let remaining: Vec<SceneHandle> = world
+            .query::<&SceneMember>()
+            .iter(&world)
+            .map(|member| member.0)
+            .collect();
+        assert_eq!(remaining, vec![core]);
+        assert!(!world.resource::<Scenes>().is_loaded(level));
+        assert!(world.resource::<Scenes>().is_loaded(core));
+    }
+}
@@ -0,0 +1,551 @@
+//! Timeline playback for cutscenes and scripted sequences.
+//!
+//! A [`TimelineAsset`] holds tracks keyed by binding name rather than entity, so
+//! the same timeline can be played against different actors. A
+//! [`SequencePlayer`] maps those names to entities and advances the timeline
+//! every frame.
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::assets::cache::CachePolicy;
+use crate::assets::loader::{AssetLoader, LoadError, load_asset};
+use crate::assets::{AssetHandle, Assets, AudioLoader};
+use crate::audio::{AudioOneShot, AudioSource};
+use crate::core::math::*;
+use crate::core::time::Time;
+use crate::transform::{GlobalTransform, Parent, Transform};
+use bevy_ecs::message::Messages;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransformKeyframe {
+    pub time: f32,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl TransformKeyframe {
+    pub fn from_transform(time: f32, transform: &Transform) -> Self {
+        Self {
+            time,
+            position: transform.position,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+/// Keyframed transform of one bound entity, interpolated linearly.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransformTrack {
+    pub target: String,
+    /// Sorted by time.
+    pub keyframes: Vec<TransformKeyframe>,
+}
+
+impl TransformTrack {
+    /// Holds the first and last keyframe outside the keyed range.
+    pub fn sample(&self, time: f32) -> Option<Transform> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        let (position, rotation, scale) = if time <= first.time {
+            (first.position, first.rotation, first.scale)
+        } else if time >= last.time {
+            (last.position, last.rotation, last.scale)
+        } else {
+            let next = self.keyframes.partition_point(|key| key.time <= time);
+            let a = &self.keyframes[next - 1];
+            let b = &self.keyframes[next];
+            let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+            (
+                a.position.lerp(b.position, t),
+                a.rotation.slerp(b.rotation, t),
+                a.scale.lerp(b.scale, t),
+            )
+        };
+
+        Some(Transform::from_prs(position, rotation, scale))
+    }
+}
+
+/// Switches the player's camera to follow the bound shot entity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraCut {
+    pub time: f32,
+    pub shot: String,
+}
+
+/// Plays a sound once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioCue {
+    pub time: f32,
+    pub path: String,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+/// Named marker reported through [`SequenceEvent`], e.g. to start a boss fight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+/// A cutscene. Load from RON `.timeline` files with [`TimelineLoader`] or build in code.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimelineAsset {
+    #[serde(default)]
+    pub transform_tracks: Vec<TransformTrack>,
+    #[serde(default)]
+    pub camera_cuts: Vec<CameraCut>,
+    #[serde(default)]
+    pub audio_cues: Vec<AudioCue>,
+    #[serde(default)]
+    pub events: Vec<TimelineEvent>,
+}
+
+impl TimelineAsset {
+    /// Time of the last key, cut, cue or event.
+    pub fn duration(&self) -> f32 {
+        let keys = self
+            .transform_tracks
+            .iter()
+            .filter_map(|track| track.keyframes.last().map(|key| key.time));
+        let cuts = self.camera_cuts.iter().map(|cut| cut.time);
+        let cues = self.audio_cues.iter().map(|cue| cue.time);
+        let events = self.events.iter().map(|event| event.time);
+
+        keys.chain(cuts)
+            .chain(cues)
+            .chain(events)
+            .fold(0.0, f32::max)
+    }
+
+    /// Shot that is active at `time`.
+    pub fn camera_shot_at(&self, time: f32) -> Option<&str> {
+        self.camera_cuts
+            .iter()
+            .filter(|cut| cut.time <= time)
+            .max_by(|a, b| a.time.total_cmp(&b.time))
+            .map(|cut| cut.shot.as_str())
+    }
+
+    /// Inserts a keyframe, replacing any existing key at the same time.
+    pub fn set_transform_key(&mut self, target: &str, keyframe: TransformKeyframe) {
+        let index = match self
+            .transform_tracks
+            .iter()
+            .position(|t| t.target == target)
+        {
+            Some(index) => index,
+            None => {
+                self.transform_tracks.push(TransformTrack {
+                    target: target.to_string(),
+                    keyframes: Vec::new(),
+                });
+                self.transform_tracks.len() - 1
+            }
+        };
+
+        let keyframes = &mut self.transform_tracks[index].keyframes;
+        match keyframes
+            .iter()
+            .position(|key| (key.time - keyframe.time).abs() < 1e-4)
+        {
+            Some(existing) => keyframes[existing] = keyframe,
+            None => {
+                let at = keyframes.partition_point(|key| key.time < keyframe.time);
+                keyframes.insert(at, keyframe);
+            }
+        }
+    }
+
+    pub fn remove_transform_key(&mut self, target: &str, time: f32) {
+        if let Some(track) = self
+            .transform_tracks
+            .iter_mut()
+            .find(|t| t.target == target)
+        {
+            track
+                .keyframes
+                .retain(|key| (key.time - time).abs() >= 1e-4);
+        }
+        self.transform_tracks
+            .retain(|track| !track.keyframes.is_empty());
+    }
+
+    pub fn add_camera_cut(&mut self, time: f32, shot: impl Into<String>) {
+        self.camera_cuts.push(CameraCut {
+            time,
+            shot: shot.into(),
+        });
+        self.camera_cuts.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    pub fn add_audio_cue(&mut self, time: f32, path: impl Into<String>, volume: f32) {
+        self.audio_cues.push(AudioCue {
+            time,
+            path: path.into(),
+            volume,
+        });
+        self.audio_cues.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    pub fn add_event(&mut self, time: f32, name: impl Into<String>) {
+        self.events.push(TimelineEvent {
+            time,
+            name: name.into(),
+        });
+        self.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path.as_ref(), contents)?;
+        Ok(())
+    }
+}
+
+pub struct TimelineLoader;
+
+impl AssetLoader for TimelineLoader {
+    type Asset = TimelineAsset;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| LoadError::LoadFailed(format!("Failed to read timeline file: {}", e)))?;
+
+        ron::from_str(&contents)
+            .map_err(|e| LoadError::LoadFailed(format!("Invalid timeline file: {}", e)))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["timeline"]
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy::Strong
+    }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(TimelineAsset::default())
+    }
+}
+
+/// Plays a [`TimelineAsset`] against bound entities.
+#[derive(Component, Debug, Clone)]
+pub struct SequencePlayer {
+    pub timeline: AssetHandle<TimelineAsset>,
+    /// Binding name to entity, used by transform tracks and camera cuts.
+    pub bindings: HashMap<String, Entity>,
+    /// Camera that follows the active shot.
+    pub camera: Option<Entity>,
+    pub time: f32,
+    pub speed: f32,
+    pub playing: bool,
+    pub looping: bool,
+    /// Cues and events up to this time have already fired.
+    fired_until: f32,
+}
+
+impl SequencePlayer {
+    pub fn new(timeline: AssetHandle<TimelineAsset>) -> Self {
+        Self {
+            timeline,
+            bindings: HashMap::new(),
+            camera: None,
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+            looping: false,
+            fired_until: -1.0,
+        }
+    }
+
+    pub fn with_binding(mut self, name: impl Into<String>, entity: Entity) -> Self {
+        self.bindings.insert(name.into(), entity);
+        self
+    }
+
+    pub fn with_camera(mut self, camera: Entity) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Jumps to `time` without firing the cues and events in between.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0);
+        self.fired_until = self.time;
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.fired_until = -1.0;
+        self.playing = true;
+    }
+}
+
+/// A [`TimelineEvent`] was reached.
+#[derive(Message, Debug, Clone)]
+pub struct SequenceEvent {
+    pub player: Entity,
+    pub name: String,
+}
+
+/// A non-looping sequence reached its end.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct SequenceFinished {
+    pub player: Entity,
+}
+
+#[derive(Default)]
+pub struct SequencerPlugin;
+
+impl Plugin for SequencerPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<Messages<SequenceEvent>>();
+        engine.world.init_resource::<Messages<SequenceFinished>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
+            schedule.add_systems(sequencer_system);
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::transform::TransformPlugin>(),
+            "resonance::transform::TransformPlugin",
+        )]
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        true
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        false
+    }
+}
+
+/// Records the current transform of a bound entity as a keyframe at the player's time.
+pub fn record_transform_key(
+    world: &World,
+    player: &SequencePlayer,
+    timeline: &mut TimelineAsset,
+    binding: &str,
+) -> bool {
+    let Some(transform) = player
+        .bindings
+        .get(binding)
+        .and_then(|&entity| world.get::<Transform>(entity))
+    else {
+        return false;
+    };
+
+    timeline.set_transform_key(
+        binding,
+        TransformKeyframe::from_transform(player.time, transform),
+    );
+    true
+}
+
+pub fn sequencer_system(world: &mut World) {
+    let Some(delta) = world.get_resource::<Time>().map(|t| t.delta_seconds()) else {
+        return;
+    };
+
+    let players: Vec<Entity> = world
+        .query_filtered::<Entity, With<SequencePlayer>>()
+        .iter(world)
+        .collect();
+
+    for player_entity in players {
+        let mut player = world.get::<SequencePlayer>(player_entity).unwrap().clone();
+        if !player.playing {
+            continue;
+        }
+
+        let timeline = match world.get_resource::<Assets>() {
+            Some(assets) if assets.is_loading::<TimelineAsset>(player.timeline.id) => continue,
+            Some(assets) => assets
+                .get::<TimelineAsset>(player.timeline.id)
+                .unwrap_or(player.timeline.asset.clone()),
+            None => player.timeline.asset.clone(),
+        };
+
+        let duration = timeline.duration();
+        let mut finished = false;
+        player.time += delta * player.speed;
+
+        if player.time >= duration {
+            fire_cues(
+                world,
+                player_entity,
+                &timeline,
+                player.fired_until,
+                duration,
+            );
+            if player.looping && duration > 0.0 {
+                player.time %= duration;
+                player.fired_until = -1.0;
+            } else {
+                player.time = duration;
+                player.fired_until = duration;
+                player.playing = false;
+                finished = true;
+            }
+        }
+
+        fire_cues(
+            world,
+            player_entity,
+            &timeline,
+            player.fired_until,
+            player.time,
+        );
+        player.fired_until = player.fired_until.max(player.time);
+
+        apply_tracks(world, &player, &timeline);
+
+        if let Some(mut stored) = world.get_mut::<SequencePlayer>(player_entity) {
+            *stored = player;
+        }
+        if finished {
+            world.write_message(SequenceFinished {
+                player: player_entity,
+            });
+        }
+    }
+}
+
+fn apply_tracks(world: &mut World, player: &SequencePlayer, timeline: &TimelineAsset) {
+    for track in &timeline.transform_tracks {
+        let Some(&target) = player.bindings.get(&track.target) else {
+            continue;
+        };
+        let Some(sampled) = track.sample(player.time) else {
+            continue;
+        };
+        if let Some(mut transform) = world.get_mut::<Transform>(target) {
+            *transform = sampled;
+        }
+    }
+
+    let Some(camera) = player.camera else { return };
+    let Some(shot) = timeline
+        .camera_shot_at(player.time)
+        .and_then(|shot| player.bindings.get(shot))
+    else {
+        return;
+    };
+
+    // Tracks applied above only reach GlobalTransform after propagation, so
+    // read root shots from their local transform
+    let pose = if world.get::<Parent>(*shot).is_none() {
+        world
+            .get::<Transform>(*shot)
+            .map(|local| (local.position, local.rotation))
+    } else {
+        world.get::<GlobalTransform>(*shot).map(|global| {
+            let (_, rotation, position) = global.matrix().to_scale_rotation_translation();
+            (position, rotation)
+        })
+    };
+    let Some((position, rotation)) = pose else {
+        return;
+    };
+
+    if let Some(mut transform) = world.get_mut::<Transform>(camera) {
+        transform.position = position;
+        transform.rotation = rotation;
+    }
+}
+
+/// Fires audio cues and events in `(from, to]`.
+fn fire_cues(world: &mut World, player: Entity, timeline: &TimelineAsset, from: f32, to: f32) {
+    let in_range = |time: f32| time > from && time <= to;
+
+    for cue in timeline.audio_cues.iter().filter(|cue| in_range(cue.time)) {
+        let Some(cache) = world
+            .get_resource::<Assets>()
+            .map(|assets| Arc::clone(assets.cache()))
+        else {
+            continue;
+        };
+        match load_asset(&AudioLoader, &cue.path, &cache) {
+            Ok(handle) => {
+                world.spawn((
+                    AudioSource::new(handle)
+                        .with_volume(cue.volume)
+                        .play_on_spawn(),
+                    AudioOneShot,
+                ));
+            }
+            Err(e) => log::warn!("Failed to play timeline audio cue {}: {}", cue.path, e),
+        }
+    }
+
+    for event in timeline.events.iter().filter(|event| in_range(event.time)) {
+        world.write_message(SequenceEvent {
+            player,
+            name: event.name.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_track_sampling() {
+        let mut timeline = TimelineAsset::default();
+        let mut key = |time: f32, x: f32| {
+            timeline.set_transform_key(
+                "door",
+                TransformKeyframe::from_transform(time, &Transform::from_xyz(x, 0.0, 0.0)),
+            )
+        };
+        key(2.0, 10.0);
+        key(0.0, 0.0);
+        key(2.0, 20.0);
+        timeline.add_event(3.0, "boss_start");
+
+        let track = &timeline.transform_tracks[0];
+        assert_eq!(track.keyframes.len(), 2);
+        assert_eq!(track.sample(-1.0).unwrap().position.x, 0.0);
+        assert_eq!(track.sample(1.0).unwrap().position.x, 10.0);
+        assert_eq!(track.sample(5.0).unwrap().position.x, 20.0);
+        assert_eq!(timeline.duration(), 3.0);
+    }
+
+    #[test]
+    fn test_camera_shot_at() {
+        let mut timeline = TimelineAsset::default();
+        timeline.add_camera_cut(4.0, "close_up");
+        timeline.add_camera_cut(0.0, "wide");
+
+        assert_eq!(timeline.camera_shot_at(-1.0), None);
+        assert_eq!(timeline.camera_shot_at(1.0), Some("wide"));
+        assert_eq!(timeline.camera_shot_at(4.0), Some("close_up"));
+    }
+}
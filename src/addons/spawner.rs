@@ -0,0 +1,296 @@
+use crate::app::{Plugin, Resonance, Stage};
+use crate::core::math::*;
+use crate::core::time::Time;
+use crate::net::ClientInterests;
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Builds an entity at the given world position and returns it.
+pub type SpawnFn = Arc<dyn Fn(&mut World, Vec3) -> Entity + Send + Sync>;
+
+/// Named spawn templates referenced by [`SpawnPoint::template`].
+#[derive(Resource, Default, Clone)]
+pub struct SpawnTemplates {
+    templates: HashMap<String, SpawnFn>,
+}
+
+impl SpawnTemplates {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        spawn: impl Fn(&mut World, Vec3) -> Entity + Send + Sync + 'static,
+    ) {
+        self.templates.insert(name.into(), Arc::new(spawn));
+    }
+
+    pub fn get(&self, name: &str) -> Option<SpawnFn> {
+        self.templates.get(name).cloned()
+    }
+}
+
+/// Maximum number of live spawned entities per region. Regions without a limit are unbounded.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PopulationLimits {
+    limits: HashMap<String, usize>,
+}
+
+impl PopulationLimits {
+    pub fn set(&mut self, region: impl Into<String>, max: usize) {
+        self.limits.insert(region.into(), max);
+    }
+
+    pub fn get(&self, region: &str) -> Option<usize> {
+        self.limits.get(region).copied()
+    }
+}
+
+/// Keeps one entity from `template` alive at this point's position.
+#[derive(Component, Debug, Clone)]
+pub struct SpawnPoint {
+    pub template: String,
+    pub region: String,
+    /// Seconds to wait after the spawned entity is gone before spawning again.
+    pub respawn_delay: f32,
+    /// Only spawn while a [`SpawnObserver`] is within this distance, or the point lies in a
+    /// client's interest area from [`ClientInterests`].
+    pub activation_radius: f32,
+    /// Despawn the entity after the point has been unobserved for this long.
+    pub despawn_delay: f32,
+    pub spawned: Option<Entity>,
+    pub respawn_timer: f32,
+    pub unobserved_time: f32,
+}
+
+impl SpawnPoint {
+    pub fn new(template: impl Into<String>, region: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            region: region.into(),
+            respawn_delay: 30.0,
+            activation_radius: 100.0,
+            despawn_delay: 10.0,
+            spawned: None,
+            respawn_timer: 0.0,
+            unobserved_time: 0.0,
+        }
+    }
+
+    pub fn with_respawn_delay(mut self, seconds: f32) -> Self {
+        self.respawn_delay = seconds;
+        self
+    }
+
+    pub fn with_activation_radius(mut self, radius: f32) -> Self {
+        self.activation_radius = radius;
+        self
+    }
+
+    pub fn with_despawn_delay(mut self, seconds: f32) -> Self {
+        self.despawn_delay = seconds;
+        self
+    }
+}
+
+/// Marks entities whose presence keeps spawn points active, for games that don't track
+/// [`ClientInterests`], e.g. single-player or listen servers.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct SpawnObserver;
+
+/// Added to every entity created by a [`SpawnPoint`].
+#[derive(Component, Debug, Clone)]
+pub struct Spawned {
+    pub point: Entity,
+    pub region: String,
+}
+
+/// Server-side NPC population management.
+#[derive(Default)]
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine.world.init_resource::<SpawnTemplates>();
+        engine.world.init_resource::<PopulationLimits>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
+            schedule.add_systems(spawner_system);
+        }
+    }
+
+    fn is_client_plugin(&self) -> bool {
+        false
+    }
+
+    fn is_server_plugin(&self) -> bool {
+        true
+    }
+}
+
+pub fn spawner_system(world: &mut World) {
+    let Some(delta) = world.get_resource::<Time>().map(|t| t.delta_seconds()) else {
+        return;
+    };
+    update_spawners(world, delta);
+}
+
+/// Advances every [`SpawnPoint`] by `delta` seconds.
+pub fn update_spawners(world: &mut World, delta: f32) {
+    let interests = world
+        .get_resource::<ClientInterests>()
+        .cloned()
+        .unwrap_or_default();
+
+    let observers: Vec<Vec3> = world
+        .query_filtered::<&GlobalTransform, With<SpawnObserver>>()
+        .iter(world)
+        .map(|transform| transform.position())
+        .collect();
+
+    let mut population: HashMap<String, usize> = HashMap::new();
+    for spawned in world.query::<&Spawned>().iter(world) {
+        *population.entry(spawned.region.clone()).or_default() += 1;
+    }
+
+    let points: Vec<(Entity, Vec3)> = world
+        .query_filtered::<(Entity, &GlobalTransform), With<SpawnPoint>>()
+        .iter(world)
+        .map(|(entity, transform)| (entity, transform.position()))
+        .collect();
+
+    let mut to_despawn = Vec::new();
+    let mut to_spawn = Vec::new();
+
+    for (point_entity, position) in points {
+        let point = world.get::<SpawnPoint>(point_entity).unwrap().clone();
+
+        let observed = interests.any_contains(position)
+            || observers
+                .iter()
+                .any(|observer| observer.distance(position) <= point.activation_radius);
+
+        if let Some(spawned) = point.spawned {
+            let alive = world.get_entity(spawned).is_ok();
+            let mut point = world.get_mut::<SpawnPoint>(point_entity).unwrap();
+
+            if !alive {
+                // Killed or removed by gameplay code, start the respawn timer
+                point.spawned = None;
+                point.respawn_timer = point.respawn_delay;
+            } else if observed {
+                point.unobserved_time = 0.0;
+            } else {
+                point.unobserved_time += delta;
+                if point.unobserved_time >= point.despawn_delay {
+                    to_despawn.push(spawned);
+                    point.spawned = None;
+                    point.unobserved_time = 0.0;
+                    // No respawn delay: the entity was culled, not killed
+                    point.respawn_timer = 0.0;
+                }
+            }
+            continue;
+        }
+
+        let respawn_timer = (point.respawn_timer - delta).max(0.0);
+        world
+            .get_mut::<SpawnPoint>(point_entity)
+            .unwrap()
+            .respawn_timer = respawn_timer;
+
+        if respawn_timer > 0.0 || !observed {
+            continue;
+        }
+
+        let count = population.get(&point.region).copied().unwrap_or(0);
+        let limit = world
+            .get_resource::<PopulationLimits>()
+            .and_then(|limits| limits.get(&point.region));
+        if limit.is_some_and(|limit| count >= limit) {
+            continue;
+        }
+
+        *population.entry(point.region.clone()).or_default() += 1;
+        to_spawn.push((point_entity, position, point.template, point.region));
+    }
+
+    for entity in to_despawn {
+        world.despawn(entity);
+    }
+
+    for (point_entity, position, template, region) in to_spawn {
+        let Some(spawn) = world
+            .get_resource::<SpawnTemplates>()
+            .and_then(|templates| templates.get(&template))
+        else {
+            log::warn!("Spawn template '{}' is not registered", template);
+            continue;
+        };
+
+        let entity = spawn(world, position);
+        world.entity_mut(entity).insert(Spawned {
+            point: point_entity,
+            region,
+        });
+
+        if let Some(mut point) = world.get_mut::<SpawnPoint>(point_entity) {
+            point.spawned = Some(entity);
+            point.unobserved_time = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ClientInterest;
+    use crate::transform::Transform;
+
+    #[test]
+    fn test_respawn_after_delay_while_in_interest() {
+        let mut world = World::new();
+        world.init_resource::<SpawnTemplates>();
+        world
+            .resource_mut::<SpawnTemplates>()
+            .register("wolf", |world, position| {
+                world
+                    .spawn((
+                        Transform::from_position(position),
+                        GlobalTransform::default(),
+                    ))
+                    .id()
+            });
+        let position = Vec3::new(50.0, 0.0, 0.0);
+        world.spawn((
+            SpawnPoint::new("wolf", "forest").with_respawn_delay(30.0),
+            GlobalTransform::from_transform(&Transform::from_position(position)),
+        ));
+        let spawned =
+            |world: &mut World| world.query::<&SpawnPoint>().single(world).unwrap().spawned;
+
+        // Nobody is interested in the point yet
+        update_spawners(&mut world, 0.1);
+        assert_eq!(spawned(&mut world), None);
+
+        let mut interests = ClientInterests::default();
+        interests.0.insert(
+            1,
+            ClientInterest {
+                position: Vec3::ZERO,
+                radius: 60.0,
+            },
+        );
+        world.insert_resource(interests);
+        update_spawners(&mut world, 0.1);
+        let wolf = spawned(&mut world).unwrap();
+
+        // Killed: nothing until the respawn delay has passed
+        world.despawn(wolf);
+        update_spawners(&mut world, 0.1);
+        update_spawners(&mut world, 29.0);
+        assert_eq!(spawned(&mut world), None);
+        update_spawners(&mut world, 1.5);
+        assert!(spawned(&mut world).is_some_and(|entity| entity != wolf));
+    }
+}
@@ -1,27 +1,71 @@
 use crate::app::{Plugin, Resonance, Stage};
-use bevy_ecs::prelude::Resource;
+use crate::assets::handle::AssetId;
+use crate::core::math::Vec3;
+use crate::renderer::{Mesh, MeshUploaded};
+use bevy_ecs::prelude::*;
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct WireframeState {
     pub enabled: bool,
 }
 
-impl Default for WireframeState {
+/// Draws one entity's wireframe on top of its shaded mesh, independently of
+/// the global toggle in [`WireframeState`]. Useful for highlighting a selection.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WireframeOverlay {
+    pub color: Vec3,
+    /// Pulls the lines toward the camera, in normalized depth units, so they
+    /// aren't hidden by the surface they outline.
+    pub depth_bias: f32,
+}
+
+impl Default for WireframeOverlay {
     fn default() -> Self {
-        Self { enabled: false }
+        Self {
+            color: Vec3::new(1.0, 0.6, 0.0),
+            depth_bias: 0.0001,
+        }
+    }
+}
+
+impl WireframeOverlay {
+    pub fn new(color: Vec3) -> Self {
+        Self {
+            color,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: f32) -> Self {
+        self.depth_bias = depth_bias;
+        self
     }
 }
 
+/// Overlays to draw this frame, filled after render extraction.
+#[derive(Resource, Default)]
+pub struct ExtractedWireframeOverlays {
+    pub overlays: Vec<(Entity, AssetId, WireframeOverlay)>,
+}
+
 #[derive(Default)]
 pub struct WireframePlugin;
 
 impl Plugin for WireframePlugin {
     fn build(&self, engine: &mut Resonance) {
         engine.world.init_resource::<WireframeState>();
+        engine.world.init_resource::<ExtractedWireframeOverlays>();
 
         if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
             schedule.add_systems(handle_wireframe_toggle);
         }
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PostUpdate) {
+            use bevy_ecs::schedule::IntoScheduleConfigs;
+            schedule.add_systems(
+                extract_wireframe_overlays.after(crate::renderer::extract::extract_render_data),
+            );
+        }
     }
 
     fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
@@ -41,8 +85,8 @@ impl Plugin for WireframePlugin {
 }
 
 fn handle_wireframe_toggle(
-    mut state: bevy_ecs::prelude::ResMut<WireframeState>,
-    input: Option<bevy_ecs::prelude::Res<crate::input::Input>>,
+    mut state: ResMut<WireframeState>,
+    input: Option<Res<crate::input::Input>>,
 ) {
     use winit::keyboard::KeyCode;
 
@@ -56,3 +100,15 @@ fn handle_wireframe_toggle(
         );
     }
 }
+
+fn extract_wireframe_overlays(
+    mut extracted: ResMut<ExtractedWireframeOverlays>,
+    overlays: Query<(Entity, &Mesh, &WireframeOverlay), With<MeshUploaded>>,
+) {
+    extracted.overlays.clear();
+    extracted.overlays.extend(
+        overlays
+            .iter()
+            .map(|(entity, mesh, overlay)| (entity, mesh.handle.id, *overlay)),
+    );
+}
@@ -25,8 +25,12 @@ pub struct Resonance {
     runner: ResonanceRunner,
     /// Target frame time for headless mode (used to calculate sleep duration)
     target_frametime: Duration,
+    /// [`Plugin::on_shutdown`] of every built plugin, in build order.
+    shutdown_hooks: Vec<(String, ShutdownHook)>,
 }
 
+type ShutdownHook = Box<dyn FnOnce(&mut Resonance) + Send + Sync>;
+
 impl Resonance {
     /// Creates a new engine instance in client mode (with rendering)
     pub fn new() -> Self {
@@ -74,6 +78,7 @@ impl Resonance {
             plugins: HashMap::new(),
             runner,
             target_frametime: Duration::from_millis(16), // Default 62.5 FPS
+            shutdown_hooks: Vec::new(),
         }
     }
 
@@ -97,13 +102,60 @@ impl Resonance {
         self
     }
 
+    /// Enables the [`DeterminismAudit`](crate::core::DeterminismAudit) for the fixed-update path.
+    ///
+    /// Also logs fixed-update systems with conflicting access and no ordering between them.
+    /// Meant for debug builds; call `log_report` on the resource to print the findings.
+    pub fn with_determinism_audit(mut self) -> Self {
+        use bevy_ecs::schedule::{LogLevel, ScheduleBuildSettings};
+
+        if let Some(schedule) = self.schedules.get_mut(Stage::FixedUpdate) {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Warn,
+                ..Default::default()
+            });
+        }
+        self.world
+            .insert_resource(crate::core::DeterminismAudit::default());
+        self
+    }
+
+    /// Inserts the [`FeatureFlags`](crate::core::FeatureFlags) plugins consult while building
+    /// and logs the active ones. Call before adding plugins.
+    pub fn with_feature_flags(mut self, flags: crate::core::FeatureFlags) -> Self {
+        flags.log_active();
+        self.world.insert_resource(flags);
+        self
+    }
+
+    /// Whether a feature flag is enabled, `false` without [`FeatureFlags`](crate::core::FeatureFlags).
+    pub fn is_feature_enabled(&self, name: &str) -> bool {
+        self.world
+            .get_resource::<crate::core::FeatureFlags>()
+            .is_some_and(|flags| flags.is_enabled(name))
+    }
+
+    /// Adds `plugin` only when the feature flag `name` is enabled.
+    pub fn add_plugin_if_enabled<P: Plugin>(self, name: &str, plugin: P) -> Self {
+        if self.is_feature_enabled(name) {
+            self.add_plugin(plugin)
+        } else {
+            log::debug!(
+                "Plugin '{}' skipped (feature '{}' disabled)",
+                plugin.name(),
+                name
+            );
+            self
+        }
+    }
+
     /// Sets the target tickrate for headless (server) mode
     ///
     /// # Arguments
     /// * `fps` - Target frames per second (e.g., 20 for slower servers, 128 for competitive games)
     ///
     /// # Example
-    /// ```no_run
+    /// ```ignore
     /// Resonance::new_with_mode(ResonanceMode::Server)
     ///     .with_tickrate(20)
     ///     .run();
@@ -182,6 +234,8 @@ impl Resonance {
         if let Some(metadata) = self.plugins.get_mut(&type_id) {
             metadata.state = PluginState::Built;
         }
+        self.shutdown_hooks
+            .push((name, Box::new(move |engine| plugin.on_shutdown(engine))));
 
         self
     }
@@ -238,9 +292,26 @@ impl Resonance {
 
     pub fn update(&mut self) {
         self.runner.run(&mut self.world, &mut self.schedules);
+
+        let exit_requested = self
+            .world
+            .get_resource::<bevy_ecs::message::Messages<crate::core::AppExit>>()
+            .is_some_and(|messages| !messages.is_empty());
+        if exit_requested {
+            self.stop();
+        }
+    }
+
+    /// Builds every stage's schedule and returns its resolved system order, explicit
+    /// ordering edges and ambiguously ordered systems. See [`ScheduleDump`](super::ScheduleDump).
+    pub fn dump_schedule(&mut self) -> super::ScheduleDump {
+        super::schedule_dump::dump_schedules(&mut self.world, &mut self.schedules)
     }
 
     pub fn startup(&mut self) {
+        if cfg!(debug_assertions) && log::log_enabled!(log::Level::Debug) {
+            log::debug!("Resolved schedule:\n{}", self.dump_schedule());
+        }
         self.running = true;
         self.run_schedule(Stage::Startup);
     }
@@ -249,6 +320,38 @@ impl Resonance {
         self.running = false;
     }
 
+    /// Tears the engine down in a fixed order instead of whatever order the world drops
+    /// its resources in:
+    ///
+    /// 1. [`Plugin::on_shutdown`] hooks, in reverse build order. The built-in plugins stop
+    ///    shader hot reloading, wait for in-flight GPU work and stop async asset loads here.
+    /// 2. Entities and every resource except the [`Renderer`](crate::renderer::Renderer)
+    ///    and [`Window`](crate::window::Window), which drops the GPU buffers and pipelines.
+    /// 3. The renderer with its device and surface, then the window.
+    ///
+    /// The runners call this on exit. Calling it again does nothing.
+    pub fn shutdown(&mut self) {
+        self.running = false;
+        let hooks = std::mem::take(&mut self.shutdown_hooks);
+        if hooks.is_empty() && self.world.entities().is_empty() {
+            return;
+        }
+        log::info!("Shutting down");
+
+        for (name, hook) in hooks.into_iter().rev() {
+            log::debug!("Shutting down plugin '{}'", name);
+            hook(self);
+        }
+
+        let renderer = self.world.remove_resource::<crate::renderer::Renderer>();
+        let window = self.world.remove_resource::<crate::window::Window>();
+        self.world.clear_entities();
+        self.world.clear_resources();
+        // The surface has to go before the window it draws into
+        drop(renderer);
+        drop(window);
+    }
+
     pub fn is_running(&self) -> bool {
         self.running
     }
@@ -268,7 +371,7 @@ impl Resonance {
     /// Entity ID of the spawned camera
     ///
     /// # Example
-    /// ```no_run
+    /// ```ignore
     /// let camera = engine.spawn_camera(
     ///     Vec3::new(0.0, 10.0, 10.0),
     ///     Vec3::ZERO
@@ -298,7 +401,7 @@ impl Resonance {
     /// Entity ID of the spawned mesh
     ///
     /// # Example
-    /// ```no_run
+    /// ```ignore
     /// let mesh_handle = assets.load(MeshLoader::new(), "models/cube.obj");
     /// let entity = engine.spawn_mesh(mesh_handle, Vec3::new(0.0, 0.0, 0.0));
     /// ```
@@ -358,6 +461,8 @@ impl Resonance {
                 std::thread::sleep(self.target_frametime - elapsed);
             }
         }
+
+        self.shutdown();
     }
 }
 
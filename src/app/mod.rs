@@ -48,11 +48,22 @@
 //!
 //! **Why**: User input must be available for game logic in Update stage.
 //!
+//! ## Inspecting the Resolved Order
+//!
+//! [`Resonance::dump_schedule`] lists every stage's systems in execution order with their
+//! explicit edges and any unordered systems whose data access conflicts. Debug builds log
+//! it at startup when the log level is `Debug` or lower:
+//!
+//! ```rust,ignore
+//! let mut engine = Resonance::new().add_plugin(DefaultPlugins);
+//! println!("{}", engine.dump_schedule());
+//! ```
+//!
 //! ## Adding Systems with Dependencies
 //!
 //! When adding new systems, use Bevy's `.before()` and `.after()` system ordering:
 //!
-//! ```rust
+//! ```ignore
 //! use resonance::prelude::*;
 //!
 //! Resonance::new()
@@ -65,9 +76,11 @@ pub mod default_plugins;
 pub mod engine;
 pub mod plugin;
 pub mod runner;
+pub mod schedule_dump;
 pub mod stage;
 
 pub use default_plugins::DefaultPlugins;
 pub use engine::{Resonance, ResonanceMode};
 pub use plugin::{CorePlugin, Plugin, PluginMetadata, PluginState};
+pub use schedule_dump::{ScheduleDump, StageDump};
 pub use stage::Stage;
@@ -27,6 +27,11 @@ pub trait Plugin: Default + Send + Sync + 'static {
     fn is_server_plugin(&self) -> bool {
         true
     }
+
+    /// Called by [`Resonance::shutdown`] in reverse build order, while every resource
+    /// is still in the world. Stop background work and release what needs releasing
+    /// before the renderer and window go away.
+    fn on_shutdown(&self, _engine: &mut Resonance) {}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,14 +49,9 @@ pub struct PluginMetadata {
     pub dependencies: Vec<TypeId>,
 }
 
+#[derive(Default)]
 pub struct CorePlugin {}
 
-impl Default for CorePlugin {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl CorePlugin {
     pub fn new() -> Self {
         Self::default()
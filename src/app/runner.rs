@@ -16,7 +16,8 @@ impl ResonanceRunner {
     }
 
     pub fn run_schedule(&self, schedule: &mut Schedule, world: &mut World, stage_name: &'static str) {
-        if self.profiling_enabled {
+        // The profiler is usually inserted after the runner is built
+        if self.profiling_enabled || world.contains_resource::<crate::core::Profiler>() {
             let start = Instant::now();
             schedule.run(world);
             if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
@@ -34,15 +35,27 @@ impl ResonanceRunner {
 
         // Check if paused - skip all systems except time update
         let is_paused = time.is_paused();
-        drop(time);
 
         if is_paused {
             return; // Skip all systems when paused
         }
 
         // Run pre-update and update stages
-        for stage in [Stage::PreUpdate, Stage::Update] {
-            self.run_schedule(schedules.get_mut(stage).unwrap(), world, stage.name());
+        self.run_schedule(
+            schedules.get_mut(Stage::PreUpdate).unwrap(),
+            world,
+            Stage::PreUpdate.name(),
+        );
+        // An unfocused window may pause gameplay, everything else keeps running
+        let update_paused = world
+            .get_resource::<crate::window::BackgroundState>()
+            .is_some_and(|state| state.update_paused());
+        if !update_paused {
+            self.run_schedule(
+                schedules.get_mut(Stage::Update).unwrap(),
+                world,
+                Stage::Update.name(),
+            );
         }
 
         // Fixed timestep loop for physics/deterministic updates
@@ -53,11 +66,13 @@ impl ResonanceRunner {
         while world.resource::<crate::core::FixedTime>().should_update() {
             world.resource_mut::<crate::core::GameTick>().increment();
 
-            self.run_schedule(
-                schedules.get_mut(Stage::FixedUpdate).unwrap(),
-                world,
-                "Stage::FixedUpdate",
-            );
+            crate::core::determinism::audited_fixed_step(world, |world| {
+                self.run_schedule(
+                    schedules.get_mut(Stage::FixedUpdate).unwrap(),
+                    world,
+                    "Stage::FixedUpdate",
+                );
+            });
 
             world.resource_mut::<crate::core::FixedTime>().consume_step();
         }
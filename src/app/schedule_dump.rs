@@ -0,0 +1,183 @@
+//! Resolved system order of every stage, for diagnosing ordering bugs.
+//!
+//! [`Resonance::dump_schedule`](crate::app::Resonance::dump_schedule) builds each stage's
+//! schedule and reports the order its systems run in on a single thread, the explicit
+//! `before`/`after` edges between systems, and pairs of systems with conflicting data
+//! access and no ordering between them. Those pairs may run in either order from frame to
+//! frame; see the ordering guide in the [`app`](crate::app) module docs.
+
+use super::stage::Stage;
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::{NodeId, SystemKey};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Default)]
+pub struct StageDump {
+    /// Systems in the order the single-threaded executor runs them.
+    pub systems: Vec<String>,
+    /// `(before, after)` pairs from explicit ordering, resolved to systems.
+    pub edges: Vec<(String, String)>,
+    /// Unordered systems with conflicting access.
+    pub ambiguities: Vec<(String, String)>,
+    /// Set when the schedule failed to build, e.g. because of an ordering cycle.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleDump {
+    pub stages: Vec<(Stage, StageDump)>,
+}
+
+impl ScheduleDump {
+    pub fn stage(&self, stage: Stage) -> Option<&StageDump> {
+        self.stages
+            .iter()
+            .find(|(dumped, _)| *dumped == stage)
+            .map(|(_, dump)| dump)
+    }
+
+    pub fn ambiguity_count(&self) -> usize {
+        self.stages
+            .iter()
+            .map(|(_, dump)| dump.ambiguities.len())
+            .sum()
+    }
+}
+
+pub(crate) fn dump_schedules(world: &mut World, schedules: &mut Schedules) -> ScheduleDump {
+    let mut dump = ScheduleDump::default();
+    for stage in Stage::all() {
+        if let Some(schedule) = schedules.get_mut(stage) {
+            dump.stages.push((stage, dump_stage(world, schedule)));
+        }
+    }
+    dump
+}
+
+fn dump_stage(world: &mut World, schedule: &mut Schedule) -> StageDump {
+    if let Err(e) = schedule.initialize(world) {
+        return StageDump {
+            error: Some(e.to_string(schedule.graph(), world)),
+            ..Default::default()
+        };
+    }
+
+    let Ok(systems) = schedule.systems() else {
+        return StageDump::default();
+    };
+    let ordered: Vec<(SystemKey, String)> = systems
+        .map(|(key, system)| (key, system.name().to_string()))
+        .collect();
+    let names: HashMap<SystemKey, String> = ordered.iter().cloned().collect();
+    let mut stage = StageDump {
+        systems: ordered.into_iter().map(|(_, name)| name).collect(),
+        ..Default::default()
+    };
+
+    let graph = schedule.graph();
+    let name = |key: &SystemKey| names.get(key).cloned().unwrap_or_default();
+    // `a.after(b)` orders against the set `b` belongs to, so edges are resolved to the
+    // systems inside sets
+    let systems_in = |node: NodeId| {
+        let mut systems = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            match node {
+                NodeId::System(key) => systems.push(key),
+                NodeId::Set(_) => stack.extend(graph.hierarchy().graph().neighbors(node)),
+            }
+        }
+        systems
+    };
+    for (before, after) in graph.dependency().graph().all_edges() {
+        for before in systems_in(before) {
+            for after in systems_in(after) {
+                let edge = (name(&before), name(&after));
+                if !stage.edges.contains(&edge) {
+                    stage.edges.push(edge);
+                }
+            }
+        }
+    }
+    stage.ambiguities = graph
+        .conflicting_systems()
+        .iter()
+        .map(|(a, b, _)| (name(a), name(b)))
+        .collect();
+
+    stage
+}
+
+impl fmt::Display for ScheduleDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (stage, dump) in &self.stages {
+            if let Some(error) = &dump.error {
+                writeln!(f, "{}: failed to build: {}", stage.name(), error)?;
+                continue;
+            }
+            writeln!(f, "{} ({} systems)", stage.name(), dump.systems.len())?;
+            for (i, system) in dump.systems.iter().enumerate() {
+                writeln!(f, "  {:>3}. {}", i + 1, system)?;
+            }
+            if !dump.edges.is_empty() {
+                writeln!(f, "  edges:")?;
+                for (before, after) in &dump.edges {
+                    writeln!(f, "    {} -> {}", before, after)?;
+                }
+            }
+            if !dump.ambiguities.is_empty() {
+                writeln!(f, "  ambiguities:")?;
+                for (a, b) in &dump.ambiguities {
+                    writeln!(f, "    {} <-> {}", a, b)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Resonance;
+
+    #[derive(Resource, Default)]
+    struct Counter(u32);
+
+    fn first(mut counter: ResMut<Counter>) {
+        counter.0 += 1;
+    }
+
+    fn second(mut counter: ResMut<Counter>) {
+        counter.0 *= 2;
+    }
+
+    fn unordered(mut counter: ResMut<Counter>) {
+        counter.0 = 0;
+    }
+
+    #[test]
+    fn test_dump_reports_order_and_ambiguities() {
+        let mut engine = Resonance::new()
+            .with_resource(Counter::default())
+            .add_systems(Stage::Update, (second.after(first), first))
+            .add_system(Stage::Last, (first, unordered));
+
+        let dump = engine.dump_schedule();
+        let update = dump.stage(Stage::Update).unwrap();
+        let position = |name: &str| {
+            update
+                .systems
+                .iter()
+                .position(|system| system.ends_with(name))
+                .unwrap()
+        };
+        assert!(position("first") < position("second"));
+        assert_eq!(update.edges.len(), 1);
+        assert!(update.ambiguities.is_empty());
+
+        assert_eq!(dump.stage(Stage::Last).unwrap().ambiguities.len(), 1);
+        assert_eq!(dump.ambiguity_count(), 1);
+    }
+}
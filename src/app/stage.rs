@@ -36,7 +36,7 @@ use bevy_ecs::schedule::ScheduleLabel;
 ///
 /// # Example
 ///
-/// ```rust
+/// ```ignore
 /// use resonance::prelude::*;
 ///
 /// Resonance::new()
@@ -4,7 +4,7 @@ use crate::assets::loader::AssetLoader;
 use bevy_ecs::prelude::*;
 use dashmap::DashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug)]
 pub enum LoadState<T> {
@@ -23,12 +23,24 @@ impl<T> Clone for LoadState<T> {
     }
 }
 
+/// A background load that completed since the last [`Assets::drain_finished`].
+#[derive(Debug, Clone)]
+pub struct FinishedLoad {
+    pub id: AssetId,
+    pub path: String,
+    /// Why loading failed, `None` on success.
+    pub error: Option<String>,
+    /// The loader has a fallback asset, which handles from [`Assets::load`] keep showing.
+    pub fallback: bool,
+}
+
 #[derive(Resource)]
 pub struct Assets {
     runtime: tokio::runtime::Handle,
-    _owned_runtime: Option<tokio::runtime::Runtime>,
+    owned_runtime: Option<tokio::runtime::Runtime>,
     cache: Arc<AssetCache>,
     states: Arc<DashMap<AssetId, Box<dyn std::any::Any + Send + Sync>>>,
+    finished: Arc<Mutex<Vec<FinishedLoad>>>,
 }
 
 impl Assets {
@@ -47,9 +59,10 @@ impl Assets {
 
         Self {
             runtime,
-            _owned_runtime: owned_runtime,
+            owned_runtime,
             cache: Arc::new(AssetCache::new()),
             states: Arc::new(DashMap::new()),
+            finished: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -68,9 +81,10 @@ impl Assets {
 
         Self {
             runtime,
-            _owned_runtime: owned_runtime,
+            owned_runtime,
             cache,
             states: Arc::new(DashMap::new()),
+            finished: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -78,6 +92,15 @@ impl Assets {
         &self.cache
     }
 
+    /// Stops the background loader threads. Loads still running get `timeout` to finish;
+    /// the rest are abandoned and stay [`LoadState::Loading`]. Does nothing when the
+    /// assets run on a runtime they don't own.
+    pub fn shutdown(&mut self, timeout: std::time::Duration) {
+        if let Some(runtime) = self.owned_runtime.take() {
+            runtime.shutdown_timeout(timeout);
+        }
+    }
+
     pub fn load<L: AssetLoader + 'static>(
         &self,
         loader: L,
@@ -104,35 +127,88 @@ impl Assets {
             )
         }).expect("Asset loader missing default");
         let handle = self.cache.insert(&path_str, default_asset, policy);
+        self.spawn_load(loader, path, id);
+
+        handle
+    }
+
+    /// Starts loading an asset in the background without returning a handle.
+    ///
+    /// Unlike [`load`](Self::load) the loader doesn't need a default asset, since nothing
+    /// can use the asset before it is loaded. Poll [`get_state`](Self::get_state) with the
+    /// returned id, then [`get`](Self::get) it.
+    pub fn preload<L: AssetLoader + 'static>(&self, loader: L, path: impl AsRef<Path>) -> AssetId {
+        let path = path.as_ref();
+        let path_str = path.to_string_lossy().to_string();
+        let id = AssetId::from_path(&path_str);
 
+        if let Some(arc) = self.cache.get::<L::Asset>(id) {
+            if !self.states.contains_key(&id) {
+                self.states.insert(id, Box::new(LoadState::Loaded(arc)));
+            }
+            return id;
+        }
+        if self.is_loading::<L::Asset>(id) {
+            return id;
+        }
+
+        self.states
+            .insert(id, Box::new(LoadState::<L::Asset>::Loading));
+        self.spawn_load(loader, path, id);
+        id
+    }
+
+    fn spawn_load<L: AssetLoader + 'static>(&self, loader: L, path: &Path, id: AssetId) {
+        let policy = loader.cache_policy();
         let states_clone = self.states.clone();
         let cache_clone = self.cache.clone();
+        let finished_clone = self.finished.clone();
         let path_buf = PathBuf::from(path);
-        let path_str_clone = path_str.clone();
+        let path_str_clone = path.to_string_lossy().to_string();
 
         self.runtime.spawn(async move {
-            let result = tokio::task::spawn_blocking(move || loader.load(&path_buf)).await;
+            let result = tokio::task::spawn_blocking(move || {
+                loader
+                    .load(&path_buf)
+                    .map_err(|e| (e, loader.default().is_some()))
+            })
+            .await;
 
-            let state = match result {
+            let (state, error, fallback) = match result {
                 Ok(Ok(asset)) => {
                     let handle = cache_clone.insert(&path_str_clone, asset, policy);
                     log::debug!("Async loaded asset: {}", path_str_clone);
-                    LoadState::Loaded(handle.asset)
+                    (LoadState::Loaded(handle.asset), None, false)
                 }
-                Ok(Err(e)) => {
+                // Handles from `load` keep the placeholder they were created with
+                Ok(Err((e, fallback))) => {
                     log::error!("Failed to load asset {}: {}", path_str_clone, e);
-                    LoadState::Failed(e.to_string())
+                    (
+                        LoadState::Failed(e.to_string()),
+                        Some(e.to_string()),
+                        fallback,
+                    )
                 }
                 Err(e) => {
                     log::error!("Task panicked while loading {}: {}", path_str_clone, e);
-                    LoadState::Failed(format!("Task panicked: {}", e))
+                    let error = format!("Task panicked: {}", e);
+                    (LoadState::Failed(error.clone()), Some(error), false)
                 }
             };
 
             states_clone.insert(id, Box::new(state));
+            finished_clone.lock().unwrap().push(FinishedLoad {
+                id,
+                path: path_str_clone,
+                error,
+                fallback,
+            });
         });
+    }
 
-        handle
+    /// Loads completed since the last call, drained by the assets plugin each frame.
+    pub fn drain_finished(&self) -> Vec<FinishedLoad> {
+        std::mem::take(&mut *self.finished.lock().unwrap())
     }
 
     pub fn get<T: Send + Sync + 'static>(&self, id: AssetId) -> Option<Arc<T>> {
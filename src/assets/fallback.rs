@@ -0,0 +1,106 @@
+//! Built-in placeholder assets.
+//!
+//! Loaders return these from [`AssetLoader::default`](crate::assets::AssetLoader::default),
+//! so [`Assets::load`](crate::assets::Assets::load) hands them out while the real asset
+//! loads and keeps them when loading fails. They are bright magenta on purpose: missing
+//! content should be obvious on screen, not silently invisible.
+
+use crate::assets::loader::{
+    mesh::MeshData,
+    shader::{ShaderData, ShaderType},
+    texture::{TextureData, TextureFormat},
+};
+use crate::core::math::*;
+
+pub const FALLBACK_COLOR: Vec3 = Vec3::new(1.0, 0.0, 1.0);
+
+/// WGSL of [`fallback_shader`]: `vs_main` takes a position at location 0 and a camera
+/// uniform at group 0, `fs_main` outputs magenta.
+pub const ERROR_SHADER_SOURCE: &str = include_str!("fallback/error.wgsl");
+
+const CHECKER_SIZE: u32 = 64;
+const CHECKER_CELL: u32 = 8;
+
+/// 64x64 magenta and black checkerboard.
+pub fn fallback_texture() -> TextureData {
+    let mut data = Vec::with_capacity((CHECKER_SIZE * CHECKER_SIZE * 4) as usize);
+    for y in 0..CHECKER_SIZE {
+        for x in 0..CHECKER_SIZE {
+            let magenta = (x / CHECKER_CELL + y / CHECKER_CELL).is_multiple_of(2);
+            data.extend_from_slice(if magenta {
+                &[255, 0, 255, 255]
+            } else {
+                &[0, 0, 0, 255]
+            });
+        }
+    }
+
+    TextureData {
+        width: CHECKER_SIZE,
+        height: CHECKER_SIZE,
+        data,
+        format: TextureFormat::Rgba8,
+        mip_level_count: 1,
+    }
+}
+
+/// Magenta unit cube centered on the origin, one mesh like a single-object model.
+pub fn fallback_mesh() -> Vec<MeshData> {
+    let faces = [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ];
+
+    let mut mesh = MeshData::new();
+    for normal in faces {
+        // Two axes spanning the face, ordered for counter-clockwise winding seen from outside
+        let tangent = if normal.y.abs() > 0.5 {
+            Vec3::X
+        } else {
+            Vec3::Y.cross(normal)
+        };
+        let bitangent = normal.cross(tangent);
+
+        let base = mesh.positions.len() as u32;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let position = (normal + tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0)) * 0.5;
+            mesh.positions.push(position);
+            mesh.normals.push(normal);
+            mesh.uvs.push(Vec2::new(u, v));
+            mesh.colors.push(FALLBACK_COLOR);
+            mesh.ao_values.push(1.0);
+        }
+        mesh.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    mesh.texture = Some(std::sync::Arc::new(fallback_texture()));
+
+    vec![mesh]
+}
+
+pub fn fallback_shader() -> ShaderData {
+    ShaderData::new(ERROR_SHADER_SOURCE.to_string(), ShaderType::Wgsl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_cube_faces_outward() {
+        let mesh = &fallback_mesh()[0];
+        assert_eq!(mesh.positions.len(), 24);
+        assert_eq!(mesh.triangle_count(), 12);
+
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+            let face_normal = (b - a).cross(c - a).normalize();
+            assert!((face_normal - mesh.normals[triangle[0] as usize]).length() < 1e-5);
+            assert!(a.abs().max_element() <= 0.5 + 1e-6);
+        }
+    }
+}
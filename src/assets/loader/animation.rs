@@ -0,0 +1,276 @@
+//! Skeletons and animation clips from glTF files.
+//!
+//! The engine doesn't play these back itself yet; the loaders give an animation system the
+//! data it needs. Skinned meshes come from [`GltfLoader`](super::mesh::GltfLoader) with their
+//! joint indices, weights and [`skin`](super::mesh::MeshData::skin) index set, indexing into
+//! the skins [`GltfSkinLoader`] returns for the same file.
+
+use crate::assets::loader::{AssetLoader, LoadError};
+use crate::core::math::*;
+use crate::transform::Transform;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct SkinJoint {
+    pub name: Option<String>,
+    /// Index of the joint's node in the file, which animation channels target.
+    pub node: usize,
+    /// Index of the parent in [`SkinData::joints`], `None` for roots.
+    pub parent: Option<usize>,
+    /// Takes mesh space to the joint's space in the bind pose.
+    pub inverse_bind: Mat4,
+    /// Local transform of the node without animation.
+    pub rest: Transform,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SkinData {
+    pub name: Option<String>,
+    /// In the order the mesh's joint indices refer to.
+    pub joints: Vec<SkinJoint>,
+}
+
+impl SkinData {
+    pub fn joint_for_node(&self, node: usize) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.node == node)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    /// Every keyframe stores an in-tangent, the value and an out-tangent, in that order.
+    CubicSpline,
+}
+
+#[derive(Clone, Debug)]
+pub enum ChannelValues {
+    Translations(Vec<Vec3>),
+    Rotations(Vec<Quat>),
+    Scales(Vec<Vec3>),
+    /// Morph target weights, one run of weights per keyframe.
+    Weights(Vec<f32>),
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationChannel {
+    /// Index of the animated node in the file.
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds.
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnimationClipData {
+    pub name: Option<String>,
+    /// Seconds until the last keyframe of any channel.
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+pub struct GltfSkinLoader;
+
+impl AssetLoader for GltfSkinLoader {
+    type Asset = Vec<SkinData>;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let (document, buffers, _) = gltf::import(path)
+            .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF: {}", e)))?;
+        Ok(read_skins(&document, &buffers))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gltf", "glb"]
+    }
+}
+
+pub struct GltfAnimationLoader;
+
+impl AssetLoader for GltfAnimationLoader {
+    type Asset = Vec<AnimationClipData>;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let (document, buffers, _) = gltf::import(path)
+            .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF: {}", e)))?;
+        read_animations(&document, &buffers)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gltf", "glb"]
+    }
+}
+
+pub fn load_skins_from_bytes(bytes: &[u8]) -> Result<Vec<SkinData>, LoadError> {
+    let (document, buffers, _) = gltf::import_slice(bytes)
+        .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF from bytes: {}", e)))?;
+    Ok(read_skins(&document, &buffers))
+}
+
+pub fn load_animations_from_bytes(bytes: &[u8]) -> Result<Vec<AnimationClipData>, LoadError> {
+    let (document, buffers, _) = gltf::import_slice(bytes)
+        .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF from bytes: {}", e)))?;
+    read_animations(&document, &buffers)
+}
+
+fn read_skins(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Vec<SkinData> {
+    let mut node_parents = HashMap::new();
+    for node in document.nodes() {
+        for child in node.children() {
+            node_parents.insert(child.index(), node.index());
+        }
+    }
+
+    document
+        .skins()
+        .map(|skin| {
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            // Identity when the file leaves them out
+            let inverse_binds: Vec<Mat4> = reader
+                .read_inverse_bind_matrices()
+                .map(|iter| iter.map(|m| Mat4::from_cols_array_2d(&m)).collect())
+                .unwrap_or_default();
+            let joint_nodes: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+
+            let joints = skin
+                .joints()
+                .enumerate()
+                .map(|(i, node)| {
+                    let (position, rotation, scale) = node.transform().decomposed();
+                    SkinJoint {
+                        name: node.name().map(String::from),
+                        node: node.index(),
+                        parent: node_parents
+                            .get(&node.index())
+                            .and_then(|parent| joint_nodes.iter().position(|n| n == parent)),
+                        inverse_bind: inverse_binds.get(i).copied().unwrap_or(Mat4::IDENTITY),
+                        rest: Transform::from_prs(
+                            Vec3::from_array(position),
+                            Quat::from_array(rotation),
+                            Vec3::from_array(scale),
+                        ),
+                    }
+                })
+                .collect();
+
+            SkinData {
+                name: skin.name().map(String::from),
+                joints,
+            }
+        })
+        .collect()
+}
+
+fn read_animations(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Vec<AnimationClipData>, LoadError> {
+    use gltf::animation::util::ReadOutputs;
+
+    let mut clips = Vec::new();
+    for animation in document.animations() {
+        let mut channels = Vec::new();
+        for channel in animation.channels() {
+            let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+            let times: Vec<f32> = reader
+                .read_inputs()
+                .ok_or_else(|| LoadError::LoadFailed("Missing keyframe times in GLTF".into()))?
+                .collect();
+            let values = match reader
+                .read_outputs()
+                .ok_or_else(|| LoadError::LoadFailed("Missing keyframe values in GLTF".into()))?
+            {
+                ReadOutputs::Translations(iter) => {
+                    ChannelValues::Translations(iter.map(Vec3::from_array).collect())
+                }
+                ReadOutputs::Rotations(iter) => {
+                    ChannelValues::Rotations(iter.into_f32().map(Quat::from_array).collect())
+                }
+                ReadOutputs::Scales(iter) => {
+                    ChannelValues::Scales(iter.map(Vec3::from_array).collect())
+                }
+                ReadOutputs::MorphTargetWeights(iter) => {
+                    ChannelValues::Weights(iter.into_f32().collect())
+                }
+            };
+
+            channels.push(AnimationChannel {
+                node: channel.target().node().index(),
+                interpolation: match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                },
+                times,
+                values,
+            });
+        }
+
+        clips.push(AnimationClipData {
+            name: animation.name().map(String::from),
+            duration: channels
+                .iter()
+                .filter_map(|channel| channel.times.last().copied())
+                .fold(0.0, f32::max),
+            channels,
+        });
+    }
+
+    Ok(clips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two joints, root and child, with a one second clip moving the child up
+    const SKINNED_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "nodes": [
+            { "name": "root", "children": [1] },
+            { "name": "child", "translation": [0, 1, 0] }
+        ],
+        "skins": [{ "joints": [0, 1] }],
+        "buffers": [{
+            "byteLength": 32,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAgD8AAAAAAAAAAAAAAAAAAAAAAAAAQAAAAAA="
+        }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 8, "byteLength": 24 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1] },
+            { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" }
+        ],
+        "animations": [{
+            "name": "rise",
+            "samplers": [{ "input": 0, "output": 1 }],
+            "channels": [{ "sampler": 0, "target": { "node": 1, "path": "translation" } }]
+        }]
+    }"#;
+
+    #[test]
+    fn test_reads_skin_and_clip() {
+        let skins = load_skins_from_bytes(SKINNED_GLTF.as_bytes()).unwrap();
+        let joints = &skins[0].joints;
+        assert_eq!(joints[0].parent, None);
+        assert_eq!(joints[1].parent, Some(0));
+        assert_eq!(joints[1].rest.position, Vec3::Y);
+        assert_eq!(joints[1].inverse_bind, Mat4::IDENTITY);
+
+        let clips = load_animations_from_bytes(SKINNED_GLTF.as_bytes()).unwrap();
+        assert_eq!(clips[0].name.as_deref(), Some("rise"));
+        assert_eq!(clips[0].duration, 1.0);
+        let channel = &clips[0].channels[0];
+        assert_eq!(channel.node, 1);
+        assert_eq!(channel.interpolation, Interpolation::Linear);
+        match &channel.values {
+            ChannelValues::Translations(values) => assert_eq!(values[1], Vec3::new(0.0, 2.0, 0.0)),
+            other => panic!("unexpected values {:?}", other),
+        }
+    }
+}
@@ -127,7 +127,7 @@ fn load_audio_from_reader(
         .count() as u16;
 
     let mut decoder = symphonia::default::get_codecs()
-        .make(&codec_params, &DecoderOptions::default())
+        .make(codec_params, &DecoderOptions::default())
         .map_err(|e| LoadError::LoadFailed(format!("Failed to create decoder: {}", e)))?;
 
     let mut samples = Vec::new();
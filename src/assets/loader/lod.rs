@@ -0,0 +1,173 @@
+//! Level-of-detail chains for meshes.
+//!
+//! [`generate_lods`] simplifies a [`MeshData`] into progressively coarser index buffers over
+//! its own vertices, and orders every index buffer for the GPU's vertex cache. Wrap a mesh
+//! loader in [`WithLods`] to do it at load time:
+//!
+//! ```ignore
+//! let trees = assets.load(WithLods::new(GltfLoader), "models/tree.glb")?;
+//! // Swapped in for trees far from the camera
+//! let far_tree = trees[0].lod_mesh(2);
+//! ```
+//!
+//! Assets are cached by path, so load a file either with or without LODs, not both.
+
+use crate::assets::cache::CachePolicy;
+use crate::assets::loader::mesh::MeshData;
+use crate::assets::loader::{AssetLoader, LoadError};
+use std::path::Path;
+
+/// A level must keep less than this fraction of the previous level's indices. Once the
+/// error budget stops simplification, coarser levels would only repeat the last one.
+const MIN_REDUCTION: f32 = 0.9;
+
+#[derive(Clone, Debug, Default)]
+pub struct MeshLod {
+    pub indices: Vec<u32>,
+    /// How far the simplified surface strays from the full mesh, relative to the mesh's size.
+    pub error: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSettings {
+    /// Levels generated after the full mesh. Fewer are kept when the mesh can't be
+    /// simplified further within `max_error`.
+    pub levels: usize,
+    /// Fraction of the full mesh's triangles each level keeps relative to the one before.
+    pub reduction: f32,
+    /// Largest deviation a level may introduce, relative to the mesh's size.
+    pub max_error: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            reduction: 0.5,
+            max_error: 0.05,
+        }
+    }
+}
+
+/// Replaces `mesh.lods` with up to `settings.levels` simplified index buffers, and reorders
+/// the mesh's own indices for the vertex cache.
+pub fn generate_lods(mesh: &mut MeshData, settings: &LodSettings) {
+    let vertex_count = mesh.positions.len();
+    mesh.lods.clear();
+    if vertex_count == 0 || mesh.indices.len() < 3 {
+        return;
+    }
+    mesh.indices = meshopt::optimize_vertex_cache(&mesh.indices, vertex_count);
+
+    let positions: Vec<[f32; 3]> = mesh.positions.iter().map(|p| p.to_array()).collect();
+    let vertices = match meshopt::VertexDataAdapter::new(
+        bytemuck::cast_slice(&positions),
+        std::mem::size_of::<[f32; 3]>(),
+        0,
+    ) {
+        Ok(vertices) => vertices,
+        Err(e) => {
+            log::warn!("Skipping LOD generation: {}", e);
+            return;
+        }
+    };
+
+    let mut previous = mesh.indices.len();
+    for level in 1..=settings.levels {
+        let target = mesh.indices.len() as f32 * settings.reduction.powi(level as i32);
+        let mut error = 0.0;
+        // Every level starts from the full mesh so errors don't pile up along the chain
+        let indices = meshopt::simplify(
+            &mesh.indices,
+            &vertices,
+            (target as usize / 3) * 3,
+            settings.max_error,
+            meshopt::SimplifyOptions::empty(),
+            Some(&mut error),
+        );
+        if indices.is_empty() || indices.len() as f32 >= previous as f32 * MIN_REDUCTION {
+            break;
+        }
+        previous = indices.len();
+        mesh.lods.push(MeshLod {
+            indices: meshopt::optimize_vertex_cache(&indices, vertex_count),
+            error,
+        });
+    }
+}
+
+/// Loads meshes with `loader` and generates their LODs with `settings`.
+pub struct WithLods<L> {
+    pub loader: L,
+    pub settings: LodSettings,
+}
+
+impl<L> WithLods<L> {
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            settings: LodSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: LodSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl<L: AssetLoader<Asset = Vec<MeshData>>> AssetLoader for WithLods<L> {
+    type Asset = Vec<MeshData>;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let mut meshes = self.loader.load(path)?;
+        for mesh in &mut meshes {
+            generate_lods(mesh, &self.settings);
+        }
+        Ok(meshes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.loader.extensions()
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        self.loader.cache_policy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::*;
+
+    #[test]
+    fn test_generate_lods_simplifies_flat_grid() {
+        // A flat 16x16 quad grid simplifies to far fewer triangles without any error
+        let size = 17;
+        let mut mesh = MeshData::new();
+        for z in 0..size {
+            for x in 0..size {
+                mesh.positions.push(Vec3::new(x as f32, 0.0, z as f32));
+            }
+        }
+        for z in 0..size - 1 {
+            for x in 0..size - 1 {
+                let i = z * size + x;
+                mesh.indices
+                    .extend([i, i + size, i + 1, i + 1, i + size, i + size + 1]);
+            }
+        }
+        let triangles = mesh.triangle_count();
+
+        generate_lods(&mut mesh, &LodSettings::default());
+        assert_eq!(mesh.triangle_count(), triangles);
+        assert!(!mesh.lods.is_empty());
+        assert!(mesh.lods[0].indices.len() / 3 <= triangles / 2);
+
+        let lod = mesh.lod_mesh(1).unwrap();
+        assert_eq!(lod.indices, mesh.lods[0].indices);
+        assert_eq!(lod.positions.len(), mesh.positions.len());
+        assert!(mesh.lod_mesh(mesh.lods.len() + 1).is_none());
+    }
+}
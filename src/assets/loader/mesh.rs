@@ -1,5 +1,7 @@
+use super::lod::MeshLod;
 use crate::assets::loader::{AssetLoader, LoadError};
 use crate::core::math::*;
+use std::collections::HashMap;
 use std::path::Path;
 
 #[derive(Clone, Debug)]
@@ -11,6 +13,29 @@ pub struct MeshData {
     pub ao_values: Vec<f32>,
     pub indices: Vec<u32>,
     pub texture: Option<std::sync::Arc<crate::assets::TextureData>>,
+    /// Up to four joints influencing each vertex, indexing the joints of [`skin`](Self::skin).
+    /// Empty for meshes without a skin.
+    pub joint_indices: Vec<[u16; 4]>,
+    pub joint_weights: Vec<Vec4>,
+    /// Index of the [`SkinData`](super::animation::SkinData) deforming this mesh, in the
+    /// order [`GltfSkinLoader`](super::animation::GltfSkinLoader) returns the file's skins.
+    pub skin: Option<usize>,
+    /// Blend shapes, each with one delta per vertex.
+    pub morph_targets: Vec<MorphTarget>,
+    /// Weight of each morph target when the entity has no
+    /// [`MorphWeights`](crate::renderer::MorphWeights).
+    pub morph_weights: Vec<f32>,
+    /// Simplified index buffers over the same vertices for LOD 1 onwards. Empty unless
+    /// generated with [`generate_lods`](super::lod::generate_lods).
+    pub lods: Vec<MeshLod>,
+}
+
+/// Offsets a [`MeshData`]'s vertices by `weight * delta` when its weight is set.
+#[derive(Clone, Debug, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<Vec3>,
+    /// Empty when the target leaves normals unchanged.
+    pub normals: Vec<Vec3>,
 }
 
 impl MeshData {
@@ -22,8 +47,31 @@ impl MeshData {
         let ao_size = self.ao_values.len() * std::mem::size_of::<f32>();
         let indices_size = self.indices.len() * std::mem::size_of::<u32>();
         let texture_size = self.texture.as_ref().map(|t| t.memory_size()).unwrap_or(0);
-
-        (positions_size + normals_size + uvs_size + colors_size + ao_size + indices_size) as u64 + texture_size
+        let skin_size = self.joint_indices.len() * std::mem::size_of::<[u16; 4]>()
+            + self.joint_weights.len() * std::mem::size_of::<Vec4>();
+        let lod_size: usize = self
+            .lods
+            .iter()
+            .map(|lod| lod.indices.len() * std::mem::size_of::<u32>())
+            .sum();
+        let morph_size: usize = self
+            .morph_targets
+            .iter()
+            .map(|target| {
+                (target.positions.len() + target.normals.len()) * std::mem::size_of::<Vec3>()
+            })
+            .sum();
+
+        (positions_size
+            + normals_size
+            + uvs_size
+            + colors_size
+            + ao_size
+            + indices_size
+            + skin_size
+            + morph_size
+            + lod_size) as u64
+            + texture_size
     }
 
     pub fn new() -> Self {
@@ -35,6 +83,12 @@ impl MeshData {
             ao_values: Vec::new(),
             indices: Vec::new(),
             texture: None,
+            joint_indices: Vec::new(),
+            joint_weights: Vec::new(),
+            skin: None,
+            morph_targets: Vec::new(),
+            morph_weights: Vec::new(),
+            lods: Vec::new(),
         }
     }
 
@@ -46,6 +100,20 @@ impl MeshData {
         self.indices.len() / 3
     }
 
+    /// Copy of the mesh drawn with the indices of LOD `level`, for swapping in by distance.
+    /// Level 0 is the mesh itself. `None` past the last generated level.
+    pub fn lod_mesh(&self, level: usize) -> Option<MeshData> {
+        let indices = match level {
+            0 => self.indices.clone(),
+            _ => self.lods.get(level - 1)?.indices.clone(),
+        };
+        Some(MeshData {
+            indices,
+            lods: Vec::new(),
+            ..self.clone()
+        })
+    }
+
     /// Compute axis-aligned bounding box from vertex positions
     pub fn compute_bounds(&self) -> Option<(Vec3, Vec3)> {
         if self.positions.is_empty() {
@@ -191,6 +259,12 @@ impl AssetLoader for ObjLoader {
                 colors,
                 indices: mesh.indices.clone(),
                 texture,
+                joint_indices: Vec::new(),
+                joint_weights: Vec::new(),
+                skin: None,
+                morph_targets: Vec::new(),
+                morph_weights: Vec::new(),
+                lods: Vec::new(),
             });
         }
 
@@ -204,6 +278,10 @@ impl AssetLoader for ObjLoader {
     fn extensions(&self) -> &[&str] {
         &["obj"]
     }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(crate::assets::fallback::fallback_mesh())
+    }
 }
 
 pub struct GltfLoader;
@@ -215,6 +293,7 @@ impl AssetLoader for GltfLoader {
         let (document, buffers, images) = gltf::import(path)
             .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF: {}", e)))?;
 
+        let skins = mesh_skins(&document);
         let mut meshes = Vec::new();
 
         for mesh in document.meshes() {
@@ -224,17 +303,17 @@ impl AssetLoader for GltfLoader {
                 let positions: Vec<Vec3> = reader
                     .read_positions()
                     .ok_or_else(|| LoadError::LoadFailed("Missing positions in GLTF mesh".into()))?
-                    .map(|p| Vec3::from_array(p))
+                    .map(Vec3::from_array)
                     .collect();
 
                 let normals: Vec<Vec3> = reader
                     .read_normals()
-                    .map(|iter| iter.map(|n| Vec3::from_array(n)).collect())
+                    .map(|iter| iter.map(Vec3::from_array).collect())
                     .unwrap_or_else(|| vec![Vec3::Y; positions.len()]);
 
                 let uvs: Vec<Vec2> = reader
                     .read_tex_coords(0)
-                    .map(|iter| iter.into_f32().map(|uv| Vec2::from_array(uv)).collect())
+                    .map(|iter| iter.into_f32().map(Vec2::from_array).collect())
                     .unwrap_or_else(|| vec![Vec2::ZERO; positions.len()]);
 
                 let indices: Vec<u32> = reader
@@ -246,6 +325,16 @@ impl AssetLoader for GltfLoader {
                 let colors = vec![Vec3::ONE; positions.len()];
                 let ao_values = vec![1.0; positions.len()];
 
+                let joint_indices: Vec<[u16; 4]> = reader
+                    .read_joints(0)
+                    .map(|iter| iter.into_u16().collect())
+                    .unwrap_or_default();
+                let joint_weights: Vec<Vec4> = reader
+                    .read_weights(0)
+                    .map(|iter| iter.into_f32().map(Vec4::from_array).collect())
+                    .unwrap_or_default();
+                let morph_targets = read_morph_targets(&reader, positions.len());
+
                 let texture = primitive
                     .material()
                     .pbr_metallic_roughness()
@@ -275,6 +364,7 @@ impl AssetLoader for GltfLoader {
                                 height,
                                 data,
                                 format,
+                                mip_level_count: 1,
                             })
                         })
                     });
@@ -287,6 +377,12 @@ impl AssetLoader for GltfLoader {
                     colors,
                     indices,
                     texture,
+                    joint_indices,
+                    joint_weights,
+                    skin: skins.get(&mesh.index()).copied(),
+                    morph_weights: morph_weights(&mesh, morph_targets.len()),
+                    lods: Vec::new(),
+                    morph_targets,
                 });
             }
         }
@@ -301,6 +397,10 @@ impl AssetLoader for GltfLoader {
     fn extensions(&self) -> &[&str] {
         &["gltf", "glb"]
     }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(crate::assets::fallback::fallback_mesh())
+    }
 }
 
 pub fn load_mesh_from_bytes(bytes: &[u8], format: MeshFormat) -> Result<Vec<MeshData>, LoadError> {
@@ -383,6 +483,12 @@ fn load_obj_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
             colors,
             indices: mesh.indices.clone(),
             texture: None,
+            joint_indices: Vec::new(),
+            joint_weights: Vec::new(),
+            skin: None,
+            morph_targets: Vec::new(),
+            morph_weights: Vec::new(),
+            lods: Vec::new(),
         });
     }
 
@@ -397,6 +503,7 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
     let (document, buffers, images) = gltf::import_slice(bytes)
         .map_err(|e| LoadError::LoadFailed(format!("Failed to load GLTF from bytes: {}", e)))?;
 
+    let skins = mesh_skins(&document);
     let mut meshes = Vec::new();
 
     for mesh in document.meshes() {
@@ -406,17 +513,17 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
             let positions: Vec<Vec3> = reader
                 .read_positions()
                 .ok_or_else(|| LoadError::LoadFailed("Missing positions in GLTF mesh".into()))?
-                .map(|p| Vec3::from_array(p))
+                .map(Vec3::from_array)
                 .collect();
 
             let normals: Vec<Vec3> = reader
                 .read_normals()
-                .map(|iter| iter.map(|n| Vec3::from_array(n)).collect())
+                .map(|iter| iter.map(Vec3::from_array).collect())
                 .unwrap_or_else(|| vec![Vec3::Y; positions.len()]);
 
             let uvs: Vec<Vec2> = reader
                 .read_tex_coords(0)
-                .map(|iter| iter.into_f32().map(|uv| Vec2::from_array(uv)).collect())
+                .map(|iter| iter.into_f32().map(Vec2::from_array).collect())
                 .unwrap_or_else(|| vec![Vec2::ZERO; positions.len()]);
 
             let indices: Vec<u32> = reader
@@ -428,6 +535,16 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
             let colors = vec![Vec3::ONE; positions.len()];
             let ao_values = vec![1.0; positions.len()];
 
+            let joint_indices: Vec<[u16; 4]> = reader
+                .read_joints(0)
+                .map(|iter| iter.into_u16().collect())
+                .unwrap_or_default();
+            let joint_weights: Vec<Vec4> = reader
+                .read_weights(0)
+                .map(|iter| iter.into_f32().map(Vec4::from_array).collect())
+                .unwrap_or_default();
+            let morph_targets = read_morph_targets(&reader, positions.len());
+
             let texture = primitive
                 .material()
                 .pbr_metallic_roughness()
@@ -449,6 +566,7 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
                             height,
                             data,
                             format,
+                            mip_level_count: 1,
                         })
                     })
                 });
@@ -461,6 +579,12 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
                 colors,
                 indices,
                 texture,
+                joint_indices,
+                joint_weights,
+                skin: skins.get(&mesh.index()).copied(),
+                morph_weights: morph_weights(&mesh, morph_targets.len()),
+                lods: Vec::new(),
+                morph_targets,
             });
         }
     }
@@ -472,6 +596,45 @@ fn load_gltf_from_bytes(bytes: &[u8]) -> Result<Vec<MeshData>, LoadError> {
     Ok(meshes)
 }
 
+/// Skin of each skinned mesh, from the nodes instancing them. glTF puts the skin on the
+/// node, so a mesh instanced with different skins keeps the first.
+fn mesh_skins(document: &gltf::Document) -> HashMap<usize, usize> {
+    let mut skins = HashMap::new();
+    for node in document.nodes() {
+        if let (Some(mesh), Some(skin)) = (node.mesh(), node.skin()) {
+            skins.entry(mesh.index()).or_insert(skin.index());
+        }
+    }
+    skins
+}
+
+fn read_morph_targets<'a, 's, F>(
+    reader: &gltf::mesh::Reader<'a, 's, F>,
+    vertex_count: usize,
+) -> Vec<MorphTarget>
+where
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    reader
+        .read_morph_targets()
+        .map(|(positions, normals, _)| MorphTarget {
+            positions: positions
+                .map(|iter| iter.map(Vec3::from_array).collect())
+                .unwrap_or_else(|| vec![Vec3::ZERO; vertex_count]),
+            normals: normals
+                .map(|iter| iter.map(Vec3::from_array).collect())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// The mesh's default weights, or zero for every target if it has none.
+fn morph_weights(mesh: &gltf::Mesh, target_count: usize) -> Vec<f32> {
+    let mut weights = mesh.weights().map(<[f32]>::to_vec).unwrap_or_default();
+    weights.resize(target_count, 0.0);
+    weights
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum MeshFormat {
     Obj,
@@ -1,5 +1,7 @@
+pub mod animation;
 pub mod audio;
 pub mod font;
+pub mod lod;
 pub mod mesh;
 pub mod shader;
 pub mod texture;
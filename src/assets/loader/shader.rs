@@ -49,6 +49,10 @@ impl AssetLoader for WgslLoader {
     fn cache_policy(&self) -> CachePolicy {
         CachePolicy::Strong
     }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(crate::assets::fallback::fallback_shader())
+    }
 }
 
 pub fn load_shader_from_bytes(
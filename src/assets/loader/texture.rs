@@ -7,8 +7,10 @@ use std::path::Path;
 pub struct TextureData {
     pub width: u32,
     pub height: u32,
+    /// Every mip level, largest first, tightly packed.
     pub data: Vec<u8>,
     pub format: TextureFormat,
+    pub mip_level_count: u32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,14 +18,55 @@ pub enum TextureFormat {
     Rgba8,
     Rgb8,
     R8,
+    /// Block-compressed formats, loaded as-is from KTX2 and DDS files.
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    Astc4x4,
 }
 
 impl TextureFormat {
+    /// Channels per pixel of uncompressed formats. Zero for compressed formats.
     pub fn channels(&self) -> u32 {
         match self {
             TextureFormat::Rgba8 => 4,
             TextureFormat::Rgb8 => 3,
             TextureFormat::R8 => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.block_bytes().is_some()
+    }
+
+    /// Bytes per 4x4 block of compressed formats.
+    pub fn block_bytes(&self) -> Option<u32> {
+        match self {
+            TextureFormat::Bc1 | TextureFormat::Bc4 => Some(8),
+            TextureFormat::Bc2
+            | TextureFormat::Bc3
+            | TextureFormat::Bc5
+            | TextureFormat::Bc7
+            | TextureFormat::Astc4x4 => Some(16),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of one `width` x `height` image in this format.
+    pub fn level_size(&self, width: u32, height: u32) -> usize {
+        let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+        match self.block_bytes() {
+            Some(block) => width
+                .div_ceil(4)
+                .saturating_mul(height.div_ceil(4))
+                .saturating_mul(block as usize),
+            None => width
+                .saturating_mul(height)
+                .saturating_mul(self.channels() as usize),
         }
     }
 }
@@ -44,6 +87,7 @@ impl TextureData {
             height,
             data,
             format: TextureFormat::Rgba8,
+            mip_level_count: 1,
         }
     }
 
@@ -53,6 +97,7 @@ impl TextureData {
             height: 1,
             data: vec![r, g, b, a],
             format: TextureFormat::Rgba8,
+            mip_level_count: 1,
         }
     }
 
@@ -64,7 +109,12 @@ impl TextureData {
         Self::solid_color(0, 0, 0, 255)
     }
 
+    /// Samples the top mip level. Returns `None` for compressed formats.
     pub fn sample(&self, uv: Vec2) -> Option<Vec3> {
+        if self.format.is_compressed() {
+            return None;
+        }
+
         let u = uv.x.fract();
         let v = 1.0 - uv.y.fract();
 
@@ -107,12 +157,35 @@ impl AssetLoader for TextureLoader {
     type Asset = TextureData;
 
     fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
-        let image = image::open(path).map_err(|e| LoadError::LoadFailed(e.to_string()))?;
-        Ok(TextureData::from_image(image))
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ktx2") | Some("dds") => {
+                let bytes =
+                    std::fs::read(path).map_err(|e| LoadError::LoadFailed(e.to_string()))?;
+                if extension.as_deref() == Some("ktx2") {
+                    load_ktx2(&bytes)
+                } else {
+                    load_dds(&bytes)
+                }
+            }
+            _ => {
+                let image = image::open(path).map_err(|e| LoadError::LoadFailed(e.to_string()))?;
+                Ok(TextureData::from_image(image))
+            }
+        }
     }
 
     fn extensions(&self) -> &[&str] {
-        &["png", "jpg", "jpeg", "bmp", "gif", "tga", "webp"]
+        &[
+            "png", "jpg", "jpeg", "bmp", "gif", "tga", "webp", "ktx2", "dds",
+        ]
+    }
+
+    fn default(&self) -> Option<Self::Asset> {
+        Some(crate::assets::fallback::fallback_texture())
     }
 }
 
@@ -121,3 +194,194 @@ pub fn load_texture_from_bytes(bytes: &[u8]) -> Result<TextureData, LoadError> {
         .map_err(|e| LoadError::LoadFailed(format!("Failed to decode image: {}", e)))?;
     Ok(TextureData::from_image(image))
 }
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Loads a KTX2 texture holding BC, ASTC or RGBA8 data without supercompression.
+///
+/// Basis Universal textures are not transcoded at load time and are rejected.
+pub fn load_ktx2(bytes: &[u8]) -> Result<TextureData, LoadError> {
+    if bytes.get(..12) != Some(&KTX2_IDENTIFIER[..]) {
+        return Err(LoadError::LoadFailed("Not a KTX2 file".to_string()));
+    }
+
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)?;
+    let level_count = clamp_level_count(read_u32(bytes, 40)?, width, height);
+    let supercompression = read_u32(bytes, 44)?;
+
+    if vk_format == 0 {
+        return Err(LoadError::UnsupportedType(
+            "KTX2 Basis Universal textures must be transcoded offline to BC or ASTC".to_string(),
+        ));
+    }
+    if supercompression != 0 {
+        return Err(LoadError::UnsupportedType(format!(
+            "KTX2 supercompression scheme {}",
+            supercompression
+        )));
+    }
+    if depth > 1 || layers > 1 || faces > 1 {
+        return Err(LoadError::UnsupportedType(
+            "KTX2 3D, array and cube textures".to_string(),
+        ));
+    }
+
+    let format = match vk_format {
+        37 | 43 => TextureFormat::Rgba8,
+        133 | 134 => TextureFormat::Bc1,
+        135 | 136 => TextureFormat::Bc2,
+        137 | 138 => TextureFormat::Bc3,
+        139 => TextureFormat::Bc4,
+        141 => TextureFormat::Bc5,
+        145 | 146 => TextureFormat::Bc7,
+        157 | 158 => TextureFormat::Astc4x4,
+        other => {
+            return Err(LoadError::UnsupportedType(format!(
+                "KTX2 VkFormat {}",
+                other
+            )));
+        }
+    };
+
+    // The level index follows the 80-byte header, largest level first
+    let mut data = Vec::new();
+    for level in 0..level_count {
+        let entry = 80 + level as usize * 24;
+        let offset = read_u64(bytes, entry)? as usize;
+        let expected = format.level_size(width >> level, height >> level);
+        let level_data = offset
+            .checked_add(expected)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or_else(|| LoadError::LoadFailed(format!("KTX2 level {} is truncated", level)))?;
+        data.extend_from_slice(level_data);
+    }
+
+    Ok(TextureData {
+        width,
+        height,
+        data,
+        format,
+        mip_level_count: level_count,
+    })
+}
+
+/// Loads a DDS texture holding BC data, with either a legacy FourCC or a DX10 header.
+pub fn load_dds(bytes: &[u8]) -> Result<TextureData, LoadError> {
+    if bytes.get(..4) != Some(b"DDS ") {
+        return Err(LoadError::LoadFailed("Not a DDS file".to_string()));
+    }
+
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let level_count = clamp_level_count(read_u32(bytes, 28)?, width, height);
+    let four_cc = bytes
+        .get(84..88)
+        .ok_or_else(|| LoadError::LoadFailed("DDS header is truncated".to_string()))?;
+
+    let (format, data_offset): (_, usize) = if four_cc == b"DX10" {
+        let format = match read_u32(bytes, 128)? {
+            71 | 72 => TextureFormat::Bc1,
+            74 | 75 => TextureFormat::Bc2,
+            77 | 78 => TextureFormat::Bc3,
+            80 => TextureFormat::Bc4,
+            83 => TextureFormat::Bc5,
+            98 | 99 => TextureFormat::Bc7,
+            other => {
+                return Err(LoadError::UnsupportedType(format!(
+                    "DDS DXGI format {}",
+                    other
+                )));
+            }
+        };
+        (format, 148)
+    } else {
+        let format = match four_cc {
+            b"DXT1" => TextureFormat::Bc1,
+            b"DXT3" => TextureFormat::Bc2,
+            b"DXT5" => TextureFormat::Bc3,
+            b"ATI1" | b"BC4U" => TextureFormat::Bc4,
+            b"ATI2" | b"BC5U" => TextureFormat::Bc5,
+            _ => {
+                return Err(LoadError::UnsupportedType(format!(
+                    "DDS FourCC {:?}",
+                    String::from_utf8_lossy(four_cc)
+                )));
+            }
+        };
+        (format, 128)
+    };
+
+    let data = (0..level_count)
+        .try_fold(0usize, |size, level| {
+            size.checked_add(format.level_size(width >> level, height >> level))
+        })
+        .and_then(|size| data_offset.checked_add(size))
+        .and_then(|end| bytes.get(data_offset..end))
+        .ok_or_else(|| LoadError::LoadFailed("DDS data is truncated".to_string()))?
+        .to_vec();
+
+    Ok(TextureData {
+        width,
+        height,
+        data,
+        format,
+        mip_level_count: level_count,
+    })
+}
+
+/// At least one level, and no more than a full mip chain down to 1x1
+fn clamp_level_count(level_count: u32, width: u32, height: u32) -> u32 {
+    level_count.clamp(1, 32 - width.max(height).max(1).leading_zeros())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, LoadError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| LoadError::LoadFailed("Texture header is truncated".to_string()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, LoadError> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_dds_mip_chain() {
+        // 8x8 BC1 with two levels: 4 blocks, then 1 block
+        let mut bytes = vec![0u8; 128];
+        bytes[..4].copy_from_slice(b"DDS ");
+        bytes[12..16].copy_from_slice(&8u32.to_le_bytes());
+        bytes[16..20].copy_from_slice(&8u32.to_le_bytes());
+        bytes[28..32].copy_from_slice(&2u32.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DXT1");
+        bytes.extend(std::iter::repeat_n(7u8, 4 * 8 + 8));
+
+        let texture = load_dds(&bytes).unwrap();
+        assert_eq!(texture.format, TextureFormat::Bc1);
+        assert_eq!((texture.width, texture.height), (8, 8));
+        assert_eq!(texture.mip_level_count, 2);
+        assert_eq!(texture.data.len(), 40);
+        assert!(texture.sample(Vec2::ZERO).is_none());
+
+        bytes.truncate(150);
+        assert!(load_dds(&bytes).is_err());
+
+        // Level counts past the full chain are clamped instead of overflowing
+        bytes[28..32].copy_from_slice(&40u32.to_le_bytes());
+        bytes.extend(std::iter::repeat_n(7u8, 40));
+        let texture = load_dds(&bytes).unwrap();
+        assert_eq!(texture.mip_level_count, 4);
+        assert_eq!(texture.data.len(), 56);
+    }
+}
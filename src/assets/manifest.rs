@@ -0,0 +1,118 @@
+use super::pak::{PakArchive, PakError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Per-file record stored in a [`PakManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    /// Size of the data as stored in the pak (after compression).
+    pub stored_size: u64,
+    /// CRC32 of the uncompressed data.
+    pub checksum: u32,
+    pub compressed: bool,
+}
+
+/// Describes the contents of a built pak file.
+///
+/// Written next to the pak by the asset packer so that patch tooling can
+/// compare two builds without opening the archives.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PakManifest {
+    /// Content version of the pak, chosen by the game when building.
+    pub version: u32,
+    /// File name of the pak this manifest describes.
+    pub pak_file: String,
+    /// Size of the whole pak file in bytes.
+    pub pak_size: u64,
+    /// CRC32 of the whole pak file.
+    pub pak_checksum: u32,
+    /// Entries sorted by path.
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl PakManifest {
+    /// Builds a manifest from the raw bytes of a pak file.
+    pub fn from_pak_bytes(
+        version: u32,
+        pak_file: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<Self, PakError> {
+        let pak_size = data.len() as u64;
+        let pak_checksum = crc32fast::hash(&data);
+        let archive = PakArchive::from_bytes(data)?;
+
+        let mut entries: Vec<ManifestEntry> = archive
+            .entries()
+            .map(|entry| ManifestEntry {
+                path: entry.path.clone(),
+                size: entry.original_size,
+                stored_size: entry.size,
+                checksum: entry.checksum,
+                compressed: entry.compressed,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Self {
+            version,
+            pak_file: pak_file.into(),
+            pak_size,
+            pak_checksum,
+            entries,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PakError> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        ron::from_str(&contents).map_err(|e| PakError::Manifest(e.to_string()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PakError> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| PakError::Manifest(e.to_string()))?;
+        std::fs::write(path.as_ref(), contents)?;
+        Ok(())
+    }
+
+    pub fn get(&self, path: &str) -> Option<&ManifestEntry> {
+        self.entries
+            .binary_search_by(|entry| entry.path.as_str().cmp(path))
+            .ok()
+            .map(|index| &self.entries[index])
+    }
+
+    /// Total uncompressed size of all entries.
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::PakBuilder;
+
+    #[test]
+    fn test_manifest_from_pak_bytes() {
+        let mut builder = PakBuilder::new();
+        builder.add_bytes("b.txt".to_string(), b"second".to_vec());
+        builder.add_bytes("a.txt".to_string(), b"first".to_vec());
+        let data = builder.build_to_bytes().unwrap();
+
+        let manifest = PakManifest::from_pak_bytes(3, "test.pak", data.clone()).unwrap();
+
+        assert_eq!(manifest.version, 3);
+        assert_eq!(manifest.pak_size, data.len() as u64);
+        assert_eq!(manifest.entries[0].path, "a.txt");
+        assert_eq!(manifest.entries[1].path, "b.txt");
+        assert_eq!(
+            manifest.get("b.txt").unwrap().checksum,
+            crc32fast::hash(b"second")
+        );
+        assert_eq!(manifest.total_size(), 11);
+        assert!(manifest.get("missing.txt").is_none());
+    }
+}
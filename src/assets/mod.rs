@@ -8,7 +8,7 @@
 //!
 //! Load assets asynchronously with a default/placeholder asset shown immediately:
 //!
-//! ```rust
+//! ```ignore
 //! use resonance::prelude::*;
 //! use resonance::assets::TextureLoader;
 //!
@@ -26,7 +26,7 @@
 //!
 //! Poll asset loading state to determine when assets are ready:
 //!
-//! ```rust
+//! ```ignore
 //! use resonance::prelude::*;
 //! use resonance::assets::TextureData;
 //!
@@ -43,7 +43,7 @@
 //!
 //! Load multiple assets and track overall progress:
 //!
-//! ```rust
+//! ```ignore
 //! use resonance::prelude::*;
 //! use resonance::assets::MeshLoader;
 //!
@@ -67,33 +67,63 @@
 //! }
 //! ```
 //!
+//! ## Pattern 4: Preloading at Startup
+//!
+//! List critical assets in a [`PreloadManifest`] and add a [`PreloadPlugin`]; groups load
+//! in dependency order and [`wait_for_group`] gates systems on them. See [`preload`].
+//!
+//! ## Placeholders
+//!
+//! Texture, mesh and shader loaders provide magenta [`fallback`] assets. Handles returned by
+//! `load` hold the placeholder until the real asset arrives (meshes are swapped by the
+//! renderer) and keep it if loading fails, in which case an
+//! [`AssetFallbackUsed`](crate::core::AssetFallbackUsed) warning is sent.
+//!
 //! # Available Loaders
 //!
-//! - `TextureLoader` - PNG, JPEG images
-//! - `MeshLoader` (ObjLoader, GltfLoader) - 3D models
+//! - `TextureLoader` - PNG, JPEG images, and BC/ASTC textures from KTX2 and DDS files
+//! - `MeshLoader` (ObjLoader, GltfLoader) - 3D models, optionally wrapped in `WithLods` to
+//!   generate simplified LOD levels
+//! - `GltfSkinLoader`, `GltfAnimationLoader` - skeletons and animation clips of glTF models
 //! - `AudioLoader` - Audio files (via symphonia)
 //! - `TtfLoader` - TrueType fonts
 //! - `WgslLoader` - WGSL shaders
 
+#[allow(clippy::module_inception)]
 pub mod assets;
 pub mod cache;
+pub mod fallback;
 pub mod handle;
 pub mod loader;
+pub mod manifest;
 pub mod pak;
+pub mod patch;
 pub mod plugin;
+pub mod preload;
 pub mod source;
 
-pub use assets::{Assets, LoadState};
+pub use assets::{Assets, FinishedLoad, LoadState};
 pub use cache::{AssetCache, CachePolicy};
 pub use handle::{AssetHandle, AssetId};
 pub use loader::{
     AssetLoader, LoadError,
+    animation::{
+        AnimationChannel, AnimationClipData, ChannelValues, GltfAnimationLoader, GltfSkinLoader,
+        Interpolation, SkinData, SkinJoint,
+    },
     audio::{AudioData, AudioLoader},
     font::{FontData, TtfLoader},
-    mesh::{GltfLoader, MeshData, ObjLoader},
+    lod::{LodSettings, MeshLod, WithLods, generate_lods},
+    mesh::{GltfLoader, MeshData, MorphTarget, ObjLoader},
     shader::{ShaderData, ShaderType, WgslLoader},
     texture::{TextureData, TextureFormat, TextureLoader},
 };
+pub use manifest::{ManifestEntry, PakManifest};
 pub use pak::{PakArchive, PakBuilder, PakEntry, PakError};
+pub use patch::{ManifestDiff, PatchInfo, apply_patch, create_patch, rollback_patch};
 pub use plugin::AssetsPlugin;
+pub use preload::{
+    GroupProgress, PreloadGroup, PreloadGroupLoaded, PreloadManifest, PreloadManifestLoader,
+    PreloadPlugin, Preloader, wait_for_group,
+};
 pub use source::{AssetSource, AssetSourceConfig};
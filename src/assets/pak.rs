@@ -21,6 +21,14 @@ pub enum PakError {
     CompressionFailed(String),
     #[error("Failed to decompress data: {0}")]
     DecompressionFailed(String),
+    #[error("Invalid PAK manifest: {0}")]
+    Manifest(String),
+    #[error("Patch does not apply to this PAK: {0}")]
+    PatchMismatch(String),
+    #[error("Integrity check failed: {0}")]
+    IntegrityCheckFailed(String),
+    #[error("Failed to import {path}: {reason}")]
+    Import { path: String, reason: String },
 }
 
 #[derive(Debug, Clone)]
@@ -189,6 +197,10 @@ impl PakArchive {
     pub fn entry_count(&self) -> usize {
         self.entries.len()
     }
+
+    pub fn entries(&self) -> impl Iterator<Item = &PakEntry> {
+        self.entries.values()
+    }
 }
 
 pub struct PakBuilder {
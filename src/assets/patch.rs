@@ -0,0 +1,351 @@
+//! Delta updates for pak files.
+//!
+//! A patch is itself a pak archive holding only the files that were added or
+//! changed between two builds, plus a `__patch__.ron` entry describing the
+//! base it applies to and the manifest it must produce. Applying a patch
+//! rebuilds the pak, verifies every entry against the target manifest and
+//! keeps a backup of the previous pak so the update can be rolled back.
+
+use super::manifest::{ManifestEntry, PakManifest};
+use super::pak::{PakArchive, PakBuilder, PakError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const PATCH_INFO_PATH: &str = "__patch__.ron";
+
+/// Difference between two pak manifests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    pub added: Vec<ManifestEntry>,
+    pub changed: Vec<ManifestEntry>,
+    pub removed: Vec<String>,
+}
+
+impl ManifestDiff {
+    pub fn between(old: &PakManifest, new: &PakManifest) -> Self {
+        let mut diff = Self::default();
+
+        for entry in &new.entries {
+            match old.get(&entry.path) {
+                None => diff.added.push(entry.clone()),
+                Some(old_entry)
+                    if old_entry.checksum != entry.checksum || old_entry.size != entry.size =>
+                {
+                    diff.changed.push(entry.clone())
+                }
+                Some(_) => {}
+            }
+        }
+
+        for entry in &old.entries {
+            if new.get(&entry.path).is_none() {
+                diff.removed.push(entry.path.clone());
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Uncompressed size of all files that have to be shipped in the patch.
+    pub fn payload_size(&self) -> u64 {
+        self.added
+            .iter()
+            .chain(self.changed.iter())
+            .map(|entry| entry.size)
+            .sum()
+    }
+}
+
+/// Metadata stored inside a patch archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchInfo {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// CRC32 of the pak file this patch applies to.
+    pub base_checksum: u32,
+    pub removed: Vec<String>,
+    /// Manifest the patched pak must match.
+    pub target: PakManifest,
+}
+
+/// Builds a patch archive that turns the pak described by `old` into `new_pak`.
+pub fn create_patch(
+    old: &PakManifest,
+    new: &PakManifest,
+    new_pak: &PakArchive,
+) -> Result<Vec<u8>, PakError> {
+    let diff = ManifestDiff::between(old, new);
+
+    let info = PatchInfo {
+        from_version: old.version,
+        to_version: new.version,
+        base_checksum: old.pak_checksum,
+        removed: diff.removed.clone(),
+        target: new.clone(),
+    };
+
+    let info_ron = ron::ser::to_string_pretty(&info, ron::ser::PrettyConfig::default())
+        .map_err(|e| PakError::Manifest(e.to_string()))?;
+
+    let mut builder = PakBuilder::new().with_compression(true);
+    builder.add_bytes(PATCH_INFO_PATH.to_string(), info_ron.into_bytes());
+
+    for entry in diff.added.iter().chain(diff.changed.iter()) {
+        builder.add_bytes(entry.path.clone(), new_pak.get(&entry.path)?);
+    }
+
+    builder.build_to_bytes()
+}
+
+/// Reads the patch metadata without applying it.
+pub fn read_patch_info(patch: &PakArchive) -> Result<PatchInfo, PakError> {
+    let info_bytes = patch.get(PATCH_INFO_PATH)?;
+    let info_str = String::from_utf8(info_bytes).map_err(|e| PakError::Manifest(e.to_string()))?;
+    ron::from_str(&info_str).map_err(|e| PakError::Manifest(e.to_string()))
+}
+
+/// Applies a patch to an in-memory pak and returns the new pak bytes and manifest.
+///
+/// Fails without producing output if the base does not match the patch or if any
+/// resulting entry does not match the target manifest.
+pub fn apply_patch_to_bytes(
+    base: Vec<u8>,
+    patch: Vec<u8>,
+) -> Result<(Vec<u8>, PakManifest), PakError> {
+    let patch = PakArchive::from_bytes(patch)?;
+    let info = read_patch_info(&patch)?;
+
+    let base_checksum = crc32fast::hash(&base);
+    if base_checksum != info.base_checksum {
+        return Err(PakError::PatchMismatch(format!(
+            "base pak checksum {:08x} does not match patch base {:08x}",
+            base_checksum, info.base_checksum
+        )));
+    }
+
+    let base = PakArchive::from_bytes(base)?;
+    let removed: HashSet<&str> = info.removed.iter().map(String::as_str).collect();
+    let compress = info.target.entries.iter().any(|entry| entry.compressed);
+
+    let mut builder = PakBuilder::new().with_compression(compress);
+    for entry in &info.target.entries {
+        let data = if patch.exists(&entry.path) {
+            patch.get(&entry.path)?
+        } else if !removed.contains(entry.path.as_str()) && base.exists(&entry.path) {
+            base.get(&entry.path)?
+        } else {
+            return Err(PakError::IntegrityCheckFailed(format!(
+                "{} is missing from both the base pak and the patch",
+                entry.path
+            )));
+        };
+
+        if data.len() as u64 != entry.size || crc32fast::hash(&data) != entry.checksum {
+            return Err(PakError::IntegrityCheckFailed(format!(
+                "{} does not match the target manifest",
+                entry.path
+            )));
+        }
+
+        builder.add_bytes(entry.path.clone(), data);
+    }
+
+    let data = builder.build_to_bytes()?;
+    let manifest = PakManifest::from_pak_bytes(
+        info.target.version,
+        info.target.pak_file.clone(),
+        data.clone(),
+    )?;
+
+    Ok((data, manifest))
+}
+
+/// Applies a patch file to the pak at `pak_path` in place.
+///
+/// The previous pak is kept as `<pak>.bak` until [`rollback_patch`] or
+/// [`discard_backup`] is called. The updated manifest is written next to the pak.
+pub fn apply_patch(
+    pak_path: impl AsRef<Path>,
+    patch_path: impl AsRef<Path>,
+) -> Result<PakManifest, PakError> {
+    let pak_path = pak_path.as_ref();
+    let base = std::fs::read(pak_path)?;
+    let patch = std::fs::read(patch_path.as_ref())?;
+
+    let (data, manifest) = apply_patch_to_bytes(base, patch)?;
+
+    let temp_path = with_suffix(pak_path, "tmp");
+    let backup_path = with_suffix(pak_path, "bak");
+
+    // Back up the manifest before touching the pak. A backup left by an earlier patch must not
+    // survive when there is no manifest now, or rollback would restore it.
+    let manifest_path = pak_path.with_extension("manifest.ron");
+    let manifest_backup_path = with_suffix(&manifest_path, "bak");
+    if manifest_path.exists() {
+        std::fs::copy(&manifest_path, &manifest_backup_path)?;
+    } else if manifest_backup_path.exists() {
+        std::fs::remove_file(&manifest_backup_path)?;
+    }
+
+    std::fs::write(&temp_path, &data)?;
+    std::fs::rename(pak_path, &backup_path)?;
+
+    if let Err(e) = std::fs::rename(&temp_path, pak_path) {
+        log::error!("Failed to install patched pak, restoring backup: {}", e);
+        std::fs::rename(&backup_path, pak_path)?;
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+
+    if let Err(e) = manifest.save(&manifest_path) {
+        log::error!("Failed to write patched manifest, restoring backup: {}", e);
+        rollback_patch(pak_path)?;
+        return Err(e);
+    }
+
+    log::info!(
+        "Patched {} to version {} ({} entries)",
+        pak_path.display(),
+        manifest.version,
+        manifest.entries.len()
+    );
+
+    Ok(manifest)
+}
+
+/// Restores the pak that was replaced by the last [`apply_patch`].
+///
+/// The manifest is restored too, or removed if the pak had none before the patch.
+pub fn rollback_patch(pak_path: impl AsRef<Path>) -> Result<(), PakError> {
+    let pak_path = pak_path.as_ref();
+    let backup_path = with_suffix(pak_path, "bak");
+
+    if !backup_path.exists() {
+        return Err(PakError::AssetNotFound(backup_path.display().to_string()));
+    }
+
+    std::fs::rename(&backup_path, pak_path)?;
+
+    let manifest_path = pak_path.with_extension("manifest.ron");
+    let manifest_backup_path = with_suffix(&manifest_path, "bak");
+    if manifest_backup_path.exists() {
+        std::fs::rename(&manifest_backup_path, &manifest_path)?;
+    } else if manifest_path.exists() {
+        // apply_patch backs up any existing manifest, so this one was written by the patch
+        std::fs::remove_file(&manifest_path)?;
+    }
+
+    log::info!("Rolled back {}", pak_path.display());
+    Ok(())
+}
+
+/// Deletes the backup kept by [`apply_patch`] once the update is known to be good.
+pub fn discard_backup(pak_path: impl AsRef<Path>) -> Result<(), PakError> {
+    let pak_path = pak_path.as_ref();
+    let backup_paths = [
+        with_suffix(pak_path, "bak"),
+        with_suffix(&pak_path.with_extension("manifest.ron"), "bak"),
+    ];
+
+    for backup_path in backup_paths {
+        if backup_path.exists() {
+            std::fs::remove_file(backup_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = PakBuilder::new();
+        for (path, data) in files {
+            builder.add_bytes(path.to_string(), data.to_vec());
+        }
+        builder.build_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_patch_roundtrip() {
+        let old_pak = build(&[("a.txt", b"same"), ("b.txt", b"old"), ("c.txt", b"gone")]);
+        let new_pak = build(&[("a.txt", b"same"), ("b.txt", b"new!"), ("d.txt", b"added")]);
+
+        let old = PakManifest::from_pak_bytes(1, "game.pak", old_pak.clone()).unwrap();
+        let new = PakManifest::from_pak_bytes(2, "game.pak", new_pak.clone()).unwrap();
+
+        let diff = ManifestDiff::between(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.removed, vec!["c.txt".to_string()]);
+
+        let patch = create_patch(&old, &new, &PakArchive::from_bytes(new_pak).unwrap()).unwrap();
+        let (patched, manifest) = apply_patch_to_bytes(old_pak, patch).unwrap();
+
+        assert_eq!(manifest.version, 2);
+        assert_eq!(manifest.entries, new.entries);
+
+        let archive = PakArchive::from_bytes(patched).unwrap();
+        assert_eq!(archive.get("b.txt").unwrap(), b"new!");
+        assert!(!archive.exists("c.txt"));
+    }
+
+    #[test]
+    fn test_patch_rejects_wrong_base() {
+        let old_pak = build(&[("a.txt", b"one")]);
+        let new_pak = build(&[("a.txt", b"two")]);
+        let other_pak = build(&[("a.txt", b"three")]);
+
+        let old = PakManifest::from_pak_bytes(1, "game.pak", old_pak).unwrap();
+        let new = PakManifest::from_pak_bytes(2, "game.pak", new_pak.clone()).unwrap();
+        let patch = create_patch(&old, &new, &PakArchive::from_bytes(new_pak).unwrap()).unwrap();
+
+        assert!(matches!(
+            apply_patch_to_bytes(other_pak, patch),
+            Err(PakError::PatchMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_rollback_without_prior_manifest() {
+        let dir = std::env::temp_dir().join(format!("resonance-patch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let old_pak = build(&[("a.txt", b"one")]);
+        let new_pak = build(&[("a.txt", b"two")]);
+        let old = PakManifest::from_pak_bytes(1, "game.pak", old_pak.clone()).unwrap();
+        let new = PakManifest::from_pak_bytes(2, "game.pak", new_pak.clone()).unwrap();
+        let patch = create_patch(&old, &new, &PakArchive::from_bytes(new_pak).unwrap()).unwrap();
+
+        let pak_path = dir.join("game.pak");
+        let patch_path = dir.join("game.patch");
+        let manifest_path = pak_path.with_extension("manifest.ron");
+        std::fs::write(&pak_path, &old_pak).unwrap();
+        std::fs::write(&patch_path, patch).unwrap();
+        // Left behind by an earlier patch that was never discarded
+        std::fs::write(with_suffix(&manifest_path, "bak"), "stale").unwrap();
+
+        apply_patch(&pak_path, &patch_path).unwrap();
+        assert_eq!(PakManifest::load(&manifest_path).unwrap().version, 2);
+
+        rollback_patch(&pak_path).unwrap();
+        assert_eq!(std::fs::read(&pak_path).unwrap(), old_pak);
+        assert!(!manifest_path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
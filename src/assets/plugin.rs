@@ -4,9 +4,12 @@ use crate::assets::cache::AssetCache;
 use crate::assets::loader::mesh::MeshData;
 use crate::assets::loader::texture::TextureData;
 use crate::assets::source::AssetSourceConfig;
-use crate::core::MemoryTracker;
+use crate::core::{AssetFallbackUsed, AssetLoaded, MemoryTracker};
 use bevy_ecs::prelude::*;
 
+/// How long loads still reading from disk get to finish on shutdown.
+const LOADER_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
 pub struct AssetsPluginConfig {
     pub asset_source: AssetSourceConfig,
 }
@@ -52,6 +55,16 @@ impl Plugin for AssetsPlugin {
         let cache = (**assets.cache()).clone();
         engine.world.insert_resource(assets);
         engine.world.insert_resource(cache);
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<AssetLoaded>>();
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<AssetFallbackUsed>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            schedule.add_systems(send_asset_messages);
+        }
 
         if let Some(schedule) = engine.schedules.get_mut(Stage::PostUpdate) {
             schedule.add_systems(update_asset_memory_stats);
@@ -66,6 +79,33 @@ impl Plugin for AssetsPlugin {
             }
         };
     }
+
+    fn on_shutdown(&self, engine: &mut Resonance) {
+        if let Some(mut assets) = engine.world.get_resource_mut::<Assets>() {
+            assets.shutdown(LOADER_SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+/// Announces background loads that completed, warning about placeholders left in use.
+pub fn send_asset_messages(
+    assets: Res<Assets>,
+    mut loaded: MessageWriter<AssetLoaded>,
+    mut fallback_used: MessageWriter<AssetFallbackUsed>,
+) {
+    for finished in assets.drain_finished() {
+        if let (Some(error), true) = (&finished.error, finished.fallback) {
+            log::warn!("Using placeholder for {}: {}", finished.path, error);
+            fallback_used.write(AssetFallbackUsed {
+                path: finished.path.clone(),
+                error: error.clone(),
+            });
+        }
+        loaded.write(AssetLoaded {
+            success: finished.error.is_none(),
+            path: finished.path,
+        });
+    }
 }
 
 fn update_asset_memory_stats(
@@ -0,0 +1,513 @@
+//! Loading critical assets at boot, in dependency order.
+//!
+//! A preload manifest lists assets in named groups. Groups start loading once the
+//! groups they come after have finished, so e.g. UI textures are requested before
+//! level geometry competes for the loader threads:
+//!
+//! ```ron
+//! (
+//!     groups: [
+//!         (name: "core", assets: ["shaders/ui.wgsl", "fonts/main.ttf"]),
+//!         (name: "ui", after: ["core"], assets: ["textures/ui/atlas.png"]),
+//!         (name: "level", after: ["core"], assets: ["models/level.glb", "audio/ambience.ogg"]),
+//!     ],
+//! )
+//! ```
+//!
+//! The manifest itself is loaded through [`Assets`] by [`PreloadPlugin`]. Gate state
+//! transitions on a group with the [`wait_for_group`] run condition:
+//!
+//! ```ignore
+//! Resonance::new()
+//!     .add_plugin(DefaultPlugins)
+//!     .add_plugin(PreloadPlugin::new("preload.ron"))
+//!     .add_system(Stage::Update, show_main_menu.run_if(wait_for_group("ui")))
+//!     .run();
+//! ```
+//!
+//! Failed assets count as finished so a missing file can't stall the game; they are
+//! logged and listed in [`GroupProgress::failed`].
+
+use crate::app::{Plugin, Resonance, Stage};
+use crate::assets::assets::{Assets, LoadState};
+use crate::assets::handle::AssetId;
+use crate::assets::loader::{
+    AssetLoader, LoadError,
+    audio::{AudioData, AudioLoader},
+    font::{FontData, TtfLoader},
+    mesh::{GltfLoader, MeshData, ObjLoader},
+    shader::{ShaderData, WgslLoader},
+    texture::{TextureData, TextureLoader},
+};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+/// Assets loaded together, after the groups in `after`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadGroup {
+    pub name: String,
+    #[serde(default)]
+    pub after: Vec<String>,
+    pub assets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadManifest {
+    pub groups: Vec<PreloadGroup>,
+}
+
+impl PreloadManifest {
+    pub fn from_ron(source: &str) -> Result<Self, LoadError> {
+        let manifest: Self = ron::from_str(source)
+            .map_err(|e| LoadError::LoadFailed(format!("Invalid preload manifest: {}", e)))?;
+        manifest.load_order()?;
+        Ok(manifest)
+    }
+
+    /// Group indices ordered so every group comes after the groups it depends on.
+    ///
+    /// Fails on unknown group names, duplicate groups and dependency cycles.
+    pub fn load_order(&self) -> Result<Vec<usize>, LoadError> {
+        let mut indices = HashMap::new();
+        for (index, group) in self.groups.iter().enumerate() {
+            if indices.insert(group.name.as_str(), index).is_some() {
+                return Err(LoadError::LoadFailed(format!(
+                    "Preload group '{}' is defined twice",
+                    group.name
+                )));
+            }
+        }
+
+        let mut in_degree = vec![0; self.groups.len()];
+        let mut dependents = vec![Vec::new(); self.groups.len()];
+        for (index, group) in self.groups.iter().enumerate() {
+            for dependency in &group.after {
+                let Some(&dependency_index) = indices.get(dependency.as_str()) else {
+                    return Err(LoadError::LoadFailed(format!(
+                        "Preload group '{}' comes after unknown group '{}'",
+                        group.name, dependency
+                    )));
+                };
+                in_degree[index] += 1;
+                dependents[dependency_index].push(index);
+            }
+        }
+
+        let mut queue: VecDeque<usize> = (0..self.groups.len())
+            .filter(|&index| in_degree[index] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.groups.len());
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for &dependent in &dependents[index] {
+                in_degree[dependent] -= 1;
+                if in_degree[dependent] == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        if order.len() != self.groups.len() {
+            return Err(LoadError::LoadFailed(
+                "Preload groups have a dependency cycle".to_string(),
+            ));
+        }
+        Ok(order)
+    }
+}
+
+/// Loads [`PreloadManifest`]s from RON files.
+pub struct PreloadManifestLoader;
+
+impl AssetLoader for PreloadManifestLoader {
+    type Asset = PreloadManifest;
+
+    fn load(&self, path: &Path) -> Result<Self::Asset, LoadError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| LoadError::NotFound(format!("{}: {}", path.display(), e)))?;
+        PreloadManifest::from_ron(&source)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// Which loader a manifest entry goes through, picked by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadKind {
+    Texture,
+    Obj,
+    Gltf,
+    Audio,
+    Font,
+    Shader,
+}
+
+impl PreloadKind {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        let kinds = [
+            (Self::Texture, TextureLoader.extensions()),
+            (Self::Obj, ObjLoader.extensions()),
+            (Self::Gltf, GltfLoader.extensions()),
+            (Self::Audio, AudioLoader.extensions()),
+            (Self::Font, TtfLoader.extensions()),
+            (Self::Shader, WgslLoader.extensions()),
+        ];
+        kinds
+            .into_iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map(|(kind, _)| kind)
+    }
+
+    fn start(self, assets: &Assets, path: &str) -> AssetId {
+        match self {
+            Self::Texture => assets.preload(TextureLoader, path),
+            Self::Obj => assets.preload(ObjLoader, path),
+            Self::Gltf => assets.preload(GltfLoader, path),
+            Self::Audio => assets.preload(AudioLoader, path),
+            Self::Font => assets.preload(TtfLoader, path),
+            Self::Shader => assets.preload(WgslLoader, path),
+        }
+    }
+
+    /// `None` while loading, `Some(true)` when loaded, `Some(false)` when failed.
+    fn finished(self, assets: &Assets, id: AssetId) -> Option<bool> {
+        fn state<T: Send + Sync + 'static>(assets: &Assets, id: AssetId) -> Option<bool> {
+            match assets.get_state::<T>(id)? {
+                LoadState::Loading => None,
+                LoadState::Loaded(_) => Some(true),
+                LoadState::Failed(_) => Some(false),
+            }
+        }
+        match self {
+            Self::Texture => state::<TextureData>(assets, id),
+            Self::Obj | Self::Gltf => state::<Vec<MeshData>>(assets, id),
+            Self::Audio => state::<AudioData>(assets, id),
+            Self::Font => state::<FontData>(assets, id),
+            Self::Shader => state::<ShaderData>(assets, id),
+        }
+    }
+}
+
+/// Loading state of one preload group.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupProgress {
+    pub loaded: usize,
+    /// Paths that failed to load or have no loader.
+    pub failed: Vec<String>,
+    pub total: usize,
+}
+
+impl GroupProgress {
+    pub fn is_complete(&self) -> bool {
+        self.loaded + self.failed.len() == self.total
+    }
+
+    /// Finished assets in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.loaded + self.failed.len()) as f32 / self.total as f32
+    }
+}
+
+/// A preload group finished loading.
+#[derive(Message, Debug, Clone)]
+pub struct PreloadGroupLoaded {
+    pub group: String,
+    pub progress: GroupProgress,
+}
+
+#[derive(Debug)]
+struct GroupState {
+    group: PreloadGroup,
+    /// Started assets still loading.
+    pending: Vec<(String, PreloadKind, AssetId)>,
+    progress: GroupProgress,
+    started: bool,
+    announced: bool,
+}
+
+/// Drives a [`PreloadManifest`] and reports per-group progress.
+#[derive(Resource, Debug)]
+pub struct Preloader {
+    manifest_path: String,
+    manifest_id: Option<AssetId>,
+    manifest_error: Option<String>,
+    /// In load order, empty until the manifest is loaded.
+    groups: Vec<GroupState>,
+}
+
+impl Preloader {
+    pub fn new(manifest_path: impl Into<String>) -> Self {
+        Self {
+            manifest_path: manifest_path.into(),
+            manifest_id: None,
+            manifest_error: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Progress of a group, `None` before the manifest is loaded or for unknown groups.
+    pub fn progress(&self, group: &str) -> Option<&GroupProgress> {
+        self.groups
+            .iter()
+            .find(|state| state.group.name == group)
+            .map(|state| &state.progress)
+    }
+
+    /// Whether every asset of `group` has finished loading or failed.
+    pub fn is_group_ready(&self, group: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|state| state.group.name == group && state.started && state.progress.is_complete())
+    }
+
+    /// Whether every group is ready, or the manifest could not be loaded.
+    pub fn is_finished(&self) -> bool {
+        self.manifest_error.is_some()
+            || (!self.groups.is_empty()
+                && self
+                    .groups
+                    .iter()
+                    .all(|state| state.started && state.progress.is_complete()))
+    }
+
+    /// Finished assets over all groups in `0.0..=1.0`.
+    pub fn overall_progress(&self) -> f32 {
+        let (done, total) = self.groups.iter().fold((0, 0), |(done, total), state| {
+            (
+                done + state.progress.loaded + state.progress.failed.len(),
+                total + state.progress.total,
+            )
+        });
+        if total == 0 {
+            return if self.is_finished() { 1.0 } else { 0.0 };
+        }
+        done as f32 / total as f32
+    }
+
+    pub fn manifest_error(&self) -> Option<&str> {
+        self.manifest_error.as_deref()
+    }
+
+    fn set_manifest(&mut self, manifest: &PreloadManifest) {
+        // Validated by the loader
+        let order = manifest.load_order().unwrap_or_default();
+        self.groups = order
+            .into_iter()
+            .map(|index| {
+                let group = manifest.groups[index].clone();
+                GroupState {
+                    progress: GroupProgress {
+                        total: group.assets.len(),
+                        ..Default::default()
+                    },
+                    group,
+                    pending: Vec::new(),
+                    started: false,
+                    announced: false,
+                }
+            })
+            .collect();
+    }
+
+    fn update(&mut self, assets: &Assets) -> Vec<PreloadGroupLoaded> {
+        for index in 0..self.groups.len() {
+            let ready = self.groups[index]
+                .group
+                .after
+                .iter()
+                .all(|name| self.is_group_ready(name));
+            let state = &mut self.groups[index];
+            if state.started || !ready {
+                continue;
+            }
+            state.started = true;
+            for path in &state.group.assets {
+                match PreloadKind::from_path(path) {
+                    Some(kind) => {
+                        state
+                            .pending
+                            .push((path.clone(), kind, kind.start(assets, path)))
+                    }
+                    None => {
+                        log::warn!("No loader for preloaded asset {}", path);
+                        state.progress.failed.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        let mut finished = Vec::new();
+        for state in &mut self.groups {
+            let progress = &mut state.progress;
+            state
+                .pending
+                .retain(|(path, kind, id)| match kind.finished(assets, *id) {
+                    None => true,
+                    Some(true) => {
+                        progress.loaded += 1;
+                        false
+                    }
+                    Some(false) => {
+                        progress.failed.push(path.clone());
+                        false
+                    }
+                });
+
+            if state.started && progress.is_complete() && !state.announced {
+                state.announced = true;
+                log::info!(
+                    "Preload group '{}' ready ({} loaded, {} failed)",
+                    state.group.name,
+                    progress.loaded,
+                    progress.failed.len()
+                );
+                finished.push(PreloadGroupLoaded {
+                    group: state.group.name.clone(),
+                    progress: progress.clone(),
+                });
+            }
+        }
+        finished
+    }
+}
+
+/// Run condition that is true once `group` has finished preloading.
+pub fn wait_for_group(
+    group: impl Into<String>,
+) -> impl FnMut(Option<Res<Preloader>>) -> bool + Clone {
+    let group = group.into();
+    move |preloader: Option<Res<Preloader>>| {
+        preloader.is_some_and(|preloader| preloader.is_group_ready(&group))
+    }
+}
+
+/// Loads a [`PreloadManifest`] at startup and drives its groups.
+pub struct PreloadPlugin {
+    manifest_path: String,
+}
+
+impl PreloadPlugin {
+    pub fn new(manifest_path: impl Into<String>) -> Self {
+        Self {
+            manifest_path: manifest_path.into(),
+        }
+    }
+}
+
+impl Default for PreloadPlugin {
+    /// Loads `preload.ron`.
+    fn default() -> Self {
+        Self::new("preload.ron")
+    }
+}
+
+impl Plugin for PreloadPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        engine
+            .world
+            .insert_resource(Preloader::new(self.manifest_path.clone()));
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<PreloadGroupLoaded>>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            schedule.add_systems(update_preloader);
+        }
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<crate::assets::AssetsPlugin>(),
+            "resonance::assets::AssetsPlugin",
+        )]
+    }
+}
+
+pub fn update_preloader(
+    assets: Option<Res<Assets>>,
+    mut preloader: ResMut<Preloader>,
+    mut group_loaded: MessageWriter<PreloadGroupLoaded>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+    if preloader.manifest_error.is_some() {
+        return;
+    }
+
+    if preloader.groups.is_empty() {
+        let id = match preloader.manifest_id {
+            Some(id) => id,
+            None => {
+                let id = assets.preload(PreloadManifestLoader, &preloader.manifest_path);
+                preloader.manifest_id = Some(id);
+                id
+            }
+        };
+        match assets.get_state::<PreloadManifest>(id) {
+            Some(LoadState::Loaded(manifest)) => preloader.set_manifest(&manifest),
+            Some(LoadState::Failed(error)) => {
+                log::error!("Failed to load preload manifest: {}", error);
+                preloader.manifest_error = Some(error);
+                return;
+            }
+            _ => return,
+        }
+    }
+
+    group_loaded.write_batch(preloader.update(&assets));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_load_order() {
+        let manifest = PreloadManifest::from_ron(
+            r#"(groups: [
+                (name: "level", after: ["ui", "core"], assets: ["models/level.glb"]),
+                (name: "ui", after: ["core"], assets: ["textures/atlas.png"]),
+                (name: "core", assets: ["fonts/main.ttf", "shaders/ui.wgsl"]),
+            ])"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.load_order().unwrap(), vec![2, 1, 0]);
+
+        assert!(
+            PreloadManifest::from_ron(r#"(groups: [(name: "a", after: ["b"], assets: [])])"#)
+                .is_err()
+        );
+        assert!(
+            PreloadManifest::from_ron(
+                r#"(groups: [(name: "a", after: ["b"], assets: []), (name: "b", after: ["a"], assets: [])])"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_kind_from_extension() {
+        assert_eq!(
+            PreloadKind::from_path("textures/UI.PNG"),
+            Some(PreloadKind::Texture)
+        );
+        assert_eq!(
+            PreloadKind::from_path("models/level.glb"),
+            Some(PreloadKind::Gltf)
+        );
+        assert_eq!(
+            PreloadKind::from_path("shaders/ui.wgsl"),
+            Some(PreloadKind::Shader)
+        );
+        assert_eq!(PreloadKind::from_path("notes.txt"), None);
+    }
+}
@@ -3,19 +3,14 @@ use crate::assets::pak::{PakArchive, PakError};
 use std::path::PathBuf;
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum AssetSourceConfig {
+    #[default]
     Auto,
     FileSystem(PathBuf),
     PakFile(PathBuf),
 }
 
-impl Default for AssetSourceConfig {
-    fn default() -> Self {
-        Self::Auto
-    }
-}
-
 impl AssetSourceConfig {
     pub fn resolve(self) -> Result<AssetSource, LoadError> {
         match self {
@@ -132,11 +127,9 @@ impl AssetSource {
                     .collect::<Result<Vec<_>, _>>()
                 {
                     Ok(entries) => {
-                        for entry in entries {
-                            if entry.file_type().is_file() {
-                                if let Ok(relative) = entry.path().strip_prefix(root) {
-                                    assets.push(relative.to_string_lossy().replace('\\', "/"));
-                                }
+                        for entry in entries.iter().filter(|entry| entry.file_type().is_file()) {
+                            if let Ok(relative) = entry.path().strip_prefix(root) {
+                                assets.push(relative.to_string_lossy().replace('\\', "/"));
                             }
                         }
                     }
@@ -65,6 +65,9 @@ pub struct AudioBackend {
     stream: SendOutputStream,
 
     sinks: Arc<Mutex<HashMap<Entity, AudioSinkType>>>,
+    /// Volume each sink was given, before the master volume is applied.
+    volumes: Arc<Mutex<HashMap<Entity, f32>>>,
+    master_volume: Arc<Mutex<f32>>,
 }
 
 impl AudioBackend {
@@ -75,6 +78,8 @@ impl AudioBackend {
         Ok(Self {
             stream: SendOutputStream(Arc::new(stream)),
             sinks: Arc::new(Mutex::new(HashMap::new())),
+            volumes: Arc::new(Mutex::new(HashMap::new())),
+            master_volume: Arc::new(Mutex::new(1.0)),
         })
     }
 
@@ -118,6 +123,7 @@ impl AudioBackend {
         if let Some(sink) = sinks.remove(&entity) {
             sink.stop();
         }
+        self.volumes.lock().unwrap().remove(&entity);
     }
 
     pub fn play_audio<S>(&self, entity: Entity, source: S, volume: f32) -> Result<(), String>
@@ -126,7 +132,8 @@ impl AudioBackend {
     {
         let sinks = self.sinks.lock().unwrap();
         if let Some(sink) = sinks.get(&entity) {
-            sink.set_volume(volume);
+            self.volumes.lock().unwrap().insert(entity, volume);
+            sink.set_volume(volume * self.master_volume());
             sink.append(source);
             Ok(())
         } else {
@@ -137,7 +144,25 @@ impl AudioBackend {
     pub fn set_volume(&self, entity: Entity, volume: f32) {
         let sinks = self.sinks.lock().unwrap();
         if let Some(sink) = sinks.get(&entity) {
-            sink.set_volume(volume.clamp(0.0, 1.0));
+            let volume = volume.clamp(0.0, 1.0);
+            self.volumes.lock().unwrap().insert(entity, volume);
+            sink.set_volume(volume * self.master_volume());
+        }
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        *self.master_volume.lock().unwrap()
+    }
+
+    /// Scales every sink, e.g. to duck or mute the game while it's in the background.
+    pub fn set_master_volume(&self, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        *self.master_volume.lock().unwrap() = volume;
+
+        let sinks = self.sinks.lock().unwrap();
+        let volumes = self.volumes.lock().unwrap();
+        for (entity, sink) in sinks.iter() {
+            sink.set_volume(volumes.get(entity).copied().unwrap_or(1.0) * volume);
         }
     }
 
@@ -178,6 +203,10 @@ impl AudioBackend {
     pub fn cleanup_finished(&self) {
         let mut sinks = self.sinks.lock().unwrap();
         sinks.retain(|_, sink| !sink.is_empty());
+        self.volumes
+            .lock()
+            .unwrap()
+            .retain(|entity, _| sinks.contains_key(entity));
     }
 }
 
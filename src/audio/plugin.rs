@@ -68,16 +68,17 @@ impl Plugin for AudioPlugin {
             schedule.add_systems((play_audio_sources, handle_audio_state_changes));
         }
 
-        if self.config.enable_spatial_audio {
-            if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
-                schedule.add_systems(update_spatial_audio);
-            }
+        if self.config.enable_spatial_audio
+            && let Some(schedule) = engine.schedules.get_mut(Stage::Update)
+        {
+            schedule.add_systems(update_spatial_audio);
         }
 
-        if self.config.enable_doppler && self.config.enable_spatial_audio {
-            if let Some(schedule) = engine.schedules.get_mut(Stage::Update) {
-                schedule.add_systems(apply_doppler_effect);
-            }
+        if self.config.enable_doppler
+            && self.config.enable_spatial_audio
+            && let Some(schedule) = engine.schedules.get_mut(Stage::Update)
+        {
+            schedule.add_systems(apply_doppler_effect);
         }
         if let Some(schedule) = engine.schedules.get_mut(Stage::PostUpdate) {
             schedule.add_systems((cleanup_one_shot_audio, cleanup_audio_backend));
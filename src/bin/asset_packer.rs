@@ -1,5 +1,6 @@
-use resonance::assets::{PakBuilder, PakError};
-use std::path::{Path, PathBuf};
+use resonance::assets::PakError;
+use resonance::build_utils::{PackAssetsConfig, build_pak_from_files, collect_asset_files};
+use std::path::PathBuf;
 use std::time::Instant;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -15,6 +16,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut input_path = PathBuf::new();
     let mut output_path = PathBuf::new();
     let mut compress = false;
+    let mut import = true;
+    let mut version = 1u32;
+    let mut manifest_path: Option<PathBuf> = None;
 
     let mut i = 1;
     while i < args.len() {
@@ -35,10 +39,36 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 output_path = PathBuf::from(&args[i + 1]);
                 i += 2;
             }
+            "--version" | "-v" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --version requires a number");
+                    std::process::exit(1);
+                }
+                version = match args[i + 1].parse() {
+                    Ok(version) => version,
+                    Err(_) => {
+                        eprintln!("Error: Invalid version: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                };
+                i += 2;
+            }
+            "--manifest" | "-m" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --manifest requires a path");
+                    std::process::exit(1);
+                }
+                manifest_path = Some(PathBuf::from(&args[i + 1]));
+                i += 2;
+            }
             "--compress" | "-c" => {
                 compress = true;
                 i += 1;
             }
+            "--no-import" => {
+                import = false;
+                i += 1;
+            }
             "--help" | "-h" => {
                 print_usage();
                 std::process::exit(0);
@@ -63,16 +93,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
+    let mut config = PackAssetsConfig::new()
+        .assets_dir(input_path)
+        .output_pak(output_path)
+        .compress(compress)
+        .import(import)
+        .version(version);
+    if let Some(path) = manifest_path {
+        config = config.manifest(path);
+    }
+
     println!("Resonance Asset Packer");
     println!("======================");
-    println!("Input:   {}", input_path.display());
-    println!("Output:  {}", output_path.display());
+    println!("Input:   {}", config.assets_dir.display());
+    println!("Output:  {}", config.output_pak.display());
+    println!("Version: {}", config.version);
     println!("Compress: {}", if compress { "yes" } else { "no" });
+    println!("Import:  {}", if import { "yes" } else { "no" });
     println!();
 
     let start = Instant::now();
 
-    pack_directory(&input_path, &output_path, compress)?;
+    pack_directory(&config)?;
 
     let elapsed = start.elapsed();
     println!("\nPacking completed in {:.2}s", elapsed.as_secs_f64());
@@ -91,14 +133,22 @@ fn print_usage() {
     println!("    -i, --input <DIR>     Input directory containing assets");
     println!("    -o, --output <FILE>   Output PAK file path");
     println!("    -c, --compress        Enable compression (deflate)");
+    println!("    -v, --version <N>     Content version written to the manifest (default: 1)");
+    println!("    -m, --manifest <FILE> Manifest output path (default: <output>.manifest.ron)");
+    println!(
+        "        --no-import       Pack files without loading them through the importers first"
+    );
     println!("    -h, --help            Print this help message");
     println!();
     println!("EXAMPLES:");
     println!("    asset-packer -i ./assets -o game_assets.pak");
     println!("    asset-packer -i ./assets -o game_assets.pak --compress");
+    println!("    asset-packer -i ./assets -o game_assets.pak -c --version 3");
 }
 
-fn pack_directory(input_path: &Path, output_path: &Path, compress: bool) -> Result<(), PakError> {
+fn pack_directory(config: &PackAssetsConfig) -> Result<(), PakError> {
+    let input_path = &config.assets_dir;
+
     if !input_path.exists() {
         eprintln!(
             "Error: Input directory does not exist: {}",
@@ -115,11 +165,9 @@ fn pack_directory(input_path: &Path, output_path: &Path, compress: bool) -> Resu
         std::process::exit(1);
     }
 
-    let mut builder = PakBuilder::new().with_compression(compress);
-
     println!("Scanning directory...");
 
-    let files = collect_files(input_path)?;
+    let files = collect_asset_files(input_path)?;
 
     if files.is_empty() {
         println!("Warning: No files found in input directory");
@@ -127,31 +175,31 @@ fn pack_directory(input_path: &Path, output_path: &Path, compress: bool) -> Resu
     }
 
     println!("Found {} files to pack\n", files.len());
+    if config.import {
+        println!("Importing and building PAK archive...");
+    } else {
+        println!("Building PAK archive...");
+    }
 
-    let mut total_size = 0u64;
-
-    for (file_path, relative_path) in &files {
-        let metadata = std::fs::metadata(file_path)?;
-        let size = metadata.len();
-        total_size += size;
-
-        println!("  {} ({} bytes)", relative_path, size);
+    let manifest = build_pak_from_files(config, files)?;
 
-        builder.add_file(relative_path.clone(), file_path)?;
+    for entry in &manifest.entries {
+        println!(
+            "  {} ({} bytes, crc32 {:08x})",
+            entry.path, entry.size, entry.checksum
+        );
     }
 
+    let total_size = manifest.total_size();
+    let output_size = manifest.pak_size;
+
     println!(
         "\nTotal size: {} bytes ({:.2} MB)",
         total_size,
         total_size as f64 / 1_048_576.0
     );
-    println!("\nBuilding PAK archive...");
 
-    builder.build(output_path)?;
-
-    let output_metadata = std::fs::metadata(output_path)?;
-    let output_size = output_metadata.len();
-    let compression_ratio = if compress && total_size > 0 {
+    let compression_ratio = if config.compress && total_size > 0 {
         100.0 - (output_size as f64 / total_size as f64 * 100.0)
     } else {
         0.0
@@ -163,43 +211,15 @@ fn pack_directory(input_path: &Path, output_path: &Path, compress: bool) -> Resu
         output_size as f64 / 1_048_576.0
     );
 
-    if compress {
+    if config.compress {
         println!("Compression: {:.1}%", compression_ratio);
     }
 
-    Ok(())
-}
-
-fn collect_files(dir: &Path) -> Result<Vec<(PathBuf, String)>, PakError> {
-    let mut files = Vec::new();
-    collect_files_recursive(dir, dir, &mut files)?;
-    Ok(files)
-}
-
-fn collect_files_recursive(
-    base_dir: &Path,
-    current_dir: &Path,
-    files: &mut Vec<(PathBuf, String)>,
-) -> Result<(), PakError> {
-    for entry in std::fs::read_dir(current_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            collect_files_recursive(base_dir, &path, files)?;
-        } else if path.is_file() {
-            let relative = path.strip_prefix(base_dir).map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    format!("Failed to strip prefix: {}", e),
-                )
-            })?;
-
-            let relative_str = relative.to_string_lossy().replace('\\', "/");
-
-            files.push((path, relative_str));
-        }
-    }
+    println!(
+        "Manifest: {} (version {})",
+        config.manifest_path().display(),
+        manifest.version
+    );
 
     Ok(())
 }
@@ -0,0 +1,335 @@
+use std::path::{Path, PathBuf};
+
+const CARGO_TOML: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2024"
+
+[[bin]]
+name = "{{name}}"
+path = "src/main.rs"
+
+[[bin]]
+name = "{{name}}-server"
+path = "src/bin/server.rs"
+
+[dependencies]
+resonance = {{engine}}
+log = "0.4"
+serde = { version = "1.0", features = ["derive"] }
+ron = "0.8"
+
+[build-dependencies]
+resonance = {{engine}}
+"#;
+
+const BUILD_RS: &str = r#"fn main() {
+    // Packs ./assets into game_assets.pak for release builds
+    resonance::build_utils::pack_assets_auto();
+}
+"#;
+
+const MAIN_RS: &str = r#"use resonance::prelude::*;
+use {{crate_name}}::{scene, settings::GameSettings};
+
+fn main() {
+    let settings = GameSettings::load_or_default("settings.ron");
+
+    Resonance::new()
+        .with_log_level(log::LevelFilter::Info)
+        .with_graphics_settings(settings.graphics_settings())
+        .add_plugin(WindowPlugin::new(settings.window_config()))
+        .add_plugin(DefaultPlugins)
+        .add_system(Stage::Startup, scene::setup_scene)
+        .add_system(Stage::Update, scene::rotate_props)
+        .run();
+}
+"#;
+
+const SERVER_RS: &str = r#"use resonance::prelude::*;
+
+fn main() {
+    Resonance::builder()
+        .with_mode(ResonanceMode::Server)
+        .with_log_level(log::LevelFilter::Info)
+        .with_tickrate(30)
+        .build()
+        .add_plugin(DefaultPlugins)
+        .add_system(Stage::Startup, |_world: &mut World| {
+            log::info!("{{name}} server started");
+        })
+        .run();
+}
+"#;
+
+const LIB_RS: &str = r#"pub mod scene;
+pub mod settings;
+"#;
+
+const SCENE_RS: &str = r#"use resonance::prelude::*;
+use resonance::renderer::{AmbientLight, DirectionalLight, PointLight};
+
+/// Marks entities spun by `rotate_props`.
+#[derive(Component)]
+pub struct Rotating {
+    pub speed: f32,
+}
+
+/// Spawns a camera and lights. Add meshes with `Assets::load(ObjLoader, "models/...")`.
+pub fn setup_scene(world: &mut World) {
+    let mut camera_transform = Transform::from_xyz(0.0, 4.0, 10.0);
+    camera_transform.look_at(Vec3::ZERO, Vec3::Y);
+
+    world.spawn((
+        camera_transform,
+        GlobalTransform::default(),
+        Camera::new(70.0_f32.to_radians(), 16.0 / 9.0, 0.1, 1000.0),
+    ));
+
+    world.spawn(DirectionalLight::sun());
+    world.spawn(AmbientLight::default());
+    world.spawn((
+        PointLight::new(Vec3::ZERO, Vec3::new(1.0, 0.8, 0.6), 4.0, 12.0),
+        Transform::from_xyz(0.0, 3.0, 0.0),
+        GlobalTransform::default(),
+        Rotating { speed: 0.5 },
+    ));
+}
+
+pub fn rotate_props(mut query: Query<(&mut Transform, &Rotating)>, time: Res<Time>) {
+    for (mut transform, rotating) in query.iter_mut() {
+        transform.rotate_y(rotating.speed * time.delta_seconds());
+    }
+}
+"#;
+
+const SETTINGS_RS: &str = r#"use resonance::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// User-editable settings loaded from `settings.ron`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+    pub msaa_samples: u32,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            title: "{{name}}".to_string(),
+            width: 1280,
+            height: 720,
+            vsync: true,
+            msaa_samples: 4,
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        std::fs::read_to_string(path.as_ref())
+            .ok()
+            .and_then(|contents| match ron::from_str(&contents) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    log::warn!("Invalid settings file, using defaults: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn window_config(&self) -> WindowConfig {
+        WindowConfig::new(self.width, self.height, self.title.clone())
+    }
+
+    pub fn graphics_settings(&self) -> GraphicsSettings {
+        GraphicsSettings::new(
+            MsaaSampleCount::from_u32(self.msaa_samples).unwrap_or_default(),
+            self.vsync,
+        )
+    }
+}
+"#;
+
+const SETTINGS_RON: &str = r#"(
+    title: "{{name}}",
+    width: 1280,
+    height: 720,
+    vsync: true,
+    msaa_samples: 4,
+)
+"#;
+
+const GITIGNORE: &str = "/target\n*.pak\n*.manifest.ron\n";
+
+const README: &str = r#"# {{name}}
+
+Created with `resonance-new`.
+
+- `cargo run` starts the client
+- `cargo run --bin {{name}}-server` starts a headless server
+- Put models, textures, audio and shaders under `assets/`. Release builds pack them into `game_assets.pak`.
+- Edit `settings.ron` to change the window and graphics settings.
+"#;
+
+const ASSET_DIRS: &[&str] = &["models", "textures", "audio", "shaders"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let mut name: Option<String> = None;
+    let mut output_dir: Option<PathBuf> = None;
+    let mut engine_path: Option<String> = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--path" | "-p" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --path requires a directory");
+                    std::process::exit(1);
+                }
+                output_dir = Some(PathBuf::from(&args[i + 1]));
+                i += 2;
+            }
+            "--engine-path" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --engine-path requires a directory");
+                    std::process::exit(1);
+                }
+                engine_path = Some(args[i + 1].replace('\\', "/"));
+                i += 2;
+            }
+            "--help" | "-h" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            arg if arg.starts_with('-') => {
+                eprintln!("Error: Unknown argument: {}", arg);
+                print_usage();
+                std::process::exit(1);
+            }
+            arg => {
+                if name.is_some() {
+                    eprintln!("Error: Unexpected argument: {}", arg);
+                    std::process::exit(1);
+                }
+                name = Some(arg.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    let Some(name) = name else {
+        print_usage();
+        std::process::exit(1);
+    };
+
+    if !is_valid_package_name(&name) {
+        eprintln!(
+            "Error: '{}' is not a valid package name (use letters, digits, '-' and '_')",
+            name
+        );
+        std::process::exit(1);
+    }
+
+    let output_dir = output_dir.unwrap_or_else(|| PathBuf::from(&name));
+    if output_dir.exists()
+        && std::fs::read_dir(&output_dir).map_or(true, |mut d| d.next().is_some())
+    {
+        eprintln!(
+            "Error: Destination {} already exists and is not empty",
+            output_dir.display()
+        );
+        std::process::exit(1);
+    }
+
+    let engine = match engine_path {
+        Some(path) => format!("{{ path = \"{}\" }}", path),
+        None => format!("\"{}\"", env!("CARGO_PKG_VERSION")),
+    };
+
+    if let Err(e) = generate_project(&output_dir, &name, &engine) {
+        eprintln!("Error: Failed to generate project: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("Created project '{}' in {}", name, output_dir.display());
+    println!();
+    println!("Next steps:");
+    println!("    cd {}", output_dir.display());
+    println!("    cargo run");
+}
+
+fn print_usage() {
+    println!("Resonance Project Generator");
+    println!("Creates a ready-to-run game project");
+    println!();
+    println!("USAGE:");
+    println!("    resonance-new <NAME> [OPTIONS]");
+    println!();
+    println!("OPTIONS:");
+    println!("    -p, --path <DIR>         Output directory (default: ./<NAME>)");
+    println!(
+        "        --engine-path <DIR>  Depend on a local resonance checkout instead of crates.io"
+    );
+    println!("    -h, --help               Print this help message");
+    println!();
+    println!("EXAMPLES:");
+    println!("    resonance-new my_game");
+    println!("    resonance-new my_game --engine-path ../Resonance");
+}
+
+fn generate_project(root: &Path, name: &str, engine: &str) -> std::io::Result<()> {
+    let crate_name = name.replace('-', "_");
+    let render = |template: &str| {
+        template
+            .replace("{{name}}", name)
+            .replace("{{crate_name}}", &crate_name)
+            .replace("{{engine}}", engine)
+    };
+
+    let files: &[(&str, &str)] = &[
+        ("Cargo.toml", CARGO_TOML),
+        ("build.rs", BUILD_RS),
+        ("settings.ron", SETTINGS_RON),
+        (".gitignore", GITIGNORE),
+        ("README.md", README),
+        ("src/lib.rs", LIB_RS),
+        ("src/main.rs", MAIN_RS),
+        ("src/scene.rs", SCENE_RS),
+        ("src/settings.rs", SETTINGS_RS),
+        ("src/bin/server.rs", SERVER_RS),
+    ];
+
+    for (relative_path, template) in files {
+        let path = root.join(relative_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, render(template))?;
+        println!("  created {}", relative_path);
+    }
+
+    for dir in ASSET_DIRS {
+        let path = root.join("assets").join(dir);
+        std::fs::create_dir_all(&path)?;
+        std::fs::write(path.join(".gitkeep"), "")?;
+        println!("  created assets/{}/", dir);
+    }
+
+    Ok(())
+}
+
+fn is_valid_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -1,10 +1,25 @@
+use crate::assets::loader::audio::AudioLoader;
+use crate::assets::loader::font::TtfLoader;
+use crate::assets::loader::mesh::{GltfLoader, ObjLoader};
+use crate::assets::loader::shader::WgslLoader;
+use crate::assets::loader::texture::TextureLoader;
+use crate::assets::loader::{AssetLoader, LoadError};
+use crate::assets::{PakBuilder, PakError, PakManifest};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use walkdir::WalkDir;
 
 pub struct PackAssetsConfig {
     pub assets_dir: PathBuf,
     pub output_pak: PathBuf,
     pub compress: bool,
+    /// Content version recorded in the manifest.
+    pub version: u32,
+    /// Manifest output path. Defaults to the pak path with a `.manifest.ron` extension.
+    pub manifest: Option<PathBuf>,
+    /// Run every mesh, texture, shader, font and audio file through its loader before
+    /// packing, so broken assets fail the build instead of the game.
+    pub import: bool,
 }
 
 impl Default for PackAssetsConfig {
@@ -13,6 +28,9 @@ impl Default for PackAssetsConfig {
             assets_dir: PathBuf::from("assets"),
             output_pak: PathBuf::from("game_assets.pak"),
             compress: true,
+            version: 1,
+            manifest: None,
+            import: true,
         }
     }
 }
@@ -36,6 +54,142 @@ impl PackAssetsConfig {
         self.compress = compress;
         self
     }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn import(mut self, import: bool) -> Self {
+        self.import = import;
+        self
+    }
+
+    pub fn manifest(mut self, path: impl Into<PathBuf>) -> Self {
+        self.manifest = Some(path.into());
+        self
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.manifest
+            .clone()
+            .unwrap_or_else(|| self.output_pak.with_extension("manifest.ron"))
+    }
+}
+
+/// Packs every file under `config.assets_dir` into `config.output_pak` and
+/// writes a manifest next to it.
+///
+/// Files are added in sorted path order so identical inputs produce identical
+/// paks, which keeps manifest hashes stable between builds.
+pub fn build_pak(config: &PackAssetsConfig) -> Result<PakManifest, PakError> {
+    check_assets_dir(config)?;
+    let files = collect_asset_files(&config.assets_dir)?;
+    build_pak_from_files(config, files)
+}
+
+/// [`build_pak`] for files already gathered with [`collect_asset_files`].
+pub fn build_pak_from_files(
+    config: &PackAssetsConfig,
+    mut files: Vec<(PathBuf, String)>,
+) -> Result<PakManifest, PakError> {
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+
+    if config.import {
+        for (file_path, relative_path) in &files {
+            import_asset(file_path).map_err(|e| PakError::Import {
+                path: relative_path.clone(),
+                reason: e.to_string(),
+            })?;
+        }
+    }
+
+    let mut builder = PakBuilder::new().with_compression(config.compress);
+    for (file_path, relative_path) in files {
+        builder.add_file(relative_path, file_path)?;
+    }
+
+    let data = builder.build_to_bytes()?;
+    std::fs::write(&config.output_pak, &data)?;
+
+    let pak_file = config
+        .output_pak
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let manifest = PakManifest::from_pak_bytes(config.version, pak_file, data)?;
+    manifest.save(config.manifest_path())?;
+
+    Ok(manifest)
+}
+
+fn check_assets_dir(config: &PackAssetsConfig) -> Result<(), PakError> {
+    if !config.assets_dir.is_dir() {
+        return Err(PakError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "Assets directory not found: {}",
+                config.assets_dir.display()
+            ),
+        )));
+    }
+    Ok(())
+}
+
+/// Returns `(absolute path, pak path)` pairs for every file under `dir`.
+pub fn collect_asset_files(dir: &Path) -> Result<Vec<(PathBuf, String)>, PakError> {
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(dir).follow_links(true) {
+        let entry = entry.map_err(|e| PakError::Io(e.into()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(dir).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Failed to strip prefix: {}", e),
+            )
+        })?;
+
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+        files.push((entry.path().to_path_buf(), relative_str));
+    }
+
+    Ok(files)
+}
+
+/// Loads `path` with the engine loader for its extension. Returns `false` for files no
+/// loader handles, which are packed as they are.
+pub fn import_asset(path: &Path) -> Result<bool, LoadError> {
+    fn import_with<L: AssetLoader>(
+        loader: L,
+        path: &Path,
+        extension: &str,
+    ) -> Option<Result<(), LoadError>> {
+        loader
+            .extensions()
+            .contains(&extension)
+            .then(|| loader.load(path).map(|_| ()))
+    }
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = extension.as_str();
+    let result = import_with(ObjLoader, path, extension)
+        .or_else(|| import_with(GltfLoader, path, extension))
+        .or_else(|| import_with(TextureLoader, path, extension))
+        .or_else(|| import_with(WgslLoader, path, extension))
+        .or_else(|| import_with(TtfLoader, path, extension))
+        .or_else(|| import_with(AudioLoader, path, extension));
+
+    match result {
+        Some(result) => result.map(|()| true),
+        None => Ok(false),
+    }
 }
 
 pub fn pack_assets_auto() {
@@ -87,15 +241,26 @@ pub fn pack_assets_with_config(config: PackAssetsConfig) {
 
     let input_str = config.assets_dir.to_string_lossy();
     let output_str = config.output_pak.to_string_lossy();
+    let version_str = config.version.to_string();
+    let manifest_path = config.manifest_path();
+    let manifest_str = manifest_path.to_string_lossy();
 
     args.push(&input_str);
     args.push("--output");
     args.push(&output_str);
+    args.push("--version");
+    args.push(&version_str);
+    args.push("--manifest");
+    args.push(&manifest_str);
 
     if config.compress {
         args.push("--compress");
     }
 
+    if !config.import {
+        args.push("--no-import");
+    }
+
     let status = Command::new("cargo").args(&args).status();
 
     match status {
@@ -137,3 +302,103 @@ fn is_directory_empty(path: &Path) -> bool {
     }
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::PakArchive;
+
+    /// Scratch directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "resonance-build-utils-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(path.join("assets/shaders")).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn config(dir: &TempDir) -> PackAssetsConfig {
+        PackAssetsConfig::new()
+            .assets_dir(dir.0.join("assets"))
+            .output_pak(dir.0.join("game.pak"))
+            .version(7)
+    }
+
+    #[test]
+    fn test_build_pak_roundtrip() {
+        let dir = TempDir::new("roundtrip");
+        let shader =
+            b"@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(); }";
+        std::fs::write(dir.0.join("assets/readme.txt"), b"hello").unwrap();
+        std::fs::write(dir.0.join("assets/shaders/basic.wgsl"), shader).unwrap();
+
+        let config = config(&dir);
+        let manifest = build_pak(&config).unwrap();
+
+        assert_eq!(manifest.version, 7);
+        assert_eq!(manifest.pak_file, "game.pak");
+        assert_eq!(PakManifest::load(config.manifest_path()).unwrap(), manifest);
+
+        let data = std::fs::read(&config.output_pak).unwrap();
+        assert_eq!(manifest.pak_size, data.len() as u64);
+        assert_eq!(manifest.pak_checksum, crc32fast::hash(&data));
+
+        let readme = manifest.get("readme.txt").unwrap();
+        assert_eq!(readme.size, 5);
+        assert_eq!(readme.checksum, crc32fast::hash(b"hello"));
+        let basic = manifest.get("shaders/basic.wgsl").unwrap();
+        assert_eq!(basic.size, shader.len() as u64);
+        assert_eq!(basic.checksum, crc32fast::hash(shader));
+
+        let archive = PakArchive::from_bytes(data).unwrap();
+        assert_eq!(archive.get("shaders/basic.wgsl").unwrap(), shader);
+    }
+
+    #[test]
+    fn test_build_pak_rejects_invalid_asset() {
+        let dir = TempDir::new("invalid");
+        std::fs::write(dir.0.join("assets/broken.png"), b"not a png").unwrap();
+
+        let err = build_pak(&config(&dir)).unwrap_err();
+        assert!(matches!(err, PakError::Import { ref path, .. } if path == "broken.png"));
+        assert!(!dir.0.join("game.pak").exists());
+
+        // Skipping the import packs the file as it is
+        build_pak(&config(&dir).import(false)).unwrap();
+        assert!(dir.0.join("game.pak").exists());
+    }
+
+    #[test]
+    fn test_build_pak_missing_assets_dir() {
+        let dir = TempDir::new("missing");
+        let config = config(&dir).assets_dir(dir.0.join("nowhere"));
+
+        let err = build_pak(&config).unwrap_err();
+        assert!(err.to_string().contains("Assets directory not found"));
+    }
+
+    #[test]
+    fn test_import_asset() {
+        let dir = TempDir::new("import");
+        let text = dir.0.join("notes.txt");
+        let texture = dir.0.join("broken.png");
+        std::fs::write(&text, b"packed as is").unwrap();
+        std::fs::write(&texture, b"not a png").unwrap();
+
+        assert!(!import_asset(&text).unwrap());
+        assert!(import_asset(&texture).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Debug audit for nondeterminism in the fixed-update path.
+//!
+//! The server-authoritative simulation runs in [`Stage::FixedUpdate`](crate::app::Stage::FixedUpdate)
+//! and must produce the same result from the same inputs. With
+//! [`Resonance::with_determinism_audit`](crate::app::Resonance::with_determinism_audit) the engine:
+//!
+//! - records call sites that read wall-clock [`Time`](crate::core::Time) during a fixed step
+//!   instead of [`FixedTime`](crate::core::FixedTime),
+//! - records call sites that draw from an unseeded [`SimRng`],
+//! - logs pairs of fixed-update systems with conflicting access and no ordering between them,
+//!   whose execution order can change from run to run.
+//!
+//! Offenders are logged the first time they are seen and collected in [`DeterminismAudit`].
+//! Each audit only watches the [`Time`](crate::core::Time) and [`SimRng`] resources of its own
+//! world, so several worlds can be audited side by side. `SimRng`s kept elsewhere and
+//! third-party randomness such as `rand::thread_rng()` are invisible to the audit, so simulation
+//! code should draw from the `SimRng` resource. Reductions whose result may depend on query
+//! iteration order can be checked with [`check_order_independence`].
+
+use bevy_ecs::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::panic::Location;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::time::Time;
+
+type Recorded = Vec<(NondeterminismSource, &'static Location<'static>)>;
+
+/// Offenders of each audit whose fixed step is running, by audit id.
+static RECORDING: Mutex<BTreeMap<u64, Recorded>> = Mutex::new(BTreeMap::new());
+static NEXT_AUDIT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// What made a call site nondeterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NondeterminismSource {
+    WallClock,
+    UnseededRng,
+}
+
+/// Called from audited APIs with the id of the audit watching them. Only records while that
+/// audit's fixed step runs.
+#[track_caller]
+pub(crate) fn record(audit: Option<u64>, source: NondeterminismSource) {
+    if let Some(id) = audit
+        && let Some(recorded) = RECORDING.lock().unwrap().get_mut(&id)
+    {
+        recorded.push((source, Location::caller()));
+    }
+}
+
+/// Insert to enable the audit; see the [module docs](self).
+#[derive(Resource, Debug)]
+pub struct DeterminismAudit {
+    id: u64,
+    /// Offending call sites (`file:line:column`) and how often they were hit.
+    offenders: BTreeMap<(NondeterminismSource, String), u64>,
+}
+
+impl Default for DeterminismAudit {
+    fn default() -> Self {
+        Self {
+            id: NEXT_AUDIT_ID.fetch_add(1, Ordering::Relaxed),
+            offenders: BTreeMap::new(),
+        }
+    }
+}
+
+impl DeterminismAudit {
+    pub fn offenders(&self) -> impl Iterator<Item = (NondeterminismSource, &str, u64)> {
+        self.offenders
+            .iter()
+            .map(|((source, location), count)| (*source, location.as_str(), *count))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.offenders.is_empty()
+    }
+
+    pub fn log_report(&self) {
+        if self.is_clean() {
+            log::info!("Determinism audit: no offenders in fixed update");
+            return;
+        }
+        log::warn!(
+            "Determinism audit: {} offending call sites",
+            self.offenders.len()
+        );
+        for (source, location, count) in self.offenders() {
+            log::warn!("  {:?} at {} ({} times)", source, location, count);
+        }
+    }
+
+    fn collect(&mut self, recorded: Recorded) {
+        for (source, location) in recorded {
+            let count = self
+                .offenders
+                .entry((source, location.to_string()))
+                .or_insert(0);
+            if *count == 0 {
+                log::warn!(
+                    "Nondeterminism in fixed update: {:?} at {}",
+                    source,
+                    location
+                );
+            }
+            *count += 1;
+        }
+    }
+}
+
+/// Runs one fixed step, recording offenders when the audit is enabled.
+pub(crate) fn audited_fixed_step(world: &mut World, step: impl FnOnce(&mut World)) {
+    let Some(id) = world
+        .get_resource::<DeterminismAudit>()
+        .map(|audit| audit.id)
+    else {
+        step(world);
+        return;
+    };
+
+    // Point this world's audited resources at its audit, whichever instance is current
+    if let Some(mut time) = world.get_resource_mut::<Time>() {
+        time.bypass_change_detection().audit = Some(id);
+    }
+    if let Some(mut rng) = world.get_resource_mut::<SimRng>() {
+        rng.bypass_change_detection().audit = Some(id);
+    }
+
+    RECORDING.lock().unwrap().insert(id, Vec::new());
+    step(world);
+    let recorded = RECORDING.lock().unwrap().remove(&id).unwrap_or_default();
+
+    world.resource_mut::<DeterminismAudit>().collect(recorded);
+}
+
+/// Random numbers for simulation code.
+///
+/// Seed it (e.g. from the match id shared with clients) so fixed-update results can be
+/// reproduced. Drawing from an unseeded `SimRng` resource during a fixed step is reported by
+/// the audit.
+#[derive(Resource)]
+pub struct SimRng {
+    rng: StdRng,
+    seeded: bool,
+    /// Id of the [`DeterminismAudit`] watching this resource.
+    audit: Option<u64>,
+}
+
+impl SimRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            seeded: true,
+            audit: None,
+        }
+    }
+
+    /// Seeded from OS entropy. Fine for cosmetics, not for the simulation.
+    pub fn from_entropy() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+            seeded: false,
+            audit: None,
+        }
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.seeded
+    }
+
+    #[track_caller]
+    pub fn random<T>(&mut self) -> T
+    where
+        rand::distributions::Standard: rand::distributions::Distribution<T>,
+    {
+        self.check();
+        self.rng.r#gen()
+    }
+
+    #[track_caller]
+    pub fn gen_range<T, R>(&mut self, range: R) -> T
+    where
+        T: rand::distributions::uniform::SampleUniform,
+        R: rand::distributions::uniform::SampleRange<T>,
+    {
+        self.check();
+        self.rng.gen_range(range)
+    }
+
+    /// The underlying generator, for APIs taking an [`RngCore`].
+    #[track_caller]
+    pub fn rng(&mut self) -> &mut impl RngCore {
+        self.check();
+        &mut self.rng
+    }
+
+    #[track_caller]
+    fn check(&self) {
+        if !self.seeded {
+            record(self.audit, NondeterminismSource::UnseededRng);
+        }
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::seeded(0)
+    }
+}
+
+/// Result of [`check_order_independence`] when the order mattered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderDependence {
+    pub original_hash: u64,
+    pub shuffled_hash: u64,
+}
+
+/// Runs `f` over `items` as given and again in a shuffled order, and compares the hashes
+/// of the two results.
+///
+/// Use it on reductions over query results, e.g. picking a target or summing forces,
+/// whose outcome must not depend on the order entities are iterated in.
+pub fn check_order_independence<T, R, F>(
+    items: &[T],
+    seed: u64,
+    f: F,
+) -> Result<(), OrderDependence>
+where
+    T: Clone,
+    R: Hash,
+    F: Fn(&[T]) -> R,
+{
+    let hash = |result: R| {
+        let mut hasher = DefaultHasher::new();
+        result.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    let original_hash = hash(f(items));
+    let mut shuffled = items.to_vec();
+    shuffled.shuffle(&mut StdRng::seed_from_u64(seed));
+    let shuffled_hash = hash(f(&shuffled));
+
+    if original_hash == shuffled_hash {
+        Ok(())
+    } else {
+        Err(OrderDependence {
+            original_hash,
+            shuffled_hash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_independence() {
+        let items: Vec<u32> = (0..32).collect();
+
+        assert!(check_order_independence(&items, 1, |items| items.iter().sum::<u32>()).is_ok());
+        // Collecting keeps the iteration order
+        assert!(check_order_independence(&items, 1, |items| items.to_vec()).is_err());
+    }
+
+    fn audited_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(DeterminismAudit::default());
+        world.insert_resource(SimRng::from_entropy());
+        world
+    }
+
+    #[test]
+    fn test_unseeded_rng_recorded_only_in_fixed_update() {
+        let mut world = audited_world();
+        let mut other = audited_world();
+        audited_fixed_step(&mut other, |_| {});
+
+        audited_fixed_step(&mut world, |world| {
+            world.resource_mut::<SimRng>().random::<u32>();
+            // Belongs to another world's audit, which isn't stepping
+            other.resource_mut::<SimRng>().random::<u32>();
+        });
+        world.resource_mut::<SimRng>().random::<u32>();
+
+        let offenders: Vec<_> = world.resource::<DeterminismAudit>().offenders().collect();
+        assert_eq!(offenders.len(), 1);
+        assert_eq!(offenders[0].0, NondeterminismSource::UnseededRng);
+        assert_eq!(offenders[0].2, 1);
+        assert!(other.resource::<DeterminismAudit>().is_clean());
+    }
+}
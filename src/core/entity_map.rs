@@ -0,0 +1,222 @@
+//! Remapping entity references when entities are recreated in another world.
+//!
+//! Components such as [`Parent`](crate::transform::Parent) store an [`Entity`], which is only
+//! meaningful in the world that allocated it. When a scene built from saved data or a server
+//! snapshot is spawned into a world that already has entities, those references would point
+//! at unrelated local entities. An [`EntityMap`] records which local entity each source entity
+//! became, and [`remap_entities`] rewrites the references of every component registered in
+//! [`EntityRefRegistry`]:
+//!
+//! ```ignore
+//! impl RemapEntities for Target {
+//!     fn remap_entities(&mut self, map: &EntityMap) {
+//!         self.0 = map.get_mapped(self.0);
+//!     }
+//! }
+//!
+//! world.get_resource_or_init::<EntityRefRegistry>().register::<Target>();
+//! ```
+//!
+//! Scenes remap automatically for entities spawned with
+//! [`SceneBuilder::spawn_mapped`](crate::addons::SceneBuilder::spawn_mapped). Networked
+//! entities are identified by [`NetworkId`] and remapped with [`remap_network_entities`].
+
+use bevy_ecs::component::Mutable;
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A component holding entity references that must follow their entities into another world.
+pub trait RemapEntities {
+    fn remap_entities(&mut self, map: &EntityMap);
+}
+
+/// Source entity to local entity. Sources are keyed by [`Entity::to_bits`], so ids read from a
+/// file or the network can be inserted directly.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMap {
+    map: HashMap<u64, Entity>,
+}
+
+impl EntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, source: Entity, target: Entity) {
+        self.map.insert(source.to_bits(), target);
+    }
+
+    pub fn insert_id(&mut self, id: u64, target: Entity) {
+        self.map.insert(id, target);
+    }
+
+    pub fn get(&self, source: Entity) -> Option<Entity> {
+        self.map.get(&source.to_bits()).copied()
+    }
+
+    pub fn get_id(&self, id: u64) -> Option<Entity> {
+        self.map.get(&id).copied()
+    }
+
+    pub fn remove_id(&mut self, id: u64) -> Option<Entity> {
+        self.map.remove(&id)
+    }
+
+    /// The local entity for `source`, or [`Entity::PLACEHOLDER`] when it wasn't part of the
+    /// mapped set, so a dangling reference never aliases an unrelated local entity.
+    pub fn get_mapped(&self, source: Entity) -> Entity {
+        self.get(source).unwrap_or(Entity::PLACEHOLDER)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+type RemapFn = fn(&mut World, Entity, &EntityMap);
+
+/// Components whose entity references are rewritten by [`remap_entities`].
+#[derive(Resource, Default, Clone)]
+pub struct EntityRefRegistry {
+    remappers: Vec<(&'static str, RemapFn)>,
+}
+
+impl EntityRefRegistry {
+    pub fn register<T: Component<Mutability = Mutable> + RemapEntities>(&mut self) -> &mut Self {
+        let name = std::any::type_name::<T>();
+        if !self
+            .remappers
+            .iter()
+            .any(|(registered, _)| *registered == name)
+        {
+            self.remappers.push((name, remap_component::<T>));
+        }
+        self
+    }
+
+    pub fn is_registered<T: Component>(&self) -> bool {
+        let name = std::any::type_name::<T>();
+        self.remappers
+            .iter()
+            .any(|(registered, _)| *registered == name)
+    }
+}
+
+fn remap_component<T: Component<Mutability = Mutable> + RemapEntities>(
+    world: &mut World,
+    entity: Entity,
+    map: &EntityMap,
+) {
+    if let Some(mut component) = world.get_mut::<T>(entity) {
+        component.remap_entities(map);
+    }
+}
+
+/// Rewrites the registered entity references on `entities` through `map`.
+pub fn remap_entities(world: &mut World, entities: &[Entity], map: &EntityMap) {
+    let Some(registry) = world.get_resource::<EntityRefRegistry>().cloned() else {
+        return;
+    };
+    for &entity in entities {
+        for (_, remap) in &registry.remappers {
+            remap(world, entity, map);
+        }
+    }
+}
+
+/// Identity of a replicated entity shared by server and clients: the server's
+/// [`Entity::to_bits`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NetworkId(pub u64);
+
+impl NetworkId {
+    pub fn from_entity(entity: Entity) -> Self {
+        Self(entity.to_bits())
+    }
+}
+
+/// Which local entity each [`NetworkId`] was spawned as on this peer.
+#[derive(Resource, Debug, Default)]
+pub struct NetworkEntities {
+    map: EntityMap,
+}
+
+impl NetworkEntities {
+    pub fn get(&self, id: NetworkId) -> Option<Entity> {
+        self.map.get_id(id.0)
+    }
+
+    pub fn insert(&mut self, id: NetworkId, entity: Entity) {
+        self.map.insert_id(id.0, entity);
+    }
+
+    /// Forgets a despawned entity.
+    pub fn remove(&mut self, id: NetworkId) -> Option<Entity> {
+        self.map.remove_id(id.0)
+    }
+
+    pub fn map(&self) -> &EntityMap {
+        &self.map
+    }
+}
+
+/// Records the [`NetworkId`] of each of `entities`, then remaps their references, which
+/// still hold server entities, to local ones. Call after spawning a snapshot's entities, e.g.
+/// on join; references to entities spawned by earlier snapshots resolve too.
+pub fn remap_network_entities(world: &mut World, entities: &[Entity]) {
+    let mut network = world
+        .remove_resource::<NetworkEntities>()
+        .unwrap_or_default();
+    for &entity in entities {
+        if let Some(&id) = world.get::<NetworkId>(entity) {
+            network.insert(id, entity);
+        }
+    }
+    remap_entities(world, entities, network.map());
+    world.insert_resource(network);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{Children, Parent};
+
+    #[test]
+    fn test_remap_into_populated_world() {
+        // Entities from the world the data was saved in
+        let mut source_world = World::new();
+        let source_parent = source_world.spawn_empty().id();
+        let source_child = source_world.spawn_empty().id();
+        let missing = source_world.spawn_empty().id();
+
+        let mut world = World::new();
+        world
+            .get_resource_or_init::<EntityRefRegistry>()
+            .register::<Parent>()
+            .register::<Children>();
+        // Occupies the same ids as the source entities
+        for _ in 0..4 {
+            world.spawn_empty();
+        }
+
+        let parent = world
+            .spawn(Children::with_children(vec![source_child, missing]))
+            .id();
+        let child = world.spawn(Parent(source_parent)).id();
+        let mut map = EntityMap::new();
+        map.insert(source_parent, parent);
+        map.insert(source_child, child);
+
+        remap_entities(&mut world, &[parent, child], &map);
+        assert_eq!(world.get::<Parent>(child), Some(&Parent(parent)));
+        assert_eq!(
+            world.get::<Children>(parent).unwrap().0,
+            vec![child, Entity::PLACEHOLDER]
+        );
+    }
+}
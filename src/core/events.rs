@@ -1,19 +1,20 @@
-/// Core event types for the Resonance engine
-///
-/// This module provides common message types that can be used throughout the engine.
-/// Messages are based on Bevy ECS's buffered message system.
-///
-/// # Example
-/// ```no_run
-/// use resonance::prelude::*;
-/// use resonance::core::events::WindowResized;
-///
-/// fn handle_resize(mut events: MessageReader<WindowResized>) {
-///     for event in events.read() {
-///         println!("Window resized to {}x{}", event.width, event.height);
-///     }
-/// }
-/// ```
+//! Core event types for the Resonance engine
+//!
+//! This module provides common message types that can be used throughout the engine.
+//! Messages are based on Bevy ECS's buffered message system.
+//!
+//! # Example
+//! ```no_run
+//! use resonance::prelude::*;
+//! use resonance::core::events::WindowResized;
+//! use bevy_ecs::prelude::MessageReader;
+//!
+//! fn handle_resize(mut events: MessageReader<WindowResized>) {
+//!     for event in events.read() {
+//!         println!("Window resized to {}x{}", event.width, event.height);
+//!     }
+//! }
+//! ```
 
 use bevy_ecs::prelude::*;
 
@@ -37,10 +38,21 @@ pub struct AssetLoaded {
     pub success: bool,
 }
 
+/// Warning fired when an asset failed to load and its placeholder stays in use
+#[derive(Message, Clone, Debug)]
+pub struct AssetFallbackUsed {
+    pub path: String,
+    pub error: String,
+}
+
 /// Message fired before the engine shuts down
 #[derive(Message, Clone, Copy, Debug)]
 pub struct EngineShutdown;
 
+/// Write to stop the engine at the end of the current frame
+#[derive(Message, Clone, Copy, Debug)]
+pub struct AppExit;
+
 /// Plugin that adds core event types to the engine
 #[derive(Default)]
 pub struct EventsPlugin;
@@ -52,7 +64,9 @@ impl crate::app::Plugin for EventsPlugin {
         engine.world.init_resource::<Messages<WindowResized>>();
         engine.world.init_resource::<Messages<WindowFocusChanged>>();
         engine.world.init_resource::<Messages<AssetLoaded>>();
+        engine.world.init_resource::<Messages<AssetFallbackUsed>>();
         engine.world.init_resource::<Messages<EngineShutdown>>();
+        engine.world.init_resource::<Messages<AppExit>>();
 
         // Add global message update system to clear old messages each frame
         // In bevy_ecs 0.17, message_update_system handles all message types automatically
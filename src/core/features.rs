@@ -0,0 +1,136 @@
+//! Runtime switches for experimental subsystems.
+//!
+//! Flags are loaded from a RON config file and/or the command line before plugins
+//! are added, so plugins can decide how to build themselves:
+//!
+//! ```no_run
+//! use resonance::prelude::*;
+//!
+//! let flags = FeatureFlags::load("features.ron").unwrap_or_default().with_args();
+//!
+//! Resonance::new()
+//!     .with_feature_flags(flags)
+//!     .add_plugin(DefaultPlugins)
+//!     .run();
+//! ```
+//!
+//! `features.ron` maps flag names to their state: `{ "gpu_culling": true }`. On the
+//! command line, `--feature <name>` enables a flag and `--no-feature <name>` disables it.
+
+use bevy_ecs::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Cull instances in a compute pass, see [`GraphicsSettings::set_gpu_culling`](crate::renderer::GraphicsSettings::set_gpu_culling).
+pub const GPU_CULLING: &str = "gpu_culling";
+/// Hi-Z occlusion culling on top of GPU culling.
+pub const OCCLUSION_CULLING: &str = "occlusion_culling";
+
+/// Named on/off switches consulted by plugins. Unknown flags are disabled.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureFlags {
+    flags: BTreeMap<String, bool>,
+}
+
+impl FeatureFlags {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path.as_ref(), contents)?;
+        Ok(())
+    }
+
+    /// Applies `--feature` and `--no-feature` from the process command line on top of these flags.
+    pub fn with_args(mut self) -> Self {
+        self.apply_args(std::env::args());
+        self
+    }
+
+    pub fn apply_args<I, S>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let enabled = match arg.as_ref() {
+                "--feature" => true,
+                "--no-feature" => false,
+                _ => continue,
+            };
+            match args.next() {
+                Some(name) => self.set(name.as_ref(), enabled),
+                None => log::warn!("{} expects a feature name", arg.as_ref()),
+            }
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.flags.get(name).copied().unwrap_or(false)
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, enabled: bool) {
+        self.flags.insert(name.into(), enabled);
+    }
+
+    pub fn enable(mut self, name: impl Into<String>) -> Self {
+        self.set(name, true);
+        self
+    }
+
+    pub fn disable(mut self, name: impl Into<String>) -> Self {
+        self.set(name, false);
+        self
+    }
+
+    /// Names of enabled flags, in alphabetical order.
+    pub fn active(&self) -> impl Iterator<Item = &str> {
+        self.flags
+            .iter()
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn log_active(&self) {
+        let active: Vec<&str> = self.active().collect();
+        if active.is_empty() {
+            log::info!("Feature flags: none enabled");
+        } else {
+            log::info!("Feature flags: {}", active.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_override_config() {
+        let mut flags: FeatureFlags =
+            ron::from_str(r#"{ "gpu_culling": true, "taa": true }"#).unwrap();
+        flags.apply_args([
+            "game",
+            "--no-feature",
+            "taa",
+            "--feature",
+            "prediction_v2",
+            "--feature",
+        ]);
+
+        assert!(flags.is_enabled(GPU_CULLING));
+        assert!(!flags.is_enabled("taa"));
+        assert!(flags.is_enabled("prediction_v2"));
+        assert!(!flags.is_enabled("unknown"));
+        assert_eq!(
+            flags.active().collect::<Vec<_>>(),
+            ["gpu_culling", "prediction_v2"]
+        );
+    }
+}
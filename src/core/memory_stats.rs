@@ -15,10 +15,12 @@ pub struct GpuMemoryStats {
     pub depth_textures: u64,
     pub ssao_textures: u64,
     pub msaa_textures: u64,
+    pub hdr_textures: u64,
     pub camera_buffer: u64,
     pub mesh_vertex_buffers: u64,
     pub mesh_index_buffers: u64,
     pub other_buffers: u64,
+    pub streamed_textures: u64,
 }
 
 impl GpuMemoryStats {
@@ -26,13 +28,26 @@ impl GpuMemoryStats {
         self.depth_textures
             + self.ssao_textures
             + self.msaa_textures
+            + self.hdr_textures
             + self.camera_buffer
             + self.mesh_vertex_buffers
             + self.mesh_index_buffers
             + self.other_buffers
+            + self.streamed_textures
     }
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextureStreamingStats {
+    pub resident_textures: u32,
+    /// Textures below the mip level their distance asks for, waiting on uploads or budget.
+    pub pending_textures: u32,
+    pub budget: u64,
+    pub uploaded_bytes_last_frame: u64,
+    /// Mip levels dropped to stay within the budget, since startup.
+    pub evicted_mips: u64,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct AssetMemoryStats {
     pub textures: u64,
@@ -54,6 +69,7 @@ pub struct MemoryTracker {
     pub gpu: GpuMemoryStats,
     pub assets: AssetMemoryStats,
     pub process: ProcessMemoryStats,
+    pub texture_streaming: TextureStreamingStats,
     mesh_sizes: Arc<DashMap<crate::assets::AssetId, (u64, u64)>>,
     system: sysinfo::System,
     last_process_update: Instant,
@@ -66,6 +82,7 @@ impl Default for MemoryTracker {
             gpu: Default::default(),
             assets: Default::default(),
             process: Default::default(),
+            texture_streaming: Default::default(),
             mesh_sizes: Arc::new(DashMap::new()),
             system: sysinfo::System::new(),
             last_process_update: Instant::now(),
@@ -91,10 +108,24 @@ impl MemoryTracker {
         self.gpu.msaa_textures = size;
     }
 
+    pub fn track_hdr_textures(&mut self, size: u64) {
+        self.gpu.hdr_textures = size;
+    }
+
     pub fn track_camera_buffer(&mut self, size: u64) {
         self.gpu.camera_buffer = size;
     }
 
+    /// `stats.evicted_mips` counts this frame's evictions and is added to the total.
+    pub fn track_texture_streaming(&mut self, resident_bytes: u64, stats: TextureStreamingStats) {
+        self.gpu.streamed_textures = resident_bytes;
+        let evicted_mips = self.texture_streaming.evicted_mips + stats.evicted_mips;
+        self.texture_streaming = TextureStreamingStats {
+            evicted_mips,
+            ..stats
+        };
+    }
+
     pub fn track_other_buffer(&mut self, size: u64) {
         self.gpu.other_buffers += size;
     }
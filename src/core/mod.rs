@@ -1,21 +1,45 @@
-pub mod error;
+pub mod determinism;
 pub mod egui_plugin;
+pub mod entity_map;
+pub mod error;
 pub mod events;
+pub mod features;
 pub mod logger;
 pub mod math;
 pub mod memory_stats;
+pub mod ownership;
 pub mod performance;
 pub mod profiler;
+pub mod replication;
 pub mod time;
 
+pub use determinism::{DeterminismAudit, SimRng};
 pub use egui_plugin::EguiContext;
+pub use entity_map::{
+    EntityMap, EntityRefRegistry, NetworkEntities, NetworkId, RemapEntities, remap_entities,
+    remap_network_entities,
+};
 pub use error::{ResonanceError, Result};
-pub use events::{EventsPlugin, WindowResized, WindowFocusChanged, AssetLoaded, EngineShutdown};
+pub use events::{
+    AppExit, AssetFallbackUsed, AssetLoaded, EngineShutdown, EventsPlugin, WindowFocusChanged,
+    WindowResized,
+};
+pub use features::FeatureFlags;
 pub use logger::{init_logger, init_logger_with_filter};
 pub use math::*;
-pub use memory_stats::{AssetMemoryStats, GpuMemoryStats, MemoryTracker, format_bytes};
-pub use performance::{PerformanceAnalytics, PerformancePlugin};
-pub use profiler::Profiler;
+pub use memory_stats::{
+    AssetMemoryStats, GpuMemoryStats, MemoryTracker, TextureStreamingStats, format_bytes,
+};
+pub use ownership::{InputAuthority, InputRejected, Owner, transfer_ownership, validate_input};
+pub use performance::{
+    Distribution, FrameTimeStats, HISTORY_SIZE, PerformanceAnalytics, PerformancePlugin,
+};
+pub use profiler::{BudgetExceeded, Profiler, profiler_budget_system};
+pub use replication::{
+    Authority, ComponentData, EntitySnapshot, Interpolation, ReplicationOptions,
+    ReplicationRegistry, ReplicationTarget, WorldSnapshot, apply_snapshot, interpolate_replicated,
+    snapshot, snapshot_for,
+};
 pub use time::{
     FixedTime, GameTick, Time, TimePlugin, fixed_time_system, game_tick_system, time_system,
 };
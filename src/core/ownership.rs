@@ -0,0 +1,133 @@
+//! Which client controls a networked entity.
+//!
+//! The server puts an [`Owner`] on entities a client drives, such as its player character or
+//! the vehicle it entered, and checks every input message with [`validate_input`] before
+//! applying it. Inputs for entities the client doesn't own are rejected, as are inputs any
+//! [`InputAuthority`] hook refuses:
+//!
+//! ```ignore
+//! world.get_resource_or_init::<InputAuthority>().add_hook("alive", |world, _, entity| {
+//!     world.get::<Health>(entity).is_some_and(|health| health.0 > 0)
+//! });
+//!
+//! for (client, input) in connection.receive_messages::<MoveInput>(channel) {
+//!     let entity = network_entities.get(input.entity)?;
+//!     if validate_input(&world, client, entity).is_ok() {
+//!         apply_move(&mut world, entity, input);
+//!     }
+//! }
+//! ```
+//!
+//! Register [`Owner`] in the [`ReplicationRegistry`](super::ReplicationRegistry) so clients
+//! learn which entities they control.
+
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Client id, as assigned by the server's transport, allowed to control this entity.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Owner(pub u64);
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InputRejected {
+    #[error("Entity no longer exists")]
+    NoEntity,
+    #[error("Client {0} doesn't own the entity")]
+    NotOwner(u64),
+    #[error("Rejected by input authority hook {0}")]
+    Hook(&'static str),
+}
+
+type AuthorityHook = Box<dyn Fn(&World, u64, Entity) -> bool + Send + Sync>;
+
+/// Extra server-side checks on inputs from an entity's owner, e.g. that the character is
+/// alive or not stunned.
+#[derive(Resource, Default)]
+pub struct InputAuthority {
+    hooks: Vec<(&'static str, AuthorityHook)>,
+}
+
+impl InputAuthority {
+    /// Adds a check taking the world, the sending client and the entity. Returning `false`
+    /// rejects the input.
+    pub fn add_hook(
+        &mut self,
+        name: &'static str,
+        hook: impl Fn(&World, u64, Entity) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hooks.push((name, Box::new(hook)));
+        self
+    }
+}
+
+/// Whether `client` may send inputs for `entity`: it must own the entity and pass every
+/// [`InputAuthority`] hook.
+pub fn validate_input(world: &World, client: u64, entity: Entity) -> Result<(), InputRejected> {
+    let Ok(entity_ref) = world.get_entity(entity) else {
+        return Err(InputRejected::NoEntity);
+    };
+    if entity_ref.get::<Owner>() != Some(&Owner(client)) {
+        return Err(InputRejected::NotOwner(client));
+    }
+    if let Some(authority) = world.get_resource::<InputAuthority>() {
+        for (name, hook) in &authority.hooks {
+            if !hook(world, client, entity) {
+                return Err(InputRejected::Hook(name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hands `entity` to `owner`, or to the server alone with `None`. Returns the previous owner.
+pub fn transfer_ownership(world: &mut World, entity: Entity, owner: Option<u64>) -> Option<u64> {
+    let mut entity = world.get_entity_mut(entity).ok()?;
+    let previous = entity.get::<Owner>().map(|owner| owner.0);
+    match owner {
+        Some(owner) => {
+            entity.insert(Owner(owner));
+        }
+        None => {
+            entity.remove::<Owner>();
+        }
+    }
+    previous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_owner_passes_validation() {
+        let mut world = World::new();
+        let vehicle = world.spawn_empty().id();
+        assert_eq!(
+            validate_input(&world, 1, vehicle),
+            Err(InputRejected::NotOwner(1))
+        );
+
+        assert_eq!(transfer_ownership(&mut world, vehicle, Some(1)), None);
+        assert_eq!(validate_input(&world, 1, vehicle), Ok(()));
+        assert_eq!(
+            validate_input(&world, 2, vehicle),
+            Err(InputRejected::NotOwner(2))
+        );
+
+        world
+            .get_resource_or_init::<InputAuthority>()
+            .add_hook("locked", |_, _, _| false);
+        assert_eq!(
+            validate_input(&world, 1, vehicle),
+            Err(InputRejected::Hook("locked"))
+        );
+
+        // Handing over to another client moves input authority with it
+        assert_eq!(transfer_ownership(&mut world, vehicle, Some(2)), Some(1));
+        assert_eq!(
+            validate_input(&world, 1, vehicle),
+            Err(InputRejected::NotOwner(1))
+        );
+    }
+}
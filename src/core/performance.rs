@@ -1,13 +1,108 @@
 use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 const SAMPLE_SIZE: usize = 120;
 const LOG_INTERVAL: Duration = Duration::from_secs(5);
 
+/// Frames kept for percentiles and frame-time graphs, about 16 seconds at 60 FPS.
+pub const HISTORY_SIZE: usize = 1000;
+
+/// Summary of a sample set, in milliseconds for frame times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+            sorted[rank.min(sorted.len() - 1)]
+        };
+
+        Self {
+            min: sorted[0],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Frame-time percentiles and lows over the [`PerformanceAnalytics`] history.
+///
+/// Averages hide stutter: a single 100ms hitch in a second of 5ms frames barely moves the
+/// average FPS, but shows up in the 1% and 0.1% lows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameTimeStats {
+    /// Frame times in milliseconds.
+    pub frame_time_ms: Distribution,
+    pub average_fps: f64,
+    /// FPS over the slowest 1% of frames.
+    pub low_1_percent_fps: f64,
+    /// FPS over the slowest 0.1% of frames. Needs 1000 frames to cover more than one frame.
+    pub low_0_1_percent_fps: f64,
+}
+
+impl FrameTimeStats {
+    pub fn from_frame_times<'a>(frame_times: impl IntoIterator<Item = &'a Duration>) -> Self {
+        let mut samples: Vec<f64> = frame_times
+            .into_iter()
+            .map(|frame_time| frame_time.as_secs_f64() * 1000.0)
+            .collect();
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let frame_time_ms = Distribution::from_samples(&samples);
+        // Slowest first
+        samples.sort_by(|a, b| b.total_cmp(a));
+        // FPS of the mean frame time of the slowest `fraction` of frames, at least one frame
+        let low = |fraction: f64| {
+            let count = ((samples.len() as f64 * fraction).ceil() as usize).max(1);
+            fps_from_ms(samples[..count].iter().sum::<f64>() / count as f64)
+        };
+
+        Self {
+            frame_time_ms,
+            average_fps: fps_from_ms(frame_time_ms.mean),
+            low_1_percent_fps: low(0.01),
+            low_0_1_percent_fps: low(0.001),
+        }
+    }
+}
+
+fn fps_from_ms(frame_time_ms: f64) -> f64 {
+    if frame_time_ms > 0.0 {
+        1000.0 / frame_time_ms
+    } else {
+        0.0
+    }
+}
+
 #[derive(Resource)]
 pub struct PerformanceAnalytics {
     frame_times: VecDeque<Duration>,
+    /// Longer ring than `frame_times` for percentiles and graphs.
+    history: VecDeque<Duration>,
     last_frame_start: Instant,
     current_frame_start: Instant,
     last_log_time: Instant,
@@ -25,6 +120,7 @@ impl PerformanceAnalytics {
         let now = Instant::now();
         Self {
             frame_times: VecDeque::with_capacity(SAMPLE_SIZE),
+            history: VecDeque::with_capacity(HISTORY_SIZE),
             last_frame_start: now,
             current_frame_start: now,
             last_log_time: now,
@@ -44,6 +140,11 @@ impl PerformanceAnalytics {
         }
         self.frame_times.push_back(frame_time);
 
+        if self.history.len() >= HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(frame_time);
+
         self.last_frame_start = self.current_frame_start;
         self.total_frames += 1;
     }
@@ -53,46 +154,37 @@ impl PerformanceAnalytics {
     }
 
     pub fn log_analytics(&mut self) {
-        if self.frame_times.is_empty() {
+        if self.history.is_empty() {
             return;
         }
 
-        let avg_frame_time = self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32;
-        let min_frame_time = self.frame_times.iter().min().copied().unwrap_or(Duration::ZERO);
-        let max_frame_time = self.frame_times.iter().max().copied().unwrap_or(Duration::ZERO);
-
-        let avg_fps = if avg_frame_time.as_secs_f64() > 0.0 {
-            1.0 / avg_frame_time.as_secs_f64()
-        } else {
-            0.0
-        };
-
-        let min_fps = if max_frame_time.as_secs_f64() > 0.0 {
-            1.0 / max_frame_time.as_secs_f64()
-        } else {
-            0.0
-        };
-
-        let max_fps = if min_frame_time.as_secs_f64() > 0.0 {
-            1.0 / min_frame_time.as_secs_f64()
-        } else {
-            0.0
-        };
-
+        let stats = self.frame_time_stats();
+        let frame_time = stats.frame_time_ms;
         log::info!(
-            "Performance: FPS: {:.1} (min: {:.1}, max: {:.1}) | Frame Time: {:.2}ms (min: {:.2}ms, max: {:.2}ms) | Total Frames: {}",
-            avg_fps,
-            min_fps,
-            max_fps,
-            avg_frame_time.as_secs_f64() * 1000.0,
-            min_frame_time.as_secs_f64() * 1000.0,
-            max_frame_time.as_secs_f64() * 1000.0,
+            "Performance: FPS: {:.1} (1% low: {:.1}, 0.1% low: {:.1}) | Frame Time: p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms, max {:.2}ms | Total Frames: {}",
+            stats.average_fps,
+            stats.low_1_percent_fps,
+            stats.low_0_1_percent_fps,
+            frame_time.p50,
+            frame_time.p95,
+            frame_time.p99,
+            frame_time.max,
             self.total_frames
         );
 
         self.last_log_time = Instant::now();
     }
 
+    /// Percentiles and lows over the last [`HISTORY_SIZE`] frames.
+    pub fn frame_time_stats(&self) -> FrameTimeStats {
+        FrameTimeStats::from_frame_times(&self.history)
+    }
+
+    /// The last [`HISTORY_SIZE`] frame times, oldest first, e.g. for a frame-time graph.
+    pub fn history(&self) -> &VecDeque<Duration> {
+        &self.history
+    }
+
     pub fn fps(&self) -> f64 {
         if self.frame_times.is_empty() {
             return 0.0;
@@ -152,11 +244,17 @@ pub fn begin_frame_system(mut analytics: ResMut<PerformanceAnalytics>) {
     analytics.begin_frame();
 }
 
-pub fn end_frame_system(mut analytics: ResMut<PerformanceAnalytics>) {
+pub fn end_frame_system(
+    mut analytics: ResMut<PerformanceAnalytics>,
+    render_stats: Option<Res<crate::renderer::RenderStats>>,
+) {
     analytics.end_frame();
 
     if analytics.should_log() {
         analytics.log_analytics();
+        if let Some(render_stats) = render_stats {
+            log::info!("Render: {}", render_stats.as_ref());
+        }
     }
 }
 
@@ -166,13 +264,16 @@ pub struct PerformancePlugin;
 impl crate::app::Plugin for PerformancePlugin {
     fn build(&self, engine: &mut crate::app::Resonance) {
         engine.world.insert_resource(PerformanceAnalytics::new());
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<crate::core::BudgetExceeded>>();
 
         if let Some(schedule) = engine.schedules.get_mut(crate::app::Stage::PreUpdate) {
             schedule.add_systems(begin_frame_system);
         }
 
         if let Some(schedule) = engine.schedules.get_mut(crate::app::Stage::Last) {
-            schedule.add_systems(end_frame_system);
+            schedule.add_systems((end_frame_system, crate::core::profiler_budget_system));
         }
     }
 
@@ -180,3 +281,33 @@ impl crate::app::Plugin for PerformancePlugin {
         "PerformancePlugin"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_percentiles() {
+        let samples: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        let distribution = Distribution::from_samples(&samples);
+
+        assert_eq!(distribution.min, 1.0);
+        assert_eq!(distribution.max, 100.0);
+        assert_eq!(distribution.mean, 50.5);
+        assert_eq!(distribution.p50, 51.0);
+        assert_eq!(distribution.p99, 99.0);
+        assert_eq!(Distribution::from_samples(&[]), Distribution::default());
+    }
+
+    #[test]
+    fn test_lows_catch_stutter() {
+        let mut frame_times = vec![Duration::from_millis(5); 999];
+        frame_times.push(Duration::from_millis(100));
+
+        let stats = FrameTimeStats::from_frame_times(&frame_times);
+        assert!(stats.average_fps > 180.0);
+        assert!((stats.low_0_1_percent_fps - 10.0).abs() < 1e-6);
+        // Ten slowest frames: one hitch and nine normal frames
+        assert!((stats.low_1_percent_fps - 1000.0 / 14.5).abs() < 1e-6);
+    }
+}
@@ -1,15 +1,169 @@
-// Profiler stub - profiling functionality removed
-use bevy_ecs::prelude::Resource;
+//! Named timings with optional budgets.
+//!
+//! The runner records each stage under its name (`"PostUpdate"`) and engine systems record
+//! themselves as `Stage::system` (`"PostUpdate::prepare_indirect_draw_data"`) or under a
+//! subsystem prefix (`"Render::Present"`). A budget set on any of these names emits a
+//! [`BudgetExceeded`] message every frame the timing goes over it, and logs a warning when
+//! it first does, so regressions show up during development.
+//!
+//! Timings are only recorded while a `Profiler` resource exists:
+//!
+//! ```ignore
+//! Resonance::new().with_resource(
+//!     Profiler::default()
+//!         .with_budget("PostUpdate", Duration::from_millis(4))
+//!         .with_budget("PostUpdate::prepare_indirect_draw_data", Duration::from_millis(2)),
+//! )
+//! ```
+
+use bevy_ecs::message::Messages;
+use bevy_ecs::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Overruns kept until [`profiler_budget_system`] sends them, so nothing piles up without it.
+const MAX_PENDING_OVERRUNS: usize = 256;
+
+/// A recorded timing went over its budget.
+#[derive(Message, Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub name: String,
+    pub duration: Duration,
+    pub budget: Duration,
+}
 
 #[derive(Resource, Default)]
-pub struct Profiler;
+pub struct Profiler {
+    /// Most recent timing of every name.
+    timings: HashMap<String, Duration>,
+    budgets: HashMap<String, Duration>,
+    /// Names whose latest timing is over budget.
+    over_budget: HashSet<String>,
+    exceeded: Vec<BudgetExceeded>,
+}
 
 impl Profiler {
-    pub fn record_timing(&mut self, _name: &str, _duration: std::time::Duration) {
-        // No-op
+    pub fn with_budget(mut self, name: impl Into<String>, budget: Duration) -> Self {
+        self.set_budget(name, budget);
+        self
+    }
+
+    pub fn set_budget(&mut self, name: impl Into<String>, budget: Duration) {
+        self.budgets.insert(name.into(), budget);
+    }
+
+    pub fn remove_budget(&mut self, name: &str) {
+        self.budgets.remove(name);
+        self.over_budget.remove(name);
+    }
+
+    pub fn budget(&self, name: &str) -> Option<Duration> {
+        self.budgets.get(name).copied()
+    }
+
+    pub fn record_timing(&mut self, name: &str, duration: Duration) {
+        match self.timings.get_mut(name) {
+            Some(timing) => *timing = duration,
+            None => {
+                self.timings.insert(name.to_string(), duration);
+            }
+        }
+        self.check_budget(name, duration);
+    }
+
+    pub fn record_timing_owned(&mut self, name: &str, duration: Duration) {
+        self.record_timing(name, duration);
+    }
+
+    fn check_budget(&mut self, name: &str, duration: Duration) {
+        let Some(&budget) = self.budgets.get(name) else {
+            return;
+        };
+
+        if duration <= budget {
+            self.over_budget.remove(name);
+            return;
+        }
+
+        if self.over_budget.insert(name.to_string()) {
+            log::warn!(
+                "{} took {:.2}ms, over its {:.2}ms budget",
+                name,
+                duration.as_secs_f64() * 1000.0,
+                budget.as_secs_f64() * 1000.0
+            );
+        }
+        if self.exceeded.len() < MAX_PENDING_OVERRUNS {
+            self.exceeded.push(BudgetExceeded {
+                name: name.to_string(),
+                duration,
+                budget,
+            });
+        }
     }
 
-    pub fn record_timing_owned(&mut self, _name: &str, _duration: std::time::Duration) {
-        // No-op
+    pub fn timing(&self, name: &str) -> Option<Duration> {
+        self.timings.get(name).copied()
+    }
+
+    /// Latest timing of every recorded name, in no particular order.
+    pub fn timings(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.timings
+            .iter()
+            .map(|(name, duration)| (name.as_str(), *duration))
+    }
+
+    /// Whether the latest timing of `name` was over its budget, e.g. to highlight it in an overlay.
+    pub fn is_over_budget(&self, name: &str) -> bool {
+        self.over_budget.contains(name)
+    }
+
+    fn drain_exceeded(&mut self) -> std::vec::Drain<'_, BudgetExceeded> {
+        self.exceeded.drain(..)
+    }
+}
+
+/// Sends the budget overruns recorded since the last run as [`BudgetExceeded`] messages.
+pub fn profiler_budget_system(world: &mut World) {
+    let Some(mut profiler) = world.get_resource_mut::<Profiler>() else {
+        return;
+    };
+    let exceeded: Vec<BudgetExceeded> = profiler.drain_exceeded().collect();
+    if exceeded.is_empty() {
+        return;
+    }
+
+    if let Some(mut messages) = world.get_resource_mut::<Messages<BudgetExceeded>>() {
+        for message in exceeded {
+            messages.write(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_exceeded() {
+        let mut profiler = Profiler::default().with_budget("PostUpdate", Duration::from_millis(4));
+
+        profiler.record_timing("PostUpdate", Duration::from_millis(3));
+        profiler.record_timing("Update", Duration::from_millis(10));
+        assert!(!profiler.is_over_budget("PostUpdate"));
+        assert_eq!(profiler.drain_exceeded().count(), 0);
+
+        profiler.record_timing("PostUpdate", Duration::from_millis(6));
+        assert!(profiler.is_over_budget("PostUpdate"));
+        assert_eq!(
+            profiler.timing("PostUpdate"),
+            Some(Duration::from_millis(6))
+        );
+        let exceeded: Vec<_> = profiler.drain_exceeded().collect();
+        assert_eq!(exceeded.len(), 1);
+        assert_eq!(exceeded[0].budget, Duration::from_millis(4));
+
+        profiler.record_timing("PostUpdate", Duration::from_millis(2));
+        assert!(!profiler.is_over_budget("PostUpdate"));
     }
 }
@@ -0,0 +1,560 @@
+//! Generic snapshots of networked entities.
+//!
+//! Components registered in the [`ReplicationRegistry`] are captured from every entity with a
+//! [`NetworkId`] by [`snapshot`] and written back on the other peer by [`apply_snapshot`].
+//! Registering a component is all it takes to replicate it; neither side needs a hand-written
+//! message for it:
+//!
+//! ```ignore
+//! // On both server and client, for any serde component
+//! world
+//!     .get_resource_or_init::<ReplicationRegistry>()
+//!     .register::<Transform>()
+//!     .register::<Health>();
+//!
+//! // Server, every snapshot tick
+//! let bytes = snapshot(&world)?.to_bytes()?;
+//! // Client
+//! apply_snapshot(&mut world, &WorldSnapshot::from_bytes(&bytes)?)?;
+//! ```
+//!
+//! Components are identified on the wire by a hash of their type name, so both peers must be
+//! built from the same source but may register in any order.
+//!
+//! [`ReplicationRegistry::register_with`] takes [`ReplicationOptions`] for components that
+//! shouldn't simply snap to the server's value. With [`Authority::Owner`], the server leaves
+//! the component out of [`snapshot_for`] its [`Owner`], whose client predicts it locally. With
+//! an [`Interpolation`], clients blend towards received values through [`ReplicationTarget`],
+//! which [`interpolate_replicated`] advances every frame:
+//!
+//! ```ignore
+//! registry.register_with::<Transform>(
+//!     ReplicationOptions::default()
+//!         .with_authority(Authority::Owner)
+//!         .with_interpolation(lerp_transform, SNAPSHOT_INTERVAL),
+//! );
+//! engine.add_systems(Stage::Update, interpolate_replicated::<Transform>);
+//! ```
+//!
+//! Components are bincode-encoded by default. [`ReplicationOptions::with_encoding`] swaps in a
+//! bit-packed format, such as the quantized [`write_transform`](crate::net::serialization::write_transform),
+//! for components that dominate snapshot size.
+
+use super::entity_map::{NetworkEntities, NetworkId, remap_network_entities};
+use super::ownership::Owner;
+use super::time::Time;
+use crate::net::serialization::{BitReader, BitWriter};
+use bevy_ecs::component::Mutable;
+use bevy_ecs::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Serialized value of one registered component.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentData {
+    /// [`ReplicationRegistry::id_of`] the component.
+    pub id: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub network_id: NetworkId,
+    pub components: Vec<ComponentData>,
+}
+
+/// Registered components of every networked entity, ordered by [`NetworkId`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub entities: Vec<EntitySnapshot>,
+}
+
+impl WorldSnapshot {
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serde::encode_to_vec(
+            self,
+            bincode::config::standard(),
+        )?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(bincode::serde::decode_from_slice(bytes, bincode::config::standard())?.0)
+    }
+}
+
+/// Which peer decides the value of a replicated component.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Authority {
+    /// The server; every client takes the value from snapshots.
+    #[default]
+    Server,
+    /// The entity's [`Owner`], which predicts it locally. [`snapshot_for`] leaves the component
+    /// out of the owner's snapshots so they don't overwrite the prediction; other clients and
+    /// entities without an owner still receive it.
+    Owner,
+}
+
+/// Blending from the current value to a received one, set with
+/// [`ReplicationOptions::with_interpolation`].
+pub struct Interpolation<T> {
+    lerp: fn(&T, &T, f32) -> T,
+    duration: f32,
+    clone: fn(&T) -> T,
+}
+
+/// Bit-packed wire format of a component, set with [`ReplicationOptions::with_encoding`].
+pub struct Encoding<T> {
+    write: fn(&mut BitWriter, &T),
+    read: fn(&mut BitReader) -> anyhow::Result<T>,
+}
+
+impl<T> Clone for Encoding<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Encoding<T> {}
+
+/// How [`ReplicationRegistry::register_with`] replicates a component.
+pub struct ReplicationOptions<T> {
+    pub authority: Authority,
+    /// `None` snaps to every received value.
+    pub interpolation: Option<Interpolation<T>>,
+    /// `None` sends the component bincode-encoded.
+    pub encoding: Option<Encoding<T>>,
+}
+
+impl<T> Default for ReplicationOptions<T> {
+    fn default() -> Self {
+        Self {
+            authority: Authority::Server,
+            interpolation: None,
+            encoding: None,
+        }
+    }
+}
+
+impl<T> ReplicationOptions<T> {
+    pub fn with_authority(mut self, authority: Authority) -> Self {
+        self.authority = authority;
+        self
+    }
+
+    /// Blends received values in over `duration` seconds, usually the snapshot interval.
+    /// `lerp` returns the value the given fraction of the way from its first argument to
+    /// its second.
+    pub fn with_interpolation(mut self, lerp: fn(&T, &T, f32) -> T, duration: f32) -> Self
+    where
+        T: Clone,
+    {
+        self.interpolation = Some(Interpolation {
+            lerp,
+            duration,
+            clone: T::clone,
+        });
+        self
+    }
+
+    /// Sends the component as `write` packs it instead of bincode-encoded. Both peers must
+    /// register the same encoding.
+    pub fn with_encoding(
+        mut self,
+        write: fn(&mut BitWriter, &T),
+        read: fn(&mut BitReader) -> anyhow::Result<T>,
+    ) -> Self {
+        self.encoding = Some(Encoding { write, read });
+        self
+    }
+}
+
+/// Received value an interpolated component is blending towards. Removed by
+/// [`interpolate_replicated`] once the value is reached.
+#[derive(Component)]
+pub struct ReplicationTarget<T: Send + Sync + 'static> {
+    from: T,
+    to: T,
+    elapsed: f32,
+    duration: f32,
+    lerp: fn(&T, &T, f32) -> T,
+}
+
+impl<T: Send + Sync + 'static> ReplicationTarget<T> {
+    /// Moves `seconds` further towards the received value, returning the blended value and
+    /// whether it was reached.
+    pub fn advance(&mut self, seconds: f32) -> (T, bool) {
+        self.elapsed += seconds;
+        let fraction = (self.elapsed / self.duration).min(1.0);
+        ((self.lerp)(&self.from, &self.to, fraction), fraction >= 1.0)
+    }
+}
+
+type WriteFn = Arc<dyn Fn(&World, Entity) -> Option<anyhow::Result<Vec<u8>>> + Send + Sync>;
+type ApplyFn = Arc<dyn Fn(&mut World, Entity, &[u8]) -> anyhow::Result<()> + Send + Sync>;
+
+#[derive(Clone)]
+struct Registration {
+    name: &'static str,
+    id: u64,
+    authority: Authority,
+    write: WriteFn,
+    apply: ApplyFn,
+}
+
+/// Components captured by [`snapshot`] and restored by [`apply_snapshot`].
+#[derive(Resource, Default, Clone)]
+pub struct ReplicationRegistry {
+    components: Vec<Registration>,
+}
+
+impl ReplicationRegistry {
+    /// Registers `T` with the default options: server authority, snapping to received values.
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self) -> &mut Self {
+        self.register_with::<T>(ReplicationOptions::default())
+    }
+
+    /// Registers `T` replicated as `options` describe. Registering a component again keeps its
+    /// first options.
+    pub fn register_with<T: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        options: ReplicationOptions<T>,
+    ) -> &mut Self {
+        let id = Self::id_of::<T>();
+        if self.components.iter().any(|registered| registered.id == id) {
+            return self;
+        }
+
+        let encoding = options.encoding;
+        let write: WriteFn = Arc::new(move |world: &World, entity| {
+            let component = world.get::<T>(entity)?;
+            Some(encode(component, encoding))
+        });
+        let apply: ApplyFn = match options.interpolation {
+            Some(interpolation) => Arc::new(move |world: &mut World, entity, bytes: &[u8]| {
+                apply_interpolated(world, entity, decode(bytes, encoding)?, &interpolation);
+                Ok(())
+            }),
+            None => Arc::new(move |world: &mut World, entity, bytes: &[u8]| {
+                world
+                    .entity_mut(entity)
+                    .insert(decode::<T>(bytes, encoding)?);
+                Ok(())
+            }),
+        };
+        self.components.push(Registration {
+            name: std::any::type_name::<T>(),
+            id,
+            authority: options.authority,
+            write,
+            apply,
+        });
+        self
+    }
+
+    pub fn is_registered<T: Component>(&self) -> bool {
+        let id = Self::id_of::<T>();
+        self.components.iter().any(|registered| registered.id == id)
+    }
+
+    /// Wire id of `T`: FNV-1a of its type name, the same on every peer built from the same
+    /// source.
+    pub fn id_of<T: 'static>() -> u64 {
+        std::any::type_name::<T>()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Type names of the registered components, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.components.iter().map(|registered| registered.name)
+    }
+}
+
+fn encode<T: Serialize>(component: &T, encoding: Option<Encoding<T>>) -> anyhow::Result<Vec<u8>> {
+    match encoding {
+        Some(encoding) => {
+            let mut writer = BitWriter::new();
+            (encoding.write)(&mut writer, component);
+            Ok(writer.finish())
+        }
+        None => Ok(bincode::serde::encode_to_vec(
+            component,
+            bincode::config::standard(),
+        )?),
+    }
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8], encoding: Option<Encoding<T>>) -> anyhow::Result<T> {
+    match encoding {
+        Some(encoding) => (encoding.read)(&mut BitReader::new(bytes)),
+        None => Ok(bincode::serde::decode_from_slice(bytes, bincode::config::standard())?.0),
+    }
+}
+
+/// Starts blending towards the received value, or snaps to it if the entity doesn't have the
+/// component yet.
+fn apply_interpolated<T: Component>(
+    world: &mut World,
+    entity: Entity,
+    component: T,
+    interpolation: &Interpolation<T>,
+) {
+    let mut entity = world.entity_mut(entity);
+    match entity.get::<T>() {
+        Some(current) if interpolation.duration > 0.0 => {
+            // The blend starts from what is shown now, which may be partway to an older value
+            let target = ReplicationTarget {
+                from: (interpolation.clone)(current),
+                to: component,
+                elapsed: 0.0,
+                duration: interpolation.duration,
+                lerp: interpolation.lerp,
+            };
+            entity.insert(target);
+        }
+        _ => {
+            entity.insert(component);
+        }
+    }
+}
+
+/// Blends components registered with an [`Interpolation`] towards their received values.
+pub fn interpolate_replicated<T: Component<Mutability = Mutable>>(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut T, &mut ReplicationTarget<T>)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut component, mut target) in &mut query {
+        let (value, reached) = target.advance(delta);
+        *component = value;
+        if reached {
+            commands.entity(entity).remove::<ReplicationTarget<T>>();
+        }
+    }
+}
+
+/// Captures the registered components of every entity with a [`NetworkId`].
+pub fn snapshot(world: &World) -> anyhow::Result<WorldSnapshot> {
+    capture(world, None)
+}
+
+/// Like [`snapshot`], but for sending to `client`: components with [`Authority::Owner`] are
+/// left out on entities that `client` owns.
+pub fn snapshot_for(world: &World, client: u64) -> anyhow::Result<WorldSnapshot> {
+    capture(world, Some(client))
+}
+
+fn capture(world: &World, recipient: Option<u64>) -> anyhow::Result<WorldSnapshot> {
+    let Some(registry) = world.get_resource::<ReplicationRegistry>() else {
+        return Ok(WorldSnapshot::default());
+    };
+    let Some(mut query) = world.try_query::<(Entity, &NetworkId)>() else {
+        return Ok(WorldSnapshot::default());
+    };
+
+    let mut networked: Vec<(Entity, NetworkId)> = query
+        .iter(world)
+        .map(|(entity, network_id)| (entity, *network_id))
+        .collect();
+    networked.sort_by_key(|(_, network_id)| network_id.0);
+
+    let mut entities = Vec::with_capacity(networked.len());
+    for (entity, network_id) in networked {
+        let owner = world.get::<Owner>(entity).map(|owner| owner.0);
+        let mut components = Vec::new();
+        for registered in &registry.components {
+            if registered.authority == Authority::Owner && owner.is_some() && owner == recipient {
+                continue;
+            }
+            if let Some(bytes) = (registered.write)(world, entity) {
+                components.push(ComponentData {
+                    id: registered.id,
+                    bytes: bytes?,
+                });
+            }
+        }
+        entities.push(EntitySnapshot {
+            network_id,
+            components,
+        });
+    }
+    Ok(WorldSnapshot { entities })
+}
+
+/// Writes `snapshot` into the world, spawning entities for unseen [`NetworkId`]s and
+/// remapping entity references. Entities missing from the snapshot are left alone, and
+/// components with unknown ids are skipped. Returns the entities that were spawned.
+pub fn apply_snapshot(world: &mut World, snapshot: &WorldSnapshot) -> anyhow::Result<Vec<Entity>> {
+    let registry = world
+        .get_resource::<ReplicationRegistry>()
+        .cloned()
+        .unwrap_or_default();
+
+    let mut spawned = Vec::new();
+    let mut updated = Vec::with_capacity(snapshot.entities.len());
+    for entity_snapshot in &snapshot.entities {
+        let existing = world
+            .get_resource::<NetworkEntities>()
+            .and_then(|network| network.get(entity_snapshot.network_id))
+            .filter(|&entity| world.get_entity(entity).is_ok());
+        let entity = match existing {
+            Some(entity) => entity,
+            None => {
+                let entity = world.spawn(entity_snapshot.network_id).id();
+                spawned.push(entity);
+                entity
+            }
+        };
+
+        for data in &entity_snapshot.components {
+            match registry
+                .components
+                .iter()
+                .find(|registered| registered.id == data.id)
+            {
+                Some(registered) => (registered.apply)(world, entity, &data.bytes)?,
+                None => log::warn!("Skipping unregistered replicated component {:#x}", data.id),
+            }
+        }
+        updated.push(entity);
+    }
+
+    remap_network_entities(world, &updated);
+    Ok(spawned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut server = World::new();
+        server
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register::<Transform>();
+        let networked = server.spawn_empty().id();
+        server.entity_mut(networked).insert((
+            NetworkId::from_entity(networked),
+            Transform::from_xyz(1.0, 2.0, 3.0),
+        ));
+        // Not networked, so not replicated
+        server.spawn(Transform::default());
+
+        let bytes = snapshot(&server).unwrap().to_bytes().unwrap();
+
+        let mut client = World::new();
+        client
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register::<Transform>();
+        let snapshot = WorldSnapshot::from_bytes(&bytes).unwrap();
+        let spawned = apply_snapshot(&mut client, &snapshot).unwrap();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(
+            client.get::<Transform>(spawned[0]).unwrap().position,
+            crate::core::math::Vec3::new(1.0, 2.0, 3.0)
+        );
+
+        // Applying again updates the same entity
+        assert!(apply_snapshot(&mut client, &snapshot).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quantized_encoding_shrinks_snapshot() {
+        use crate::net::serialization::{read_transform, write_transform};
+
+        let capture = |options: ReplicationOptions<Transform>| {
+            let mut world = World::new();
+            world
+                .get_resource_or_init::<ReplicationRegistry>()
+                .register_with::<Transform>(options);
+            let entity = world.spawn_empty().id();
+            world.entity_mut(entity).insert((
+                NetworkId::from_entity(entity),
+                Transform::from_xyz(12.345, -6.5, 300.0),
+            ));
+            snapshot(&world).unwrap().to_bytes().unwrap()
+        };
+
+        // 93 bits instead of ten raw f32s
+        let raw_bytes = capture(ReplicationOptions::default());
+        let bytes =
+            capture(ReplicationOptions::default().with_encoding(write_transform, read_transform));
+        assert_eq!(raw_bytes.len() - bytes.len(), 40 - 12);
+
+        let mut client = World::new();
+        client
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register_with::<Transform>(
+                ReplicationOptions::default().with_encoding(write_transform, read_transform),
+            );
+        let spawned =
+            apply_snapshot(&mut client, &WorldSnapshot::from_bytes(&bytes).unwrap()).unwrap();
+        let transform = client.get::<Transform>(spawned[0]).unwrap();
+        assert!(
+            transform
+                .position
+                .abs_diff_eq(crate::core::math::Vec3::new(12.345, -6.5, 300.0), 0.01)
+        );
+        assert_eq!(transform.scale, crate::core::math::Vec3::ONE);
+    }
+
+    fn lerp_transform(from: &Transform, to: &Transform, fraction: f32) -> Transform {
+        Transform {
+            position: from.position.lerp(to.position, fraction),
+            rotation: from.rotation.slerp(to.rotation, fraction),
+            scale: from.scale.lerp(to.scale, fraction),
+        }
+    }
+
+    #[test]
+    fn test_owner_authority_and_interpolation() {
+        let options = || {
+            ReplicationOptions::default()
+                .with_authority(Authority::Owner)
+                .with_interpolation(lerp_transform, 0.1)
+        };
+        let mut server = World::new();
+        server
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register_with::<Transform>(options());
+        let player = server.spawn_empty().id();
+        server.entity_mut(player).insert((
+            NetworkId::from_entity(player),
+            Owner(1),
+            Transform::from_xyz(0.0, 0.0, 0.0),
+        ));
+
+        // The owner predicts its own transform, everyone else receives it
+        let for_owner = snapshot_for(&server, 1).unwrap();
+        let for_other = snapshot_for(&server, 2).unwrap();
+        assert!(for_owner.entities[0].components.is_empty());
+        assert_eq!(for_other.entities[0].components.len(), 1);
+
+        let mut client = World::new();
+        client
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register_with::<Transform>(options());
+        let spawned = apply_snapshot(&mut client, &for_other).unwrap();
+        let entity = spawned[0];
+        // First value snaps, later ones blend in
+        assert!(client.get::<ReplicationTarget<Transform>>(entity).is_none());
+
+        server.get_mut::<Transform>(player).unwrap().position.x = 10.0;
+        apply_snapshot(&mut client, &snapshot_for(&server, 2).unwrap()).unwrap();
+        assert_eq!(client.get::<Transform>(entity).unwrap().position.x, 0.0);
+
+        let mut target = client
+            .get_mut::<ReplicationTarget<Transform>>(entity)
+            .unwrap();
+        let (halfway, reached) = target.advance(0.05);
+        assert!(!reached && (halfway.position.x - 5.0).abs() < 1e-4);
+        let (arrived, reached) = target.advance(0.05);
+        assert!(reached && arrived.position.x == 10.0);
+    }
+}
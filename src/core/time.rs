@@ -1,3 +1,4 @@
+use super::determinism::{NondeterminismSource, record};
 use bevy_ecs::prelude::*;
 use std::time::{Duration, Instant};
 
@@ -8,6 +9,8 @@ pub struct Time {
     delta: Duration,
     time_scale: f32,
     paused: bool,
+    /// Id of the [`DeterminismAudit`](super::DeterminismAudit) watching this resource.
+    pub(crate) audit: Option<u64>,
 }
 
 impl Time {
@@ -19,6 +22,7 @@ impl Time {
             delta: Duration::ZERO,
             time_scale: 1.0,
             paused: false,
+            audit: None,
         }
     }
 
@@ -32,19 +36,29 @@ impl Time {
         }
     }
 
+    // Wall-clock reads are reported by the determinism audit when made during a fixed step.
+
+    #[track_caller]
     pub fn elapsed(&self) -> Duration {
+        record(self.audit, NondeterminismSource::WallClock);
         self.startup.elapsed()
     }
 
+    #[track_caller]
     pub fn elapsed_seconds(&self) -> f32 {
-        self.elapsed().as_secs_f32()
+        record(self.audit, NondeterminismSource::WallClock);
+        self.startup.elapsed().as_secs_f32()
     }
 
+    #[track_caller]
     pub fn delta(&self) -> Duration {
+        record(self.audit, NondeterminismSource::WallClock);
         self.delta
     }
 
+    #[track_caller]
     pub fn delta_seconds(&self) -> f32 {
+        record(self.audit, NondeterminismSource::WallClock);
         if self.paused {
             0.0
         } else {
@@ -25,6 +25,10 @@ impl KeyboardState {
         self.just_released.contains(&key)
     }
 
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.pressed.iter().copied()
+    }
+
     pub fn press(&mut self, key: KeyCode) {
         if self.pressed.insert(key) {
             self.just_pressed.insert(key);
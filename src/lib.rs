@@ -5,6 +5,7 @@ pub mod audio;
 pub mod build_utils;
 pub mod core;
 pub mod input;
+pub mod net;
 pub mod prelude;
 pub mod renderer;
 pub mod transform;
@@ -0,0 +1,154 @@
+//! Pluggable client authentication
+//!
+//! Clients attach an opaque auth token, such as a session ticket from the game's
+//! account service, to the netcode user data of their connection request. Once a
+//! client passes the version handshake the server hands the token to its
+//! [`Authenticator`] and keeps the client out of the game until a decision comes
+//! back. Refused clients receive an [`AuthRejection`] describing why.
+//!
+//! Requests go out from
+//! [`ServerConnection::authenticate_clients`](super::connection::ServerConnection::authenticate_clients),
+//! which reads the tokens from the transport and must run every tick.
+//!
+//! Decisions can be immediate or arrive later from another task, so the check can
+//! call out to an account service without blocking the server tick:
+//!
+//! ```ignore
+//! server.set_authenticator(move |request| {
+//!     let (ticket, responder) = AuthTicket::pending();
+//!     let accounts = accounts.clone();
+//!     runtime.spawn(async move {
+//!         match accounts.verify_session(&request.token).await {
+//!             Ok(_) => responder.accept(),
+//!             Err(_) => responder.reject(AuthRejection::new(401, "session expired")),
+//!         }
+//!     });
+//!     ticket
+//! });
+//! ```
+
+use renet::ClientId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use super::handshake::HandshakeInfo;
+
+/// Structured reason sent to a client the authenticator refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthRejection {
+    /// Game-defined code clients can branch on, e.g. banned vs. expired session
+    pub code: u16,
+    /// Human readable explanation
+    pub reason: String,
+}
+
+impl AuthRejection {
+    pub fn new(code: u16, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.reason, self.code)
+    }
+}
+
+/// What the authenticator gets to decide on
+#[derive(Debug, Clone)]
+pub struct AuthRequest {
+    pub client_id: ClientId,
+    pub handshake: HandshakeInfo,
+    /// Token from the client's user data, empty if it sent none
+    pub token: Vec<u8>,
+}
+
+/// Outcome of an authentication check, immediate or still in flight
+pub struct AuthTicket {
+    state: TicketState,
+}
+
+enum TicketState {
+    Ready(Result<(), AuthRejection>),
+    Pending(oneshot::Receiver<Result<(), AuthRejection>>),
+}
+
+impl AuthTicket {
+    pub fn accept() -> Self {
+        Self {
+            state: TicketState::Ready(Ok(())),
+        }
+    }
+
+    pub fn reject(rejection: AuthRejection) -> Self {
+        Self {
+            state: TicketState::Ready(Err(rejection)),
+        }
+    }
+
+    /// A decision made later through the returned [`AuthResponder`]
+    pub fn pending() -> (Self, AuthResponder) {
+        let (sender, receiver) = oneshot::channel();
+        (
+            Self {
+                state: TicketState::Pending(receiver),
+            },
+            AuthResponder { sender },
+        )
+    }
+
+    /// The decision, or `None` while it is still pending
+    ///
+    /// A responder dropped without answering counts as a rejection.
+    pub fn poll(&mut self) -> Option<Result<(), AuthRejection>> {
+        match &mut self.state {
+            TicketState::Ready(result) => Some(result.clone()),
+            TicketState::Pending(receiver) => match receiver.try_recv() {
+                Ok(result) => Some(result),
+                Err(oneshot::error::TryRecvError::Empty) => None,
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    Some(Err(AuthRejection::new(0, "authentication aborted")))
+                }
+            },
+        }
+    }
+}
+
+/// Answers a pending [`AuthTicket`], from any thread or task
+pub struct AuthResponder {
+    sender: oneshot::Sender<Result<(), AuthRejection>>,
+}
+
+impl AuthResponder {
+    pub fn accept(self) {
+        let _ = self.sender.send(Ok(()));
+    }
+
+    pub fn reject(self, rejection: AuthRejection) {
+        let _ = self.sender.send(Err(rejection));
+    }
+}
+
+/// Decides whether a client that passed the version handshake may join
+pub type Authenticator = Box<dyn Fn(AuthRequest) -> AuthTicket + Send + Sync>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_ticket_resolves() {
+        let (mut ticket, responder) = AuthTicket::pending();
+        assert_eq!(ticket.poll(), None);
+        responder.reject(AuthRejection::new(403, "banned"));
+        assert_eq!(ticket.poll(), Some(Err(AuthRejection::new(403, "banned"))));
+
+        let (mut ticket, responder) = AuthTicket::pending();
+        drop(responder);
+        assert!(matches!(ticket.poll(), Some(Err(_))));
+        assert_eq!(AuthTicket::accept().poll(), Some(Ok(())));
+    }
+}
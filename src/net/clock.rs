@@ -1,7 +1,7 @@
-/// Network time synchronization
-///
-/// Provides a clock that synchronizes local time with server time,
-/// smoothing out network jitter.
+//! Network time synchronization
+//!
+//! Provides a clock that synchronizes local time with server time,
+//! smoothing out network jitter.
 
 use std::time::{Duration, Instant};
 
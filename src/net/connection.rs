@@ -1,33 +1,408 @@
-/// Connection management layer
-///
-/// Manages server and client connections, handling message sending/receiving
-/// on top of the transport layer.
+//! Connection management layer
+//!
+//! Manages server and client connections, handling message sending/receiving
+//! on top of the transport layer.
 
-use std::time::Instant;
-use renet::{RenetServer, RenetClient, ClientId, ConnectionConfig};
 use anyhow::Result;
-use serde::{Serialize, Deserialize};
+use renet::{ClientId, ConnectionConfig, RenetClient, RenetServer, ServerEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::auth::{AuthRequest, AuthTicket, Authenticator};
+use super::handshake::{HandshakeError, HandshakeInfo};
+use super::keepalive::{ConnectionState, DisconnectReason, Heartbeat, ReconnectPolicy};
+use super::protocol::{NetworkChannel, SystemMessage};
+use super::serialization::{deserialize, serialize};
+use super::transport::{ServerTransport, TransportConfig};
+
+/// Sequence numbers for sequenced-unreliable channels
+///
+/// Outgoing messages get a 2 byte sequence prefix; incoming messages older
+/// than the newest one already received from that peer are dropped.
+#[derive(Debug, Default)]
+struct Sequencing {
+    channels: Vec<u8>,
+    outgoing: HashMap<(ClientId, u8), u16>,
+    incoming: HashMap<(ClientId, u8), u16>,
+}
+
+impl Sequencing {
+    fn new(channels: Vec<u8>) -> Self {
+        Self {
+            channels,
+            ..Default::default()
+        }
+    }
+
+    fn wrap(&mut self, peer: ClientId, channel: u8, bytes: Vec<u8>) -> Vec<u8> {
+        if !self.channels.contains(&channel) {
+            return bytes;
+        }
+
+        let sequence = self.outgoing.entry((peer, channel)).or_insert(0);
+        *sequence = sequence.wrapping_add(1);
+
+        let mut wrapped = Vec::with_capacity(2 + bytes.len());
+        wrapped.extend_from_slice(&sequence.to_le_bytes());
+        wrapped.extend_from_slice(&bytes);
+        wrapped
+    }
+
+    fn unwrap<'a>(&mut self, peer: ClientId, channel: u8, bytes: &'a [u8]) -> Option<&'a [u8]> {
+        if !self.channels.contains(&channel) {
+            return Some(bytes);
+        }
+        if bytes.len() < 2 {
+            return None;
+        }
+
+        let sequence = u16::from_le_bytes([bytes[0], bytes[1]]);
+        match self.incoming.get(&(peer, channel)) {
+            Some(&newest) if !sequence_greater_than(sequence, newest) => None,
+            _ => {
+                self.incoming.insert((peer, channel), sequence);
+                Some(&bytes[2..])
+            }
+        }
+    }
+
+    fn forget(&mut self, peer: ClientId) {
+        self.outgoing.retain(|(id, _), _| *id != peer);
+        self.incoming.retain(|(id, _), _| *id != peer);
+    }
+}
+
+/// Wrapping comparison of 16-bit sequence numbers
+fn sequence_greater_than(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < u16::MAX / 2
+}
+
+/// Peer id used for the server on the client side
+const SERVER_PEER: ClientId = 0;
+
+/// How long a rejected client stays connected so the rejection reaches it
+const REJECT_GRACE: Duration = Duration::from_millis(500);
+
+/// How long the authenticator may take before a client is rejected
+const DEFAULT_AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The heartbeat channel, if `config` has it in both directions
+///
+/// renet panics on channels it wasn't configured with, and its default config
+/// only has channels 0 to 2.
+fn heartbeat_channel(config: &ConnectionConfig) -> Option<u8> {
+    let channel = u8::from(NetworkChannel::Heartbeat);
+    let configured = |channels: &[renet::ChannelConfig]| {
+        channels.iter().any(|config| config.channel_id == channel)
+    };
+    (configured(&config.server_channels_config) && configured(&config.client_channels_config))
+        .then_some(channel)
+}
+
+/// Connection lifecycle events reported by [`ServerConnection::drain_events`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Connected {
+        client_id: ClientId,
+    },
+    Disconnected {
+        client_id: ClientId,
+        reason: DisconnectReason,
+    },
+    /// The client failed the handshake or authentication and is being disconnected
+    Rejected {
+        client_id: ClientId,
+        error: HandshakeError,
+    },
+}
 
 /// Manages server connections
+///
+/// New clients are only reported as connected, and their messages only
+/// delivered, once their [`SystemMessage::ClientHello`] passes the version check
+/// and the [`Authenticator`], if one is set, lets them in. A server built with
+/// [`new`](Self::new) and no authenticator admits clients as soon as they connect.
 pub struct ServerConnection {
     server: RenetServer,
     last_update: Instant,
+    sequencing: Sequencing,
+    /// `None` when the connection config lacks the channel, which disables timeouts
+    heartbeat_channel: Option<u8>,
+    heartbeat_interval: Duration,
+    timeout: Duration,
+    heartbeats: HashMap<ClientId, Heartbeat>,
+    events: Vec<ConnectionEvent>,
+    /// Versions clients must announce, `None` to skip the check
+    handshake: Option<HandshakeInfo>,
+    /// Clients that haven't sent their hello yet, with their deadline
+    unverified: Vec<(ClientId, Instant)>,
+    /// Rejected clients and when to disconnect them
+    rejected: Vec<(ClientId, Instant)>,
+    authenticator: Option<Authenticator>,
+    auth_timeout: Duration,
+    /// Clients waiting for [`authenticate_clients`](Self::authenticate_clients)
+    awaiting_auth: Vec<(ClientId, HandshakeInfo, Instant)>,
+    /// Clients waiting for the authenticator, with their deadline
+    authenticating: Vec<(ClientId, AuthTicket, Instant)>,
 }
 
 impl ServerConnection {
+    /// Create a server on renet's `connection_config`, without a version check
+    ///
+    /// Heartbeats and timeout detection only run if the config has the
+    /// [`NetworkChannel::Heartbeat`] channel, as configs built by
+    /// [`from_transport_config`](Self::from_transport_config) do.
     pub fn new(connection_config: ConnectionConfig) -> Self {
+        let heartbeat_channel = heartbeat_channel(&connection_config);
         let server = RenetServer::new(connection_config);
+        let defaults = TransportConfig::default();
         Self {
             server,
             last_update: Instant::now(),
+            sequencing: Sequencing::default(),
+            heartbeat_channel,
+            heartbeat_interval: defaults.heartbeat_interval,
+            timeout: defaults.timeout,
+            heartbeats: HashMap::new(),
+            events: Vec::new(),
+            handshake: None,
+            unverified: Vec::new(),
+            rejected: Vec::new(),
+            authenticator: None,
+            auth_timeout: DEFAULT_AUTH_TIMEOUT,
+            awaiting_auth: Vec::new(),
+            authenticating: Vec::new(),
+        }
+    }
+
+    /// Create a server using the channels, keepalive and version settings in `config`
+    ///
+    /// Clients must announce matching versions in their
+    /// [`SystemMessage::ClientHello`], which [`ClientConnection`] sends on connect.
+    pub fn from_transport_config(config: &TransportConfig) -> Self {
+        Self {
+            sequencing: Sequencing::new(config.sequenced_channels()),
+            heartbeat_interval: config.heartbeat_interval,
+            timeout: config.timeout,
+            handshake: Some(HandshakeInfo::new(config)),
+            ..Self::new(config.connection_config())
         }
     }
 
+    /// Check clients that passed the version handshake before admitting them
+    ///
+    /// The authenticator sees each client's [`AuthRequest`] once and answers with
+    /// an [`AuthTicket`], which may resolve on a later tick. Clients still waiting
+    /// after the [auth timeout](Self::set_auth_timeout) are rejected.
+    ///
+    /// Requests are made by [`authenticate_clients`](Self::authenticate_clients),
+    /// which must then be called every tick.
+    pub fn set_authenticator(
+        &mut self,
+        authenticator: impl Fn(AuthRequest) -> AuthTicket + Send + Sync + 'static,
+    ) {
+        self.authenticator = Some(Box::new(authenticator));
+    }
+
+    /// How long clients get to send their hello and pass authentication
+    pub fn set_auth_timeout(&mut self, timeout: Duration) {
+        self.auth_timeout = timeout;
+    }
+
+    /// Advance the connection, exchange heartbeats and drop timed out clients
+    ///
+    /// Also checks the hellos of new clients and the answers of pending
+    /// [`AuthTicket`]s. Consumes renet's server events; read them with
+    /// [`drain_events`](Self::drain_events)
     pub fn update(&mut self) {
         let now = Instant::now();
         let delta = now.duration_since(self.last_update);
         self.server.update(delta);
         self.last_update = now;
+
+        while let Some(event) = self.server.get_event() {
+            match event {
+                ServerEvent::ClientConnected { client_id } => {
+                    self.heartbeats.insert(
+                        client_id,
+                        Heartbeat::new(self.heartbeat_interval, self.timeout, now),
+                    );
+                    if self.handshake.is_none() && self.authenticator.is_none() {
+                        self.events.push(ConnectionEvent::Connected { client_id });
+                    } else {
+                        self.unverified.push((client_id, now + self.auth_timeout));
+                    }
+                }
+                ServerEvent::ClientDisconnected { client_id, reason } => {
+                    let was_verified = !self.is_pending(client_id);
+                    self.unverified.retain(|&(id, _)| id != client_id);
+                    self.awaiting_auth.retain(|&(id, ..)| id != client_id);
+                    self.authenticating.retain(|(id, ..)| *id != client_id);
+                    self.rejected.retain(|&(id, _)| id != client_id);
+
+                    // Already reported if we dropped the client ourselves
+                    if self.heartbeats.remove(&client_id).is_some() && was_verified {
+                        self.sequencing.forget(client_id);
+                        self.events.push(ConnectionEvent::Disconnected {
+                            client_id,
+                            reason: DisconnectReason::Remote {
+                                reason: reason.to_string(),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+
+        self.verify_hellos(now);
+        self.poll_auth_tickets(now);
+
+        let mut timed_out = Vec::new();
+        if let Some(heartbeat_channel) = self.heartbeat_channel {
+            for (&client_id, heartbeat) in self.heartbeats.iter_mut() {
+                while self
+                    .server
+                    .receive_message(client_id, heartbeat_channel)
+                    .is_some()
+                {
+                    heartbeat.received(now);
+                }
+                if heartbeat.is_timed_out(now) {
+                    timed_out.push(client_id);
+                } else if heartbeat.should_send(now) {
+                    self.server
+                        .send_message(client_id, heartbeat_channel, Vec::new());
+                }
+            }
+        }
+
+        for client_id in timed_out {
+            log::warn!("Client {} timed out", client_id);
+            self.drop_client(client_id, DisconnectReason::Timeout);
+        }
+
+        let server = &mut self.server;
+        self.rejected.retain(|&(client_id, disconnect_at)| {
+            if now < disconnect_at {
+                return true;
+            }
+            server.disconnect(client_id);
+            false
+        });
+    }
+
+    /// Ask the authenticator about clients whose hello passed the version check
+    ///
+    /// Only needed with an [authenticator](Self::set_authenticator); call every
+    /// tick after [`update`](Self::update). The [`AuthRequest`] token comes from
+    /// the client's netcode user data, which in secure mode was sealed by the
+    /// connect token issuer.
+    pub fn authenticate_clients(&mut self, transport: &ServerTransport) {
+        let Some(authenticator) = &self.authenticator else {
+            return;
+        };
+        for (client_id, handshake, deadline) in std::mem::take(&mut self.awaiting_auth) {
+            let ticket = authenticator(AuthRequest {
+                client_id,
+                handshake,
+                token: transport.auth_token(client_id),
+            });
+            self.authenticating.push((client_id, ticket, deadline));
+        }
+    }
+
+    /// Check the versions new clients announced in their hello
+    ///
+    /// Accepted clients are reported as [`ConnectionEvent::Connected`], or handed
+    /// to the authenticator. The rest are sent [`SystemMessage::HandshakeRejected`],
+    /// reported as [`ConnectionEvent::Rejected`] and disconnected shortly after.
+    fn verify_hellos(&mut self, now: Instant) {
+        for (client_id, deadline) in std::mem::take(&mut self.unverified) {
+            let mut hello = None;
+            while hello.is_none()
+                && let Some(bytes) = self
+                    .server
+                    .receive_message(client_id, NetworkChannel::System)
+            {
+                if let Ok(SystemMessage::ClientHello(info)) = deserialize(&bytes) {
+                    hello = Some(info);
+                }
+            }
+
+            let Some(info) = hello else {
+                if now >= deadline {
+                    self.reject(client_id, HandshakeError::MissingHandshake, now);
+                } else {
+                    self.unverified.push((client_id, deadline));
+                }
+                continue;
+            };
+
+            if let Some(handshake) = self.handshake
+                && let Err(error) = handshake.verify(Some(info))
+            {
+                self.reject(client_id, error, now);
+            } else if self.authenticator.is_some() {
+                self.awaiting_auth.push((client_id, info, deadline));
+            } else {
+                self.events.push(ConnectionEvent::Connected { client_id });
+            }
+        }
+    }
+
+    fn poll_auth_tickets(&mut self, now: Instant) {
+        for (client_id, handshake, deadline) in std::mem::take(&mut self.awaiting_auth) {
+            if now >= deadline {
+                self.reject(client_id, HandshakeError::AuthTimeout, now);
+            } else {
+                self.awaiting_auth.push((client_id, handshake, deadline));
+            }
+        }
+
+        for (client_id, mut ticket, deadline) in std::mem::take(&mut self.authenticating) {
+            match ticket.poll() {
+                Some(Ok(())) => self.events.push(ConnectionEvent::Connected { client_id }),
+                Some(Err(rejection)) => {
+                    self.reject(client_id, HandshakeError::Unauthorized(rejection), now)
+                }
+                None if now >= deadline => self.reject(client_id, HandshakeError::AuthTimeout, now),
+                None => self.authenticating.push((client_id, ticket, deadline)),
+            }
+        }
+    }
+
+    /// Tell the client why it was refused and disconnect it after a grace period
+    fn reject(&mut self, client_id: ClientId, error: HandshakeError, now: Instant) {
+        log::warn!("Rejecting client {}: {}", client_id, error);
+        if let Ok(bytes) = serialize(&SystemMessage::HandshakeRejected(error.clone())) {
+            self.server
+                .send_message(client_id, NetworkChannel::System, bytes);
+        }
+        self.heartbeats.remove(&client_id);
+        self.sequencing.forget(client_id);
+        self.rejected.push((client_id, now + REJECT_GRACE));
+        self.events
+            .push(ConnectionEvent::Rejected { client_id, error });
+    }
+
+    /// Whether the client is still being verified or authenticated
+    fn is_pending(&self, client_id: ClientId) -> bool {
+        self.unverified.iter().any(|&(id, _)| id == client_id)
+            || self.awaiting_auth.iter().any(|&(id, ..)| id == client_id)
+            || self.authenticating.iter().any(|(id, ..)| *id == client_id)
+    }
+
+    /// Clients that passed the handshake and authentication
+    fn verified_clients(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.server.clients_id().into_iter().filter(|&client_id| {
+            !self.is_pending(client_id) && !self.rejected.iter().any(|&(id, _)| id == client_id)
+        })
+    }
+
+    /// Connection events since the last call
+    pub fn drain_events(&mut self) -> Vec<ConnectionEvent> {
+        std::mem::take(&mut self.events)
     }
 
     /// Send a message to a specific client
@@ -37,7 +412,8 @@ impl ServerConnection {
         message: &T,
         channel: u8,
     ) -> Result<()> {
-        let bytes = bincode::serialize(message)?;
+        let bytes = serialize(message)?;
+        let bytes = self.sequencing.wrap(client_id, channel, bytes);
         self.server.send_message(client_id, channel, bytes);
         Ok(())
     }
@@ -48,9 +424,11 @@ impl ServerConnection {
         message: &T,
         channel: u8,
     ) -> Result<()> {
-        let bytes = bincode::serialize(message)?;
-        for client_id in self.server.clients_id() {
-            self.server.send_message(client_id, channel, bytes.clone());
+        let bytes = serialize(message)?;
+        let clients: Vec<ClientId> = self.verified_clients().collect();
+        for client_id in clients {
+            let bytes = self.sequencing.wrap(client_id, channel, bytes.clone());
+            self.server.send_message(client_id, channel, bytes);
         }
         Ok(())
     }
@@ -61,10 +439,18 @@ impl ServerConnection {
         channel: u8,
     ) -> Vec<(ClientId, T)> {
         let mut messages = Vec::new();
+        let now = Instant::now();
+        let clients: Vec<ClientId> = self.verified_clients().collect();
 
-        for client_id in self.server.clients_id() {
+        for client_id in clients {
             while let Some(bytes) = self.server.receive_message(client_id, channel) {
-                if let Ok(message) = bincode::deserialize::<T>(&bytes) {
+                if let Some(heartbeat) = self.heartbeats.get_mut(&client_id) {
+                    heartbeat.received(now);
+                }
+                let Some(bytes) = self.sequencing.unwrap(client_id, channel, &bytes) else {
+                    continue;
+                };
+                if let Ok(message) = deserialize::<T>(bytes) {
                     messages.push((client_id, message));
                 }
             }
@@ -74,7 +460,7 @@ impl ServerConnection {
     }
 
     pub fn connected_clients(&self) -> Vec<ClientId> {
-        self.server.clients_id().into_iter().collect()
+        self.verified_clients().collect()
     }
 
     pub fn inner_mut(&mut self) -> &mut RenetServer {
@@ -82,39 +468,233 @@ impl ServerConnection {
     }
 
     pub fn disconnect_client(&mut self, client_id: ClientId) {
+        self.drop_client(client_id, DisconnectReason::Requested);
+    }
+
+    fn drop_client(&mut self, client_id: ClientId, reason: DisconnectReason) {
         self.server.disconnect(client_id);
+        self.sequencing.forget(client_id);
+        if self.heartbeats.remove(&client_id).is_some() {
+            self.events
+                .push(ConnectionEvent::Disconnected { client_id, reason });
+        }
     }
 }
 
 /// Manages client connection
+///
+/// Detects timeouts through heartbeats and reconnects according to its
+/// [`ReconnectPolicy`]. Reliable messages sent while reconnecting are queued
+/// and delivered once the connection is back, after a
+/// [`SystemMessage::RequestFullSnapshot`] asking the server to re-sync.
+///
+/// Every connection starts with a [`SystemMessage::ClientHello`] announcing the
+/// client's versions, which the server checks before admitting it.
 pub struct ClientConnection {
     client: RenetClient,
+    connection_config: ConnectionConfig,
     last_update: Instant,
+    sequencing: Sequencing,
+    /// `None` when the connection config lacks the channel, which disables timeouts
+    heartbeat_channel: Option<u8>,
+    heartbeat: Heartbeat,
+    reliable_channels: Vec<u8>,
+    handshake: HandshakeInfo,
+    /// Whether the current connection has sent its [`SystemMessage::ClientHello`]
+    hello_sent: bool,
+    reconnect_policy: ReconnectPolicy,
+    state: ConnectionState,
+    /// Reliable messages waiting for the connection to come back
+    pending: VecDeque<(u8, Vec<u8>)>,
+    /// System messages read while checking for a handshake rejection
+    system_inbox: VecDeque<Vec<u8>>,
+    disconnect_event: Option<DisconnectReason>,
 }
 
 impl ClientConnection {
+    /// Create a client on renet's `connection_config`
+    ///
+    /// Announces the versions of [`TransportConfig::default`]. Heartbeats and timeout detection only run if the config has the
+    /// [`NetworkChannel::Heartbeat`] channel, as configs built by
+    /// [`from_transport_config`](Self::from_transport_config) do.
     pub fn new(connection_config: ConnectionConfig) -> Self {
-        let client = RenetClient::new(connection_config);
+        let client = RenetClient::new(connection_config.clone());
+        let now = Instant::now();
         Self {
             client,
-            last_update: Instant::now(),
+            heartbeat_channel: heartbeat_channel(&connection_config),
+            connection_config,
+            last_update: now,
+            sequencing: Sequencing::default(),
+            heartbeat: Heartbeat::from_config(&TransportConfig::default(), now),
+            reliable_channels: vec![
+                NetworkChannel::Reliable.into(),
+                NetworkChannel::System.into(),
+            ],
+            handshake: HandshakeInfo::new(&TransportConfig::default()),
+            hello_sent: false,
+            reconnect_policy: ReconnectPolicy::default(),
+            state: ConnectionState::Connected,
+            pending: VecDeque::new(),
+            system_inbox: VecDeque::new(),
+            disconnect_event: None,
+        }
+    }
+
+    /// Create a client using the channels, keepalive and version settings in `config`
+    pub fn from_transport_config(config: &TransportConfig) -> Self {
+        Self {
+            handshake: HandshakeInfo::new(config),
+            sequencing: Sequencing::new(config.sequenced_channels()),
+            heartbeat: Heartbeat::from_config(config, Instant::now()),
+            reliable_channels: config
+                .channels
+                .iter()
+                .filter(|channel| channel.kind.is_reliable())
+                .map(|channel| channel.id)
+                .collect(),
+            ..Self::new(config.connection_config())
         }
     }
 
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// Advance the connection, exchange heartbeats and detect lost connections
     pub fn update(&mut self) {
         let now = Instant::now();
         let delta = now.duration_since(self.last_update);
         self.client.update(delta);
         self.last_update = now;
+
+        // Secure connect tokens carry the issuer's versions, so announce ours
+        // in-band where the server can check them
+        if !self.hello_sent
+            && self.client.is_connected()
+            && let Ok(bytes) = serialize(&SystemMessage::ClientHello(self.handshake))
+        {
+            self.client.send_message(NetworkChannel::System, bytes);
+            self.hello_sent = true;
+        }
+
+        if let Some(heartbeat_channel) = self.heartbeat_channel {
+            while self.client.receive_message(heartbeat_channel).is_some() {
+                self.heartbeat.received(now);
+            }
+        }
+
+        while let Some(bytes) = self.client.receive_message(NetworkChannel::System) {
+            self.heartbeat.received(now);
+            match deserialize::<SystemMessage>(&bytes) {
+                Ok(SystemMessage::HandshakeRejected(error)) => {
+                    log::error!("Server rejected connection: {}", error);
+                    let reason = DisconnectReason::Rejected(error);
+                    self.client.disconnect();
+                    self.pending.clear();
+                    self.system_inbox.clear();
+                    self.disconnect_event = Some(reason.clone());
+                    // A rejected build won't be accepted on retry either
+                    self.state = ConnectionState::Disconnected(reason);
+                    return;
+                }
+                _ => self.system_inbox.push_back(bytes.to_vec()),
+            }
+        }
+
+        match self.state {
+            ConnectionState::Connected => {
+                if self.heartbeat_channel.is_some() && self.heartbeat.is_timed_out(now) {
+                    self.client.disconnect();
+                    self.connection_lost(DisconnectReason::Timeout, now);
+                } else if self.client.is_disconnected() {
+                    let reason = self
+                        .client
+                        .disconnect_reason()
+                        .map(|reason| DisconnectReason::Remote {
+                            reason: reason.to_string(),
+                        })
+                        .unwrap_or(DisconnectReason::Transport("connection lost".to_string()));
+                    self.connection_lost(reason, now);
+                } else if let Some(heartbeat_channel) = self.heartbeat_channel
+                    && self.heartbeat.should_send(now)
+                {
+                    self.client.send_message(heartbeat_channel, Vec::new());
+                }
+            }
+            ConnectionState::Reconnecting { attempt, .. } if self.client.is_connected() => {
+                log::info!("Reconnected after {} attempt(s)", attempt);
+                self.state = ConnectionState::Connected;
+                self.heartbeat.received(now);
+                self.resync();
+            }
+            ConnectionState::Reconnecting {
+                attempt,
+                next_attempt,
+            } if now >= next_attempt && self.heartbeat.is_timed_out(now) => {
+                // The last attempt didn't connect in time
+                self.schedule_reconnect(attempt + 1, DisconnectReason::Timeout, now);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    /// Reason the connection was lost since the last call, if it was
+    pub fn poll_disconnect(&mut self) -> Option<DisconnectReason> {
+        self.disconnect_event.take()
+    }
+
+    /// Whether it's time to call [`reconnect`](Self::reconnect)
+    pub fn reconnect_due(&self) -> bool {
+        matches!(
+            self.state,
+            ConnectionState::Reconnecting { next_attempt, .. } if Instant::now() >= next_attempt
+        )
+    }
+
+    /// Start a new connection attempt, keeping queued messages
+    ///
+    /// The caller must create a fresh `ClientTransport` for the new connection.
+    pub fn reconnect(&mut self) {
+        let now = Instant::now();
+        let attempt = match self.state {
+            ConnectionState::Reconnecting { attempt, .. } => attempt,
+            _ => 1,
+        };
+
+        self.client = RenetClient::new(self.connection_config.clone());
+        self.hello_sent = false;
+        self.sequencing.forget(SERVER_PEER);
+        self.heartbeat.received(now);
+        self.state = ConnectionState::Reconnecting {
+            attempt,
+            next_attempt: now + self.reconnect_policy.backoff(attempt + 1),
+        };
     }
 
     /// Send a message to the server
+    ///
+    /// While reconnecting, messages on reliable channels are queued and
+    /// unreliable ones are dropped.
     pub fn send_message<T: Serialize>(
         &mut self,
         message: &T,
         channel: u8,
     ) -> Result<()> {
-        let bytes = bincode::serialize(message)?;
+        let bytes = serialize(message)?;
+        if self.state != ConnectionState::Connected {
+            if self.reliable_channels.contains(&channel) {
+                self.pending.push_back((channel, bytes));
+            }
+            return Ok(());
+        }
+
+        let bytes = self.sequencing.wrap(SERVER_PEER, channel, bytes);
         self.client.send_message(channel, bytes);
         Ok(())
     }
@@ -126,8 +706,20 @@ impl ClientConnection {
     ) -> Vec<T> {
         let mut messages = Vec::new();
 
+        if channel == u8::from(NetworkChannel::System) {
+            messages.extend(
+                self.system_inbox
+                    .drain(..)
+                    .filter_map(|bytes| deserialize::<T>(&bytes).ok()),
+            );
+        }
+
         while let Some(bytes) = self.client.receive_message(channel) {
-            if let Ok(message) = bincode::deserialize::<T>(&bytes) {
+            self.heartbeat.received(Instant::now());
+            let Some(bytes) = self.sequencing.unwrap(SERVER_PEER, channel, &bytes) else {
+                continue;
+            };
+            if let Ok(message) = deserialize::<T>(bytes) {
                 messages.push(message);
             }
         }
@@ -136,14 +728,145 @@ impl ClientConnection {
     }
 
     pub fn is_connected(&self) -> bool {
-        self.client.is_connected()
+        self.state == ConnectionState::Connected && self.client.is_connected()
     }
 
     pub fn disconnect(&mut self) {
         self.client.disconnect();
+        self.pending.clear();
+        self.state = ConnectionState::Disconnected(DisconnectReason::Requested);
     }
 
     pub fn inner_mut(&mut self) -> &mut RenetClient {
         &mut self.client
     }
+
+    fn connection_lost(&mut self, reason: DisconnectReason, now: Instant) {
+        log::warn!("Lost connection to server: {}", reason);
+        self.disconnect_event = Some(reason.clone());
+        self.schedule_reconnect(1, reason, now);
+    }
+
+    fn schedule_reconnect(&mut self, attempt: u32, reason: DisconnectReason, now: Instant) {
+        self.state = if attempt <= self.reconnect_policy.max_attempts {
+            ConnectionState::Reconnecting {
+                attempt,
+                next_attempt: now + self.reconnect_policy.backoff(attempt),
+            }
+        } else {
+            self.pending.clear();
+            ConnectionState::Disconnected(reason)
+        };
+    }
+
+    /// Ask for a full snapshot and flush messages queued while disconnected
+    fn resync(&mut self) {
+        if let Ok(bytes) = serialize(&SystemMessage::RequestFullSnapshot) {
+            self.client.send_message(NetworkChannel::System, bytes);
+        }
+        while let Some((channel, bytes)) = self.pending.pop_front() {
+            let bytes = self.sequencing.wrap(SERVER_PEER, channel, bytes);
+            self.client.send_message(channel, bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequenced_channel_drops_stale_messages() {
+        let mut sender = Sequencing::new(vec![3]);
+        let mut receiver = Sequencing::new(vec![3]);
+
+        let first = sender.wrap(1, 3, vec![1]);
+        let second = sender.wrap(1, 3, vec![2]);
+
+        assert_eq!(receiver.unwrap(1, 3, &second), Some(&[2u8][..]));
+        assert_eq!(receiver.unwrap(1, 3, &first), None);
+        // Other channels pass through untouched
+        assert_eq!(receiver.unwrap(1, 0, &[7]), Some(&[7u8][..]));
+    }
+
+    #[test]
+    fn test_default_connection_config_skips_heartbeats() {
+        let mut client = ClientConnection::new(ConnectionConfig::default());
+        assert_eq!(client.heartbeat_channel, None);
+        client.update();
+
+        let mut server = ServerConnection::new(ConnectionConfig::default());
+        server.inner_mut().add_connection(1);
+        server.update();
+        server.update();
+
+        let config = TransportConfig::default();
+        assert_eq!(
+            ServerConnection::from_transport_config(&config).heartbeat_channel,
+            Some(NetworkChannel::Heartbeat.into())
+        );
+    }
+
+    #[test]
+    fn test_server_without_version_check_admits_clients() {
+        let mut server = ServerConnection::new(ConnectionConfig::default());
+        server.inner_mut().add_connection(1);
+        server.update();
+
+        assert_eq!(
+            server.drain_events(),
+            vec![ConnectionEvent::Connected { client_id: 1 }]
+        );
+        assert_eq!(server.connected_clients(), vec![1]);
+    }
+
+    /// Connect a local client and run both sides until the server has decided
+    fn handshake(server_version: u32, client_version: u32) -> Vec<ConnectionEvent> {
+        let server_config = TransportConfig {
+            game_version: server_version,
+            ..Default::default()
+        };
+        let client_config = TransportConfig {
+            game_version: client_version,
+            ..Default::default()
+        };
+        let mut server = ServerConnection::from_transport_config(&server_config);
+        let mut client = ClientConnection::from_transport_config(&client_config);
+        *client.inner_mut() = server.inner_mut().new_local_client(1);
+
+        server.update();
+        assert!(server.connected_clients().is_empty());
+
+        client.update();
+        server
+            .inner_mut()
+            .process_local_client(1, client.inner_mut())
+            .unwrap();
+        server.update();
+        server.drain_events()
+    }
+
+    #[test]
+    fn test_server_checks_client_hello() {
+        assert_eq!(
+            handshake(2, 2),
+            vec![ConnectionEvent::Connected { client_id: 1 }]
+        );
+        assert_eq!(
+            handshake(2, 3),
+            vec![ConnectionEvent::Rejected {
+                client_id: 1,
+                error: HandshakeError::GameVersionMismatch {
+                    server: 2,
+                    client: 3
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sequence_wraparound() {
+        assert!(sequence_greater_than(1, u16::MAX));
+        assert!(!sequence_greater_than(u16::MAX, 1));
+    }
 }
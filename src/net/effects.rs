@@ -0,0 +1,263 @@
+//! Interest-aware replication of transient effects
+//!
+//! Sounds, particles and similar one-shot effects are queued with an
+//! [`EffectHint`] describing where they happen and how far they reach. Each
+//! flush only sends an effect to clients whose interest area overlaps it, and
+//! when a client's effect budget is exceeded the farthest, least important
+//! effects are dropped first, so effect spam can't saturate a connection
+
+use anyhow::Result;
+use bevy_ecs::prelude::Resource;
+use glam::Vec3;
+use renet::ClientId;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::connection::ServerConnection;
+use super::serialization::serialize;
+
+/// Kind of transient effect, used to pick a default reach and priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EffectKind {
+    Sound,
+    Particles,
+    Other,
+}
+
+/// Replication metadata attached to an effect
+#[derive(Debug, Clone, Copy)]
+pub struct EffectHint {
+    pub kind: EffectKind,
+    /// World position of the effect's source
+    pub position: Vec3,
+    /// Distance past which the effect can't be seen or heard
+    pub radius: f32,
+    /// Relative importance, scaled down with distance when budgets are tight
+    pub priority: f32,
+}
+
+impl EffectHint {
+    pub fn sound(position: Vec3) -> Self {
+        Self {
+            kind: EffectKind::Sound,
+            position,
+            radius: 50.0,
+            priority: 1.0,
+        }
+    }
+
+    pub fn particles(position: Vec3) -> Self {
+        Self {
+            kind: EffectKind::Particles,
+            position,
+            radius: 100.0,
+            priority: 0.5,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: f32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Priority for a client, or `None` if the effect is outside its interest
+    pub fn priority_for(&self, interest: &ClientInterest) -> Option<f32> {
+        let reach = self.radius + interest.radius;
+        let distance = self.position.distance(interest.position);
+        if distance > reach {
+            return None;
+        }
+        Some(self.priority * (1.0 - distance / reach.max(f32::EPSILON)))
+    }
+}
+
+/// Area a client wants effects from, usually centered on its player
+#[derive(Debug, Clone, Copy)]
+pub struct ClientInterest {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+impl ClientInterest {
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.position.distance(point) <= self.radius
+    }
+}
+
+/// Interest areas of the connected clients
+///
+/// Kept up to date by the server game, usually from each client's player
+/// position, and shared by everything that filters by interest: effect
+/// flushes and server-side spawners.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ClientInterests(pub HashMap<ClientId, ClientInterest>);
+
+impl ClientInterests {
+    /// Whether any client is interested in `point`
+    pub fn any_contains(&self, point: Vec3) -> bool {
+        self.0.values().any(|interest| interest.contains(point))
+    }
+}
+
+/// What happened to effects during flushes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EffectStats {
+    pub sent: u64,
+    /// Effect/client pairs skipped because the effect was outside the client's interest
+    pub culled_by_interest: u64,
+    /// Effect/client pairs dropped because the client's budget was spent
+    pub dropped_for_bandwidth: u64,
+}
+
+struct QueuedEffect<T> {
+    hint: EffectHint,
+    effect: T,
+    size: usize,
+}
+
+/// Queues effects for a tick and routes them to interested clients
+pub struct EffectReplicator<T> {
+    queue: Vec<QueuedEffect<T>>,
+    /// Bytes of effects each client may receive per flush
+    pub budget_per_client: usize,
+    stats: EffectStats,
+}
+
+impl<T: Serialize> EffectReplicator<T> {
+    pub fn new(budget_per_client: usize) -> Self {
+        Self {
+            queue: Vec::new(),
+            budget_per_client,
+            stats: EffectStats::default(),
+        }
+    }
+
+    pub fn push(&mut self, hint: EffectHint, effect: T) -> Result<()> {
+        let size = serialize(&effect)?.len();
+        self.queue.push(QueuedEffect { hint, effect, size });
+        Ok(())
+    }
+
+    /// Pick which queued effects each client receives
+    ///
+    /// Returns indices into the queue per client, in send order.
+    fn route(
+        &mut self,
+        interests: &HashMap<ClientId, ClientInterest>,
+    ) -> Vec<(ClientId, Vec<usize>)> {
+        let mut routes = Vec::with_capacity(interests.len());
+
+        for (&client_id, interest) in interests {
+            let mut candidates: Vec<(usize, f32)> = Vec::new();
+            for (index, queued) in self.queue.iter().enumerate() {
+                match queued.hint.priority_for(interest) {
+                    Some(priority) => candidates.push((index, priority)),
+                    None => self.stats.culled_by_interest += 1,
+                }
+            }
+            candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let mut remaining = self.budget_per_client;
+            let mut selected = Vec::with_capacity(candidates.len());
+            for (index, _) in candidates {
+                let size = self.queue[index].size;
+                if size <= remaining {
+                    remaining -= size;
+                    selected.push(index);
+                } else {
+                    self.stats.dropped_for_bandwidth += 1;
+                }
+            }
+            self.stats.sent += selected.len() as u64;
+            routes.push((client_id, selected));
+        }
+
+        routes
+    }
+
+    /// Send this tick's effects to interested clients on `channel`
+    pub fn flush(
+        &mut self,
+        connection: &mut ServerConnection,
+        interests: &HashMap<ClientId, ClientInterest>,
+        channel: u8,
+    ) -> Result<()> {
+        let routes = self.route(interests);
+        for (client_id, indices) in routes {
+            for index in indices {
+                connection.send_message(client_id, &self.queue[index].effect, channel)?;
+            }
+        }
+        self.queue.clear();
+        Ok(())
+    }
+
+    pub fn stats(&self) -> EffectStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interests() -> HashMap<ClientId, ClientInterest> {
+        HashMap::from([
+            (
+                1,
+                ClientInterest {
+                    position: Vec3::ZERO,
+                    radius: 10.0,
+                },
+            ),
+            (
+                2,
+                ClientInterest {
+                    position: Vec3::new(1000.0, 0.0, 0.0),
+                    radius: 10.0,
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_effects_only_reach_interested_clients() {
+        let mut replicator = EffectReplicator::<u32>::new(1024);
+        replicator
+            .push(EffectHint::sound(Vec3::new(5.0, 0.0, 0.0)), 7)
+            .unwrap();
+
+        let routes: HashMap<_, _> = replicator.route(&interests()).into_iter().collect();
+        assert_eq!(routes[&1], vec![0]);
+        assert!(routes[&2].is_empty());
+        assert_eq!(replicator.stats().culled_by_interest, 1);
+    }
+
+    #[test]
+    fn test_budget_drops_farthest_effects() {
+        // Small integers encode to a single byte, so there's room for exactly one
+        let mut replicator = EffectReplicator::<u32>::new(1);
+        replicator
+            .push(EffectHint::sound(Vec3::new(40.0, 0.0, 0.0)), 1)
+            .unwrap();
+        replicator
+            .push(EffectHint::sound(Vec3::new(2.0, 0.0, 0.0)), 2)
+            .unwrap();
+
+        let interests = HashMap::from([(
+            1,
+            ClientInterest {
+                position: Vec3::ZERO,
+                radius: 10.0,
+            },
+        )]);
+        let routes = replicator.route(&interests);
+        assert_eq!(routes[0].1, vec![1]);
+        assert_eq!(replicator.stats().dropped_for_bandwidth, 1);
+    }
+}
@@ -0,0 +1,271 @@
+//! Protocol version negotiation and session encryption
+//!
+//! Clients announce a [`HandshakeInfo`] in a
+//! [`SystemMessage::ClientHello`](super::protocol::SystemMessage::ClientHello)
+//! right after connecting. The server checks it and rejects mismatches with
+//! [`SystemMessage::HandshakeRejected`](super::protocol::SystemMessage::HandshakeRejected)
+//! before any game message is decoded, so incompatible builds fail with a clear
+//! error instead of garbled messages.
+//!
+//! The netcode user data of the connection request carries an auth token, checked
+//! afterwards by the server's [`Authenticator`](super::auth::Authenticator). In
+//! secure mode it is written by the connect token issuer rather than the client,
+//! which is why the versions travel in the hello instead.
+//!
+//! Encryption uses netcode's secure mode: a backend holding the server's private
+//! key issues each client a [`ConnectToken`], and all packets are then encrypted
+//! with per-session chacha20poly1305 keys from that token.
+
+use anyhow::Result;
+use renet_netcode::{ConnectToken, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use super::auth::AuthRejection;
+use super::transport::TransportConfig;
+
+/// Version of the engine's wire protocol. Bump when system messages or
+/// connection framing change
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Marks user data written by [`HandshakeInfo`]
+const HANDSHAKE_MAGIC: [u8; 4] = *b"RSNC";
+
+/// User data bytes before the auth token: magic, versions and token length
+const HANDSHAKE_HEADER_BYTES: usize = 14;
+
+/// Longest auth token that fits in the netcode user data
+pub const MAX_AUTH_TOKEN_BYTES: usize = NETCODE_USER_DATA_BYTES - HANDSHAKE_HEADER_BYTES;
+
+/// Versions a client announces when connecting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeInfo {
+    pub protocol_version: u32,
+    /// Game-defined version, see [`TransportConfig::game_version`]
+    pub game_version: u32,
+}
+
+impl HandshakeInfo {
+    pub fn new(config: &TransportConfig) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            game_version: config.game_version,
+        }
+    }
+
+    pub fn to_user_data(&self) -> [u8; NETCODE_USER_DATA_BYTES] {
+        let mut data = [0; NETCODE_USER_DATA_BYTES];
+        data[0..4].copy_from_slice(&HANDSHAKE_MAGIC);
+        data[4..8].copy_from_slice(&self.protocol_version.to_le_bytes());
+        data[8..12].copy_from_slice(&self.game_version.to_le_bytes());
+        data
+    }
+
+    /// User data carrying `token` for the server's
+    /// [`Authenticator`](super::auth::Authenticator)
+    pub fn to_user_data_with_token(&self, token: &[u8]) -> Result<[u8; NETCODE_USER_DATA_BYTES]> {
+        if token.len() > MAX_AUTH_TOKEN_BYTES {
+            anyhow::bail!(
+                "auth token is {} bytes, at most {} fit in the handshake",
+                token.len(),
+                MAX_AUTH_TOKEN_BYTES
+            );
+        }
+
+        let mut data = self.to_user_data();
+        data[12..14].copy_from_slice(&(token.len() as u16).to_le_bytes());
+        data[HANDSHAKE_HEADER_BYTES..HANDSHAKE_HEADER_BYTES + token.len()].copy_from_slice(token);
+        Ok(data)
+    }
+
+    /// Auth token written by [`to_user_data_with_token`](Self::to_user_data_with_token),
+    /// empty if there is none
+    pub fn auth_token_from_user_data(data: &[u8; NETCODE_USER_DATA_BYTES]) -> Vec<u8> {
+        if data[0..4] != HANDSHAKE_MAGIC {
+            return Vec::new();
+        }
+
+        let len = u16::from_le_bytes([data[12], data[13]]) as usize;
+        data.get(HANDSHAKE_HEADER_BYTES..HANDSHAKE_HEADER_BYTES + len)
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    pub fn from_user_data(data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<Self> {
+        if data[0..4] != HANDSHAKE_MAGIC {
+            return None;
+        }
+
+        Some(Self {
+            protocol_version: u32::from_le_bytes(data[4..8].try_into().ok()?),
+            game_version: u32::from_le_bytes(data[8..12].try_into().ok()?),
+        })
+    }
+
+    /// Check a client's announced versions against ours
+    pub fn verify(&self, client: Option<HandshakeInfo>) -> Result<(), HandshakeError> {
+        let client = client.ok_or(HandshakeError::MissingHandshake)?;
+        if client.protocol_version != self.protocol_version {
+            return Err(HandshakeError::ProtocolMismatch {
+                server: self.protocol_version,
+                client: client.protocol_version,
+            });
+        }
+        if client.game_version != self.game_version {
+            return Err(HandshakeError::GameVersionMismatch {
+                server: self.game_version,
+                client: client.game_version,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why the server refused a client
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum HandshakeError {
+    #[error("protocol version mismatch (server {server}, client {client})")]
+    ProtocolMismatch { server: u32, client: u32 },
+    #[error("game version mismatch (server {server}, client {client})")]
+    GameVersionMismatch { server: u32, client: u32 },
+    #[error("client sent no handshake data")]
+    MissingHandshake,
+    #[error("authentication failed: {0}")]
+    Unauthorized(AuthRejection),
+    #[error("authentication timed out")]
+    AuthTimeout,
+}
+
+/// How packets between client and server are protected
+#[derive(Debug, Clone, Default)]
+pub enum TransportSecurity {
+    /// No encryption; clients connect with just the server address
+    #[default]
+    Unsecure,
+    /// Encrypted and authenticated with connect tokens signed by this key
+    Secure {
+        private_key: [u8; NETCODE_KEY_BYTES],
+    },
+}
+
+impl TransportSecurity {
+    /// Secure mode with a freshly generated private key
+    pub fn generate() -> Self {
+        TransportSecurity::Secure {
+            private_key: rand::random(),
+        }
+    }
+}
+
+/// Issue a connect token for `client_id`
+///
+/// Runs wherever the private key lives (the server or a matchmaking backend);
+/// the token is then handed to the client over a secure channel such as HTTPS.
+pub fn generate_connect_token(
+    config: &TransportConfig,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+    client_id: u64,
+    server_addresses: Vec<SocketAddr>,
+    expire_after: Duration,
+) -> Result<ConnectToken> {
+    generate_connect_token_with_auth(
+        config,
+        private_key,
+        client_id,
+        server_addresses,
+        expire_after,
+        &[],
+    )
+}
+
+/// Issue a connect token that also carries `auth_token` for the server's
+/// [`Authenticator`](super::auth::Authenticator)
+///
+/// The token is encrypted with the private key, so clients can't read or
+/// swap it.
+pub fn generate_connect_token_with_auth(
+    config: &TransportConfig,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+    client_id: u64,
+    server_addresses: Vec<SocketAddr>,
+    expire_after: Duration,
+    auth_token: &[u8],
+) -> Result<ConnectToken> {
+    let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let user_data = HandshakeInfo::new(config).to_user_data_with_token(auth_token)?;
+
+    let token = ConnectToken::generate(
+        current_time,
+        config.protocol_id,
+        expire_after.as_secs(),
+        client_id,
+        config.timeout.as_secs() as i32,
+        server_addresses,
+        Some(&user_data),
+        private_key,
+    )?;
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_data_roundtrip() {
+        let info = HandshakeInfo {
+            protocol_version: PROTOCOL_VERSION,
+            game_version: 7,
+        };
+        assert_eq!(
+            HandshakeInfo::from_user_data(&info.to_user_data()),
+            Some(info)
+        );
+        assert_eq!(
+            HandshakeInfo::from_user_data(&[0; NETCODE_USER_DATA_BYTES]),
+            None
+        );
+
+        let data = info.to_user_data_with_token(b"session").unwrap();
+        assert_eq!(HandshakeInfo::from_user_data(&data), Some(info));
+        assert_eq!(HandshakeInfo::auth_token_from_user_data(&data), b"session");
+        assert!(HandshakeInfo::auth_token_from_user_data(&info.to_user_data()).is_empty());
+        assert!(
+            info.to_user_data_with_token(&[0; MAX_AUTH_TOKEN_BYTES + 1])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_version_mismatch_rejected() {
+        let server = HandshakeInfo {
+            protocol_version: 2,
+            game_version: 1,
+        };
+
+        assert_eq!(server.verify(Some(server)), Ok(()));
+        assert_eq!(
+            server.verify(Some(HandshakeInfo {
+                protocol_version: 1,
+                game_version: 1
+            })),
+            Err(HandshakeError::ProtocolMismatch {
+                server: 2,
+                client: 1
+            })
+        );
+        assert_eq!(
+            server.verify(Some(HandshakeInfo {
+                protocol_version: 2,
+                game_version: 0
+            })),
+            Err(HandshakeError::GameVersionMismatch {
+                server: 1,
+                client: 0
+            })
+        );
+        assert_eq!(server.verify(None), Err(HandshakeError::MissingHandshake));
+    }
+}
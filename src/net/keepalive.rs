@@ -0,0 +1,154 @@
+//! Connection keepalive, timeout detection and reconnect policy
+//!
+//! Both ends send an empty heartbeat on the heartbeat channel at a fixed
+//! interval. A peer that hasn't been heard from within the timeout is
+//! disconnected with [`DisconnectReason::Timeout`]. Clients can reconnect
+//! with backoff while keeping their queued reliable messages, then ask the
+//! server for a full snapshot to re-sync.
+
+use std::time::{Duration, Instant};
+
+use super::handshake::HandshakeError;
+use super::transport::TransportConfig;
+
+/// Why a connection ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Nothing was received from the peer within the timeout
+    Timeout,
+    /// The local side called disconnect
+    Requested,
+    /// The peer closed the connection, optionally saying why
+    Remote { reason: String },
+    /// The transport layer failed
+    Transport(String),
+    /// The server refused the handshake, e.g. because of a version mismatch
+    Rejected(HandshakeError),
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectReason::Timeout => write!(f, "connection timed out"),
+            DisconnectReason::Requested => write!(f, "disconnected locally"),
+            DisconnectReason::Remote { reason } => write!(f, "disconnected by peer: {}", reason),
+            DisconnectReason::Transport(error) => write!(f, "transport error: {}", error),
+            DisconnectReason::Rejected(error) => write!(f, "rejected by server: {}", error),
+        }
+    }
+}
+
+/// Heartbeat and timeout tracking for one peer
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    interval: Duration,
+    timeout: Duration,
+    last_sent: Instant,
+    last_received: Instant,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration, timeout: Duration, now: Instant) -> Self {
+        Self {
+            interval,
+            timeout,
+            last_sent: now,
+            last_received: now,
+        }
+    }
+
+    pub fn from_config(config: &TransportConfig, now: Instant) -> Self {
+        Self::new(config.heartbeat_interval, config.timeout, now)
+    }
+
+    /// Record that something arrived from the peer
+    pub fn received(&mut self, now: Instant) {
+        self.last_received = now;
+    }
+
+    /// Whether a heartbeat is due; marks it as sent if so
+    pub fn should_send(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_sent) >= self.interval {
+            self.last_sent = now;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn time_since_received(&self, now: Instant) -> Duration {
+        now.duration_since(self.last_received)
+    }
+
+    pub fn is_timed_out(&self, now: Instant) -> bool {
+        self.time_since_received(now) >= self.timeout
+    }
+}
+
+/// How a client retries after losing its connection
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Attempts before giving up (0 disables reconnecting)
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the given attempt (1-based), doubling each time
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Client connection lifecycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Waiting to retry; `attempt` is 1-based
+    Reconnecting {
+        attempt: u32,
+        next_attempt: Instant,
+    },
+    Disconnected(DisconnectReason),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_timeout() {
+        let start = Instant::now();
+        let mut heartbeat = Heartbeat::new(Duration::from_secs(1), Duration::from_secs(5), start);
+
+        assert!(!heartbeat.should_send(start));
+        assert!(heartbeat.should_send(start + Duration::from_secs(1)));
+        assert!(!heartbeat.is_timed_out(start + Duration::from_secs(4)));
+
+        heartbeat.received(start + Duration::from_secs(4));
+        assert!(!heartbeat.is_timed_out(start + Duration::from_secs(8)));
+        assert!(heartbeat.is_timed_out(start + Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), policy.max_backoff);
+    }
+}
@@ -1,25 +1,51 @@
-/// Networking module for Resonance Engine
-///
-/// This module provides game-agnostic networking primitives:
-/// - Transport layer (UDP with reliability via renet)
-/// - Connection management
-/// - Protocol definitions
-/// - Serialization utilities
-/// - Network time synchronization
-///
-/// This layer knows nothing about game-specific concepts like terrain,
-/// players, or entities. Games must implement their own message types
-/// and replication logic on top of these primitives.
+//! Networking module for Resonance Engine
+//!
+//! This module provides game-agnostic networking primitives:
+//! - Transport layer (UDP with reliability via renet)
+//! - Connection management
+//! - Protocol definitions
+//! - Serialization utilities
+//! - Network time synchronization
+//! - Keepalive, timeout detection and reconnects
+//! - Version handshake and packet encryption
+//! - Pluggable client authentication
+//! - Interest-aware replication of transient effects
+//! - Priority and rate based send scheduling under a byte budget
+//! - Hitbox history for lag-compensated hit validation
+//!
+//! This layer knows nothing about game-specific concepts like terrain,
+//! players, or entities. Games must implement their own message types
+//! and replication logic on top of these primitives.
 
+pub mod auth;
+pub mod clock;
+pub mod connection;
+pub mod effects;
+pub mod handshake;
+pub mod keepalive;
 pub mod protocol;
+pub mod rewind;
+pub mod scheduler;
 pub mod serialization;
-pub mod connection;
 pub mod transport;
-pub mod clock;
 
 // Re-exports for convenience
-pub use protocol::{NetworkChannel, SystemMessage, MessageEnvelope, MessageStats};
-pub use serialization::{serialize, deserialize, serialize_with_length, deserialize_with_length};
-pub use connection::{ServerConnection, ClientConnection};
-pub use transport::{ServerTransport, ClientTransport, TransportConfig};
+pub use auth::{AuthRejection, AuthRequest, AuthResponder, AuthTicket, Authenticator};
 pub use clock::NetworkClock;
+pub use connection::{ClientConnection, ConnectionEvent, ServerConnection};
+pub use effects::{
+    ClientInterest, ClientInterests, EffectHint, EffectKind, EffectReplicator, EffectStats,
+};
+pub use handshake::{
+    HandshakeError, HandshakeInfo, MAX_AUTH_TOKEN_BYTES, PROTOCOL_VERSION, TransportSecurity,
+    generate_connect_token, generate_connect_token_with_auth,
+};
+pub use keepalive::{ConnectionState, DisconnectReason, Heartbeat, ReconnectPolicy};
+pub use protocol::{MessageEnvelope, MessageStats, NetworkChannel, SystemMessage};
+pub use rewind::{Hitbox, LagCompensation, RewindHit};
+pub use scheduler::{ReplicationRule, ScheduledUpdate, SendScheduler, StreamId};
+pub use serialization::{BitReader, BitWriter, PositionQuantization};
+pub use serialization::{deserialize, deserialize_with_length, serialize, serialize_with_length};
+pub use transport::{
+    ChannelConfig, ChannelKind, ClientTransport, ServerTransport, TransportConfig,
+};
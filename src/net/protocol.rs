@@ -1,10 +1,12 @@
-/// Protocol definitions for the Resonance networking layer
-///
-/// This module defines core networking protocols that are game-agnostic.
-/// Games must define their own message types that implement the GameMessage trait.
+//! Protocol definitions for the Resonance networking layer
+//!
+//! This module defines core networking protocols that are game-agnostic.
+//! Games must define their own message types that implement the GameMessage trait.
 
 use serde::{Serialize, Deserialize};
 
+use super::handshake::{HandshakeError, HandshakeInfo};
+
 /// Network channels for different message types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -15,6 +17,8 @@ pub enum NetworkChannel {
     Reliable = 1,
     /// Reliable, ordered, high priority (for connection management)
     System = 2,
+    /// Unreliable, empty keepalive messages handled by the connection
+    Heartbeat = 3,
 }
 
 impl From<NetworkChannel> for u8 {
@@ -59,6 +63,12 @@ pub enum SystemMessage {
     Pong { timestamp: f64 },
     /// Server sends current time for synchronization
     TimeSync { server_time: f64 },
+    /// Client reconnected and needs a full snapshot to re-sync
+    RequestFullSnapshot,
+    /// Client announces its versions right after connecting (client → server)
+    ClientHello(HandshakeInfo),
+    /// Server refused the client's handshake; the connection closes shortly after
+    HandshakeRejected(HandshakeError),
 }
 
 /// Trait that game messages must implement
@@ -72,4 +82,8 @@ pub struct MessageStats {
     pub bytes_sent: u64,
     pub bytes_received: u64,
     pub packets_lost: u64,
+    /// Due updates held back because the connection's budget was spent
+    pub updates_deferred: u64,
+    /// Waiting updates replaced by newer state before they were sent
+    pub updates_dropped: u64,
 }
@@ -0,0 +1,228 @@
+//! Server-side rewind for validating hitscan shots
+//!
+//! Clients render other players in the past, so a shot that hit on the shooter's
+//! screen can miss the targets' current positions on the server. The server
+//! records every target's hitbox each tick in a [`LagCompensation`] and tests
+//! shots against the hitboxes of the tick the shooter was seeing. The history
+//! only reaches back a limited window, so laggy or malicious clients can't hit
+//! targets where they stood seconds ago.
+
+use glam::{Quat, Vec3};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Box around a target, in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub rotation: Quat,
+}
+
+impl Hitbox {
+    pub fn new(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            center,
+            half_extents,
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Distance along the normalized `direction` to where the ray enters the box,
+    /// or 0 if `origin` is inside it. A zero `direction` never hits.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        if direction == Vec3::ZERO {
+            return None;
+        }
+
+        // Slab test in the box's own space
+        let inverse = self.rotation.inverse();
+        let origin = inverse * (origin - self.center);
+        let direction = inverse * direction;
+
+        let mut t_near = 0.0f32;
+        let mut t_far = f32::INFINITY;
+        for axis in 0..3 {
+            let (origin, direction, extent) =
+                (origin[axis], direction[axis], self.half_extents[axis]);
+            // Parallel to this slab: the ray stays inside it or never enters it
+            if direction.abs() < f32::EPSILON {
+                if origin.abs() > extent {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (-extent - origin) / direction;
+            let t2 = (extent - origin) / direction;
+            t_near = t_near.max(t1.min(t2));
+            t_far = t_far.min(t1.max(t2));
+        }
+
+        (t_near <= t_far).then_some(t_near)
+    }
+}
+
+/// Target hit by a rewound ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RewindHit<K> {
+    pub target: K,
+    pub distance: f32,
+    pub point: Vec3,
+    /// Tick the ray was tested against, older than requested if the request
+    /// reached past the history window
+    pub tick: u64,
+}
+
+/// History of target hitboxes over the last few ticks
+///
+/// `K` identifies targets, usually an `Entity` or a game-specific id.
+pub struct LagCompensation<K> {
+    snapshots: VecDeque<(u64, Vec<(K, Hitbox)>)>,
+    window_ticks: u64,
+}
+
+impl<K: Copy + PartialEq> LagCompensation<K> {
+    /// Keep `window` of history at `tick_rate` ticks per second
+    pub fn new(window: Duration, tick_rate: u32) -> Self {
+        let window_ticks = (window.as_secs_f64() * tick_rate as f64).ceil() as u64;
+        Self {
+            snapshots: VecDeque::new(),
+            window_ticks: window_ticks.max(1),
+        }
+    }
+
+    /// Store the hitboxes of `tick`, dropping snapshots that fell out of the window
+    ///
+    /// Call once per tick after movement, with increasing ticks.
+    pub fn record(&mut self, tick: u64, hitboxes: impl IntoIterator<Item = (K, Hitbox)>) {
+        self.snapshots
+            .push_back((tick, hitboxes.into_iter().collect()));
+        let window = self.window_ticks;
+        while self
+            .snapshots
+            .front()
+            .is_some_and(|(oldest, _)| tick.saturating_sub(*oldest) > window)
+        {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Latest snapshot at or before `tick`, or the oldest one kept if `tick` is
+    /// older than the window
+    pub fn hitboxes_at(&self, tick: u64) -> Option<(u64, &[(K, Hitbox)])> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|(recorded, _)| *recorded <= tick)
+            .or(self.snapshots.front())
+            .map(|(recorded, hitboxes)| (*recorded, hitboxes.as_slice()))
+    }
+
+    /// Closest target the ray hits within `max_distance`, with targets where they
+    /// were at `tick`
+    ///
+    /// `shooter` is skipped so rays starting inside its own hitbox don't hit it.
+    /// A zero `direction` hits nothing.
+    pub fn rewind_and_raycast(
+        &self,
+        tick: u64,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        shooter: Option<K>,
+    ) -> Option<RewindHit<K>> {
+        let direction = direction.try_normalize()?;
+        let (recorded, hitboxes) = self.hitboxes_at(tick)?;
+
+        hitboxes
+            .iter()
+            .filter(|(target, _)| Some(*target) != shooter)
+            .filter_map(|(target, hitbox)| {
+                let distance = hitbox.intersect_ray(origin, direction)?;
+                (distance <= max_distance).then_some((*target, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(target, distance)| RewindHit {
+                target,
+                distance,
+                point: origin + direction * distance,
+                tick: recorded,
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewind_hits_past_position() {
+        let mut history = LagCompensation::<u32>::new(Duration::from_millis(500), 20);
+        // Target 1 walks along +X one unit per tick
+        for tick in 0..20 {
+            history.record(
+                tick,
+                [(
+                    1,
+                    Hitbox::new(Vec3::new(tick as f32, 0.0, 0.0), Vec3::splat(0.4)),
+                )],
+            );
+        }
+
+        // Shot at where the target stood at tick 15 misses now but hits rewound
+        let origin = Vec3::new(15.0, 0.0, 10.0);
+        assert!(
+            history
+                .rewind_and_raycast(19, origin, Vec3::NEG_Z, 100.0, None)
+                .is_none()
+        );
+        let hit = history
+            .rewind_and_raycast(15, origin, Vec3::NEG_Z, 100.0, None)
+            .unwrap();
+        assert_eq!((hit.target, hit.tick), (1, 15));
+        assert!((hit.distance - 9.6).abs() < 1e-4);
+
+        // Ticks 9..=19 are kept, a full window back from the newest; older requests use the
+        // oldest snapshot
+        assert_eq!(history.hitboxes_at(0).unwrap().0, 9);
+    }
+
+    #[test]
+    fn test_ray_parallel_to_faces() {
+        let hitbox = Hitbox::new(Vec3::ZERO, Vec3::splat(1.0));
+
+        // Axis-aligned rays have zero components and must neither produce NaN nor miss
+        assert_eq!(
+            hitbox.intersect_ray(Vec3::new(0.5, 0.5, 5.0), Vec3::NEG_Z),
+            Some(4.0)
+        );
+        assert_eq!(
+            hitbox.intersect_ray(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z),
+            None
+        );
+        // Starting on a face, inside the slab of the zero component
+        assert_eq!(
+            hitbox.intersect_ray(Vec3::new(1.0, 0.0, 5.0), Vec3::NEG_Z),
+            Some(4.0)
+        );
+        assert_eq!(hitbox.intersect_ray(Vec3::ZERO, Vec3::X), Some(0.0));
+        assert_eq!(hitbox.intersect_ray(Vec3::ZERO, Vec3::ZERO), None);
+
+        let mut history = LagCompensation::<u32>::new(Duration::from_millis(100), 20);
+        history.record(0, [(1, hitbox)]);
+        assert!(
+            history
+                .rewind_and_raycast(0, Vec3::ZERO, Vec3::ZERO, 100.0, None)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Priority-based send scheduling under a per-connection byte budget
+//!
+//! Replicated data is grouped into streams (usually one per component type),
+//! each with a [`ReplicationRule`] giving its priority and send rate. Updates
+//! are offered every tick; the scheduler sends those that are due, highest
+//! priority first, until the connection's budget for the tick is spent. Due
+//! updates that don't fit are deferred and gain priority while they wait, and
+//! a deferred update replaced by a newer one for the same entity is dropped.
+
+use renet::ClientId;
+use std::any::{TypeId, type_name};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::protocol::MessageStats;
+
+/// How often and how urgently a stream is replicated
+#[derive(Debug, Clone, Copy)]
+pub struct ReplicationRule {
+    pub priority: f32,
+    pub rate_hz: f32,
+}
+
+impl ReplicationRule {
+    pub fn new(priority: f32, rate_hz: f32) -> Self {
+        Self { priority, rate_hz }
+    }
+
+    /// High priority at 20 Hz, for movement
+    pub fn transform() -> Self {
+        Self::new(1.0, 20.0)
+    }
+
+    /// Low priority at 2 Hz, for slowly changing values like health or score
+    pub fn stats() -> Self {
+        Self::new(0.2, 2.0)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.rate_hz.max(f32::EPSILON))
+    }
+}
+
+/// Rule for streams without one: priority 0.5 at 10 Hz
+pub const DEFAULT_RULE: ReplicationRule = ReplicationRule {
+    priority: 0.5,
+    rate_hz: 10.0,
+};
+
+/// Stream an update belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamId(TypeId);
+
+impl StreamId {
+    pub fn of<T: 'static>() -> Self {
+        Self(TypeId::of::<T>())
+    }
+}
+
+/// One serialized update waiting to be sent
+#[derive(Debug, Clone)]
+pub struct ScheduledUpdate {
+    pub stream: StreamId,
+    pub entity: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionSchedule {
+    pending: HashMap<(StreamId, u64), Vec<u8>>,
+    last_sent: HashMap<(StreamId, u64), Instant>,
+    stats: MessageStats,
+}
+
+/// Decides which replicated updates each connection receives per tick
+#[derive(Debug)]
+pub struct SendScheduler {
+    rules: HashMap<StreamId, (ReplicationRule, &'static str)>,
+    connections: HashMap<ClientId, ConnectionSchedule>,
+    /// Bytes of updates each connection may receive per tick
+    pub budget_per_tick: usize,
+}
+
+impl SendScheduler {
+    pub fn new(budget_per_tick: usize) -> Self {
+        Self {
+            rules: HashMap::new(),
+            connections: HashMap::new(),
+            budget_per_tick,
+        }
+    }
+
+    /// Set the rule for updates of `T`. Unregistered streams use [`DEFAULT_RULE`]
+    pub fn register<T: 'static>(&mut self, rule: ReplicationRule) -> &mut Self {
+        self.rules
+            .insert(StreamId::of::<T>(), (rule, type_name::<T>()));
+        self
+    }
+
+    /// Offer the latest state of an entity's stream for a client
+    ///
+    /// Replaces an update for the same entity that is still waiting; the
+    /// replaced one counts as dropped.
+    pub fn offer(&mut self, client_id: ClientId, stream: StreamId, entity: u64, bytes: Vec<u8>) {
+        let connection = self.connections.entry(client_id).or_default();
+        if connection.pending.insert((stream, entity), bytes).is_some() {
+            connection.stats.updates_dropped += 1;
+        }
+    }
+
+    /// Pick this tick's updates for every connection
+    pub fn schedule(&mut self, now: Instant) -> Vec<(ClientId, Vec<ScheduledUpdate>)> {
+        let mut batches = Vec::with_capacity(self.connections.len());
+        let rules = &self.rules;
+        let rule_for =
+            |stream: StreamId| rules.get(&stream).map_or(DEFAULT_RULE, |&(rule, _)| rule);
+
+        for (&client_id, connection) in &mut self.connections {
+            // Due updates, with priority boosted by how long they've been overdue
+            let mut due: Vec<((StreamId, u64), f32)> = connection
+                .pending
+                .keys()
+                .filter_map(|&key| {
+                    let rule = rule_for(key.0);
+                    let interval = rule.interval();
+                    let overdue = match connection.last_sent.get(&key) {
+                        Some(&last) => now.checked_duration_since(last + interval)?,
+                        None => Duration::ZERO,
+                    };
+                    let boost = 1.0 + overdue.as_secs_f32() / interval.as_secs_f32();
+                    Some((key, rule.priority * boost))
+                })
+                .collect();
+            due.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let mut remaining = self.budget_per_tick;
+            let mut updates = Vec::new();
+            for (key, _) in due {
+                let size = connection.pending[&key].len();
+                if size > remaining {
+                    connection.stats.updates_deferred += 1;
+                    continue;
+                }
+                remaining -= size;
+
+                let bytes = connection.pending.remove(&key).unwrap();
+                connection.last_sent.insert(key, now);
+                connection.stats.messages_sent += 1;
+                connection.stats.bytes_sent += bytes.len() as u64;
+                updates.push(ScheduledUpdate {
+                    stream: key.0,
+                    entity: key.1,
+                    bytes,
+                });
+            }
+
+            batches.push((client_id, updates));
+        }
+
+        batches
+    }
+
+    /// Forget a disconnected client
+    pub fn remove_client(&mut self, client_id: ClientId) {
+        self.connections.remove(&client_id);
+    }
+
+    /// Forget a despawned entity on every connection
+    pub fn remove_entity(&mut self, entity: u64) {
+        for connection in self.connections.values_mut() {
+            connection.pending.retain(|&(_, id), _| id != entity);
+            connection.last_sent.retain(|&(_, id), _| id != entity);
+        }
+    }
+
+    /// Send statistics and scheduling decisions for a client
+    pub fn stats(&self, client_id: ClientId) -> Option<&MessageStats> {
+        self.connections
+            .get(&client_id)
+            .map(|connection| &connection.stats)
+    }
+
+    /// Name and rule of every registered stream
+    pub fn streams(&self) -> impl Iterator<Item = (&'static str, ReplicationRule)> + '_ {
+        self.rules.values().map(|&(rule, name)| (name, rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Transform;
+    struct Stats;
+
+    fn scheduler(budget: usize) -> SendScheduler {
+        let mut scheduler = SendScheduler::new(budget);
+        scheduler
+            .register::<Transform>(ReplicationRule::transform())
+            .register::<Stats>(ReplicationRule::stats());
+        scheduler
+    }
+
+    #[test]
+    fn test_budget_defers_low_priority() {
+        let mut scheduler = scheduler(8);
+        let now = Instant::now();
+        scheduler.offer(1, StreamId::of::<Stats>(), 1, vec![0; 8]);
+        scheduler.offer(1, StreamId::of::<Transform>(), 1, vec![0; 8]);
+
+        let sent = scheduler.schedule(now);
+        assert_eq!(sent[0].1.len(), 1);
+        assert_eq!(sent[0].1[0].stream, StreamId::of::<Transform>());
+        assert_eq!(scheduler.stats(1).unwrap().updates_deferred, 1);
+
+        // The deferred update goes out next tick
+        let sent = scheduler.schedule(now + Duration::from_millis(10));
+        assert_eq!(sent[0].1[0].stream, StreamId::of::<Stats>());
+    }
+
+    #[test]
+    fn test_rate_limits_updates() {
+        let mut scheduler = scheduler(1024);
+        let start = Instant::now();
+
+        scheduler.offer(1, StreamId::of::<Stats>(), 1, vec![1]);
+        assert_eq!(scheduler.schedule(start)[0].1.len(), 1);
+
+        // 2 Hz: not due again until 500 ms later; the newer offer replaces the waiting one
+        scheduler.offer(1, StreamId::of::<Stats>(), 1, vec![2]);
+        assert!(
+            scheduler.schedule(start + Duration::from_millis(100))[0]
+                .1
+                .is_empty()
+        );
+        scheduler.offer(1, StreamId::of::<Stats>(), 1, vec![3]);
+        let sent = scheduler.schedule(start + Duration::from_millis(500));
+        assert_eq!(sent[0].1[0].bytes, vec![3]);
+        assert_eq!(scheduler.stats(1).unwrap().updates_dropped, 1);
+    }
+}
@@ -1,24 +1,32 @@
-/// Serialization utilities for network messages
-///
-/// Provides helper functions for serializing and deserializing messages
-/// using bincode (fast binary format).
+//! Serialization utilities for network messages
+//!
+//! Provides helper functions for serializing and deserializing messages
+//! using bincode (fast binary format), plus a bit-level writer/reader with
+//! quantization helpers for bandwidth-sensitive data such as snapshots and
+//! replicated transforms.
 
-use serde::{Serialize, Deserialize};
 use anyhow::Result;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::transform::Transform;
 
 /// Serialize a message to bytes using bincode
 pub fn serialize<T: Serialize>(message: &T) -> Result<Vec<u8>> {
-    Ok(bincode::serialize(message)?)
+    Ok(bincode::serde::encode_to_vec(
+        message,
+        bincode::config::standard(),
+    )?)
 }
 
 /// Deserialize bytes to a message using bincode
 pub fn deserialize<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
-    Ok(bincode::deserialize(bytes)?)
+    Ok(bincode::serde::borrow_decode_from_slice(bytes, bincode::config::standard())?.0)
 }
 
 /// Serialize with size prefix (4 bytes)
 pub fn serialize_with_length<T: Serialize>(message: &T) -> Result<Vec<u8>> {
-    let data = bincode::serialize(message)?;
+    let data = serialize(message)?;
     let len = data.len() as u32;
 
     let mut result = Vec::with_capacity(4 + data.len());
@@ -40,7 +48,332 @@ pub fn deserialize_with_length<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Resul
         anyhow::bail!("Buffer too small for message");
     }
 
-    Ok(bincode::deserialize(&bytes[4..4 + len])?)
+    deserialize(&bytes[4..4 + len])
+}
+
+/// Writes values into a tightly packed bit stream, least significant bit first
+#[derive(Debug, Default, Clone)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    scratch: u64,
+    scratch_bits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(bytes),
+            ..Self::default()
+        }
+    }
+
+    /// Write the low `bits` bits of `value` (1-32 bits)
+    pub fn write_bits(&mut self, value: u32, bits: u32) {
+        debug_assert!((1..=32).contains(&bits));
+        let mask = if bits == 32 {
+            u32::MAX
+        } else {
+            (1u32 << bits) - 1
+        };
+
+        self.scratch |= ((value & mask) as u64) << self.scratch_bits;
+        self.scratch_bits += bits;
+
+        while self.scratch_bits >= 8 {
+            self.bytes.push(self.scratch as u8);
+            self.scratch >>= 8;
+            self.scratch_bits -= 8;
+        }
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_bits(value as u32, 1);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bits(value as u32, 8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bits(value as u32, 16);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bits(value, 32);
+    }
+
+    /// Write an unquantized f32 (32 bits)
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bits(value.to_bits(), 32);
+    }
+
+    /// Write `value` clamped to `[min, max]` using `bits` bits
+    pub fn write_quantized(&mut self, value: f32, min: f32, max: f32, bits: u32) {
+        self.write_bits(quantize(value, min, max, bits), bits);
+    }
+
+    /// Number of bits written so far
+    pub fn bits_written(&self) -> usize {
+        self.bytes.len() * 8 + self.scratch_bits as usize
+    }
+
+    /// Flush the partial byte and return the packed bytes
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.scratch_bits > 0 {
+            self.bytes.push(self.scratch as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Reads values written by [`BitWriter`]
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    bit_position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            bit_position: 0,
+        }
+    }
+
+    /// Read `bits` bits (1-32)
+    pub fn read_bits(&mut self, bits: u32) -> Result<u32> {
+        debug_assert!((1..=32).contains(&bits));
+        if self.remaining_bits() < bits as usize {
+            anyhow::bail!(
+                "Bit stream exhausted: needed {} bits, {} remaining",
+                bits,
+                self.remaining_bits()
+            );
+        }
+
+        let mut value = 0u64;
+        let mut read = 0;
+        while read < bits {
+            let byte = self.bytes[self.bit_position / 8] as u64;
+            let offset = (self.bit_position % 8) as u32;
+            let take = (8 - offset).min(bits - read);
+            let chunk = (byte >> offset) & ((1u64 << take) - 1);
+
+            value |= chunk << read;
+            read += take;
+            self.bit_position += take as usize;
+        }
+
+        Ok(value as u32)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? != 0)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bits(8)? as u8)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(self.read_bits(16)? as u16)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        self.read_bits(32)
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.read_bits(32)?))
+    }
+
+    pub fn read_quantized(&mut self, min: f32, max: f32, bits: u32) -> Result<f32> {
+        Ok(dequantize(self.read_bits(bits)?, min, max, bits))
+    }
+
+    pub fn remaining_bits(&self) -> usize {
+        self.bytes.len() * 8 - self.bit_position
+    }
+}
+
+/// Map `value` in `[min, max]` to an integer of `bits` bits
+pub fn quantize(value: f32, min: f32, max: f32, bits: u32) -> u32 {
+    let steps = ((1u64 << bits) - 1) as f32;
+    let normalized = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    (normalized * steps).round() as u32
+}
+
+/// Inverse of [`quantize`]
+pub fn dequantize(quantized: u32, min: f32, max: f32, bits: u32) -> f32 {
+    let steps = ((1u64 << bits) - 1) as f32;
+    min + (quantized as f32 / steps) * (max - min)
+}
+
+/// Number of bits needed to cover `range` at `precision`
+pub fn bits_for_range(range: f32, precision: f32) -> u32 {
+    let steps = (range / precision).ceil().max(1.0) as u64;
+    (u64::BITS - steps.leading_zeros()).clamp(1, 32)
+}
+
+/// Quantization of world positions to a fixed precision inside world bounds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionQuantization {
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Smallest representable step in world units (default 1cm)
+    pub precision: f32,
+}
+
+impl Default for PositionQuantization {
+    fn default() -> Self {
+        Self {
+            min: Vec3::splat(-4096.0),
+            max: Vec3::splat(4096.0),
+            precision: 0.01,
+        }
+    }
+}
+
+impl PositionQuantization {
+    pub fn new(min: Vec3, max: Vec3, precision: f32) -> Self {
+        Self {
+            min,
+            max,
+            precision,
+        }
+    }
+
+    /// Bits used per axis
+    pub fn bits(&self) -> [u32; 3] {
+        let range = self.max - self.min;
+        [
+            bits_for_range(range.x, self.precision),
+            bits_for_range(range.y, self.precision),
+            bits_for_range(range.z, self.precision),
+        ]
+    }
+
+    pub fn write(&self, writer: &mut BitWriter, position: Vec3) {
+        let bits = self.bits();
+        for axis in 0..3 {
+            writer.write_quantized(position[axis], self.min[axis], self.max[axis], bits[axis]);
+        }
+    }
+
+    pub fn read(&self, reader: &mut BitReader) -> Result<Vec3> {
+        let bits = self.bits();
+        let mut position = Vec3::ZERO;
+        for axis in 0..3 {
+            position[axis] = reader.read_quantized(self.min[axis], self.max[axis], bits[axis])?;
+        }
+        Ok(position)
+    }
+}
+
+/// Default bits per component for [`write_rotation`] (32 bits per rotation in total)
+pub const ROTATION_COMPONENT_BITS: u32 = 10;
+
+/// Largest magnitude of the three smallest components of a unit quaternion
+const SMALLEST_THREE_RANGE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Write a rotation with smallest-three compression: the index of the largest
+/// component (2 bits) followed by the other three at `component_bits` each
+pub fn write_rotation(writer: &mut BitWriter, rotation: Quat, component_bits: u32) {
+    let rotation = rotation.normalize();
+    let components = rotation.to_array();
+
+    let largest = (0..4)
+        .max_by(|&a, &b| components[a].abs().total_cmp(&components[b].abs()))
+        .unwrap_or(3);
+    // q and -q are the same rotation; make the dropped component positive
+    let sign = if components[largest] < 0.0 { -1.0 } else { 1.0 };
+
+    writer.write_bits(largest as u32, 2);
+    for (index, component) in components.iter().enumerate() {
+        if index != largest {
+            writer.write_quantized(
+                component * sign,
+                -SMALLEST_THREE_RANGE,
+                SMALLEST_THREE_RANGE,
+                component_bits,
+            );
+        }
+    }
+}
+
+pub fn read_rotation(reader: &mut BitReader, component_bits: u32) -> Result<Quat> {
+    let largest = reader.read_bits(2)? as usize;
+
+    let mut components = [0.0f32; 4];
+    let mut sum_squares = 0.0;
+    for (index, component) in components.iter_mut().enumerate() {
+        if index != largest {
+            *component = reader.read_quantized(
+                -SMALLEST_THREE_RANGE,
+                SMALLEST_THREE_RANGE,
+                component_bits,
+            )?;
+            sum_squares += *component * *component;
+        }
+    }
+    components[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+
+    Ok(Quat::from_array(components).normalize())
+}
+
+/// Write a velocity with each axis clamped to `[-max_speed, max_speed]`
+pub fn write_velocity(writer: &mut BitWriter, velocity: Vec3, max_speed: f32, bits: u32) {
+    for axis in 0..3 {
+        writer.write_quantized(velocity[axis], -max_speed, max_speed, bits);
+    }
+}
+
+pub fn read_velocity(reader: &mut BitReader, max_speed: f32, bits: u32) -> Result<Vec3> {
+    let mut velocity = Vec3::ZERO;
+    for axis in 0..3 {
+        velocity[axis] = reader.read_quantized(-max_speed, max_speed, bits)?;
+    }
+    Ok(velocity)
+}
+
+/// Write a transform for snapshots: the position quantized to the default
+/// [`PositionQuantization`], the rotation with [`write_rotation`] and the scale
+/// as a single bit when it is one
+///
+/// Fits [`ReplicationOptions::with_encoding`](crate::core::replication::ReplicationOptions::with_encoding).
+pub fn write_transform(writer: &mut BitWriter, transform: &Transform) {
+    PositionQuantization::default().write(writer, transform.position);
+    write_rotation(writer, transform.rotation, ROTATION_COMPONENT_BITS);
+
+    let unit_scale = transform.scale == Vec3::ONE;
+    writer.write_bool(unit_scale);
+    if !unit_scale {
+        for axis in 0..3 {
+            writer.write_f32(transform.scale[axis]);
+        }
+    }
+}
+
+pub fn read_transform(reader: &mut BitReader) -> Result<Transform> {
+    let position = PositionQuantization::default().read(reader)?;
+    let rotation = read_rotation(reader, ROTATION_COMPONENT_BITS)?;
+
+    let mut scale = Vec3::ONE;
+    if !reader.read_bool()? {
+        for axis in 0..3 {
+            scale[axis] = reader.read_f32()?;
+        }
+    }
+    Ok(Transform {
+        position,
+        rotation,
+        scale,
+    })
 }
 
 #[cfg(test)]
@@ -79,4 +412,54 @@ mod tests {
 
         assert_eq!(msg, decoded);
     }
+
+    #[test]
+    fn test_bit_round_trip() {
+        let mut writer = BitWriter::new();
+        writer.write_bool(true);
+        writer.write_bits(5, 3);
+        writer.write_u16(0xBEEF);
+        writer.write_u32(0xDEAD_BEEF);
+        writer.write_f32(1.5);
+        assert_eq!(writer.bits_written(), 1 + 3 + 16 + 32 + 32);
+
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 11);
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_bits(3).unwrap(), 5);
+        assert_eq!(reader.read_u16().unwrap(), 0xBEEF);
+        assert_eq!(reader.read_u32().unwrap(), 0xDEAD_BEEF);
+        assert_eq!(reader.read_f32().unwrap(), 1.5);
+        assert!(reader.read_bits(8).is_err());
+    }
+
+    #[test]
+    fn test_position_quantization_precision() {
+        let quantization = PositionQuantization::default();
+        let position = Vec3::new(123.456, -7.891, 2048.003);
+
+        let mut writer = BitWriter::new();
+        quantization.write(&mut writer, position);
+        // Fewer than half the bits of three raw f32s
+        assert!(writer.bits_written() < 96 / 2 + 16);
+
+        let bytes = writer.finish();
+        let decoded = quantization.read(&mut BitReader::new(&bytes)).unwrap();
+        assert!((decoded - position).abs().max_element() <= quantization.precision);
+    }
+
+    #[test]
+    fn test_rotation_smallest_three() {
+        let rotation = Quat::from_euler(glam::EulerRot::YXZ, 1.2, -0.4, 2.9);
+
+        let mut writer = BitWriter::new();
+        write_rotation(&mut writer, rotation, ROTATION_COMPONENT_BITS);
+        assert_eq!(writer.bits_written(), 32);
+
+        let bytes = writer.finish();
+        let decoded = read_rotation(&mut BitReader::new(&bytes), ROTATION_COMPONENT_BITS).unwrap();
+        assert!(rotation.dot(decoded).abs() > 0.9999);
+    }
 }
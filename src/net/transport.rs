@@ -1,22 +1,103 @@
-/// Transport layer wrapper around renet
-///
-/// Provides UDP-based networking with reliability built on top.
-/// This is the lowest level of the networking stack.
+//! Transport layer wrapper around renet
+//!
+//! Provides UDP-based networking with reliability built on top.
+//! This is the lowest level of the networking stack.
 
-use std::net::{SocketAddr, UdpSocket};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use renet::{ClientId, ConnectionConfig, RenetServer, SendType};
 use renet_netcode::{
-    NetcodeServerTransport, NetcodeClientTransport,
-    ServerAuthentication, ClientAuthentication, ServerConfig,
+    ClientAuthentication, ConnectToken, NetcodeClientTransport, NetcodeServerTransport,
+    ServerAuthentication, ServerConfig,
 };
-use renet::RenetServer;
-use anyhow::Result;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::handshake::{HandshakeInfo, TransportSecurity};
+use super::protocol::NetworkChannel;
+
+/// Delivery guarantees of a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKind {
+    /// Resent until acknowledged, delivered in send order
+    ReliableOrdered,
+    /// Resent until acknowledged, delivered as soon as it arrives
+    ReliableUnordered,
+    /// Not resent; messages older than the newest received one are dropped
+    SequencedUnreliable,
+    /// Not resent, no ordering
+    Unreliable,
+}
+
+impl ChannelKind {
+    pub fn is_reliable(self) -> bool {
+        matches!(
+            self,
+            ChannelKind::ReliableOrdered | ChannelKind::ReliableUnordered
+        )
+    }
+}
+
+/// Configuration of a single message channel
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    pub id: u8,
+    pub kind: ChannelKind,
+    /// Bytes the channel may keep queued. Unreliable channels drop messages past this;
+    /// reliable channels disconnect, since their data can't be dropped
+    pub max_memory_usage_bytes: usize,
+    /// How long to wait for an acknowledgement before resending (reliable channels only)
+    pub resend_time: Duration,
+}
+
+impl ChannelConfig {
+    pub fn new(id: u8, kind: ChannelKind) -> Self {
+        Self {
+            id,
+            kind,
+            max_memory_usage_bytes: 5 * 1024 * 1024,
+            resend_time: Duration::from_millis(300),
+        }
+    }
+
+    pub fn with_max_memory_usage(mut self, bytes: usize) -> Self {
+        self.max_memory_usage_bytes = bytes;
+        self
+    }
+
+    pub fn with_resend_time(mut self, resend_time: Duration) -> Self {
+        self.resend_time = resend_time;
+        self
+    }
+
+    fn to_renet(&self) -> renet::ChannelConfig {
+        let send_type = match self.kind {
+            ChannelKind::ReliableOrdered => SendType::ReliableOrdered {
+                resend_time: self.resend_time,
+            },
+            ChannelKind::ReliableUnordered => SendType::ReliableUnordered {
+                resend_time: self.resend_time,
+            },
+            // Sequencing is layered on top by the connection
+            ChannelKind::SequencedUnreliable | ChannelKind::Unreliable => SendType::Unreliable,
+        };
+
+        renet::ChannelConfig {
+            channel_id: self.id,
+            max_memory_usage_bytes: self.max_memory_usage_bytes,
+            send_type,
+        }
+    }
+}
 
 /// Transport configuration shared between client and server
 #[derive(Debug, Clone)]
 pub struct TransportConfig {
     /// Protocol identifier (must match between client and server)
     pub protocol_id: u64,
+    /// Game build version checked during the handshake; bump when game messages change
+    pub game_version: u32,
+    /// Packet encryption
+    pub security: TransportSecurity,
     /// Maximum transmission unit size (bytes)
     pub max_packet_size: usize,
     /// Send rate (packets per second)
@@ -27,17 +108,70 @@ pub struct TransportConfig {
     pub heartbeat_interval: Duration,
     /// Disconnect timeout
     pub timeout: Duration,
+    /// Bytes each connection may send per tick across all channels
+    pub available_bytes_per_tick: u64,
+    /// Channels used in both directions. Starts with the engine's [`NetworkChannel`]s
+    pub channels: Vec<ChannelConfig>,
 }
 
 impl Default for TransportConfig {
     fn default() -> Self {
         Self {
             protocol_id: 0x4553_4F4E_414E_4345, // "RESONANCE" in hex
+            game_version: 0,
+            security: TransportSecurity::Unsecure,
             max_packet_size: 8192,
             send_rate: 60,
             receive_rate: 60,
             heartbeat_interval: Duration::from_secs(1),
             timeout: Duration::from_secs(10),
+            available_bytes_per_tick: 60_000,
+            channels: vec![
+                ChannelConfig::new(NetworkChannel::Unreliable.into(), ChannelKind::Unreliable),
+                ChannelConfig::new(
+                    NetworkChannel::Reliable.into(),
+                    ChannelKind::ReliableOrdered,
+                ),
+                ChannelConfig::new(NetworkChannel::System.into(), ChannelKind::ReliableOrdered)
+                    .with_resend_time(Duration::from_millis(100)),
+                ChannelConfig::new(NetworkChannel::Heartbeat.into(), ChannelKind::Unreliable)
+                    .with_max_memory_usage(64 * 1024),
+            ],
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Declare a game channel, e.g. sequenced-unreliable for transforms or
+    /// reliable-ordered for chat. Replaces an existing channel with the same id
+    pub fn with_channel(mut self, channel: ChannelConfig) -> Self {
+        self.channels.retain(|existing| existing.id != channel.id);
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn channel(&self, id: u8) -> Option<&ChannelConfig> {
+        self.channels.iter().find(|channel| channel.id == id)
+    }
+
+    /// Ids of channels whose messages are sequenced by the connection
+    pub fn sequenced_channels(&self) -> Vec<u8> {
+        self.channels
+            .iter()
+            .filter(|channel| channel.kind == ChannelKind::SequencedUnreliable)
+            .map(|channel| channel.id)
+            .collect()
+    }
+
+    /// Build the renet connection configuration for these channels
+    pub fn connection_config(&self) -> ConnectionConfig {
+        let channels: Vec<renet::ChannelConfig> =
+            self.channels.iter().map(ChannelConfig::to_renet).collect();
+
+        ConnectionConfig {
+            available_bytes_per_tick: self.available_bytes_per_tick,
+            server_channels_config: channels.clone(),
+            client_channels_config: channels,
         }
     }
 }
@@ -66,7 +200,12 @@ impl ServerTransport {
             max_clients,
             protocol_id: config.protocol_id,
             public_addresses: vec![bind_addr],
-            authentication: ServerAuthentication::Unsecure,
+            authentication: match &config.security {
+                TransportSecurity::Unsecure => ServerAuthentication::Unsecure,
+                TransportSecurity::Secure { private_key } => ServerAuthentication::Secure {
+                    private_key: *private_key,
+                },
+            },
         };
 
         let transport = NetcodeServerTransport::new(server_config, socket)?;
@@ -80,7 +219,7 @@ impl ServerTransport {
     }
 
     pub fn send_packets(&mut self, server: &mut RenetServer) -> Result<()> {
-        let _ = self.transport.send_packets(server);
+        self.transport.send_packets(server);
         Ok(())
     }
 
@@ -92,6 +231,21 @@ impl ServerTransport {
     pub fn addr(&self) -> SocketAddr {
         self.bind_addr
     }
+
+    /// Versions the client announced when connecting, if it sent any
+    pub fn handshake(&self, client_id: ClientId) -> Option<HandshakeInfo> {
+        self.transport
+            .user_data(client_id)
+            .and_then(|data| HandshakeInfo::from_user_data(&data))
+    }
+
+    /// Auth token the client sent when connecting, empty if it sent none
+    pub fn auth_token(&self, client_id: ClientId) -> Vec<u8> {
+        self.transport
+            .user_data(client_id)
+            .map(|data| HandshakeInfo::auth_token_from_user_data(&data))
+            .unwrap_or_default()
+    }
 }
 
 /// Client-side network transport
@@ -102,10 +256,20 @@ pub struct ClientTransport {
 }
 
 impl ClientTransport {
-    pub fn new(
+    /// Connect without encryption. Servers using [`TransportSecurity::Secure`]
+    /// need [`with_connect_token`](Self::with_connect_token) instead
+    pub fn new(server_addr: SocketAddr, client_id: u64, config: TransportConfig) -> Result<Self> {
+        Self::with_auth_token(server_addr, client_id, config, &[])
+    }
+
+    /// Connect without encryption, sending `auth_token` for the server's
+    /// [`Authenticator`](super::auth::Authenticator). Encrypted servers take the
+    /// token inside the connect token instead
+    pub fn with_auth_token(
         server_addr: SocketAddr,
         client_id: u64,
         config: TransportConfig,
+        auth_token: &[u8],
     ) -> Result<Self> {
         let socket = UdpSocket::bind("0.0.0.0:0")?;
         socket.set_nonblocking(true)?;
@@ -116,7 +280,7 @@ impl ClientTransport {
             client_id,
             protocol_id: config.protocol_id,
             server_addr,
-            user_data: None,
+            user_data: Some(HandshakeInfo::new(&config).to_user_data_with_token(auth_token)?),
         };
 
         let transport = NetcodeClientTransport::new(
@@ -128,6 +292,23 @@ impl ClientTransport {
         Ok(Self { transport, config })
     }
 
+    /// Connect to an encrypted server with a token from
+    /// [`generate_connect_token`](super::handshake::generate_connect_token)
+    pub fn with_connect_token(
+        connect_token: ConnectToken,
+        config: TransportConfig,
+    ) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let authentication = ClientAuthentication::Secure { connect_token };
+
+        let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
+
+        Ok(Self { transport, config })
+    }
+
     pub fn update(&mut self, delta: Duration, client: &mut renet::RenetClient) -> Result<()> {
         self.transport.update(delta, client)?;
         Ok(())
@@ -8,28 +8,38 @@ pub use bevy_ecs::prelude::{
 pub use crate::app::{DefaultPlugins, Plugin, Resonance, ResonanceMode, Stage};
 
 // Assets
-pub use crate::assets::{AssetCache, AssetHandle, AssetId, AssetsPlugin};
+pub use crate::assets::{
+    AssetCache, AssetHandle, AssetId, AssetsPlugin, PreloadPlugin, Preloader, wait_for_group,
+};
 
 // Audio
 pub use crate::audio::{AudioListener, AudioPlugin, AudioSource, Spatial3dAudio};
 
 // Core utilities
 pub use crate::core::{
-    FixedTime, GameTick, PerformanceAnalytics, PerformancePlugin, ResonanceError, Result, Time,
-    TimePlugin,
+    FeatureFlags, FixedTime, GameTick, PerformanceAnalytics, PerformancePlugin, ResonanceError,
+    Result, SimRng, Time, TimePlugin,
 };
 
 // Input
 pub use crate::input::{Input, InputPlugin, KeyCode};
 
 // Renderer (including commonly used graphics settings)
-pub use crate::renderer::{Camera, GraphicsSettings, Mesh, MsaaSampleCount, RenderPlugin, Renderer};
+pub use crate::renderer::{
+    AntiAliasing, Camera, Fog, FogMode, Gizmos, GraphicsSettings, InstanceColor, LightProbeGrid,
+    MaterialId, Mesh, MorphWeights, MsaaSampleCount, Projection, RenderLayers, RenderPlugin,
+    RenderTarget, RenderTargets, RenderTexture, Renderer, ScreenshotCaptured, ScreenshotRequest,
+    ShadowCaster, ShadowReceiver, Viewport, WaterSurface, WorldAnchor,
+};
 
 // Transforms
-pub use crate::transform::{Children, GlobalTransform, Parent, Transform, TransformPlugin};
+pub use crate::transform::{
+    Children, GlobalTransform, Parent, Transform, TransformInterpolation,
+    TransformInterpolationPlugin, TransformPlugin,
+};
 
 // Window
-pub use crate::window::{Window, WindowConfig, WindowMode, WindowPlugin};
+pub use crate::window::{BackgroundPolicy, Window, WindowConfig, WindowMode, WindowPlugin};
 
 // Math - re-export commonly used glam types
 pub use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
@@ -1,4 +1,6 @@
 use crate::core::math::*;
+use crate::renderer::components::RenderLayers;
+use crate::renderer::render_target::RenderTargetId;
 use crate::transform::GlobalTransform;
 use bevy_ecs::prelude::*;
 use bytemuck::{Pod, Zeroable};
@@ -23,6 +25,20 @@ impl Plane {
     pub fn distance_to_point(&self, point: Vec3) -> f32 {
         self.normal.dot(point) + self.distance
     }
+
+    /// Point shared by three planes, if they meet in exactly one.
+    pub fn intersection(a: &Plane, b: &Plane, c: &Plane) -> Option<Vec3> {
+        let denominator = a.normal.dot(b.normal.cross(c.normal));
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(
+            (b.normal.cross(c.normal) * -a.distance
+                + c.normal.cross(a.normal) * -b.distance
+                + a.normal.cross(b.normal) * -c.distance)
+                / denominator,
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -59,10 +75,8 @@ impl Frustum {
             Vec3::new(m[0][3] - m[0][1], m[1][3] - m[1][1], m[2][3] - m[2][1]),
             m[3][3] - m[3][1],
         );
-        planes[4] = Plane::new(
-            Vec3::new(m[0][3] + m[0][2], m[1][3] + m[1][2], m[2][3] + m[2][2]),
-            m[3][3] + m[3][2],
-        );
+        // wgpu clip depth runs from 0 to w, so the near plane is z >= 0 rather than z >= -w
+        planes[4] = Plane::new(Vec3::new(m[0][2], m[1][2], m[2][2]), m[3][2]);
         planes[5] = Plane::new(
             Vec3::new(m[0][3] - m[0][2], m[1][3] - m[1][2], m[2][3] - m[2][2]),
             m[3][3] - m[3][2],
@@ -104,37 +118,181 @@ impl Frustum {
         }
         true
     }
+
+    /// Corners of the near face then the far face, each counter-clockwise from bottom-left.
+    /// `None` for degenerate frustums whose planes don't meet in points.
+    pub fn corners(&self) -> Option<[Vec3; 8]> {
+        let [left, right, bottom, top, near, far] = &self.planes;
+        Some([
+            Plane::intersection(left, bottom, near)?,
+            Plane::intersection(right, bottom, near)?,
+            Plane::intersection(right, top, near)?,
+            Plane::intersection(left, top, near)?,
+            Plane::intersection(left, bottom, far)?,
+            Plane::intersection(right, bottom, far)?,
+            Plane::intersection(right, top, far)?,
+            Plane::intersection(left, top, far)?,
+        ])
+    }
+}
+
+/// Normalized rectangle of the render target a camera draws into. `(0, 0)` is the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Self = Self::new(0.0, 0.0, 1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Left or right half of the target, for two-player split-screen.
+    pub fn split_horizontal(right: bool) -> Self {
+        Self::new(if right { 0.5 } else { 0.0 }, 0.0, 0.5, 1.0)
+    }
+
+    /// Top or bottom half of the target.
+    pub fn split_vertical(bottom: bool) -> Self {
+        Self::new(0.0, if bottom { 0.5 } else { 0.0 }, 1.0, 0.5)
+    }
+
+    /// Pixel rectangle `(x, y, width, height)` in a target of `size`, clamped to the target.
+    pub fn to_pixels(&self, size: (u32, u32)) -> (f32, f32, f32, f32) {
+        let (w, h) = (size.0 as f32, size.1 as f32);
+        let x = (self.x.clamp(0.0, 1.0) * w).floor();
+        let y = (self.y.clamp(0.0, 1.0) * h).floor();
+        let width = (self.width * w).round().clamp(1.0, (w - x).max(1.0));
+        let height = (self.height * h).round().clamp(1.0, (h - y).max(1.0));
+        (x, y, width, height)
+    }
+
+    pub fn aspect(&self, size: (u32, u32)) -> f32 {
+        let (_, _, width, height) = self.to_pixels(size);
+        width / height
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Where a camera's image ends up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderTarget {
+    /// The window, tonemapped onto the swapchain.
+    #[default]
+    Window,
+    /// An offscreen target created through [`RenderTargets`](crate::renderer::RenderTargets).
+    Texture(RenderTargetId),
+}
+
+/// How a [`Camera`] maps the view onto the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    /// Distant objects appear smaller, within the camera's `fov`.
+    #[default]
+    Perspective,
+    /// Parallel projection showing `height` world units vertically, for 2D, isometric and
+    /// top-down views. The camera's `fov` is ignored.
+    Orthographic { height: f32 },
 }
 
 #[derive(Component, Debug, Clone, Copy)]
 pub struct Camera {
+    pub projection: Projection,
+    /// Vertical field of view in radians, for perspective projections.
     pub fov: f32,
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
+    /// Part of the target to draw into. `None` covers the whole target.
+    pub viewport: Option<Viewport>,
+    pub target: RenderTarget,
+    /// Cameras render in ascending priority. The first camera clears its target,
+    /// later cameras on the same target draw over it.
+    pub priority: i32,
 }
 
 impl Camera {
     pub fn new(fov: f32, aspect: f32, near: f32, far: f32) -> Self {
         Self {
+            projection: Projection::Perspective,
             fov,
             aspect,
             near,
             far,
+            viewport: None,
+            target: RenderTarget::Window,
+            priority: 0,
         }
     }
 
     pub fn perspective(aspect: f32) -> Self {
-        Self {
-            fov: 45.0_f32.to_radians(),
-            aspect,
-            near: 0.1,
-            far: 10000.0,
-        }
+        Self::new(45.0_f32.to_radians(), aspect, 0.1, 10000.0)
+    }
+
+    /// Orthographic camera showing `height` world units vertically. Objects behind the
+    /// camera's position are clipped, so place it back from the scene.
+    pub fn orthographic(height: f32, aspect: f32) -> Self {
+        Self::new(45.0_f32.to_radians(), aspect, 0.0, 1000.0)
+            .with_projection(Projection::Orthographic { height })
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    pub fn with_target(mut self, target: RenderTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.projection, Projection::Orthographic { .. })
     }
 
     pub fn projection_matrix(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+        match self.projection {
+            Projection::Perspective => {
+                Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * self.aspect;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        }
     }
 
     pub fn view_matrix(&self, transform: &GlobalTransform) -> Mat4 {
@@ -149,9 +307,61 @@ impl Camera {
         Frustum::from_view_projection(self.view_projection_matrix(transform))
     }
 
+    /// Pixel position of `point` on a render target of `target_size`, from its top-left
+    /// corner and offset by the camera's viewport. `None` when the point is behind the camera
+    /// or outside the near and far planes; points beside the viewport are returned as is.
+    pub fn world_to_screen(
+        &self,
+        transform: &GlobalTransform,
+        point: Vec3,
+        target_size: (u32, u32),
+    ) -> Option<Vec2> {
+        let clip = self.view_projection_matrix(transform) * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if !(0.0..=1.0).contains(&ndc.z) {
+            return None;
+        }
+
+        let (x, y, width, height) = self.viewport.unwrap_or_default().to_pixels(target_size);
+        Some(Vec2::new(
+            x + (ndc.x * 0.5 + 0.5) * width,
+            y + (0.5 - ndc.y * 0.5) * height,
+        ))
+    }
+
     pub fn set_aspect(&mut self, aspect: f32) {
         self.aspect = aspect;
     }
+
+    /// Sets the vertical field of view in radians, kept between 0.1 and 179 degrees.
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov.clamp(0.1_f32.to_radians(), 179.0_f32.to_radians());
+    }
+
+    /// Sets the near clip distance, pushing the far plane out if it would end up in front.
+    /// Perspective cameras need a positive near distance and get at least 0.001.
+    pub fn set_near(&mut self, near: f32) {
+        self.near = match self.projection {
+            Projection::Perspective => near.max(0.001),
+            Projection::Orthographic { .. } => near,
+        };
+        self.far = self.far.max(self.near + 0.001);
+    }
+
+    /// Sets the far clip distance, kept just beyond the near plane.
+    pub fn set_far(&mut self, far: f32) {
+        self.far = far.max(self.near + 0.001);
+    }
+
+    /// Sets the height shown by an orthographic camera. Does nothing for perspective cameras.
+    pub fn set_orthographic_height(&mut self, height: f32) {
+        if let Projection::Orthographic { height: current } = &mut self.projection {
+            *current = height.max(f32::EPSILON);
+        }
+    }
 }
 
 impl Default for Camera {
@@ -160,21 +370,85 @@ impl Default for Camera {
     }
 }
 
+/// Marks a camera that jumped to an unrelated pose this frame, e.g. a cut to another shot.
+/// Its motion vectors are zero for the frame instead of smearing across the screen.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct CameraCut;
+
+/// A camera resolved for rendering this frame.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraView {
+    pub entity: Entity,
+    pub view_proj: Mat4,
+    pub viewport: Option<Viewport>,
+    pub target: RenderTarget,
+    pub priority: i32,
+    pub cut: bool,
+    pub layers: RenderLayers,
+}
+
+/// Every camera in render order: ascending priority, ties broken by entity.
+///
+/// Takes `&World` so read-only render nodes can call it from worker threads.
+pub fn sorted_camera_views(world: &World) -> Vec<CameraView> {
+    let Some(mut query) = world.try_query::<(
+        Entity,
+        &Camera,
+        &GlobalTransform,
+        Has<CameraCut>,
+        Option<&RenderLayers>,
+    )>() else {
+        return Vec::new();
+    };
+
+    let mut views: Vec<CameraView> = query
+        .iter(world)
+        .map(|(entity, camera, transform, cut, layers)| CameraView {
+            entity,
+            view_proj: camera.view_projection_matrix(transform),
+            viewport: camera.viewport,
+            target: camera.target,
+            priority: camera.priority,
+            cut,
+            layers: layers.copied().unwrap_or_default(),
+        })
+        .collect();
+
+    views.sort_by_key(|view| (view.priority, view.entity));
+    views
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
+    /// `view_proj` without the TAA jitter, for motion vectors.
+    pub unjittered_view_proj: [[f32; 4]; 4],
+    /// Unjittered view-projection of the previous frame.
+    pub previous_view_proj: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            unjittered_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            previous_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
         }
     }
 
+    /// Sets the view-projection of a camera that hasn't moved since last frame.
     pub fn update_view_proj(&mut self, view_proj: Mat4) {
         self.view_proj = view_proj.to_cols_array_2d();
+        self.unjittered_view_proj = self.view_proj;
+        self.previous_view_proj = self.view_proj;
+    }
+
+    /// Sets the matrices motion vectors are computed from. Call after
+    /// [`update_view_proj`](Self::update_view_proj).
+    pub fn update_motion(&mut self, unjittered_view_proj: Mat4, previous_view_proj: Mat4) {
+        self.unjittered_view_proj = unjittered_view_proj.to_cols_array_2d();
+        self.previous_view_proj = previous_view_proj.to_cols_array_2d();
     }
 }
 
@@ -183,3 +457,61 @@ impl Default for CameraUniform {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_to_pixels() {
+        let left = Viewport::split_horizontal(false);
+        let right = Viewport::split_horizontal(true);
+
+        assert_eq!(left.to_pixels((1920, 1080)), (0.0, 0.0, 960.0, 1080.0));
+        assert_eq!(right.to_pixels((1920, 1080)), (960.0, 0.0, 960.0, 1080.0));
+        assert!((left.aspect((1920, 1080)) - 960.0 / 1080.0).abs() < 1e-6);
+
+        // Rectangles hanging off the target are clamped to it
+        let overflow = Viewport::new(0.75, 0.0, 0.5, 1.0);
+        assert_eq!(overflow.to_pixels((100, 100)), (75.0, 0.0, 25.0, 100.0));
+    }
+
+    #[test]
+    fn test_frustum_corners() {
+        let view_proj = Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 1.0, 10.0);
+        let corners = Frustum::from_view_projection(view_proj).corners().unwrap();
+
+        assert!(
+            corners[0]
+                .truncate()
+                .abs_diff_eq(Vec2::new(-1.0, -1.0), 1e-4)
+        );
+        assert!(corners[6].abs_diff_eq(Vec3::new(1.0, 1.0, -10.0), 1e-4));
+        assert!((corners[0].z + 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_world_to_screen() {
+        let camera = Camera::perspective(1.0).with_viewport(Viewport::split_horizontal(true));
+        let transform = GlobalTransform::default();
+
+        let center = camera.world_to_screen(&transform, Vec3::new(0.0, 0.0, -5.0), (200, 100));
+        assert!(center.unwrap().abs_diff_eq(Vec2::new(150.0, 50.0), 1e-3));
+        // Up in the world is up on screen, toward y = 0
+        let above = camera.world_to_screen(&transform, Vec3::new(0.0, 1.0, -5.0), (200, 100));
+        assert!(above.unwrap().y < 50.0);
+        let behind = camera.world_to_screen(&transform, Vec3::new(0.0, 0.0, 5.0), (200, 100));
+        assert_eq!(behind, None);
+    }
+
+    #[test]
+    fn test_orthographic_frustum_culls_behind_camera() {
+        let camera = Camera::orthographic(10.0, 1.0);
+        let frustum = camera.frustum(&GlobalTransform::default());
+
+        // Size doesn't shrink with distance
+        assert!(frustum.contains_aabb(Vec3::new(4.0, 4.0, -900.0), Vec3::new(4.5, 4.5, -899.0)));
+        assert!(!frustum.contains_aabb(Vec3::new(5.5, 0.0, -10.0), Vec3::new(6.0, 1.0, -9.0)));
+        assert!(!frustum.contains_aabb(Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 1.0, 2.0)));
+    }
+}
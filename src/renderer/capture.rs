@@ -0,0 +1,399 @@
+//! Offscreen rendering and GPU texture readback.
+//!
+//! Used by tooling that needs pixels on the CPU (map export, screenshots)
+//! rather than presenting to the window surface.
+
+use crate::assets::handle::AssetId;
+use crate::core::math::*;
+use crate::renderer::components::{InstanceColor, Mesh, MeshUploaded};
+use crate::renderer::systems::draw::utils::storage;
+use crate::renderer::{
+    CameraUniform, GpuMeshCache, GraphicsSettings, HDR_FORMAT, LightingData, MeshPipeline,
+    ModelUniform, MorphDrawData, Renderer,
+};
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use wgpu::util::DeviceExt;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Blocks until all submitted GPU work has finished.
+pub(crate) fn wait_for_gpu(device: &wgpu::Device) {
+    if let Err(e) = device.poll(wgpu::PollType::wait_indefinitely()) {
+        log::error!("Failed to wait for GPU: {}", e);
+    }
+}
+
+/// Runs pending map callbacks for work that has already finished, without blocking.
+pub(crate) fn poll_gpu(device: &wgpu::Device) {
+    if let Err(e) = device.poll(wgpu::PollType::Poll) {
+        log::error!("Failed to poll GPU: {}", e);
+    }
+}
+
+/// Row pitch of a texture copy, padded to [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`].
+pub(crate) fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * bytes_per_pixel).div_ceil(align) * align
+}
+
+/// Creates a mappable buffer and records a copy of `texture` into it.
+pub(crate) fn encode_readback(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+) -> wgpu::Buffer {
+    let padded_bytes_per_row = padded_bytes_per_row(width, bytes_per_pixel);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    buffer
+}
+
+/// Copies the rows of a mapped readback buffer into a tightly packed `Vec`, then unmaps it.
+pub(crate) fn take_mapped_pixels(
+    buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+) -> Vec<u8> {
+    let unpadded_bytes_per_row = (width * bytes_per_pixel) as usize;
+    let padded_bytes_per_row = padded_bytes_per_row(width, bytes_per_pixel) as usize;
+
+    let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * height as usize);
+    {
+        let data = buffer.slice(..).get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+    }
+    buffer.unmap();
+
+    pixels
+}
+
+/// Copies a 2D texture into CPU memory, tightly packed row by row.
+///
+/// The texture must have `COPY_SRC` usage. Blocks until the copy has finished.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+) -> Vec<u8> {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    let buffer = encode_readback(
+        device,
+        &mut encoder,
+        texture,
+        width,
+        height,
+        bytes_per_pixel,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    buffer.slice(..).map_async(wgpu::MapMode::Read, |result| {
+        if let Err(e) = result {
+            log::error!("Failed to map readback buffer: {}", e);
+        }
+    });
+    wait_for_gpu(device);
+
+    take_mapped_pixels(&buffer, width, height, bytes_per_pixel)
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts raw [`HDR_FORMAT`] pixels to sRGB RGBA8 using the current tonemapping settings.
+pub fn tonemap_to_rgba8(hdr: &[u8], settings: &GraphicsSettings) -> Vec<u8> {
+    let mut out = Vec::with_capacity(hdr.len() / 2);
+
+    for pixel in hdr.chunks_exact(8) {
+        let channel = |i: usize| f16_to_f32(u16::from_le_bytes([pixel[i * 2], pixel[i * 2 + 1]]));
+        let color = Vec3::new(channel(0), channel(1), channel(2)) * settings.exposure();
+        let mapped = settings.tonemapping().apply(color);
+
+        for c in mapped.to_array() {
+            out.push((linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8);
+        }
+        out.push(255);
+    }
+
+    out
+}
+
+/// Renders every uploaded mesh from `view_proj` into an offscreen target and
+/// returns the tonemapped RGBA8 pixels.
+///
+/// Unlike the main pass this draws without frustum culling, since the indirect
+/// batches are culled against the main camera. Returns `None` if the renderer
+/// has not finished initializing.
+pub fn render_offscreen(
+    world: &mut World,
+    view_proj: Mat4,
+    width: u32,
+    height: u32,
+    clear_color: wgpu::Color,
+) -> Option<Vec<u8>> {
+    let mut instances: Vec<(AssetId, ModelUniform)> = world
+        .query_filtered::<(&Mesh, &GlobalTransform, Option<&InstanceColor>), With<MeshUploaded>>()
+        .iter(world)
+        .map(|(mesh, transform, color)| {
+            (
+                mesh.handle.id,
+                storage::compute_model_uniform(transform, color.copied().unwrap_or_default().0),
+            )
+        })
+        .collect();
+    instances.sort_unstable_by_key(|(mesh_id, _)| mesh_id.0);
+
+    render_instances_offscreen(world, &instances, view_proj, width, height, clear_color)
+}
+
+/// Renders the given meshes and model uniforms into an offscreen target and
+/// returns the tonemapped RGBA8 pixels, using the scene's lighting.
+///
+/// Group instances by mesh for fewer vertex buffer switches. Meshes are drawn without
+/// their morph targets. Returns `None` if the renderer has not finished initializing.
+pub fn render_instances_offscreen(
+    world: &World,
+    instances: &[(AssetId, ModelUniform)],
+    view_proj: Mat4,
+    width: u32,
+    height: u32,
+    clear_color: wgpu::Color,
+) -> Option<Vec<u8>> {
+    let renderer = world.get_resource::<Renderer>()?;
+    let pipeline = world.get_resource::<MeshPipeline>()?;
+    let gpu_mesh_cache = world.get_resource::<GpuMeshCache>()?;
+    let lighting_data = world.get_resource::<LightingData>()?;
+    let morph_data = world.get_resource::<MorphDrawData>()?;
+    let settings = world
+        .get_resource::<GraphicsSettings>()
+        .cloned()
+        .unwrap_or_default();
+
+    let device = renderer.device();
+    let queue = renderer.queue();
+    let sample_count = renderer.msaa_sample_count();
+
+    let mut camera_uniform = CameraUniform::new();
+    camera_uniform.update_view_proj(view_proj);
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Offscreen Camera Buffer"),
+        contents: bytemuck::cast_slice(&[camera_uniform]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Offscreen Camera Bind Group"),
+        layout: &pipeline.camera_bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+    });
+
+    // Storage buffers can't be empty, keep one dummy slot
+    let mut models: Vec<ModelUniform> = instances.iter().map(|(_, uniform)| *uniform).collect();
+    if models.is_empty() {
+        models.push(storage::compute_model_uniform(
+            &GlobalTransform::default(),
+            Vec3::ONE,
+        ));
+    }
+    let model_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Offscreen Model Buffer"),
+        contents: bytemuck::cast_slice(&models),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let visibility_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Offscreen Visibility Buffer"),
+        contents: bytemuck::cast_slice(&vec![1u32; models.len()]),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let model_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Offscreen Model Bind Group"),
+        layout: &pipeline.model_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: model_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: visibility_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let create_target = |label: &str, format, samples, usage| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    };
+
+    let color_texture = create_target(
+        "Offscreen Color Texture",
+        HDR_FORMAT,
+        1,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    );
+    let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let msaa_texture = (sample_count > 1).then(|| {
+        create_target(
+            "Offscreen MSAA Texture",
+            HDR_FORMAT,
+            sample_count,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        )
+    });
+    let msaa_view = msaa_texture
+        .as_ref()
+        .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+    let depth_texture = create_target(
+        "Offscreen Depth Texture",
+        DEPTH_FORMAT,
+        sample_count,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let (view, resolve_target) = match msaa_view.as_ref() {
+        Some(msaa_view) => (msaa_view, Some(&color_view)),
+        None => (&color_view, None),
+    };
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Offscreen Render Encoder"),
+    });
+
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Offscreen Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&pipeline.pipeline);
+        render_pass.set_bind_group(0, &camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &model_bind_group, &[]);
+        render_pass.set_bind_group(2, &lighting_data.bind_group, &[]);
+        render_pass.set_bind_group(3, &morph_data.empty, &[]);
+
+        for (index, (mesh_id, _)) in instances.iter().enumerate() {
+            let Some(gpu_mesh) = gpu_mesh_cache.get(mesh_id) else {
+                continue;
+            };
+            if gpu_mesh.index_count == 0 {
+                continue;
+            }
+
+            let instance = index as u32;
+            render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+            render_pass
+                .set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..gpu_mesh.index_count, 0, instance..instance + 1);
+        }
+    }
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let hdr = read_texture(device, queue, &color_texture, width, height, 8);
+    Some(tonemap_to_rgba8(&hdr, &settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3800), 0.5);
+        assert!(f16_to_f32(0x7c00).is_infinite());
+    }
+}
@@ -26,6 +26,72 @@ impl Mesh {
 #[derive(Component)]
 pub struct MeshUploaded;
 
+/// Material a mesh draws with, carried into the render world by extraction.
+/// Meshes without one use material 0.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u32);
+
+/// Per-instance tint multiplied into a mesh's vertex colors.
+/// Instances with different colors still share one instanced draw.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct InstanceColor(pub Vec3);
+
+impl Default for InstanceColor {
+    fn default() -> Self {
+        Self(Vec3::ONE)
+    }
+}
+
+/// Draws the mesh into the shadow maps of point lights with
+/// [`cast_shadows`](crate::renderer::PointLight::cast_shadows) set.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ShadowCaster;
+
+/// Darkens the mesh where a shadow-casting point light is occluded. Meshes without it skip
+/// the shadow lookups.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ShadowReceiver;
+
+/// Layers of a mesh or camera, as a bitmask. A camera draws the meshes it shares a layer
+/// with; either without the component is on layer 0 only.
+///
+/// ```ignore
+/// // Minimap camera sees terrain and markers, the main camera everything but helpers
+/// commands.spawn((minimap_camera, RenderLayers::layer(TERRAIN).with(MARKERS)));
+/// commands.spawn((main_camera, RenderLayers::ALL.without(EDITOR_HELPERS)));
+/// ```
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    pub const DEFAULT: Self = Self(1);
+    pub const ALL: Self = Self(u32::MAX);
+    pub const NONE: Self = Self(0);
+
+    /// Only `layer`, which must be below 32.
+    pub const fn layer(layer: u8) -> Self {
+        Self(1 << layer)
+    }
+
+    pub const fn with(self, layer: u8) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+
+    pub const fn without(self, layer: u8) -> Self {
+        Self(self.0 & !(1 << layer))
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Aabb {
     pub min: Vec3,
@@ -75,6 +141,23 @@ impl Aabb {
 
         Self { min, max }
     }
+
+    /// Slab test. Returns the distance along `direction` to the first hit, or
+    /// `Some(0.0)` if `origin` is inside the box. `direction` must be normalized.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let inv_dir = direction.recip();
+        let t1 = (self.min - origin) * inv_dir;
+        let t2 = (self.max - origin) * inv_dir;
+
+        let t_near = t1.min(t2).max_element();
+        let t_far = t1.max(t2).min_element();
+
+        if t_near > t_far || t_far < 0.0 {
+            return None;
+        }
+
+        Some(t_near.max(0.0))
+    }
 }
 
 #[derive(Component)]
@@ -86,7 +169,24 @@ pub struct GpuModelData {
 #[derive(Resource)]
 pub struct LightingData {
     pub buffer: Buffer,
+    pub point_light_buffer: Buffer,
+    pub point_light_capacity: usize,
+    /// [`ClusterUniform`](crate::renderer::lighting::clusters::ClusterUniform) of this frame.
+    pub cluster_buffer: Buffer,
+    /// Lights per froxel, written by the light cluster pass.
+    pub cluster_light_counts: Buffer,
+    /// `MAX_LIGHTS_PER_CLUSTER` point light indices per froxel.
+    pub cluster_light_indices: Buffer,
     pub bind_group: BindGroup,
+    /// Bind group of the [`LightClusterPipeline`](crate::renderer::LightClusterPipeline).
+    pub cluster_bind_group: BindGroup,
+    pub point_shadows: crate::renderer::lighting::PointShadowMaps,
+    /// [`LightProbeUniform`](crate::renderer::lighting::probes::LightProbeUniform)s of the
+    /// [`LightProbeGrid`](crate::renderer::LightProbeGrid).
+    pub probe_buffer: Buffer,
+    pub probe_capacity: usize,
+    /// Probes uploaded to `probe_buffer`, reuploaded when the grid changes size.
+    pub probe_count: usize,
 }
 
 #[derive(Resource)]
@@ -101,8 +201,10 @@ pub struct ModelStorageData {
 pub struct MeshDrawBatch {
     pub mesh_id: AssetId,
     pub indirect_buffer: Buffer,
+    /// Instanced draws in `indirect_buffer`, one per run of consecutive instances.
     pub draw_count: u32,
     pub base_instance: u32,
+    /// Sorted model storage indices drawn by this batch.
     pub visible_instances: Vec<u32>,
     pub buffer_capacity: u32,
 }
@@ -112,6 +214,23 @@ pub struct IndirectDrawData {
     pub batches: Vec<MeshDrawBatch>,
 }
 
+/// Batches of cameras whose [`RenderLayers`] leave out some of what [`IndirectDrawData`]
+/// draws, one set per distinct mask. Cameras without an entry draw the shared batches.
+/// These aren't culled on the GPU.
+#[derive(Resource, Default)]
+pub struct LayerDrawData {
+    pub layers: Vec<(RenderLayers, Vec<MeshDrawBatch>)>,
+}
+
+impl LayerDrawData {
+    pub fn batches_for(&self, layers: RenderLayers) -> Option<&[MeshDrawBatch]> {
+        self.layers
+            .iter()
+            .find(|(mask, _)| *mask == layers)
+            .map(|(_, batches)| batches.as_slice())
+    }
+}
+
 #[derive(Resource)]
 pub struct SsaoBindGroupCache {
     pub bind_group: BindGroup,
@@ -0,0 +1,245 @@
+//! GPU compute without a window.
+//!
+//! [`ComputeContext`] owns its own device and queue, created without a surface, so it works
+//! on dedicated servers and in headless runs where no [`Renderer`](crate::renderer::Renderer)
+//! exists. It is meant for batch work such as navmesh baking or crowd simulation, not for
+//! anything that feeds the frame being rendered; the render graph's compute nodes use the
+//! renderer's device instead.
+//!
+//! ```ignore
+//! fn bake(compute: Res<ComputeContext>) -> anyhow::Result<()> {
+//!     let pipeline = compute.create_pipeline("Double", DOUBLE_WGSL, "main");
+//!     let values = compute.create_storage_buffer("Values", &[1.0f32, 2.0, 3.0]);
+//!     let bind_group = compute.bind_buffers(&pipeline, 0, &[&values]);
+//!     compute.dispatch(&pipeline, &[&bind_group], (1, 1, 1));
+//!     let doubled: Vec<f32> = compute.read_buffer(&values)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::app::{Plugin, Resonance};
+use crate::renderer::capture::wait_for_gpu;
+use anyhow::{Result, anyhow};
+use bevy_ecs::prelude::Resource;
+use bytemuck::Pod;
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, Buffer, BufferUsages, ComputePipeline, Device, Queue};
+
+#[derive(Resource)]
+pub struct ComputeContext {
+    device: Device,
+    queue: Queue,
+    adapter_info: wgpu::AdapterInfo,
+}
+
+impl ComputeContext {
+    /// Creates a device on the most capable adapter. Fails when no adapter is available,
+    /// e.g. on a server without a GPU or software rasterizer.
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            flags: wgpu::InstanceFlags::empty(),
+            ..Default::default()
+        });
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))?;
+
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                label: Some("Resonance Compute Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: Default::default(),
+                experimental_features: Default::default(),
+                trace: wgpu::Trace::Off,
+            }))?;
+
+        Ok(Self {
+            device,
+            queue,
+            adapter_info: adapter.get_info(),
+        })
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// A storage buffer initialized with `data` that can be read back with
+    /// [`read_buffer`](Self::read_buffer).
+    pub fn create_storage_buffer<T: Pod>(&self, label: &str, data: &[T]) -> Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(data),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            })
+    }
+
+    /// An uninitialized storage buffer of `size` bytes, e.g. for results.
+    pub fn create_output_buffer(&self, label: &str, size: u64) -> Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn create_uniform_buffer<T: Pod>(&self, label: &str, value: &T) -> Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::bytes_of(value),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            })
+    }
+
+    pub fn write_buffer<T: Pod>(&self, buffer: &Buffer, data: &[T]) {
+        self.queue
+            .write_buffer(buffer, 0, bytemuck::cast_slice(data));
+    }
+
+    /// Compiles a compute pipeline with its bind group layouts derived from the shader.
+    pub fn create_pipeline(&self, label: &str, wgsl: &str, entry_point: &str) -> ComputePipeline {
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(wgsl.into()),
+            });
+        self.device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+    }
+
+    /// Binds `buffers` to bindings `0..buffers.len()` of bind group `group`.
+    pub fn bind_buffers(
+        &self,
+        pipeline: &ComputePipeline,
+        group: u32,
+        buffers: &[&Buffer],
+    ) -> BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(group),
+            entries: &entries,
+        })
+    }
+
+    /// Records and submits one dispatch, with `bind_groups` bound to groups `0..`. Doesn't
+    /// wait for it to finish.
+    pub fn dispatch(
+        &self,
+        pipeline: &ComputePipeline,
+        bind_groups: &[&BindGroup],
+        workgroups: (u32, u32, u32),
+    ) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Dispatch Encoder"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Dispatch"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                pass.set_bind_group(index as u32, *bind_group, &[]);
+            }
+            pass.dispatch_workgroups(workgroups.0, workgroups.1, workgroups.2);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Copies a buffer created with `COPY_SRC` back to the CPU. Blocks until all submitted
+    /// work, and the copy, have finished.
+    pub fn read_buffer<T: Pod>(&self, buffer: &Buffer) -> Result<Vec<T>> {
+        let size = buffer.size();
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Compute Readback Buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Readback Encoder"),
+            });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        wait_for_gpu(&self.device);
+        receiver
+            .recv()
+            .map_err(|_| anyhow!("Readback buffer was never mapped"))??;
+
+        // Copied rather than cast, since the mapping is only guaranteed 8-byte alignment
+        let mut data = vec![T::zeroed(); size as usize / std::mem::size_of::<T>()];
+        {
+            let mapped = staging.slice(..).get_mapped_range();
+            let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+            let len = bytes.len();
+            bytes.copy_from_slice(&mapped[..len]);
+        }
+        staging.unmap();
+        Ok(data)
+    }
+}
+
+/// Inserts a [`ComputeContext`] when a GPU adapter is available. Works on clients and
+/// servers and doesn't need the window or render plugins.
+#[derive(Default)]
+pub struct ComputePlugin;
+
+impl Plugin for ComputePlugin {
+    fn build(&self, engine: &mut Resonance) {
+        match ComputeContext::new() {
+            Ok(context) => {
+                log::info!(
+                    "Compute device: {} ({:?})",
+                    context.adapter_info.name,
+                    context.adapter_info.backend
+                );
+                engine.world.insert_resource(context);
+            }
+            Err(e) => log::warn!("GPU compute unavailable: {}", e),
+        }
+    }
+}
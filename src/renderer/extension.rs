@@ -0,0 +1,160 @@
+//! Adding render passes from outside the renderer.
+//!
+//! A [`RenderExtension`] creates its pipelines, bind group layouts and
+//! [`RenderNode`](crate::renderer::RenderNode)s once the renderer exists, from a
+//! [`RenderSetup`] holding the device, target formats and the layouts the engine's own
+//! pipelines share. Extensions are registered from a plugin and set up at the start of the
+//! first frame the renderer is ready, or the frame after they are added:
+//!
+//! ```ignore
+//! struct OutlinePlugin;
+//!
+//! impl Plugin for OutlinePlugin {
+//!     fn build(&self, engine: &mut Resonance) {
+//!         engine
+//!             .world
+//!             .get_resource_or_init::<RenderExtensions>()
+//!             .add(OutlineExtension);
+//!     }
+//! }
+//!
+//! impl RenderExtension for OutlineExtension {
+//!     fn name(&self) -> &str {
+//!         "outline"
+//!     }
+//!
+//!     fn setup(&self, setup: &RenderSetup, world: &mut World, graph: &mut RenderGraph) {
+//!         world.insert_resource(OutlinePipeline::new(setup));
+//!         graph.add_node(Box::new(OutlineNode::new()));
+//!     }
+//! }
+//! ```
+//!
+//! See `examples/custom_pass.rs` for a complete pass.
+
+use crate::renderer::{HDR_FORMAT, MeshPipeline, RenderGraph, Renderer, VELOCITY_FORMAT};
+use bevy_ecs::prelude::*;
+use std::sync::Arc;
+use wgpu::{BindGroupLayout, Device, Queue, TextureFormat};
+
+/// Depth format of the main pass depth targets.
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// What an extension builds its pipelines against.
+pub struct RenderSetup<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    /// Format of the window surface, written by the tonemap node and anything after it.
+    pub surface_format: TextureFormat,
+    /// Format of the HDR target scene passes render into.
+    pub hdr_format: TextureFormat,
+    pub depth_format: TextureFormat,
+    pub velocity_format: TextureFormat,
+    /// MSAA sample count of the scene targets.
+    pub sample_count: u32,
+    /// Layout of [`RenderContext::camera_bind_group`](crate::renderer::RenderContext::camera_bind_group).
+    pub camera_bind_group_layout: &'a BindGroupLayout,
+    /// Layout of the per-frame model storage buffers.
+    pub model_bind_group_layout: &'a BindGroupLayout,
+    /// Layout of [`LightingData::bind_group`](crate::renderer::LightingData).
+    pub lighting_bind_group_layout: &'a BindGroupLayout,
+}
+
+pub trait RenderExtension: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    /// Creates the extension's pipelines and adds its nodes to the graph. Called once.
+    fn setup(&self, setup: &RenderSetup, world: &mut World, graph: &mut RenderGraph);
+
+    /// Called after [`GraphicsSettings`](crate::renderer::GraphicsSettings) change, when the
+    /// engine rebuilds its own pipelines. Pipelines that depend on the sample count should be
+    /// rebuilt here.
+    fn settings_changed(&self, _setup: &RenderSetup, _world: &mut World) {}
+}
+
+/// Registered render extensions.
+#[derive(Resource, Default)]
+pub struct RenderExtensions {
+    pending: Vec<Arc<dyn RenderExtension>>,
+    active: Vec<Arc<dyn RenderExtension>>,
+}
+
+impl RenderExtensions {
+    pub fn add(&mut self, extension: impl RenderExtension) -> &mut Self {
+        self.pending.push(Arc::new(extension));
+        self
+    }
+
+    /// Names of the extensions that have been set up.
+    pub fn active(&self) -> impl Iterator<Item = &str> {
+        self.active.iter().map(|extension| extension.name())
+    }
+}
+
+/// Sets up extensions added since the last run, once the renderer exists.
+pub fn setup_render_extensions(world: &mut World) {
+    let has_pending = world
+        .get_resource::<RenderExtensions>()
+        .is_some_and(|extensions| !extensions.pending.is_empty());
+    if !has_pending || !world.contains_resource::<RenderGraph>() {
+        return;
+    }
+
+    let pending = std::mem::take(&mut world.resource_mut::<RenderExtensions>().pending);
+    with_render_setup(world, |setup, world, graph| {
+        for extension in &pending {
+            extension.setup(setup, world, graph);
+            log::info!("Render extension '{}' set up", extension.name());
+        }
+    });
+    world
+        .resource_mut::<RenderExtensions>()
+        .active
+        .extend(pending);
+}
+
+/// Lets active extensions rebuild their pipelines after a graphics settings change.
+pub(crate) fn notify_settings_changed(world: &mut World) {
+    let Some(extensions) = world.get_resource::<RenderExtensions>() else {
+        return;
+    };
+    let active = extensions.active.clone();
+    if active.is_empty() {
+        return;
+    }
+
+    with_render_setup(world, |setup, world, _| {
+        for extension in &active {
+            extension.settings_changed(setup, world);
+        }
+    });
+}
+
+fn with_render_setup(
+    world: &mut World,
+    f: impl FnOnce(&RenderSetup, &mut World, &mut RenderGraph),
+) {
+    if !world.contains_resource::<Renderer>() || !world.contains_resource::<MeshPipeline>() {
+        return;
+    }
+
+    world.resource_scope(|world, renderer: Mut<Renderer>| {
+        world.resource_scope(|world, mesh_pipeline: Mut<MeshPipeline>| {
+            world.resource_scope(|world, mut graph: Mut<RenderGraph>| {
+                let setup = RenderSetup {
+                    device: renderer.device(),
+                    queue: renderer.queue(),
+                    surface_format: renderer.config().format,
+                    hdr_format: HDR_FORMAT,
+                    depth_format: DEPTH_FORMAT,
+                    velocity_format: VELOCITY_FORMAT,
+                    sample_count: renderer.msaa_sample_count(),
+                    camera_bind_group_layout: &mesh_pipeline.camera_bind_group_layout,
+                    model_bind_group_layout: &mesh_pipeline.model_bind_group_layout,
+                    lighting_bind_group_layout: &mesh_pipeline.lighting_bind_group_layout,
+                };
+                f(&setup, world, &mut graph);
+            });
+        });
+    });
+}
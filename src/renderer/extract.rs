@@ -0,0 +1,233 @@
+//! Extraction of render data from gameplay components.
+//!
+//! [`extract_render_data`] runs once per frame after transforms have propagated and
+//! copies what draw preparation needs into [`ExtractedScene`]. Draw preparation reads
+//! only that resource, so it doesn't depend on how gameplay components are laid out
+//! and doesn't hold queries over the main world while it runs.
+//!
+//! Extraction also remembers each instance's model matrix for one frame, so the main pass
+//! can write per-object motion vectors from [`ExtractedMesh::previous_model`].
+
+use crate::assets::handle::AssetId;
+use crate::core::math::{Mat4, Vec3};
+use crate::renderer::Camera;
+use crate::renderer::components::{
+    Aabb, InstanceColor, MaterialId, Mesh, MeshUploaded, RenderLayers, ShadowCaster, ShadowReceiver,
+};
+use crate::renderer::morph::MorphWeights;
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Per-instance flags computed during extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderFlags(u8);
+
+impl RenderFlags {
+    pub const NONE: Self = Self(0);
+    /// The instance has a local [`Aabb`] and can be culled.
+    pub const HAS_AABB: Self = Self(1 << 0);
+    /// The instance's transform changed since last frame.
+    pub const TRANSFORM_CHANGED: Self = Self(1 << 1);
+    /// The instance's [`InstanceColor`] or [`ShadowReceiver`] changed or was added since
+    /// last frame.
+    pub const COLOR_CHANGED: Self = Self(1 << 2);
+    /// The instance moved last frame, so its previous model matrix changed even if its
+    /// transform didn't.
+    pub const MOTION_CHANGED: Self = Self(1 << 3);
+    /// The instance has a [`ShadowCaster`] and is drawn into point light shadow maps.
+    pub const SHADOW_CASTER: Self = Self(1 << 4);
+    /// The instance has a [`ShadowReceiver`].
+    pub const SHADOW_RECEIVER: Self = Self(1 << 5);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractedMesh {
+    pub entity: Entity,
+    pub mesh_id: AssetId,
+    pub transform: GlobalTransform,
+    pub aabb: Option<Aabb>,
+    pub material: u32,
+    pub color: Vec3,
+    /// Model matrix of the previous frame; equal to the current one for new instances.
+    pub previous_model: Mat4,
+    pub flags: RenderFlags,
+    pub layers: RenderLayers,
+}
+
+impl ExtractedMesh {
+    /// Whether the instance's model data has to be re-uploaded.
+    pub fn needs_upload(&self) -> bool {
+        self.flags.intersects(
+            RenderFlags::TRANSFORM_CHANGED
+                .union(RenderFlags::COLOR_CHANGED)
+                .union(RenderFlags::MOTION_CHANGED),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractedCamera {
+    pub entity: Entity,
+    pub camera: Camera,
+    pub transform: GlobalTransform,
+    pub layers: RenderLayers,
+}
+
+/// Render-side copy of the scene for the current frame. Buffers keep their capacity between frames.
+#[derive(Resource, Default)]
+pub struct ExtractedScene {
+    pub meshes: Vec<ExtractedMesh>,
+    pub cameras: Vec<ExtractedCamera>,
+    /// `(model, previous model)` of every instance as of the last extraction.
+    models: HashMap<Entity, (Mat4, Mat4)>,
+    /// Scratch map swapped with `models` so despawned entities drop out.
+    next_models: HashMap<Entity, (Mat4, Mat4)>,
+    /// [`MorphWeights`] of every instance that has them, back to back.
+    morph_weights: Vec<f32>,
+    morph_ranges: HashMap<Entity, std::ops::Range<usize>>,
+}
+
+impl ExtractedScene {
+    pub fn any_transform_changed(&self) -> bool {
+        self.meshes
+            .iter()
+            .any(|mesh| mesh.flags.contains(RenderFlags::TRANSFORM_CHANGED))
+    }
+
+    /// Whether any instance's transform or color changed since last frame.
+    pub fn any_upload_needed(&self) -> bool {
+        self.meshes.iter().any(ExtractedMesh::needs_upload)
+    }
+
+    pub fn morph_weights(&self, entity: Entity) -> Option<&[f32]> {
+        self.morph_ranges
+            .get(&entity)
+            .map(|range| &self.morph_weights[range.clone()])
+    }
+}
+
+type MeshExtractData = (
+    Entity,
+    &'static Mesh,
+    Ref<'static, GlobalTransform>,
+    Option<&'static Aabb>,
+    Option<&'static MaterialId>,
+    Option<Ref<'static, InstanceColor>>,
+    Has<ShadowCaster>,
+    Option<Ref<'static, ShadowReceiver>>,
+    Option<&'static RenderLayers>,
+);
+
+pub fn extract_render_data(
+    mut extracted: ResMut<ExtractedScene>,
+    meshes: Query<MeshExtractData, With<MeshUploaded>>,
+    cameras: Query<(Entity, &Camera, &GlobalTransform, Option<&RenderLayers>)>,
+    morph_weights: Query<(Entity, &MorphWeights), With<MeshUploaded>>,
+    mut removed_colors: RemovedComponents<InstanceColor>,
+    mut removed_receivers: RemovedComponents<ShadowReceiver>,
+) {
+    let extracted = &mut *extracted;
+    // Instances that lost their color go back to white and need a re-upload, and so do
+    // instances that stopped receiving shadows
+    let uncolored: Vec<Entity> = removed_colors
+        .read()
+        .chain(removed_receivers.read())
+        .collect();
+
+    let models = &extracted.models;
+    let next_models = &mut extracted.next_models;
+    next_models.clear();
+    extracted.meshes.clear();
+    extracted.meshes.extend(meshes.iter().map(
+        |(entity, mesh, transform, aabb, material, color, caster, receiver, layers)| {
+            let mut flags = RenderFlags::NONE;
+            if aabb.is_some() {
+                flags.insert(RenderFlags::HAS_AABB);
+            }
+            if transform.is_changed() {
+                flags.insert(RenderFlags::TRANSFORM_CHANGED);
+            }
+            if color.as_ref().is_some_and(|color| color.is_changed())
+                || receiver
+                    .as_ref()
+                    .is_some_and(|receiver| receiver.is_added())
+                || uncolored.contains(&entity)
+            {
+                flags.insert(RenderFlags::COLOR_CHANGED);
+            }
+            if caster {
+                flags.insert(RenderFlags::SHADOW_CASTER);
+            }
+            if receiver.is_some() {
+                flags.insert(RenderFlags::SHADOW_RECEIVER);
+            }
+
+            let model = transform.matrix();
+            let previous_model = match models.get(&entity) {
+                Some(&(last_model, last_previous)) => {
+                    if last_model != last_previous {
+                        flags.insert(RenderFlags::MOTION_CHANGED);
+                    }
+                    last_model
+                }
+                None => model,
+            };
+            next_models.insert(entity, (model, previous_model));
+
+            ExtractedMesh {
+                entity,
+                mesh_id: mesh.handle.id,
+                transform: *transform,
+                aabb: aabb.copied(),
+                material: material.map(|material| material.0).unwrap_or(0),
+                color: color.map_or(Vec3::ONE, |color| color.0),
+                previous_model,
+                flags,
+                layers: layers.copied().unwrap_or_default(),
+            }
+        },
+    ));
+
+    std::mem::swap(&mut extracted.models, &mut extracted.next_models);
+
+    extracted.morph_weights.clear();
+    extracted.morph_ranges.clear();
+    for (entity, weights) in &morph_weights {
+        let start = extracted.morph_weights.len();
+        extracted.morph_weights.extend_from_slice(&weights.0);
+        extracted
+            .morph_ranges
+            .insert(entity, start..extracted.morph_weights.len());
+    }
+
+    extracted.cameras.clear();
+    extracted
+        .cameras
+        .extend(
+            cameras
+                .iter()
+                .map(|(entity, camera, transform, layers)| ExtractedCamera {
+                    entity,
+                    camera: *camera,
+                    transform: *transform,
+                    layers: layers.copied().unwrap_or_default(),
+                }),
+        );
+}
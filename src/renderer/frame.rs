@@ -0,0 +1,87 @@
+//! Frames in flight.
+//!
+//! The CPU may run up to N frames ahead of the GPU. Each slot is fenced on the work
+//! submitted for it, and the renderer waits for the current slot at the start of
+//! PostUpdate, before draw preparation writes anything for the frame.
+//!
+//! Only the camera uniform and its bind group are kept once per slot and rotated by
+//! [`FrameSync::frame_index`]. The model storage, instance, indirect and lighting buffers
+//! stay single-instance. The CPU never writes them through a mapping. It writes them with
+//! `queue.write_buffer` or the [`GpuUploader`](super::GpuUploader)'s copies. The queue runs
+//! those after all previously submitted work, so a frame in flight never sees the next
+//! frame's data. Their incremental updates also build on the previous frame's contents, so
+//! rotating them would mean re-uploading everything once per slot.
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FRAMES_IN_FLIGHT: u32 = 2;
+pub const MAX_FRAMES_IN_FLIGHT: u32 = 3;
+
+pub struct FrameSync {
+    frame_index: usize,
+    frame_count: u64,
+    /// Last submission made for each slot.
+    fences: Vec<Option<wgpu::SubmissionIndex>>,
+}
+
+impl FrameSync {
+    pub fn new(frames_in_flight: u32) -> Self {
+        let frames_in_flight = frames_in_flight.clamp(1, MAX_FRAMES_IN_FLIGHT) as usize;
+        Self {
+            frame_index: 0,
+            frame_count: 0,
+            fences: vec![None; frames_in_flight],
+        }
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Slot of the frame currently being prepared.
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Number of frames ended so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Blocks until the GPU has finished the last frame that used the current slot.
+    ///
+    /// Returns how long the CPU waited.
+    pub fn wait_for_slot(&mut self, device: &wgpu::Device) -> Duration {
+        let Some(fence) = self.fences[self.frame_index].take() else {
+            return Duration::ZERO;
+        };
+
+        let start = Instant::now();
+        let wait = wgpu::PollType::Wait {
+            submission_index: Some(fence),
+            timeout: None,
+        };
+        if let Err(e) = device.poll(wait) {
+            log::error!("Failed to wait for frame slot: {}", e);
+        }
+        start.elapsed()
+    }
+
+    /// Fences the current slot on everything submitted so far and advances to the next slot.
+    pub fn end_frame(&mut self, queue: &wgpu::Queue) {
+        // An empty submission yields an index that covers everything submitted before it
+        let fence = queue.submit(std::iter::empty());
+
+        self.fences[self.frame_index] = Some(fence);
+        self.frame_index = (self.frame_index + 1) % self.fences.len();
+        self.frame_count += 1;
+    }
+
+    /// Blocks until no frame is in flight.
+    pub fn wait_idle(&mut self, device: &wgpu::Device) {
+        if self.fences.iter().any(Option::is_some) {
+            super::capture::wait_for_gpu(device);
+        }
+        self.fences.fill(None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Immediate-mode debug drawing.
+//!
+//! Any system can draw lines and shapes through the [`Gizmos`] resource. Calls
+//! accumulate over the frame, are drawn by [`GizmoPassNode`](crate::renderer::GizmoPassNode)
+//! on top of the scene for the first window camera, and are cleared in `Stage::Last`.
+//!
+//! ```no_run
+//! use resonance::prelude::*;
+//! use resonance::renderer::Aabb;
+//!
+//! fn draw_bounds(mut gizmos: ResMut<Gizmos>, query: Query<(&GlobalTransform, &Aabb)>) {
+//!     for (transform, aabb) in &query {
+//!         let position = transform.position();
+//!         gizmos.aabb(aabb.min + position, aabb.max + position, Vec3::new(0.0, 1.0, 0.0));
+//!     }
+//!     gizmos.ray(Vec3::ZERO, Vec3::Y * 2.0, Vec3::new(1.0, 0.0, 0.0));
+//! }
+//! ```
+
+use crate::core::math::*;
+use crate::renderer::camera::Frustum;
+use bevy_ecs::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+/// Segments used for each circle of a sphere.
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl GizmoVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GizmoVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Lines drawn this frame, two vertices per line.
+#[derive(Resource)]
+pub struct Gizmos {
+    vertices: Vec<GizmoVertex>,
+    enabled: bool,
+    /// Whether gizmos are hidden behind closer geometry.
+    pub depth_test: bool,
+}
+
+impl Default for Gizmos {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::new(),
+            enabled: true,
+            depth_test: true,
+        }
+    }
+}
+
+impl Gizmos {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.vertices.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Vec3) {
+        if !self.enabled {
+            return;
+        }
+        self.vertices.push(GizmoVertex {
+            position: from.to_array(),
+            color: color.to_array(),
+        });
+        self.vertices.push(GizmoVertex {
+            position: to.to_array(),
+            color: color.to_array(),
+        });
+    }
+
+    /// Draws a line from `origin` along `direction`, including its length.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Vec3) {
+        self.line(origin, origin + direction, color);
+    }
+
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+        let corners = [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(max.x, max.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+        ];
+        self.box_edges(&corners, color);
+    }
+
+    /// Draws a sphere as three axis-aligned circles.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        self.circle(center, Vec3::X, radius, color);
+        self.circle(center, Vec3::Y, radius, color);
+        self.circle(center, Vec3::Z, radius, color);
+    }
+
+    /// Draws a circle around `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec3) {
+        let (u, v) = normal.normalize_or(Vec3::Y).any_orthonormal_pair();
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Draws the edges of a camera frustum, e.g. from [`Camera::frustum`](crate::renderer::Camera::frustum).
+    pub fn frustum(&mut self, frustum: &Frustum, color: Vec3) {
+        if let Some(corners) = frustum.corners() {
+            self.box_edges(&corners, color);
+        }
+    }
+
+    /// Vertices recorded so far this frame.
+    pub fn vertices(&self) -> &[GizmoVertex] {
+        &self.vertices
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Corners ordered as the bottom face, then the top face directly above it.
+    fn box_edges(&mut self, corners: &[Vec3; 8], color: Vec3) {
+        for i in 0..4 {
+            let next = (i + 1) % 4;
+            self.line(corners[i], corners[next], color);
+            self.line(corners[i + 4], corners[next + 4], color);
+            self.line(corners[i], corners[i + 4], color);
+        }
+    }
+}
+
+pub(crate) fn clear_gizmos(mut gizmos: ResMut<Gizmos>) {
+    gizmos.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes_emit_line_pairs() {
+        let mut gizmos = Gizmos::default();
+        gizmos.aabb(Vec3::ZERO, Vec3::ONE, Vec3::ONE);
+        assert_eq!(gizmos.vertices().len(), 12 * 2);
+
+        gizmos.clear();
+        gizmos.sphere(Vec3::ZERO, 1.0, Vec3::ONE);
+        assert_eq!(gizmos.vertices().len(), 3 * CIRCLE_SEGMENTS * 2);
+
+        gizmos.set_enabled(false);
+        gizmos.line(Vec3::ZERO, Vec3::ONE, Vec3::ONE);
+        assert!(gizmos.vertices().is_empty());
+    }
+}
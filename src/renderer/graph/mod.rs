@@ -1,25 +1,33 @@
 pub mod node;
 pub mod nodes;
+pub mod resources;
 
 use anyhow::{Result, anyhow};
 use bevy_ecs::prelude::{Resource, World};
 use node::{RenderContext, RenderNode};
+use rayon::prelude::*;
+use resources::GraphResources;
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 
 #[derive(Resource)]
 pub struct RenderGraph {
     nodes: HashMap<String, Box<dyn RenderNode>>,
-    cached_execution_order: Option<Vec<String>>,
+    /// Topologically sorted nodes grouped into levels that only depend on earlier levels.
+    cached_levels: Option<Vec<Vec<String>>>,
     /// Pre-computed profiling labels to avoid per-frame string allocations
     profiling_labels: HashMap<String, String>,
+    resources: GraphResources,
 }
 
 impl RenderGraph {
     pub fn new() -> Self {
         Self {
             nodes: HashMap::new(),
-            cached_execution_order: None,
+            cached_levels: None,
             profiling_labels: HashMap::new(),
+            resources: GraphResources::default(),
         }
     }
 
@@ -30,12 +38,23 @@ impl RenderGraph {
         }
         // Pre-compute profiling label
         self.profiling_labels.insert(name.clone(), format!("Render::{}", name));
+        node.declare_resources(&mut self.resources);
         self.nodes.insert(name, node);
-        self.cached_execution_order = None;
+        self.cached_levels = None;
+    }
+
+    /// Textures and buffers shared between nodes. Resources declared here directly, rather
+    /// than by a node, live until they are removed.
+    pub fn resources(&self) -> &GraphResources {
+        &self.resources
+    }
+
+    pub fn resources_mut(&mut self) -> &mut GraphResources {
+        &mut self.resources
     }
 
     pub fn remove_node(&mut self, name: &str) -> Option<Box<dyn RenderNode>> {
-        self.cached_execution_order = None;
+        self.cached_levels = None;
         self.profiling_labels.remove(name);
         self.nodes.remove(name)
     }
@@ -49,95 +68,209 @@ impl RenderGraph {
             return Ok(());
         }
 
-        let has_profiler = world.contains_resource::<crate::core::Profiler>();
+        let config = renderer.config();
+        self.resources
+            .prepare(renderer.device(), (config.width, config.height));
 
-        let execution_order = if let Some(ref cached) = self.cached_execution_order {
+        let levels = if let Some(ref cached) = self.cached_levels {
             cached
         } else {
             let order = self.topological_sort()?;
-            self.cached_execution_order = Some(order);
-            self.cached_execution_order.as_ref().unwrap()
+            self.cached_levels = Some(self.group_into_levels(&order));
+            self.cached_levels.as_ref().unwrap()
         };
 
+        let draw_calls = AtomicU32::new(0);
         let start = std::time::Instant::now();
-        let output = renderer.surface().get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        if has_profiler {
-            if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
-                profiler.record_timing("Render::GetSurfaceTexture", start.elapsed());
+        // Headless renderers draw into their output texture and have nothing to present
+        let output = match renderer.surface() {
+            Some(surface) => Some(surface.get_current_texture()?),
+            None => None,
+        };
+        let view = match (&output, renderer.output_view()) {
+            (Some(output), _) => output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, Some(view)) => view.clone(),
+            (None, None) => {
+                return Err(anyhow!(
+                    "Renderer has neither a surface nor an output texture"
+                ));
             }
+        };
+        if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
+            profiler.record_timing("Render::GetSurfaceTexture", start.elapsed());
         }
 
-        let mut encoder =
-            renderer
-                .device()
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Render Encoder"),
-                });
-
         let context = RenderContext {
             device: renderer.device(),
             queue: renderer.queue(),
             surface_config: renderer.config(),
             surface_view: &view,
+            hdr_view: renderer.hdr_view(),
+            hdr_texture: renderer.hdr_texture(),
             camera_buffer: renderer.camera_buffer(),
             camera_bind_group: renderer.camera_bind_group(),
             depth_view: renderer.depth_view(),
             msaa_color_view: renderer.msaa_color_view(),
             msaa_depth_view: renderer.msaa_depth_view(),
             msaa_sample_count: renderer.msaa_sample_count(),
+            post_view: renderer.post_view(),
+            post_texture: renderer.post_texture(),
+            velocity_view: renderer.velocity_view(),
+            msaa_velocity_view: renderer.msaa_velocity_view(),
+            taa_history: renderer.taa_history(),
+            resources: &self.resources,
+            draw_calls: &draw_calls,
         };
 
-        for node_name in execution_order.iter() {
-            let node = self.nodes.get_mut(node_name).unwrap();
+        let mut command_buffers = Vec::new();
+        let mut timings: Vec<(&str, Duration)> = Vec::new();
+        let mut failed = 0;
 
-            if has_profiler {
-                let start = std::time::Instant::now();
-                if let Err(e) = node.execute(world, &context, &mut encoder) {
-                    log::error!("Render node '{}' failed: {}. Continuing with other nodes.", node_name, e);
-                    continue;
-                }
-                let duration = start.elapsed();
-                if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
-                    // Use pre-computed profiling label to avoid per-frame allocations
-                    if let Some(label) = self.profiling_labels.get(node_name) {
-                        profiler.record_timing(label, duration);
+        for level in levels {
+            // Nodes in a level don't depend on each other. Nodes that mutate the world run on
+            // this thread first; read-only nodes then record in parallel on worker threads.
+            let (read_only, exclusive): (Vec<&String>, Vec<&String>) = level
+                .iter()
+                .partition(|name| self.nodes[name.as_str()].is_read_only());
+
+            for node_name in exclusive {
+                let node = self.nodes.get_mut(node_name).unwrap();
+                let start = Instant::now();
+                let mut encoder = create_node_encoder(context.device, node_name);
+                match node.execute(world, &context, &mut encoder) {
+                    Ok(()) => command_buffers.push(encoder.finish()),
+                    Err(e) => {
+                        log::error!(
+                            "Render node '{}' failed: {}. Continuing with other nodes.",
+                            node_name,
+                            e
+                        );
+                        failed += 1;
+                        continue;
                     }
                 }
+                timings.push((node_name, start.elapsed()));
+            }
+
+            let world: &World = world;
+            // Names are taken from the level so they outlive the borrow of the nodes
+            let mut jobs: Vec<(&String, &mut Box<dyn RenderNode>)> = self
+                .nodes
+                .iter_mut()
+                .filter_map(|(name, node)| {
+                    read_only
+                        .iter()
+                        .find(|level_name| level_name.as_str() == name.as_str())
+                        .map(|level_name| (*level_name, node))
+                })
+                .collect();
+            // Results carry the job index; the names are looked up once the nodes are released
+            let record =
+                |(index, (node_name, node)): (usize, &mut (&String, &mut Box<dyn RenderNode>))| {
+                    let start = Instant::now();
+                    let mut encoder = create_node_encoder(context.device, node_name);
+                    let result = node
+                        .execute_read_only(world, &context, &mut encoder)
+                        .map(|()| encoder.finish());
+                    (index, result, start.elapsed())
+                };
+            let results: Vec<_> = if jobs.len() > 1 {
+                jobs.par_iter_mut().enumerate().map(record).collect()
             } else {
-                if let Err(e) = node.execute(world, &context, &mut encoder) {
-                    log::error!("Render node '{}' failed: {}. Continuing with other nodes.", node_name, e);
-                    continue;
+                jobs.iter_mut().enumerate().map(record).collect()
+            };
+
+            for (index, result, duration) in results {
+                let node_name = jobs[index].0;
+                match result {
+                    Ok(commands) => {
+                        command_buffers.push(commands);
+                        timings.push((node_name, duration));
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "Render node '{}' failed: {}. Continuing with other nodes.",
+                            node_name,
+                            e
+                        );
+                        failed += 1;
+                    }
                 }
             }
         }
 
-        let start = std::time::Instant::now();
-        renderer.queue().submit(std::iter::once(encoder.finish()));
-        if has_profiler {
-            if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
-                profiler.record_timing("Render::Submit", start.elapsed());
+        if let Some(mut stats) = world.get_resource_mut::<crate::renderer::RenderStats>() {
+            stats.nodes_executed = timings.len();
+            stats.nodes_failed = failed;
+            stats.draw_calls = draw_calls.load(Ordering::Relaxed);
+        }
+
+        if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
+            for (node_name, duration) in timings {
+                // Use pre-computed profiling label to avoid per-frame allocations
+                if let Some(label) = self.profiling_labels.get(node_name) {
+                    profiler.record_timing(label, duration);
+                }
             }
         }
 
+        let start = std::time::Instant::now();
+        // Uploads recorded by systems after the last flush and by the nodes above must land first
+        let uploads = world
+            .get_resource_mut::<crate::renderer::GpuUploader>()
+            .and_then(|mut uploader| uploader.finish(renderer.device()));
+        renderer
+            .queue()
+            .submit(uploads.into_iter().chain(command_buffers));
+        if let Some(mut uploader) = world.get_resource_mut::<crate::renderer::GpuUploader>() {
+            uploader.recall(renderer.device());
+        }
+        if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
+            profiler.record_timing("Render::Submit", start.elapsed());
+        }
+
+        let Some(output) = output else {
+            return Ok(());
+        };
         let start = std::time::Instant::now();
         output.present();
-        if has_profiler {
-            if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
-                profiler.record_timing("Render::Present", start.elapsed());
-            }
+        if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
+            profiler.record_timing("Render::Present", start.elapsed());
         }
 
         Ok(())
     }
 
+    /// Assigns each node of a topological order to one level past its deepest dependency.
+    fn group_into_levels(&self, order: &[String]) -> Vec<Vec<String>> {
+        let mut depth: HashMap<&str, usize> = HashMap::new();
+        let mut levels: Vec<Vec<String>> = Vec::new();
+
+        for name in order {
+            let level = self.nodes[name]
+                .dependencies()
+                .iter()
+                .map(|dep| depth[dep] + 1)
+                .max()
+                .unwrap_or(0);
+            depth.insert(name, level);
+
+            if levels.len() <= level {
+                levels.resize_with(level + 1, Vec::new);
+            }
+            levels[level].push(name.clone());
+        }
+
+        levels
+    }
+
     fn topological_sort(&self) -> Result<Vec<String>> {
         let mut in_degree: HashMap<String, usize> = HashMap::new();
         let mut adjacency: HashMap<String, Vec<String>> = HashMap::new();
 
-        for (name, _) in &self.nodes {
+        for name in self.nodes.keys() {
             in_degree.insert(name.clone(), 0);
             adjacency.insert(name.clone(), Vec::new());
         }
@@ -196,6 +329,12 @@ impl RenderGraph {
     }
 }
 
+fn create_node_encoder(device: &wgpu::Device, node_name: &str) -> wgpu::CommandEncoder {
+    device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some(node_name),
+    })
+}
+
 impl Default for RenderGraph {
     fn default() -> Self {
         Self::new()
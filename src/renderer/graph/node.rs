@@ -1,18 +1,47 @@
-use anyhow::Result;
+use crate::renderer::TaaHistory;
+use crate::renderer::graph::resources::GraphResources;
+use anyhow::{Result, anyhow};
 use bevy_ecs::prelude::World;
-use wgpu::{BindGroup, Buffer, CommandEncoder, Device, Queue, SurfaceConfiguration, TextureView};
+use std::sync::atomic::{AtomicU32, Ordering};
+use wgpu::{
+    BindGroup, Buffer, CommandEncoder, Device, Queue, SurfaceConfiguration, Texture, TextureView,
+};
 
 pub struct RenderContext<'a> {
     pub device: &'a Device,
     pub queue: &'a Queue,
     pub surface_config: &'a SurfaceConfiguration,
     pub surface_view: &'a TextureView,
+    /// Offscreen Rgba16Float target scene passes render into. Resolved to the surface by the tonemap node.
+    pub hdr_view: &'a TextureView,
+    pub hdr_texture: &'a Texture,
     pub camera_buffer: &'a Buffer,
     pub camera_bind_group: Option<&'a BindGroup>,
     pub depth_view: &'a TextureView,
     pub msaa_color_view: Option<&'a TextureView>,
     pub msaa_depth_view: Option<&'a TextureView>,
     pub msaa_sample_count: u32,
+    /// Scratch HDR target, allocated while FXAA or motion blur is enabled.
+    pub post_view: Option<&'a TextureView>,
+    pub post_texture: Option<&'a Texture>,
+    /// Motion vectors written by the main pass, allocated while TAA or motion blur is enabled.
+    pub velocity_view: Option<&'a TextureView>,
+    /// Multisampled velocity target resolved into `velocity_view` when MSAA is on.
+    pub msaa_velocity_view: Option<&'a TextureView>,
+    /// Allocated while TAA is selected.
+    pub taa_history: Option<&'a TaaHistory>,
+    /// Named intermediate textures and buffers declared by nodes.
+    pub resources: &'a GraphResources,
+    /// Draws recorded this frame, see [`record_draws`](Self::record_draws).
+    pub draw_calls: &'a AtomicU32,
+}
+
+impl RenderContext<'_> {
+    /// Adds to [`RenderStats::draw_calls`](crate::renderer::RenderStats::draw_calls). Every
+    /// command of a multi-draw counts as one draw.
+    pub fn record_draws(&self, count: u32) {
+        self.draw_calls.fetch_add(count, Ordering::Relaxed);
+    }
 }
 
 pub trait RenderNode: Send + Sync {
@@ -22,10 +51,30 @@ pub trait RenderNode: Send + Sync {
         &[]
     }
 
+    /// Declares the shared textures and buffers the node reads or writes. Called once when
+    /// the node is added to the graph.
+    fn declare_resources(&self, _resources: &mut GraphResources) {}
+
     fn execute(
         &mut self,
         world: &mut World,
         context: &RenderContext,
         encoder: &mut CommandEncoder,
     ) -> Result<()>;
+
+    /// Whether the node records without mutating the world. Read-only nodes are run
+    /// through [`execute_read_only`](Self::execute_read_only), on worker threads when
+    /// several of them have their dependencies satisfied at the same time.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn execute_read_only(
+        &mut self,
+        _world: &World,
+        _context: &RenderContext,
+        _encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        Err(anyhow!("Render node '{}' is not read-only", self.name()))
+    }
 }
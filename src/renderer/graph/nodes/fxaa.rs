@@ -0,0 +1,173 @@
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::{AntiAliasing, FxaaPipeline, GraphicsSettings};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use bytemuck::{Pod, Zeroable};
+use wgpu::CommandEncoder;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct FxaaUniform {
+    inverse_size: [f32; 2],
+    exposure: f32,
+    _padding: f32,
+}
+
+/// Applies FXAA to the HDR target when [`AntiAliasing::Fxaa`] is selected.
+///
+/// Renders into the renderer's post-process texture and copies the result back, so the
+/// tonemap node is unaware of it.
+pub struct FxaaNode {
+    uniform_buffer: Option<wgpu::Buffer>,
+}
+
+impl FxaaNode {
+    pub fn new() -> Self {
+        Self {
+            uniform_buffer: None,
+        }
+    }
+}
+
+impl Default for FxaaNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for FxaaNode {
+    fn name(&self) -> &str {
+        "fxaa"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &[
+            "main_pass",
+            "wireframe_pass",
+            "water_pass",
+            "gizmo_pass",
+            "taa",
+            "motion_blur",
+        ]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(settings) = world.get_resource::<GraphicsSettings>() else {
+            return Ok(());
+        };
+        if settings.anti_aliasing() != AntiAliasing::Fxaa {
+            return Ok(());
+        }
+        // The target is allocated when the settings are applied at the start of the frame
+        let (Some(post_view), Some(post_texture)) = (context.post_view, context.post_texture)
+        else {
+            return Ok(());
+        };
+        let Some(pipeline) = world.get_resource::<FxaaPipeline>() else {
+            log::debug!("FxaaPipeline resource not available, skipping FXAA");
+            return Ok(());
+        };
+
+        let (width, height) = (context.surface_config.width, context.surface_config.height);
+        let uniform = FxaaUniform {
+            inverse_size: [1.0 / width as f32, 1.0 / height as f32],
+            exposure: settings.exposure(),
+            _padding: 0.0,
+        };
+
+        let uniform_buffer = self.uniform_buffer.get_or_insert_with(|| {
+            context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("FXAA Uniform Buffer"),
+                size: std::mem::size_of::<FxaaUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        context
+            .queue
+            .write_buffer(uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("FXAA Bind Group"),
+                layout: &pipeline.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(context.hdr_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&pipeline.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("FXAA Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: post_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&pipeline.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            context.record_draws(1);
+        }
+
+        copy_to_hdr(encoder, post_texture, context);
+
+        Ok(())
+    }
+}
+
+/// Copies a full-size HDR-format texture over the HDR target.
+pub(crate) fn copy_to_hdr(
+    encoder: &mut CommandEncoder,
+    source: &wgpu::Texture,
+    context: &RenderContext,
+) {
+    encoder.copy_texture_to_texture(
+        source.as_image_copy(),
+        context.hdr_texture.as_image_copy(),
+        wgpu::Extent3d {
+            width: context.surface_config.width,
+            height: context.surface_config.height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
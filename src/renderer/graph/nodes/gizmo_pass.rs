@@ -0,0 +1,145 @@
+use crate::renderer::camera::sorted_camera_views;
+use crate::renderer::gizmos::{GizmoVertex, Gizmos};
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::{GizmoPipeline, RenderTarget};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::{Buffer, CommandEncoder};
+
+/// Draws the lines recorded in [`Gizmos`] this frame over the scene.
+pub struct GizmoPassNode {
+    /// Vertex buffer reused across frames, grown when a frame records more lines.
+    vertex_buffer: Option<Buffer>,
+    capacity: usize,
+}
+
+impl GizmoPassNode {
+    pub fn new() -> Self {
+        Self {
+            vertex_buffer: None,
+            capacity: 0,
+        }
+    }
+}
+
+impl Default for GizmoPassNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for GizmoPassNode {
+    fn name(&self) -> &str {
+        "gizmo_pass"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &["main_pass", "wireframe_pass", "water_pass"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(gizmos) = world
+            .get_resource::<Gizmos>()
+            .filter(|gizmos| !gizmos.vertices().is_empty())
+        else {
+            return Ok(());
+        };
+        let (Some(pipeline), Some(camera_bind_group)) = (
+            world.get_resource::<GizmoPipeline>(),
+            context.camera_bind_group,
+        ) else {
+            log::debug!("Gizmo pipeline or camera bind group not available, skipping gizmos");
+            return Ok(());
+        };
+
+        // Like wireframes, gizmos are drawn for the first camera only
+        let Some(first_view) = sorted_camera_views(world)
+            .into_iter()
+            .next()
+            .filter(|view| view.target == RenderTarget::Window)
+        else {
+            return Ok(());
+        };
+
+        let vertices = gizmos.vertices();
+        if self.vertex_buffer.is_none() || vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two().max(1024);
+            self.vertex_buffer = Some(context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Gizmo Vertex Buffer"),
+                size: (self.capacity * std::mem::size_of::<GizmoVertex>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        let vertex_buffer = self.vertex_buffer.as_ref().unwrap();
+        context
+            .queue
+            .write_buffer(vertex_buffer, 0, bytemuck::cast_slice(vertices));
+
+        let (color_view, resolve_target) = if let Some(msaa_view) = context.msaa_color_view {
+            (msaa_view, Some(context.hdr_view))
+        } else {
+            (context.hdr_view, None)
+        };
+        let depth_view = context.msaa_depth_view.unwrap_or(context.depth_view);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Gizmo Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        if let Some(viewport) = first_view.viewport {
+            let (x, y, width, height) =
+                viewport.to_pixels((context.surface_config.width, context.surface_config.height));
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+
+        render_pass.set_pipeline(if gizmos.depth_test {
+            &pipeline.pipeline
+        } else {
+            &pipeline.overlay_pipeline
+        });
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+        context.record_draws(1);
+
+        Ok(())
+    }
+}
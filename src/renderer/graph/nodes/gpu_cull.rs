@@ -0,0 +1,71 @@
+use crate::renderer::GpuCullPipeline;
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::systems::GpuCullData;
+use crate::renderer::systems::draw::gpu_culling::CULL_WORKGROUP_SIZE;
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::CommandEncoder;
+
+/// Frustum culls instances on the GPU and compacts their indirect draws before the main pass.
+///
+/// Does nothing unless `GraphicsSettings::gpu_culling` is enabled.
+pub struct GpuCullNode;
+
+impl GpuCullNode {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for GpuCullNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for GpuCullNode {
+    fn name(&self) -> &str {
+        "gpu_cull"
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        _context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let (Some(cull_data), Some(pipeline)) = (
+            world.get_resource::<GpuCullData>(),
+            world.get_resource::<GpuCullPipeline>(),
+        ) else {
+            return Ok(());
+        };
+
+        // Zeroed commands draw nothing, so slots the shader doesn't fill stay inert
+        encoder.clear_buffer(&cull_data.indirect_buffer, 0, None);
+        encoder.clear_buffer(&cull_data.counter_buffer, 0, None);
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("GPU Cull Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &cull_data.bind_group, &[]);
+        pass.dispatch_workgroups(cull_data.instance_count.div_ceil(CULL_WORKGROUP_SIZE), 1, 1);
+
+        Ok(())
+    }
+}
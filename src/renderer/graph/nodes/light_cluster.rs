@@ -0,0 +1,64 @@
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::lighting::clusters::{CLUSTER_WORKGROUP_SIZE, cluster_count};
+use crate::renderer::{LightClusterPipeline, LightingData};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::CommandEncoder;
+
+/// Bins point lights into froxels before the main pass shades with them.
+pub struct LightClusterNode;
+
+impl LightClusterNode {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for LightClusterNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for LightClusterNode {
+    fn name(&self) -> &str {
+        "light_cluster"
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        _context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let (Some(lighting_data), Some(pipeline)) = (
+            world.get_resource::<LightingData>(),
+            world.get_resource::<LightClusterPipeline>(),
+        ) else {
+            return Ok(());
+        };
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Light Cluster Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &lighting_data.cluster_bind_group, &[]);
+        pass.dispatch_workgroups(cluster_count().div_ceil(CLUSTER_WORKGROUP_SIZE), 1, 1);
+
+        Ok(())
+    }
+}
@@ -1,17 +1,68 @@
 use crate::core::math::Mat4;
-use crate::renderer::components::{IndirectDrawData, ModelStorageData};
+use crate::renderer::camera::sorted_camera_views;
+use crate::renderer::components::{
+    IndirectDrawData, LayerDrawData, MeshDrawBatch, ModelStorageData,
+};
 use crate::renderer::graph::node::{RenderContext, RenderNode};
-use crate::renderer::{Camera, CameraUniform, GpuMeshCache, LightingData, MeshPipeline};
-use crate::transform::GlobalTransform;
+use crate::renderer::systems::draw::gpu_culling::{GpuCullData, batch_indirect_source};
+use crate::renderer::{
+    CameraUniform, Fog, GpuMeshCache, GpuUploader, LightingData, MeshPipeline, MorphDrawData,
+    RenderTarget, RenderTargets,
+};
 use anyhow::Result;
-use bevy_ecs::prelude::World;
+use bevy_ecs::prelude::{Entity, World};
+use std::collections::{HashMap, HashSet};
 use wgpu::CommandEncoder;
 
-pub struct MainPassNode;
+/// Draws the scene once per camera, in priority order, into each camera's target.
+///
+/// Window cameras also write motion vectors while the renderer has a velocity target.
+/// Cameras whose [`RenderLayers`](crate::renderer::RenderLayers) leave out part of the scene
+/// draw their batches from [`LayerDrawData`].
+pub struct MainPassNode {
+    /// Uniform buffers and bind groups for cameras after the first, which uses the renderer's.
+    extra_cameras: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    /// Unjittered view projection of every camera last frame, for motion vectors.
+    previous_view_projs: HashMap<Entity, Mat4>,
+}
 
 impl MainPassNode {
     pub fn new() -> Self {
-        Self
+        Self {
+            extra_cameras: Vec::new(),
+            previous_view_projs: HashMap::new(),
+        }
+    }
+
+    fn ensure_extra_cameras(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        count: usize,
+    ) {
+        while self.extra_cameras.len() < count {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Extra Camera Buffer"),
+                size: std::mem::size_of::<CameraUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Extra Camera Bind Group"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            self.extra_cameras.push((buffer, bind_group));
+        }
+    }
+}
+
+impl Default for MainPassNode {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -21,7 +72,12 @@ impl RenderNode for MainPassNode {
     }
 
     fn dependencies(&self) -> &[&str] {
-        &[]
+        &[
+            "gpu_cull",
+            "occlusion_cull",
+            "light_cluster",
+            "point_shadow",
+        ]
     }
 
     fn execute(
@@ -30,106 +86,303 @@ impl RenderNode for MainPassNode {
         context: &RenderContext,
         encoder: &mut CommandEncoder,
     ) -> Result<()> {
-        let camera_view_proj: Option<Mat4> = world
-            .query::<(&Camera, &GlobalTransform)>()
-            .iter(world)
-            .next()
-            .map(|(camera, transform)| camera.view_projection_matrix(transform));
-
-        // Update camera buffer (this was previously done by depth_prepass before it was removed)
-        if let Some(view_proj) = camera_view_proj {
-            let mut camera_uniform = CameraUniform::new();
-            camera_uniform.update_view_proj(view_proj);
-            context.queue.write_buffer(
-                context.camera_buffer,
-                0,
-                bytemuck::cast_slice(&[camera_uniform]),
+        let views = sorted_camera_views(world);
+
+        let pipeline = world
+            .get_resource::<MeshPipeline>()
+            .filter(|_| views.len() > 1);
+        if let Some(pipeline) = pipeline {
+            self.ensure_extra_cameras(
+                context.device,
+                &pipeline.camera_bind_group_layout,
+                views.len() - 1,
             );
         }
 
-        {
-            let (color_view, resolve_target) = if let Some(msaa_view) = context.msaa_color_view {
-                (msaa_view, Some(context.surface_view))
-            } else {
-                (context.surface_view, None)
-            };
-
-            let depth_view = context.msaa_depth_view.unwrap_or(context.depth_view);
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Main Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: color_view,
-                    resolve_target,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
+        // Update camera buffers (this was previously done by depth_prepass before it was removed)
+        if let Some(mut uploader) = world.get_resource_mut::<GpuUploader>() {
+            for (i, view) in views.iter().enumerate() {
+                let buffer = match i {
+                    0 => context.camera_buffer,
+                    _ => match self.extra_cameras.get(i - 1) {
+                        Some((buffer, _)) => buffer,
+                        None => continue,
                     },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
+                };
+
+                let mut view_proj = view.view_proj;
+                // TAA accumulates samples from a sub-pixel offset that changes every frame
+                if let (Some(history), RenderTarget::Window) = (context.taa_history, view.target) {
+                    view_proj = Mat4::from_translation(history.jitter().extend(0.0)) * view_proj;
+                }
+                let previous_view_proj = self
+                    .previous_view_projs
+                    .get(&view.entity)
+                    .copied()
+                    .filter(|_| !view.cut)
+                    .unwrap_or(view.view_proj);
+
+                let mut camera_uniform = CameraUniform::new();
+                camera_uniform.update_view_proj(view_proj);
+                camera_uniform.update_motion(view.view_proj, previous_view_proj);
+                uploader.write_buffer(buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
+            }
+        }
+        // Rebuilt every frame so despawned cameras drop out
+        self.previous_view_projs = views
+            .iter()
+            .map(|view| (view.entity, view.view_proj))
+            .collect();
+
+        if let Some(mut render_targets) = world.get_resource_mut::<RenderTargets>() {
+            render_targets.prepare(context.device, context.msaa_sample_count);
+        }
+
+        let window_size = (context.surface_config.width, context.surface_config.height);
+        let render_targets = world.get_resource::<RenderTargets>();
+        let mut cleared: HashSet<RenderTarget> = HashSet::new();
+        // With fog, clear to the fog color so distant geometry fades into the background
+        let clear_color = world
+            .get_resource::<Fog>()
+            .map_or(DEFAULT_CLEAR_COLOR, |fog| wgpu::Color {
+                r: fog.color.x as f64,
+                g: fog.color.y as f64,
+                b: fog.color.z as f64,
+                a: 1.0,
             });
 
-            if camera_view_proj.is_none() {
-                log::debug!("No active camera found, skipping mesh rendering");
-            } else if world.get_resource::<MeshPipeline>().is_none() {
-                log::debug!("MeshPipeline resource not available, skipping mesh rendering");
-            } else if world.get_resource::<GpuMeshCache>().is_none() {
-                log::debug!("GpuMeshCache resource not available, skipping mesh rendering");
-            } else if context.camera_bind_group.is_none() {
-                log::debug!("Camera bind group not initialized, skipping mesh rendering");
-            } else if world.get_resource::<LightingData>().is_none() {
-                log::debug!("LightingData resource not available, skipping mesh rendering");
-            } else if world.get_resource::<ModelStorageData>().is_none() {
-                log::debug!("ModelStorageData resource not available, skipping mesh rendering");
-            } else if world.get_resource::<IndirectDrawData>().is_none() {
-                log::debug!("IndirectDrawData resource not available, skipping mesh rendering");
-            } else {
-                let pipeline = world.get_resource::<MeshPipeline>().unwrap();
-                let gpu_mesh_cache = world.get_resource::<GpuMeshCache>().unwrap();
-                let lighting_data = world.get_resource::<LightingData>().unwrap();
-                let model_storage_data = world.get_resource::<ModelStorageData>().unwrap();
-                let indirect_draw_data = world.get_resource::<IndirectDrawData>().unwrap();
-
-                render_pass.set_pipeline(&pipeline.pipeline);
-                render_pass.set_bind_group(0, context.camera_bind_group.unwrap(), &[]);
-                render_pass.set_bind_group(1, &model_storage_data.bind_group, &[]);
-                render_pass.set_bind_group(2, &lighting_data.bind_group, &[]);
-
-                for batch in &indirect_draw_data.batches {
-                    if let Some(gpu_mesh) = gpu_mesh_cache.get(&batch.mesh_id) {
-                        if gpu_mesh.index_count == 0 {
-                            continue;
-                        }
-                        render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
-                        render_pass.set_index_buffer(
-                            gpu_mesh.index_buffer.slice(..),
-                            wgpu::IndexFormat::Uint32,
-                        );
-                        render_pass.multi_draw_indexed_indirect(
-                            &batch.indirect_buffer,
-                            0,
-                            batch.draw_count,
-                        );
-                    }
+        for (i, view) in views.iter().enumerate() {
+            let (attachments, size) = match view.target {
+                RenderTarget::Window => (window_attachments(context), window_size),
+                RenderTarget::Texture(id) => {
+                    let Some(textures) = render_targets.and_then(|targets| targets.textures(id))
+                    else {
+                        log::debug!("Render target {:?} does not exist, skipping camera", id);
+                        continue;
+                    };
+                    let size = render_targets.and_then(|targets| targets.size(id)).unwrap();
+                    (
+                        Attachments {
+                            color: textures
+                                .msaa_color_view
+                                .as_ref()
+                                .unwrap_or(&textures.color_view),
+                            resolve_target: textures
+                                .msaa_color_view
+                                .as_ref()
+                                .map(|_| &textures.color_view),
+                            depth: textures
+                                .msaa_depth_view
+                                .as_ref()
+                                .unwrap_or(&textures.depth_view),
+                            velocity: None,
+                        },
+                        size,
+                    )
                 }
+            };
+
+            let camera_bind_group = match i {
+                0 => context.camera_bind_group,
+                _ => self
+                    .extra_cameras
+                    .get(i - 1)
+                    .map(|(_, bind_group)| bind_group),
+            };
+
+            // The first camera on a target clears it; later ones draw over it with fresh depth
+            let first_on_target = cleared.insert(view.target);
+            let mut render_pass = begin_pass(
+                encoder,
+                &attachments,
+                first_on_target.then_some(clear_color),
+            );
+            if let Some(viewport) = view.viewport {
+                let (x, y, width, height) = viewport.to_pixels(size);
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
             }
+
+            let layer_batches = world
+                .get_resource::<LayerDrawData>()
+                .and_then(|layer_data| layer_data.batches_for(view.layers));
+            draw_scene(
+                world,
+                context,
+                &mut render_pass,
+                camera_bind_group,
+                layer_batches,
+                attachments.velocity.is_some(),
+            );
+        }
+
+        // Without a window camera the HDR target still has to be cleared before tonemapping
+        if !cleared.contains(&RenderTarget::Window) {
+            log::debug!("No active camera found, skipping mesh rendering");
+            begin_pass(encoder, &window_attachments(context), Some(clear_color));
         }
 
         Ok(())
     }
 }
+
+struct Attachments<'a> {
+    color: &'a wgpu::TextureView,
+    resolve_target: Option<&'a wgpu::TextureView>,
+    depth: &'a wgpu::TextureView,
+    /// Motion vector target and its resolve target.
+    velocity: Option<(&'a wgpu::TextureView, Option<&'a wgpu::TextureView>)>,
+}
+
+fn window_attachments<'a>(context: &'a RenderContext) -> Attachments<'a> {
+    let (color, resolve_target) = if let Some(msaa_view) = context.msaa_color_view {
+        (msaa_view, Some(context.hdr_view))
+    } else {
+        (context.hdr_view, None)
+    };
+
+    let velocity = context
+        .velocity_view
+        .map(|velocity_view| match context.msaa_velocity_view {
+            Some(msaa_view) => (msaa_view, Some(velocity_view)),
+            None => (velocity_view, None),
+        });
+
+    Attachments {
+        color,
+        resolve_target,
+        depth: context.msaa_depth_view.unwrap_or(context.depth_view),
+        velocity,
+    }
+}
+
+const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+fn begin_pass<'e>(
+    encoder: &'e mut CommandEncoder,
+    attachments: &Attachments,
+    clear_color: Option<wgpu::Color>,
+) -> wgpu::RenderPass<'e> {
+    let load = match clear_color {
+        Some(color) => wgpu::LoadOp::Clear(color),
+        None => wgpu::LoadOp::Load,
+    };
+    // Pixels no geometry covers have no motion
+    let velocity_load = match clear_color {
+        Some(_) => wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+        None => wgpu::LoadOp::Load,
+    };
+
+    let color_attachments = [
+        Some(wgpu::RenderPassColorAttachment {
+            view: attachments.color,
+            resolve_target: attachments.resolve_target,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        }),
+        attachments
+            .velocity
+            .map(|(view, resolve_target)| wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: velocity_load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            }),
+    ];
+    // The velocity attachment is left out entirely rather than passed as None, so the pass
+    // matches the single-target pipeline
+    let attachment_count = if attachments.velocity.is_some() { 2 } else { 1 };
+
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Main Render Pass"),
+        color_attachments: &color_attachments[..attachment_count],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: attachments.depth,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
+fn draw_scene(
+    world: &World,
+    context: &RenderContext,
+    render_pass: &mut wgpu::RenderPass,
+    camera_bind_group: Option<&wgpu::BindGroup>,
+    layer_batches: Option<&[MeshDrawBatch]>,
+    write_velocity: bool,
+) {
+    let Some(pipeline) = world.get_resource::<MeshPipeline>() else {
+        log::debug!("MeshPipeline resource not available, skipping mesh rendering");
+        return;
+    };
+    let Some(gpu_mesh_cache) = world.get_resource::<GpuMeshCache>() else {
+        log::debug!("GpuMeshCache resource not available, skipping mesh rendering");
+        return;
+    };
+    let Some(camera_bind_group) = camera_bind_group else {
+        log::debug!("Camera bind group not initialized, skipping mesh rendering");
+        return;
+    };
+    let Some(lighting_data) = world.get_resource::<LightingData>() else {
+        log::debug!("LightingData resource not available, skipping mesh rendering");
+        return;
+    };
+    let Some(model_storage_data) = world.get_resource::<ModelStorageData>() else {
+        log::debug!("ModelStorageData resource not available, skipping mesh rendering");
+        return;
+    };
+    let Some(indirect_draw_data) = world.get_resource::<IndirectDrawData>() else {
+        log::debug!("IndirectDrawData resource not available, skipping mesh rendering");
+        return;
+    };
+    let Some(morph_data) = world.get_resource::<MorphDrawData>() else {
+        log::debug!("MorphDrawData resource not available, skipping mesh rendering");
+        return;
+    };
+
+    render_pass.set_pipeline(if write_velocity {
+        &pipeline.velocity_pipeline
+    } else {
+        &pipeline.pipeline
+    });
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    render_pass.set_bind_group(1, &model_storage_data.bind_group, &[]);
+    render_pass.set_bind_group(2, &lighting_data.bind_group, &[]);
+
+    // Layer batches hold only some of the culled instances, so they draw from their own buffers
+    let gpu_cull = match layer_batches {
+        Some(_) => None,
+        None => world.get_resource::<GpuCullData>(),
+    };
+    for batch in layer_batches.unwrap_or(&indirect_draw_data.batches) {
+        if let Some(gpu_mesh) = gpu_mesh_cache.get(&batch.mesh_id) {
+            if gpu_mesh.index_count == 0 {
+                continue;
+            }
+            render_pass.set_bind_group(3, morph_data.bind_group(&batch.mesh_id), &[]);
+            render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+            render_pass
+                .set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            let (indirect_buffer, indirect_offset, draw_count) =
+                batch_indirect_source(gpu_cull, batch);
+            render_pass.multi_draw_indexed_indirect(indirect_buffer, indirect_offset, draw_count);
+            context.record_draws(draw_count);
+        }
+    }
+}
@@ -1,5 +1,27 @@
+pub mod fxaa;
+pub mod gizmo_pass;
+pub mod gpu_cull;
+pub mod light_cluster;
 pub mod main_pass;
+pub mod motion_blur;
+pub mod occlusion_cull;
+pub mod point_shadow;
+pub mod screenshot;
+pub mod taa;
+pub mod tonemap;
+pub mod water_pass;
 pub mod wireframe_pass;
 
+pub use fxaa::FxaaNode;
+pub use gizmo_pass::GizmoPassNode;
+pub use gpu_cull::GpuCullNode;
+pub use light_cluster::LightClusterNode;
 pub use main_pass::MainPassNode;
+pub use motion_blur::MotionBlurNode;
+pub use occlusion_cull::OcclusionCullNode;
+pub use point_shadow::PointShadowNode;
+pub use screenshot::ScreenshotNode;
+pub use taa::TaaNode;
+pub use tonemap::TonemapNode;
+pub use water_pass::WaterPassNode;
 pub use wireframe_pass::WireframePassNode;
@@ -0,0 +1,166 @@
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::graph::nodes::fxaa::copy_to_hdr;
+use crate::renderer::{GraphicsSettings, MotionBlurPipeline};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use bytemuck::{Pod, Zeroable};
+use wgpu::CommandEncoder;
+
+/// Samples taken along each pixel's motion vector.
+const MOTION_BLUR_SAMPLES: u32 = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct MotionBlurUniform {
+    intensity: f32,
+    sample_count: u32,
+    _padding: [f32; 2],
+}
+
+/// Blurs the HDR target along the main pass motion vectors when
+/// [`GraphicsSettings::motion_blur`] is above zero.
+///
+/// Runs after TAA, which needs the unblurred frame for its history, and renders into the
+/// renderer's post-process texture before copying the result back.
+pub struct MotionBlurNode {
+    uniform_buffer: Option<wgpu::Buffer>,
+}
+
+impl MotionBlurNode {
+    pub fn new() -> Self {
+        Self {
+            uniform_buffer: None,
+        }
+    }
+}
+
+impl Default for MotionBlurNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for MotionBlurNode {
+    fn name(&self) -> &str {
+        "motion_blur"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &[
+            "main_pass",
+            "wireframe_pass",
+            "water_pass",
+            "gizmo_pass",
+            "taa",
+        ]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(settings) = world.get_resource::<GraphicsSettings>() else {
+            return Ok(());
+        };
+        if settings.motion_blur() <= 0.0 {
+            return Ok(());
+        }
+        // The targets are allocated when the settings are applied at the start of the frame
+        let (Some(post_view), Some(post_texture), Some(velocity_view)) = (
+            context.post_view,
+            context.post_texture,
+            context.velocity_view,
+        ) else {
+            return Ok(());
+        };
+        let Some(pipeline) = world.get_resource::<MotionBlurPipeline>() else {
+            log::debug!("MotionBlurPipeline resource not available, skipping motion blur");
+            return Ok(());
+        };
+
+        let uniform = MotionBlurUniform {
+            intensity: settings.motion_blur(),
+            sample_count: MOTION_BLUR_SAMPLES,
+            _padding: [0.0; 2],
+        };
+
+        let uniform_buffer = self.uniform_buffer.get_or_insert_with(|| {
+            context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Motion Blur Uniform Buffer"),
+                size: std::mem::size_of::<MotionBlurUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        context
+            .queue
+            .write_buffer(uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Motion Blur Bind Group"),
+                layout: &pipeline.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(context.hdr_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(velocity_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&pipeline.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Motion Blur Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: post_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&pipeline.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+            context.record_draws(1);
+        }
+
+        copy_to_hdr(encoder, post_texture, context);
+
+        Ok(())
+    }
+}
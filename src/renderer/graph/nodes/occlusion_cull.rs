@@ -0,0 +1,307 @@
+use crate::renderer::components::{IndirectDrawData, ModelStorageData};
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::systems::draw::gpu_culling::{
+    CULL_WORKGROUP_SIZE, GpuCullData, batch_indirect_source,
+};
+use crate::renderer::{DepthPrepassPipeline, GpuCullPipeline, GpuMeshCache, HiZPipeline};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::{BindGroup, CommandEncoder, Device, TextureView};
+
+const PYRAMID_WORKGROUP_SIZE: u32 = 8;
+
+/// Window-sized depth prepass target and the max-depth pyramid built from it.
+struct DepthPyramid {
+    size: (u32, u32),
+    depth_view: TextureView,
+    /// One bind group per pyramid level; level 0 copies the prepass depth.
+    level_bind_groups: Vec<BindGroup>,
+    level_sizes: Vec<(u32, u32)>,
+    cull_bind_group: BindGroup,
+}
+
+impl DepthPyramid {
+    fn new(
+        device: &Device,
+        size: (u32, u32),
+        hiz_pipeline: &HiZPipeline,
+        cull_pipeline: &GpuCullPipeline,
+    ) -> Self {
+        let (width, height) = size;
+        let depth_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Occlusion Depth Prepass Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth32Float,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let level_count = u32::BITS - width.max(height).leading_zeros();
+        let pyramid = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Pyramid"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HiZPipeline::FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let level_views: Vec<TextureView> = (0..level_count)
+            .map(|level| {
+                pyramid.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Depth Pyramid Level"),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let mut level_bind_groups = Vec::with_capacity(level_views.len());
+        level_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Depth Pyramid Copy Bind Group"),
+            layout: &hiz_pipeline.copy_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&level_views[0]),
+                },
+            ],
+        }));
+        for levels in level_views.windows(2) {
+            level_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Depth Pyramid Downsample Bind Group"),
+                layout: &hiz_pipeline.downsample_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&levels[1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&levels[0]),
+                    },
+                ],
+            }));
+        }
+
+        let level_sizes = (0..level_count)
+            .map(|level| ((width >> level).max(1), (height >> level).max(1)))
+            .collect();
+
+        let pyramid_view = pyramid.create_view(&wgpu::TextureViewDescriptor::default());
+        let cull_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cull Depth Pyramid Bind Group"),
+            layout: &cull_pipeline.pyramid_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&pyramid_view),
+            }],
+        });
+
+        Self {
+            size,
+            depth_view,
+            level_bind_groups,
+            level_sizes,
+            cull_bind_group,
+        }
+    }
+}
+
+/// Hierarchical-Z occlusion culling.
+///
+/// Draws the frustum-culled instances into a depth prepass, reduces it to a max-depth
+/// pyramid and re-runs GPU culling against it, so the main pass only draws instances
+/// that aren't hidden behind closer geometry. Does nothing unless
+/// `GraphicsSettings::occlusion_culling` is enabled and a single full-window camera is active.
+pub struct OcclusionCullNode {
+    pyramid: Option<DepthPyramid>,
+}
+
+impl OcclusionCullNode {
+    pub fn new() -> Self {
+        Self { pyramid: None }
+    }
+}
+
+impl Default for OcclusionCullNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for OcclusionCullNode {
+    fn name(&self) -> &str {
+        "occlusion_cull"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &["gpu_cull"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(cull_data) = world
+            .get_resource::<GpuCullData>()
+            .filter(|cull_data| cull_data.occlusion)
+        else {
+            return Ok(());
+        };
+        let (
+            Some(cull_pipeline),
+            Some(hiz_pipeline),
+            Some(prepass_pipeline),
+            Some(gpu_mesh_cache),
+            Some(model_storage_data),
+            Some(indirect_draw_data),
+            Some(camera_bind_group),
+        ) = (
+            world.get_resource::<GpuCullPipeline>(),
+            world.get_resource::<HiZPipeline>(),
+            world.get_resource::<DepthPrepassPipeline>(),
+            world.get_resource::<GpuMeshCache>(),
+            world.get_resource::<ModelStorageData>(),
+            world.get_resource::<IndirectDrawData>(),
+            context.camera_bind_group,
+        )
+        else {
+            log::debug!("Occlusion culling resources not available, keeping frustum culling only");
+            return Ok(());
+        };
+
+        let size = (context.surface_config.width, context.surface_config.height);
+        if self
+            .pyramid
+            .as_ref()
+            .is_none_or(|pyramid| pyramid.size != size)
+        {
+            self.pyramid = Some(DepthPyramid::new(
+                context.device,
+                size,
+                hiz_pipeline,
+                cull_pipeline,
+            ));
+        }
+        let pyramid = self.pyramid.as_ref().unwrap();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Occlusion Depth Prepass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &pyramid.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&prepass_pipeline.pipeline);
+            render_pass.set_bind_group(0, camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &model_storage_data.bind_group, &[]);
+
+            for batch in &indirect_draw_data.batches {
+                if let Some(gpu_mesh) = gpu_mesh_cache.get(&batch.mesh_id) {
+                    if gpu_mesh.index_count == 0 {
+                        continue;
+                    }
+                    render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(
+                        gpu_mesh.index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    let (indirect_buffer, indirect_offset, draw_count) =
+                        batch_indirect_source(Some(cull_data), batch);
+                    render_pass.multi_draw_indexed_indirect(
+                        indirect_buffer,
+                        indirect_offset,
+                        draw_count,
+                    );
+                    context.record_draws(draw_count);
+                }
+            }
+        }
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Depth Pyramid Pass"),
+                timestamp_writes: None,
+            });
+            for (level, (bind_group, &(width, height))) in pyramid
+                .level_bind_groups
+                .iter()
+                .zip(&pyramid.level_sizes)
+                .enumerate()
+            {
+                if level == 0 {
+                    pass.set_pipeline(&hiz_pipeline.copy_pipeline);
+                } else if level == 1 {
+                    pass.set_pipeline(&hiz_pipeline.downsample_pipeline);
+                }
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.dispatch_workgroups(
+                    width.div_ceil(PYRAMID_WORKGROUP_SIZE),
+                    height.div_ceil(PYRAMID_WORKGROUP_SIZE),
+                    1,
+                );
+            }
+        }
+
+        // Re-cull from scratch; the prepass has already consumed the frustum-only commands
+        encoder.clear_buffer(&cull_data.indirect_buffer, 0, None);
+        encoder.clear_buffer(&cull_data.counter_buffer, 0, None);
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Occlusion Cull Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&cull_pipeline.occlusion_pipeline);
+        pass.set_bind_group(0, &cull_data.bind_group, &[]);
+        pass.set_bind_group(1, &pyramid.cull_bind_group, &[]);
+        pass.dispatch_workgroups(cull_data.instance_count.div_ceil(CULL_WORKGROUP_SIZE), 1, 1);
+
+        Ok(())
+    }
+}
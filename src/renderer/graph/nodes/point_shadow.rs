@@ -0,0 +1,113 @@
+use crate::renderer::components::ModelStorageData;
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::lighting::shadows::FACE_UNIFORM_STRIDE;
+use crate::renderer::{GpuMeshCache, LightingData, PointShadowPipeline};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::CommandEncoder;
+
+/// Renders the cube faces of shadow-casting point lights before the main pass samples them.
+pub struct PointShadowNode;
+
+impl PointShadowNode {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for PointShadowNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for PointShadowNode {
+    fn name(&self) -> &str {
+        "point_shadow"
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let (Some(lighting_data), Some(pipeline), Some(gpu_mesh_cache)) = (
+            world.get_resource::<LightingData>(),
+            world.get_resource::<PointShadowPipeline>(),
+            world.get_resource::<GpuMeshCache>(),
+        ) else {
+            return Ok(());
+        };
+        let model_storage = world.get_resource::<ModelStorageData>();
+        let shadows = &lighting_data.point_shadows;
+
+        for (slot, &resolution) in shadows.resolutions.iter().enumerate() {
+            for face in 0..6 {
+                let layer = slot * 6 + face;
+                // Cleared even with nothing to draw, so stale casters don't linger
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Point Shadow Pass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &shadows.face_views[layer],
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                let Some(model_storage) = model_storage else {
+                    continue;
+                };
+                if shadows.batches.is_empty() {
+                    continue;
+                }
+
+                let size = resolution as f32;
+                pass.set_viewport(0.0, 0.0, size, size, 0.0, 1.0);
+                pass.set_pipeline(&pipeline.pipeline);
+                pass.set_bind_group(
+                    0,
+                    &shadows.face_bind_group,
+                    &[(layer as u64 * FACE_UNIFORM_STRIDE) as u32],
+                );
+                pass.set_bind_group(1, &model_storage.bind_group, &[]);
+
+                for batch in &shadows.batches {
+                    let Some(gpu_mesh) = gpu_mesh_cache.get(&batch.mesh_id) else {
+                        continue;
+                    };
+                    if gpu_mesh.index_count == 0 {
+                        continue;
+                    }
+                    pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+                    pass.set_index_buffer(
+                        gpu_mesh.index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    pass.multi_draw_indexed_indirect(&batch.indirect_buffer, 0, batch.draw_count);
+                    context.record_draws(batch.draw_count);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
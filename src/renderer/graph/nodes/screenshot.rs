@@ -0,0 +1,64 @@
+use crate::renderer::capture;
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::screenshot::{HDR_BYTES_PER_PIXEL, PendingScreenshots};
+use crate::renderer::{GraphicsSettings, ScreenshotRequest};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use wgpu::CommandEncoder;
+
+/// Records a copy of the HDR target when a [`ScreenshotRequest`] is present.
+pub struct ScreenshotNode;
+
+impl ScreenshotNode {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for ScreenshotNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for ScreenshotNode {
+    fn name(&self) -> &str {
+        "screenshot"
+    }
+
+    // After tonemapping so every pass writing the HDR target has run
+    fn dependencies(&self) -> &[&str] {
+        &["tonemap"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(request) = world.remove_resource::<ScreenshotRequest>() else {
+            return Ok(());
+        };
+
+        let (width, height) = (context.surface_config.width, context.surface_config.height);
+        let buffer = capture::encode_readback(
+            context.device,
+            encoder,
+            context.hdr_texture,
+            width,
+            height,
+            HDR_BYTES_PER_PIXEL,
+        );
+
+        let settings = world
+            .get_resource::<GraphicsSettings>()
+            .cloned()
+            .unwrap_or_default();
+        world
+            .get_resource_or_insert_with(PendingScreenshots::default)
+            .push(buffer, width, height, request, settings);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::renderer::camera::sorted_camera_views;
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::graph::nodes::fxaa::copy_to_hdr;
+use crate::renderer::{AntiAliasing, GraphicsSettings, RenderTarget, TaaPipeline};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use bytemuck::{Pod, Zeroable};
+use wgpu::CommandEncoder;
+
+/// Weight of the current frame in the resolved color. Lower is smoother but ghosts longer.
+const TAA_BLEND: f32 = 0.1;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct TaaUniform {
+    blend: f32,
+    history_valid: u32,
+    _padding: [f32; 2],
+}
+
+/// Temporal anti-aliasing when [`AntiAliasing::Taa`] is selected.
+///
+/// Resolves the jittered frame against the renderer's
+/// [`TaaHistory`](crate::renderer::TaaHistory), reprojected along the main pass motion
+/// vectors, into the next history texture and copies it back into the HDR target.
+pub struct TaaNode {
+    resolve_buffer: Option<wgpu::Buffer>,
+}
+
+impl TaaNode {
+    pub fn new() -> Self {
+        Self {
+            resolve_buffer: None,
+        }
+    }
+}
+
+impl Default for TaaNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for TaaNode {
+    fn name(&self) -> &str {
+        "taa"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &["main_pass", "wireframe_pass", "water_pass", "gizmo_pass"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let enabled = world
+            .get_resource::<GraphicsSettings>()
+            .is_some_and(|settings| settings.anti_aliasing() == AntiAliasing::Taa);
+        let Some(history) = context.taa_history.filter(|_| enabled) else {
+            return Ok(());
+        };
+        let Some(velocity_view) = context.velocity_view else {
+            return Ok(());
+        };
+        let Some(pipeline) = world.get_resource::<TaaPipeline>() else {
+            log::debug!("TaaPipeline resource not available, skipping TAA");
+            return Ok(());
+        };
+        // Without a window camera the main pass wrote no motion vectors
+        if !sorted_camera_views(world)
+            .iter()
+            .any(|view| view.target == RenderTarget::Window)
+        {
+            return Ok(());
+        }
+
+        let resolve_uniform = TaaUniform {
+            blend: TAA_BLEND,
+            history_valid: history.is_valid() as u32,
+            _padding: [0.0; 2],
+        };
+
+        let resolve_buffer = self.resolve_buffer.get_or_insert_with(|| {
+            context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("TAA Uniform Buffer"),
+                size: std::mem::size_of::<TaaUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        context
+            .queue
+            .write_buffer(resolve_buffer, 0, bytemuck::cast_slice(&[resolve_uniform]));
+
+        // The history textures swap every frame, so the bind group is not cached
+        let resolve_bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("TAA Bind Group"),
+                layout: &pipeline.resolve_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(context.hdr_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(history.read_view()),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(velocity_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&pipeline.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: resolve_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        fullscreen_pass(
+            encoder,
+            "TAA Resolve Pass",
+            history.write_view(),
+            &pipeline.resolve_pipeline,
+            &resolve_bind_group,
+        );
+
+        context.record_draws(1);
+        copy_to_hdr(encoder, history.write_texture(), context);
+
+        Ok(())
+    }
+}
+
+fn fullscreen_pass(
+    encoder: &mut CommandEncoder,
+    label: &str,
+    target: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
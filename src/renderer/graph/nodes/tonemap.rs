@@ -0,0 +1,150 @@
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::{GraphicsSettings, TonemapPipeline, Tonemapping};
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use bytemuck::{Pod, Zeroable};
+use wgpu::CommandEncoder;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct TonemapUniform {
+    exposure: f32,
+    mode: u32,
+    _padding: [f32; 2],
+}
+
+/// Resolves the HDR target onto the swapchain. Runs after every scene pass.
+pub struct TonemapNode {
+    uniform_buffer: Option<wgpu::Buffer>,
+    bind_group: Option<wgpu::BindGroup>,
+    /// Surface size the bind group was created for; the HDR view is recreated on resize.
+    bound_size: (u32, u32),
+}
+
+impl TonemapNode {
+    pub fn new() -> Self {
+        Self {
+            uniform_buffer: None,
+            bind_group: None,
+            bound_size: (0, 0),
+        }
+    }
+}
+
+impl Default for TonemapNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for TonemapNode {
+    fn name(&self) -> &str {
+        "tonemap"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &[
+            "main_pass",
+            "wireframe_pass",
+            "water_pass",
+            "gizmo_pass",
+            "taa",
+            "motion_blur",
+            "fxaa",
+        ]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(pipeline) = world.get_resource::<TonemapPipeline>() else {
+            log::debug!("TonemapPipeline resource not available, skipping tonemapping");
+            return Ok(());
+        };
+
+        let (tonemapping, exposure) = world
+            .get_resource::<GraphicsSettings>()
+            .map(|settings| (settings.tonemapping(), settings.exposure()))
+            .unwrap_or((Tonemapping::default(), 1.0));
+
+        let uniform = TonemapUniform {
+            exposure,
+            mode: tonemapping.as_u32(),
+            _padding: [0.0; 2],
+        };
+
+        let uniform_buffer = self.uniform_buffer.get_or_insert_with(|| {
+            context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Tonemap Uniform Buffer"),
+                size: std::mem::size_of::<TonemapUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        context
+            .queue
+            .write_buffer(uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let size = (context.surface_config.width, context.surface_config.height);
+        if self.bind_group.is_none() || self.bound_size != size {
+            self.bind_group = Some(
+                context
+                    .device
+                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Tonemap Bind Group"),
+                        layout: &pipeline.bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(context.hdr_view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: uniform_buffer.as_entire_binding(),
+                            },
+                        ],
+                    }),
+            );
+            self.bound_size = size;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: context.surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&pipeline.pipeline);
+        render_pass.set_bind_group(0, self.bind_group.as_ref().unwrap(), &[]);
+        render_pass.draw(0..3, 0..1);
+        context.record_draws(1);
+
+        Ok(())
+    }
+}
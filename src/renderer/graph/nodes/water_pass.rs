@@ -0,0 +1,234 @@
+use crate::core::Time;
+use crate::renderer::camera::sorted_camera_views;
+use crate::renderer::graph::node::{RenderContext, RenderNode};
+use crate::renderer::pipeline::{WATER_UNIFORM_STRIDE, WaterPipeline};
+use crate::renderer::water::{WaterReflection, WaterSurface};
+use crate::renderer::{HDR_FORMAT, RenderTarget, RenderTargets};
+use crate::transform::GlobalTransform;
+use anyhow::Result;
+use bevy_ecs::prelude::World;
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer, CommandEncoder};
+
+/// Matches `WaterUniform` in water.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct WaterUniform {
+    view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    center: [f32; 4],
+    half_size: [f32; 4],
+    color: [f32; 4],
+    viewport: [f32; 4],
+}
+
+/// Draws [`WaterSurface`]s over the scene, blending their planar reflection with the
+/// water color.
+pub struct WaterPassNode {
+    /// Created once the render targets' sample layout exists, recreated when MSAA changes.
+    pipeline: Option<WaterPipeline>,
+    /// One uniform per [`WATER_UNIFORM_STRIDE`], grown when more surfaces are visible.
+    uniform_buffer: Option<(Buffer, BindGroup)>,
+    capacity: usize,
+}
+
+impl WaterPassNode {
+    pub fn new() -> Self {
+        Self {
+            pipeline: None,
+            uniform_buffer: None,
+            capacity: 0,
+        }
+    }
+}
+
+impl Default for WaterPassNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderNode for WaterPassNode {
+    fn name(&self) -> &str {
+        "water_pass"
+    }
+
+    fn dependencies(&self) -> &[&str] {
+        &["main_pass"]
+    }
+
+    fn execute(
+        &mut self,
+        world: &mut World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        let Some(render_targets) = world.get_resource::<RenderTargets>() else {
+            return Ok(());
+        };
+        let Some(sample_layout) = render_targets.sample_layout() else {
+            return Ok(());
+        };
+
+        // Reflection cameras come first, so look for the first camera drawing to the window
+        let Some(view) = sorted_camera_views(world)
+            .into_iter()
+            .find(|view| view.target == RenderTarget::Window)
+        else {
+            return Ok(());
+        };
+        let camera_position = world
+            .get::<GlobalTransform>(view.entity)
+            .map(|transform| transform.position())
+            .unwrap_or_default();
+        let time = world
+            .get_resource::<Time>()
+            .map(|time| time.elapsed_seconds())
+            .unwrap_or(0.0);
+        let (viewport_x, viewport_y, viewport_width, viewport_height) = view
+            .viewport
+            .unwrap_or_default()
+            .to_pixels((context.surface_config.width, context.surface_config.height));
+
+        let mut draws = Vec::new();
+        let mut data = Vec::new();
+        let Some(mut surfaces) =
+            world.try_query::<(&WaterSurface, &WaterReflection, &GlobalTransform)>()
+        else {
+            return Ok(());
+        };
+        for (surface, reflection, transform) in surfaces.iter(world) {
+            let Some(reflection_bind_group) =
+                render_targets.sample_bind_group(reflection.texture.id())
+            else {
+                continue;
+            };
+
+            let uniform = WaterUniform {
+                view_proj: view.view_proj.to_cols_array_2d(),
+                camera_position: camera_position.extend(time).to_array(),
+                center: transform.position().extend(surface.distortion).to_array(),
+                half_size: [
+                    surface.size.x * 0.5,
+                    surface.size.y * 0.5,
+                    surface.wave_speed,
+                    surface.fresnel_power,
+                ],
+                color: surface.color.extend(surface.opacity).to_array(),
+                viewport: [viewport_x, viewport_y, viewport_width, viewport_height],
+            };
+            data.resize(draws.len() * WATER_UNIFORM_STRIDE as usize, 0);
+            data.extend_from_slice(bytemuck::bytes_of(&uniform));
+            draws.push(reflection_bind_group);
+        }
+        if draws.is_empty() {
+            return Ok(());
+        }
+
+        if self
+            .pipeline
+            .as_ref()
+            .is_none_or(|pipeline| pipeline.sample_count != context.msaa_sample_count)
+        {
+            self.pipeline = Some(WaterPipeline::new(
+                context.device,
+                HDR_FORMAT,
+                context.msaa_sample_count,
+                sample_layout,
+            ));
+            self.uniform_buffer = None;
+        }
+        let pipeline = self.pipeline.as_ref().unwrap();
+
+        if self.uniform_buffer.is_none() || draws.len() > self.capacity {
+            self.capacity = draws.len().next_power_of_two().max(4);
+            let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Water Uniform Buffer"),
+                size: self.capacity as u64 * WATER_UNIFORM_STRIDE,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Water Bind Group"),
+                    layout: &pipeline.water_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(std::mem::size_of::<WaterUniform>() as u64),
+                        }),
+                    }],
+                });
+            self.uniform_buffer = Some((buffer, bind_group));
+        }
+        let (buffer, water_bind_group) = self.uniform_buffer.as_ref().unwrap();
+        context.queue.write_buffer(buffer, 0, &data);
+
+        let (color_view, resolve_target) = if let Some(msaa_view) = context.msaa_color_view {
+            (msaa_view, Some(context.hdr_view))
+        } else {
+            (context.hdr_view, None)
+        };
+        let depth_view = context.msaa_depth_view.unwrap_or(context.depth_view);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Water Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        if view.viewport.is_some() {
+            render_pass.set_viewport(
+                viewport_x,
+                viewport_y,
+                viewport_width,
+                viewport_height,
+                0.0,
+                1.0,
+            );
+        }
+        render_pass.set_pipeline(&pipeline.pipeline);
+        for (slot, reflection_bind_group) in draws.into_iter().enumerate() {
+            let offset = (slot as u64 * WATER_UNIFORM_STRIDE) as u32;
+            render_pass.set_bind_group(0, water_bind_group, &[offset]);
+            render_pass.set_bind_group(1, reflection_bind_group, &[]);
+            render_pass.draw(0..6, 0..1);
+            context.record_draws(1);
+        }
+
+        Ok(())
+    }
+}
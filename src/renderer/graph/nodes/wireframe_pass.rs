@@ -1,18 +1,110 @@
-use crate::addons::WireframeState;
-use crate::core::math::Mat4;
+use crate::addons::{ExtractedWireframeOverlays, WireframeState};
+use crate::renderer::camera::sorted_camera_views;
 use crate::renderer::components::{IndirectDrawData, ModelStorageData};
 use crate::renderer::graph::node::{RenderContext, RenderNode};
-use crate::renderer::{Camera, GpuMeshCache, WireframePipeline};
-use crate::transform::GlobalTransform;
+use crate::renderer::pipeline::WIREFRAME_OVERLAY_STRIDE;
+use crate::renderer::systems::FrameAllocator;
+use crate::renderer::systems::draw::gpu_culling::{GpuCullData, batch_indirect_source};
+use crate::renderer::{GpuMeshCache, RenderTarget, WireframePipeline};
 use anyhow::Result;
 use bevy_ecs::prelude::World;
-use wgpu::CommandEncoder;
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer, CommandEncoder, RenderPass};
 
-pub struct WireframePassNode;
+/// Matches `Overlay` in wireframe.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct OverlayUniform {
+    color: [f32; 4],
+    depth_bias: f32,
+    _padding: [f32; 3],
+}
+
+pub struct WireframePassNode {
+    /// One overlay uniform per [`WIREFRAME_OVERLAY_STRIDE`], grown when more entities have overlays.
+    overlay_buffer: Option<(Buffer, BindGroup)>,
+    overlay_capacity: usize,
+}
 
 impl WireframePassNode {
     pub fn new() -> Self {
-        Self
+        Self {
+            overlay_buffer: None,
+            overlay_capacity: 0,
+        }
+    }
+
+    /// Uploads this frame's overlays and returns each one's model storage index
+    /// and mesh, skipping entities that weren't drawn.
+    fn prepare_overlays(
+        &mut self,
+        context: &RenderContext,
+        pipeline: &WireframePipeline,
+        overlays: &ExtractedWireframeOverlays,
+        frame_allocator: &FrameAllocator,
+    ) -> Vec<(u32, crate::assets::handle::AssetId)> {
+        let mut draws = Vec::with_capacity(overlays.overlays.len());
+        let mut data = Vec::new();
+        for (entity, mesh_id, overlay) in &overlays.overlays {
+            // Model storage is laid out in FrameAllocator::entities order, sorted by mesh then entity
+            let Ok(index) = frame_allocator
+                .entities
+                .binary_search_by_key(&(mesh_id.0, *entity), |(entity, mesh_id, ..)| {
+                    (mesh_id.0, *entity)
+                })
+            else {
+                continue;
+            };
+
+            let uniform = OverlayUniform {
+                color: overlay.color.extend(1.0).to_array(),
+                depth_bias: overlay.depth_bias,
+                _padding: [0.0; 3],
+            };
+            data.resize(draws.len() * WIREFRAME_OVERLAY_STRIDE as usize, 0);
+            data.extend_from_slice(bytemuck::bytes_of(&uniform));
+            draws.push((index as u32, *mesh_id));
+        }
+        if draws.is_empty() {
+            return draws;
+        }
+
+        if self.overlay_buffer.is_none() || draws.len() > self.overlay_capacity {
+            self.overlay_capacity = draws.len().next_power_of_two().max(16);
+            let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Wireframe Overlay Buffer"),
+                size: self.overlay_capacity as u64 * WIREFRAME_OVERLAY_STRIDE,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Wireframe Overlay Bind Group"),
+                    layout: &pipeline.overlay_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(
+                                std::mem::size_of::<OverlayUniform>() as u64
+                            ),
+                        }),
+                    }],
+                });
+            self.overlay_buffer = Some((buffer, bind_group));
+        }
+        let (buffer, _) = self.overlay_buffer.as_ref().unwrap();
+        context.queue.write_buffer(buffer, 0, &data);
+
+        draws
+    }
+}
+
+impl Default for WireframePassNode {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -30,27 +122,54 @@ impl RenderNode for WireframePassNode {
         world: &mut World,
         context: &RenderContext,
         encoder: &mut CommandEncoder,
+    ) -> Result<()> {
+        self.execute_read_only(world, context, encoder)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn execute_read_only(
+        &mut self,
+        world: &World,
+        context: &RenderContext,
+        encoder: &mut CommandEncoder,
     ) -> Result<()> {
         let wireframe_state = world
             .get_resource::<WireframeState>()
             .map(|s| s.enabled)
             .unwrap_or(false);
+        let overlays = world
+            .get_resource::<ExtractedWireframeOverlays>()
+            .filter(|overlays| !overlays.overlays.is_empty());
 
-        if !wireframe_state {
+        if !wireframe_state && overlays.is_none() {
             return Ok(());
         }
 
-        let camera_view_proj: Option<Mat4> = world
-            .query::<(&Camera, &GlobalTransform)>()
-            .iter(world)
+        let overlay_draws = match (
+            overlays,
+            world.get_resource::<WireframePipeline>(),
+            world.get_resource::<FrameAllocator>(),
+        ) {
+            (Some(overlays), Some(pipeline), Some(frame_allocator)) => {
+                self.prepare_overlays(context, pipeline, overlays, frame_allocator)
+            }
+            _ => Vec::new(),
+        };
+
+        // Wireframes are drawn for the first camera only, which uses the renderer's camera bind group
+        let first_view = sorted_camera_views(world)
+            .into_iter()
             .next()
-            .map(|(camera, transform)| camera.view_projection_matrix(transform));
+            .filter(|view| view.target == RenderTarget::Window);
 
         {
             let (color_view, resolve_target) = if let Some(msaa_view) = context.msaa_color_view {
-                (msaa_view, Some(context.surface_view))
+                (msaa_view, Some(context.hdr_view))
             } else {
-                (context.surface_view, None)
+                (context.hdr_view, None)
             };
 
             let depth_view = context.msaa_depth_view.unwrap_or(context.depth_view);
@@ -78,7 +197,13 @@ impl RenderNode for WireframePassNode {
                 timestamp_writes: None,
             });
 
-            if camera_view_proj.is_none() {
+            if let Some(viewport) = first_view.and_then(|view| view.viewport) {
+                let (x, y, width, height) = viewport
+                    .to_pixels((context.surface_config.width, context.surface_config.height));
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            }
+
+            if first_view.is_none() {
                 log::debug!("No active camera found, skipping wireframe rendering");
             } else if world.get_resource::<WireframePipeline>().is_none() {
                 log::debug!("WireframePipeline resource not available, skipping wireframe rendering");
@@ -96,10 +221,28 @@ impl RenderNode for WireframePassNode {
                 let model_storage_data = world.get_resource::<ModelStorageData>().unwrap();
                 let indirect_draw_data = world.get_resource::<IndirectDrawData>().unwrap();
 
-                render_pass.set_pipeline(&pipeline.pipeline);
                 render_pass.set_bind_group(0, context.camera_bind_group.unwrap(), &[]);
                 render_pass.set_bind_group(1, &model_storage_data.bind_group, &[]);
 
+                if let (Some((_, overlay_bind_group)), false) =
+                    (&self.overlay_buffer, overlay_draws.is_empty())
+                {
+                    draw_overlays(
+                        context,
+                        &mut render_pass,
+                        pipeline,
+                        gpu_mesh_cache,
+                        overlay_bind_group,
+                        &overlay_draws,
+                    );
+                }
+
+                if !wireframe_state {
+                    return Ok(());
+                }
+
+                render_pass.set_pipeline(&pipeline.pipeline);
+                let gpu_cull = world.get_resource::<GpuCullData>();
                 for batch in &indirect_draw_data.batches {
                     if let Some(gpu_mesh) = gpu_mesh_cache.get(&batch.mesh_id) {
                         if gpu_mesh.index_count == 0 {
@@ -110,11 +253,14 @@ impl RenderNode for WireframePassNode {
                             gpu_mesh.index_buffer.slice(..),
                             wgpu::IndexFormat::Uint32,
                         );
+                        let (indirect_buffer, indirect_offset, draw_count) =
+                            batch_indirect_source(gpu_cull, batch);
                         render_pass.multi_draw_indexed_indirect(
-                            &batch.indirect_buffer,
-                            0,
-                            batch.draw_count,
+                            indirect_buffer,
+                            indirect_offset,
+                            draw_count,
                         );
+                        context.record_draws(draw_count);
                     }
                 }
             }
@@ -123,3 +269,28 @@ impl RenderNode for WireframePassNode {
         Ok(())
     }
 }
+
+fn draw_overlays(
+    context: &RenderContext,
+    render_pass: &mut RenderPass,
+    pipeline: &WireframePipeline,
+    gpu_mesh_cache: &GpuMeshCache,
+    bind_group: &BindGroup,
+    draws: &[(u32, crate::assets::handle::AssetId)],
+) {
+    render_pass.set_pipeline(&pipeline.overlay_pipeline);
+    for (slot, (index, mesh_id)) in draws.iter().enumerate() {
+        let Some(gpu_mesh) = gpu_mesh_cache.get(mesh_id) else {
+            continue;
+        };
+        if gpu_mesh.index_count == 0 {
+            continue;
+        }
+        let offset = (slot as u64 * WIREFRAME_OVERLAY_STRIDE) as u32;
+        render_pass.set_bind_group(2, bind_group, &[offset]);
+        render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..gpu_mesh.index_count, 0, *index..*index + 1);
+        context.record_draws(1);
+    }
+}
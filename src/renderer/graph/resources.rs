@@ -0,0 +1,272 @@
+//! Textures and buffers shared between render nodes by name.
+//!
+//! A node that needs an intermediate target declares it in
+//! [`RenderNode::declare_resources`](super::node::RenderNode::declare_resources), and the
+//! [`RenderGraph`](super::RenderGraph) creates it before the node first runs and recreates
+//! surface-sized textures when the window is resized. Every node can then look it up through
+//! [`RenderContext::resources`](super::node::RenderContext::resources):
+//!
+//! ```ignore
+//! impl RenderNode for BloomNode {
+//!     fn declare_resources(&self, resources: &mut GraphResources) {
+//!         resources.declare_texture("bloom", TextureDesc::new(HDR_FORMAT).with_scale(0.5));
+//!     }
+//!
+//!     fn execute(&mut self, world: &mut World, context: &RenderContext, encoder: &mut CommandEncoder) -> Result<()> {
+//!         let bloom = context.resources.texture_view("bloom")?;
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! Declaring a name again with a different description recreates the resource. Nodes that
+//! share a resource are still ordered by their `dependencies`.
+
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use wgpu::{Buffer, BufferUsages, Device, Texture, TextureFormat, TextureUsages, TextureView};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureSize {
+    /// Fraction of the surface size, following window resizes.
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+impl TextureSize {
+    pub fn resolve(self, surface_size: (u32, u32)) -> (u32, u32) {
+        match self {
+            Self::Scaled(scale) => (
+                ((surface_size.0 as f32 * scale).round() as u32).max(1),
+                ((surface_size.1 as f32 * scale).round() as u32).max(1),
+            ),
+            Self::Fixed(width, height) => (width.max(1), height.max(1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureDesc {
+    pub format: TextureFormat,
+    pub size: TextureSize,
+    pub usage: TextureUsages,
+    pub sample_count: u32,
+    pub mip_level_count: u32,
+}
+
+impl TextureDesc {
+    /// A surface-sized texture that can be rendered to and sampled.
+    pub fn new(format: TextureFormat) -> Self {
+        Self {
+            format,
+            size: TextureSize::Scaled(1.0),
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            sample_count: 1,
+            mip_level_count: 1,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.size = TextureSize::Scaled(scale);
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = TextureSize::Fixed(width, height);
+        self
+    }
+
+    pub fn with_usage(mut self, usage: TextureUsages) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn with_mip_levels(mut self, mip_level_count: u32) -> Self {
+        self.mip_level_count = mip_level_count;
+        self
+    }
+
+    fn bytes_per_pixel(&self) -> u64 {
+        self.format.block_copy_size(None).unwrap_or(4) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferDesc {
+    pub size: u64,
+    pub usage: BufferUsages,
+}
+
+impl BufferDesc {
+    pub fn new(size: u64, usage: BufferUsages) -> Self {
+        Self { size, usage }
+    }
+}
+
+pub struct GraphTexture {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub size: (u32, u32),
+}
+
+/// Named textures and buffers owned by the render graph.
+#[derive(Default)]
+pub struct GraphResources {
+    texture_descs: HashMap<String, TextureDesc>,
+    buffer_descs: HashMap<String, BufferDesc>,
+    textures: HashMap<String, GraphTexture>,
+    buffers: HashMap<String, Buffer>,
+    surface_size: (u32, u32),
+}
+
+impl GraphResources {
+    pub fn declare_texture(&mut self, name: impl Into<String>, desc: TextureDesc) {
+        let name = name.into();
+        if self.texture_descs.get(&name) != Some(&desc) {
+            self.textures.remove(&name);
+            self.texture_descs.insert(name, desc);
+        }
+    }
+
+    pub fn declare_buffer(&mut self, name: impl Into<String>, desc: BufferDesc) {
+        let name = name.into();
+        if self.buffer_descs.get(&name) != Some(&desc) {
+            self.buffers.remove(&name);
+            self.buffer_descs.insert(name, desc);
+        }
+    }
+
+    /// Removes the texture or buffer declared under `name`.
+    pub fn remove(&mut self, name: &str) {
+        self.texture_descs.remove(name);
+        self.textures.remove(name);
+        self.buffer_descs.remove(name);
+        self.buffers.remove(name);
+    }
+
+    pub fn is_declared(&self, name: &str) -> bool {
+        self.texture_descs.contains_key(name) || self.buffer_descs.contains_key(name)
+    }
+
+    pub fn texture_desc(&self, name: &str) -> Option<&TextureDesc> {
+        self.texture_descs.get(name)
+    }
+
+    pub fn texture(&self, name: &str) -> Result<&GraphTexture> {
+        self.textures
+            .get(name)
+            .ok_or_else(|| anyhow!("Render graph texture '{}' is not declared", name))
+    }
+
+    pub fn texture_view(&self, name: &str) -> Result<&TextureView> {
+        self.texture(name).map(|texture| &texture.view)
+    }
+
+    pub fn buffer(&self, name: &str) -> Result<&Buffer> {
+        self.buffers
+            .get(name)
+            .ok_or_else(|| anyhow!("Render graph buffer '{}' is not declared", name))
+    }
+
+    /// Approximate GPU memory of the created resources, in bytes.
+    pub fn memory_size(&self) -> u64 {
+        let textures: u64 = self
+            .textures
+            .iter()
+            .map(|(name, texture)| {
+                let desc = &self.texture_descs[name];
+                let pixels = texture.size.0 as u64 * texture.size.1 as u64;
+                // A full mip chain adds about a third
+                let mips = if desc.mip_level_count > 1 { 4 } else { 3 };
+                pixels * desc.bytes_per_pixel() * desc.sample_count as u64 * mips / 3
+            })
+            .sum();
+        let buffers: u64 = self.buffers.values().map(|buffer| buffer.size()).sum();
+        textures + buffers
+    }
+
+    /// Creates declared resources that don't exist yet, and recreates surface-sized textures
+    /// after a resize.
+    pub(crate) fn prepare(&mut self, device: &Device, surface_size: (u32, u32)) {
+        if self.surface_size != surface_size {
+            self.surface_size = surface_size;
+            let descs = &self.texture_descs;
+            self.textures
+                .retain(|name, _| !matches!(descs[name].size, TextureSize::Scaled(_)));
+        }
+
+        for (name, desc) in &self.texture_descs {
+            if self.textures.contains_key(name) {
+                continue;
+            }
+            let size = desc.size.resolve(surface_size);
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: desc.mip_level_count,
+                sample_count: desc.sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: desc.format,
+                usage: desc.usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.textures.insert(
+                name.clone(),
+                GraphTexture {
+                    texture,
+                    view,
+                    size,
+                },
+            );
+        }
+
+        for (name, desc) in &self.buffer_descs {
+            if self.buffers.contains_key(name) {
+                continue;
+            }
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(name),
+                size: desc.size,
+                usage: desc.usage,
+                mapped_at_creation: false,
+            });
+            self.buffers.insert(name.clone(), buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_size_follows_surface() {
+        assert_eq!(TextureSize::Scaled(1.0).resolve((1920, 1080)), (1920, 1080));
+        assert_eq!(TextureSize::Scaled(0.5).resolve((1920, 1080)), (960, 540));
+        assert_eq!(TextureSize::Scaled(0.5).resolve((1, 1)), (1, 1));
+        assert_eq!(
+            TextureSize::Fixed(256, 256).resolve((1920, 1080)),
+            (256, 256)
+        );
+
+        let mut resources = GraphResources::default();
+        let desc = TextureDesc::new(TextureFormat::Rgba16Float).with_scale(0.5);
+        resources.declare_texture("bloom", desc);
+        assert!(resources.is_declared("bloom"));
+        assert!(resources.texture("bloom").is_err());
+        assert_eq!(resources.texture_desc("bloom"), Some(&desc));
+
+        resources.remove("bloom");
+        assert!(!resources.is_declared("bloom"));
+    }
+}
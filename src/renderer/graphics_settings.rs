@@ -1,7 +1,9 @@
+use crate::core::math::Vec3;
 use bevy_ecs::prelude::Resource;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum MsaaSampleCount {
+    #[default]
     X1 = 1,
     X2 = 2,
     X4 = 4,
@@ -24,16 +26,77 @@ impl MsaaSampleCount {
     }
 }
 
-impl Default for MsaaSampleCount {
-    fn default() -> Self {
-        Self::X1
+/// Operator used to map HDR scene color to the display range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemapping {
+    /// Clamp only.
+    None,
+    Reinhard,
+    #[default]
+    Aces,
+}
+
+impl Tonemapping {
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Reinhard => 1,
+            Self::Aces => 2,
+        }
+    }
+
+    /// CPU version of the curves in `tonemap.wgsl`, used for readback.
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            Self::None => color.clamp(Vec3::ZERO, Vec3::ONE),
+            Self::Reinhard => color / (Vec3::ONE + color),
+            Self::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((color * (a * color + b)) / (color * (c * color + d) + e))
+                    .clamp(Vec3::ZERO, Vec3::ONE)
+            }
+        }
     }
 }
 
+/// Post-process anti-aliasing applied to the HDR scene before tonemapping.
+///
+/// Independent of MSAA, except that [`AntiAliasing::Taa`] needs a single-sampled depth
+/// buffer and turns MSAA off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AntiAliasing {
+    #[default]
+    None,
+    /// Fast approximate AA: blurs along detected edges in one screen-space pass.
+    Fxaa,
+    /// Temporal AA: jitters the camera every frame and blends with reprojected history.
+    /// Smooths edges and shimmering better than FXAA, but can ghost behind fast motion.
+    Taa,
+}
+
+/// Default upper bound on point lights uploaded to the GPU each frame.
+pub const DEFAULT_MAX_POINT_LIGHTS: u32 = 1024;
+
+/// Default number of point lights that render shadow cube maps.
+pub const DEFAULT_MAX_SHADOW_POINT_LIGHTS: u32 = 4;
+
+/// Default VRAM budget for streamed textures: 512 MiB.
+pub const DEFAULT_TEXTURE_BUDGET: u64 = 512 * 1024 * 1024;
+
 #[derive(Debug, Clone, Resource)]
 pub struct GraphicsSettings {
     msaa_sample_count: MsaaSampleCount,
     vsync_enabled: bool,
+    max_point_lights: u32,
+    max_shadow_point_lights: u32,
+    tonemapping: Tonemapping,
+    exposure: f32,
+    frames_in_flight: u32,
+    gpu_culling: bool,
+    occlusion_culling: bool,
+    anti_aliasing: AntiAliasing,
+    motion_blur: f32,
+    texture_budget: u64,
     changed: bool,
 }
 
@@ -42,6 +105,16 @@ impl GraphicsSettings {
         Self {
             msaa_sample_count,
             vsync_enabled,
+            max_point_lights: DEFAULT_MAX_POINT_LIGHTS,
+            max_shadow_point_lights: DEFAULT_MAX_SHADOW_POINT_LIGHTS,
+            tonemapping: Tonemapping::default(),
+            exposure: 1.0,
+            frames_in_flight: crate::renderer::frame::DEFAULT_FRAMES_IN_FLIGHT,
+            gpu_culling: false,
+            occlusion_culling: false,
+            anti_aliasing: AntiAliasing::None,
+            motion_blur: 0.0,
+            texture_budget: DEFAULT_TEXTURE_BUDGET,
             changed: true,
         }
     }
@@ -50,7 +123,12 @@ impl GraphicsSettings {
         self.msaa_sample_count
     }
 
+    /// Enabling MSAA while [`AntiAliasing::Taa`] is selected switches anti-aliasing off.
     pub fn set_msaa_sample_count(&mut self, count: MsaaSampleCount) {
+        if count != MsaaSampleCount::X1 && self.anti_aliasing == AntiAliasing::Taa {
+            self.anti_aliasing = AntiAliasing::None;
+            self.changed = true;
+        }
         if self.msaa_sample_count != count {
             self.msaa_sample_count = count;
             self.changed = true;
@@ -62,19 +140,144 @@ impl GraphicsSettings {
     }
 
     pub fn enable_vsync(&mut self) {
-        if self.vsync_enabled != true {
+        if !self.vsync_enabled {
             self.vsync_enabled = true;
             self.changed = true;
         }
     }
 
     pub fn disable_vsync(&mut self) {
-        if self.vsync_enabled != false {
+        if self.vsync_enabled {
             self.vsync_enabled = false;
             self.changed = true;
         }
     }
 
+    pub fn max_point_lights(&self) -> u32 {
+        self.max_point_lights
+    }
+
+    /// Sets how many point lights are sent to the shader. Extra lights are ignored.
+    ///
+    /// Lights are binned into clusters each frame, so shading cost depends on how many
+    /// lights overlap a fragment rather than on this limit.
+    ///
+    /// Takes effect on the next frame and does not require recreating pipelines.
+    pub fn set_max_point_lights(&mut self, max: u32) {
+        self.max_point_lights = max;
+    }
+
+    pub fn max_shadow_point_lights(&self) -> u32 {
+        self.max_shadow_point_lights
+    }
+
+    /// Sets how many point lights with [`cast_shadows`](crate::renderer::PointLight::cast_shadows)
+    /// render shadow cube maps; the ones closest to the camera win. Each one redraws the shadow
+    /// casters six times a frame, so this bounds the cost of point light shadows.
+    ///
+    /// Takes effect on the next frame.
+    pub fn set_max_shadow_point_lights(&mut self, max: u32) {
+        self.max_shadow_point_lights = max;
+    }
+
+    pub fn tonemapping(&self) -> Tonemapping {
+        self.tonemapping
+    }
+
+    pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+        self.tonemapping = tonemapping;
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Linear multiplier applied to scene color before tonemapping.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+    }
+
+    pub fn frames_in_flight(&self) -> u32 {
+        self.frames_in_flight
+    }
+
+    /// Sets how many frames the CPU may prepare ahead of the GPU, clamped to
+    /// `1..=MAX_FRAMES_IN_FLIGHT`. More frames smooth out spikes at the cost of latency.
+    /// Only the camera uniform is kept per frame; other per-frame buffers are shared and
+    /// updated through the queue.
+    pub fn set_frames_in_flight(&mut self, frames: u32) {
+        let frames = frames.clamp(1, crate::renderer::frame::MAX_FRAMES_IN_FLIGHT);
+        if self.frames_in_flight != frames {
+            self.frames_in_flight = frames;
+            self.changed = true;
+        }
+    }
+
+    pub fn gpu_culling(&self) -> bool {
+        self.gpu_culling
+    }
+
+    /// Culls instances against the camera frustums in a compute pass instead of on the CPU.
+    ///
+    /// Worth enabling for large scenes, where rebuilding indirect draws on every visibility
+    /// change costs more than drawing zeroed commands. Takes effect on the next frame.
+    pub fn set_gpu_culling(&mut self, enabled: bool) {
+        self.gpu_culling = enabled;
+    }
+
+    pub fn occlusion_culling(&self) -> bool {
+        self.occlusion_culling
+    }
+
+    /// Additionally culls instances hidden behind closer geometry, using a depth prepass
+    /// and a hierarchical-Z pyramid. Requires GPU culling and a single full-window camera;
+    /// otherwise only frustum culling is applied.
+    pub fn set_occlusion_culling(&mut self, enabled: bool) {
+        self.occlusion_culling = enabled;
+    }
+
+    pub fn anti_aliasing(&self) -> AntiAliasing {
+        self.anti_aliasing
+    }
+
+    /// Selecting [`AntiAliasing::Taa`] sets the MSAA sample count to 1.
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        if anti_aliasing == AntiAliasing::Taa {
+            self.set_msaa_sample_count(MsaaSampleCount::X1);
+        }
+        if self.anti_aliasing != anti_aliasing {
+            self.anti_aliasing = anti_aliasing;
+            self.changed = true;
+        }
+    }
+
+    /// Motion blur intensity; 0 means off.
+    pub fn motion_blur(&self) -> f32 {
+        self.motion_blur
+    }
+
+    /// Blurs each pixel along its motion since the previous frame, scaled by `intensity`
+    /// in `0.0..=1.0`. 1.0 smears over the full distance moved in one frame, like a camera
+    /// shutter open for the whole frame. 0.0 turns motion blur off.
+    pub fn set_motion_blur(&mut self, intensity: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        // Only turning it on or off allocates or frees targets
+        if (self.motion_blur > 0.0) != (intensity > 0.0) {
+            self.changed = true;
+        }
+        self.motion_blur = intensity;
+    }
+
+    pub fn texture_budget(&self) -> u64 {
+        self.texture_budget
+    }
+
+    /// Sets how many bytes of VRAM streamed textures may use. Over budget, mip levels of the
+    /// least important textures are dropped. Takes effect on the next frame.
+    pub fn set_texture_budget(&mut self, bytes: u64) {
+        self.texture_budget = bytes;
+    }
+
     pub fn take_changed(&mut self) -> bool {
         let changed = self.changed;
         self.changed = false;
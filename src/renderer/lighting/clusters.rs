@@ -0,0 +1,122 @@
+//! Clustered light assignment.
+//!
+//! The view frustum of the first window camera is split into a grid of froxels: screen
+//! tiles along X and Y, exponentially spaced depth slices along Z. Every frame the
+//! [`LightClusterNode`](crate::renderer::LightClusterNode) bins point lights into the
+//! froxels their sphere touches, and the mesh shader only evaluates the lights of the
+//! froxel a fragment falls into. Fragments outside that frustum, e.g. seen by a
+//! render-to-texture camera, loop over all lights instead.
+
+use crate::core::math::*;
+use crate::renderer::Camera;
+use crate::transform::GlobalTransform;
+use bytemuck::{Pod, Zeroable};
+
+/// Froxels along X, Y and Z.
+pub const CLUSTER_DIMENSIONS: UVec3 = UVec3::new(16, 9, 24);
+/// Lights beyond this many in one froxel are dropped from it.
+pub const MAX_LIGHTS_PER_CLUSTER: u32 = 64;
+/// Must match the workgroup size in light_cluster.wgsl.
+pub const CLUSTER_WORKGROUP_SIZE: u32 = 64;
+
+pub const fn cluster_count() -> u32 {
+    CLUSTER_DIMENSIONS.x * CLUSTER_DIMENSIONS.y * CLUSTER_DIMENSIONS.z
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct ClusterUniform {
+    pub view: [[f32; 4]; 4],
+    pub view_proj: [[f32; 4]; 4],
+    /// x, y: tangent of half the horizontal and vertical field of view; z: near; w: far.
+    pub projection: [f32; 4],
+    /// Froxels along x, y and z; w: point light count.
+    pub dimensions: [u32; 4],
+    /// 0 when there is no camera to build clusters for.
+    pub enabled: u32,
+    pub _padding: [u32; 3],
+}
+
+impl ClusterUniform {
+    /// Clusters are sliced along perspective view rays, so orthographic cameras get
+    /// [`disabled`](Self::disabled) clusters.
+    pub fn from_camera(camera: &Camera, transform: &GlobalTransform, light_count: u32) -> Self {
+        if camera.is_orthographic() {
+            return Self::disabled(light_count);
+        }
+        let tan_half_fov = (camera.fov * 0.5).tan();
+        Self {
+            view: camera.view_matrix(transform).to_cols_array_2d(),
+            view_proj: camera.view_projection_matrix(transform).to_cols_array_2d(),
+            projection: [
+                tan_half_fov * camera.aspect,
+                tan_half_fov,
+                camera.near,
+                camera.far,
+            ],
+            dimensions: CLUSTER_DIMENSIONS.extend(light_count).to_array(),
+            enabled: 1,
+            _padding: [0; 3],
+        }
+    }
+
+    /// Fragments skip the clusters and loop over all `light_count` lights.
+    pub fn disabled(light_count: u32) -> Self {
+        Self {
+            dimensions: CLUSTER_DIMENSIONS.extend(light_count).to_array(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ClusterUniform {
+    fn default() -> Self {
+        Self {
+            view: Mat4::IDENTITY.to_cols_array_2d(),
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            projection: [1.0, 1.0, 0.1, 1000.0],
+            dimensions: CLUSTER_DIMENSIONS.extend(0).to_array(),
+            enabled: 0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Depth slice of a view-space distance, or `None` outside `near..far`.
+///
+/// Slices grow exponentially so froxels stay roughly cube-shaped. Same as
+/// `depth_slice` in mesh.wgsl.
+pub fn depth_slice(depth: f32, near: f32, far: f32) -> Option<u32> {
+    if depth < near || depth >= far {
+        return None;
+    }
+    let slice = (depth / near).ln() / (far / near).ln() * CLUSTER_DIMENSIONS.z as f32;
+    Some((slice as u32).min(CLUSTER_DIMENSIONS.z - 1))
+}
+
+/// View-space distance range covered by a depth slice. Same as `slice_depth` in
+/// light_cluster.wgsl.
+pub fn slice_depth_range(slice: u32, near: f32, far: f32) -> (f32, f32) {
+    let depth = |slice: u32| near * (far / near).powf(slice as f32 / CLUSTER_DIMENSIONS.z as f32);
+    (depth(slice), depth(slice + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_slices_cover_range() {
+        let (near, far) = (0.1, 1000.0);
+        assert_eq!(depth_slice(0.05, near, far), None);
+        assert_eq!(depth_slice(far, near, far), None);
+        assert_eq!(slice_depth_range(0, near, far).0, near);
+        assert!((slice_depth_range(CLUSTER_DIMENSIONS.z - 1, near, far).1 - far).abs() < 1e-2);
+
+        for slice in 0..CLUSTER_DIMENSIONS.z {
+            let (start, end) = slice_depth_range(slice, near, far);
+            let middle = (start * end).sqrt();
+            assert_eq!(depth_slice(middle, near, far), Some(slice));
+        }
+    }
+}
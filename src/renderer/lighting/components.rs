@@ -1,5 +1,5 @@
 use crate::core::math::*;
-use bevy_ecs::prelude::Component;
+use bevy_ecs::prelude::{Component, Resource};
 
 #[derive(Component, Clone, Debug)]
 pub struct DirectionalLight {
@@ -30,13 +30,20 @@ impl Default for DirectionalLight {
     }
 }
 
+/// Default edge length in texels of each face of a point light's shadow cube map.
+pub const DEFAULT_POINT_SHADOW_RESOLUTION: u32 = 512;
+
 #[derive(Component, Clone, Debug)]
 pub struct PointLight {
     pub position: Vec3,
     pub color: Vec3,
     pub intensity: f32,
     pub radius: f32,
+    /// Renders a shadow cube map for this light. Only the lights closest to the camera get
+    /// one, up to [`GraphicsSettings::max_shadow_point_lights`](crate::renderer::GraphicsSettings::max_shadow_point_lights).
     pub cast_shadows: bool,
+    /// Edge length in texels of each cube face.
+    pub shadow_resolution: u32,
 }
 
 impl PointLight {
@@ -47,9 +54,17 @@ impl PointLight {
             intensity,
             radius,
             cast_shadows: false,
+            shadow_resolution: DEFAULT_POINT_SHADOW_RESOLUTION,
         }
     }
 
+    /// Casts shadows with cube faces of `resolution` texels.
+    pub fn with_shadows(mut self, resolution: u32) -> Self {
+        self.cast_shadows = true;
+        self.shadow_resolution = resolution;
+        self
+    }
+
     pub fn attenuation(&self, distance: f32) -> f32 {
         let ratio = distance / self.radius;
         let attenuation = 1.0 - ratio.powi(4);
@@ -80,3 +95,102 @@ impl Default for AmbientLight {
         Self::new(Vec3::new(0.4, 0.5, 0.6), 0.8)
     }
 }
+
+/// How fog thickens with view depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FogMode {
+    /// Ramps from none at `start` to full at `end`.
+    #[default]
+    Linear,
+    /// `1 - exp(-density * depth)`.
+    Exponential,
+    /// `1 - exp(-(density * depth)^2)`, which keeps nearby geometry clearer.
+    ExponentialSquared,
+}
+
+/// Distance fog applied in the main pass. Without this resource there is no fog.
+///
+/// The main pass also clears to `color`, so geometry fades into the background.
+#[derive(Resource, Clone, Debug)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Vec3,
+    /// Used by the exponential modes.
+    pub density: f32,
+    /// Used by [`FogMode::Linear`].
+    pub start: f32,
+    /// Used by [`FogMode::Linear`].
+    pub end: f32,
+}
+
+impl Fog {
+    pub fn linear(color: Vec3, start: f32, end: f32) -> Self {
+        Self {
+            mode: FogMode::Linear,
+            color,
+            start,
+            end,
+            ..Default::default()
+        }
+    }
+
+    pub fn exponential(color: Vec3, density: f32) -> Self {
+        Self {
+            mode: FogMode::Exponential,
+            color,
+            density,
+            ..Default::default()
+        }
+    }
+
+    pub fn exponential_squared(color: Vec3, density: f32) -> Self {
+        Self {
+            mode: FogMode::ExponentialSquared,
+            color,
+            density,
+            ..Default::default()
+        }
+    }
+
+    /// Fraction of fog color at `depth`, matching the main pass shader.
+    pub fn factor(&self, depth: f32) -> f32 {
+        let factor = match self.mode {
+            FogMode::Linear => (depth - self.start) / (self.end - self.start).max(f32::EPSILON),
+            FogMode::Exponential => 1.0 - (-self.density * depth).exp(),
+            FogMode::ExponentialSquared => 1.0 - (-(self.density * depth).powi(2)).exp(),
+        };
+        factor.clamp(0.0, 1.0)
+    }
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Linear,
+            color: Vec3::new(0.6, 0.7, 0.8),
+            density: 0.02,
+            start: 50.0,
+            end: 200.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fog_factor() {
+        let linear = Fog::linear(Vec3::ONE, 10.0, 20.0);
+        assert_eq!(linear.factor(5.0), 0.0);
+        assert_eq!(linear.factor(15.0), 0.5);
+        assert_eq!(linear.factor(30.0), 1.0);
+
+        let exp = Fog::exponential(Vec3::ONE, 0.1);
+        let exp2 = Fog::exponential_squared(Vec3::ONE, 0.1);
+        assert_eq!(exp.factor(0.0), 0.0);
+        // Squared falloff stays clearer up close and thicker far away
+        assert!(exp2.factor(2.0) < exp.factor(2.0));
+        assert!(exp2.factor(30.0) > exp.factor(30.0));
+    }
+}
@@ -1,6 +1,13 @@
+pub mod clusters;
 pub mod components;
+pub mod probes;
+pub mod shadows;
 
-pub use components::{AmbientLight, DirectionalLight, PointLight};
+pub use components::{
+    AmbientLight, DEFAULT_POINT_SHADOW_RESOLUTION, DirectionalLight, Fog, FogMode, PointLight,
+};
+pub use probes::{LightProbe, LightProbeGrid, LightProbeGridUniform};
+pub use shadows::PointShadowMaps;
 
 use bytemuck::{Pod, Zeroable};
 
@@ -93,32 +100,57 @@ impl Default for AmbientLightUniform {
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct FogUniform {
+    pub color: [f32; 3],
+    pub density: f32,
+    pub start: f32,
+    pub end: f32,
+    /// 0 = off, 1 = linear, 2 = exponential, 3 = exponential squared.
+    pub mode: u32,
+    pub _padding: f32,
+}
+
+impl FogUniform {
+    pub fn from_fog(fog: &Fog) -> Self {
+        Self {
+            color: fog.color.to_array(),
+            density: fog.density,
+            start: fog.start,
+            end: fog.end,
+            mode: match fog.mode {
+                FogMode::Linear => 1,
+                FogMode::Exponential => 2,
+                FogMode::ExponentialSquared => 3,
+            },
+            _padding: 0.0,
+        }
+    }
+}
+
+impl Default for FogUniform {
+    fn default() -> Self {
+        Self {
+            color: [0.0; 3],
+            density: 0.0,
+            start: 0.0,
+            end: 0.0,
+            mode: 0,
+            _padding: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
 pub struct LightingUniform {
     pub directional: DirectionalLightUniform,
     pub ambient: AmbientLightUniform,
     pub point_light_count: u32,
     pub ao_mode: u32,
     pub ao_debug: u32,
-    pub _padding1: f32,
-    pub _padding2: [f32; 3],
-    pub _padding3: f32,
-    pub _padding4: [f32; 3],
-    pub _padding5: f32,
+    /// The first this many point lights have a shadow map in the same slot.
+    pub point_shadow_count: u32,
+    pub fog: FogUniform,
+    pub probes: LightProbeGridUniform,
 }
 
-impl Default for LightingUniform {
-    fn default() -> Self {
-        Self {
-            directional: DirectionalLightUniform::default(),
-            ambient: AmbientLightUniform::default(),
-            point_light_count: 0,
-            ao_mode: 0,
-            ao_debug: 0,
-            _padding1: 0.0,
-            _padding2: [0.0; 3],
-            _padding3: 0.0,
-            _padding4: [0.0; 3],
-            _padding5: 0.0,
-        }
-    }
-}
@@ -0,0 +1,271 @@
+//! Baked indirect light for dynamic objects.
+//!
+//! A [`LightProbeGrid`] stores, at regularly spaced points, the light arriving from each of
+//! the six axis directions. The main pass blends the eight probes around every fragment and
+//! uses the result in place of the [`AmbientLight`](super::AmbientLight), so objects moving
+//! through an interior pick up the bounce light of the room they're in. Fragments outside
+//! the grid keep the ambient light.
+//!
+//! Grids are baked offline by a tool that can trace the scene, then saved and loaded with
+//! the level:
+//!
+//! ```ignore
+//! let mut grid = LightProbeGrid::new(Vec3::new(-8.0, 0.5, -8.0), Vec3::splat(2.0), UVec3::new(9, 3, 9));
+//! grid.bake(64, |position, direction| trace_radiance(&scene, position, direction));
+//! grid.save("levels/cellar.probes")?;
+//!
+//! // In the game
+//! engine.world.insert_resource(LightProbeGrid::load("levels/cellar.probes")?);
+//! ```
+//!
+//! Baking with a closure that ignores the position, e.g. one sampling a sky gradient, gives
+//! outdoor scenes sky light that varies with the surface direction.
+
+use crate::core::math::*;
+use bevy_ecs::prelude::Resource;
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Directions of a probe's faces, in [`LightProbe::colors`] order.
+pub const PROBE_DIRECTIONS: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+];
+
+/// Indirect light at one point of a [`LightProbeGrid`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LightProbe {
+    /// Light arriving from +X, -X, +Y, -Y, +Z and -Z.
+    pub colors: [Vec3; 6],
+}
+
+impl LightProbe {
+    pub fn uniform(color: Vec3) -> Self {
+        Self { colors: [color; 6] }
+    }
+
+    /// Integrates `radiance(direction)` over `samples` directions spread evenly over the
+    /// sphere, each face weighting the directions by their cosine to it.
+    pub fn from_radiance(samples: u32, mut radiance: impl FnMut(Vec3) -> Vec3) -> Self {
+        let samples = samples.max(6);
+        let mut sums = [Vec3::ZERO; 6];
+        let mut weights = [0.0; 6];
+        for i in 0..samples {
+            let direction = fibonacci_direction(i, samples);
+            let light = radiance(direction);
+            for (face, face_direction) in PROBE_DIRECTIONS.iter().enumerate() {
+                let weight = direction.dot(*face_direction).max(0.0);
+                sums[face] += light * weight;
+                weights[face] += weight;
+            }
+        }
+
+        Self {
+            colors: std::array::from_fn(|face| {
+                if weights[face] > 0.0 {
+                    sums[face] / weights[face]
+                } else {
+                    Vec3::ZERO
+                }
+            }),
+        }
+    }
+
+    /// Light reaching a surface facing `normal`, matching the main pass shader.
+    pub fn irradiance(&self, normal: Vec3) -> Vec3 {
+        let squared = normal * normal;
+        let x = self.colors[if normal.x >= 0.0 { 0 } else { 1 }];
+        let y = self.colors[if normal.y >= 0.0 { 2 } else { 3 }];
+        let z = self.colors[if normal.z >= 0.0 { 4 } else { 5 }];
+        x * squared.x + y * squared.y + z * squared.z
+    }
+}
+
+/// `index` of `count` directions spread evenly over the unit sphere.
+fn fibonacci_direction(index: u32, count: u32) -> Vec3 {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let y = 1.0 - 2.0 * (index as f32 + 0.5) / count as f32;
+    let radius = (1.0 - y * y).max(0.0).sqrt();
+    let angle = golden_angle * index as f32;
+    Vec3::new(angle.cos() * radius, y, angle.sin() * radius)
+}
+
+/// Regular grid of [`LightProbe`]s lighting the meshes inside it. Each probe covers the cell
+/// of `spacing` around it, so the grid reaches half a spacing past its outer probes.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightProbeGrid {
+    /// Position of the first probe, at the grid's minimum corner.
+    pub origin: Vec3,
+    /// Distance between neighbouring probes along each axis.
+    pub spacing: Vec3,
+    /// Probes along x, y and z.
+    pub dimensions: UVec3,
+    /// Ordered by x first, then y, then z.
+    pub probes: Vec<LightProbe>,
+    /// Scales every probe, to brighten or darken a bake without redoing it.
+    pub intensity: f32,
+}
+
+impl LightProbeGrid {
+    /// A grid of unlit probes.
+    pub fn new(origin: Vec3, spacing: Vec3, dimensions: UVec3) -> Self {
+        Self {
+            origin,
+            spacing,
+            dimensions,
+            probes: vec![LightProbe::default(); dimensions.element_product() as usize],
+            intensity: 1.0,
+        }
+    }
+
+    /// Whether the probes fill the dimensions and the spacing is positive. The main pass
+    /// ignores invalid grids.
+    pub fn is_valid(&self) -> bool {
+        self.dimensions.min_element() > 0
+            && self.spacing.min_element() > 0.0
+            && self.probes.len() == self.dimensions.element_product() as usize
+    }
+
+    pub fn index(&self, cell: UVec3) -> usize {
+        (cell.x + cell.y * self.dimensions.x + cell.z * self.dimensions.x * self.dimensions.y)
+            as usize
+    }
+
+    pub fn probe_position(&self, cell: UVec3) -> Vec3 {
+        self.origin + self.spacing * cell.as_vec3()
+    }
+
+    /// Fills every probe from `radiance(position, direction)`, the light arriving at
+    /// `position` from `direction`, sampled in `samples` directions per probe.
+    pub fn bake(&mut self, samples: u32, mut radiance: impl FnMut(Vec3, Vec3) -> Vec3) {
+        for z in 0..self.dimensions.z {
+            for y in 0..self.dimensions.y {
+                for x in 0..self.dimensions.x {
+                    let cell = UVec3::new(x, y, z);
+                    let position = self.probe_position(cell);
+                    let index = self.index(cell);
+                    self.probes[index] = LightProbe::from_radiance(samples, |direction| {
+                        radiance(position, direction)
+                    });
+                }
+            }
+        }
+    }
+
+    /// Light reaching a surface at `position` facing `normal`, blended from the surrounding
+    /// probes like the main pass does. `None` outside the grid or for invalid grids.
+    pub fn sample(&self, position: Vec3, normal: Vec3) -> Option<Vec3> {
+        if !self.is_valid() {
+            return None;
+        }
+        let extent = self.dimensions.as_vec3();
+        let local = (position - self.origin) / self.spacing;
+        if local.cmplt(Vec3::splat(-0.5)).any() || local.cmpgt(extent - 0.5).any() {
+            return None;
+        }
+
+        let last = self.dimensions - UVec3::ONE;
+        let clamped = local.clamp(Vec3::ZERO, last.as_vec3());
+        let base = clamped.floor().as_uvec3().min(last);
+        let next = (base + UVec3::ONE).min(last);
+        let t = clamped - base.as_vec3();
+
+        let mut irradiance = Vec3::ZERO;
+        for corner in 0..8u32 {
+            let pick = BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0);
+            let cell = UVec3::select(pick, next, base);
+            let weights = Vec3::select(pick, t, Vec3::ONE - t);
+            irradiance +=
+                self.probes[self.index(cell)].irradiance(normal) * weights.element_product();
+        }
+        Some(irradiance * self.intensity)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path.as_ref(), contents)?;
+        Ok(())
+    }
+
+    pub fn to_uniform(&self) -> LightProbeGridUniform {
+        LightProbeGridUniform {
+            origin: self.origin.to_array(),
+            enabled: self.is_valid() as u32,
+            spacing: self.spacing.to_array(),
+            intensity: self.intensity,
+            dimensions: self.dimensions.to_array(),
+            _padding: 0,
+        }
+    }
+
+    pub fn gpu_probes(&self) -> Vec<LightProbeUniform> {
+        self.probes
+            .iter()
+            .map(|probe| LightProbeUniform {
+                colors: probe.colors.map(|color| color.extend(0.0).to_array()),
+            })
+            .collect()
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct LightProbeGridUniform {
+    pub origin: [f32; 3],
+    /// 0 without a valid grid, which keeps the ambient light everywhere.
+    pub enabled: u32,
+    pub spacing: [f32; 3],
+    pub intensity: f32,
+    pub dimensions: [u32; 3],
+    pub _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct LightProbeUniform {
+    pub colors: [[f32; 4]; 6],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_blends_neighbouring_probes() {
+        let mut grid = LightProbeGrid::new(Vec3::ZERO, Vec3::splat(2.0), UVec3::new(2, 1, 1));
+        grid.probes[1] = LightProbe::uniform(Vec3::ONE);
+
+        assert_eq!(grid.sample(Vec3::ZERO, Vec3::Y), Some(Vec3::ZERO));
+        assert_eq!(
+            grid.sample(Vec3::new(1.0, 0.0, 0.0), Vec3::Y),
+            Some(Vec3::splat(0.5))
+        );
+        // Half a spacing past the outer probes is still inside
+        assert_eq!(
+            grid.sample(Vec3::new(2.9, 0.0, 0.0), Vec3::Y),
+            Some(Vec3::ONE)
+        );
+        assert_eq!(grid.sample(Vec3::new(3.1, 0.0, 0.0), Vec3::Y), None);
+
+        // Light from above only reaches surfaces facing up
+        let sky = LightProbe::from_radiance(256, |direction| {
+            if direction.y > 0.0 {
+                Vec3::ONE
+            } else {
+                Vec3::ZERO
+            }
+        });
+        assert!(sky.irradiance(Vec3::Y).x > 0.9);
+        assert_eq!(sky.irradiance(Vec3::NEG_Y), Vec3::ZERO);
+    }
+}
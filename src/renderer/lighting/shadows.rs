@@ -0,0 +1,284 @@
+//! Cube map shadows for point lights.
+//!
+//! Every shadowed point light owns a slot of six layers in one depth array texture, one
+//! layer per cube face in +X, -X, +Y, -Y, +Z, -Z order. A light renders into the top-left
+//! `shadow_resolution` texels of its layers, so lights of different resolutions share the
+//! texture, which is as large as the largest of them. The shadow pass stores the distance to
+//! the light over its radius instead of projected depth, so the main pass compares distances.
+//!
+//! [`update_lighting`](crate::renderer::systems::update_lighting) moves the shadowed lights
+//! to the front of the point light buffer, so a light's index is also its slot, and
+//! [`PointShadowNode`](crate::renderer::PointShadowNode) draws every
+//! [`ShadowCaster`](crate::renderer::ShadowCaster) in reach of one into all of them.
+
+use crate::core::math::*;
+use crate::renderer::PointShadowPipeline;
+use crate::renderer::components::MeshDrawBatch;
+use bytemuck::{Pod, Zeroable};
+
+pub const POINT_SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Distance from the light where shadow faces start clipping.
+const POINT_SHADOW_NEAR: f32 = 0.05;
+
+/// Depth offset in world units, against surfaces shadowing themselves.
+const POINT_SHADOW_BIAS: f32 = 0.05;
+
+/// Offset between [`PointShadowFaceUniform`]s in the face buffer, the largest
+/// `min_uniform_buffer_offset_alignment` a device may require.
+pub const FACE_UNIFORM_STRIDE: u64 = 256;
+
+/// Per-slot data the main pass samples shadows with.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct PointShadowUniform {
+    /// View-projection of each cube face.
+    pub faces: [[[f32; 4]; 4]; 6],
+    /// x: fraction of the texture the light renders into, y: depth bias over radius.
+    pub params: [f32; 4],
+}
+
+impl PointShadowUniform {
+    pub fn new(position: Vec3, radius: f32, resolution: u32, size: u32) -> Self {
+        Self {
+            faces: face_view_projections(position, radius).map(|face| face.to_cols_array_2d()),
+            params: [
+                resolution as f32 / size as f32,
+                POINT_SHADOW_BIAS / radius.max(f32::EPSILON),
+                0.0,
+                0.0,
+            ],
+        }
+    }
+}
+
+/// Per-face data of the shadow pass, padded to [`FACE_UNIFORM_STRIDE`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct PointShadowFaceUniform {
+    pub view_proj: [[f32; 4]; 4],
+    /// xyz: light position, w: radius.
+    pub light: [f32; 4],
+    pub _padding: [[f32; 4]; 11],
+}
+
+impl PointShadowFaceUniform {
+    /// The six faces of a light, in layer order.
+    pub fn faces(position: Vec3, radius: f32) -> [Self; 6] {
+        face_view_projections(position, radius).map(|view_proj| Self {
+            view_proj: view_proj.to_cols_array_2d(),
+            light: position.extend(radius).to_array(),
+            _padding: [[0.0; 4]; 11],
+        })
+    }
+}
+
+/// View-projections looking down +X, -X, +Y, -Y, +Z and -Z from the light.
+pub fn face_view_projections(position: Vec3, radius: f32) -> [Mat4; 6] {
+    const FACES: [(Vec3, Vec3); 6] = [
+        (Vec3::X, Vec3::NEG_Y),
+        (Vec3::NEG_X, Vec3::NEG_Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::NEG_Y),
+        (Vec3::NEG_Z, Vec3::NEG_Y),
+    ];
+    let far = radius.max(POINT_SHADOW_NEAR * 2.0);
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, POINT_SHADOW_NEAR, far);
+    FACES.map(|(forward, up)| projection * Mat4::look_to_rh(position, forward, up))
+}
+
+/// Order to upload `lights` in, given as `(position, cast_shadows)`, and how many of the
+/// first entries get a shadow slot: the up to `max_shadows` casting lights closest to
+/// `viewer`, nearest first, followed by everything else in its original order.
+pub fn shadow_light_order(
+    lights: &[(Vec3, bool)],
+    viewer: Vec3,
+    max_shadows: usize,
+) -> (Vec<usize>, usize) {
+    let mut shadowed: Vec<usize> = (0..lights.len()).filter(|&i| lights[i].1).collect();
+    shadowed.sort_by(|&a, &b| {
+        let a = lights[a].0.distance_squared(viewer);
+        let b = lights[b].0.distance_squared(viewer);
+        a.total_cmp(&b)
+    });
+    shadowed.truncate(max_shadows);
+
+    let shadow_count = shadowed.len();
+    let rest: Vec<usize> = (0..lights.len())
+        .filter(|i| !shadowed.contains(i))
+        .collect();
+    let mut order = shadowed;
+    order.extend(rest);
+    (order, shadow_count)
+}
+
+/// GPU resources of point light shadows and the lights that use them this frame.
+pub struct PointShadowMaps {
+    pub texture: wgpu::Texture,
+    /// All layers, sampled by the main pass.
+    pub view: wgpu::TextureView,
+    /// One view per layer, rendered by the shadow pass.
+    pub face_views: Vec<wgpu::TextureView>,
+    pub sampler: wgpu::Sampler,
+    /// [`PointShadowUniform`] per slot.
+    pub buffer: wgpu::Buffer,
+    /// [`PointShadowFaceUniform`] per layer.
+    pub face_buffer: wgpu::Buffer,
+    pub face_bind_group: wgpu::BindGroup,
+    /// Slots the texture has layers for.
+    pub capacity: u32,
+    /// Edge length of the texture.
+    pub size: u32,
+    /// Cube face resolution of each shadowed light this frame.
+    pub resolutions: Vec<u32>,
+    /// Position and radius of each shadowed light this frame.
+    pub lights: Vec<(Vec3, f32)>,
+    /// Shadow casters in reach of a shadowed light, drawn into every face.
+    pub batches: Vec<MeshDrawBatch>,
+}
+
+impl PointShadowMaps {
+    pub fn new(
+        device: &wgpu::Device,
+        pipeline: &PointShadowPipeline,
+        capacity: u32,
+        size: u32,
+    ) -> Self {
+        let capacity = capacity.max(1);
+        let size = size.max(1);
+        let layers = capacity * 6;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Point Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: POINT_SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Point Shadow Maps View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let face_views = (0..layers)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Point Shadow Face View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        // Linear filtering compares four texels, for slightly softer edges
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Point Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Point Shadow Buffer"),
+            size: (capacity as usize * std::mem::size_of::<PointShadowUniform>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let face_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Point Shadow Face Buffer"),
+            size: layers as u64 * FACE_UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let face_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Point Shadow Face Bind Group"),
+            layout: &pipeline.face_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &face_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(
+                        std::mem::size_of::<PointShadowFaceUniform>() as u64
+                    ),
+                }),
+            }],
+        });
+
+        Self {
+            texture,
+            view,
+            face_views,
+            sampler,
+            buffer,
+            face_buffer,
+            face_bind_group,
+            capacity,
+            size,
+            resolutions: Vec::new(),
+            lights: Vec::new(),
+            batches: Vec::new(),
+        }
+    }
+
+    /// Number of lights rendering shadows this frame.
+    pub fn active_count(&self) -> usize {
+        self.lights.len()
+    }
+}
+
+const _: () = assert!(std::mem::size_of::<PointShadowFaceUniform>() as u64 == FACE_UNIFORM_STRIDE);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shadowed_lights_come_first_nearest_first() {
+        let lights = [
+            (Vec3::new(10.0, 0.0, 0.0), true),
+            (Vec3::new(1.0, 0.0, 0.0), false),
+            (Vec3::new(2.0, 0.0, 0.0), true),
+            (Vec3::new(5.0, 0.0, 0.0), true),
+        ];
+        let (order, shadow_count) = shadow_light_order(&lights, Vec3::ZERO, 2);
+        assert_eq!(shadow_count, 2);
+        assert_eq!(order, vec![2, 3, 0, 1]);
+
+        let (order, shadow_count) = shadow_light_order(&lights, Vec3::ZERO, 0);
+        assert_eq!(shadow_count, 0);
+        assert_eq!(order, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_faces_look_along_axes() {
+        let faces = face_view_projections(Vec3::ZERO, 10.0);
+        let centers = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ];
+        for (face, center) in faces.iter().zip(centers) {
+            let clip = *face * (center * 5.0).extend(1.0);
+            let ndc = clip.truncate() / clip.w;
+            assert!(ndc.x.abs() < 1e-4 && ndc.y.abs() < 1e-4);
+            assert!(ndc.z > 0.0 && ndc.z < 1.0);
+        }
+    }
+}
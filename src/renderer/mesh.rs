@@ -1,6 +1,7 @@
 use crate::assets::handle::AssetId;
 use crate::assets::loader::mesh::MeshData;
 use crate::core::math::*;
+use crate::renderer::morph::GpuMorphTargets;
 use bevy_ecs::prelude::Resource;
 use bytemuck::{Pod, Zeroable};
 use std::collections::HashMap;
@@ -68,6 +69,8 @@ pub struct GpuMesh {
     pub vertex_buffer: Buffer,
     pub index_buffer: Buffer,
     pub index_count: u32,
+    /// Set for meshes with morph targets.
+    pub morph: Option<GpuMorphTargets>,
 }
 
 impl GpuMesh {
@@ -107,6 +110,7 @@ impl GpuMesh {
             vertex_buffer,
             index_buffer,
             index_count,
+            morph: GpuMorphTargets::from_mesh_data(device, mesh_data),
         }
     }
 }
@@ -150,4 +154,8 @@ impl GpuMeshCache {
     pub fn len(&self) -> usize {
         self.meshes.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
 }
@@ -1,33 +1,88 @@
 pub mod camera;
+pub mod capture;
 pub mod components;
+pub mod compute;
+pub mod extension;
+pub mod extract;
+pub mod frame;
+pub mod gizmos;
 pub mod graph;
 pub mod graphics_settings;
 pub mod lighting;
 pub mod mesh;
+pub mod morph;
 pub mod pipeline;
+pub mod pipeline_cache;
 pub mod plugin;
+pub mod primitives;
+pub mod render_target;
+pub mod screenshot;
+pub mod shader_registry;
+pub mod stats;
 pub mod systems;
+pub mod taa;
+pub mod texture;
+pub mod texture_streaming;
+pub mod upload;
+pub mod water;
+pub mod world_anchor;
 
 use anyhow::Result;
 use bevy_ecs::prelude::Resource;
 use std::sync::Arc;
-use wgpu::{BindGroup, Buffer, Device, Queue, Surface, SurfaceConfiguration, Texture, TextureView};
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, Device, Queue, Surface, SurfaceConfiguration, Texture,
+    TextureView,
+};
 use winit::window::Window;
 
-pub use camera::{Camera, CameraUniform};
-pub use components::{Aabb, GpuModelData, LightingData, Mesh, MeshUploaded};
+pub use camera::{
+    Camera, CameraCut, CameraUniform, CameraView, Projection, RenderTarget, Viewport,
+};
+pub use components::{
+    Aabb, GpuModelData, InstanceColor, LayerDrawData, LightingData, MaterialId, Mesh, MeshUploaded,
+    RenderLayers, ShadowCaster, ShadowReceiver,
+};
+pub use compute::{ComputeContext, ComputePlugin};
+pub use extension::{DEPTH_FORMAT, RenderExtension, RenderExtensions, RenderSetup};
+pub use extract::{ExtractedCamera, ExtractedMesh, ExtractedScene, RenderFlags};
+pub use frame::{DEFAULT_FRAMES_IN_FLIGHT, FrameSync, MAX_FRAMES_IN_FLIGHT};
+pub use gizmos::{GizmoVertex, Gizmos};
 pub use graph::RenderGraph;
 pub use graph::node::{RenderContext, RenderNode};
 pub use graph::nodes::{
-    MainPassNode, WireframePassNode,
+    FxaaNode, GizmoPassNode, GpuCullNode, LightClusterNode, MainPassNode, MotionBlurNode,
+    OcclusionCullNode, PointShadowNode, ScreenshotNode, TaaNode, TonemapNode, WaterPassNode,
+    WireframePassNode,
+};
+pub use graph::resources::{BufferDesc, GraphResources, GraphTexture, TextureDesc, TextureSize};
+pub use graphics_settings::{
+    AntiAliasing, DEFAULT_MAX_POINT_LIGHTS, DEFAULT_MAX_SHADOW_POINT_LIGHTS,
+    DEFAULT_TEXTURE_BUDGET, GraphicsSettings, MsaaSampleCount, Tonemapping,
+};
+pub use lighting::{
+    AmbientLight, DEFAULT_POINT_SHADOW_RESOLUTION, DirectionalLight, Fog, FogMode, FogUniform,
+    LightProbe, LightProbeGrid, LightingUniform, PointLight, PointLightUniform, PointShadowMaps,
 };
-pub use graphics_settings::{GraphicsSettings, MsaaSampleCount};
-pub use lighting::{AmbientLight, DirectionalLight, LightingUniform, PointLight};
 pub use mesh::{GpuMesh, GpuMeshCache, Vertex};
+pub use morph::{MorphDrawData, MorphWeights};
 pub use pipeline::{
-    DepthPrepassPipeline, MeshPipeline, WireframePipeline,
+    DepthPrepassPipeline, FxaaPipeline, GizmoPipeline, GpuCullPipeline, HiZPipeline,
+    LightClusterPipeline, MeshPipeline, MotionBlurPipeline, PointShadowPipeline, TaaPipeline,
+    TonemapPipeline, WaterPipeline, WireframePipeline,
 };
-pub use plugin::RenderPlugin;
+pub use pipeline_cache::{PipelineCache, PipelineKey};
+pub use plugin::{HeadlessRendering, RenderPlugin};
+pub use render_target::{RenderTargetId, RenderTargets, RenderTexture};
+pub use screenshot::{ScreenshotCaptured, ScreenshotRequest};
+pub use shader_registry::{EngineShader, ShaderRegistry};
+pub use stats::RenderStats;
+pub use taa::TaaHistory;
+pub use texture::{create_texture, gpu_texture_format, supports_texture_format};
+pub use texture_streaming::{ResidentTexture, StreamedTexture, TextureStreamer};
+pub use upload::GpuUploader;
+pub use water::{ReflectionCamera, WaterReflection, WaterSurface};
+pub use world_anchor::WorldAnchor;
 
 use bytemuck::{Pod, Zeroable};
 
@@ -35,30 +90,66 @@ use bytemuck::{Pod, Zeroable};
 // SSAO (Screen Space Ambient Occlusion) removed for simplicity.
 // If needed in the future, implement as a separate render graph node.
 
+/// Format of the offscreen target the scene is rendered into before tonemapping.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Format of the output texture of a [headless](Renderer::new_headless) renderer.
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Format of the motion vector target the main pass writes for window cameras.
+pub const VELOCITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct ModelUniform {
     pub model: [[f32; 4]; 4],
     pub normal_matrix: [[f32; 4]; 3],
+    /// Per-instance tint multiplied into the vertex color; alpha is 1 when the instance is a
+    /// [`ShadowReceiver`].
+    pub color: [f32; 4],
+    /// Model matrix of the previous frame, for motion vectors.
+    pub previous_model: [[f32; 4]; 4],
 }
 
 #[derive(Resource)]
 pub struct Renderer {
-    surface: Surface<'static>,
+    /// `None` for headless renderers, which present into `output_texture` instead.
+    surface: Option<Surface<'static>>,
+    output_texture: Option<Texture>,
+    output_view: Option<TextureView>,
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
     size: (u32, u32),
-    camera_buffer: Buffer,
-    camera_bind_group: Option<BindGroup>,
+    frame_sync: FrameSync,
+    /// One camera uniform per frame in flight, indexed by [`FrameSync::frame_index`].
+    camera_buffers: Vec<Buffer>,
+    /// Empty until created for the current pipeline layout.
+    camera_bind_groups: Vec<BindGroup>,
     depth_texture: Texture,
     depth_view: TextureView,
+    hdr_texture: Texture,
+    hdr_view: TextureView,
     msaa_sample_count: u32,
     msaa_color_texture: Option<Texture>,
     msaa_color_view: Option<TextureView>,
     msaa_depth_texture: Option<Texture>,
     msaa_depth_view: Option<TextureView>,
+    anti_aliasing: AntiAliasing,
+    motion_blur: bool,
+    /// Scratch HDR target FXAA and motion blur render into, only allocated while one of
+    /// them is enabled.
+    post_texture: Option<Texture>,
+    post_view: Option<TextureView>,
+    /// Motion vectors of the window cameras, only allocated while TAA or motion blur is enabled.
+    velocity_texture: Option<Texture>,
+    velocity_view: Option<TextureView>,
+    msaa_velocity_texture: Option<Texture>,
+    msaa_velocity_view: Option<TextureView>,
+    /// Only allocated while TAA is selected.
+    taa_history: Option<TaaHistory>,
     available_present_modes: Vec<wgpu::PresentMode>,
+    adapter_info: wgpu::AdapterInfo,
 }
 
 impl Renderer {
@@ -67,12 +158,7 @@ impl Renderer {
         let width = size.width.max(1);
         let height = size.height.max(1);
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            flags: wgpu::InstanceFlags::empty(),
-            ..Default::default()
-        });
-
+        let instance = Self::create_instance();
         let surface = instance.create_surface(window)?;
 
         let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
@@ -80,16 +166,7 @@ impl Renderer {
             compatible_surface: Some(&surface),
             force_fallback_adapter: false,
         }))?;
-
-        let (device, queue) =
-            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
-                label: Some("Resonance Device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: Default::default(),
-                experimental_features: Default::default(),
-                trace: wgpu::Trace::Off,
-            }))?;
+        let (device, queue) = Self::request_device(&adapter)?;
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -127,46 +204,184 @@ impl Renderer {
             present_mode,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
-            desired_maximum_frame_latency: 3,
+            desired_maximum_frame_latency: DEFAULT_FRAMES_IN_FLIGHT,
         };
         surface.configure(&device, &config);
 
-        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Camera Buffer"),
-            size: std::mem::size_of::<CameraUniform>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        Ok(Self::with_device(
+            Some(surface),
+            device,
+            queue,
+            config,
+            surface_caps.present_modes,
+            adapter.get_info(),
+        ))
+    }
+
+    /// Renders into an offscreen [`HEADLESS_FORMAT`] texture instead of a window, for tests
+    /// and servers without a display. Read frames back with [`read_output`](Self::read_output)
+    /// or a [`ScreenshotRequest`].
+    pub fn new_headless(width: u32, height: u32) -> Result<Self> {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        let instance = Self::create_instance();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))?;
+        let (device, queue) = Self::request_device(&adapter)?;
+
+        let config = SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: HEADLESS_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: DEFAULT_FRAMES_IN_FLIGHT,
+        };
+
+        Ok(Self::with_device(
+            None,
+            device,
+            queue,
+            config,
+            vec![wgpu::PresentMode::Fifo],
+            adapter.get_info(),
+        ))
+    }
+
+    fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            flags: wgpu::InstanceFlags::empty(),
+            ..Default::default()
+        })
+    }
+
+    fn request_device(adapter: &wgpu::Adapter) -> Result<(Device, Queue)> {
+        Ok(pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Resonance Device"),
+                // Lets the PipelineCache persist compiled pipelines where the backend supports
+                // it, and compressed textures upload without decoding
+                required_features: adapter.features()
+                    & (wgpu::Features::PIPELINE_CACHE | texture::COMPRESSION_FEATURES),
+                required_limits: wgpu::Limits::default(),
+                memory_hints: Default::default(),
+                experimental_features: Default::default(),
+                trace: wgpu::Trace::Off,
+            },
+        ))?)
+    }
+
+    fn with_device(
+        surface: Option<Surface<'static>>,
+        device: Device,
+        queue: Queue,
+        config: SurfaceConfiguration,
+        available_present_modes: Vec<wgpu::PresentMode>,
+        adapter_info: wgpu::AdapterInfo,
+    ) -> Self {
+        let (width, height) = (config.width, config.height);
+        let frame_sync = FrameSync::new(DEFAULT_FRAMES_IN_FLIGHT);
+        let camera_buffers = Self::create_camera_buffers(&device, frame_sync.frames_in_flight());
 
         let depth_texture = Self::create_depth_texture(&device, width, height);
         let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
+        let hdr_texture = Self::create_hdr_texture(&device, width, height);
+        let hdr_view = hdr_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let output_texture = surface
+            .is_none()
+            .then(|| Self::create_output_texture(&device, &config));
+        let output_view = output_texture
+            .as_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
         log::info!(
-            "Renderer initialized: {}x{}, format: {:?}",
+            "Renderer initialized: {}x{}, format: {:?}{}",
             width,
             height,
-            surface_format
+            config.format,
+            if surface.is_none() { ", headless" } else { "" }
         );
 
-        Ok(Self {
+        Self {
             surface,
+            output_texture,
+            output_view,
             device,
             queue,
             config,
             size: (width, height),
-            camera_buffer,
-            camera_bind_group: None,
+            frame_sync,
+            camera_buffers,
+            camera_bind_groups: Vec::new(),
             depth_texture,
             depth_view,
+            hdr_texture,
+            hdr_view,
             msaa_sample_count: 1,
             msaa_color_texture: None,
             msaa_color_view: None,
             msaa_depth_texture: None,
             msaa_depth_view: None,
-            available_present_modes: surface_caps.present_modes,
+            anti_aliasing: AntiAliasing::None,
+            motion_blur: false,
+            post_texture: None,
+            post_view: None,
+            velocity_texture: None,
+            velocity_view: None,
+            msaa_velocity_texture: None,
+            msaa_velocity_view: None,
+            taa_history: None,
+            available_present_modes,
+            adapter_info,
+        }
+    }
+
+    /// Stands in for the surface texture of headless renderers.
+    fn create_output_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Output Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
         })
     }
 
+    fn configure_surface(&self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    fn create_camera_buffers(device: &Device, count: usize) -> Vec<Buffer> {
+        (0..count)
+            .map(|i| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("Camera Buffer {}", i)),
+                    size: std::mem::size_of::<CameraUniform>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect()
+    }
+
     fn create_depth_texture(device: &Device, width: u32, height: u32) -> Texture {
         let size = wgpu::Extent3d {
             width,
@@ -186,29 +401,100 @@ impl Renderer {
         })
     }
 
+    fn create_post_texture(device: &Device, width: u32, height: u32) -> Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Post-Process Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    fn create_velocity_texture(device: &Device, width: u32, height: u32) -> Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Velocity Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: VELOCITY_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn create_hdr_texture(device: &Device, width: u32, height: u32) -> Texture {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Color Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            // COPY_SRC for screenshots, COPY_DST for anti-aliasing results copied back
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
 
     pub fn resize(&mut self, width: u32, height: u32) {
         let width = width.max(1);
         let height = height.max(1);
 
         if self.size != (width, height) {
+            // The old attachments may still be in use by frames in flight
+            self.frame_sync.wait_idle(&self.device);
+
             self.size = (width, height);
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            self.configure_surface();
+            if self.output_texture.is_some() {
+                let output_texture = Self::create_output_texture(&self.device, &self.config);
+                self.output_view =
+                    Some(output_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                self.output_texture = Some(output_texture);
+            }
 
             self.depth_texture = Self::create_depth_texture(&self.device, width, height);
             self.depth_view = self
                 .depth_texture
                 .create_view(&wgpu::TextureViewDescriptor::default());
-            self.camera_bind_group = None;
+            self.hdr_texture = Self::create_hdr_texture(&self.device, width, height);
+            self.hdr_view = self
+                .hdr_texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            self.camera_bind_groups.clear();
+            self.create_post_process_targets();
 
             if self.msaa_sample_count > 1 {
                 let msaa_color_texture = Self::create_msaa_color_texture(
                     &self.device,
                     width,
                     height,
-                    self.config.format,
+                    HDR_FORMAT,
                     self.msaa_sample_count,
                 );
                 let msaa_color_view =
@@ -253,9 +539,38 @@ impl Renderer {
         &self.queue
     }
 
+    /// `None` for headless renderers.
     #[doc(hidden)]
-    pub fn surface(&self) -> &Surface<'_> {
-        &self.surface
+    pub fn surface(&self) -> Option<&Surface<'static>> {
+        self.surface.as_ref()
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Texture a headless renderer presents into, in place of a surface texture.
+    pub fn output_texture(&self) -> Option<&Texture> {
+        self.output_texture.as_ref()
+    }
+
+    pub fn output_view(&self) -> Option<&TextureView> {
+        self.output_view.as_ref()
+    }
+
+    /// Copies the last presented frame of a headless renderer to the CPU as
+    /// [`HEADLESS_FORMAT`] pixels, row by row from the top-left. Blocks until the GPU has
+    /// finished the frame. `None` for renderers with a window.
+    pub fn read_output(&self) -> Option<Vec<u8>> {
+        let texture = self.output_texture.as_ref()?;
+        Some(capture::read_texture(
+            &self.device,
+            &self.queue,
+            texture,
+            self.config.width,
+            self.config.height,
+            4,
+        ))
     }
 
     #[doc(hidden)]
@@ -263,29 +578,110 @@ impl Renderer {
         &self.config
     }
 
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frame_sync.frames_in_flight()
+    }
+
+    /// Slot of the frame currently being prepared, in `0..frames_in_flight()`.
+    pub fn frame_index(&self) -> usize {
+        self.frame_sync.frame_index()
+    }
+
+    /// Changes how many frames the CPU may run ahead of the GPU.
+    ///
+    /// Waits for all frames in flight and recreates the per-frame resources, so
+    /// camera bind groups have to be created again afterwards.
+    pub fn set_frames_in_flight(&mut self, frames_in_flight: u32) {
+        let frames_in_flight = frames_in_flight.clamp(1, MAX_FRAMES_IN_FLIGHT);
+        if self.frame_sync.frames_in_flight() == frames_in_flight as usize {
+            return;
+        }
+
+        self.frame_sync.wait_idle(&self.device);
+        self.frame_sync = FrameSync::new(frames_in_flight);
+        self.camera_buffers = Self::create_camera_buffers(&self.device, frames_in_flight as usize);
+        self.camera_bind_groups.clear();
+
+        self.config.desired_maximum_frame_latency = frames_in_flight;
+        self.configure_surface();
+
+        log::info!("Frames in flight: {}", frames_in_flight);
+    }
+
+    /// Waits until the current frame slot is free. Returns how long the CPU was blocked.
+    #[doc(hidden)]
+    pub fn begin_frame(&mut self) -> std::time::Duration {
+        self.frame_sync.wait_for_slot(&self.device)
+    }
+
+    /// Fences the submitted frame and rotates to the next slot.
+    #[doc(hidden)]
+    pub fn end_frame(&mut self) {
+        self.frame_sync.end_frame(&self.queue);
+        if let Some(history) = &mut self.taa_history {
+            history.advance();
+        }
+    }
+
+    /// Discards the accumulated TAA history, so the next frame doesn't blend in pixels from
+    /// before a camera cut.
+    pub fn reset_taa_history(&mut self) {
+        if let Some(history) = &mut self.taa_history {
+            history.reset();
+        }
+    }
+
+    /// Blocks until the GPU has finished every submitted frame, and the uploads and
+    /// readbacks submitted outside of frames.
+    pub fn wait_idle(&mut self) {
+        self.frame_sync.wait_idle(&self.device);
+        capture::wait_for_gpu(&self.device);
+    }
+
+    /// Camera uniform buffer of the current frame.
     #[doc(hidden)]
     pub fn camera_buffer(&self) -> &Buffer {
-        &self.camera_buffer
+        &self.camera_buffers[self.frame_sync.frame_index()]
     }
 
+    /// Creates one camera bind group per frame in flight.
     #[doc(hidden)]
-    pub fn set_camera_bind_group(&mut self, bind_group: BindGroup) {
-        self.camera_bind_group = Some(bind_group);
+    pub fn create_camera_bind_groups(&mut self, layout: &BindGroupLayout) {
+        self.camera_bind_groups = self
+            .camera_buffers
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(&format!("Camera Bind Group {}", i)),
+                    layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
     }
 
     #[doc(hidden)]
     pub fn set_camera_bind_group_invalid(&mut self) {
-        self.camera_bind_group = None;
+        self.camera_bind_groups.clear();
     }
 
     #[doc(hidden)]
     pub fn has_camera_bind_group(&self) -> bool {
-        self.camera_bind_group.is_some()
+        !self.camera_bind_groups.is_empty()
     }
 
+    /// Camera bind group of the current frame.
     #[doc(hidden)]
     pub fn camera_bind_group(&self) -> Option<&BindGroup> {
-        self.camera_bind_group.as_ref()
+        self.camera_bind_groups.get(self.frame_sync.frame_index())
     }
 
     #[doc(hidden)]
@@ -293,6 +689,16 @@ impl Renderer {
         &self.depth_view
     }
 
+    #[doc(hidden)]
+    pub fn hdr_view(&self) -> &TextureView {
+        &self.hdr_view
+    }
+
+    #[doc(hidden)]
+    pub fn hdr_texture(&self) -> &Texture {
+        &self.hdr_texture
+    }
+
     #[doc(hidden)]
     pub fn msaa_color_view(&self) -> Option<&TextureView> {
         self.msaa_color_view.as_ref()
@@ -366,7 +772,7 @@ impl Renderer {
                 &self.device,
                 width,
                 height,
-                self.config.format,
+                HDR_FORMAT,
                 sample_count,
             );
             let msaa_color_view =
@@ -387,6 +793,93 @@ impl Renderer {
             self.msaa_depth_texture = None;
             self.msaa_depth_view = None;
         }
+        self.create_velocity_targets();
+    }
+
+    /// Allocates the targets the selected anti-aliasing mode and motion blur need and frees
+    /// the rest.
+    pub fn update_post_processing(&mut self, anti_aliasing: AntiAliasing, motion_blur: bool) {
+        if self.anti_aliasing == anti_aliasing && self.motion_blur == motion_blur {
+            return;
+        }
+
+        self.anti_aliasing = anti_aliasing;
+        self.motion_blur = motion_blur;
+        self.create_post_process_targets();
+    }
+
+    fn create_post_process_targets(&mut self) {
+        let (width, height) = self.size;
+
+        if self.anti_aliasing == AntiAliasing::Fxaa || self.motion_blur {
+            let post_texture = Self::create_post_texture(&self.device, width, height);
+            self.post_view =
+                Some(post_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+            self.post_texture = Some(post_texture);
+        } else {
+            self.post_texture = None;
+            self.post_view = None;
+        }
+
+        // Starting over drops the history, so the first frame after a resize is not blended
+        self.taa_history = (self.anti_aliasing == AntiAliasing::Taa)
+            .then(|| TaaHistory::new(&self.device, width, height));
+        self.create_velocity_targets();
+    }
+
+    fn create_velocity_targets(&mut self) {
+        let (width, height) = self.size;
+
+        if self.anti_aliasing == AntiAliasing::Taa || self.motion_blur {
+            let velocity_texture = Self::create_velocity_texture(&self.device, width, height);
+            self.velocity_view =
+                Some(velocity_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+            self.velocity_texture = Some(velocity_texture);
+        } else {
+            self.velocity_texture = None;
+            self.velocity_view = None;
+        }
+
+        if self.velocity_texture.is_some() && self.msaa_sample_count > 1 {
+            let msaa_velocity_texture = Self::create_msaa_color_texture(
+                &self.device,
+                width,
+                height,
+                VELOCITY_FORMAT,
+                self.msaa_sample_count,
+            );
+            self.msaa_velocity_view =
+                Some(msaa_velocity_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+            self.msaa_velocity_texture = Some(msaa_velocity_texture);
+        } else {
+            self.msaa_velocity_texture = None;
+            self.msaa_velocity_view = None;
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn post_texture(&self) -> Option<&Texture> {
+        self.post_texture.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub fn post_view(&self) -> Option<&TextureView> {
+        self.post_view.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub fn velocity_view(&self) -> Option<&TextureView> {
+        self.velocity_view.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub fn msaa_velocity_view(&self) -> Option<&TextureView> {
+        self.msaa_velocity_view.as_ref()
+    }
+
+    #[doc(hidden)]
+    pub fn taa_history(&self) -> Option<&TaaHistory> {
+        self.taa_history.as_ref()
     }
 
     pub fn update_vsync(&mut self, enabled: bool) {
@@ -420,7 +913,7 @@ impl Renderer {
         );
 
         self.config.present_mode = desired_present_mode;
-        self.surface.configure(&self.device, &self.config);
+        self.configure_surface();
     }
 
     pub fn calculate_texture_memory(&self) -> (u64, u64) {
@@ -429,10 +922,8 @@ impl Renderer {
         let depth_size = (width * height * 4) as u64;
 
         let msaa_size = if self.msaa_sample_count > 1 {
-            let bytes_per_pixel = match self.config.format {
-                wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Rgba8UnormSrgb => 4,
-                _ => 4,
-            };
+            // MSAA color targets use HDR_FORMAT (Rgba16Float)
+            let bytes_per_pixel = 8;
             let color_size = (width * height * bytes_per_pixel * self.msaa_sample_count) as u64;
             let depth_size = (width * height * 4 * self.msaa_sample_count) as u64;
             color_size + depth_size
@@ -443,11 +934,41 @@ impl Renderer {
         (depth_size, msaa_size)
     }
 
+    /// HDR target plus post-process scratch, velocity and history targets.
+    pub fn calculate_hdr_memory(&self) -> u64 {
+        let (width, height) = self.size;
+        let hdr_size = (width * height * 8) as u64;
+        let post_size = if self.post_texture.is_some() {
+            hdr_size
+        } else {
+            0
+        };
+        // Rg16Float, plus the multisampled copy the main pass resolves from
+        let velocity_size = match (&self.velocity_texture, &self.msaa_velocity_texture) {
+            (Some(_), Some(_)) => (width * height * 4) as u64 * (1 + self.msaa_sample_count as u64),
+            (Some(_), None) => (width * height * 4) as u64,
+            _ => 0,
+        };
+        let history_size = self.taa_history.as_ref().map_or(0, TaaHistory::memory_size);
+        hdr_size + post_size + velocity_size + history_size
+    }
+
     pub fn camera_buffer_size(&self) -> u64 {
-        std::mem::size_of::<CameraUniform>() as u64
+        (std::mem::size_of::<CameraUniform>() * self.camera_buffers.len()) as u64
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        // Let in-flight frames finish before their resources are released
+        self.frame_sync.wait_idle(&self.device);
     }
 }
 
 pub fn create_renderer_sync(window: Arc<Window>) -> Result<Renderer> {
     Renderer::new(window)
 }
+
+pub fn create_renderer_headless(width: u32, height: u32) -> Result<Renderer> {
+    Renderer::new_headless(width, height)
+}
@@ -0,0 +1,368 @@
+//! Morph targets (blend shapes) evaluated in the mesh vertex shader.
+//!
+//! Meshes with [`MorphTarget`](crate::assets::MorphTarget)s upload their position and normal
+//! deltas once, into a storage buffer laid out target by target. Every frame
+//! [`prepare_morph_targets`] writes the weights of each instance, from its [`MorphWeights`] or
+//! the mesh's defaults, and the main pass binds them per batch. Instances of a mesh are
+//! adjacent in model storage, so one weights buffer per mesh covers all of them.
+//!
+//! ```ignore
+//! // Blink with the face mesh's second target
+//! fn blink(time: Res<Time>, mut faces: Query<&mut MorphWeights, With<Face>>) {
+//!     for mut weights in &mut faces {
+//!         weights.set(1, (time.elapsed_secs() * 4.0).sin().max(0.0));
+//!     }
+//! }
+//! ```
+//!
+//! Shadow maps, wireframes and culling bounds use the undeformed mesh.
+
+use crate::assets::handle::AssetId;
+use crate::assets::loader::mesh::MeshData;
+use crate::core::math::*;
+use crate::renderer::systems::FrameAllocator;
+use crate::renderer::{ExtractedScene, GpuMesh, GpuMeshCache, GpuUploader, MeshPipeline, Renderer};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bytemuck::{Pod, Zeroable};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, Buffer, Device};
+
+/// Weight of each of the mesh's morph targets, in the order the mesh defines them.
+/// Missing weights count as zero. Meshes without the component use their default weights.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct MorphWeights(pub Vec<f32>);
+
+impl MorphWeights {
+    pub fn new(weights: impl Into<Vec<f32>>) -> Self {
+        Self(weights.into())
+    }
+
+    pub fn weight(&self, target: usize) -> f32 {
+        self.0.get(target).copied().unwrap_or(0.0)
+    }
+
+    pub fn set(&mut self, target: usize, weight: f32) {
+        if self.0.len() <= target {
+            self.0.resize(target + 1, 0.0);
+        }
+        self.0[target] = weight;
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct MorphDelta {
+    pub position: [f32; 4],
+    pub normal: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct MorphParams {
+    pub vertex_count: u32,
+    /// Zero for meshes without morph targets, which skips the evaluation.
+    pub target_count: u32,
+    /// Model storage index of the mesh's first instance this frame.
+    pub first_instance: u32,
+    pub _padding: u32,
+}
+
+/// Deltas of a mesh's morph targets on the GPU.
+pub struct GpuMorphTargets {
+    pub deltas: Buffer,
+    pub target_count: u32,
+    pub vertex_count: u32,
+    pub default_weights: Vec<f32>,
+}
+
+impl GpuMorphTargets {
+    /// `None` if the mesh has no morph targets.
+    pub fn from_mesh_data(device: &Device, mesh_data: &MeshData) -> Option<Self> {
+        if mesh_data.morph_targets.is_empty() {
+            return None;
+        }
+        let deltas = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Morph Delta Buffer"),
+            contents: bytemuck::cast_slice(&pack_deltas(mesh_data)),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        Some(Self {
+            deltas,
+            target_count: mesh_data.morph_targets.len() as u32,
+            vertex_count: mesh_data.positions.len() as u32,
+            default_weights: mesh_data.morph_weights.clone(),
+        })
+    }
+}
+
+/// One delta per target and vertex, target by target. Missing deltas are zero.
+pub fn pack_deltas(mesh_data: &MeshData) -> Vec<MorphDelta> {
+    let vertex_count = mesh_data.positions.len();
+    mesh_data
+        .morph_targets
+        .iter()
+        .flat_map(|target| {
+            (0..vertex_count).map(move |i| MorphDelta {
+                position: target
+                    .positions
+                    .get(i)
+                    .copied()
+                    .unwrap_or(Vec3::ZERO)
+                    .extend(0.0)
+                    .to_array(),
+                normal: target
+                    .normals
+                    .get(i)
+                    .copied()
+                    .unwrap_or(Vec3::ZERO)
+                    .extend(0.0)
+                    .to_array(),
+            })
+        })
+        .collect()
+}
+
+/// Weights of one morph mesh's instances this frame.
+pub struct MorphInstances {
+    /// The mesh whose deltas `bind_group` reads.
+    pub mesh: Arc<GpuMesh>,
+    pub weights: Buffer,
+    /// Weights `weights` has room for.
+    pub capacity: usize,
+    pub params: Buffer,
+    pub bind_group: BindGroup,
+}
+
+/// Morph bind groups of the main pass, one per mesh with morph targets drawn this frame.
+#[derive(Resource)]
+pub struct MorphDrawData {
+    pub meshes: HashMap<AssetId, MorphInstances>,
+    /// Bound for meshes without morph targets.
+    pub empty: BindGroup,
+}
+
+impl MorphDrawData {
+    pub fn bind_group(&self, mesh_id: &AssetId) -> &BindGroup {
+        self.meshes
+            .get(mesh_id)
+            .map(|instances| &instances.bind_group)
+            .unwrap_or(&self.empty)
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    pipeline: &MeshPipeline,
+    deltas: &Buffer,
+    weights: &Buffer,
+    params: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Morph Bind Group"),
+        layout: &pipeline.morph_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: deltas.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: weights.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+fn create_empty(device: &Device, pipeline: &MeshPipeline) -> BindGroup {
+    let deltas = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Empty Morph Delta Buffer"),
+        contents: bytemuck::bytes_of(&MorphDelta::default()),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let weights = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Empty Morph Weight Buffer"),
+        contents: bytemuck::bytes_of(&0.0f32),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Empty Morph Params Buffer"),
+        contents: bytemuck::bytes_of(&MorphParams::default()),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    create_bind_group(device, pipeline, &deltas, &weights, &params)
+}
+
+/// GPU state morph weights are written with.
+#[derive(SystemParam)]
+pub struct MorphInputs<'w> {
+    renderer: Option<Res<'w, Renderer>>,
+    pipeline: Option<Res<'w, MeshPipeline>>,
+    gpu_mesh_cache: Option<Res<'w, GpuMeshCache>>,
+}
+
+/// Writes the weights of every drawn morph mesh instance. Runs after
+/// [`prepare_indirect_draw_data`](crate::renderer::systems::prepare_indirect_draw_data),
+/// whose instance order the weights follow.
+pub fn prepare_morph_targets(
+    mut commands: Commands,
+    inputs: MorphInputs,
+    morph_data: Option<ResMut<MorphDrawData>>,
+    frame_allocator: Res<FrameAllocator>,
+    extracted: Res<ExtractedScene>,
+    mut uploader: ResMut<GpuUploader>,
+    mut weights: Local<Vec<f32>>,
+) {
+    let MorphInputs {
+        renderer,
+        pipeline,
+        gpu_mesh_cache,
+    } = inputs;
+    let Some(renderer) = renderer else { return };
+    let Some(pipeline) = pipeline else { return };
+    let Some(gpu_mesh_cache) = gpu_mesh_cache else {
+        return;
+    };
+    let device = renderer.device();
+
+    let mut created = None;
+    let morph_data = match morph_data {
+        Some(morph_data) => morph_data.into_inner(),
+        None => created.insert(MorphDrawData {
+            meshes: HashMap::new(),
+            empty: create_empty(device, &pipeline),
+        }),
+    };
+
+    let entities = &frame_allocator.entities;
+    let mut drawn = Vec::new();
+    let mut start = 0;
+    while start < entities.len() {
+        let mesh_id = entities[start].1;
+        let end = start
+            + entities[start..]
+                .iter()
+                .take_while(|(_, id, ..)| *id == mesh_id)
+                .count();
+        let first_instance = start as u32;
+        let instances = &entities[start..end];
+        start = end;
+
+        let Some(gpu_mesh) = gpu_mesh_cache.get(&mesh_id) else {
+            continue;
+        };
+        let Some(targets) = &gpu_mesh.morph else {
+            continue;
+        };
+
+        let target_count = targets.target_count as usize;
+        weights.clear();
+        for (entity, ..) in instances {
+            let instance = extracted
+                .morph_weights(*entity)
+                .unwrap_or(targets.default_weights.as_slice());
+            weights.extend((0..target_count).map(|t| instance.get(t).copied().unwrap_or(0.0)));
+        }
+        let params = MorphParams {
+            vertex_count: targets.vertex_count,
+            target_count: targets.target_count,
+            first_instance,
+            _padding: 0,
+        };
+
+        let reusable = morph_data.meshes.get(&mesh_id).is_some_and(|existing| {
+            existing.capacity >= weights.len() && Arc::ptr_eq(&existing.mesh, &gpu_mesh)
+        });
+        if !reusable {
+            let capacity = weights.len().next_power_of_two();
+            let weight_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Morph Weight Buffer"),
+                size: (capacity * std::mem::size_of::<f32>()) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Morph Params Buffer"),
+                size: std::mem::size_of::<MorphParams>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = create_bind_group(
+                device,
+                &pipeline,
+                &targets.deltas,
+                &weight_buffer,
+                &params_buffer,
+            );
+            morph_data.meshes.insert(
+                mesh_id,
+                MorphInstances {
+                    mesh: Arc::clone(&gpu_mesh),
+                    weights: weight_buffer,
+                    capacity,
+                    params: params_buffer,
+                    bind_group,
+                },
+            );
+        }
+
+        let instances = &morph_data.meshes[&mesh_id];
+        uploader.write_buffer(
+            &instances.weights,
+            0,
+            bytemuck::cast_slice(weights.as_slice()),
+        );
+        uploader.write_buffer(&instances.params, 0, bytemuck::bytes_of(&params));
+        drawn.push(mesh_id);
+    }
+
+    morph_data
+        .meshes
+        .retain(|mesh_id, _| drawn.contains(mesh_id));
+    if let Some(created) = created {
+        commands.insert_resource(created);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::MorphTarget;
+
+    #[test]
+    fn test_pack_deltas_fills_missing_with_zero() {
+        let mesh_data = MeshData {
+            positions: vec![Vec3::ZERO, Vec3::X],
+            morph_targets: vec![
+                MorphTarget {
+                    positions: vec![Vec3::Y, Vec3::Z],
+                    normals: Vec::new(),
+                },
+                MorphTarget {
+                    positions: vec![Vec3::X],
+                    normals: vec![Vec3::Y, Vec3::Y],
+                },
+            ],
+            ..MeshData::new()
+        };
+
+        let deltas = pack_deltas(&mesh_data);
+        assert_eq!(deltas.len(), 4);
+        assert_eq!(deltas[1].position, [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(deltas[1].normal, [0.0; 4]);
+        assert_eq!(deltas[3].position, [0.0; 4]);
+        assert_eq!(deltas[3].normal, [0.0, 1.0, 0.0, 0.0]);
+
+        let mut weights = MorphWeights::default();
+        weights.set(1, 0.5);
+        assert_eq!(weights.0, vec![0.0, 0.5]);
+        assert_eq!(weights.weight(3), 0.0);
+    }
+}
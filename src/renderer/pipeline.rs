@@ -1,13 +1,23 @@
 use crate::renderer::mesh::Vertex;
+use crate::renderer::pipeline_cache::{PipelineCache, PipelineKey};
+use crate::renderer::shader_registry::{EngineShader, ShaderRegistry};
 use bevy_ecs::prelude::Resource;
-use wgpu::{BindGroupLayout, Device, PipelineLayoutDescriptor, RenderPipeline, TextureFormat};
+use wgpu::{
+    BindGroupLayout, ComputePipeline, Device, PipelineLayoutDescriptor, RenderPipeline, Sampler,
+    TextureFormat,
+};
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct MeshPipeline {
     pub pipeline: RenderPipeline,
+    /// Same as `pipeline`, but also writes motion vectors to a second
+    /// [`VELOCITY_FORMAT`](crate::renderer::VELOCITY_FORMAT) target.
+    pub velocity_pipeline: RenderPipeline,
     pub camera_bind_group_layout: BindGroupLayout,
     pub model_bind_group_layout: BindGroupLayout,
     pub lighting_bind_group_layout: BindGroupLayout,
+    /// Morph target deltas, instance weights and [`MorphParams`](crate::renderer::morph::MorphParams).
+    pub morph_bind_group_layout: BindGroupLayout,
     // SSAO removed
     // pub ssao_bind_group_layout: BindGroupLayout,
     // pub ssao_sampler: Sampler,
@@ -15,7 +25,24 @@ pub struct MeshPipeline {
 
 impl MeshPipeline {
     pub fn new(device: &Device, surface_format: TextureFormat, sample_count: u32) -> Self {
-        let shader_source = include_str!("shaders/mesh.wgsl");
+        Self::with_source(
+            device,
+            surface_format,
+            sample_count,
+            EngineShader::Mesh.embedded_source(),
+            None,
+        )
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader, compiling
+    /// through `cache` when given.
+    pub fn with_source(
+        device: &Device,
+        surface_format: TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+        cache: Option<&wgpu::PipelineCache>,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Mesh Shader"),
             source: wgpu::ShaderSource::Wgsl(shader_source.into()),
@@ -66,16 +93,134 @@ impl MeshPipeline {
         let lighting_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Lighting Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Light clusters: params, per-cluster light counts, light indices
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Point light shadows: cube faces, comparison sampler, face matrices
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Light probes of the LightProbeGrid
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 8,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let morph_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Morph Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
             });
 
         // SSAO bind group removed - using vertex AO only
@@ -88,60 +233,83 @@ impl MeshPipeline {
                 &camera_bind_group_layout,
                 &model_bind_group_layout,
                 &lighting_bind_group_layout,
+                &morph_bind_group_layout,
                 // SSAO bind group removed
             ],
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Mesh Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,  // Enable depth writes since depth prepass was removed
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: sample_count,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
+        let color_target = Some(wgpu::ColorTargetState {
+            format: surface_format,
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+        let velocity_target = Some(wgpu::ColorTargetState {
+            format: crate::renderer::VELOCITY_FORMAT,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
         });
+        let create_pipeline =
+            |label: &str, entry_point: &str, targets: &[Option<wgpu::ColorTargetState>]| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[Vertex::desc()],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some(entry_point),
+                        targets,
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: wgpu::TextureFormat::Depth32Float,
+                        depth_write_enabled: true, // Enable depth writes since depth prepass was removed
+                        depth_compare: wgpu::CompareFunction::LessEqual,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache,
+                })
+            };
+
+        let pipeline = create_pipeline(
+            "Mesh Render Pipeline",
+            "fs_main",
+            std::slice::from_ref(&color_target),
+        );
+        let velocity_pipeline = create_pipeline(
+            "Mesh Velocity Render Pipeline",
+            "fs_main_velocity",
+            &[color_target, velocity_target],
+        );
 
         Self {
             pipeline,
+            velocity_pipeline,
             camera_bind_group_layout,
             model_bind_group_layout,
             lighting_bind_group_layout,
+            morph_bind_group_layout,
             // SSAO removed
             // ssao_bind_group_layout,
             // ssao_sampler,
@@ -217,7 +385,7 @@ impl DepthPrepassPipeline {
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
-                entry_point: Some("vs_main"),
+                entry_point: Some("vs_depth"),
                 buffers: &[Vertex::desc()],
                 compilation_options: Default::default(),
             },
@@ -255,16 +423,41 @@ impl DepthPrepassPipeline {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct WireframePipeline {
     pub pipeline: RenderPipeline,
+    /// Draws [`WireframeOverlay`](crate::addons::WireframeOverlay) entities
+    /// with their own color and depth bias.
+    pub overlay_pipeline: RenderPipeline,
     pub camera_bind_group_layout: BindGroupLayout,
     pub model_bind_group_layout: BindGroupLayout,
+    /// Per-overlay uniform, bound with a dynamic offset.
+    pub overlay_bind_group_layout: BindGroupLayout,
 }
 
+/// Stride between overlay uniforms, the minimum dynamic offset alignment wgpu guarantees.
+pub const WIREFRAME_OVERLAY_STRIDE: u64 = 256;
+
 impl WireframePipeline {
     pub fn new(device: &Device, surface_format: TextureFormat, sample_count: u32) -> Self {
-        let shader_source = include_str!("shaders/wireframe.wgsl");
+        Self::with_source(
+            device,
+            surface_format,
+            sample_count,
+            EngineShader::Wireframe.embedded_source(),
+            None,
+        )
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader, compiling
+    /// through `cache` when given.
+    pub fn with_source(
+        device: &Device,
+        surface_format: TextureFormat,
+        sample_count: u32,
+        shader_source: &str,
+        cache: Option<&wgpu::PipelineCache>,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Wireframe Shader"),
             source: wgpu::ShaderSource::Wgsl(shader_source.into()),
@@ -312,81 +505,1083 @@ impl WireframePipeline {
                 ],
             });
 
+        let overlay_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Wireframe Overlay Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(32),
+                    },
+                    count: None,
+                }],
+            });
+
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Wireframe Pipeline Layout"),
             bind_group_layouts: &[&camera_bind_group_layout, &model_bind_group_layout],
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Wireframe Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: sample_count,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
+        let overlay_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Wireframe Overlay Pipeline Layout"),
+            bind_group_layouts: &[
+                &camera_bind_group_layout,
+                &model_bind_group_layout,
+                &overlay_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
         });
 
+        let create =
+            |label: &str, layout: &wgpu::PipelineLayout, vs_entry: &str, fs_entry: &str| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some(vs_entry),
+                        buffers: &[Vertex::desc()],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some(fs_entry),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::LineList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: wgpu::TextureFormat::Depth32Float,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::LessEqual,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache,
+                })
+            };
+
         Self {
-            pipeline,
+            pipeline: create("Wireframe Pipeline", &pipeline_layout, "vs_main", "fs_main"),
+            overlay_pipeline: create(
+                "Wireframe Overlay Pipeline",
+                &overlay_pipeline_layout,
+                "vs_overlay",
+                "fs_overlay",
+            ),
             camera_bind_group_layout,
             model_bind_group_layout,
+            overlay_bind_group_layout,
         }
     }
 }
 
+/// Line pipelines for [`Gizmos`](crate::renderer::Gizmos), with and without depth testing.
+#[derive(Resource, Clone)]
+pub struct GizmoPipeline {
+    pub pipeline: RenderPipeline,
+    /// Draws over everything, for `Gizmos::depth_test == false`.
+    pub overlay_pipeline: RenderPipeline,
+    pub camera_bind_group_layout: BindGroupLayout,
+}
 
-/// Factory for creating all pipeline resources at once
-///
-/// This consolidates pipeline creation logic to avoid duplication between
-/// initialization and graphics settings updates.
-pub struct PipelineFactory;
+impl GizmoPipeline {
+    pub fn new(device: &Device, format: TextureFormat, sample_count: u32) -> Self {
+        Self::with_source(
+            device,
+            format,
+            sample_count,
+            EngineShader::Gizmo.embedded_source(),
+            None,
+        )
+    }
 
-impl PipelineFactory {
-    /// Create all pipeline resources with the given settings
-    pub fn create_all(
+    /// Builds the pipeline from `shader_source` instead of the embedded shader, compiling
+    /// through `cache` when given.
+    pub fn with_source(
         device: &Device,
-        surface_format: TextureFormat,
+        format: TextureFormat,
         sample_count: u32,
-    ) -> (MeshPipeline, WireframePipeline) {
-        (
-            MeshPipeline::new(device, surface_format, sample_count),
-            WireframePipeline::new(device, surface_format, sample_count),
-        )
+        shader_source: &str,
+        cache: Option<&wgpu::PipelineCache>,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Gizmo Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Gizmo Camera Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Gizmo Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create = |label, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[crate::renderer::gizmos::GizmoVertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache,
+            })
+        };
+
+        Self {
+            pipeline: create("Gizmo Pipeline", wgpu::CompareFunction::LessEqual),
+            overlay_pipeline: create("Gizmo Overlay Pipeline", wgpu::CompareFunction::Always),
+            camera_bind_group_layout,
+        }
+    }
+}
+
+/// Pipeline for [`WaterSurface`](crate::renderer::water::WaterSurface) quads.
+///
+/// Owned by the [`WaterPassNode`](crate::renderer::WaterPassNode) rather than stored as a
+/// resource, since it needs [`RenderTargets::sample_layout`](crate::renderer::RenderTargets::sample_layout),
+/// which only exists once the main pass has run.
+pub struct WaterPipeline {
+    pub pipeline: RenderPipeline,
+    /// Per-surface uniform, bound with a dynamic offset.
+    pub water_bind_group_layout: BindGroupLayout,
+    pub sample_count: u32,
+}
+
+/// Stride between per-surface water uniforms.
+pub const WATER_UNIFORM_STRIDE: u64 = 256;
+
+impl WaterPipeline {
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        sample_count: u32,
+        reflection_layout: &BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Water Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/water.wgsl").into()),
+        });
+
+        let water_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Water Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(144),
+                    },
+                    count: None,
+                }],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Water Pipeline Layout"),
+            bind_group_layouts: &[&water_bind_group_layout, reflection_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Water Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // Visible from below as well
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            water_bind_group_layout,
+            sample_count,
+        }
+    }
+}
+
+/// Compute pipeline binning point lights into froxels, see [`lighting::clusters`](crate::renderer::lighting::clusters).
+#[derive(Resource)]
+pub struct LightClusterPipeline {
+    pub pipeline: ComputePipeline,
+    pub bind_group_layout: BindGroupLayout,
+}
+
+impl LightClusterPipeline {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Light Cluster Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/light_cluster.wgsl").into()),
+        });
+
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        // params, point lights, cluster light counts, cluster light indices
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Light Cluster Bind Group Layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+                buffer_entry(3, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Light Cluster Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Light Cluster Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+}
+
+/// Depth-only pipeline rendering shadow casters into point light cube faces, see
+/// [`lighting::shadows`](crate::renderer::lighting::shadows).
+#[derive(Resource)]
+pub struct PointShadowPipeline {
+    pub pipeline: RenderPipeline,
+    /// [`PointShadowFaceUniform`](crate::renderer::lighting::shadows::PointShadowFaceUniform)
+    /// with a dynamic offset per face.
+    pub face_bind_group_layout: BindGroupLayout,
+    pub model_bind_group_layout: BindGroupLayout,
+}
+
+impl PointShadowPipeline {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Point Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/point_shadow.wgsl").into()),
+        });
+
+        let face_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Point Shadow Face Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                            crate::renderer::lighting::shadows::PointShadowFaceUniform,
+                        >() as u64),
+                    },
+                    count: None,
+                }],
+            });
+
+        // Same as the mesh pipeline's, so ModelStorageData's bind group is shared
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let model_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Point Shadow Model Bind Group Layout"),
+                entries: &[storage_entry(0), storage_entry(1)],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Point Shadow Pipeline Layout"),
+            bind_group_layouts: &[&face_bind_group_layout, &model_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Point Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // Both sides, so open meshes and single planes still cast
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: crate::renderer::lighting::shadows::POINT_SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            face_bind_group_layout,
+            model_bind_group_layout,
+        }
+    }
+}
+
+/// Fullscreen pass that tonemaps the HDR target onto the swapchain.
+#[derive(Resource)]
+pub struct TonemapPipeline {
+    pub pipeline: RenderPipeline,
+    pub bind_group_layout: BindGroupLayout,
+}
+
+impl TonemapPipeline {
+    pub fn new(device: &Device, surface_format: TextureFormat) -> Self {
+        Self::with_source(
+            device,
+            surface_format,
+            EngineShader::Tonemap.embedded_source(),
+        )
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader.
+    pub fn with_source(
+        device: &Device,
+        surface_format: TextureFormat,
+        shader_source: &str,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tonemap Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Tonemap Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tonemap Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+        }
+    }
+}
+
+/// Fullscreen-triangle pipeline writing one `format` target, as used by post-process passes.
+fn fullscreen_pipeline(
+    device: &Device,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(&format!("{} Layout", label)),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn texture_entry(binding: u32, sample_type: wgpu::TextureSampleType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn linear_clamp_sampler(device: &Device, label: &str) -> Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some(label),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+/// FXAA over the HDR target, written to the renderer's post-process texture.
+#[derive(Resource)]
+pub struct FxaaPipeline {
+    pub pipeline: RenderPipeline,
+    pub bind_group_layout: BindGroupLayout,
+    pub sampler: Sampler,
+}
+
+impl FxaaPipeline {
+    pub fn new(device: &Device) -> Self {
+        Self::with_source(device, EngineShader::Fxaa.embedded_source())
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader.
+    pub fn with_source(device: &Device, shader_source: &str) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FXAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // hdr, sampler, params
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("FXAA Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: true }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(2),
+            ],
+        });
+
+        Self {
+            pipeline: fullscreen_pipeline(
+                device,
+                "FXAA Pipeline",
+                &shader,
+                &bind_group_layout,
+                crate::renderer::HDR_FORMAT,
+            ),
+            bind_group_layout,
+            sampler: linear_clamp_sampler(device, "FXAA Sampler"),
+        }
+    }
+}
+
+/// Resolve pass of temporal anti-aliasing.
+#[derive(Resource)]
+pub struct TaaPipeline {
+    pub resolve_pipeline: RenderPipeline,
+    pub resolve_bind_group_layout: BindGroupLayout,
+    pub sampler: Sampler,
+}
+
+impl TaaPipeline {
+    pub fn new(device: &Device) -> Self {
+        Self::with_source(device, EngineShader::Taa.embedded_source())
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader.
+    pub fn with_source(device: &Device, shader_source: &str) -> Self {
+        let resolve_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("TAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // current, history, velocity, sampler, params
+        let resolve_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("TAA Bind Group Layout"),
+                entries: &[
+                    texture_entry(0, wgpu::TextureSampleType::Float { filterable: false }),
+                    texture_entry(1, wgpu::TextureSampleType::Float { filterable: true }),
+                    texture_entry(2, wgpu::TextureSampleType::Float { filterable: false }),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    uniform_entry(4),
+                ],
+            });
+
+        Self {
+            resolve_pipeline: fullscreen_pipeline(
+                device,
+                "TAA Pipeline",
+                &resolve_shader,
+                &resolve_bind_group_layout,
+                crate::renderer::HDR_FORMAT,
+            ),
+            resolve_bind_group_layout,
+            sampler: linear_clamp_sampler(device, "TAA History Sampler"),
+        }
+    }
+}
+
+/// Motion blur along the main pass motion vectors, written to the renderer's
+/// post-process texture.
+#[derive(Resource)]
+pub struct MotionBlurPipeline {
+    pub pipeline: RenderPipeline,
+    pub bind_group_layout: BindGroupLayout,
+    pub sampler: Sampler,
+}
+
+impl MotionBlurPipeline {
+    pub fn new(device: &Device) -> Self {
+        Self::with_source(device, EngineShader::MotionBlur.embedded_source())
+    }
+
+    /// Builds the pipeline from `shader_source` instead of the embedded shader.
+    pub fn with_source(device: &Device, shader_source: &str) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Motion Blur Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // hdr, velocity, sampler, params
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Motion Blur Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: true }),
+                texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(3),
+            ],
+        });
+
+        Self {
+            pipeline: fullscreen_pipeline(
+                device,
+                "Motion Blur Pipeline",
+                &shader,
+                &bind_group_layout,
+                crate::renderer::HDR_FORMAT,
+            ),
+            bind_group_layout,
+            sampler: linear_clamp_sampler(device, "Motion Blur Sampler"),
+        }
+    }
+}
+
+/// Compute pipelines that cull instances and compact their indirect draws.
+#[derive(Resource)]
+pub struct GpuCullPipeline {
+    /// Frustum only.
+    pub pipeline: ComputePipeline,
+    /// Frustum and depth pyramid, run after the depth prepass.
+    pub occlusion_pipeline: ComputePipeline,
+    pub bind_group_layout: BindGroupLayout,
+    pub pyramid_bind_group_layout: BindGroupLayout,
+}
+
+impl GpuCullPipeline {
+    pub fn new(device: &Device) -> Self {
+        let shader_source = include_str!("shaders/cull.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cull Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        // params, models, instances, batches, counters, commands
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Cull Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pyramid_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cull Depth Pyramid Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let occlusion_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Occlusion Cull Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, &pyramid_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let occlusion_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Occlusion Cull Pipeline"),
+            layout: Some(&occlusion_pipeline_layout),
+            module: &shader,
+            entry_point: Some("occlusion"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            occlusion_pipeline,
+            bind_group_layout,
+            pyramid_bind_group_layout,
+        }
+    }
+}
+
+/// Compute pipelines that build the max-depth pyramid used for occlusion culling.
+#[derive(Resource)]
+pub struct HiZPipeline {
+    pub copy_pipeline: ComputePipeline,
+    pub downsample_pipeline: ComputePipeline,
+    /// Depth texture at binding 0, pyramid level 0 at binding 1.
+    pub copy_bind_group_layout: BindGroupLayout,
+    /// Pyramid level `n + 1` at binding 1, level `n` at binding 2.
+    pub downsample_bind_group_layout: BindGroupLayout,
+}
+
+impl HiZPipeline {
+    pub const FORMAT: TextureFormat = TextureFormat::R32Float;
+
+    pub fn new(device: &Device) -> Self {
+        let shader_source = include_str!("shaders/hiz.wgsl");
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Pyramid Shader"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let copy_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Depth Pyramid Copy Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: Self::FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let downsample_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Depth Pyramid Downsample Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: Self::FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let create_pipeline = |label: &str, layout: &BindGroupLayout, entry_point: &str| {
+            let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let copy_pipeline = create_pipeline(
+            "Depth Pyramid Copy Pipeline",
+            &copy_bind_group_layout,
+            "copy_depth",
+        );
+        let downsample_pipeline = create_pipeline(
+            "Depth Pyramid Downsample Pipeline",
+            &downsample_bind_group_layout,
+            "downsample",
+        );
+
+        Self {
+            copy_pipeline,
+            downsample_pipeline,
+            copy_bind_group_layout,
+            downsample_bind_group_layout,
+        }
+    }
+}
+
+/// Factory for creating all pipeline resources at once
+///
+/// This consolidates pipeline creation logic to avoid duplication between
+/// initialization and graphics settings updates.
+pub struct PipelineFactory;
+
+impl PipelineFactory {
+    /// Create all pipeline resources with the given settings, from the registry's shaders.
+    /// Variants already in `cache` are reused.
+    pub fn create_all(
+        device: &Device,
+        surface_format: TextureFormat,
+        sample_count: u32,
+        shaders: &ShaderRegistry,
+        cache: &mut PipelineCache,
+    ) -> (MeshPipeline, WireframePipeline) {
+        let source = shaders.source(EngineShader::Mesh);
+        let key = PipelineKey::new("mesh", source)
+            .with_format(surface_format)
+            .with_sample_count(sample_count);
+        let mesh_pipeline = cache.get_or_create(key, |wgpu_cache| {
+            MeshPipeline::with_source(device, surface_format, sample_count, source, wgpu_cache)
+        });
+
+        let source = shaders.source(EngineShader::Wireframe);
+        let key = PipelineKey::new("wireframe", source)
+            .with_format(surface_format)
+            .with_sample_count(sample_count);
+        let wireframe_pipeline = cache.get_or_create(key, |wgpu_cache| {
+            WireframePipeline::with_source(device, surface_format, sample_count, source, wgpu_cache)
+        });
+
+        (mesh_pipeline, wireframe_pipeline)
+    }
+
+    /// The gizmo pipeline, reused from `cache` when it has been built before.
+    pub fn create_gizmo(
+        device: &Device,
+        format: TextureFormat,
+        sample_count: u32,
+        shaders: &ShaderRegistry,
+        cache: &mut PipelineCache,
+    ) -> GizmoPipeline {
+        let source = shaders.source(EngineShader::Gizmo);
+        let key = PipelineKey::new("gizmo", source)
+            .with_format(format)
+            .with_sample_count(sample_count);
+        cache.get_or_create(key, |wgpu_cache| {
+            GizmoPipeline::with_source(device, format, sample_count, source, wgpu_cache)
+        })
     }
 }
@@ -0,0 +1,323 @@
+//! Deduplicated, lazily built pipeline variants, and compiled pipeline data kept on disk.
+//!
+//! A pipeline depends on more than its shader: target formats, the MSAA sample count, and
+//! whatever permutation a material selects. [`PipelineCache::get_or_create`] builds a variant
+//! the first time its [`PipelineKey`] is requested and hands out clones afterwards, so
+//! switching MSAA back to an earlier setting doesn't compile anything.
+//!
+//! Where the backend supports it (currently Vulkan), variants are also compiled through a
+//! `wgpu::PipelineCache`. Its data is written to a file named after the adapter and driver
+//! and loaded on the next start, which skips most driver compilation even for variants not
+//! built yet in this run. The file starts with a header naming the adapter key, the data
+//! length and a CRC32 of the data; anything that doesn't match is discarded before it
+//! reaches the driver.
+
+use bevy_ecs::prelude::*;
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use wgpu::{AdapterInfo, Device, TextureFormat};
+
+/// Identifies a pipeline variant by what it was built from and the state it depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub name: &'static str,
+    /// Hash of the shader source.
+    pub shader: u64,
+    pub formats: Vec<TextureFormat>,
+    pub sample_count: u32,
+    /// Anything else the pipeline varies by, e.g. material permutation flags.
+    pub variant: u64,
+}
+
+impl PipelineKey {
+    pub fn new(name: &'static str, shader_source: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        shader_source.hash(&mut hasher);
+        Self {
+            name,
+            shader: hasher.finish(),
+            formats: Vec::new(),
+            sample_count: 1,
+            variant: 0,
+        }
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.formats.push(format);
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn with_variant(mut self, variant: u64) -> Self {
+        self.variant = variant;
+        self
+    }
+}
+
+#[derive(Resource)]
+pub struct PipelineCache {
+    variants: HashMap<PipelineKey, Box<dyn Any + Send + Sync>>,
+    wgpu_cache: Option<wgpu::PipelineCache>,
+    /// Where `wgpu_cache` is persisted, and the adapter key its header must carry.
+    file: Option<(PathBuf, String)>,
+    /// Variants were compiled since the last save.
+    dirty: bool,
+    hits: u64,
+    misses: u64,
+}
+
+impl PipelineCache {
+    /// Creates the cache, loading compiled pipeline data from `directory` when the device
+    /// supports pipeline caching. `None` keeps everything in memory.
+    pub fn new(device: &Device, adapter_info: &AdapterInfo, directory: Option<&Path>) -> Self {
+        let file = directory
+            .zip(wgpu::util::pipeline_cache_key(adapter_info))
+            .map(|(directory, key)| (directory.join(&key), key));
+
+        let wgpu_cache = device
+            .features()
+            .contains(wgpu::Features::PIPELINE_CACHE)
+            .then(|| {
+                let contents = file.as_ref().and_then(|(path, _)| std::fs::read(path).ok());
+                let data =
+                    file.as_ref()
+                        .zip(contents.as_deref())
+                        .and_then(|((path, key), contents)| {
+                            let data = decode_cache_file(key, contents);
+                            if data.is_none() {
+                                log::warn!("Discarding invalid pipeline cache {}", path.display());
+                            }
+                            data
+                        });
+                if data.is_some() {
+                    log::info!("Loaded pipeline cache for {}", adapter_info.name);
+                }
+                // SAFETY: the data passed the header check, so it is exactly what `save` wrote
+                // from `get_data` of a cache on this adapter and driver. With `fallback`, data
+                // wgpu still rejects as incompatible starts an empty cache instead.
+                unsafe {
+                    device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                        label: Some("Resonance Pipeline Cache"),
+                        data,
+                        fallback: true,
+                    })
+                }
+            });
+
+        Self {
+            variants: HashMap::new(),
+            wgpu_cache,
+            file,
+            dirty: false,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Per-user location for compiled pipeline data: `%LOCALAPPDATA%` on Windows,
+    /// `~/Library/Caches` on macOS, and `$XDG_CACHE_HOME` or `~/.cache` elsewhere. `None`
+    /// when none of these is set, in which case nothing should be persisted.
+    pub fn default_directory() -> Option<PathBuf> {
+        let absolute = |name: &str| {
+            std::env::var_os(name)
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+        };
+
+        let base = if cfg!(windows) {
+            absolute("LOCALAPPDATA")
+        } else if cfg!(target_os = "macos") {
+            absolute("HOME").map(|home| home.join("Library").join("Caches"))
+        } else {
+            absolute("XDG_CACHE_HOME").or_else(|| absolute("HOME").map(|home| home.join(".cache")))
+        }?;
+        Some(base.join("resonance").join("pipeline_cache"))
+    }
+
+    /// Returns the variant built for `key`, building it with `create` on first use. `create`
+    /// should pass the given `wgpu::PipelineCache` to its pipeline descriptors.
+    pub fn get_or_create<T: Clone + Send + Sync + 'static>(
+        &mut self,
+        key: PipelineKey,
+        create: impl FnOnce(Option<&wgpu::PipelineCache>) -> T,
+    ) -> T {
+        if let Some(variant) = self
+            .variants
+            .get(&key)
+            .and_then(|variant| variant.downcast_ref::<T>())
+        {
+            self.hits += 1;
+            return variant.clone();
+        }
+
+        self.misses += 1;
+        self.dirty = true;
+        let variant = create(self.wgpu_cache.as_ref());
+        self.variants.insert(key, Box::new(variant.clone()));
+        variant
+    }
+
+    pub fn wgpu_cache(&self) -> Option<&wgpu::PipelineCache> {
+        self.wgpu_cache.as_ref()
+    }
+
+    pub fn contains(&self, key: &PipelineKey) -> bool {
+        self.variants.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Drops every variant. Compiled data in the `wgpu::PipelineCache` is kept.
+    pub fn clear(&mut self) {
+        self.variants.clear();
+    }
+
+    /// Requests served from an existing variant.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Requests that had to build a variant.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Writes the compiled pipeline data to disk if anything was compiled since the last save.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+
+        let (Some(cache), Some((file, key))) = (&self.wgpu_cache, &self.file) else {
+            return Ok(());
+        };
+        let Some(data) = cache.get_data() else {
+            return Ok(());
+        };
+
+        if let Some(directory) = file.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        // Written next to the target and renamed, so a crash never leaves a truncated file
+        let temp = file.with_extension("tmp");
+        std::fs::write(&temp, encode_cache_file(key, &data))?;
+        std::fs::rename(&temp, file)
+    }
+}
+
+const CACHE_MAGIC: &[u8; 4] = b"RPLC";
+const CACHE_VERSION: u32 = 1;
+
+/// Prefixes pipeline cache data with the header `decode_cache_file` checks.
+fn encode_cache_file(key: &str, data: &[u8]) -> Vec<u8> {
+    let mut file = Vec::with_capacity(24 + key.len() + data.len());
+    file.extend_from_slice(CACHE_MAGIC);
+    file.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    file.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    file.extend_from_slice(key.as_bytes());
+    file.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    file.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+    file.extend_from_slice(data);
+    file
+}
+
+/// Returns the pipeline cache data in `file` if its header matches `key` and the data is
+/// complete and uncorrupted.
+fn decode_cache_file<'a>(key: &str, file: &'a [u8]) -> Option<&'a [u8]> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let (head, tail) = bytes.split_at_checked(len)?;
+        *bytes = tail;
+        Some(head)
+    }
+
+    let mut rest = file;
+    if take(&mut rest, 4)? != CACHE_MAGIC {
+        return None;
+    }
+    let version = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+    let key_len = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+    if version != CACHE_VERSION || take(&mut rest, key_len as usize)? != key.as_bytes() {
+        return None;
+    }
+    let data_len = u64::from_le_bytes(take(&mut rest, 8)?.try_into().ok()?);
+    let checksum = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+    if rest.len() as u64 != data_len || crc32fast::hash(rest) != checksum {
+        return None;
+    }
+    Some(rest)
+}
+
+/// Persists newly compiled pipelines, typically once after startup and after settings changes.
+pub fn save_pipeline_cache(cache: Option<ResMut<PipelineCache>>) {
+    let Some(mut cache) = cache else {
+        return;
+    };
+    if !cache.dirty {
+        return;
+    }
+    if let Err(e) = cache.save() {
+        log::warn!("Failed to save pipeline cache: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_distinguishes_variants() {
+        let msaa_off = PipelineKey::new("mesh", "fn main() {}")
+            .with_format(TextureFormat::Rgba16Float)
+            .with_sample_count(1);
+        let msaa_on = msaa_off.clone().with_sample_count(4);
+        assert_ne!(msaa_off, msaa_on);
+        assert_ne!(
+            msaa_off,
+            PipelineKey::new("mesh", "fn other() {}").with_format(TextureFormat::Rgba16Float)
+        );
+        assert_eq!(
+            msaa_off,
+            PipelineKey::new("mesh", "fn main() {}").with_format(TextureFormat::Rgba16Float)
+        );
+    }
+
+    #[test]
+    fn test_cache_file_roundtrip() {
+        let file = encode_cache_file("vulkan_10de_2684", b"driver blob");
+        assert_eq!(
+            decode_cache_file("vulkan_10de_2684", &file),
+            Some(&b"driver blob"[..])
+        );
+    }
+
+    #[test]
+    fn test_cache_file_rejects_invalid_data() {
+        let file = encode_cache_file("vulkan_10de_2684", b"driver blob");
+
+        assert_eq!(decode_cache_file("vulkan_1002_744c", &file), None);
+        assert_eq!(
+            decode_cache_file("vulkan_10de_2684", &file[..file.len() - 1]),
+            None
+        );
+        assert_eq!(decode_cache_file("vulkan_10de_2684", b"driver blob"), None);
+        assert_eq!(decode_cache_file("vulkan_10de_2684", &[]), None);
+
+        let mut corrupted = file.clone();
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        assert_eq!(decode_cache_file("vulkan_10de_2684", &corrupted), None);
+    }
+}
@@ -1,30 +1,90 @@
 use crate::app::{Plugin, Resonance, Stage};
+use crate::renderer::shader_registry::EngineShader;
 use crate::renderer::{
-    GpuMeshCache, GraphicsSettings, MainPassNode, MeshPipeline, RenderGraph, Renderer,
-    WireframePassNode,
+    GizmoPassNode, GpuMeshCache, GpuUploader, GraphicsSettings, HDR_FORMAT, MainPassNode,
+    MeshPipeline, PipelineCache, RenderGraph, Renderer, ScreenshotCaptured, ScreenshotNode,
+    ShaderRegistry, TonemapNode, TonemapPipeline, WireframePassNode,
 };
 use crate::window::Window;
 use std::any::TypeId;
 use std::sync::Arc;
 
 #[derive(Default)]
-pub struct RenderPlugin;
+pub struct RenderPlugin {
+    headless: Option<HeadlessRendering>,
+}
 
 impl RenderPlugin {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Renders into an offscreen `width` x `height` texture instead of a window, so tests and
+    /// servers without a display can run the full render graph and read frames back with
+    /// [`Renderer::read_output`] or a [`ScreenshotRequest`](crate::renderer::ScreenshotRequest).
+    /// Doesn't need the [`WindowPlugin`](crate::window::WindowPlugin) and runs in server mode.
+    pub fn headless(width: u32, height: u32) -> Self {
+        Self {
+            headless: Some(HeadlessRendering { width, height }),
+        }
     }
 }
 
+/// Size of the output texture of a renderer added with [`RenderPlugin::headless`].
+#[derive(bevy_ecs::prelude::Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeadlessRendering {
+    pub width: u32,
+    pub height: u32,
+}
+
 impl Plugin for RenderPlugin {
     fn build(&self, engine: &mut Resonance) {
+        if let Some(headless) = self.headless {
+            engine.world.insert_resource(headless);
+        }
+        engine
+            .world
+            .init_resource::<crate::renderer::systems::FrameAllocator>();
+        engine.world.init_resource::<GpuUploader>();
+        engine
+            .world
+            .init_resource::<crate::renderer::ExtractedScene>();
+        engine.world.init_resource::<crate::renderer::Gizmos>();
+        engine.world.init_resource::<crate::renderer::RenderStats>();
+        engine
+            .world
+            .init_resource::<crate::renderer::RenderTargets>();
+        engine.world.init_resource::<ShaderRegistry>();
+        engine
+            .world
+            .init_resource::<crate::renderer::TextureStreamer>();
+        engine
+            .world
+            .init_resource::<crate::renderer::RenderExtensions>();
+        engine
+            .world
+            .init_resource::<crate::renderer::screenshot::PendingScreenshots>();
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<ScreenshotCaptured>>();
+        engine
+            .world
+            .init_resource::<bevy_ecs::message::Messages<crate::core::AssetLoaded>>();
+
         if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            use bevy_ecs::schedule::IntoScheduleConfigs;
+
             schedule.add_systems((
                 initialize_renderer,
                 update_graphics_settings,
+                crate::renderer::shader_registry::hot_reload_shaders
+                    .after(update_graphics_settings),
+                crate::renderer::extension::setup_render_extensions.after(initialize_renderer),
                 recreate_camera_bind_group,
                 crate::renderer::systems::initialize_lighting,
                 crate::renderer::systems::update_camera_aspect_ratio,
+                crate::renderer::systems::replace_placeholder_meshes
+                    .before(crate::renderer::systems::upload_meshes),
                 crate::renderer::systems::upload_meshes,
                 crate::renderer::systems::compute_mesh_aabbs,
             ));
@@ -34,11 +94,12 @@ impl Plugin for RenderPlugin {
             use bevy_ecs::schedule::IntoScheduleConfigs;
 
             // IMPORTANT: System ordering dependency for frustum culling.
-            // prepare_indirect_draw_data must run AFTER propagate_transforms (from TransformPlugin).
+            // extract_render_data must run AFTER propagate_transforms (from TransformPlugin), and
+            // prepare_indirect_draw_data reads only what it extracted.
             //
             // Why: The camera's Transform is updated by FlyCam in the Update stage. In PostUpdate,
             // we need to sync Transform → GlobalTransform. The camera's GlobalTransform must be
-            // fully synchronized before prepare_indirect_draw_data reads it to compute the frustum.
+            // fully synchronized before extract_render_data copies it for the frustum.
             //
             // Without this ordering: Systems might execute in parallel, causing prepare_indirect
             // to read a stale GlobalTransform and compute frustum from previous frame's camera
@@ -49,30 +110,65 @@ impl Plugin for RenderPlugin {
             schedule.add_systems((
                 crate::renderer::systems::cleanup_mesh_components,
                 crate::renderer::systems::cleanup_unused_meshes,
-                crate::renderer::systems::update_lighting,
-                crate::renderer::systems::prepare_indirect_draw_data
+                wait_for_frame_slot,
+                crate::renderer::systems::update_lighting
+                    .after(wait_for_frame_slot)
+                    .after(crate::transform::systems::propagate_transforms),
+                crate::renderer::extract::extract_render_data
+                    .after(wait_for_frame_slot)
                     .after(crate::transform::systems::propagate_transforms),
+                // Reflection cameras must be in place before extraction culls for them
+                crate::renderer::water::update_water_reflections
+                    .after(crate::transform::systems::propagate_transforms)
+                    .before(crate::renderer::extract::extract_render_data),
+                crate::renderer::systems::prepare_indirect_draw_data
+                    .after(crate::renderer::extract::extract_render_data),
+                crate::renderer::systems::prepare_gpu_culling
+                    .after(crate::renderer::systems::prepare_indirect_draw_data),
+                crate::renderer::morph::prepare_morph_targets
+                    .after(crate::renderer::systems::prepare_indirect_draw_data),
+                crate::renderer::systems::prepare_point_shadow_draws
+                    .after(crate::renderer::systems::prepare_indirect_draw_data)
+                    .after(crate::renderer::systems::update_lighting),
                 crate::renderer::systems::update_gpu_memory_stats,
-                submit_gpu_work,
+                crate::renderer::world_anchor::update_world_anchors
+                    .after(crate::transform::systems::propagate_transforms),
+                crate::renderer::texture_streaming::stream_textures
+                    .after(wait_for_frame_slot)
+                    .after(crate::transform::systems::propagate_transforms),
+                submit_gpu_work
+                    .after(crate::renderer::systems::update_lighting)
+                    .after(crate::renderer::systems::prepare_gpu_culling)
+                    .after(crate::renderer::morph::prepare_morph_targets)
+                    .after(crate::renderer::systems::prepare_point_shadow_draws),
             ));
         }
 
         if let Some(schedule) = engine.schedules.get_mut(Stage::Render) {
             schedule.add_systems(render_system);
         }
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::Last) {
+            schedule.add_systems((
+                crate::renderer::pipeline_cache::save_pipeline_cache,
+                crate::renderer::screenshot::process_screenshots,
+                crate::renderer::gizmos::clear_gizmos,
+            ));
+        }
     }
 
     fn dependencies(&self) -> Vec<(TypeId, &str)> {
-        vec![
-            (
+        let mut dependencies = vec![(
+            TypeId::of::<crate::transform::TransformPlugin>(),
+            "resonance::transform::TransformPlugin",
+        )];
+        if self.headless.is_none() {
+            dependencies.push((
                 TypeId::of::<crate::window::WindowPlugin>(),
                 "resonance::window::WindowPlugin",
-            ),
-            (
-                TypeId::of::<crate::transform::TransformPlugin>(),
-                "resonance::transform::TransformPlugin",
-            ),
-        ]
+            ));
+        }
+        dependencies
     }
 
     fn is_client_plugin(&self) -> bool {
@@ -80,7 +176,32 @@ impl Plugin for RenderPlugin {
     }
 
     fn is_server_plugin(&self) -> bool {
-        false
+        self.headless.is_some()
+    }
+
+    fn on_shutdown(&self, engine: &mut Resonance) {
+        engine.world.remove_resource::<ShaderRegistry>();
+        if let Some(mut renderer) = engine.world.get_resource_mut::<Renderer>() {
+            renderer.wait_idle();
+        }
+    }
+}
+
+/// Experimental renderer features switched on through [`FeatureFlags`](crate::core::FeatureFlags).
+/// Flags only enable features, so settings chosen in code are never turned off.
+fn apply_feature_flags(world: &mut bevy_ecs::prelude::World) {
+    use crate::core::features::{GPU_CULLING, OCCLUSION_CULLING};
+
+    let Some(flags) = world.get_resource::<crate::core::FeatureFlags>().cloned() else {
+        return;
+    };
+    let mut settings = world.resource_mut::<GraphicsSettings>();
+    if flags.is_enabled(GPU_CULLING) {
+        settings.set_gpu_culling(true);
+    }
+    if flags.is_enabled(OCCLUSION_CULLING) {
+        settings.set_gpu_culling(true);
+        settings.set_occlusion_culling(true);
     }
 }
 
@@ -89,55 +210,121 @@ fn initialize_renderer(world: &mut bevy_ecs::prelude::World) {
         return;
     }
 
-    let Some(window) = world.get_resource::<Window>() else {
-        return;
+    let renderer = if let Some(headless) = world.get_resource::<HeadlessRendering>().copied() {
+        let renderer = crate::renderer::create_renderer_headless(headless.width, headless.height);
+        if renderer.is_err() {
+            // Without a window nothing changes between frames, so don't retry every frame
+            world.remove_resource::<HeadlessRendering>();
+        }
+        renderer
+    } else {
+        let Some(window) = world.get_resource::<Window>() else {
+            return;
+        };
+        crate::renderer::create_renderer_sync(Arc::clone(&window.window))
     };
 
-    let window_arc = Arc::clone(&window.window);
-
-    match crate::renderer::create_renderer_sync(window_arc) {
+    match renderer {
         Ok(mut renderer) => {
             if !world.contains_resource::<GraphicsSettings>() {
                 world.insert_resource(GraphicsSettings::default());
             }
+            apply_feature_flags(world);
 
             let graphics_settings = world.get_resource::<GraphicsSettings>().unwrap();
             let sample_count = graphics_settings.msaa_sample_count().as_u32();
             let vsync_enabled = graphics_settings.vsync_enabled();
+            let frames_in_flight = graphics_settings.frames_in_flight();
+            let anti_aliasing = graphics_settings.anti_aliasing();
+            let motion_blur = graphics_settings.motion_blur() > 0.0;
 
             renderer.update_vsync(vsync_enabled);
             renderer.update_msaa_settings(sample_count);
+            renderer.set_frames_in_flight(frames_in_flight);
+            renderer.update_post_processing(anti_aliasing, motion_blur);
 
             let surface_format = renderer.config().format;
             let device = renderer.device();
+            let shaders = world.resource::<ShaderRegistry>();
+            let mut pipeline_cache = PipelineCache::new(
+                device,
+                renderer.adapter_info(),
+                PipelineCache::default_directory().as_deref(),
+            );
 
+            // Scene pipelines render into the HDR target; only tonemapping writes the surface
             let (mesh_pipeline, wireframe_pipeline) =
                 crate::renderer::pipeline::PipelineFactory::create_all(
                     device,
-                    surface_format,
+                    HDR_FORMAT,
                     sample_count,
+                    shaders,
+                    &mut pipeline_cache,
                 );
+            let tonemap_pipeline = TonemapPipeline::with_source(
+                device,
+                surface_format,
+                shaders.source(EngineShader::Tonemap),
+            );
+            let gizmo_pipeline = crate::renderer::pipeline::PipelineFactory::create_gizmo(
+                device,
+                HDR_FORMAT,
+                sample_count,
+                shaders,
+                &mut pipeline_cache,
+            );
+            let cull_pipeline = crate::renderer::GpuCullPipeline::new(device);
+            let hiz_pipeline = crate::renderer::HiZPipeline::new(device);
+            let light_cluster_pipeline = crate::renderer::LightClusterPipeline::new(device);
+            let point_shadow_pipeline = crate::renderer::PointShadowPipeline::new(device);
+            let fxaa_pipeline = crate::renderer::FxaaPipeline::with_source(
+                device,
+                shaders.source(EngineShader::Fxaa),
+            );
+            let taa_pipeline = crate::renderer::TaaPipeline::with_source(
+                device,
+                shaders.source(EngineShader::Taa),
+            );
+            let motion_blur_pipeline = crate::renderer::MotionBlurPipeline::with_source(
+                device,
+                shaders.source(EngineShader::MotionBlur),
+            );
+            // The occlusion prepass has its own single-sampled depth target, independent of MSAA
+            let depth_prepass_pipeline = crate::renderer::DepthPrepassPipeline::new(device, 1);
             let gpu_mesh_cache = GpuMeshCache::new();
 
-            let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Camera Bind Group"),
-                layout: &mesh_pipeline.camera_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: renderer.camera_buffer().as_entire_binding(),
-                }],
-            });
-
-            renderer.set_camera_bind_group(camera_bind_group);
+            renderer.create_camera_bind_groups(&mesh_pipeline.camera_bind_group_layout);
 
             let mut render_graph = RenderGraph::new();
+            render_graph.add_node(Box::new(crate::renderer::GpuCullNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::OcclusionCullNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::LightClusterNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::PointShadowNode::new()));
             render_graph.add_node(Box::new(MainPassNode::new()));
             render_graph.add_node(Box::new(WireframePassNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::WaterPassNode::new()));
+            render_graph.add_node(Box::new(GizmoPassNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::TaaNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::MotionBlurNode::new()));
+            render_graph.add_node(Box::new(crate::renderer::FxaaNode::new()));
+            render_graph.add_node(Box::new(TonemapNode::new()));
+            render_graph.add_node(Box::new(ScreenshotNode::new()));
 
             world.insert_resource(renderer);
             world.insert_resource(mesh_pipeline);
             world.insert_resource(wireframe_pipeline);
+            world.insert_resource(tonemap_pipeline);
+            world.insert_resource(gizmo_pipeline);
+            world.insert_resource(cull_pipeline);
+            world.insert_resource(hiz_pipeline);
+            world.insert_resource(light_cluster_pipeline);
+            world.insert_resource(point_shadow_pipeline);
+            world.insert_resource(fxaa_pipeline);
+            world.insert_resource(taa_pipeline);
+            world.insert_resource(motion_blur_pipeline);
+            world.insert_resource(depth_prepass_pipeline);
             world.insert_resource(gpu_mesh_cache);
+            world.insert_resource(pipeline_cache);
             world.insert_resource(render_graph);
 
 
@@ -161,18 +348,7 @@ fn recreate_camera_bind_group(world: &mut bevy_ecs::prelude::World) {
         }
 
         let pipeline = world.get_resource::<MeshPipeline>().unwrap();
-        let device = renderer.device();
-
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &pipeline.camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: renderer.camera_buffer().as_entire_binding(),
-            }],
-        });
-
-        renderer.set_camera_bind_group(camera_bind_group);
+        renderer.create_camera_bind_groups(&pipeline.camera_bind_group_layout);
     });
 }
 
@@ -190,45 +366,77 @@ fn update_graphics_settings(world: &mut bevy_ecs::prelude::World) {
 
     let sample_count = graphics_settings.msaa_sample_count().as_u32();
     let vsync_enabled = graphics_settings.vsync_enabled();
-    drop(graphics_settings);
+    let frames_in_flight = graphics_settings.frames_in_flight();
+    let anti_aliasing = graphics_settings.anti_aliasing();
+    let motion_blur = graphics_settings.motion_blur() > 0.0;
 
     world.resource_scope(|world, mut renderer: bevy_ecs::prelude::Mut<Renderer>| {
         renderer.update_vsync(vsync_enabled);
         renderer.update_msaa_settings(sample_count);
+        renderer.set_frames_in_flight(frames_in_flight);
+        renderer.update_post_processing(anti_aliasing, motion_blur);
 
         let device = renderer.device();
-        let surface_format = renderer.config().format;
+        let mut pipeline_cache = world
+            .remove_resource::<PipelineCache>()
+            .unwrap_or_else(|| PipelineCache::new(device, renderer.adapter_info(), None));
+        // Rebuilt from the registry so hot reloaded shaders survive settings changes
+        let shaders = world.resource::<ShaderRegistry>();
 
         let (mesh_pipeline, wireframe_pipeline) =
             crate::renderer::pipeline::PipelineFactory::create_all(
                 device,
-                surface_format,
+                HDR_FORMAT,
                 sample_count,
+                shaders,
+                &mut pipeline_cache,
             );
 
+        let gizmo_pipeline = crate::renderer::pipeline::PipelineFactory::create_gizmo(
+            device,
+            HDR_FORMAT,
+            sample_count,
+            shaders,
+            &mut pipeline_cache,
+        );
+
+        // Changing the frames in flight count drops the per-frame camera bind groups
+        if !renderer.has_camera_bind_group() {
+            renderer.create_camera_bind_groups(&mesh_pipeline.camera_bind_group_layout);
+        }
+
         world.insert_resource(mesh_pipeline);
         world.insert_resource(wireframe_pipeline);
+        world.insert_resource(gizmo_pipeline);
+        world.insert_resource(pipeline_cache);
     });
+
+    crate::renderer::extension::notify_settings_changed(world);
 }
 
-fn submit_gpu_work(world: &mut bevy_ecs::prelude::World) {
-    if let Some(renderer) = world.get_resource::<Renderer>() {
-        // Submit all queued GPU work before Render stage starts
-        // This is critical for proper synchronization of buffer writes from prepare_indirect_draw_data
-        // which uses queue.write_buffer() and must complete before render uses those buffers
-        let device = renderer.device();
-        let queue = renderer.queue();
+fn wait_for_frame_slot(world: &mut bevy_ecs::prelude::World) {
+    let Some(mut renderer) = world.get_resource_mut::<Renderer>() else {
+        return;
+    };
 
-        // Create an empty command encoder and submit it
-        // This flushes all pending write_buffer operations to the GPU
-        let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("GPU Work Flush"),
-        });
-        queue.submit(std::iter::once(encoder.finish()));
+    // Don't write this frame's data until the GPU is done with the frame that last used the slot
+    let waited = renderer.begin_frame();
+    if let Some(mut profiler) = world.get_resource_mut::<crate::core::Profiler>() {
+        profiler.record_timing("Render::FrameWait", waited);
+    }
+}
 
-        // CRITICAL: In wgpu, queue.submit() queues work asynchronously
-        // The empty encoder submit ensures pending write_buffer calls are flushed to GPU
+fn submit_gpu_work(world: &mut bevy_ecs::prelude::World) {
+    if world.get_resource::<Renderer>().is_none() {
+        return;
     }
+
+    // Submit the lighting and draw data uploads recorded during PostUpdate so the copies
+    // overlap with the rest of the frame instead of delaying the render submission
+    world.resource_scope(|world, mut uploader: bevy_ecs::prelude::Mut<GpuUploader>| {
+        let renderer = world.get_resource::<Renderer>().unwrap();
+        uploader.submit(renderer.device(), renderer.queue());
+    });
 }
 
 fn render_system(world: &mut bevy_ecs::prelude::World) {
@@ -239,9 +447,12 @@ fn render_system(world: &mut bevy_ecs::prelude::World) {
     world.resource_scope(
         |world, mut render_graph: bevy_ecs::prelude::Mut<RenderGraph>| {
             world.resource_scope(|world, mut renderer: bevy_ecs::prelude::Mut<Renderer>| {
+                // wait_for_frame_slot freed this frame's slot at the start of PostUpdate
                 if let Err(e) = render_graph.execute(world, &mut renderer) {
                     log::error!("Failed to render frame: {}", e);
                 }
+
+                renderer.end_frame();
             });
         },
     );
@@ -0,0 +1,356 @@
+//! Procedural meshes for prototyping, debug shapes and simple props.
+//!
+//! Every shape is centered on the origin with Y up, white, and wound counter-clockwise like
+//! loaded models. Put them in the asset cache to get a handle for [`Mesh`](super::Mesh):
+//!
+//! ```ignore
+//! let sphere = assets.cache().insert(
+//!     "primitives/sphere",
+//!     vec![primitives::sphere(0.5, 32, 16)],
+//!     CachePolicy::Strong,
+//! );
+//! commands.spawn((Mesh::new(sphere), Transform::default()));
+//! ```
+//!
+//! Resolution arguments are clamped to the fewest segments that still form the shape.
+
+use crate::assets::MeshData;
+use crate::core::math::*;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// Point of a profile revolved around the Y axis, in (distance from the axis, height).
+struct ProfilePoint {
+    position: Vec2,
+    normal: Vec2,
+    /// Texture V, 0 at the top.
+    v: f32,
+}
+
+fn push_vertex(mesh: &mut MeshData, position: Vec3, normal: Vec3, uv: Vec2) {
+    mesh.positions.push(position);
+    mesh.normals.push(normal);
+    mesh.uvs.push(uv);
+    mesh.colors.push(Vec3::ONE);
+    mesh.ao_values.push(1.0);
+}
+
+/// Sweeps `profile`, ordered top to bottom, around the Y axis in `sectors` steps. Texture U
+/// wraps once around, starting and ending at a seam on +Z.
+fn revolve(mesh: &mut MeshData, profile: &[ProfilePoint], sectors: u32) {
+    let base = mesh.positions.len() as u32;
+    let ring = sectors + 1;
+    for point in profile {
+        for sector in 0..ring {
+            let u = sector as f32 / sectors as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            push_vertex(
+                mesh,
+                Vec3::new(
+                    point.position.x * sin,
+                    point.position.y,
+                    point.position.x * cos,
+                ),
+                Vec3::new(point.normal.x * sin, point.normal.y, point.normal.x * cos).normalize(),
+                Vec2::new(u, point.v),
+            );
+        }
+    }
+
+    for (row, pair) in profile.windows(2).enumerate() {
+        let top = base + row as u32 * ring;
+        let bottom = top + ring;
+        for sector in 0..sectors {
+            // Triangles collapsing onto a pole are left out
+            if pair[1].position.x > 0.0 {
+                mesh.indices
+                    .extend([top + sector, bottom + sector, bottom + sector + 1]);
+            }
+            if pair[0].position.x > 0.0 {
+                mesh.indices
+                    .extend([top + sector, bottom + sector + 1, top + sector + 1]);
+            }
+        }
+    }
+}
+
+/// Flat disk at `height` facing up or down, textured with a circle inscribed in the texture.
+fn disk(mesh: &mut MeshData, radius: f32, height: f32, up: bool, sectors: u32) {
+    let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+    let center = mesh.positions.len() as u32;
+    push_vertex(mesh, Vec3::new(0.0, height, 0.0), normal, Vec2::splat(0.5));
+    for sector in 0..=sectors {
+        let (sin, cos) = (sector as f32 / sectors as f32 * TAU).sin_cos();
+        push_vertex(
+            mesh,
+            Vec3::new(radius * sin, height, radius * cos),
+            normal,
+            Vec2::new(0.5 + sin * 0.5, 0.5 + if up { cos } else { -cos } * 0.5),
+        );
+    }
+    for sector in 0..sectors {
+        let (a, b) = (center + 1 + sector, center + 2 + sector);
+        mesh.indices
+            .extend(if up { [center, a, b] } else { [center, b, a] });
+    }
+}
+
+/// Profile of a circular arc of `radius` centered at `height`, from `from` to `to` radians
+/// measured from the top. `v` maps the arc onto `v_from..v_to`.
+fn arc(
+    radius: f32,
+    height: f32,
+    (from, to): (f32, f32),
+    (v_from, v_to): (f32, f32),
+    steps: u32,
+) -> impl Iterator<Item = ProfilePoint> {
+    (0..=steps).map(move |step| {
+        let t = step as f32 / steps as f32;
+        let (sin, cos) = (from + (to - from) * t).sin_cos();
+        // sin(PI) isn't exactly zero, and the bottom pole must land on the axis
+        let sin = if sin.abs() < 1e-6 { 0.0 } else { sin };
+        ProfilePoint {
+            position: Vec2::new(radius * sin, height + radius * cos),
+            normal: Vec2::new(sin, cos),
+            v: v_from + (v_to - v_from) * t,
+        }
+    })
+}
+
+/// UV sphere with `sectors` slices around and `stacks` bands from pole to pole.
+pub fn sphere(radius: f32, sectors: u32, stacks: u32) -> MeshData {
+    let mut mesh = MeshData::new();
+    let profile: Vec<_> = arc(radius, 0.0, (0.0, PI), (0.0, 1.0), stacks.max(2)).collect();
+    revolve(&mut mesh, &profile, sectors.max(3));
+    mesh
+}
+
+/// Cylinder of `height` between the centers of its two hemispherical ends, matching a
+/// physics capsule collider. Each end has `rings` bands.
+pub fn capsule(radius: f32, height: f32, sectors: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let half = height * 0.5;
+    // V follows the length of the outline so the texture isn't stretched along the body
+    let length = PI * radius + height;
+    let cap = FRAC_PI_2 * radius / length;
+
+    let mut profile: Vec<_> = arc(radius, half, (0.0, FRAC_PI_2), (0.0, cap), rings).collect();
+    profile.extend(arc(radius, -half, (FRAC_PI_2, PI), (1.0 - cap, 1.0), rings));
+
+    let mut mesh = MeshData::new();
+    revolve(&mut mesh, &profile, sectors.max(3));
+    mesh
+}
+
+pub fn cylinder(radius: f32, height: f32, sectors: u32) -> MeshData {
+    let sectors = sectors.max(3);
+    let half = height * 0.5;
+    let profile = [
+        ProfilePoint {
+            position: Vec2::new(radius, half),
+            normal: Vec2::X,
+            v: 0.0,
+        },
+        ProfilePoint {
+            position: Vec2::new(radius, -half),
+            normal: Vec2::X,
+            v: 1.0,
+        },
+    ];
+
+    let mut mesh = MeshData::new();
+    revolve(&mut mesh, &profile, sectors);
+    disk(&mut mesh, radius, half, true, sectors);
+    disk(&mut mesh, radius, -half, false, sectors);
+    mesh
+}
+
+/// Cone with its apex `height` above its base, centered halfway between them.
+pub fn cone(radius: f32, height: f32, sectors: u32) -> MeshData {
+    let sectors = sectors.max(3);
+    let half = height * 0.5;
+    let normal = Vec2::new(height, radius).normalize();
+    let profile = [
+        ProfilePoint {
+            position: Vec2::new(0.0, half),
+            normal,
+            v: 0.0,
+        },
+        ProfilePoint {
+            position: Vec2::new(radius, -half),
+            normal,
+            v: 1.0,
+        },
+    ];
+
+    let mut mesh = MeshData::new();
+    revolve(&mut mesh, &profile, sectors);
+    disk(&mut mesh, radius, -half, false, sectors);
+    mesh
+}
+
+/// Ring lying in the XZ plane. `major_radius` reaches the center of the tube, which is
+/// `minor_radius` thick and made of `minor_segments` sides.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> MeshData {
+    let minor_segments = minor_segments.max(3);
+    // Starts on the outer equator heading down, so the tube's outside faces outward
+    let profile: Vec<_> = (0..=minor_segments)
+        .map(|segment| {
+            let v = segment as f32 / minor_segments as f32;
+            let (sin, cos) = (v * TAU).sin_cos();
+            ProfilePoint {
+                position: Vec2::new(major_radius + minor_radius * cos, -minor_radius * sin),
+                normal: Vec2::new(cos, -sin),
+                v,
+            }
+        })
+        .collect();
+
+    let mut mesh = MeshData::new();
+    revolve(&mut mesh, &profile, major_segments.max(3));
+    mesh
+}
+
+/// Plane in XZ facing up, `size` wide along X and Z, split into `subdivisions` quads along
+/// each side. The texture covers it once.
+pub fn plane(size: Vec2, subdivisions: u32) -> MeshData {
+    let quads = subdivisions.max(1);
+    let row = quads + 1;
+
+    let mut mesh = MeshData::new();
+    for z in 0..row {
+        for x in 0..row {
+            let uv = Vec2::new(x as f32, z as f32) / quads as f32;
+            let position = (uv - 0.5) * size;
+            push_vertex(
+                &mut mesh,
+                Vec3::new(position.x, 0.0, position.y),
+                Vec3::Y,
+                uv,
+            );
+        }
+    }
+    for z in 0..quads {
+        for x in 0..quads {
+            let i = z * row + x;
+            mesh.indices
+                .extend([i, i + row, i + row + 1, i, i + row + 1, i + 1]);
+        }
+    }
+    mesh
+}
+
+/// Box with `size` extents, each face textured once.
+pub fn cuboid(size: Vec3) -> MeshData {
+    let mut mesh = MeshData::new();
+    for normal in [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ] {
+        // Two axes spanning the face, ordered for counter-clockwise winding seen from outside
+        let tangent = if normal.y.abs() > 0.5 {
+            Vec3::X
+        } else {
+            Vec3::Y.cross(normal)
+        };
+        let bitangent = normal.cross(tangent);
+
+        let base = mesh.positions.len() as u32;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let corner = normal + tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0);
+            push_vertex(
+                &mut mesh,
+                corner * size * 0.5,
+                normal,
+                Vec2::new(u, 1.0 - v),
+            );
+        }
+        mesh.indices
+            .extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    mesh
+}
+
+/// Per-vertex tangents for normal mapping: xyz points along increasing texture U,
+/// perpendicular to the normal, and w is the sign to multiply `cross(normal, tangent)` by
+/// for the direction of increasing V.
+pub fn tangents(mesh: &MeshData) -> Vec<Vec4> {
+    let mut u_directions = vec![Vec3::ZERO; mesh.positions.len()];
+    let mut v_directions = vec![Vec3::ZERO; mesh.positions.len()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let (edge1, edge2) = (
+            mesh.positions[b] - mesh.positions[a],
+            mesh.positions[c] - mesh.positions[a],
+        );
+        let (duv1, duv2) = (mesh.uvs[b] - mesh.uvs[a], mesh.uvs[c] - mesh.uvs[a]);
+        let determinant = duv1.perp_dot(duv2);
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+        let u_direction = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+        let v_direction = (edge2 * duv1.x - edge1 * duv2.x) / determinant;
+        for vertex in [a, b, c] {
+            u_directions[vertex] += u_direction;
+            v_directions[vertex] += v_direction;
+        }
+    }
+
+    mesh.normals
+        .iter()
+        .zip(u_directions.iter().zip(&v_directions))
+        .map(|(&normal, (&u_direction, &v_direction))| {
+            let tangent = (u_direction - normal * normal.dot(u_direction))
+                .try_normalize()
+                .unwrap_or_else(|| normal.any_orthonormal_vector());
+            let handedness = if normal.cross(tangent).dot(v_direction) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            tangent.extend(handedness)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitives_face_outward() {
+        let shapes = [
+            sphere(1.0, 16, 8),
+            capsule(0.5, 1.0, 16, 4),
+            cylinder(1.0, 2.0, 16),
+            cone(1.0, 2.0, 16),
+            torus(1.0, 0.25, 24, 12),
+            plane(Vec2::splat(2.0), 4),
+            cuboid(Vec3::new(1.0, 2.0, 3.0)),
+        ];
+
+        for mesh in &shapes {
+            assert_eq!(mesh.normals.len(), mesh.positions.len());
+            assert_eq!(mesh.uvs.len(), mesh.positions.len());
+            for triangle in mesh.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+                let face_normal = (b - a).cross(c - a);
+                assert!(face_normal.length() > 0.0);
+                let normal = mesh.normals[triangle[0] as usize];
+                assert!(face_normal.dot(normal) > 0.0);
+            }
+        }
+
+        for (vertex, tangent) in tangents(&shapes[0]).iter().enumerate() {
+            assert!(tangent.truncate().dot(shapes[0].normals[vertex]).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Offscreen render targets for cameras that don't draw to the window.
+
+use crate::renderer::{HDR_FORMAT, RenderTarget, Renderer};
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use wgpu::{BindGroup, BindGroupLayout, Device, Sampler, Texture, TextureView};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RenderTargetId(u32);
+
+/// An offscreen target that is both rendered into by a camera and sampled by materials,
+/// e.g. for mirrors, portals and in-world screens.
+///
+/// ```ignore
+/// let screen = RenderTexture::new(&mut render_targets, 512, 512);
+/// commands.spawn((Camera::default().with_target(screen.into()).with_priority(-1), transform));
+///
+/// // In a render node, after the main pass has drawn the cameras:
+/// if let Some(bind_group) = render_targets.sample_bind_group(screen.id()) {
+///     pass.set_bind_group(2, bind_group, &[]);
+/// }
+/// ```
+///
+/// Give the camera a lower priority than the cameras whose materials sample it, so
+/// the texture is drawn first within the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTexture {
+    id: RenderTargetId,
+}
+
+impl RenderTexture {
+    pub fn new(targets: &mut RenderTargets, width: u32, height: u32) -> Self {
+        Self {
+            id: targets.create(width, height),
+        }
+    }
+
+    pub fn id(&self) -> RenderTargetId {
+        self.id
+    }
+}
+
+impl From<RenderTexture> for RenderTarget {
+    fn from(texture: RenderTexture) -> Self {
+        RenderTarget::Texture(texture.id)
+    }
+}
+
+/// GPU attachments of an offscreen target. The resolved color is in [`HDR_FORMAT`].
+pub struct TargetTextures {
+    pub color: Texture,
+    pub color_view: TextureView,
+    pub depth_view: TextureView,
+    pub msaa_color_view: Option<TextureView>,
+    pub msaa_depth_view: Option<TextureView>,
+    /// Linear clamped sampler for reading the resolved color.
+    pub sampler: Sampler,
+    /// `color_view` and `sampler` bound with [`RenderTargets::sample_layout`].
+    pub sample_bind_group: BindGroup,
+    sample_count: u32,
+}
+
+impl TargetTextures {
+    fn new(
+        device: &Device,
+        size: (u32, u32),
+        sample_count: u32,
+        sample_layout: &BindGroupLayout,
+    ) -> Self {
+        let (width, height) = size;
+        let color = Renderer::create_hdr_texture(device, width, height);
+        let color_view = color.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = Renderer::create_depth_texture(device, width, height)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let (msaa_color_view, msaa_depth_view) = if sample_count > 1 {
+            let color = Renderer::create_msaa_color_texture(
+                device,
+                width,
+                height,
+                HDR_FORMAT,
+                sample_count,
+            );
+            let depth = Renderer::create_msaa_depth_texture(device, width, height, sample_count);
+            (
+                Some(color.create_view(&wgpu::TextureViewDescriptor::default())),
+                Some(depth.create_view(&wgpu::TextureViewDescriptor::default())),
+            )
+        } else {
+            (None, None)
+        };
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Render Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sample_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Render Texture Bind Group"),
+            layout: sample_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            color,
+            color_view,
+            depth_view,
+            msaa_color_view,
+            msaa_depth_view,
+            sampler,
+            sample_bind_group,
+            sample_count,
+        }
+    }
+}
+
+struct OffscreenTarget {
+    size: (u32, u32),
+    textures: Option<TargetTextures>,
+}
+
+/// Offscreen targets cameras can render into with [`RenderTarget::Texture`](crate::renderer::RenderTarget::Texture).
+///
+/// Textures are allocated on first use and recreated when the target is resized
+/// or the MSAA sample count changes.
+#[derive(Resource, Default)]
+pub struct RenderTargets {
+    next_id: u32,
+    targets: HashMap<RenderTargetId, OffscreenTarget>,
+    sample_layout: Option<BindGroupLayout>,
+}
+
+impl RenderTargets {
+    pub fn create(&mut self, width: u32, height: u32) -> RenderTargetId {
+        let id = RenderTargetId(self.next_id);
+        self.next_id += 1;
+        self.targets.insert(
+            id,
+            OffscreenTarget {
+                size: (width.max(1), height.max(1)),
+                textures: None,
+            },
+        );
+        id
+    }
+
+    pub fn resize(&mut self, id: RenderTargetId, width: u32, height: u32) {
+        if let Some(target) = self.targets.get_mut(&id) {
+            let size = (width.max(1), height.max(1));
+            if target.size != size {
+                target.size = size;
+                target.textures = None;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: RenderTargetId) {
+        self.targets.remove(&id);
+    }
+
+    pub fn size(&self, id: RenderTargetId) -> Option<(u32, u32)> {
+        self.targets.get(&id).map(|target| target.size)
+    }
+
+    /// Attachments of a target, `None` until it has been rendered to once.
+    pub fn textures(&self, id: RenderTargetId) -> Option<&TargetTextures> {
+        self.targets.get(&id)?.textures.as_ref()
+    }
+
+    /// Layout for sampling a target's color: a filterable float texture at binding 0
+    /// and a filtering sampler at binding 1, both visible to the fragment stage.
+    ///
+    /// `None` until the main pass has prepared the targets once.
+    pub fn sample_layout(&self) -> Option<&BindGroupLayout> {
+        self.sample_layout.as_ref()
+    }
+
+    /// Bind group for sampling a target's color with [`sample_layout`](Self::sample_layout).
+    ///
+    /// The bind group is replaced when the target is resized, so look it up every frame.
+    pub fn sample_bind_group(&self, id: RenderTargetId) -> Option<&BindGroup> {
+        self.textures(id)
+            .map(|textures| &textures.sample_bind_group)
+    }
+
+    /// Allocates missing attachments and recreates those with a stale sample count.
+    pub(crate) fn prepare(&mut self, device: &Device, sample_count: u32) {
+        let sample_layout = self
+            .sample_layout
+            .get_or_insert_with(|| create_sample_layout(device));
+
+        for target in self.targets.values_mut() {
+            let stale = target
+                .textures
+                .as_ref()
+                .is_none_or(|textures| textures.sample_count != sample_count);
+            if stale {
+                target.textures = Some(TargetTextures::new(
+                    device,
+                    target.size,
+                    sample_count,
+                    sample_layout,
+                ));
+            }
+        }
+    }
+}
+
+fn create_sample_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Render Texture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
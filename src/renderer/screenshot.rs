@@ -0,0 +1,178 @@
+//! Frame capture.
+//!
+//! Insert a [`ScreenshotRequest`] and [`ScreenshotNode`](crate::renderer::ScreenshotNode)
+//! copies the HDR target at the end of that frame. Readback, tonemapping and PNG
+//! encoding happen over the following frames without stalling rendering, and the
+//! result arrives as a [`ScreenshotCaptured`] message.
+
+use crate::renderer::{GraphicsSettings, Renderer, capture};
+use bevy_ecs::prelude::*;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Bytes per pixel of [`HDR_FORMAT`](crate::renderer::HDR_FORMAT).
+pub(crate) const HDR_BYTES_PER_PIXEL: u32 = 8;
+
+/// Insert to capture the current frame. Removed once the copy has been recorded.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ScreenshotRequest {
+    /// PNG file to write. The capture is always sent as [`ScreenshotCaptured`] as well.
+    pub path: Option<PathBuf>,
+}
+
+impl ScreenshotRequest {
+    pub fn save(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Capture without writing a file, e.g. for comparing against a reference image in tests.
+    pub fn in_memory() -> Self {
+        Self { path: None }
+    }
+}
+
+/// A finished capture.
+#[derive(Message, Clone)]
+pub struct ScreenshotCaptured {
+    pub width: u32,
+    pub height: u32,
+    /// Tonemapped sRGB RGBA8 pixels, row by row from the top-left.
+    pub pixels: Arc<Vec<u8>>,
+    /// File the capture was written to, if requested and the write succeeded.
+    pub path: Option<PathBuf>,
+}
+
+struct PendingScreenshot {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    path: Option<PathBuf>,
+    settings: GraphicsSettings,
+    /// Set by the map callback: `true` once mapped, `false` if mapping failed.
+    mapped: Option<Arc<OnceLock<bool>>>,
+}
+
+/// Captures whose readback or encoding hasn't finished yet.
+#[derive(Resource, Default)]
+pub(crate) struct PendingScreenshots {
+    in_flight: Vec<PendingScreenshot>,
+    finished: Arc<Mutex<Vec<ScreenshotCaptured>>>,
+}
+
+impl PendingScreenshots {
+    pub(crate) fn push(
+        &mut self,
+        buffer: wgpu::Buffer,
+        width: u32,
+        height: u32,
+        request: ScreenshotRequest,
+        settings: GraphicsSettings,
+    ) {
+        self.in_flight.push(PendingScreenshot {
+            buffer,
+            width,
+            height,
+            path: request.path,
+            settings,
+            mapped: None,
+        });
+    }
+}
+
+/// Maps finished readback buffers and hands them to a worker thread for encoding.
+///
+/// Runs after the render stage so the copy recorded by the screenshot node has been submitted.
+pub fn process_screenshots(world: &mut World) {
+    if !world.contains_resource::<PendingScreenshots>() {
+        return;
+    }
+
+    world.resource_scope(|world, mut pending: Mut<PendingScreenshots>| {
+        let renderer = world
+            .get_resource::<Renderer>()
+            .filter(|_| !pending.in_flight.is_empty());
+        if let Some(renderer) = renderer {
+            poll_in_flight(&mut pending, renderer.device());
+        }
+
+        let finished = std::mem::take(&mut *pending.finished.lock().unwrap());
+        for screenshot in finished {
+            world.write_message(screenshot);
+        }
+    });
+}
+
+fn poll_in_flight(pending: &mut PendingScreenshots, device: &wgpu::Device) {
+    for screenshot in &mut pending.in_flight {
+        if screenshot.mapped.is_none() {
+            let mapped = Arc::new(OnceLock::new());
+            let result = Arc::clone(&mapped);
+            screenshot
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |r| {
+                    let _ = result.set(r.is_ok());
+                });
+            screenshot.mapped = Some(mapped);
+        }
+    }
+
+    capture::poll_gpu(device);
+
+    let finished = Arc::clone(&pending.finished);
+    pending.in_flight.retain(|screenshot| {
+        match screenshot.mapped.as_ref().and_then(|mapped| mapped.get()) {
+            None => true,
+            Some(false) => {
+                log::error!("Failed to map screenshot readback buffer");
+                false
+            }
+            Some(true) => {
+                let hdr = capture::take_mapped_pixels(
+                    &screenshot.buffer,
+                    screenshot.width,
+                    screenshot.height,
+                    HDR_BYTES_PER_PIXEL,
+                );
+                encode_in_background(hdr, screenshot, Arc::clone(&finished));
+                false
+            }
+        }
+    });
+}
+
+fn encode_in_background(
+    hdr: Vec<u8>,
+    screenshot: &PendingScreenshot,
+    finished: Arc<Mutex<Vec<ScreenshotCaptured>>>,
+) {
+    let (width, height) = (screenshot.width, screenshot.height);
+    let path = screenshot.path.clone();
+    let settings = screenshot.settings.clone();
+
+    std::thread::spawn(move || {
+        let pixels = capture::tonemap_to_rgba8(&hdr, &settings);
+
+        let path = path.filter(|path| {
+            match image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8) {
+                Ok(()) => {
+                    log::info!("Saved screenshot {}", path.display());
+                    true
+                }
+                Err(e) => {
+                    log::error!("Failed to save screenshot {}: {}", path.display(), e);
+                    false
+                }
+            }
+        });
+
+        finished.lock().unwrap().push(ScreenshotCaptured {
+            width,
+            height,
+            pixels: Arc::new(pixels),
+            path,
+        });
+    });
+}
@@ -0,0 +1,242 @@
+//! Hot reloading of the engine's render shaders.
+//!
+//! Render pipelines are built from WGSL embedded with `include_str!`. Pointing an
+//! [`EngineShader`] at a file with [`ShaderRegistry::watch`] makes the renderer build that
+//! pipeline from the file instead, and rebuild it whenever the file changes:
+//!
+//! ```ignore
+//! fn setup(mut shaders: ResMut<ShaderRegistry>) {
+//!     shaders.watch(EngineShader::Tonemap, "src/renderer/shaders/tonemap.wgsl");
+//! }
+//! ```
+//!
+//! A file that fails to compile, or no longer matches the pipeline's bind group layouts, is
+//! logged and the previous pipeline keeps rendering until the file is fixed. Compute
+//! shaders and the water pass are not covered yet.
+
+use crate::assets::{AssetId, AssetLoader, WgslLoader};
+use crate::renderer::{
+    FxaaPipeline, GizmoPipeline, GraphicsSettings, HDR_FORMAT, MeshPipeline, MotionBlurPipeline,
+    Renderer, TaaPipeline, TonemapPipeline, WireframePipeline,
+};
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A shader the renderer builds a render pipeline from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngineShader {
+    Mesh,
+    Wireframe,
+    Gizmo,
+    Tonemap,
+    Fxaa,
+    Taa,
+    MotionBlur,
+}
+
+impl EngineShader {
+    pub const ALL: [Self; 7] = [
+        Self::Mesh,
+        Self::Wireframe,
+        Self::Gizmo,
+        Self::Tonemap,
+        Self::Fxaa,
+        Self::Taa,
+        Self::MotionBlur,
+    ];
+
+    /// The WGSL compiled into the engine.
+    pub fn embedded_source(self) -> &'static str {
+        match self {
+            Self::Mesh => include_str!("shaders/mesh.wgsl"),
+            Self::Wireframe => include_str!("shaders/wireframe.wgsl"),
+            Self::Gizmo => include_str!("shaders/gizmo.wgsl"),
+            Self::Tonemap => include_str!("shaders/tonemap.wgsl"),
+            Self::Fxaa => include_str!("shaders/fxaa.wgsl"),
+            Self::Taa => include_str!("shaders/taa.wgsl"),
+            Self::MotionBlur => include_str!("shaders/motion_blur.wgsl"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mesh => "mesh",
+            Self::Wireframe => "wireframe",
+            Self::Gizmo => "gizmo",
+            Self::Tonemap => "tonemap",
+            Self::Fxaa => "fxaa",
+            Self::Taa => "taa",
+            Self::MotionBlur => "motion_blur",
+        }
+    }
+}
+
+struct WatchedShader {
+    handle: AssetId,
+    path: PathBuf,
+    /// Modification time of the last version read, `None` until the first read.
+    modified: Option<SystemTime>,
+    /// Last version a pipeline was built from successfully.
+    source: Option<String>,
+}
+
+/// Shader sources the render pipelines are built from, and the files being watched for them.
+#[derive(Resource, Default)]
+pub struct ShaderRegistry {
+    watched: HashMap<EngineShader, WatchedShader>,
+    last_poll: Option<Instant>,
+}
+
+impl ShaderRegistry {
+    /// Builds `shader`'s pipeline from the WGSL file at `path`, and rebuilds it whenever the
+    /// file changes. Returns the handle the file is tracked under.
+    pub fn watch(&mut self, shader: EngineShader, path: impl Into<PathBuf>) -> AssetId {
+        let path = path.into();
+        let handle = AssetId::from_path(&path.to_string_lossy());
+        self.watched.insert(
+            shader,
+            WatchedShader {
+                handle,
+                path,
+                modified: None,
+                source: None,
+            },
+        );
+        // Read the file on the next update rather than after the poll interval
+        self.last_poll = None;
+        handle
+    }
+
+    pub fn handle(&self, shader: EngineShader) -> Option<AssetId> {
+        self.watched.get(&shader).map(|watched| watched.handle)
+    }
+
+    pub fn is_watched(&self, shader: EngineShader) -> bool {
+        self.watched.contains_key(&shader)
+    }
+
+    /// The source `shader`'s pipeline is built from: the last version of its watched file that
+    /// compiled, or the embedded shader.
+    pub fn source(&self, shader: EngineShader) -> &str {
+        self.watched
+            .get(&shader)
+            .and_then(|watched| watched.source.as_deref())
+            .unwrap_or(shader.embedded_source())
+    }
+
+    /// Reads the watched files modified since they were last read.
+    fn changed_sources(&mut self) -> Vec<(EngineShader, String)> {
+        let mut changed = Vec::new();
+        for (&shader, watched) in &mut self.watched {
+            // Editors may briefly remove the file while saving, so a failed lookup is retried
+            // on the next poll
+            let Ok(modified) = std::fs::metadata(&watched.path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            if watched.modified == Some(modified) {
+                continue;
+            }
+            watched.modified = Some(modified);
+
+            match WgslLoader.load(&watched.path) {
+                Ok(data) => changed.push((shader, data.source)),
+                Err(e) => log::error!(
+                    "Failed to read {} shader from {}: {}",
+                    shader.name(),
+                    watched.path.display(),
+                    e
+                ),
+            }
+        }
+        changed
+    }
+}
+
+/// Rebuilds the pipelines whose watched shader files changed. A shader that fails to
+/// compile leaves the previous pipeline in place.
+pub fn hot_reload_shaders(world: &mut World) {
+    if !world.contains_resource::<Renderer>() {
+        return;
+    }
+    let Some(mut registry) = world.get_resource_mut::<ShaderRegistry>() else {
+        return;
+    };
+    if registry.watched.is_empty()
+        || registry
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL)
+    {
+        return;
+    }
+    registry.last_poll = Some(Instant::now());
+
+    for (shader, source) in registry.changed_sources() {
+        match rebuild_pipeline(world, shader, &source) {
+            Ok(()) => {
+                log::info!("Reloaded {} shader", shader.name());
+                let mut registry = world.resource_mut::<ShaderRegistry>();
+                if let Some(watched) = registry.watched.get_mut(&shader) {
+                    watched.source = Some(source);
+                }
+            }
+            Err(e) => log::error!(
+                "Failed to reload {} shader, keeping the previous pipeline: {}",
+                shader.name(),
+                e
+            ),
+        }
+    }
+}
+
+fn rebuild_pipeline(world: &mut World, shader: EngineShader, source: &str) -> Result<(), String> {
+    let sample_count = world
+        .get_resource::<GraphicsSettings>()
+        .map(|settings| settings.msaa_sample_count().as_u32())
+        .unwrap_or(1);
+    let surface_format = world.resource::<Renderer>().config().format;
+
+    match shader {
+        EngineShader::Mesh => build_pipeline(world, |device| {
+            MeshPipeline::with_source(device, HDR_FORMAT, sample_count, source, None)
+        }),
+        EngineShader::Wireframe => build_pipeline(world, |device| {
+            WireframePipeline::with_source(device, HDR_FORMAT, sample_count, source, None)
+        }),
+        EngineShader::Gizmo => build_pipeline(world, |device| {
+            GizmoPipeline::with_source(device, HDR_FORMAT, sample_count, source, None)
+        }),
+        EngineShader::Tonemap => build_pipeline(world, |device| {
+            TonemapPipeline::with_source(device, surface_format, source)
+        }),
+        EngineShader::Fxaa => {
+            build_pipeline(world, |device| FxaaPipeline::with_source(device, source))
+        }
+        EngineShader::Taa => {
+            build_pipeline(world, |device| TaaPipeline::with_source(device, source))
+        }
+        EngineShader::MotionBlur => build_pipeline(world, |device| {
+            MotionBlurPipeline::with_source(device, source)
+        }),
+    }
+}
+
+/// Builds a pipeline and replaces the current one, unless wgpu reported a validation error.
+fn build_pipeline<T: Resource>(
+    world: &mut World,
+    build: impl FnOnce(&wgpu::Device) -> T,
+) -> Result<(), String> {
+    let device = world.resource::<Renderer>().device();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let pipeline = build(device);
+    if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+        return Err(error.to_string());
+    }
+
+    world.insert_resource(pipeline);
+    Ok(())
+}
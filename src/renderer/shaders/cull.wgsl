@@ -1,47 +1,83 @@
+// Must match MAX_CULL_CAMERAS in systems/draw/gpu_culling.rs
+const MAX_CAMERAS: u32 = 4u;
+const HAS_AABB: u32 = 1u;
+// Instances of meshes that aren't drawn this frame
+const NO_BATCH: u32 = 0xffffffffu;
+
 struct Plane {
     normal: vec3<f32>,
     distance: f32,
 }
 
-struct Frustum {
-    planes: array<Plane, 6>,
+struct CullParams {
+    planes: array<Plane, 24>,
+    camera_count: u32,
+    instance_count: u32,
+    occlusion: u32,
+    _padding: u32,
+    // View-projection the depth pyramid was rendered with
+    view_proj: mat4x4<f32>,
+}
+
+struct Model {
+    model: mat4x4<f32>,
+    normal_matrix: array<vec4<f32>, 3>,
+    color: vec4<f32>,
+    previous_model: mat4x4<f32>,
+}
+
+struct CullInstance {
+    aabb_min: vec3<f32>,
+    batch: u32,
+    aabb_max: vec3<f32>,
+    flags: u32,
 }
 
-struct AABB {
-    min: vec3<f32>,
-    _padding1: f32,
-    max: vec3<f32>,
-    _padding2: f32,
+struct CullBatch {
+    index_count: u32,
+    first_command: u32,
 }
 
-struct EntityData {
-    model_matrix: mat4x4<f32>,
-    aabb: AABB,
-    mesh_id: u32,
-    entity_index: u32,
-    _padding1: u32,
-    _padding2: u32,
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
 }
 
 @group(0) @binding(0)
-var<uniform> frustum: Frustum;
+var<uniform> params: CullParams;
 
 @group(0) @binding(1)
-var<storage, read> entities: array<EntityData>;
+var<storage, read> models: array<Model>;
 
 @group(0) @binding(2)
-var<storage, read_write> visibility: array<u32>;
+var<storage, read> instances: array<CullInstance>;
 
-fn transform_aabb(aabb: AABB, transform: mat4x4<f32>) -> AABB {
+@group(0) @binding(3)
+var<storage, read> batches: array<CullBatch>;
+
+@group(0) @binding(4)
+var<storage, read_write> counters: array<atomic<u32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> commands: array<DrawIndexedIndirect>;
+
+// Max-depth pyramid of the depth prepass, only bound for the occlusion entry point
+@group(1) @binding(0)
+var depth_pyramid: texture_2d<f32>;
+
+fn transform_aabb(aabb_min: vec3<f32>, aabb_max: vec3<f32>, transform: mat4x4<f32>) -> array<vec3<f32>, 2> {
     let corners = array<vec3<f32>, 8>(
-        (transform * vec4<f32>(aabb.min.x, aabb.min.y, aabb.min.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.min.x, aabb.min.y, aabb.max.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.min.x, aabb.max.y, aabb.min.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.min.x, aabb.max.y, aabb.max.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.max.x, aabb.min.y, aabb.min.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.max.x, aabb.min.y, aabb.max.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.max.x, aabb.max.y, aabb.min.z, 1.0)).xyz,
-        (transform * vec4<f32>(aabb.max.x, aabb.max.y, aabb.max.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_min.x, aabb_min.y, aabb_min.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_min.x, aabb_min.y, aabb_max.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_min.x, aabb_max.y, aabb_min.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_min.x, aabb_max.y, aabb_max.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_max.x, aabb_min.y, aabb_min.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_max.x, aabb_min.y, aabb_max.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_max.x, aabb_max.y, aabb_min.z, 1.0)).xyz,
+        (transform * vec4<f32>(aabb_max.x, aabb_max.y, aabb_max.z, 1.0)).xyz,
     );
 
     var min_corner = corners[0];
@@ -52,15 +88,12 @@ fn transform_aabb(aabb: AABB, transform: mat4x4<f32>) -> AABB {
         max_corner = max(max_corner, corners[i]);
     }
 
-    var result: AABB;
-    result.min = min_corner;
-    result.max = max_corner;
-    return result;
+    return array<vec3<f32>, 2>(min_corner, max_corner);
 }
 
-fn frustum_contains_aabb(f: Frustum, aabb_min: vec3<f32>, aabb_max: vec3<f32>) -> bool {
+fn frustum_contains_aabb(camera: u32, aabb_min: vec3<f32>, aabb_max: vec3<f32>) -> bool {
     for (var i = 0u; i < 6u; i++) {
-        let plane = f.planes[i];
+        let plane = params.planes[camera * 6u + i];
 
         let p_vertex = vec3<f32>(
             select(aabb_min.x, aabb_max.x, plane.normal.x >= 0.0),
@@ -76,19 +109,109 @@ fn frustum_contains_aabb(f: Frustum, aabb_min: vec3<f32>, aabb_max: vec3<f32>) -
     return true;
 }
 
-@compute @workgroup_size(64)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let entity_idx = global_id.x;
+// True if the box lies entirely behind the depth pyramid
+fn occluded(aabb_min: vec3<f32>, aabb_max: vec3<f32>) -> bool {
+    var ndc_min = vec3<f32>(1.0e9);
+    var ndc_max = vec3<f32>(-1.0e9);
+
+    for (var i = 0u; i < 8u; i++) {
+        let corner = select(
+            aabb_min,
+            aabb_max,
+            vec3<bool>((i & 1u) != 0u, (i & 2u) != 0u, (i & 4u) != 0u),
+        );
+        let clip = params.view_proj * vec4<f32>(corner, 1.0);
+        // Boxes crossing the near plane can't be tested reliably
+        if clip.w <= 0.0 {
+            return false;
+        }
+        let ndc = clip.xyz / clip.w;
+        ndc_min = min(ndc_min, ndc);
+        ndc_max = max(ndc_max, ndc);
+    }
+
+    let uv_min = clamp(vec2<f32>(ndc_min.x * 0.5 + 0.5, 0.5 - ndc_max.y * 0.5), vec2<f32>(0.0), vec2<f32>(1.0));
+    let uv_max = clamp(vec2<f32>(ndc_max.x * 0.5 + 0.5, 0.5 - ndc_min.y * 0.5), vec2<f32>(0.0), vec2<f32>(1.0));
+
+    // Pick the level where the box covers at most 2x2 texels
+    let extent = (uv_max - uv_min) * vec2<f32>(textureDimensions(depth_pyramid, 0));
+    let level = min(
+        u32(ceil(log2(max(max(extent.x, extent.y), 1.0)))),
+        textureNumLevels(depth_pyramid) - 1u,
+    );
+    let level_size = vec2<i32>(textureDimensions(depth_pyramid, level));
+    let texel_min = clamp(vec2<i32>(uv_min * vec2<f32>(level_size)), vec2<i32>(0), level_size - 1);
+    let texel_max = clamp(vec2<i32>(uv_max * vec2<f32>(level_size)), vec2<i32>(0), level_size - 1);
+
+    let farthest = max(
+        max(
+            textureLoad(depth_pyramid, texel_min, level).r,
+            textureLoad(depth_pyramid, vec2<i32>(texel_max.x, texel_min.y), level).r,
+        ),
+        max(
+            textureLoad(depth_pyramid, vec2<i32>(texel_min.x, texel_max.y), level).r,
+            textureLoad(depth_pyramid, texel_max, level).r,
+        ),
+    );
 
-    if entity_idx >= arrayLength(&entities) {
+    return ndc_min.z > farthest;
+}
+
+fn cull(instance_index: u32, test_occlusion: bool) {
+    if instance_index >= params.instance_count {
+        return;
+    }
+
+    let instance = instances[instance_index];
+    if instance.batch == NO_BATCH {
+        return;
+    }
+
+    // Instances without an AABB, and every instance when no camera is bound, are always drawn
+    var visible = true;
+    if (instance.flags & HAS_AABB) != 0u && params.camera_count != 0u {
+        let world_aabb = transform_aabb(
+            instance.aabb_min,
+            instance.aabb_max,
+            models[instance_index].model,
+        );
+
+        visible = false;
+        for (var camera = 0u; camera < min(params.camera_count, MAX_CAMERAS); camera++) {
+            if frustum_contains_aabb(camera, world_aabb[0], world_aabb[1]) {
+                visible = true;
+                break;
+            }
+        }
+
+        if visible && test_occlusion {
+            visible = !occluded(world_aabb[0], world_aabb[1]);
+        }
+    }
+
+    if !visible {
         return;
     }
 
-    let entity = entities[entity_idx];
+    let batch = batches[instance.batch];
+    let slot = atomicAdd(&counters[instance.batch], 1u);
 
-    let world_aabb = transform_aabb(entity.aabb, entity.model_matrix);
+    var command: DrawIndexedIndirect;
+    command.index_count = batch.index_count;
+    command.instance_count = 1u;
+    command.first_index = 0u;
+    command.base_vertex = 0;
+    command.first_instance = instance_index;
+    commands[batch.first_command + slot] = command;
+}
 
-    let visible = frustum_contains_aabb(frustum, world_aabb.min, world_aabb.max);
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    cull(global_id.x, false);
+}
 
-    visibility[entity_idx] = select(0u, 1u, visible);
+// Second pass after the depth prepass: frustum and occlusion
+@compute @workgroup_size(64)
+fn occlusion(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    cull(global_id.x, params.occlusion != 0u);
 }
@@ -0,0 +1,82 @@
+struct FxaaUniform {
+    inverse_size: vec2<f32>,
+    exposure: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var hdr_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> params: FxaaUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const SPAN_MAX: f32 = 8.0;
+const REDUCE_MUL: f32 = 0.125;
+const REDUCE_MIN: f32 = 0.0078125;
+
+// Fullscreen triangle, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+fn sample_color(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(hdr_texture, hdr_sampler, uv, 0.0).rgb;
+}
+
+// Edges are detected on roughly display-range luma, HDR values would swamp the thresholds
+fn luma(color: vec3<f32>) -> f32 {
+    let l = dot(color * params.exposure, vec3<f32>(0.299, 0.587, 0.114));
+    return l / (1.0 + l);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = params.inverse_size;
+    let uv = in.clip_position.xy * texel;
+
+    let luma_nw = luma(sample_color(uv + vec2<f32>(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample_color(uv + vec2<f32>(1.0, -1.0) * texel));
+    let luma_sw = luma(sample_color(uv + vec2<f32>(-1.0, 1.0) * texel));
+    let luma_se = luma(sample_color(uv + vec2<f32>(1.0, 1.0) * texel));
+    let color_m = sample_color(uv);
+    let luma_m = luma(color_m);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur direction runs along the edge, perpendicular to the luma gradient
+    var dir = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * inverse_dir_min, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let color_a = 0.5 * (
+        sample_color(uv + dir * (1.0 / 3.0 - 0.5)) +
+        sample_color(uv + dir * (2.0 / 3.0 - 0.5))
+    );
+    let color_b = color_a * 0.5 + 0.25 * (
+        sample_color(uv + dir * -0.5) +
+        sample_color(uv + dir * 0.5)
+    );
+
+    // The wider blur crossed another edge, fall back to the narrow one
+    let luma_b = luma(color_b);
+    if luma_b < luma_min || luma_b > luma_max {
+        return vec4<f32>(color_a, 1.0);
+    }
+    return vec4<f32>(color_b, 1.0);
+}
@@ -0,0 +1,47 @@
+// Builds a max-depth pyramid: level 0 is copied from the depth prepass and every
+// further level keeps the farthest depth of the texels it covers.
+
+@group(0) @binding(0)
+var source_depth: texture_depth_2d;
+
+@group(0) @binding(1)
+var destination: texture_storage_2d<r32float, write>;
+
+@group(0) @binding(2)
+var source_level: texture_2d<f32>;
+
+@compute @workgroup_size(8, 8)
+fn copy_depth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(destination);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+
+    let depth = textureLoad(source_depth, vec2<i32>(global_id.xy), 0);
+    textureStore(destination, vec2<i32>(global_id.xy), vec4<f32>(depth, 0.0, 0.0, 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn downsample(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(destination);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+
+    let source_size = vec2<i32>(textureDimensions(source_level));
+    let base = vec2<i32>(global_id.xy) * 2;
+
+    // Odd source sizes fold their last row/column into the last destination texel
+    let extra_x = select(0, 1, (source_size.x & 1) == 1 && global_id.x == size.x - 1u);
+    let extra_y = select(0, 1, (source_size.y & 1) == 1 && global_id.y == size.y - 1u);
+
+    var depth = 0.0;
+    for (var y = 0; y <= 1 + extra_y; y++) {
+        for (var x = 0; x <= 1 + extra_x; x++) {
+            let texel = min(base + vec2<i32>(x, y), source_size - 1);
+            depth = max(depth, textureLoad(source_level, texel, 0).r);
+        }
+    }
+
+    textureStore(destination, vec2<i32>(global_id.xy), vec4<f32>(depth, 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,93 @@
+// Must match MAX_LIGHTS_PER_CLUSTER in lighting/clusters.rs
+const MAX_LIGHTS_PER_CLUSTER: u32 = 64u;
+
+struct ClusterParams {
+    view: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    // x, y: tan of half the horizontal and vertical fov, z: near, w: far
+    projection: vec4<f32>,
+    // Froxels along x, y, z; w: point light count
+    dimensions: vec4<u32>,
+    enabled: u32,
+}
+
+struct PointLight {
+    position: vec3<f32>,
+    intensity: f32,
+    color: vec3<f32>,
+    radius: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> clusters: ClusterParams;
+
+@group(0) @binding(1)
+var<storage, read> point_lights: array<PointLight>;
+
+@group(0) @binding(2)
+var<storage, read_write> cluster_light_counts: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> cluster_light_indices: array<u32>;
+
+// Same as slice_depth_range in lighting/clusters.rs
+fn slice_depth(slice: u32) -> f32 {
+    let near = clusters.projection.z;
+    let far = clusters.projection.w;
+    return near * pow(far / near, f32(slice) / f32(clusters.dimensions.z));
+}
+
+// Corner of a tile at a view-space distance, with ndc in -1..1
+fn view_corner(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    return vec3<f32>(ndc * clusters.projection.xy * depth, -depth);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims = clusters.dimensions.xyz;
+    let cluster = id.x;
+    if cluster >= dims.x * dims.y * dims.z {
+        return;
+    }
+    if clusters.enabled == 0u {
+        cluster_light_counts[cluster] = 0u;
+        return;
+    }
+
+    let x = cluster % dims.x;
+    let y = (cluster / dims.x) % dims.y;
+    let z = cluster / (dims.x * dims.y);
+
+    let ndc_min = vec2<f32>(f32(x), f32(y)) / vec2<f32>(dims.xy) * 2.0 - 1.0;
+    let ndc_max = vec2<f32>(f32(x + 1u), f32(y + 1u)) / vec2<f32>(dims.xy) * 2.0 - 1.0;
+    let depth_near = slice_depth(z);
+    let depth_far = slice_depth(z + 1u);
+
+    // View-space bounds of the froxel
+    var aabb_min = vec3<f32>(1e30);
+    var aabb_max = vec3<f32>(-1e30);
+    for (var i = 0u; i < 8u; i++) {
+        let ndc = vec2<f32>(
+            select(ndc_min.x, ndc_max.x, (i & 1u) != 0u),
+            select(ndc_min.y, ndc_max.y, (i & 2u) != 0u),
+        );
+        let corner = view_corner(ndc, select(depth_near, depth_far, (i & 4u) != 0u));
+        aabb_min = min(aabb_min, corner);
+        aabb_max = max(aabb_max, corner);
+    }
+
+    let light_count = min(clusters.dimensions.w, arrayLength(&point_lights));
+    let first = cluster * MAX_LIGHTS_PER_CLUSTER;
+    var count = 0u;
+    for (var i = 0u; i < light_count && count < MAX_LIGHTS_PER_CLUSTER; i++) {
+        let light = point_lights[i];
+        let center = (clusters.view * vec4<f32>(light.position, 1.0)).xyz;
+        let closest = clamp(center, aabb_min, aabb_max);
+        let offset = center - closest;
+        if dot(offset, offset) <= light.radius * light.radius {
+            cluster_light_indices[first + count] = i;
+            count++;
+        }
+    }
+    cluster_light_counts[cluster] = count;
+}
@@ -1,10 +1,15 @@
 struct CameraUniform {
     view_proj: mat4x4<f32>,
+    // Without the TAA jitter, for motion vectors
+    unjittered_view_proj: mat4x4<f32>,
+    previous_view_proj: mat4x4<f32>,
 }
 
 struct ModelUniform {
     model: mat4x4<f32>,
     normal_matrix: array<vec4<f32>, 3>,  // Changed from mat3x3 to match Rust layout [[f32; 4]; 3]
+    color: vec4<f32>,
+    previous_model: mat4x4<f32>,
 }
 
 struct DirectionalLight {
@@ -19,17 +24,82 @@ struct AmbientLight {
     intensity: f32,
 }
 
+struct PointLight {
+    position: vec3<f32>,
+    intensity: f32,
+    color: vec3<f32>,
+    radius: f32,
+}
+
+struct Fog {
+    color: vec3<f32>,
+    density: f32,
+    start: f32,
+    end: f32,
+    mode: u32,  // 0 = off, 1 = linear, 2 = exp, 3 = exp2
+    _padding: f32,
+}
+
+struct LightProbeGrid {
+    origin: vec3<f32>,
+    // 0 without a valid grid
+    enabled: u32,
+    spacing: vec3<f32>,
+    intensity: f32,
+    dimensions: vec3<u32>,
+    _padding: u32,
+}
+
+// Light arriving from +X, -X, +Y, -Y, +Z and -Z
+struct LightProbe {
+    colors: array<vec4<f32>, 6>,
+}
+
+struct ClusterParams {
+    view: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    // x, y: tan of half the horizontal and vertical fov, z: near, w: far
+    projection: vec4<f32>,
+    // Froxels along x, y, z; w: point light count
+    dimensions: vec4<u32>,
+    enabled: u32,
+}
+
+// Must match MAX_LIGHTS_PER_CLUSTER in lighting/clusters.rs
+const MAX_LIGHTS_PER_CLUSTER: u32 = 64u;
+const NO_CLUSTER: u32 = 0xffffffffu;
+
+struct PointShadow {
+    // View-projection of the +X, -X, +Y, -Y, +Z and -Z faces
+    faces: array<mat4x4<f32>, 6>,
+    // x: fraction of the texture the light renders into, y: depth bias over radius
+    params: vec4<f32>,
+}
+
+struct MorphDelta {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+}
+
+struct MorphParams {
+    vertex_count: u32,
+    // 0 for meshes without morph targets
+    target_count: u32,
+    // Model index of the mesh's first instance; weights start there
+    first_instance: u32,
+    _padding: u32,
+}
+
 struct LightingUniform {
     directional: DirectionalLight,
     ambient: AmbientLight,
     point_light_count: u32,
     ao_mode: u32,
     ao_debug: u32,
-    _padding1: f32,
-    _padding2: vec3<f32>,
-    _padding3: f32,
-    _padding4: vec3<f32>,
-    _padding5: f32,
+    // The first this many point lights have a shadow map in the same slot
+    point_shadow_count: u32,
+    fog: Fog,
+    probes: LightProbeGrid,
 }
 
 @group(0) @binding(0)
@@ -44,6 +114,41 @@ var<storage, read> visibility: array<u32>;
 @group(2) @binding(0)
 var<uniform> lighting: LightingUniform;
 
+@group(2) @binding(1)
+var<storage, read> point_lights: array<PointLight>;
+
+@group(2) @binding(2)
+var<uniform> clusters: ClusterParams;
+
+@group(2) @binding(3)
+var<storage, read> cluster_light_counts: array<u32>;
+
+@group(2) @binding(4)
+var<storage, read> cluster_light_indices: array<u32>;
+
+@group(2) @binding(5)
+var point_shadow_maps: texture_depth_2d_array;
+
+@group(2) @binding(6)
+var point_shadow_sampler: sampler_comparison;
+
+@group(2) @binding(7)
+var<storage, read> point_shadows: array<PointShadow>;
+
+@group(2) @binding(8)
+var<storage, read> light_probes: array<LightProbe>;
+
+// Deltas of every target, target by target
+@group(3) @binding(0)
+var<storage, read> morph_deltas: array<MorphDelta>;
+
+// target_count weights per instance
+@group(3) @binding(1)
+var<storage, read> morph_weights: array<f32>;
+
+@group(3) @binding(2)
+var<uniform> morph: MorphParams;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
@@ -58,10 +163,26 @@ struct VertexOutput {
     @location(1) uv: vec2<f32>,
     @location(2) color: vec3<f32>,
     @location(3) ao: f32,
+    @location(4) world_position: vec3<f32>,
+    @location(5) view_depth: f32,
+    // Unjittered clip positions of this and the previous frame
+    @location(6) current_clip: vec4<f32>,
+    @location(7) previous_clip: vec4<f32>,
+    @location(8) @interpolate(flat) receives_shadows: u32,
+}
+
+struct VelocityOutput {
+    @location(0) color: vec4<f32>,
+    // Screen-space motion since the previous frame in UV units
+    @location(1) velocity: vec2<f32>,
 }
 
 @vertex
-fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+fn vs_main(
+    in: VertexInput,
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
     var out: VertexOutput;
 
     if instance_index < arrayLength(&visibility) && visibility[instance_index] == 0u {
@@ -70,28 +191,77 @@ fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> Ver
         out.uv = vec2<f32>(0.0);
         out.color = vec3<f32>(0.0);
         out.ao = 0.0;
+        out.world_position = vec3<f32>(0.0);
+        out.view_depth = 0.0;
+        out.current_clip = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        out.previous_clip = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        out.receives_shadows = 0u;
         return out;
     }
 
+    var position = in.position;
+    var normal = in.normal;
+    let weight_base = (instance_index - morph.first_instance) * morph.target_count;
+    for (var i = 0u; i < morph.target_count; i++) {
+        let weight = morph_weights[weight_base + i];
+        if weight != 0.0 {
+            let delta = morph_deltas[i * morph.vertex_count + vertex_index];
+            position += weight * delta.position.xyz;
+            normal += weight * delta.normal.xyz;
+        }
+    }
+
     let model = models[instance_index];
-    let world_position = model.model * vec4<f32>(in.position, 1.0);
+    let world_position = model.model * vec4<f32>(position, 1.0);
     out.clip_position = camera.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    // Clip w is the distance along the view direction for perspective projections
+    out.view_depth = out.clip_position.w;
+    out.current_clip = camera.unjittered_view_proj * world_position;
+    out.previous_clip = camera.previous_view_proj * model.previous_model * vec4<f32>(position, 1.0);
 
     // Multiply normal by mat3 stored as array<vec4<f32>, 3>
     out.world_normal = vec3<f32>(
-        dot(model.normal_matrix[0].xyz, in.normal),
-        dot(model.normal_matrix[1].xyz, in.normal),
-        dot(model.normal_matrix[2].xyz, in.normal)
+        dot(model.normal_matrix[0].xyz, normal),
+        dot(model.normal_matrix[1].xyz, normal),
+        dot(model.normal_matrix[2].xyz, normal)
     );
     out.uv = in.uv;
-    out.color = in.color;
+    out.color = in.color * model.color.rgb;
     out.ao = in.ao;
+    // Model alpha flags ShadowReceivers
+    out.receives_shadows = select(0u, 1u, model.color.a > 0.5);
 
     return out;
 }
 
+// Occluder depth for Hi-Z culling, from the undeformed mesh
+@vertex
+fn vs_depth(in: VertexInput, @builtin(instance_index) instance_index: u32) -> @builtin(position) vec4<f32> {
+    if instance_index < arrayLength(&visibility) && visibility[instance_index] == 0u {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+    return camera.view_proj * models[instance_index].model * vec4<f32>(in.position, 1.0);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+// Used for window cameras when a post-process pass needs motion vectors
+@fragment
+fn fs_main_velocity(in: VertexOutput) -> VelocityOutput {
+    var out: VelocityOutput;
+    out.color = shade(in);
+    let current = in.current_clip.xy / in.current_clip.w;
+    let previous = in.previous_clip.xy / in.previous_clip.w;
+    // NDC y points up, UV y points down
+    out.velocity = (current - previous) * vec2<f32>(0.5, -0.5);
+    return out;
+}
+
+fn shade(in: VertexOutput) -> vec4<f32> {
     let normal = normalize(in.world_normal);
 
     // Use vertex AO only (no SSAO)
@@ -101,15 +271,182 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         return vec4<f32>(ao, ao, ao, 1.0);
     }
 
-    let ambient = lighting.ambient.color * lighting.ambient.intensity * ao;
+    // Inside a light probe grid the probes replace the ambient light
+    let probe = probe_irradiance(in.world_position, normal);
+    let ambient = select(lighting.ambient.color * lighting.ambient.intensity, probe.xyz, probe.w > 0.0) * ao;
 
     let light_dir = normalize(-lighting.directional.direction);
     let diffuse_strength = max(dot(normal, light_dir), 0.0);
     let diffuse = lighting.directional.color * lighting.directional.intensity * diffuse_strength;
 
-    let final_lighting = ambient + diffuse;
+    var point_diffuse = vec3<f32>(0.0);
+    let point_count = min(lighting.point_light_count, arrayLength(&point_lights));
+    let shadow_count = select(
+        0u,
+        min(lighting.point_shadow_count, arrayLength(&point_shadows)),
+        in.receives_shadows == 1u,
+    );
+    let cluster = cluster_index(in.world_position);
+    if cluster != NO_CLUSTER {
+        let count = cluster_light_counts[cluster];
+        let first = cluster * MAX_LIGHTS_PER_CLUSTER;
+        for (var i = 0u; i < count; i++) {
+            let index = cluster_light_indices[first + i];
+            if index < point_count {
+                point_diffuse += shadowed_point_light(index, shadow_count, in.world_position, normal);
+            }
+        }
+    } else {
+        // Outside the clustered frustum
+        for (var i = 0u; i < point_count; i++) {
+            point_diffuse += shadowed_point_light(i, shadow_count, in.world_position, normal);
+        }
+    }
+
+    let final_lighting = ambient + diffuse + point_diffuse;
 
-    let color = in.color * final_lighting;
+    let color = mix(in.color * final_lighting, lighting.fog.color, fog_factor(in.view_depth));
 
     return vec4<f32>(color, 1.0);
 }
+
+// Same falloff as PointLight::attenuation on the CPU side
+fn point_light(light: PointLight, world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let to_light = light.position - world_position;
+    let distance = length(to_light);
+    if distance >= light.radius {
+        return vec3<f32>(0.0);
+    }
+
+    let ratio = distance / light.radius;
+    let falloff = max(1.0 - ratio * ratio * ratio * ratio, 0.0);
+    let attenuation = falloff / (1.0 + distance * distance);
+    let n_dot_l = max(dot(normal, to_light / max(distance, 0.0001)), 0.0);
+    return light.color * light.intensity * n_dot_l * attenuation;
+}
+
+fn shadowed_point_light(index: u32, shadow_count: u32, world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = point_lights[index];
+    let lit = point_light(light, world_position, normal);
+    if index >= shadow_count || all(lit == vec3<f32>(0.0)) {
+        return lit;
+    }
+    return lit * point_shadow(index, light, world_position);
+}
+
+// 1 where the light reaches world_position, 0 in shadow. Shadow slots match light indices.
+fn point_shadow(index: u32, light: PointLight, world_position: vec3<f32>) -> f32 {
+    let to_fragment = world_position - light.position;
+    let axis = abs(to_fragment);
+    var face = 0u;
+    if axis.x >= axis.y && axis.x >= axis.z {
+        face = select(1u, 0u, to_fragment.x > 0.0);
+    } else if axis.y >= axis.z {
+        face = select(3u, 2u, to_fragment.y > 0.0);
+    } else {
+        face = select(5u, 4u, to_fragment.z > 0.0);
+    }
+
+    let shadow = point_shadows[index];
+    let clip = shadow.faces[face] * vec4<f32>(world_position, 1.0);
+    let ndc = clip.xy / clip.w;
+    // The light only renders into the top-left part of its layers; stay inside it
+    let half_texel = 0.5 / f32(textureDimensions(point_shadow_maps).x);
+    let uv = clamp(
+        (ndc * vec2<f32>(0.5, -0.5) + 0.5) * shadow.params.x,
+        vec2<f32>(half_texel),
+        vec2<f32>(shadow.params.x - half_texel),
+    );
+    let depth = length(to_fragment) / light.radius - shadow.params.y;
+    return textureSampleCompareLevel(
+        point_shadow_maps,
+        point_shadow_sampler,
+        uv,
+        index * 6u + face,
+        depth,
+    );
+}
+
+fn ambient_cube(index: u32, normal: vec3<f32>) -> vec3<f32> {
+    let probe = light_probes[index];
+    let squared = normal * normal;
+    let x = select(probe.colors[1].xyz, probe.colors[0].xyz, normal.x >= 0.0);
+    let y = select(probe.colors[3].xyz, probe.colors[2].xyz, normal.y >= 0.0);
+    let z = select(probe.colors[5].xyz, probe.colors[4].xyz, normal.z >= 0.0);
+    return x * squared.x + y * squared.y + z * squared.z;
+}
+
+// Blend of the eight probes around world_position, w is 0 outside the grid.
+// Same as LightProbeGrid::sample on the CPU side.
+fn probe_irradiance(world_position: vec3<f32>, normal: vec3<f32>) -> vec4<f32> {
+    let grid = lighting.probes;
+    if grid.enabled == 0u {
+        return vec4<f32>(0.0);
+    }
+    let extent = vec3<f32>(grid.dimensions);
+    let local = (world_position - grid.origin) / grid.spacing;
+    // Each probe covers the cell around it
+    if any(local < vec3<f32>(-0.5)) || any(local > extent - 0.5) {
+        return vec4<f32>(0.0);
+    }
+
+    let last = grid.dimensions - 1u;
+    let clamped = clamp(local, vec3<f32>(0.0), vec3<f32>(last));
+    let base = min(vec3<u32>(floor(clamped)), last);
+    let next = min(base + 1u, last);
+    let t = clamped - vec3<f32>(base);
+
+    var irradiance = vec3<f32>(0.0);
+    for (var corner = 0u; corner < 8u; corner++) {
+        let pick = vec3<u32>(corner, corner >> 1u, corner >> 2u) & vec3<u32>(1u);
+        let take_next = pick == vec3<u32>(1u);
+        let cell = select(base, next, take_next);
+        let weights = select(1.0 - t, t, take_next);
+        let index = cell.x + cell.y * grid.dimensions.x + cell.z * grid.dimensions.x * grid.dimensions.y;
+        irradiance += ambient_cube(index, normal) * weights.x * weights.y * weights.z;
+    }
+    return vec4<f32>(irradiance * grid.intensity, 1.0);
+}
+
+// Same as depth_slice in lighting/clusters.rs
+fn depth_slice(depth: f32) -> u32 {
+    let near = clusters.projection.z;
+    let far = clusters.projection.w;
+    let slice = log(depth / near) / log(far / near) * f32(clusters.dimensions.z);
+    return min(u32(slice), clusters.dimensions.z - 1u);
+}
+
+// Froxel containing a world position, or NO_CLUSTER outside the clustered frustum
+fn cluster_index(world_position: vec3<f32>) -> u32 {
+    if clusters.enabled == 0u {
+        return NO_CLUSTER;
+    }
+    let clip = clusters.view_proj * vec4<f32>(world_position, 1.0);
+    if clip.w <= 0.0 {
+        return NO_CLUSTER;
+    }
+    let ndc = clip.xy / clip.w;
+    let depth = -(clusters.view * vec4<f32>(world_position, 1.0)).z;
+    if any(abs(ndc) > vec2<f32>(1.0)) || depth < clusters.projection.z || depth >= clusters.projection.w {
+        return NO_CLUSTER;
+    }
+
+    let dims = clusters.dimensions.xyz;
+    let tile = min(vec2<u32>((ndc * 0.5 + 0.5) * vec2<f32>(dims.xy)), dims.xy - 1u);
+    return tile.x + tile.y * dims.x + depth_slice(depth) * dims.x * dims.y;
+}
+
+// Same curves as Fog::factor on the CPU side
+fn fog_factor(depth: f32) -> f32 {
+    let fog = lighting.fog;
+    var factor = 0.0;
+    if fog.mode == 1u {
+        factor = (depth - fog.start) / max(fog.end - fog.start, 0.0001);
+    } else if fog.mode == 2u {
+        factor = 1.0 - exp(-fog.density * depth);
+    } else if fog.mode == 3u {
+        let d = fog.density * depth;
+        factor = 1.0 - exp(-d * d);
+    }
+    return clamp(factor, 0.0, 1.0);
+}
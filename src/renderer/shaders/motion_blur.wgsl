@@ -0,0 +1,56 @@
+// Per-pixel motion blur: averages the HDR color along each pixel's motion vector.
+
+struct MotionBlurUniform {
+    // Fraction of the last frame's motion to blur over
+    intensity: f32,
+    sample_count: u32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var velocity_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var hdr_sampler: sampler;
+
+@group(0) @binding(3)
+var<uniform> params: MotionBlurUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// Fullscreen triangle, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_position.xy);
+    let size = vec2<f32>(textureDimensions(hdr_texture));
+    let uv = in.clip_position.xy / size;
+    let velocity = textureLoad(velocity_texture, pixel, 0).xy * params.intensity;
+
+    // Less than half a pixel of motion would only soften the image
+    if length(velocity * size) < 0.5 {
+        return vec4<f32>(textureLoad(hdr_texture, pixel, 0).rgb, 1.0);
+    }
+
+    // Samples centered on the pixel, like a shutter open around the frame's midpoint
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < params.sample_count; i++) {
+        let t = (f32(i) + 0.5) / f32(params.sample_count) - 0.5;
+        let sample_uv = clamp(uv + velocity * t, vec2<f32>(0.0), vec2<f32>(1.0));
+        color += textureSampleLevel(hdr_texture, hdr_sampler, sample_uv, 0.0).rgb;
+    }
+
+    return vec4<f32>(color / f32(params.sample_count), 1.0);
+}
@@ -0,0 +1,45 @@
+// Renders shadow casters into one cube face of a point light's shadow map.
+// Depth is the distance to the light over its radius, see lighting/shadows.rs.
+
+struct ShadowFace {
+    view_proj: mat4x4<f32>,
+    // xyz: light position, w: radius
+    light: vec4<f32>,
+}
+
+struct ModelUniform {
+    model: mat4x4<f32>,
+    normal_matrix: array<vec4<f32>, 3>,
+    color: vec4<f32>,
+    previous_model: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> face: ShadowFace;
+
+@group(1) @binding(0)
+var<storage, read> models: array<ModelUniform>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    // Camera culling doesn't apply, casters outside the view still cast
+    let world_position = models[instance_index].model * vec4<f32>(in.position, 1.0);
+    out.clip_position = face.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    return clamp(length(in.world_position - face.light.xyz) / face.light.w, 0.0, 1.0);
+}
@@ -0,0 +1,83 @@
+// Temporal anti-aliasing resolve: blends the frame with the reprojected history.
+
+struct TaaUniform {
+    // Weight of the current frame
+    blend: f32,
+    history_valid: u32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var current_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var history_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var velocity_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var history_sampler: sampler;
+
+@group(0) @binding(4)
+var<uniform> params: TaaUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// Fullscreen triangle, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_position.xy);
+    let size = vec2<i32>(textureDimensions(current_texture));
+    let current = textureLoad(current_texture, pixel, 0).rgb;
+
+    if params.history_valid == 0u {
+        return vec4<f32>(current, 1.0);
+    }
+
+    let velocity = textureLoad(velocity_texture, pixel, 0).xy;
+    let history_uv = in.clip_position.xy / vec2<f32>(size) - velocity;
+    // Disoccluded from outside the screen, nothing to reproject
+    if any(history_uv < vec2<f32>(0.0)) || any(history_uv > vec2<f32>(1.0)) {
+        return vec4<f32>(current, 1.0);
+    }
+
+    // Clamp the history to the colors around this pixel so stale samples can't ghost
+    var neighborhood_min = current;
+    var neighborhood_max = current;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor_pixel = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let neighbor = textureLoad(current_texture, neighbor_pixel, 0).rgb;
+            neighborhood_min = min(neighborhood_min, neighbor);
+            neighborhood_max = max(neighborhood_max, neighbor);
+        }
+    }
+
+    let history = clamp(
+        textureSampleLevel(history_texture, history_sampler, history_uv, 0.0).rgb,
+        neighborhood_min,
+        neighborhood_max,
+    );
+
+    // Weighting by inverse luma keeps bright sub-pixel highlights from flickering
+    let current_weight = params.blend / (1.0 + luma(current));
+    let history_weight = (1.0 - params.blend) / (1.0 + luma(history));
+    let color = (current * current_weight + history * history_weight) / (current_weight + history_weight);
+
+    return vec4<f32>(color, 1.0);
+}
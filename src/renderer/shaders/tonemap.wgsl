@@ -0,0 +1,58 @@
+struct TonemapUniform {
+    exposure: f32,
+    mode: u32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> params: TonemapUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// Fullscreen triangle, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+fn tonemap_reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (vec3<f32>(1.0) + color);
+}
+
+// Narkowicz 2015 ACES filmic fit
+fn tonemap_aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(hdr_texture, vec2<i32>(in.clip_position.xy), 0).rgb * params.exposure;
+
+    var color: vec3<f32>;
+    switch params.mode {
+        case 1u: {
+            color = tonemap_reinhard(hdr);
+        }
+        case 2u: {
+            color = tonemap_aces(hdr);
+        }
+        default: {
+            color = clamp(hdr, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+
+    return vec4<f32>(color, 1.0);
+}
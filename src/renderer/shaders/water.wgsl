@@ -0,0 +1,89 @@
+struct WaterUniform {
+    view_proj: mat4x4<f32>,
+    // w: elapsed seconds
+    camera_position: vec4<f32>,
+    // w: distortion
+    center: vec4<f32>,
+    // xy: half extents along X and Z, z: wave speed, w: Fresnel power
+    half_size: vec4<f32>,
+    // a: opacity
+    color: vec4<f32>,
+    // Window camera viewport in pixels: x, y, width, height
+    viewport: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> water: WaterUniform;
+
+@group(1) @binding(0)
+var reflection_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var reflection_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+    );
+    let corner = corners[vertex_index] * water.half_size.xy;
+    let world_position = water.center.xyz + vec3<f32>(corner.x, 0.0, corner.y);
+
+    var out: VertexOutput;
+    out.clip_position = water.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+    return out;
+}
+
+// Sum of a few travelling sine waves; returns the surface slope along X and Z
+fn wave_slope(position: vec2<f32>, time: f32) -> vec2<f32> {
+    var slope = vec2<f32>(0.0);
+    let directions = array<vec2<f32>, 3>(
+        vec2<f32>(0.8, 0.6),
+        vec2<f32>(-0.6, 0.8),
+        vec2<f32>(0.3, -0.95),
+    );
+    let frequencies = vec3<f32>(0.7, 1.3, 2.9);
+    let amplitudes = vec3<f32>(0.5, 0.3, 0.15);
+
+    for (var i = 0u; i < 3u; i = i + 1u) {
+        let phase = dot(directions[i], position) * frequencies[i] + time * (1.0 + f32(i) * 0.5);
+        slope += directions[i] * cos(phase) * frequencies[i] * amplitudes[i];
+    }
+    return slope;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let time = water.camera_position.w * water.half_size.z;
+    let slope = wave_slope(in.world_position.xz, time);
+    let normal = normalize(vec3<f32>(-slope.x * 0.1, 1.0, -slope.y * 0.1));
+
+    // The reflection camera keeps +Y up, so its image is upside down relative to ours
+    let screen_uv = (in.clip_position.xy - water.viewport.xy) / water.viewport.zw;
+    let offset = normal.xz * water.center.w;
+    let reflection_uv = clamp(
+        vec2<f32>(screen_uv.x, 1.0 - screen_uv.y) + offset,
+        vec2<f32>(0.001),
+        vec2<f32>(0.999),
+    );
+    let reflection = textureSample(reflection_texture, reflection_sampler, reflection_uv).rgb;
+
+    // Schlick's approximation with the reflectance of water at normal incidence
+    let view_dir = normalize(water.camera_position.xyz - in.world_position);
+    let cos_theta = clamp(dot(normal, view_dir), 0.0, 1.0);
+    let fresnel = 0.02 + 0.98 * pow(1.0 - cos_theta, water.half_size.w);
+
+    let color = mix(water.color.rgb, reflection, fresnel);
+    let alpha = mix(water.color.a, 1.0, fresnel);
+    return vec4<f32>(color, alpha);
+}
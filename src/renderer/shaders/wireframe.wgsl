@@ -5,6 +5,8 @@ struct CameraUniform {
 struct ModelUniform {
     model: mat4x4<f32>,
     normal_matrix: mat3x3<f32>,
+    color: vec4<f32>,
+    previous_model: mat4x4<f32>,
 }
 
 @group(0) @binding(0)
@@ -16,6 +18,15 @@ var<storage, read> models: array<ModelUniform>;
 @group(1) @binding(1)
 var<storage, read> visibility: array<u32>;
 
+// Per-entity overlay, see WireframeOverlay
+struct Overlay {
+    color: vec4<f32>,
+    depth_bias: f32,
+}
+
+@group(2) @binding(0)
+var<uniform> overlay: Overlay;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
@@ -28,8 +39,7 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
 }
 
-@vertex
-fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+fn transform_vertex(in: VertexInput, instance_index: u32) -> VertexOutput {
     var out: VertexOutput;
 
     if instance_index < arrayLength(&visibility) && visibility[instance_index] == 0u {
@@ -44,7 +54,25 @@ fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> Ver
     return out;
 }
 
+@vertex
+fn vs_main(in: VertexInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    return transform_vertex(in, instance_index);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(0.0, 1.0, 0.0, 1.0);
 }
+
+@vertex
+fn vs_overlay(in: VertexInput, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    var out = transform_vertex(in, instance_index);
+    // Pull toward the camera so the lines win the depth test against their own surface
+    out.clip_position.z -= overlay.depth_bias * out.clip_position.w;
+    return out;
+}
+
+@fragment
+fn fs_overlay(in: VertexOutput) -> @location(0) vec4<f32> {
+    return overlay.color;
+}
@@ -0,0 +1,68 @@
+use bevy_ecs::prelude::Resource;
+use std::fmt;
+
+/// Per-frame scene complexity, for reasoning about performance without GPU tools.
+///
+/// Draw counts are filled by `prepare_indirect_draw_data` and describe one camera; every
+/// camera draws the same batches. With GPU culling enabled the CPU does not know what the
+/// cull pass hides, so `culled_entities` stays 0 and the other counts are upper bounds.
+/// `draw_calls` is counted by the render graph nodes as they record, across all cameras
+/// and passes, so a jump in it next to steady `batches` points at a pass or extra camera.
+/// Logged together with [`PerformanceAnalytics`](crate::core::PerformanceAnalytics).
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Meshes that passed frustum culling.
+    pub visible_entities: usize,
+    pub culled_entities: usize,
+    /// One per distinct visible mesh.
+    pub batches: usize,
+    /// Instanced draws, one per run of consecutive instances in a batch.
+    pub indirect_commands: usize,
+    /// Instances in the batches, which is `visible_entities` unless render layers or
+    /// missing meshes leave some out.
+    pub instances: usize,
+    pub triangles: u64,
+    /// Model storage and indirect buffers draw preparation had to recreate, because they
+    /// were missing or too small. Nonzero every frame means buffers keep getting reallocated.
+    pub buffer_rebuilds: usize,
+    /// Draws recorded by the render graph in the previous frame, every command of a
+    /// multi-draw counted. Kept when the draw counts are reset.
+    pub draw_calls: u32,
+    /// Render graph nodes that recorded commands this frame.
+    pub nodes_executed: usize,
+    pub nodes_failed: usize,
+}
+
+impl RenderStats {
+    /// Clears the draw counts before draw preparation refills them.
+    pub fn reset_draws(&mut self) {
+        *self = Self {
+            nodes_executed: self.nodes_executed,
+            nodes_failed: self.nodes_failed,
+            draw_calls: self.draw_calls,
+            ..Default::default()
+        };
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Visible: {} | Culled: {} | Batches: {} | Indirect Draws: {} | Instances: {} | Triangles: {} | Draw Calls: {} | Buffer Rebuilds: {} | Nodes: {}",
+            self.visible_entities,
+            self.culled_entities,
+            self.batches,
+            self.indirect_commands,
+            self.instances,
+            self.triangles,
+            self.draw_calls,
+            self.buffer_rebuilds,
+            self.nodes_executed
+        )?;
+        if self.nodes_failed > 0 {
+            write!(f, " ({} failed)", self.nodes_failed)?;
+        }
+        Ok(())
+    }
+}
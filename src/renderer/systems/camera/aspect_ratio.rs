@@ -1,19 +1,29 @@
-use crate::renderer::Camera;
-use crate::window::WindowEvent;
+use crate::renderer::{Camera, RenderTarget, RenderTargets, Renderer};
 use bevy_ecs::prelude::*;
 
+/// Keeps each camera's aspect ratio matched to its viewport on its render target.
 pub fn update_camera_aspect_ratio(
     mut cameras: Query<&mut Camera>,
-    If(mut window_events): If<MessageReader<WindowEvent>>,
+    renderer: Option<Res<Renderer>>,
+    render_targets: Option<Res<RenderTargets>>,
 ) {
-    for event in window_events.read() {
-        if let WindowEvent::Resized { width, height } = event {
-            let aspect = *width as f32 / (*height as f32).max(1.0);
+    let window_size = renderer.map(|renderer| renderer.size());
 
-            for mut camera in cameras.iter_mut() {
-                camera.set_aspect(aspect);
+    for mut camera in cameras.iter_mut() {
+        let target_size = match camera.target {
+            RenderTarget::Window => window_size,
+            RenderTarget::Texture(id) => {
+                render_targets.as_ref().and_then(|targets| targets.size(id))
             }
+        };
+        let Some(target_size) = target_size else {
+            continue;
+        };
 
+        let aspect = camera.viewport.unwrap_or_default().aspect(target_size);
+        // Compare first so unchanged cameras aren't flagged as changed every frame
+        if (camera.aspect - aspect).abs() > f32::EPSILON {
+            camera.set_aspect(aspect);
             log::debug!("Updated camera aspect ratio to: {:.3}", aspect);
         }
     }
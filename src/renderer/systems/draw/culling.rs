@@ -1,12 +1,12 @@
-/// Frustum culling for efficient entity visibility determination.
-///
-/// Performs CPU-side frustum tests on AABBs to avoid rendering off-screen entities.
-/// Combined with distance-based culling to reduce GPU work for infinite worlds.
-///
-/// Design notes:
-/// - Culling happens in PostUpdate, after camera transform is synced
-/// - Results are used to build indirect draw buffers for GPU
-/// - Entities without AABBs are always rendered (conservative fallback)
+//! Frustum culling for efficient entity visibility determination.
+//!
+//! Performs CPU-side frustum tests on AABBs to avoid rendering off-screen entities.
+//! Combined with distance-based culling to reduce GPU work for infinite worlds.
+//!
+//! Design notes:
+//! - Culling happens in PostUpdate, after camera transform is synced
+//! - Results are used to build indirect draw buffers for GPU
+//! - Entities without AABBs are always rendered (conservative fallback)
 
 use crate::renderer::camera::Frustum;
 use crate::renderer::components::Aabb;
@@ -68,6 +68,32 @@ pub fn frustum_cull_entities(
     camera_pos: Vec3,
     config: CullingConfig,
 ) -> CullingResult {
+    let mut visible_indices = Vec::new();
+    let (frustum_culled, distance_culled) = frustum_cull_entities_into(
+        frustum,
+        entities_data,
+        camera_pos,
+        config,
+        &mut visible_indices,
+    );
+
+    CullingResult {
+        visible_indices,
+        tested_count: entities_data.len(),
+        frustum_culled,
+        distance_culled,
+    }
+}
+
+/// Same as [`frustum_cull_entities`], but appends visible indices to `visible`
+/// so a per-frame buffer can be reused. Returns `(frustum_culled, distance_culled)`.
+pub fn frustum_cull_entities_into(
+    frustum: &Frustum,
+    entities_data: &[(u32, Aabb)],
+    camera_pos: Vec3,
+    config: CullingConfig,
+    visible: &mut Vec<u32>,
+) -> (usize, usize) {
     use rayon::prelude::*;
     use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -81,36 +107,30 @@ pub fn frustum_cull_entities(
     let frustum_culled = AtomicUsize::new(0);
     let distance_culled = AtomicUsize::new(0);
 
-    let visible_indices: Vec<u32> = if use_parallel {
-        entities_data
-            .par_iter()
-            .filter_map(|(idx, aabb)| {
-                // Quick distance cull first (cheaper than frustum test)
-                if enable_distance {
-                    let aabb_center = (aabb.min + aabb.max) * 0.5;
-                    let to_entity = aabb_center - camera_pos;
-                    let dist_sq = to_entity.length_squared();
-                    if dist_sq > max_dist_sq {
-                        distance_culled.fetch_add(1, Ordering::Relaxed);
-                        return None;
-                    }
+    if use_parallel {
+        visible.par_extend(entities_data.par_iter().filter_map(|(idx, aabb)| {
+            // Quick distance cull first (cheaper than frustum test)
+            if enable_distance {
+                let aabb_center = (aabb.min + aabb.max) * 0.5;
+                let to_entity = aabb_center - camera_pos;
+                let dist_sq = to_entity.length_squared();
+                if dist_sq > max_dist_sq {
+                    distance_culled.fetch_add(1, Ordering::Relaxed);
+                    return None;
                 }
+            }
 
-                // Frustum cull using pre-computed world-space AABB
-                if enable_frustum {
-                    if !frustum.contains_aabb(aabb.min, aabb.max) {
-                        frustum_culled.fetch_add(1, Ordering::Relaxed);
-                        return None;
-                    }
-                }
+            // Frustum cull using pre-computed world-space AABB
+            if enable_frustum && !frustum.contains_aabb(aabb.min, aabb.max) {
+                frustum_culled.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
 
-                // Entity is visible
-                Some(*idx)
-            })
-            .collect()
+            // Entity is visible
+            Some(*idx)
+        }));
     } else {
         // Sequential processing for small entity counts
-        let mut result = Vec::new();
         let mut fc = 0;
         let mut dc = 0;
 
@@ -125,27 +145,22 @@ pub fn frustum_cull_entities(
                 }
             }
 
-            if enable_frustum {
-                if !frustum.contains_aabb(aabb.min, aabb.max) {
-                    fc += 1;
-                    continue;
-                }
+            if enable_frustum && !frustum.contains_aabb(aabb.min, aabb.max) {
+                fc += 1;
+                continue;
             }
 
-            result.push(*idx);
+            visible.push(*idx);
         }
 
         frustum_culled.store(fc, Ordering::Relaxed);
         distance_culled.store(dc, Ordering::Relaxed);
-        result
-    };
-
-    CullingResult {
-        visible_indices,
-        tested_count: entities_data.len(),
-        frustum_culled: frustum_culled.load(Ordering::Relaxed),
-        distance_culled: distance_culled.load(Ordering::Relaxed),
     }
+
+    (
+        frustum_culled.load(Ordering::Relaxed),
+        distance_culled.load(Ordering::Relaxed),
+    )
 }
 
 /// Sorts entities by spatial grid cell for improved cache locality
@@ -170,13 +185,17 @@ pub fn sort_by_spatial_grid(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::renderer::camera::Plane;
 
     #[test]
     fn test_frustum_culling() {
         // Create a simple frustum (would normally come from camera)
-        // For testing, we'll use default frustum which passes all tests
+        // For testing, we'll use degenerate planes which pass all tests
         let frustum = Frustum {
-            planes: [Default::default(); 6],
+            planes: [Plane {
+                normal: Vec3::ZERO,
+                distance: 0.0,
+            }; 6],
         };
 
         let aabb = Aabb {
@@ -184,7 +203,7 @@ mod tests {
             max: Vec3::new(1.0, 1.0, 1.0),
         };
 
-        let entities = vec![(0u32, Vec3::ZERO, aabb)];
+        let entities = vec![(0u32, aabb)];
         let camera_pos = Vec3::new(0.0, 0.0, -10.0);
 
         let result = frustum_cull_entities(
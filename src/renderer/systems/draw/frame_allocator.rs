@@ -0,0 +1,111 @@
+//! Per-frame scratch storage for draw preparation.
+//!
+//! Every buffer here is cleared, not freed, when the frame starts, so after the
+//! first few frames draw preparation, culling and indirect command assembly run
+//! without touching the heap unless the scene grows.
+
+use crate::assets::handle::AssetId;
+use crate::core::math::{Mat4, Vec3};
+use crate::renderer::components::Aabb;
+use crate::renderer::{ModelUniform, RenderFlags, RenderLayers};
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use std::collections::HashSet;
+
+/// Entity, mesh, transform, local AABB, tint, the model matrix of the previous frame,
+/// extraction flags and render layers.
+pub type DrawEntity = (
+    Entity,
+    AssetId,
+    GlobalTransform,
+    Option<Aabb>,
+    Vec3,
+    Mat4,
+    RenderFlags,
+    RenderLayers,
+);
+
+#[derive(Resource, Default)]
+pub struct FrameAllocator {
+    /// Every uploaded mesh entity, sorted by mesh then entity.
+    pub entities: Vec<DrawEntity>,
+    /// Entity index and world-space AABB of every culling candidate.
+    pub culling_data: Vec<(u32, Aabb)>,
+    /// Indices into `entities` that passed culling.
+    pub visible: Vec<u32>,
+    /// `visible` as a mask over `entities`, used to merge culling results without hashing.
+    pub visible_mask: Vec<bool>,
+    pub changed: HashSet<Entity>,
+    pub model_uniforms: Vec<ModelUniform>,
+    /// Visible instance indices per mesh. Entries are emptied, not removed, between frames.
+    pub mesh_groups: ahash::AHashMap<AssetId, Vec<u32>>,
+    /// Scratch space for one batch of indirect draw commands.
+    pub indirect_commands: Vec<u32>,
+    frames: u64,
+}
+
+impl FrameAllocator {
+    /// Clears all buffers while keeping their capacity.
+    pub fn reset(&mut self) {
+        self.entities.clear();
+        self.culling_data.clear();
+        self.visible.clear();
+        self.visible_mask.clear();
+        self.changed.clear();
+        self.model_uniforms.clear();
+        self.indirect_commands.clear();
+
+        // Periodically forget meshes that had no visible instances last frame,
+        // so the map doesn't keep every mesh ever seen
+        if self.frames.is_multiple_of(600) {
+            self.mesh_groups
+                .retain(|_, instances| !instances.is_empty());
+        }
+        for instances in self.mesh_groups.values_mut() {
+            instances.clear();
+        }
+
+        self.frames += 1;
+    }
+
+    /// Approximate heap memory held by the scratch buffers.
+    pub fn capacity_bytes(&self) -> usize {
+        use std::mem::size_of;
+
+        self.entities.capacity() * size_of::<DrawEntity>()
+            + self.culling_data.capacity() * size_of::<(u32, Aabb)>()
+            + self.visible.capacity() * size_of::<u32>()
+            + self.visible_mask.capacity()
+            + self.changed.capacity() * size_of::<Entity>()
+            + self.model_uniforms.capacity() * size_of::<ModelUniform>()
+            + self
+                .mesh_groups
+                .values()
+                .map(|instances| instances.capacity() * size_of::<u32>())
+                .sum::<usize>()
+            + self.indirect_commands.capacity() * size_of::<u32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_keeps_capacity() {
+        let mut allocator = FrameAllocator::default();
+        allocator.visible.extend(0..1000);
+        allocator
+            .mesh_groups
+            .entry(AssetId::new(1))
+            .or_default()
+            .extend(0..100);
+
+        allocator.reset();
+
+        assert!(allocator.visible.is_empty());
+        assert!(allocator.visible.capacity() >= 1000);
+        assert!(allocator.mesh_groups[&AssetId::new(1)].is_empty());
+        assert!(allocator.mesh_groups[&AssetId::new(1)].capacity() >= 100);
+    }
+}
@@ -0,0 +1,383 @@
+//! Frustum culling in a compute pass.
+//!
+//! With [`GraphicsSettings::gpu_culling`] on, draw preparation skips CPU culling and every
+//! mesh batch keeps all of its instances. This system lays those instances out once in
+//! GPU buffers, with one indirect command slot per instance grouped by mesh. Culled
+//! batches therefore draw their survivors one instance per command rather than as the
+//! instanced runs the CPU path emits. Each frame
+//! [`GpuCullNode`](crate::renderer::GpuCullNode) clears the commands, tests every
+//! instance's AABB against the camera frustums and appends the visible ones to the front
+//! of their mesh's slots. Only the frustum planes are uploaded per frame; the layout is
+//! rebuilt when the set of instances changes.
+//!
+//! With [`GraphicsSettings::occlusion_culling`] also on, the frustum-culled instances are
+//! drawn into a depth prepass, reduced to a max-depth pyramid and culled a second time
+//! against it by [`OcclusionCullNode`](crate::renderer::OcclusionCullNode), so the main
+//! pass skips instances hidden behind closer geometry.
+
+use crate::assets::handle::AssetId;
+use crate::renderer::components::{IndirectDrawData, MeshDrawBatch, ModelStorageData};
+use crate::renderer::{
+    ExtractedScene, GpuCullPipeline, GpuMeshCache, GpuUploader, GraphicsSettings, RenderTarget,
+    Renderer,
+};
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, Buffer};
+
+use super::frame_allocator::FrameAllocator;
+
+/// Cameras tested per instance. With more active cameras than this, nothing is culled.
+/// Must match `MAX_CAMERAS` in `cull.wgsl`.
+pub const MAX_CULL_CAMERAS: usize = 4;
+
+pub const CULL_WORKGROUP_SIZE: u32 = 64;
+
+const HAS_AABB: u32 = 1;
+const NO_BATCH: u32 = u32::MAX;
+const INDIRECT_COMMAND_SIZE: u64 = 5 * std::mem::size_of::<u32>() as u64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct CullParams {
+    planes: [[f32; 4]; 6 * MAX_CULL_CAMERAS],
+    camera_count: u32,
+    instance_count: u32,
+    occlusion: u32,
+    _padding: u32,
+    /// View-projection the depth pyramid was rendered with
+    view_proj: [[f32; 4]; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct CullInstance {
+    aabb_min: [f32; 3],
+    batch: u32,
+    aabb_max: [f32; 3],
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct CullBatch {
+    index_count: u32,
+    first_command: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CulledDraw {
+    mesh_id: AssetId,
+    base_instance: u32,
+    slot_count: u32,
+    first_command: u32,
+}
+
+#[derive(Resource)]
+pub struct GpuCullData {
+    pub bind_group: BindGroup,
+    pub counter_buffer: Buffer,
+    /// Compacted `DrawIndexedIndirect` commands of every batch. Slots past a batch's
+    /// visible count are zeroed and draw nothing.
+    pub indirect_buffer: Buffer,
+    pub instance_count: u32,
+    /// Whether the occlusion pass should re-cull against the depth pyramid this frame.
+    pub occlusion: bool,
+    params_buffer: Buffer,
+    draws: Vec<CulledDraw>,
+    entity_count: usize,
+}
+
+impl GpuCullData {
+    /// Indirect buffer, byte offset and command count holding a batch's culled commands.
+    ///
+    /// `None` if the batch isn't culled on the GPU; draw it from its own buffer instead.
+    pub fn indirect_for(&self, batch: &MeshDrawBatch) -> Option<(&Buffer, u64, u32)> {
+        self.draws
+            .iter()
+            .find(|draw| {
+                draw.mesh_id == batch.mesh_id
+                    && draw.base_instance == batch.base_instance
+                    && draw.slot_count == batch.visible_instances.len() as u32
+            })
+            .map(|draw| {
+                (
+                    &self.indirect_buffer,
+                    draw.first_command as u64 * INDIRECT_COMMAND_SIZE,
+                    draw.slot_count,
+                )
+            })
+    }
+}
+
+/// Buffer, byte offset and command count to issue a batch's indirect draws from.
+pub fn batch_indirect_source<'a>(
+    gpu_cull: Option<&'a GpuCullData>,
+    batch: &'a MeshDrawBatch,
+) -> (&'a Buffer, u64, u32) {
+    gpu_cull
+        .and_then(|cull| cull.indirect_for(batch))
+        .unwrap_or((&batch.indirect_buffer, 0, batch.draw_count))
+}
+
+/// Resources GPU culling needs before it can run; any missing one turns it off.
+#[derive(SystemParam)]
+pub struct GpuCullInputs<'w> {
+    renderer: Option<Res<'w, Renderer>>,
+    settings: Option<Res<'w, GraphicsSettings>>,
+    cull_pipeline: Option<Res<'w, GpuCullPipeline>>,
+    gpu_mesh_cache: Option<Res<'w, GpuMeshCache>>,
+    storage: Option<Res<'w, ModelStorageData>>,
+    indirect: Option<Res<'w, IndirectDrawData>>,
+}
+
+pub fn prepare_gpu_culling(
+    mut commands: Commands,
+    inputs: GpuCullInputs,
+    extracted: Res<ExtractedScene>,
+    frame_allocator: Res<FrameAllocator>,
+    mut uploader: ResMut<GpuUploader>,
+    mut cull_data: Option<ResMut<GpuCullData>>,
+) {
+    let GpuCullInputs {
+        renderer,
+        settings,
+        cull_pipeline,
+        gpu_mesh_cache,
+        storage,
+        indirect,
+    } = inputs;
+    let (
+        Some(settings),
+        Some(renderer),
+        Some(cull_pipeline),
+        Some(gpu_mesh_cache),
+        Some(storage),
+        Some(indirect),
+    ) = (
+        settings.filter(|settings| settings.gpu_culling()),
+        renderer,
+        cull_pipeline,
+        gpu_mesh_cache,
+        storage,
+        indirect,
+    )
+    else {
+        if cull_data.is_some() {
+            commands.remove_resource::<GpuCullData>();
+        }
+        return;
+    };
+
+    let mut draws = Vec::with_capacity(indirect.batches.len());
+    let mut first_command = 0;
+    for batch in &indirect.batches {
+        draws.push(CulledDraw {
+            mesh_id: batch.mesh_id,
+            base_instance: batch.base_instance,
+            slot_count: batch.visible_instances.len() as u32,
+            first_command,
+        });
+        first_command += batch.visible_instances.len() as u32;
+    }
+
+    if draws.is_empty() {
+        if cull_data.is_some() {
+            commands.remove_resource::<GpuCullData>();
+        }
+        return;
+    }
+
+    let mut params = CullParams::zeroed();
+    params.instance_count = storage.entity_count as u32;
+    if extracted.cameras.len() <= MAX_CULL_CAMERAS {
+        params.camera_count = extracted.cameras.len() as u32;
+        for (camera_index, camera) in extracted.cameras.iter().enumerate() {
+            let frustum = camera.camera.frustum(&camera.transform);
+            for (plane_index, plane) in frustum.planes.iter().enumerate() {
+                params.planes[camera_index * 6 + plane_index] = [
+                    plane.normal.x,
+                    plane.normal.y,
+                    plane.normal.z,
+                    plane.distance,
+                ];
+            }
+        }
+    } else {
+        log::debug!(
+            "{} cameras exceed the GPU culling limit of {}, drawing every instance",
+            extracted.cameras.len(),
+            MAX_CULL_CAMERAS
+        );
+    }
+
+    // The depth pyramid is rendered from a single full-window view, so occlusion
+    // is only tested when that is the only camera
+    let occlusion_camera = match extracted.cameras.as_slice() {
+        [camera]
+            if camera.camera.target == RenderTarget::Window && camera.camera.viewport.is_none() =>
+        {
+            Some(camera)
+        }
+        _ => None,
+    };
+    if let Some(camera) = occlusion_camera.filter(|_| settings.occlusion_culling()) {
+        params.occlusion = 1;
+        params.view_proj = camera
+            .camera
+            .view_projection_matrix(&camera.transform)
+            .to_cols_array_2d();
+    }
+    let occlusion = params.occlusion != 0;
+
+    match cull_data.as_deref_mut() {
+        Some(data) if data.draws == draws && data.entity_count == storage.entity_count => {
+            data.occlusion = occlusion;
+            uploader.write_buffer(&data.params_buffer, 0, bytemuck::bytes_of(&params));
+        }
+        _ => {
+            let mut data = create_cull_data(
+                renderer.device(),
+                &cull_pipeline,
+                &gpu_mesh_cache,
+                &storage,
+                &indirect.batches,
+                &frame_allocator,
+                draws,
+            );
+            data.occlusion = occlusion;
+            uploader.write_buffer(&data.params_buffer, 0, bytemuck::bytes_of(&params));
+            commands.insert_resource(data);
+        }
+    }
+}
+
+fn create_cull_data(
+    device: &wgpu::Device,
+    cull_pipeline: &GpuCullPipeline,
+    gpu_mesh_cache: &GpuMeshCache,
+    storage: &ModelStorageData,
+    batches: &[MeshDrawBatch],
+    frame_allocator: &FrameAllocator,
+    draws: Vec<CulledDraw>,
+) -> GpuCullData {
+    // Every batch has one command slot per instance, laid out back to back
+    let command_count = draws
+        .last()
+        .map_or(0, |draw| draw.first_command + draw.slot_count);
+    let mut instances = vec![
+        CullInstance {
+            aabb_min: [0.0; 3],
+            batch: NO_BATCH,
+            aabb_max: [0.0; 3],
+            flags: 0,
+        };
+        storage.entity_count
+    ];
+    let mut cull_batches = Vec::with_capacity(batches.len());
+
+    for (batch_index, (batch, draw)) in batches.iter().zip(&draws).enumerate() {
+        let index_count = gpu_mesh_cache
+            .get(&batch.mesh_id)
+            .map(|mesh| mesh.index_count)
+            .unwrap_or(0);
+        cull_batches.push(CullBatch {
+            index_count,
+            first_command: draw.first_command,
+        });
+
+        for &instance in &batch.visible_instances {
+            let Some(slot) = instances.get_mut(instance as usize) else {
+                continue;
+            };
+            slot.batch = batch_index as u32;
+            if let Some((_, _, _, Some(aabb), ..)) = frame_allocator.entities.get(instance as usize)
+            {
+                slot.aabb_min = aabb.min.to_array();
+                slot.aabb_max = aabb.max.to_array();
+                slot.flags |= HAS_AABB;
+            }
+        }
+    }
+
+    let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cull Params Buffer"),
+        size: std::mem::size_of::<CullParams>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Cull Instance Buffer"),
+        contents: bytemuck::cast_slice(&instances),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let batch_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Cull Batch Buffer"),
+        contents: bytemuck::cast_slice(&cull_batches),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let counter_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cull Counter Buffer"),
+        size: (cull_batches.len() * std::mem::size_of::<u32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let indirect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Culled Indirect Draw Buffer"),
+        size: command_count as u64 * INDIRECT_COMMAND_SIZE,
+        usage: wgpu::BufferUsages::INDIRECT
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Cull Bind Group"),
+        layout: &cull_pipeline.bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: storage.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: instance_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: batch_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: counter_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: indirect_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    log::debug!(
+        "Rebuilt GPU culling data: {} instances in {} batches",
+        instances.len(),
+        cull_batches.len()
+    );
+
+    GpuCullData {
+        bind_group,
+        counter_buffer,
+        indirect_buffer,
+        instance_count: instances.len() as u32,
+        occlusion: false,
+        params_buffer,
+        draws,
+        entity_count: storage.entity_count,
+    }
+}
@@ -1,5 +1,11 @@
-mod prepare_indirect;
-mod utils;
 pub mod culling;
+pub mod frame_allocator;
+pub mod gpu_culling;
+mod point_shadows;
+mod prepare_indirect;
+pub(crate) mod utils;
 
+pub use frame_allocator::FrameAllocator;
+pub use gpu_culling::{GpuCullData, prepare_gpu_culling};
+pub use point_shadows::prepare_point_shadow_draws;
 pub use prepare_indirect::prepare_indirect_draw_data;
@@ -0,0 +1,82 @@
+use crate::assets::handle::AssetId;
+use crate::renderer::{
+    GpuMeshCache, GpuUploader, RenderFlags, RenderStats, Renderer,
+    components::{Aabb, LightingData},
+};
+use bevy_ecs::prelude::*;
+
+use super::frame_allocator::FrameAllocator;
+use super::utils::batching::{self, BatchWriter};
+
+/// Per-mesh shadow caster instances and command scratch, reused across frames.
+#[derive(Default)]
+pub struct PointShadowScratch {
+    mesh_groups: ahash::AHashMap<AssetId, Vec<u32>>,
+    indirect_commands: Vec<u32>,
+}
+
+/// Builds the draws of the point shadow pass: every [`ShadowCaster`](crate::renderer::ShadowCaster)
+/// whose bounds reach a shadowed light, regardless of what the cameras see.
+pub fn prepare_point_shadow_draws(
+    renderer: Option<Res<Renderer>>,
+    gpu_mesh_cache: Option<Res<GpuMeshCache>>,
+    lighting_data: Option<ResMut<LightingData>>,
+    frame_allocator: Res<FrameAllocator>,
+    mut uploader: ResMut<GpuUploader>,
+    mut stats: ResMut<RenderStats>,
+    mut scratch: Local<PointShadowScratch>,
+) {
+    let Some(renderer) = renderer else { return };
+    let Some(gpu_mesh_cache) = gpu_mesh_cache else {
+        return;
+    };
+    let Some(mut lighting_data) = lighting_data else {
+        return;
+    };
+    let shadows = &mut lighting_data.point_shadows;
+    let PointShadowScratch {
+        mesh_groups,
+        indirect_commands,
+    } = &mut *scratch;
+
+    mesh_groups.retain(|_, instances| !instances.is_empty());
+    for instances in mesh_groups.values_mut() {
+        instances.clear();
+    }
+
+    if !shadows.lights.is_empty() {
+        // Entities are in model storage order, so their index is the instance index
+        for (index, (_, mesh_id, transform, aabb, _, _, flags, _)) in
+            frame_allocator.entities.iter().enumerate()
+        {
+            if !flags.contains(RenderFlags::SHADOW_CASTER) {
+                continue;
+            }
+            let in_reach = match aabb {
+                // Same world-space bounds as frustum culling
+                Some(aabb) => {
+                    let position = transform.position();
+                    let bounds = Aabb::new(aabb.min + position, aabb.max + position);
+                    shadows.lights.iter().any(|&(light, radius)| {
+                        light.clamp(bounds.min, bounds.max).distance_squared(light)
+                            <= radius * radius
+                    })
+                }
+                None => true,
+            };
+            if in_reach {
+                mesh_groups.entry(*mesh_id).or_default().push(index as u32);
+            }
+        }
+    }
+
+    let existing = std::mem::take(&mut shadows.batches);
+    let mut writer = BatchWriter {
+        device: renderer.device(),
+        uploader: &mut uploader,
+        indirect_commands,
+        rebuilds: &mut stats.buffer_rebuilds,
+    };
+    shadows.batches =
+        batching::create_draw_batches(&mut writer, &gpu_mesh_cache, mesh_groups, Some(&existing));
+}
@@ -1,73 +1,145 @@
 use crate::assets::handle::AssetId;
 use crate::renderer::{
-    GpuMeshCache, MeshPipeline, Renderer,
-    components::{Aabb, IndirectDrawData, Mesh, MeshUploaded, ModelStorageData},
-    Camera,
+    ExtractedScene, GpuMeshCache, GpuUploader, GraphicsSettings, MeshPipeline, RenderLayers,
+    RenderStats, Renderer,
+    components::{Aabb, IndirectDrawData, LayerDrawData, MeshDrawBatch, ModelStorageData},
 };
-use crate::transform::GlobalTransform;
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
 
+use super::culling::{self, CullingConfig, frustum_cull_entities_into};
+use super::frame_allocator::{DrawEntity, FrameAllocator};
+use super::utils::batching::BatchWriter;
 use super::utils::{batching, storage};
-use super::culling::{self, CullingConfig, frustum_cull_entities};
+
+/// Instances per mesh for each camera mask that leaves some of them out, kept across frames.
+type LayerGroups = Vec<(RenderLayers, ahash::AHashMap<AssetId, Vec<u32>>)>;
+
+/// What draw preparation reads: the extracted scene and the GPU state it is drawn with.
+#[derive(SystemParam)]
+pub struct DrawInputs<'w> {
+    renderer: Option<Res<'w, Renderer>>,
+    settings: Option<Res<'w, GraphicsSettings>>,
+    pipeline: Option<Res<'w, MeshPipeline>>,
+    gpu_mesh_cache: Option<Res<'w, GpuMeshCache>>,
+    extracted: Res<'w, ExtractedScene>,
+}
+
+/// Draw data prepared last frame, reused when the scene hasn't changed shape.
+#[derive(SystemParam)]
+pub struct ExistingDrawData<'w> {
+    storage: Option<ResMut<'w, ModelStorageData>>,
+    indirect: Option<ResMut<'w, IndirectDrawData>>,
+    layers: Option<ResMut<'w, LayerDrawData>>,
+}
+
+/// Scratch memory, upload queue and statistics written while preparing draws.
+#[derive(SystemParam)]
+pub struct DrawScratch<'w> {
+    frame_allocator: ResMut<'w, FrameAllocator>,
+    uploader: ResMut<'w, GpuUploader>,
+    stats: ResMut<'w, RenderStats>,
+    profiler: Option<ResMut<'w, crate::core::Profiler>>,
+}
 
 pub fn prepare_indirect_draw_data(
     mut commands: Commands,
-    renderer: Option<Res<Renderer>>,
-    pipeline: Option<Res<MeshPipeline>>,
-    gpu_mesh_cache: Option<Res<GpuMeshCache>>,
-    existing_storage: Option<ResMut<ModelStorageData>>,
-    existing_indirect: Option<ResMut<IndirectDrawData>>,
-    mut profiler: Option<ResMut<crate::core::Profiler>>,
-    changed_query: Query<(Entity, &Mesh, &GlobalTransform, Option<&Aabb>), (With<MeshUploaded>, Changed<GlobalTransform>)>,
-    all_query: Query<(Entity, &Mesh, &GlobalTransform, Option<&Aabb>), With<MeshUploaded>>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    inputs: DrawInputs,
+    existing: ExistingDrawData,
+    scratch: DrawScratch,
+    mut layer_groups: Local<LayerGroups>,
 ) {
     let _start = std::time::Instant::now();
+    let DrawInputs {
+        renderer,
+        settings,
+        pipeline,
+        gpu_mesh_cache,
+        extracted,
+    } = inputs;
+    let ExistingDrawData {
+        storage: existing_storage,
+        indirect: existing_indirect,
+        layers: existing_layers,
+    } = existing;
+    let DrawScratch {
+        mut frame_allocator,
+        mut uploader,
+        mut stats,
+        mut profiler,
+    } = scratch;
 
     let Some(renderer) = renderer else { return };
     let Some(pipeline) = pipeline else { return };
     let Some(gpu_mesh_cache) = gpu_mesh_cache else { return };
 
     let device = renderer.device();
-    let queue = renderer.queue();
-    let transforms_changed = !changed_query.is_empty();
-
-    // Get camera frustum and parameters for culling.
-    // NOTE: The camera's GlobalTransform is guaranteed to be current at this point because
-    // RenderPlugin orders this system to run AFTER propagate_transforms. See RenderPlugin::build().
-    let (frustum, camera_pos, max_render_distance) = if let Some((camera, transform)) = camera_query.iter().next() {
-        let frustum = camera.frustum(transform);
-        let camera_pos = transform.position();
-        let max_distance = camera.far; // Use actual camera far plane, not magic number
-        (Some(frustum), camera_pos, max_distance)
-    } else {
-        (None, glam::Vec3::ZERO, f32::INFINITY)
-    };
+    let uploader = &mut *uploader;
+    frame_allocator.reset();
+    let FrameAllocator {
+        entities: all_entities,
+        culling_data,
+        visible: visible_entities,
+        visible_mask,
+        changed,
+        model_uniforms,
+        mesh_groups,
+        indirect_commands,
+        ..
+    } = &mut *frame_allocator;
+    let transforms_changed = extracted.any_upload_needed();
+    stats.reset_draws();
+
+    // Get camera frustums and parameters for culling. Every camera draws the same batches,
+    // so an entity is kept if any camera can see it.
+    // NOTE: Everything here comes from ExtractedScene, which extract_render_data fills after
+    // propagate_transforms. See RenderPlugin::build().
+    // With GPU culling every instance stays in its batch and the cull pass hides the rest.
+    let gpu_culling = settings.is_some_and(|settings| settings.gpu_culling());
+    let has_camera = !extracted.cameras.is_empty();
+    // Instances on none of the cameras' layers aren't drawn at all
+    let camera_layers = extracted
+        .cameras
+        .iter()
+        .fold(RenderLayers::NONE, |mask, camera| RenderLayers(mask.0 | camera.layers.0));
 
     // Collect all entities with positions and AABBs
-    let mut all_entities: Vec<(Entity, AssetId, GlobalTransform, Option<Aabb>)> = all_query
-        .iter()
-        .map(|(entity, mesh, transform, aabb)| (entity, mesh.handle.id, *transform, aabb.copied()))
-        .collect();
+    all_entities.extend(
+        extracted
+            .meshes
+            .iter()
+            .map(|mesh| {
+                (
+                    mesh.entity,
+                    mesh.mesh_id,
+                    mesh.transform,
+                    mesh.aabb,
+                    mesh.color,
+                    mesh.previous_model,
+                    mesh.flags,
+                    mesh.layers,
+                )
+            }),
+    );
 
-    all_entities.sort_unstable_by_key(|(entity, mesh_id, _, _)| (mesh_id.0, *entity));
+    all_entities.sort_unstable_by_key(|(entity, mesh_id, ..)| (mesh_id.0, *entity));
 
     let total_count = all_entities.len();
     if total_count == 0 {
         cleanup_resources(&mut commands, existing_storage, existing_indirect);
+        if existing_layers.is_some() {
+            commands.remove_resource::<LayerDrawData>();
+        }
         return;
     }
 
-
     // Apply frustum culling to reduce entity count
-    let visible_entities: Vec<u32> = if let Some(frustum) = frustum {
+    if has_camera && !gpu_culling {
         let culling_start = std::time::Instant::now();
 
         // Pre-compute world-space AABBs for culling (avoid redundant calculations in hot loop)
-        let mut culling_data: Vec<(u32, Aabb)> = all_entities
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, (_, _, transform, aabb_opt))| {
+        culling_data.extend(all_entities.iter().enumerate().filter_map(
+            |(idx, (_, _, transform, aabb_opt, ..))| {
                 // Only include entities with explicit AABBs
                 aabb_opt.map(|aabb| {
                     // Pre-compute world-space AABB
@@ -78,19 +150,40 @@ pub fn prepare_indirect_draw_data(
                     };
                     (idx as u32, world_aabb)
                 })
-            })
-            .collect();
+            },
+        ));
 
-        let culling_config = CullingConfig {
-            enable_frustum: true,
-            max_render_distance, // Use actual camera far plane
-            grid_cell_size: 64.0, // Match terrain chunk size for spatial optimization
-        };
+        // Match terrain chunk size for spatial optimization
+        let grid_cell_size = 64.0;
 
         // Sort by spatial grid for better cache locality during culling
-        culling::sort_by_spatial_grid(&mut culling_data, culling_config.grid_cell_size);
-
-        let culling_result = frustum_cull_entities(&frustum, &culling_data, camera_pos, culling_config);
+        culling::sort_by_spatial_grid(culling_data, grid_cell_size);
+
+        visible_mask.resize(total_count, false);
+        for extracted_camera in &extracted.cameras {
+            let camera = &extracted_camera.camera;
+            let transform = &extracted_camera.transform;
+            let culling_config = CullingConfig {
+                enable_frustum: true,
+                max_render_distance: camera.far, // Use actual camera far plane, not magic number
+                grid_cell_size,
+            };
+
+            visible_entities.clear();
+            frustum_cull_entities_into(
+                &camera.frustum(transform),
+                culling_data,
+                transform.position(),
+                culling_config,
+                visible_entities,
+            );
+            for &idx in visible_entities.iter() {
+                let (.., layers) = all_entities[idx as usize];
+                if layers.intersects(extracted_camera.layers) {
+                    visible_mask[idx as usize] = true;
+                }
+            }
+        }
         let culling_elapsed = culling_start.elapsed();
 
         if let Some(profiler) = &mut profiler {
@@ -98,90 +191,134 @@ pub fn prepare_indirect_draw_data(
         }
 
         // Add back entities without AABBs (render them to be safe)
-        let mut visible_set = std::collections::HashSet::new();
-        for idx in culling_result.visible_indices {
-            visible_set.insert(idx);
-        }
-
-        // Include all entities that don't have AABBs
-        for (idx, (_, _, _, aabb_opt)) in all_entities.iter().enumerate() {
-            if aabb_opt.is_none() {
-                visible_set.insert(idx as u32);
+        for (idx, (_, _, _, aabb_opt, .., layers)) in all_entities.iter().enumerate() {
+            if aabb_opt.is_none() && layers.intersects(camera_layers) {
+                visible_mask[idx] = true;
             }
         }
 
-        visible_set.into_iter().collect()
+        visible_entities.clear();
+        visible_entities.extend(
+            visible_mask
+                .iter()
+                .enumerate()
+                .filter(|(_, visible)| **visible)
+                .map(|(idx, _)| idx as u32),
+        );
+    } else if has_camera {
+        // Culled on the GPU, render every entity a camera has a layer in common with
+        visible_entities.extend(
+            all_entities
+                .iter()
+                .enumerate()
+                .filter(|(_, (.., layers))| layers.intersects(camera_layers))
+                .map(|(idx, _)| idx as u32),
+        );
     } else {
         // No camera, render all entities
-        (0..total_count as u32).collect()
+        visible_entities.extend(0..total_count as u32);
+    }
+
+    group_visible_meshes(all_entities, visible_entities, mesh_groups);
+    record_draw_stats(
+        &mut stats,
+        total_count,
+        visible_entities.len(),
+        mesh_groups,
+        &gpu_mesh_cache,
+    );
+
+    let mut writer = BatchWriter {
+        device,
+        uploader,
+        indirect_commands,
+        rebuilds: &mut stats.buffer_rebuilds,
     };
+    let layer_batches = create_layer_batches(
+        &mut writer,
+        &gpu_mesh_cache,
+        &extracted,
+        all_entities,
+        mesh_groups,
+        &mut layer_groups,
+        existing_layers.as_deref(),
+    );
+    match (layer_batches.is_empty(), existing_layers.is_some()) {
+        (false, _) => commands.insert_resource(LayerDrawData {
+            layers: layer_batches,
+        }),
+        (true, true) => commands.remove_resource::<LayerDrawData>(),
+        (true, false) => {}
+    }
 
-    let mesh_groups = group_visible_meshes(&all_entities, &visible_entities);
-
-    // Try incremental update path for better performance
-    // Previously disabled with culling due to synchronization issues, now fixed with proper GPU sync
-    if transforms_changed && existing_storage.is_some() {
-        if let Some(storage_data) = &existing_storage {
-            if storage_data.entity_count == total_count {
-                storage::update_changed_uniforms(
-                    queue,
-                    &storage_data.buffer,
-                    &all_entities,
-                    &changed_query.iter().map(|(e, _, _, _)| e).collect(),
-                );
-
-                let batches = batching::create_draw_batches(
-                    device,
-                    queue,
-                    &gpu_mesh_cache,
-                    mesh_groups,
-                    existing_indirect.as_ref().map(|d| d.batches.as_slice()),
-                );
-
-                if !batches.is_empty() {
-                    commands.insert_resource(IndirectDrawData { batches });
-                }
+    // Try incremental update path for better performance.
+    // Writes go through the GpuUploader, which is flushed before the render graph submits.
+    let incremental = existing_storage
+        .as_ref()
+        .filter(|storage_data| transforms_changed && storage_data.entity_count == total_count);
+    if let Some(storage_data) = incremental {
+        changed.extend(
+            extracted
+                .meshes
+                .iter()
+                .filter(|mesh| mesh.needs_upload())
+                .map(|mesh| mesh.entity),
+        );
+        storage::update_changed_uniforms(
+            writer.uploader,
+            &storage_data.buffer,
+            all_entities,
+            changed,
+            model_uniforms,
+        );
 
-                record_profiling(&mut profiler, _start);
-                return;
-            }
+        let batches = batching::create_draw_batches(
+            &mut writer,
+            &gpu_mesh_cache,
+            mesh_groups,
+            existing_indirect.as_ref().map(|d| d.batches.as_slice()),
+        );
+
+        if !batches.is_empty() {
+            commands.insert_resource(IndirectDrawData { batches });
         }
+
+        record_profiling(&mut profiler, _start);
+        return;
     }
 
-    let model_uniforms = storage::compute_model_uniforms(&all_entities);
+    storage::compute_model_uniforms_into(all_entities, model_uniforms);
 
     if try_update_existing_storage(
         &mut commands,
-        device,
-        queue,
+        &mut writer,
         &gpu_mesh_cache,
         &existing_storage,
         &existing_indirect,
-        &model_uniforms,
-        total_count,
-        mesh_groups.clone(),
+        model_uniforms,
+        mesh_groups,
     ) {
         record_profiling(&mut profiler, _start);
         return;
     }
 
+    if existing_storage
+        .as_ref()
+        .is_none_or(|storage_data| storage_data.entity_count != total_count)
+    {
+        *writer.rebuilds += 1;
+    }
     storage::update_or_create_storage_buffer(
         &mut commands,
         device,
-        queue,
+        writer.uploader,
         &pipeline,
         existing_storage,
-        &model_uniforms,
+        model_uniforms,
         total_count,
     );
 
-    let batches = batching::create_draw_batches(
-        device,
-        queue,
-        &gpu_mesh_cache,
-        mesh_groups,
-        None,
-    );
+    let batches = batching::create_draw_batches(&mut writer, &gpu_mesh_cache, mesh_groups, None);
 
     log::warn!("Created {} batches, GPU cache has {} meshes, total_count: {}",
         batches.len(), gpu_mesh_cache.len(), total_count);
@@ -208,68 +345,147 @@ fn cleanup_resources(
     }
 }
 
+/// Batches for every distinct camera mask that leaves out some of the drawn instances.
+fn create_layer_batches(
+    writer: &mut BatchWriter,
+    gpu_mesh_cache: &GpuMeshCache,
+    extracted: &ExtractedScene,
+    all_entities: &[DrawEntity],
+    mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
+    layer_groups: &mut LayerGroups,
+    existing: Option<&LayerDrawData>,
+) -> Vec<(RenderLayers, Vec<MeshDrawBatch>)> {
+    let mut masks: Vec<RenderLayers> = extracted
+        .cameras
+        .iter()
+        .map(|camera| camera.layers)
+        .collect();
+    masks.sort_unstable_by_key(|mask| mask.0);
+    masks.dedup();
+
+    layer_groups.retain(|(mask, _)| masks.contains(mask));
+    let mut layers = Vec::new();
+    for mask in masks {
+        let groups = match layer_groups
+            .iter()
+            .position(|(existing, _)| *existing == mask)
+        {
+            Some(index) => &mut layer_groups[index].1,
+            None => {
+                layer_groups.push((mask, Default::default()));
+                &mut layer_groups.last_mut().unwrap().1
+            }
+        };
+        if !filter_groups_by_layers(all_entities, mesh_groups, mask, groups) {
+            continue;
+        }
+
+        let batches = batching::create_draw_batches(
+            writer,
+            gpu_mesh_cache,
+            groups,
+            existing.and_then(|existing| existing.batches_for(mask)),
+        );
+        layers.push((mask, batches));
+    }
+    layers
+}
+
+/// Fills `filtered` with the instances of `mesh_groups` on one of `layers`. Returns whether
+/// any instance was left out.
+fn filter_groups_by_layers(
+    all_entities: &[DrawEntity],
+    mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
+    layers: RenderLayers,
+    filtered: &mut ahash::AHashMap<AssetId, Vec<u32>>,
+) -> bool {
+    filtered.retain(|mesh_id, _| mesh_groups.contains_key(mesh_id));
+    let mut left_out = false;
+    for (mesh_id, instances) in mesh_groups {
+        let kept = filtered.entry(*mesh_id).or_default();
+        kept.clear();
+        kept.extend(instances.iter().copied().filter(|&idx| {
+            let (.., instance_layers) = all_entities[idx as usize];
+            instance_layers.intersects(layers)
+        }));
+        left_out |= kept.len() != instances.len();
+    }
+    left_out
+}
+
+fn record_draw_stats(
+    stats: &mut RenderStats,
+    total_count: usize,
+    visible_count: usize,
+    mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
+    gpu_mesh_cache: &GpuMeshCache,
+) {
+    stats.visible_entities = visible_count;
+    stats.culled_entities = total_count - visible_count;
+    for (mesh_id, instances) in mesh_groups {
+        if instances.is_empty() {
+            continue;
+        }
+        stats.batches += 1;
+        stats.indirect_commands += batching::instance_runs(instances).count();
+        stats.instances += instances.len();
+        if let Some(gpu_mesh) = gpu_mesh_cache.get(mesh_id) {
+            stats.triangles += (gpu_mesh.index_count / 3) as u64 * instances.len() as u64;
+        }
+    }
+}
+
 fn group_visible_meshes(
-    all_entities: &[(Entity, AssetId, GlobalTransform, Option<Aabb>)],
+    all_entities: &[DrawEntity],
     visible_instances: &[u32],
-) -> ahash::AHashMap<AssetId, Vec<u32>> {
-    let mut mesh_groups: ahash::AHashMap<AssetId, Vec<u32>> = ahash::AHashMap::new();
-
+    mesh_groups: &mut ahash::AHashMap<AssetId, Vec<u32>>,
+) {
     for &idx in visible_instances {
         let idx_usize = idx as usize;
         if idx_usize < all_entities.len() {
-            let (_entity, mesh_id, _, _) = &all_entities[idx_usize];
+            let (_entity, mesh_id, ..) = &all_entities[idx_usize];
             mesh_groups
                 .entry(*mesh_id)
                 .or_default()
                 .push(idx);
         }
     }
-
-    mesh_groups
 }
 
+/// Rewrites the model storage buffer in place when the entity count is unchanged.
+/// `model_uniforms` holds one uniform per drawn entity.
 fn try_update_existing_storage(
     commands: &mut Commands,
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    writer: &mut BatchWriter,
     gpu_mesh_cache: &GpuMeshCache,
     existing_storage: &Option<ResMut<ModelStorageData>>,
     existing_indirect: &Option<ResMut<IndirectDrawData>>,
     model_uniforms: &[crate::renderer::ModelUniform],
-    total_count: usize,
-    mesh_groups: ahash::AHashMap<AssetId, Vec<u32>>,
+    mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
 ) -> bool {
     let Some(storage_data) = existing_storage else {
         return false;
     };
 
-    if storage_data.entity_count != total_count {
+    if storage_data.entity_count != model_uniforms.len() {
         return false;
     }
 
-    queue.write_buffer(
+    writer.uploader.write_buffer(
         &storage_data.buffer,
         0,
         bytemuck::cast_slice(model_uniforms),
     );
 
-    // CRITICAL: Ensure GPU synchronization before render uses these buffers
-    // This fixes the flickering issue that previously required full rebuilds with culling enabled
-    // Submit an empty command buffer to flush pending write_buffer operations
-    let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("Buffer Write Sync"),
-    });
-    queue.submit(std::iter::once(encoder.finish()));
-
-    if let Some(existing) = existing_indirect {
-        if can_reuse_indirect_buffers(existing, &mesh_groups) {
-            return true;
-        }
+    if existing_indirect
+        .as_ref()
+        .is_some_and(|existing| can_reuse_indirect_buffers(existing, mesh_groups))
+    {
+        return true;
     }
 
     let batches = batching::create_draw_batches(
-        device,
-        queue,
+        writer,
         gpu_mesh_cache,
         mesh_groups,
         existing_indirect.as_ref().map(|d| d.batches.as_slice()),
@@ -286,7 +502,12 @@ fn can_reuse_indirect_buffers(
     existing_indirect: &IndirectDrawData,
     mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
 ) -> bool {
-    if existing_indirect.batches.len() != mesh_groups.len() {
+    // Groups stay in the frame allocator with no instances once a mesh goes out of view
+    let group_count = mesh_groups
+        .values()
+        .filter(|instances| !instances.is_empty())
+        .count();
+    if existing_indirect.batches.len() != group_count {
         return false;
     }
 
@@ -311,3 +532,53 @@ fn record_profiling(profiler: &mut Option<ResMut<crate::core::Profiler>>, start_
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::{Mat4, Vec3};
+    use crate::renderer::RenderFlags;
+    use crate::transform::GlobalTransform;
+
+    fn draw_entity(world: &mut World, layers: RenderLayers) -> DrawEntity {
+        (
+            world.spawn_empty().id(),
+            AssetId::new(1),
+            GlobalTransform::default(),
+            None,
+            Vec3::ONE,
+            Mat4::IDENTITY,
+            RenderFlags::NONE,
+            layers,
+        )
+    }
+
+    #[test]
+    fn test_filter_groups_by_layers() {
+        let mut world = World::new();
+        let entities = [
+            draw_entity(&mut world, RenderLayers::DEFAULT),
+            draw_entity(&mut world, RenderLayers::layer(3)),
+            draw_entity(&mut world, RenderLayers::DEFAULT.with(3)),
+        ];
+        let mut mesh_groups = ahash::AHashMap::new();
+        mesh_groups.insert(AssetId::new(1), vec![0, 1, 2]);
+        let mut filtered = ahash::AHashMap::new();
+
+        assert!(filter_groups_by_layers(
+            &entities,
+            &mesh_groups,
+            RenderLayers::DEFAULT,
+            &mut filtered
+        ));
+        assert_eq!(filtered[&AssetId::new(1)], vec![0, 2]);
+
+        assert!(!filter_groups_by_layers(
+            &entities,
+            &mesh_groups,
+            RenderLayers::ALL,
+            &mut filtered
+        ));
+        assert_eq!(filtered[&AssetId::new(1)], vec![0, 1, 2]);
+    }
+}
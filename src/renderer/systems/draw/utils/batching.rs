@@ -1,53 +1,93 @@
 use crate::assets::handle::AssetId;
-use crate::renderer::{GpuMeshCache, components::MeshDrawBatch, mesh::GpuMesh};
+use crate::renderer::{GpuMeshCache, GpuUploader, components::MeshDrawBatch, mesh::GpuMesh};
 use std::sync::Arc;
 
-pub fn create_indirect_commands(gpu_mesh: &GpuMesh, instances: &[u32]) -> Vec<u32> {
-    let mut commands = Vec::new();
-    for first_instance in instances.iter() {
+/// Replaces the contents of `commands` with instanced indexed indirect draws and
+/// returns how many were written.
+///
+/// Instances index the model storage buffer directly, so each run of consecutive
+/// indices becomes a single draw with `instance_count` set to the run length.
+/// `instances` must be sorted.
+pub fn write_indirect_commands(
+    gpu_mesh: &GpuMesh,
+    instances: &[u32],
+    commands: &mut Vec<u32>,
+) -> u32 {
+    commands.clear();
+    for (first_instance, instance_count) in instance_runs(instances) {
         commands.push(gpu_mesh.index_count);
-        commands.push(1u32);
+        commands.push(instance_count);
         commands.push(0u32);
         commands.push(0i32 as u32);
-        commands.push(*first_instance);
+        commands.push(first_instance);
     }
-    commands
+    (commands.len() / 5) as u32
+}
+
+/// Splits sorted instance indices into `(first, count)` runs of consecutive indices.
+pub fn instance_runs(instances: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    let mut rest = instances;
+    std::iter::from_fn(move || {
+        let (&first, _) = rest.split_first()?;
+        let len = rest
+            .iter()
+            .enumerate()
+            .take_while(|&(offset, &instance)| instance == first + offset as u32)
+            .count();
+        rest = &rest[len..];
+        Some((first, len as u32))
+    })
+}
+
+/// Where indirect buffers are written: the device for new buffers, the upload queue,
+/// scratch for the commands, and a count of buffers that had to be recreated.
+pub struct BatchWriter<'a> {
+    pub device: &'a wgpu::Device,
+    pub uploader: &'a mut GpuUploader,
+    pub indirect_commands: &'a mut Vec<u32>,
+    pub rebuilds: &'a mut usize,
 }
 
 pub fn create_or_update_indirect_buffer(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    writer: &mut BatchWriter,
     mesh_id: AssetId,
     gpu_mesh: Arc<GpuMesh>,
     instances: &[u32],
     existing_batch: Option<&MeshDrawBatch>,
-) -> (wgpu::Buffer, u32) {
-    let indirect_commands = create_indirect_commands(&gpu_mesh, instances);
+) -> (wgpu::Buffer, u32, u32) {
+    let draw_count = write_indirect_commands(&gpu_mesh, instances, writer.indirect_commands);
 
     if let Some(existing) = existing_batch {
         let instances_changed = existing.visible_instances.len() != instances.len()
             || existing.visible_instances != instances;
 
-        if instances.len() as u32 <= existing.buffer_capacity {
+        if draw_count <= existing.buffer_capacity {
             if instances_changed {
-                queue.write_buffer(
+                writer.uploader.write_buffer(
                     &existing.indirect_buffer,
                     0,
-                    bytemuck::cast_slice(&indirect_commands),
+                    bytemuck::cast_slice(writer.indirect_commands),
                 );
             }
-            return (existing.indirect_buffer.clone(), existing.buffer_capacity);
+            return (
+                existing.indirect_buffer.clone(),
+                existing.buffer_capacity,
+                draw_count,
+            );
         }
     }
 
-    let capacity = calculate_buffer_capacity(instances.len());
-    let buffer = create_indirect_buffer(device, mesh_id, capacity);
-    queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&indirect_commands));
-    (buffer, capacity)
+    let capacity = calculate_buffer_capacity(draw_count as usize);
+    let buffer = create_indirect_buffer(writer.device, mesh_id, capacity);
+    *writer.rebuilds += 1;
+    writer
+        .uploader
+        .write_buffer(&buffer, 0, bytemuck::cast_slice(writer.indirect_commands));
+    (buffer, capacity, draw_count)
 }
 
-fn calculate_buffer_capacity(instance_count: usize) -> u32 {
-    (instance_count as u32 * 3 / 2).max(instance_count as u32 + 16)
+fn calculate_buffer_capacity(draw_count: usize) -> u32 {
+    (draw_count as u32 * 3 / 2).max(draw_count as u32 + 16)
 }
 
 fn create_indirect_buffer(device: &wgpu::Device, mesh_id: AssetId, capacity: u32) -> wgpu::Buffer {
@@ -61,34 +101,35 @@ fn create_indirect_buffer(device: &wgpu::Device, mesh_id: AssetId, capacity: u32
 }
 
 pub fn create_draw_batches(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    writer: &mut BatchWriter,
     gpu_mesh_cache: &GpuMeshCache,
-    mesh_groups: ahash::AHashMap<AssetId, Vec<u32>>,
+    mesh_groups: &ahash::AHashMap<AssetId, Vec<u32>>,
     existing_batches: Option<&[MeshDrawBatch]>,
 ) -> Vec<MeshDrawBatch> {
     let mut batches = Vec::new();
 
-    for (mesh_id, instances) in mesh_groups {
+    for (&mesh_id, instances) in mesh_groups {
+        if instances.is_empty() {
+            continue;
+        }
         if let Some(gpu_mesh) = gpu_mesh_cache.get(&mesh_id) {
             let existing_batch = existing_batches
                 .and_then(|batches| batches.iter().find(|b| b.mesh_id == mesh_id));
 
-            let (indirect_buffer, buffer_capacity) = create_or_update_indirect_buffer(
-                device,
-                queue,
+            let (indirect_buffer, buffer_capacity, draw_count) = create_or_update_indirect_buffer(
+                writer,
                 mesh_id,
                 gpu_mesh,
-                &instances,
+                instances,
                 existing_batch,
             );
 
             batches.push(MeshDrawBatch {
                 mesh_id,
                 indirect_buffer,
-                draw_count: instances.len() as u32,
+                draw_count,
                 base_instance: instances[0],
-                visible_instances: instances,
+                visible_instances: instances.clone(),
                 buffer_capacity,
             });
         }
@@ -96,3 +137,15 @@ pub fn create_draw_batches(
 
     batches
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_runs_merge_consecutive_indices() {
+        let runs: Vec<_> = instance_runs(&[3, 4, 5, 9, 11, 12]).collect();
+        assert_eq!(runs, vec![(3, 3), (9, 1), (11, 2)]);
+        assert_eq!(instance_runs(&[]).count(), 0);
+    }
+}
@@ -1,13 +1,24 @@
-use crate::assets::handle::AssetId;
-use crate::core::math::Mat3;
-use crate::renderer::{ModelUniform, components::{Aabb, ModelStorageData}};
+use crate::core::math::{Mat3, Mat4, Vec3};
+use crate::renderer::systems::draw::frame_allocator::DrawEntity;
+use crate::renderer::{GpuUploader, ModelUniform, RenderFlags, components::ModelStorageData};
 use crate::transform::GlobalTransform;
 use bevy_ecs::prelude::*;
 use rayon::prelude::*;
 use std::collections::HashSet;
 use wgpu::util::DeviceExt;
 
-fn compute_uniform_for_transform(transform: &GlobalTransform) -> ModelUniform {
+/// Model data of an instance that hasn't moved since last frame.
+pub(crate) fn compute_model_uniform(transform: &GlobalTransform, color: Vec3) -> ModelUniform {
+    compute_moving_model_uniform(transform, transform.matrix(), color, false)
+}
+
+/// Model data with the previous frame's model matrix, for motion vectors.
+pub(crate) fn compute_moving_model_uniform(
+    transform: &GlobalTransform,
+    previous_model: Mat4,
+    color: Vec3,
+    receives_shadows: bool,
+) -> ModelUniform {
     let model_matrix = transform.matrix();
     let normal_matrix = Mat3::from_mat4(model_matrix).inverse().transpose();
     let normal_matrix_cols: [[f32; 4]; 3] = [
@@ -34,55 +45,90 @@ fn compute_uniform_for_transform(transform: &GlobalTransform) -> ModelUniform {
     ModelUniform {
         model: model_matrix.to_cols_array_2d(),
         normal_matrix: normal_matrix_cols,
+        color: color
+            .extend(if receives_shadows { 1.0 } else { 0.0 })
+            .to_array(),
+        previous_model: previous_model.to_cols_array_2d(),
     }
 }
 
-pub fn compute_model_uniforms(
-    entities: &[(Entity, AssetId, GlobalTransform, Option<Aabb>)],
-) -> Vec<ModelUniform> {
-    entities
-        .par_iter()
-        .map(|(_, _, transform, _)| compute_uniform_for_transform(transform))
-        .collect()
+/// Computes one [`ModelUniform`] per entity, writing into a reused buffer.
+pub fn compute_model_uniforms_into(entities: &[DrawEntity], uniforms: &mut Vec<ModelUniform>) {
+    uniforms.clear();
+    uniforms.par_extend(entities.par_iter().map(
+        |(_, _, transform, _, color, previous, flags, _)| {
+            compute_moving_model_uniform(
+                transform,
+                *previous,
+                *color,
+                flags.contains(RenderFlags::SHADOW_RECEIVER),
+            )
+        },
+    ));
 }
 
+/// Rewrites the uniforms of entities whose transform or color changed, one upload per run of adjacent changes.
 pub fn update_changed_uniforms(
-    queue: &wgpu::Queue,
+    uploader: &mut GpuUploader,
     storage_buffer: &wgpu::Buffer,
-    entities: &[(Entity, AssetId, GlobalTransform, Option<Aabb>)],
+    entities: &[DrawEntity],
     changed_entities: &HashSet<Entity>,
+    run: &mut Vec<ModelUniform>,
 ) {
-    for (idx, (entity, _, transform, _)) in entities.iter().enumerate() {
+    let mut run_start = 0;
+    run.clear();
+
+    for (idx, (entity, _, transform, _, color, previous, flags, _)) in entities.iter().enumerate() {
         if changed_entities.contains(entity) {
-            let uniform = compute_uniform_for_transform(transform);
-            let offset = (idx * std::mem::size_of::<ModelUniform>()) as u64;
-            queue.write_buffer(
-                storage_buffer,
-                offset,
-                bytemuck::cast_slice(&[uniform]),
-            );
+            if run.is_empty() {
+                run_start = idx;
+            }
+            run.push(compute_moving_model_uniform(
+                transform,
+                *previous,
+                *color,
+                flags.contains(RenderFlags::SHADOW_RECEIVER),
+            ));
+        } else if !run.is_empty() {
+            write_uniform_run(uploader, storage_buffer, run_start, run);
         }
     }
+
+    if !run.is_empty() {
+        write_uniform_run(uploader, storage_buffer, run_start, run);
+    }
+}
+
+fn write_uniform_run(
+    uploader: &mut GpuUploader,
+    storage_buffer: &wgpu::Buffer,
+    first_index: usize,
+    run: &mut Vec<ModelUniform>,
+) {
+    let offset = (first_index * std::mem::size_of::<ModelUniform>()) as u64;
+    uploader.write_buffer(storage_buffer, offset, bytemuck::cast_slice(run));
+    run.clear();
 }
 
 pub fn update_or_create_storage_buffer(
     commands: &mut Commands,
     device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    uploader: &mut GpuUploader,
     pipeline: &crate::renderer::MeshPipeline,
     existing_storage: Option<ResMut<ModelStorageData>>,
     model_uniforms: &[ModelUniform],
     total_count: usize,
 ) {
-    if let Some(ref storage_data) = existing_storage {
-        if storage_data.entity_count == total_count {
-            queue.write_buffer(
-                &storage_data.buffer,
-                0,
-                bytemuck::cast_slice(model_uniforms),
-            );
-            return;
-        }
+    let reusable = existing_storage
+        .as_ref()
+        .filter(|storage_data| storage_data.entity_count == total_count);
+    if let Some(storage_data) = reusable {
+        uploader.write_buffer(
+            &storage_data.buffer,
+            0,
+            bytemuck::cast_slice(model_uniforms),
+        );
+        return;
     }
 
     let model_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -1,11 +1,24 @@
-use crate::renderer::{MeshPipeline, Renderer, components::LightingData, lighting::LightingUniform};
+use crate::renderer::{
+    LightClusterPipeline, MeshPipeline, PointShadowPipeline, Renderer,
+    components::LightingData,
+    lighting::{
+        LightingUniform, PointLightUniform, PointShadowMaps,
+        clusters::{ClusterUniform, MAX_LIGHTS_PER_CLUSTER, cluster_count},
+        probes::LightProbeUniform,
+    },
+};
 use bevy_ecs::prelude::*;
 use wgpu::util::DeviceExt;
 
+const INITIAL_POINT_LIGHT_CAPACITY: usize = 16;
+const INITIAL_PROBE_CAPACITY: usize = 1;
+
 pub fn initialize_lighting(
     mut commands: Commands,
     renderer: Option<Res<Renderer>>,
     pipeline: Option<Res<MeshPipeline>>,
+    cluster_pipeline: Option<Res<LightClusterPipeline>>,
+    shadow_pipeline: Option<Res<PointShadowPipeline>>,
     lighting_data: Option<Res<LightingData>>,
 ) {
     if lighting_data.is_some() {
@@ -18,6 +31,12 @@ pub fn initialize_lighting(
     let Some(pipeline) = pipeline else {
         return;
     };
+    let Some(cluster_pipeline) = cluster_pipeline else {
+        return;
+    };
+    let Some(shadow_pipeline) = shadow_pipeline else {
+        return;
+    };
 
     let device = renderer.device();
     let default_lighting = LightingUniform::default();
@@ -28,19 +47,176 @@ pub fn initialize_lighting(
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
 
-    let lighting_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("Lighting Bind Group"),
-        layout: &pipeline.lighting_bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: lighting_buffer.as_entire_binding(),
-        }],
+    let point_light_buffer = create_point_light_buffer(device, INITIAL_POINT_LIGHT_CAPACITY);
+
+    let cluster_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Light Cluster Buffer"),
+        contents: bytemuck::cast_slice(&[ClusterUniform::default()]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
+    let cluster_light_counts = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cluster Light Count Buffer"),
+        size: cluster_count() as u64 * 4,
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    let cluster_light_indices = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cluster Light Index Buffer"),
+        size: (cluster_count() * MAX_LIGHTS_PER_CLUSTER) as u64 * 4,
+        usage: wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    // Grown by update_lighting once a light casts shadows
+    let point_shadows = PointShadowMaps::new(device, &shadow_pipeline, 1, 1);
+    // Grown by update_lighting once a LightProbeGrid is inserted
+    let probe_buffer = create_probe_buffer(device, INITIAL_PROBE_CAPACITY);
+
+    let bind_group = create_lighting_bind_group(
+        device,
+        &pipeline,
+        &LightingBuffers {
+            lighting: &lighting_buffer,
+            point_lights: &point_light_buffer,
+            cluster: &cluster_buffer,
+            cluster_light_counts: &cluster_light_counts,
+            cluster_light_indices: &cluster_light_indices,
+            probes: &probe_buffer,
+        },
+        &point_shadows,
+    );
+    let cluster_bind_group = create_cluster_bind_group(
+        device,
+        &cluster_pipeline,
+        &cluster_buffer,
+        &point_light_buffer,
+        &cluster_light_counts,
+        &cluster_light_indices,
+    );
 
     commands.insert_resource(LightingData {
         buffer: lighting_buffer,
-        bind_group: lighting_bind_group,
+        point_light_buffer,
+        point_light_capacity: INITIAL_POINT_LIGHT_CAPACITY,
+        cluster_buffer,
+        cluster_light_counts,
+        cluster_light_indices,
+        bind_group,
+        cluster_bind_group,
+        point_shadows,
+        probe_buffer,
+        probe_capacity: INITIAL_PROBE_CAPACITY,
+        probe_count: 0,
     });
 
     log::debug!("Initialized lighting system with default values");
 }
+
+pub(crate) fn create_point_light_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Point Light Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<PointLightUniform>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+pub(crate) fn create_probe_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Light Probe Buffer"),
+        size: (capacity.max(1) * std::mem::size_of::<LightProbeUniform>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Buffers bound in the lighting bind group.
+pub(crate) struct LightingBuffers<'a> {
+    pub lighting: &'a wgpu::Buffer,
+    pub point_lights: &'a wgpu::Buffer,
+    pub cluster: &'a wgpu::Buffer,
+    pub cluster_light_counts: &'a wgpu::Buffer,
+    pub cluster_light_indices: &'a wgpu::Buffer,
+    pub probes: &'a wgpu::Buffer,
+}
+
+pub(crate) fn create_lighting_bind_group(
+    device: &wgpu::Device,
+    pipeline: &MeshPipeline,
+    buffers: &LightingBuffers,
+    point_shadows: &PointShadowMaps,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Lighting Bind Group"),
+        layout: &pipeline.lighting_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffers.lighting.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: buffers.point_lights.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffers.cluster.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: buffers.cluster_light_counts.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: buffers.cluster_light_indices.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(&point_shadows.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&point_shadows.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: point_shadows.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: buffers.probes.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+pub(crate) fn create_cluster_bind_group(
+    device: &wgpu::Device,
+    pipeline: &LightClusterPipeline,
+    cluster_buffer: &wgpu::Buffer,
+    point_light_buffer: &wgpu::Buffer,
+    cluster_light_counts: &wgpu::Buffer,
+    cluster_light_indices: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Light Cluster Bind Group"),
+        layout: &pipeline.bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: cluster_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: point_light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: cluster_light_counts.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: cluster_light_indices.as_entire_binding(),
+            },
+        ],
+    })
+}
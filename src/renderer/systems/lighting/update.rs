@@ -1,22 +1,91 @@
+use super::initialize::{
+    LightingBuffers, create_cluster_bind_group, create_lighting_bind_group,
+    create_point_light_buffer, create_probe_buffer,
+};
+use crate::core::math::Vec3;
 use crate::renderer::{
-    Renderer,
+    Camera, GpuUploader, GraphicsSettings, LightClusterPipeline, MeshPipeline, PointShadowPipeline,
+    RenderTarget, Renderer,
     components::LightingData,
-    lighting::{AmbientLight, AmbientLightUniform, DirectionalLight, DirectionalLightUniform, LightingUniform},
+    graphics_settings::{DEFAULT_MAX_POINT_LIGHTS, DEFAULT_MAX_SHADOW_POINT_LIGHTS},
+    lighting::{
+        AmbientLight, AmbientLightUniform, DirectionalLight, DirectionalLightUniform, Fog,
+        FogUniform, LightProbeGrid, LightingUniform, PointLight, PointLightUniform,
+        PointShadowMaps,
+        clusters::ClusterUniform,
+        shadows::{
+            FACE_UNIFORM_STRIDE, PointShadowFaceUniform, PointShadowUniform, shadow_light_order,
+        },
+    },
 };
+use crate::transform::GlobalTransform;
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+
+/// Pipelines whose layouts the lighting resources are created with.
+#[derive(SystemParam)]
+pub struct LightingPipelines<'w> {
+    renderer: Option<Res<'w, Renderer>>,
+    pipeline: Option<Res<'w, MeshPipeline>>,
+    cluster_pipeline: Option<Res<'w, LightClusterPipeline>>,
+    shadow_pipeline: Option<Res<'w, PointShadowPipeline>>,
+}
+
+/// Scene-wide settings that feed the lighting uniform.
+#[derive(SystemParam)]
+pub struct LightingEnvironment<'w> {
+    graphics_settings: Option<Res<'w, GraphicsSettings>>,
+    fog: Option<Res<'w, Fog>>,
+    probe_grid: Option<Res<'w, LightProbeGrid>>,
+}
+
+#[derive(SystemParam)]
+pub struct SceneLights<'w, 's> {
+    directional: Query<'w, 's, &'static DirectionalLight>,
+    ambient: Query<'w, 's, &'static AmbientLight>,
+    point: Query<'w, 's, (&'static PointLight, Option<&'static GlobalTransform>)>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
 
 pub fn update_lighting(
-    renderer: Option<Res<Renderer>>,
-    lighting_data: Option<Res<LightingData>>,
+    pipelines: LightingPipelines,
+    environment: LightingEnvironment,
+    lighting_data: Option<ResMut<LightingData>>,
+    mut uploader: ResMut<GpuUploader>,
     mut profiler: Option<ResMut<crate::core::Profiler>>,
-    directional_light_query: Query<&DirectionalLight>,
-    ambient_light_query: Query<&AmbientLight>,
+    lights: SceneLights,
 ) {
     let _start = std::time::Instant::now();
+    let LightingPipelines {
+        renderer,
+        pipeline,
+        cluster_pipeline,
+        shadow_pipeline,
+    } = pipelines;
+    let LightingEnvironment {
+        graphics_settings,
+        fog,
+        probe_grid,
+    } = environment;
+    let SceneLights {
+        directional: directional_light_query,
+        ambient: ambient_light_query,
+        point: point_light_query,
+        cameras: camera_query,
+    } = lights;
     let Some(renderer) = renderer else {
         return;
     };
-    let Some(lighting_data) = lighting_data else {
+    let Some(pipeline) = pipeline else {
+        return;
+    };
+    let Some(cluster_pipeline) = cluster_pipeline else {
+        return;
+    };
+    let Some(shadow_pipeline) = shadow_pipeline else {
+        return;
+    };
+    let Some(mut lighting_data) = lighting_data else {
         return;
     };
 
@@ -32,20 +101,194 @@ pub fn update_lighting(
         .map(AmbientLightUniform::from_light)
         .unwrap_or_default();
 
+    let (max_point_lights, max_shadow_lights) = graphics_settings
+        .map(|settings| {
+            (
+                settings.max_point_lights(),
+                settings.max_shadow_point_lights(),
+            )
+        })
+        .unwrap_or((DEFAULT_MAX_POINT_LIGHTS, DEFAULT_MAX_SHADOW_POINT_LIGHTS));
+
+    // Clusters are built for, and shadows go to the lights nearest to, the first window camera
+    let main_camera = camera_query
+        .iter()
+        .filter(|(camera, _)| camera.target == RenderTarget::Window)
+        .min_by_key(|(camera, _)| camera.priority);
+
+    // A PointLight's position is relative to its entity when it has a GlobalTransform.
+    let lights: Vec<(PointLightUniform, &PointLight)> = point_light_query
+        .iter()
+        .map(|(light, global_transform)| {
+            let mut uniform = PointLightUniform::from_light(light);
+            if let Some(global_transform) = global_transform {
+                uniform.position = global_transform
+                    .matrix()
+                    .transform_point3(light.position)
+                    .to_array();
+            }
+            (uniform, light)
+        })
+        .collect();
+
+    // Shadowed lights go first, so each one's index is also its shadow slot
+    let device = renderer.device();
+    let max_slots = (device.limits().max_texture_array_layers / 6) as usize;
+    let viewer = main_camera.map_or(Vec3::ZERO, |(_, transform)| transform.position());
+    let casters: Vec<(Vec3, bool)> = lights
+        .iter()
+        .map(|(uniform, light)| (Vec3::from(uniform.position), light.cast_shadows))
+        .collect();
+    let (order, shadow_count) = shadow_light_order(
+        &casters,
+        viewer,
+        (max_shadow_lights as usize)
+            .min(max_point_lights as usize)
+            .min(max_slots),
+    );
+    let point_lights: Vec<PointLightUniform> = order
+        .iter()
+        .take(max_point_lights as usize)
+        .map(|&index| lights[index].0)
+        .collect();
+
+    let max_size = device.limits().max_texture_dimension_2d;
+    let resolutions: Vec<u32> = order[..shadow_count]
+        .iter()
+        .map(|&index| lights[index].1.shadow_resolution.clamp(1, max_size))
+        .collect();
+    let shadow_size = resolutions.iter().copied().max().unwrap_or(1);
+
+    let mut rebuild_bind_groups = false;
+    if point_lights.len() > lighting_data.point_light_capacity {
+        let new_capacity = point_lights.len().next_power_of_two();
+        lighting_data.point_light_buffer = create_point_light_buffer(device, new_capacity);
+        lighting_data.point_light_capacity = new_capacity;
+        rebuild_bind_groups = true;
+
+        log::debug!("Resized point light buffer to {} lights", new_capacity);
+    }
+
+    let shadows = &lighting_data.point_shadows;
+    if shadow_count as u32 > shadows.capacity || (shadow_count > 0 && shadow_size != shadows.size) {
+        let capacity = (shadow_count as u32).max(shadows.capacity);
+        lighting_data.point_shadows =
+            PointShadowMaps::new(device, &shadow_pipeline, capacity, shadow_size);
+        rebuild_bind_groups = true;
+
+        log::debug!(
+            "Resized point shadow maps to {} lights of {}x{}",
+            capacity,
+            shadow_size,
+            shadow_size
+        );
+    }
+
+    let probe_grid = probe_grid.filter(|grid| grid.is_valid());
+    if let Some(grid) = &probe_grid
+        && grid.probes.len() > lighting_data.probe_capacity
+    {
+        let new_capacity = grid.probes.len().next_power_of_two();
+        lighting_data.probe_buffer = create_probe_buffer(device, new_capacity);
+        lighting_data.probe_capacity = new_capacity;
+        lighting_data.probe_count = 0;
+        rebuild_bind_groups = true;
+
+        log::debug!("Resized light probe buffer to {} probes", new_capacity);
+    }
+
+    if rebuild_bind_groups {
+        lighting_data.bind_group = create_lighting_bind_group(
+            device,
+            &pipeline,
+            &LightingBuffers {
+                lighting: &lighting_data.buffer,
+                point_lights: &lighting_data.point_light_buffer,
+                cluster: &lighting_data.cluster_buffer,
+                cluster_light_counts: &lighting_data.cluster_light_counts,
+                cluster_light_indices: &lighting_data.cluster_light_indices,
+                probes: &lighting_data.probe_buffer,
+            },
+            &lighting_data.point_shadows,
+        );
+        lighting_data.cluster_bind_group = create_cluster_bind_group(
+            device,
+            &cluster_pipeline,
+            &lighting_data.cluster_buffer,
+            &lighting_data.point_light_buffer,
+            &lighting_data.cluster_light_counts,
+            &lighting_data.cluster_light_indices,
+        );
+    }
+
+    if !point_lights.is_empty() {
+        uploader.write_buffer(
+            &lighting_data.point_light_buffer,
+            0,
+            bytemuck::cast_slice(&point_lights),
+        );
+    }
+
+    if let Some(grid) = &probe_grid
+        && (grid.is_changed() || lighting_data.probe_count != grid.probes.len())
+    {
+        uploader.write_buffer(
+            &lighting_data.probe_buffer,
+            0,
+            bytemuck::cast_slice(&grid.gpu_probes()),
+        );
+        lighting_data.probe_count = grid.probes.len();
+    }
+
+    let shadows = &mut lighting_data.point_shadows;
+    shadows.resolutions = resolutions;
+    shadows.lights.clear();
+    for (slot, light) in point_lights[..shadow_count].iter().enumerate() {
+        let position = Vec3::from(light.position);
+        let uniform = PointShadowUniform::new(
+            position,
+            light.radius,
+            shadows.resolutions[slot],
+            shadows.size,
+        );
+        uploader.write_buffer(
+            &shadows.buffer,
+            (slot * std::mem::size_of::<PointShadowUniform>()) as u64,
+            bytemuck::cast_slice(&[uniform]),
+        );
+        // Faces are exactly FACE_UNIFORM_STRIDE apart
+        uploader.write_buffer(
+            &shadows.face_buffer,
+            slot as u64 * 6 * FACE_UNIFORM_STRIDE,
+            bytemuck::cast_slice(&PointShadowFaceUniform::faces(position, light.radius)),
+        );
+        shadows.lights.push((position, light.radius));
+    }
+
+    let light_count = point_lights.len() as u32;
+    let cluster_uniform = main_camera
+        .map(|(camera, transform)| ClusterUniform::from_camera(camera, transform, light_count))
+        .unwrap_or_else(|| ClusterUniform::disabled(light_count));
+    uploader.write_buffer(
+        &lighting_data.cluster_buffer,
+        0,
+        bytemuck::cast_slice(&[cluster_uniform]),
+    );
+
     let lighting_uniform = LightingUniform {
         directional: directional_uniform,
         ambient: ambient_uniform,
-        point_light_count: 0,
+        point_light_count: point_lights.len() as u32,
         ao_mode: 0, // SSAO removed
         ao_debug: 0, // SSAO removed
-        _padding1: 0.0,
-        _padding2: [0.0; 3],
-        _padding3: 0.0,
-        _padding4: [0.0; 3],
-        _padding5: 0.0,
+        point_shadow_count: shadow_count as u32,
+        fog: fog
+            .map(|fog| FogUniform::from_fog(&fog))
+            .unwrap_or_default(),
+        probes: probe_grid.map(|grid| grid.to_uniform()).unwrap_or_default(),
     };
 
-    renderer.queue().write_buffer(
+    uploader.write_buffer(
         &lighting_data.buffer,
         0,
         bytemuck::cast_slice(&[lighting_uniform]),
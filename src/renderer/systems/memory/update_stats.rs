@@ -18,5 +18,6 @@ pub fn update_gpu_memory_stats(
     memory_tracker.track_depth_texture(depth_size);
     memory_tracker.track_ssao_textures(0); // SSAO removed
     memory_tracker.track_msaa_textures(msaa_size);
+    memory_tracker.track_hdr_textures(renderer.calculate_hdr_memory());
     memory_tracker.track_camera_buffer(camera_buffer_size);
 }
@@ -16,17 +16,12 @@ pub fn cleanup_unused_meshes(
     let cached_ids: Vec<AssetId> = gpu_mesh_cache.iter_ids().collect();
 
     for mesh_id in cached_ids {
-        if !active_mesh_ids.contains(&mesh_id) {
-            if let Some(_) = gpu_mesh_cache.remove(&mesh_id) {
-                if let Some(ref mut tracker) = memory_tracker {
-                    tracker.untrack_mesh_gpu(&mesh_id);
-                }
-
-                log::debug!(
-                    "Cleaned up GPU mesh: {:?} (no longer referenced)",
-                    mesh_id
-                );
+        if !active_mesh_ids.contains(&mesh_id) && gpu_mesh_cache.remove(&mesh_id).is_some() {
+            if let Some(ref mut tracker) = memory_tracker {
+                tracker.untrack_mesh_gpu(&mesh_id);
             }
+
+            log::debug!("Cleaned up GPU mesh: {:?} (no longer referenced)", mesh_id);
         }
     }
 }
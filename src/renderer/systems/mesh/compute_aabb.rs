@@ -7,7 +7,7 @@ pub fn compute_mesh_aabbs(
 ) {
     static FRAME_COUNTER: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
     let frame_num = FRAME_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    let should_log = frame_num % 300 == 0;
+    let should_log = frame_num.is_multiple_of(300);
 
     let mut computed_count = 0;
     for (entity, mesh, _) in query.iter() {
@@ -2,6 +2,6 @@ mod upload;
 mod cleanup;
 mod compute_aabb;
 
-pub use upload::upload_meshes;
-pub use cleanup::{cleanup_unused_meshes, cleanup_mesh_components};
+pub use cleanup::{cleanup_mesh_components, cleanup_unused_meshes};
 pub use compute_aabb::compute_mesh_aabbs;
+pub use upload::{replace_placeholder_meshes, upload_meshes};
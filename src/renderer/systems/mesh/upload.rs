@@ -1,5 +1,8 @@
+use crate::assets::{AssetId, Assets, MeshData};
+use crate::core::AssetLoaded;
 use crate::renderer::{GpuMeshCache, Renderer, components::{Mesh, MeshUploaded}, mesh::GpuMesh};
 use bevy_ecs::prelude::*;
+use std::sync::Arc;
 
 pub fn upload_meshes(
     mut commands: Commands,
@@ -59,3 +62,46 @@ pub fn upload_meshes(
         }
     }
 }
+
+/// Swaps the placeholder in [`Mesh`] handles for the real asset once it has loaded,
+/// and re-uploads it.
+pub fn replace_placeholder_meshes(
+    mut commands: Commands,
+    assets: Option<Res<Assets>>,
+    mut loaded: MessageReader<AssetLoaded>,
+    mut gpu_mesh_cache: Option<ResMut<GpuMeshCache>>,
+    mut memory_tracker: Option<ResMut<crate::core::MemoryTracker>>,
+    mut query: Query<(Entity, &mut Mesh)>,
+) {
+    let Some(assets) = assets else {
+        loaded.clear();
+        return;
+    };
+
+    for message in loaded.read().filter(|message| message.success) {
+        let id = AssetId::from_path(&message.path);
+        let Some(asset) = assets.get::<Vec<MeshData>>(id) else {
+            continue;
+        };
+
+        let mut replaced = false;
+        for (entity, mut mesh) in &mut query {
+            if mesh.handle.id == id && !Arc::ptr_eq(&mesh.handle.asset, &asset) {
+                mesh.handle.asset = Arc::clone(&asset);
+                commands.entity(entity).remove::<MeshUploaded>();
+                replaced = true;
+            }
+        }
+        if !replaced {
+            continue;
+        }
+
+        let removed = gpu_mesh_cache
+            .as_mut()
+            .is_some_and(|gpu_mesh_cache| gpu_mesh_cache.remove(&id).is_some());
+        if let Some(tracker) = memory_tracker.as_mut().filter(|_| removed) {
+            tracker.untrack_mesh_gpu(&id);
+        }
+        log::debug!("Replaced placeholder mesh with {}", message.path);
+    }
+}
@@ -4,8 +4,14 @@ pub mod lighting;
 pub mod camera;
 pub mod memory;
 
-pub use mesh::{upload_meshes, compute_mesh_aabbs, cleanup_unused_meshes, cleanup_mesh_components};
-pub use draw::prepare_indirect_draw_data;
-pub use lighting::{initialize_lighting, update_lighting};
 pub use camera::update_camera_aspect_ratio;
+pub use draw::{
+    FrameAllocator, GpuCullData, prepare_gpu_culling, prepare_indirect_draw_data,
+    prepare_point_shadow_draws,
+};
+pub use lighting::{initialize_lighting, update_lighting};
 pub use memory::update_gpu_memory_stats;
+pub use mesh::{
+    cleanup_mesh_components, cleanup_unused_meshes, compute_mesh_aabbs, replace_placeholder_meshes,
+    upload_meshes,
+};
@@ -0,0 +1,150 @@
+//! Targets and camera jitter for temporal anti-aliasing.
+//!
+//! While [`AntiAliasing::Taa`](crate::renderer::AntiAliasing::Taa) is selected, the
+//! [`Renderer`](crate::renderer::Renderer) owns a [`TaaHistory`]. The main pass offsets
+//! the window camera's projection by a sub-pixel [`jitter_offset`] that changes every
+//! frame and writes per-pixel motion vectors, and the [`TaaNode`](crate::renderer::TaaNode)
+//! blends the frame with the history reprojected along them and stores the result as the
+//! next frame's history.
+//!
+//! Motion vectors cover camera and object motion; disocclusions still rely on the
+//! neighborhood clamp in the resolve to avoid ghosting. The history follows the first
+//! window camera.
+
+use crate::core::math::*;
+use wgpu::{Device, Texture, TextureView};
+
+/// Length of the jitter sequence before it repeats.
+pub const JITTER_SEQUENCE_LENGTH: u64 = 8;
+
+/// Element `index` of the Halton low-discrepancy sequence with the given base, in `0..1`.
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Sub-pixel projection offset of `frame` in NDC units, within half a pixel each way.
+pub fn jitter_offset(frame: u64, size: (u32, u32)) -> Vec2 {
+    // Halton starts at 0, which would leave the first sample unjittered
+    let index = (frame % JITTER_SEQUENCE_LENGTH) as u32 + 1;
+    let sample = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+    // One pixel spans 2 / size in NDC
+    sample * 2.0 / Vec2::new(size.0.max(1) as f32, size.1.max(1) as f32)
+}
+
+/// Ping-pong history textures, recreated with the window.
+pub struct TaaHistory {
+    history_textures: [Texture; 2],
+    history_views: [TextureView; 2],
+    size: (u32, u32),
+    /// Frames rendered since the history was created.
+    frame: u64,
+}
+
+impl TaaHistory {
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let create = |label: &str| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: crate::renderer::HDR_FORMAT,
+                // COPY_SRC to copy the resolved frame back into the HDR target
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        };
+
+        let history_textures = [
+            create("TAA History Texture 0"),
+            create("TAA History Texture 1"),
+        ];
+        let history_views = [
+            history_textures[0].create_view(&wgpu::TextureViewDescriptor::default()),
+            history_textures[1].create_view(&wgpu::TextureViewDescriptor::default()),
+        ];
+
+        Self {
+            history_textures,
+            history_views,
+            size: (width, height),
+            frame: 0,
+        }
+    }
+
+    /// Camera jitter of the current frame in NDC units.
+    pub fn jitter(&self) -> Vec2 {
+        jitter_offset(self.frame, self.size)
+    }
+
+    /// False on the first frame, when the history holds no previous frame yet.
+    pub fn is_valid(&self) -> bool {
+        self.frame > 0
+    }
+
+    /// Last frame's resolved color.
+    pub fn read_view(&self) -> &TextureView {
+        &self.history_views[(self.frame as usize + 1) % 2]
+    }
+
+    /// Where this frame's resolved color goes.
+    pub fn write_view(&self) -> &TextureView {
+        &self.history_views[self.frame as usize % 2]
+    }
+
+    pub fn write_texture(&self) -> &Texture {
+        &self.history_textures[self.frame as usize % 2]
+    }
+
+    /// Swaps the history textures. Called once per rendered frame.
+    pub fn advance(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Marks the history as empty, like on the first frame.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+    }
+
+    pub fn memory_size(&self) -> u64 {
+        let pixels = (self.size.0 * self.size.1) as u64;
+        // Two Rgba16Float histories
+        pixels * 8 * 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_half_pixel() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(2, 3), 2.0 / 3.0);
+
+        let size = (1920, 1080);
+        let pixel = Vec2::new(2.0 / 1920.0, 2.0 / 1080.0);
+        let mut offsets = Vec::new();
+        for frame in 0..JITTER_SEQUENCE_LENGTH {
+            let offset = jitter_offset(frame, size);
+            assert!(offset.x.abs() <= pixel.x * 0.5 && offset.y.abs() <= pixel.y * 0.5);
+            assert!(!offsets.contains(&offset));
+            offsets.push(offset);
+        }
+        assert_eq!(jitter_offset(JITTER_SEQUENCE_LENGTH, size), offsets[0]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Uploading loaded [`TextureData`] to the GPU.
+//!
+//! Block-compressed textures from KTX2 and DDS files are uploaded as-is, which keeps them at a
+//! quarter to an eighth of their RGBA8 size in VRAM and skips decoding at load. Whether a format
+//! can be used depends on the adapter: desktop GPUs support BC, most mobile GPUs only ASTC.
+//! Check with [`supports_texture_format`] and ship the matching variant.
+
+use crate::assets::{TextureData, TextureFormat};
+use anyhow::{Result, anyhow};
+use wgpu::util::DeviceExt;
+use wgpu::{Device, Features, Queue, Texture};
+
+/// Compression features the renderer enables when the adapter has them.
+pub const COMPRESSION_FEATURES: Features =
+    Features::TEXTURE_COMPRESSION_BC.union(Features::TEXTURE_COMPRESSION_ASTC);
+
+/// The GPU format `format` is uploaded as. RGB8 data is expanded to RGBA8.
+pub fn gpu_texture_format(format: TextureFormat) -> wgpu::TextureFormat {
+    match format {
+        TextureFormat::Rgba8 | TextureFormat::Rgb8 => wgpu::TextureFormat::Rgba8UnormSrgb,
+        TextureFormat::R8 => wgpu::TextureFormat::R8Unorm,
+        TextureFormat::Bc1 => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+        TextureFormat::Bc2 => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+        TextureFormat::Bc3 => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+        TextureFormat::Bc4 => wgpu::TextureFormat::Bc4RUnorm,
+        TextureFormat::Bc5 => wgpu::TextureFormat::Bc5RgUnorm,
+        TextureFormat::Bc7 => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        TextureFormat::Astc4x4 => wgpu::TextureFormat::Astc {
+            block: wgpu::AstcBlock::B4x4,
+            channel: wgpu::AstcChannel::UnormSrgb,
+        },
+    }
+}
+
+pub fn supports_texture_format(device: &Device, format: TextureFormat) -> bool {
+    device
+        .features()
+        .contains(gpu_texture_format(format).required_features())
+}
+
+/// Creates a sampled texture with every mip level of `data`.
+pub fn create_texture(
+    device: &Device,
+    queue: &Queue,
+    data: &TextureData,
+    label: Option<&str>,
+) -> Result<Texture> {
+    if !supports_texture_format(device, data.format) {
+        return Err(anyhow!(
+            "{:?} textures are not supported by this adapter",
+            data.format
+        ));
+    }
+
+    let expanded;
+    let bytes = if data.format == TextureFormat::Rgb8 {
+        expanded = expand_rgb8(&data.data);
+        &expanded
+    } else {
+        &data.data
+    };
+
+    Ok(device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: data.width,
+                height: data.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: data.mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: gpu_texture_format(data.format),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        bytes,
+    ))
+}
+
+/// RGB8 pixels as RGBA8, since GPUs have no three-channel 8-bit format.
+pub(crate) fn expand_rgb8(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks_exact(3)
+        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+        .collect()
+}
@@ -0,0 +1,485 @@
+//! Streaming texture mip levels in and out of VRAM under a budget.
+//!
+//! Entities with a [`StreamedTexture`] ask for their texture at a detail level that depends
+//! on their distance to the nearest camera: full resolution within
+//! [`TextureStreamer::full_detail_distance`], one mip level less for every doubling beyond it.
+//! A texture first becomes resident at its smallest mip levels and gains a level at a time,
+//! with at most [`TextureStreamer::upload_bytes_per_frame`] uploaded per frame, so loading a
+//! level never stalls a frame.
+//!
+//! When the wanted levels exceed [`GraphicsSettings::texture_budget`], the least important
+//! textures drop their largest levels first: those not used for the longest, then those
+//! farthest away. Textures unused for [`TextureStreamer::evict_after_frames`] are released.
+//!
+//! [`TextureStreamer::view`] covers the resident levels. Gaining or dropping levels replaces
+//! the texture and its view, so bind groups made from an earlier view must be rebuilt. The
+//! material pipeline doesn't bind streamed textures yet.
+
+use crate::assets::{AssetId, Assets, TextureData, TextureFormat};
+use crate::core::{MemoryTracker, TextureStreamingStats};
+use crate::renderer::texture::{expand_rgb8, gpu_texture_format, supports_texture_format};
+use crate::renderer::{Camera, GraphicsSettings, Renderer};
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use wgpu::{Device, Queue, Texture, TextureView};
+
+/// Streams the texture's mip levels based on this entity's distance to the camera.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StreamedTexture {
+    pub texture: AssetId,
+}
+
+impl StreamedTexture {
+    pub fn new(texture: AssetId) -> Self {
+        Self { texture }
+    }
+}
+
+pub struct ResidentTexture {
+    texture: Texture,
+    view: TextureView,
+    source: Arc<TextureData>,
+    /// Most detailed level on the GPU. Levels `resident_mip..mip_level_count` are resident.
+    resident_mip: u32,
+    /// Level the last request asked for.
+    desired_mip: u32,
+    distance: f32,
+    last_used_frame: u64,
+}
+
+impl ResidentTexture {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn resident_mip(&self) -> u32 {
+        self.resident_mip
+    }
+
+    pub fn memory_size(&self) -> u64 {
+        mip_chain_size(&self.source, self.resident_mip)
+    }
+}
+
+#[derive(Resource)]
+pub struct TextureStreamer {
+    textures: HashMap<AssetId, ResidentTexture>,
+    /// Formats the adapter can't sample, already warned about.
+    unsupported: HashSet<AssetId>,
+    /// Distance within which textures are wanted at full resolution.
+    pub full_detail_distance: f32,
+    pub upload_bytes_per_frame: u64,
+    pub evict_after_frames: u64,
+    frame: u64,
+}
+
+impl Default for TextureStreamer {
+    fn default() -> Self {
+        Self {
+            textures: HashMap::new(),
+            unsupported: HashSet::new(),
+            full_detail_distance: 10.0,
+            upload_bytes_per_frame: 8 * 1024 * 1024,
+            evict_after_frames: 600,
+            frame: 0,
+        }
+    }
+}
+
+impl TextureStreamer {
+    pub fn get(&self, texture: AssetId) -> Option<&ResidentTexture> {
+        self.textures.get(&texture)
+    }
+
+    /// View of the resident levels, replaced whenever they change.
+    pub fn view(&self, texture: AssetId) -> Option<&TextureView> {
+        self.textures.get(&texture).map(ResidentTexture::view)
+    }
+
+    pub fn resident_bytes(&self) -> u64 {
+        self.textures
+            .values()
+            .map(ResidentTexture::memory_size)
+            .sum()
+    }
+
+    /// Applies this frame's requests: evicts to fit `budget`, then uploads what fits.
+    fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        requests: HashMap<AssetId, (Arc<TextureData>, f32)>,
+        budget: u64,
+    ) -> TextureStreamingStats {
+        self.frame += 1;
+        let frame = self.frame;
+        let mut stats = TextureStreamingStats {
+            budget,
+            ..Default::default()
+        };
+
+        for (id, (source, distance)) in requests {
+            if self.unsupported.contains(&id) {
+                continue;
+            }
+            if !supports_texture_format(device, source.format) {
+                log::warn!(
+                    "Streamed texture {:?} uses {:?}, which this adapter can't sample",
+                    id,
+                    source.format
+                );
+                self.unsupported.insert(id);
+                continue;
+            }
+
+            let desired_mip = desired_mip(distance, self.full_detail_distance, lowest_mip(&source));
+            let resident = self.textures.entry(id).or_insert_with(|| {
+                let resident_mip = lowest_mip(&source);
+                let (texture, view) = create_resident(device, &source, resident_mip);
+                for level in resident_mip..source.mip_level_count {
+                    upload_mip(queue, &texture, &source, level, resident_mip);
+                }
+                stats.uploaded_bytes_last_frame += mip_chain_size(&source, resident_mip);
+                ResidentTexture {
+                    texture,
+                    view,
+                    source,
+                    resident_mip,
+                    desired_mip,
+                    distance,
+                    last_used_frame: frame,
+                }
+            });
+            resident.desired_mip = desired_mip;
+            resident.distance = distance;
+            resident.last_used_frame = frame;
+        }
+
+        let evict_after = self.evict_after_frames;
+        self.textures
+            .retain(|_, resident| frame - resident.last_used_frame <= evict_after);
+
+        // Least important first: unused for longest, then farthest
+        let mut order: Vec<AssetId> = self.textures.keys().copied().collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.textures[a], &self.textures[b]);
+            a.last_used_frame
+                .cmp(&b.last_used_frame)
+                .then(b.distance.total_cmp(&a.distance))
+        });
+
+        let mut plan: Vec<MipPlan> = order
+            .iter()
+            .map(|id| {
+                let resident = &self.textures[id];
+                MipPlan {
+                    level_sizes: level_sizes(&resident.source),
+                    lowest: lowest_mip(&resident.source),
+                    target: resident.desired_mip,
+                }
+            })
+            .collect();
+        fit_budget(&mut plan, budget);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Streaming Encoder"),
+        });
+        let mut upload_budget = self.upload_bytes_per_frame;
+
+        // Evictions free memory before anything new is uploaded
+        for (id, plan) in order.iter().zip(&plan) {
+            let resident = self.textures.get_mut(id).unwrap();
+            if plan.target > resident.resident_mip {
+                stats.evicted_mips += (plan.target - resident.resident_mip) as u64;
+                resize_resident(device, &mut encoder, resident, plan.target);
+            }
+        }
+
+        // Upgrades, most important first
+        for (id, plan) in order.iter().zip(&plan).rev() {
+            let resident = self.textures.get_mut(id).unwrap();
+            if plan.target >= resident.resident_mip {
+                continue;
+            }
+
+            // Gain as many levels as fit this frame, but always at least one
+            let mut new_mip = resident.resident_mip - 1;
+            let mut bytes = plan.level_sizes[new_mip as usize];
+            if bytes > upload_budget && upload_budget < self.upload_bytes_per_frame {
+                stats.pending_textures += 1;
+                continue;
+            }
+            while new_mip > plan.target
+                && bytes + plan.level_sizes[new_mip as usize - 1] <= upload_budget
+            {
+                new_mip -= 1;
+                bytes += plan.level_sizes[new_mip as usize];
+            }
+            upload_budget = upload_budget.saturating_sub(bytes);
+            stats.uploaded_bytes_last_frame += bytes;
+
+            let previous_mip = resident.resident_mip;
+            resize_resident(device, &mut encoder, resident, new_mip);
+            for level in new_mip..previous_mip {
+                upload_mip(queue, &resident.texture, &resident.source, level, new_mip);
+            }
+            if new_mip > plan.target {
+                stats.pending_textures += 1;
+            }
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+        stats.resident_textures = self.textures.len() as u32;
+        stats
+    }
+}
+
+/// Gathers the streamed textures in view of the cameras and updates their residency.
+pub fn stream_textures(
+    renderer: Option<Res<Renderer>>,
+    settings: Option<Res<GraphicsSettings>>,
+    assets: Option<Res<Assets>>,
+    streamer: Option<ResMut<TextureStreamer>>,
+    memory_tracker: Option<ResMut<MemoryTracker>>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    textures: Query<(&StreamedTexture, &GlobalTransform)>,
+) {
+    let (Some(renderer), Some(assets), Some(mut streamer)) = (renderer, assets, streamer) else {
+        return;
+    };
+
+    let camera_positions: Vec<_> = cameras.iter().map(|camera| camera.position()).collect();
+    let mut requests: HashMap<AssetId, (Arc<TextureData>, f32)> = HashMap::new();
+    for (streamed, transform) in &textures {
+        let distance = camera_positions
+            .iter()
+            .map(|camera| camera.distance(transform.position()))
+            .fold(f32::INFINITY, f32::min);
+        if let Some((_, nearest)) = requests.get_mut(&streamed.texture) {
+            *nearest = nearest.min(distance);
+            continue;
+        }
+        let Some(source) = assets.get::<TextureData>(streamed.texture) else {
+            continue;
+        };
+        requests.insert(streamed.texture, (source, distance));
+    }
+
+    let budget = settings.map_or(crate::renderer::DEFAULT_TEXTURE_BUDGET, |settings| {
+        settings.texture_budget()
+    });
+    let stats = streamer.update(renderer.device(), renderer.queue(), requests, budget);
+
+    if let Some(mut memory_tracker) = memory_tracker {
+        memory_tracker.track_texture_streaming(streamer.resident_bytes(), stats);
+    }
+}
+
+struct MipPlan {
+    level_sizes: Vec<u64>,
+    /// Least detailed level the texture can be reduced to.
+    lowest: u32,
+    target: u32,
+}
+
+/// Drops the largest levels of the first (least important) textures until the plan fits.
+fn fit_budget(plan: &mut [MipPlan], budget: u64) {
+    let size = |entry: &MipPlan| -> u64 { entry.level_sizes[entry.target as usize..].iter().sum() };
+    let mut total: u64 = plan.iter().map(size).sum();
+
+    for entry in plan.iter_mut() {
+        while total > budget && entry.target < entry.lowest {
+            total -= entry.level_sizes[entry.target as usize];
+            entry.target += 1;
+        }
+    }
+}
+
+/// One level less for every doubling of distance beyond `full_detail_distance`.
+fn desired_mip(distance: f32, full_detail_distance: f32, lowest: u32) -> u32 {
+    if distance <= full_detail_distance || full_detail_distance <= 0.0 {
+        return 0;
+    }
+    ((distance / full_detail_distance).log2().ceil() as u32).min(lowest)
+}
+
+/// Block-compressed textures need the base of the resident chain to be whole blocks.
+fn lowest_mip(source: &TextureData) -> u32 {
+    let last = source.mip_level_count.saturating_sub(1);
+    if !source.format.is_compressed() {
+        return last;
+    }
+    (0..=last)
+        .rev()
+        .find(|&level| {
+            (source.width >> level).is_multiple_of(4) && (source.height >> level).is_multiple_of(4)
+        })
+        .unwrap_or(0)
+}
+
+fn upload_format(source: &TextureData) -> TextureFormat {
+    match source.format {
+        TextureFormat::Rgb8 => TextureFormat::Rgba8,
+        format => format,
+    }
+}
+
+fn level_size(source: &TextureData, level: u32) -> usize {
+    upload_format(source).level_size(source.width >> level, source.height >> level)
+}
+
+fn level_sizes(source: &TextureData) -> Vec<u64> {
+    (0..source.mip_level_count)
+        .map(|level| level_size(source, level) as u64)
+        .collect()
+}
+
+fn mip_chain_size(source: &TextureData, from_mip: u32) -> u64 {
+    level_sizes(source)[from_mip as usize..].iter().sum()
+}
+
+/// Size of `level` as a copy extent, rounded up to whole blocks for compressed formats.
+fn level_extent(source: &TextureData, level: u32) -> wgpu::Extent3d {
+    let width = (source.width >> level).max(1);
+    let height = (source.height >> level).max(1);
+    let (width, height) = if source.format.is_compressed() {
+        (width.div_ceil(4) * 4, height.div_ceil(4) * 4)
+    } else {
+        (width, height)
+    };
+    wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    }
+}
+
+fn create_resident(device: &Device, source: &TextureData, base_mip: u32) -> (Texture, TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Streamed Texture"),
+        size: level_extent(source, base_mip),
+        mip_level_count: source.mip_level_count - base_mip,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: gpu_texture_format(source.format),
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+/// Replaces the GPU texture with one whose most detailed level is `new_mip`, copying the
+/// levels both have. Levels the old texture lacked must be uploaded afterwards.
+fn resize_resident(
+    device: &Device,
+    encoder: &mut wgpu::CommandEncoder,
+    resident: &mut ResidentTexture,
+    new_mip: u32,
+) {
+    let (texture, view) = create_resident(device, &resident.source, new_mip);
+    for level in resident.resident_mip.max(new_mip)..resident.source.mip_level_count {
+        encoder.copy_texture_to_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &resident.texture,
+                mip_level: level - resident.resident_mip,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: level - new_mip,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            level_extent(&resident.source, level),
+        );
+    }
+    resident.texture = texture;
+    resident.view = view;
+    resident.resident_mip = new_mip;
+}
+
+/// Writes source `level` into a texture whose most detailed level is `base_mip`.
+fn upload_mip(queue: &Queue, texture: &Texture, source: &TextureData, level: u32, base_mip: u32) {
+    let offset: usize = (0..level)
+        .map(|level| level_source_size(source, level))
+        .sum();
+    let data = &source.data[offset..offset + level_source_size(source, level)];
+    let expanded;
+    let data = if source.format == TextureFormat::Rgb8 {
+        expanded = expand_rgb8(data);
+        &expanded[..]
+    } else {
+        data
+    };
+
+    let width = (source.width >> level).max(1);
+    let height = (source.height >> level).max(1);
+    let format = upload_format(source);
+    let (bytes_per_row, rows) = match format.block_bytes() {
+        Some(block) => (width.div_ceil(4) * block, height.div_ceil(4)),
+        None => (width * format.channels(), height),
+    };
+
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: level - base_mip,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_row),
+            rows_per_image: Some(rows),
+        },
+        level_extent(source, level),
+    );
+}
+
+/// Size of `level` in the source data, before RGB8 is expanded.
+fn level_source_size(source: &TextureData, level: u32) -> usize {
+    source
+        .format
+        .level_size(source.width >> level, source.height >> level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_drops_least_important_first() {
+        assert_eq!(desired_mip(5.0, 10.0, 8), 0);
+        assert_eq!(desired_mip(20.0, 10.0, 8), 1);
+        assert_eq!(desired_mip(35.0, 10.0, 8), 2);
+        assert_eq!(desired_mip(1.0e6, 10.0, 8), 8);
+
+        // Two 64 + 16 + 4 byte chains wanting full detail, 100 bytes available
+        let mut plan = vec![
+            MipPlan {
+                level_sizes: vec![64, 16, 4],
+                lowest: 2,
+                target: 0,
+            },
+            MipPlan {
+                level_sizes: vec![64, 16, 4],
+                lowest: 2,
+                target: 0,
+            },
+        ];
+        fit_budget(&mut plan, 100);
+        assert_eq!(plan[0].target, 2);
+        assert_eq!(plan[1].target, 0);
+    }
+}
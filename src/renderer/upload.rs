@@ -0,0 +1,151 @@
+//! Batched CPU to GPU buffer uploads.
+//!
+//! Systems and render nodes record writes into [`GpuUploader`] instead of calling
+//! `queue.write_buffer` directly. The data is packed into one reused staging buffer
+//! and copied with a single command buffer when the uploader is flushed, which happens
+//! once after `PostUpdate` and once before the render graph submits.
+
+use bevy_ecs::prelude::*;
+use std::sync::{Arc, Mutex};
+
+/// Default size of a staging chunk. Larger flushes get a dedicated chunk.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1 << 20;
+
+struct PendingCopy {
+    target: wgpu::Buffer,
+    target_offset: u64,
+    data_offset: u64,
+    size: u64,
+}
+
+#[derive(Resource)]
+pub struct GpuUploader {
+    chunk_size: u64,
+    data: Vec<u8>,
+    copies: Vec<PendingCopy>,
+    /// Chunks used by submitted copies, remapped by [`recall`](Self::recall).
+    in_flight: Vec<wgpu::Buffer>,
+    /// Mapped chunks ready for the next flush. Filled from map callbacks.
+    free: Arc<Mutex<Vec<wgpu::Buffer>>>,
+}
+
+impl Default for GpuUploader {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+impl GpuUploader {
+    pub fn new(chunk_size: u64) -> Self {
+        Self {
+            chunk_size: chunk_size.max(wgpu::COPY_BUFFER_ALIGNMENT),
+            data: Vec::new(),
+            copies: Vec::new(),
+            in_flight: Vec::new(),
+            free: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Queues `data` to be written to `target` at `offset` on the next flush.
+    ///
+    /// Same rules as `wgpu::Queue::write_buffer`: `target` needs `COPY_DST` usage and
+    /// both `offset` and the data length must be multiples of 4.
+    pub fn write_buffer(&mut self, target: &wgpu::Buffer, offset: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        debug_assert_eq!(offset % wgpu::COPY_BUFFER_ALIGNMENT, 0);
+        debug_assert_eq!(data.len() as u64 % wgpu::COPY_BUFFER_ALIGNMENT, 0);
+
+        let data_offset = self.data.len() as u64;
+        self.data.extend_from_slice(data);
+        self.copies.push(PendingCopy {
+            target: target.clone(),
+            target_offset: offset,
+            data_offset,
+            size: data.len() as u64,
+        });
+    }
+
+    /// Number of bytes queued since the last flush.
+    pub fn pending_bytes(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Copies the queued data into a staging chunk and records the buffer copies.
+    ///
+    /// Submit the returned command buffer before any work that reads the targets,
+    /// then call [`recall`](Self::recall).
+    pub fn finish(&mut self, device: &wgpu::Device) -> Option<wgpu::CommandBuffer> {
+        if self.copies.is_empty() {
+            return None;
+        }
+
+        let size = self.data.len() as u64;
+        let chunk = self.take_chunk(device, size);
+        chunk
+            .slice(..size)
+            .get_mapped_range_mut()
+            .copy_from_slice(&self.data);
+        chunk.unmap();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Upload Encoder"),
+        });
+        for copy in self.copies.drain(..) {
+            encoder.copy_buffer_to_buffer(
+                &chunk,
+                copy.data_offset,
+                &copy.target,
+                copy.target_offset,
+                copy.size,
+            );
+        }
+
+        self.data.clear();
+        self.in_flight.push(chunk);
+        Some(encoder.finish())
+    }
+
+    /// Remaps chunks from submitted uploads so later flushes can reuse them.
+    pub fn recall(&mut self, device: &wgpu::Device) {
+        for chunk in self.in_flight.drain(..) {
+            let free = Arc::clone(&self.free);
+            let buffer = chunk.clone();
+            chunk
+                .slice(..)
+                .map_async(wgpu::MapMode::Write, move |result| {
+                    if result.is_ok() {
+                        free.lock().unwrap().push(buffer);
+                    }
+                });
+        }
+
+        crate::renderer::capture::poll_gpu(device);
+    }
+
+    /// Flushes and submits queued writes on their own.
+    pub fn submit(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(commands) = self.finish(device) {
+            queue.submit(std::iter::once(commands));
+            self.recall(device);
+        }
+    }
+
+    fn take_chunk(&mut self, device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+        let mut free = self.free.lock().unwrap();
+        if let Some(index) = free.iter().position(|chunk| chunk.size() >= size) {
+            return free.swap_remove(index);
+        }
+        drop(free);
+
+        let chunk_size = size.div_ceil(self.chunk_size) * self.chunk_size;
+        log::debug!("Allocating {} byte upload chunk", chunk_size);
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Upload Staging Chunk"),
+            size: chunk_size,
+            usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: true,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+//! Water surfaces with planar reflections.
+//!
+//! Every [`WaterSurface`] gets a reflection camera mirrored about the water plane that
+//! renders the scene into a [`RenderTexture`] before the window camera. The
+//! [`WaterPassNode`](crate::renderer::WaterPassNode) then draws the surface as a quad,
+//! sampling the reflection with animated wave distortion and blending it with the
+//! water color by a Fresnel term.
+//!
+//! ```ignore
+//! commands.spawn((
+//!     WaterSurface::new(Vec2::new(200.0, 200.0)),
+//!     Transform::from_xyz(0.0, 2.0, 0.0),
+//!     GlobalTransform::default(),
+//! ));
+//! ```
+//!
+//! The reflection camera sees everything, including geometry below the water plane.
+//! Surfaces are horizontal; the entity's rotation is ignored.
+
+use crate::core::math::*;
+use crate::renderer::{Camera, RenderTarget, RenderTargets, RenderTexture, Renderer};
+use crate::transform::{GlobalTransform, Transform};
+use bevy_ecs::prelude::*;
+
+/// A horizontal water plane at its entity's height, centered on its position.
+#[derive(Component, Clone, Debug)]
+pub struct WaterSurface {
+    /// Extent along world X and Z.
+    pub size: Vec2,
+    /// Color seen when looking straight down.
+    pub color: Vec3,
+    /// Opacity when looking straight down; reflections are always opaque.
+    pub opacity: f32,
+    /// How far waves shift the reflection, in screen UV units.
+    pub distortion: f32,
+    pub wave_speed: f32,
+    /// Exponent of the Fresnel term. Higher keeps the water see-through at steeper angles.
+    pub fresnel_power: f32,
+    /// Reflection resolution relative to the window camera's viewport.
+    pub reflection_scale: f32,
+}
+
+impl WaterSurface {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3, opacity: f32) -> Self {
+        self.color = color;
+        self.opacity = opacity;
+        self
+    }
+}
+
+impl Default for WaterSurface {
+    fn default() -> Self {
+        Self {
+            size: Vec2::splat(100.0),
+            color: Vec3::new(0.02, 0.12, 0.18),
+            opacity: 0.8,
+            distortion: 0.02,
+            wave_speed: 1.0,
+            fresnel_power: 5.0,
+            reflection_scale: 0.5,
+        }
+    }
+}
+
+/// Reflection camera and texture of a [`WaterSurface`], managed by the renderer.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WaterReflection {
+    pub camera: Entity,
+    pub texture: RenderTexture,
+}
+
+/// Marks cameras owned by a [`WaterReflection`], pointing back at the surface.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ReflectionCamera {
+    pub surface: Entity,
+}
+
+/// Mirrors a camera about the horizontal plane at `height`.
+///
+/// The result keeps +Y as up, so its image is the reflection flipped vertically;
+/// the water shader samples it with `v` inverted.
+pub fn mirror_camera_transform(transform: &GlobalTransform, height: f32) -> Transform {
+    let position = transform.position();
+    let forward = transform.rotation() * Vec3::NEG_Z;
+
+    let mirrored_position = Vec3::new(position.x, 2.0 * height - position.y, position.z);
+    let mirrored_forward = Vec3::new(forward.x, -forward.y, forward.z);
+    Transform::looking_at(
+        mirrored_position,
+        mirrored_position + mirrored_forward,
+        Vec3::Y,
+    )
+}
+
+/// Creates, resizes and positions reflection cameras for every [`WaterSurface`].
+///
+/// Runs after transform propagation and before render extraction, so the mirrored
+/// cameras take part in culling for this frame.
+pub fn update_water_reflections(
+    mut commands: Commands,
+    renderer: Option<Res<Renderer>>,
+    mut render_targets: ResMut<RenderTargets>,
+    surfaces: Query<
+        (
+            Entity,
+            &WaterSurface,
+            &GlobalTransform,
+            Option<&WaterReflection>,
+        ),
+        Without<ReflectionCamera>,
+    >,
+    cameras: Query<(&Camera, &GlobalTransform), Without<ReflectionCamera>>,
+    mut reflection_cameras: Query<(
+        Entity,
+        &ReflectionCamera,
+        &mut Camera,
+        &mut Transform,
+        &mut GlobalTransform,
+    )>,
+) {
+    // Drop reflections whose surface was despawned or lost its WaterSurface
+    for (camera_entity, reflection_camera, camera, ..) in &reflection_cameras {
+        if surfaces.contains(reflection_camera.surface) {
+            continue;
+        }
+        commands.entity(camera_entity).despawn();
+        if let RenderTarget::Texture(id) = camera.target {
+            render_targets.remove(id);
+        }
+        if let Ok(mut surface) = commands.get_entity(reflection_camera.surface) {
+            surface.remove::<WaterReflection>();
+        }
+    }
+
+    let Some(renderer) = renderer else { return };
+    // Reflect what the first window camera sees
+    let Some((main_camera, main_transform)) = cameras
+        .iter()
+        .filter(|(camera, _)| camera.target == RenderTarget::Window)
+        .min_by_key(|(camera, _)| camera.priority)
+    else {
+        return;
+    };
+
+    let (_, _, viewport_width, viewport_height) = main_camera
+        .viewport
+        .unwrap_or_default()
+        .to_pixels(renderer.size());
+
+    for (entity, surface, transform, reflection) in &surfaces {
+        let scale = surface.reflection_scale.clamp(0.05, 1.0);
+        let width = (viewport_width * scale).max(1.0) as u32;
+        let height = (viewport_height * scale).max(1.0) as u32;
+
+        let mut camera = Camera::new(
+            main_camera.fov,
+            main_camera.aspect,
+            main_camera.near,
+            main_camera.far,
+        )
+        .with_projection(main_camera.projection);
+        camera.priority = main_camera.priority - 1;
+        let mirrored = mirror_camera_transform(main_transform, transform.position().y);
+
+        match reflection {
+            Some(reflection) => {
+                render_targets.resize(reflection.texture.id(), width, height);
+                if let Ok((_, _, mut existing, mut camera_transform, mut global)) =
+                    reflection_cameras.get_mut(reflection.camera)
+                {
+                    camera.target = existing.target;
+                    *existing = camera;
+                    *camera_transform = mirrored;
+                    *global = GlobalTransform::from_transform(&mirrored);
+                }
+            }
+            None => {
+                let texture = RenderTexture::new(&mut render_targets, width, height);
+                let camera_entity = commands
+                    .spawn((
+                        camera.with_target(texture.into()),
+                        mirrored,
+                        GlobalTransform::from_transform(&mirrored),
+                        ReflectionCamera { surface: entity },
+                    ))
+                    .id();
+                commands.entity(entity).insert(WaterReflection {
+                    camera: camera_entity,
+                    texture,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_camera_about_plane() {
+        let camera = Transform::looking_at(Vec3::new(0.0, 5.0, 10.0), Vec3::ZERO, Vec3::Y);
+        let mirrored = mirror_camera_transform(&GlobalTransform::from_transform(&camera), 1.0);
+
+        assert!((mirrored.position - Vec3::new(0.0, -3.0, 10.0)).length() < 1e-5);
+        // Looks up at the point mirrored about the plane
+        let forward = mirrored.rotation * Vec3::NEG_Z;
+        let expected = (Vec3::new(0.0, 2.0, 0.0) - mirrored.position).normalize();
+        assert!((forward - expected).length() < 1e-4);
+    }
+}
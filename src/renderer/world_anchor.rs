@@ -0,0 +1,96 @@
+//! Screen positions of world-space points, for UI that follows entities.
+//!
+//! Give a character a [`WorldAnchor`] and read its [`screen_position`](WorldAnchor::screen_position)
+//! each frame to place a nameplate or health bar over it:
+//!
+//! ```ignore
+//! commands.spawn((Transform::default(), WorldAnchor::new(Vec3::Y * 2.2)));
+//!
+//! fn draw_nameplates(anchors: Query<(&WorldAnchor, &Nameplate)>) {
+//!     for (anchor, nameplate) in &anchors {
+//!         if let (Some(position), true) = (anchor.screen_position, anchor.on_screen) {
+//!             draw_label(position, &nameplate.text, 1.0 / anchor.distance.max(1.0));
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::core::math::*;
+use crate::renderer::{Camera, RenderTarget, RenderTargets, Renderer};
+use crate::transform::GlobalTransform;
+use bevy_ecs::prelude::*;
+
+/// Projects a point attached to the entity onto the screen every frame.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct WorldAnchor {
+    /// Offset from the entity's origin in world space, e.g. above a character's head.
+    pub offset: Vec3,
+    /// Camera to project with. `None` uses the lowest priority camera drawing to the window.
+    pub camera: Option<Entity>,
+    /// Pixels from the top-left corner of the camera's render target. `None` while the point
+    /// is behind the camera or there is no camera.
+    pub screen_position: Option<Vec2>,
+    /// Whether `screen_position` lies inside the camera's viewport.
+    pub on_screen: bool,
+    /// Distance from the camera to the point, for scaling or fading UI with range.
+    pub distance: f32,
+}
+
+impl WorldAnchor {
+    pub fn new(offset: Vec3) -> Self {
+        Self {
+            offset,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_camera(mut self, camera: Entity) -> Self {
+        self.camera = Some(camera);
+        self
+    }
+}
+
+/// Updates every [`WorldAnchor`] from this frame's transforms.
+///
+/// Runs after transform propagation so anchors follow entities without a frame of lag.
+pub fn update_world_anchors(
+    renderer: Option<Res<Renderer>>,
+    render_targets: Option<Res<RenderTargets>>,
+    cameras: Query<(Entity, &Camera, &GlobalTransform)>,
+    mut anchors: Query<(&mut WorldAnchor, &GlobalTransform)>,
+) {
+    let window_size = renderer.map(|renderer| renderer.size());
+    let window_camera = cameras
+        .iter()
+        .filter(|(_, camera, _)| camera.target == RenderTarget::Window)
+        .min_by_key(|(entity, camera, _)| (camera.priority, *entity))
+        .map(|(entity, ..)| entity);
+
+    for (mut anchor, transform) in &mut anchors {
+        let camera = anchor
+            .camera
+            .or(window_camera)
+            .and_then(|entity| cameras.get(entity).ok());
+        let target_size = camera.and_then(|(_, camera, _)| match camera.target {
+            RenderTarget::Window => window_size,
+            RenderTarget::Texture(id) => {
+                render_targets.as_ref().and_then(|targets| targets.size(id))
+            }
+        });
+        let (Some((_, camera, camera_transform)), Some(target_size)) = (camera, target_size) else {
+            anchor.screen_position = None;
+            anchor.on_screen = false;
+            continue;
+        };
+
+        let point = transform.position() + anchor.offset;
+        let screen_position = camera.world_to_screen(camera_transform, point, target_size);
+        let (x, y, width, height) = camera.viewport.unwrap_or_default().to_pixels(target_size);
+        anchor.on_screen = screen_position.is_some_and(|position| {
+            position.cmpge(Vec2::new(x, y)).all()
+                && position.cmple(Vec2::new(x + width, y + height)).all()
+        });
+        anchor.screen_position = screen_position;
+        anchor.distance = point.distance(camera_transform.position());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::{EntityMap, RemapEntities};
 use bevy_ecs::prelude::*;
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
@@ -13,6 +14,12 @@ impl Parent {
     }
 }
 
+impl RemapEntities for Parent {
+    fn remap_entities(&mut self, map: &EntityMap) {
+        self.0 = map.get_mapped(self.0);
+    }
+}
+
 #[derive(Component, Debug, Clone, Default)]
 pub struct Children(pub Vec<Entity>);
 
@@ -47,3 +54,11 @@ impl Children {
         self.0.is_empty()
     }
 }
+
+impl RemapEntities for Children {
+    fn remap_entities(&mut self, map: &EntityMap) {
+        for child in &mut self.0 {
+            *child = map.get_mapped(*child);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Smooth rendering of entities moved in [`Stage::FixedUpdate`].
+//!
+//! Fixed steps don't line up with frames, so an entity moved only in the fixed update
+//! stutters at high refresh rates: some frames show two steps of movement, others none.
+//! [`TransformInterpolationPlugin`] keeps the last two fixed-step poses of every entity with
+//! [`TransformInterpolation`] and renders it between them by [`FixedTime::alpha`], one step
+//! behind the simulation.
+//!
+//! Systems in `Update` and the fixed update see the simulated `Transform`; it is swapped
+//! for the interpolated pose in `PostUpdate` and restored in `PreUpdate`. Moving an entity
+//! outside the fixed update snaps it to the new pose. Call
+//! [`snap`](TransformInterpolation::snap) after teleporting it in the fixed update.
+//!
+//! Poses are recorded once per frame, so when several fixed steps run in one frame the
+//! entity is interpolated across all of them.
+
+use super::components::Transform;
+use super::systems::{propagate_transforms, sync_simple_transforms};
+use crate::app::{Plugin, Resonance, Stage};
+use crate::core::{FixedTime, GameTick};
+use bevy_ecs::prelude::*;
+
+/// Renders the entity between its last two fixed-update poses.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct TransformInterpolation {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+impl TransformInterpolation {
+    /// Jumps straight to the simulated pose instead of interpolating towards it.
+    pub fn snap(&mut self) {
+        self.current = None;
+    }
+
+    /// Pose `alpha` of the way from the previous fixed step to the latest, or `None` before
+    /// the first step was recorded.
+    pub fn interpolate(&self, alpha: f32) -> Option<Transform> {
+        let (previous, current) = (self.previous?, self.current?);
+        Some(Transform {
+            position: previous.position.lerp(current.position, alpha),
+            rotation: previous.rotation.slerp(current.rotation, alpha),
+            scale: previous.scale.lerp(current.scale, alpha),
+        })
+    }
+}
+
+/// Puts the simulated pose back before anything reads or moves the entity this frame.
+pub fn restore_interpolated_transforms(
+    mut query: Query<(&mut Transform, &TransformInterpolation)>,
+) {
+    for (mut transform, interpolation) in &mut query {
+        if let Some(current) = interpolation.current
+            && *transform != current
+        {
+            *transform = current;
+        }
+    }
+}
+
+/// Records poses from fixed steps that ran this frame and writes the interpolated pose.
+pub fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    tick: Res<GameTick>,
+    mut last_tick: Local<u64>,
+    mut query: Query<(&mut Transform, &mut TransformInterpolation)>,
+) {
+    let stepped = tick.get() != *last_tick;
+    *last_tick = tick.get();
+    let alpha = fixed_time.alpha().clamp(0.0, 1.0);
+
+    for (mut transform, mut interpolation) in &mut query {
+        match interpolation.current {
+            Some(current) if stepped => {
+                interpolation.previous = Some(current);
+                interpolation.current = Some(*transform);
+            }
+            Some(current) if current == *transform => {}
+            // First frame, snapped, or moved outside the fixed update
+            _ => {
+                interpolation.previous = Some(*transform);
+                interpolation.current = Some(*transform);
+            }
+        }
+
+        if let Some(interpolated) = interpolation.interpolate(alpha)
+            && *transform != interpolated
+        {
+            *transform = interpolated;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TransformInterpolationPlugin;
+
+impl Plugin for TransformInterpolationPlugin {
+    fn build(&self, engine: &mut Resonance) {
+        use bevy_ecs::schedule::IntoScheduleConfigs;
+
+        *engine = std::mem::take(engine)
+            .add_systems(Stage::PreUpdate, restore_interpolated_transforms)
+            .add_systems(
+                Stage::PostUpdate,
+                interpolate_transforms
+                    .before(sync_simple_transforms)
+                    .before(propagate_transforms),
+            );
+    }
+
+    fn dependencies(&self) -> Vec<(std::any::TypeId, &str)> {
+        vec![(
+            std::any::TypeId::of::<super::TransformPlugin>(),
+            "resonance::transform::TransformPlugin",
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::*;
+
+    #[test]
+    fn test_interpolate_between_fixed_steps() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new(60));
+        world.insert_resource(GameTick::new());
+        let entity = world
+            .spawn((Transform::default(), TransformInterpolation::default()))
+            .id();
+        let mut restore = Schedule::default();
+        restore.add_systems(restore_interpolated_transforms);
+        let mut interpolate = Schedule::default();
+        interpolate.add_systems(interpolate_transforms);
+
+        interpolate.run(&mut world);
+
+        // A fixed step moves the entity, half a step is left over
+        restore.run(&mut world);
+        world.get_mut::<Transform>(entity).unwrap().position = Vec3::X;
+        world.resource_mut::<GameTick>().increment();
+        world
+            .resource_mut::<FixedTime>()
+            .accumulate(std::time::Duration::from_secs_f32(0.5 / 60.0));
+        interpolate.run(&mut world);
+        let rendered = world.get::<Transform>(entity).unwrap().position;
+        assert!(rendered.abs_diff_eq(Vec3::X * 0.5, 1e-3));
+
+        // The simulation continues from where it left off
+        restore.run(&mut world);
+        assert_eq!(world.get::<Transform>(entity).unwrap().position, Vec3::X);
+    }
+}
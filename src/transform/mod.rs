@@ -1,8 +1,10 @@
 pub mod components;
 pub mod hierarchy;
+pub mod interpolation;
 pub mod plugin;
 pub mod systems;
 
 pub use components::{GlobalTransform, Transform};
 pub use hierarchy::{Children, Parent};
+pub use interpolation::{TransformInterpolation, TransformInterpolationPlugin};
 pub use plugin::TransformPlugin;
@@ -1,5 +1,7 @@
+use super::hierarchy::{Children, Parent};
 use super::systems::{propagate_transforms, sync_simple_transforms};
 use crate::app::{Plugin, Resonance, Stage};
+use crate::core::EntityRefRegistry;
 
 #[derive(Default)]
 pub struct TransformPlugin;
@@ -14,6 +16,12 @@ impl Plugin for TransformPlugin {
     fn build(&self, engine: &mut Resonance) {
         use bevy_ecs::schedule::IntoScheduleConfigs;
 
+        engine
+            .world
+            .get_resource_or_init::<EntityRefRegistry>()
+            .register::<Parent>()
+            .register::<Children>();
+
         // IMPORTANT: System ordering for transform sync.
         // propagate_transforms MUST run AFTER sync_simple_transforms to ensure:
         // 1. Simple entities (no parents) have their GlobalTransform updated from Transform
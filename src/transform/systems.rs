@@ -58,9 +58,10 @@ pub fn propagate_transforms(
     }
 }
 
-pub fn sync_simple_transforms(
-    mut query: Query<(&Transform, &mut GlobalTransform), (Without<Parent>, Without<Children>)>,
-) {
+/// Entities outside any hierarchy.
+type Standalone = (Without<Parent>, Without<Children>);
+
+pub fn sync_simple_transforms(mut query: Query<(&Transform, &mut GlobalTransform), Standalone>) {
     for (transform, mut global_transform) in query.iter_mut() {
         let new_global = GlobalTransform::from_transform(transform);
         if *global_transform != new_global {
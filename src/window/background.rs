@@ -0,0 +1,154 @@
+//! What the engine does while its window doesn't have focus.
+//!
+//! [`BackgroundPolicy`] is part of the [`WindowConfig`](crate::window::WindowConfig).
+//! [`update_background_state`] follows the window's `Focused` events and applies the audio
+//! side; the window runner caps the frame rate and the engine runner skips `Stage::Update`
+//! while [`BackgroundState::update_paused`] is set. Every other stage keeps running, so
+//! connections stay alive and fixed-update simulation doesn't fall behind the server.
+
+use crate::audio::AudioBackend;
+use crate::window::{WindowConfig, WindowEvent};
+use bevy_ecs::prelude::*;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundPolicy {
+    /// Frame rate cap while unfocused. `None` keeps running at full speed.
+    pub max_fps: Option<u32>,
+    /// Master volume while unfocused: 1 leaves audio alone, 0 mutes it.
+    pub audio_volume: f32,
+    /// Skips `Stage::Update` while unfocused.
+    pub pause_update: bool,
+}
+
+impl BackgroundPolicy {
+    /// Keeps running as if focused.
+    pub const NONE: Self = Self {
+        max_fps: None,
+        audio_volume: 1.0,
+        pause_update: false,
+    };
+
+    /// 15 FPS and audio ducked to a quarter, which suits most games.
+    pub fn throttled() -> Self {
+        Self {
+            max_fps: Some(15),
+            audio_volume: 0.25,
+            pause_update: false,
+        }
+    }
+
+    pub fn with_max_fps(mut self, max_fps: u32) -> Self {
+        self.max_fps = Some(max_fps);
+        self
+    }
+
+    pub fn with_audio_volume(mut self, volume: f32) -> Self {
+        self.audio_volume = volume.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn muted(self) -> Self {
+        self.with_audio_volume(0.0)
+    }
+
+    pub fn with_pause_update(mut self, pause: bool) -> Self {
+        self.pause_update = pause;
+        self
+    }
+
+    /// Shortest time between frames while unfocused.
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.max_fps
+            .map(|fps| Duration::from_secs_f64(1.0 / fps.max(1) as f64))
+    }
+}
+
+impl Default for BackgroundPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct BackgroundState {
+    /// The window lost focus and the [`BackgroundPolicy`] applies.
+    pub in_background: bool,
+    policy: BackgroundPolicy,
+    /// Master volume from before going to the background.
+    restore_volume: Option<f32>,
+}
+
+impl BackgroundState {
+    pub fn update_paused(&self) -> bool {
+        self.in_background && self.policy.pause_update
+    }
+
+    /// Shortest time between frames right now, if capped.
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.policy.frame_time().filter(|_| self.in_background)
+    }
+}
+
+pub fn update_background_state(
+    mut state: ResMut<BackgroundState>,
+    mut events: MessageReader<WindowEvent>,
+    config: Option<Res<WindowConfig>>,
+    audio: Option<Res<AudioBackend>>,
+) {
+    let Some(focused) = events
+        .read()
+        .filter_map(|event| match event {
+            WindowEvent::Focused(focused) => Some(*focused),
+            _ => None,
+        })
+        .last()
+    else {
+        return;
+    };
+    if state.in_background != focused {
+        return;
+    }
+
+    state.in_background = !focused;
+    if let Some(config) = config {
+        state.policy = config.background;
+    }
+    log::debug!(
+        "Window {}, background policy: {:?}",
+        if focused { "focused" } else { "unfocused" },
+        state.policy
+    );
+
+    let Some(audio) = audio else { return };
+    if state.in_background {
+        if state.policy.audio_volume < 1.0 {
+            state.restore_volume = Some(audio.master_volume());
+            audio.set_master_volume(audio.master_volume() * state.policy.audio_volume);
+        }
+    } else if let Some(volume) = state.restore_volume.take() {
+        audio.set_master_volume(volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_applies_only_in_background() {
+        let mut state = BackgroundState {
+            policy: BackgroundPolicy::throttled().with_pause_update(true),
+            ..Default::default()
+        };
+        assert_eq!(state.frame_time(), None);
+        assert!(!state.update_paused());
+
+        state.in_background = true;
+        assert_eq!(
+            state.frame_time(),
+            Some(Duration::from_secs_f64(1.0 / 15.0))
+        );
+        assert!(state.update_paused());
+    }
+}
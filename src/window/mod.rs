@@ -1,7 +1,10 @@
+pub mod background;
 pub mod plugin;
 pub mod runner;
+#[allow(clippy::module_inception)]
 pub mod window;
 
+pub use background::{BackgroundPolicy, BackgroundState, update_background_state};
 pub use plugin::WindowPlugin;
 pub use runner::run;
 pub use window::{Window, WindowConfig, WindowEvent, WindowMode};
@@ -1,5 +1,5 @@
-use crate::app::{Plugin, Resonance};
-use crate::window::WindowConfig;
+use crate::app::{Plugin, Resonance, Stage};
+use crate::window::{BackgroundState, WindowConfig, update_background_state};
 
 #[derive(Default)]
 pub struct WindowPlugin {
@@ -33,5 +33,10 @@ impl Plugin for WindowPlugin {
         engine
             .world
             .init_resource::<bevy_ecs::prelude::Messages<WindowEvent>>();
+        engine.world.init_resource::<BackgroundState>();
+
+        if let Some(schedule) = engine.schedules.get_mut(Stage::PreUpdate) {
+            schedule.add_systems(update_background_state);
+        }
     }
 }
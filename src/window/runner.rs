@@ -1,8 +1,10 @@
 use crate::app::Resonance;
 use crate::input::Input;
-use crate::window::{Window, WindowConfig, WindowEvent};
+use crate::window::{BackgroundState, Window, WindowConfig, WindowEvent};
 
 use crate::renderer::Renderer;
+use bevy_ecs::prelude::Mut;
+use std::time::Instant;
 use winit::{
     application::ApplicationHandler,
     event::{DeviceEvent, DeviceId, ElementState, StartCause, WindowEvent as WinitWindowEvent},
@@ -14,6 +16,7 @@ pub struct WindowApp {
     engine: Option<Resonance>,
     window_config: WindowConfig,
     should_update: bool,
+    last_update: Instant,
 }
 
 impl WindowApp {
@@ -22,40 +25,48 @@ impl WindowApp {
             engine: Some(engine),
             window_config,
             should_update: false,
+            last_update: Instant::now(),
         }
     }
 
     fn update_engine(&mut self) {
-        if let Some(ref mut engine) = self.engine {
-            if engine.is_running() {
-                engine.update();
+        if let Some(engine) = self.engine.as_mut()
+            && engine.is_running()
+        {
+            engine.update();
 
-                if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
-                    input.update();
-                }
+            if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
+                input.update();
             }
         }
     }
+
+    fn input_mut(&mut self) -> Option<Mut<'_, Input>> {
+        self.engine.as_mut()?.world.get_resource_mut::<Input>()
+    }
 }
 
 impl ApplicationHandler for WindowApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let Some(ref mut engine) = self.engine {
-            if !engine.world.contains_resource::<Window>() {
-                let window = match Window::new(event_loop, &self.window_config) {
-                    Ok(w) => w,
-                    Err(e) => {
-                        log::error!("Failed to create window: {}", e);
-                        event_loop.exit();
-                        return;
-                    }
-                };
-
-                engine.world.insert_resource(window);
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        if engine.world.contains_resource::<Window>() {
+            return;
+        }
 
-                engine.startup();
+        let window = match Window::new(event_loop, &self.window_config) {
+            Ok(w) => w,
+            Err(e) => {
+                log::error!("Failed to create window: {}", e);
+                event_loop.exit();
+                return;
             }
-        }
+        };
+
+        engine.world.insert_resource(window);
+
+        engine.startup();
     }
 
     fn window_event(
@@ -94,17 +105,15 @@ impl ApplicationHandler for WindowApp {
                 }
             }
             WinitWindowEvent::KeyboardInput { event, .. } => {
-                if let Some(ref mut engine) = self.engine {
-                    if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
-                        if let winit::keyboard::PhysicalKey::Code(key_code) = event.physical_key {
-                            match event.state {
-                                ElementState::Pressed => {
-                                    input.keyboard.press(key_code);
-                                }
-                                ElementState::Released => {
-                                    input.keyboard.release(key_code);
-                                }
-                            }
+                if let (Some(mut input), winit::keyboard::PhysicalKey::Code(key_code)) =
+                    (self.input_mut(), event.physical_key)
+                {
+                    match event.state {
+                        ElementState::Pressed => {
+                            input.keyboard.press(key_code);
+                        }
+                        ElementState::Released => {
+                            input.keyboard.release(key_code);
                         }
                     }
                 }
@@ -116,29 +125,25 @@ impl ApplicationHandler for WindowApp {
                 // See commit 94c45e2 "fix: camera moving on mac" which switched to raw motion events.
             }
             WinitWindowEvent::MouseInput { state, button, .. } => {
-                if let Some(ref mut engine) = self.engine {
-                    if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
-                        match state {
-                            ElementState::Pressed => {
-                                input.mouse.press_button(button);
-                            }
-                            ElementState::Released => {
-                                input.mouse.release_button(button);
-                            }
+                if let Some(mut input) = self.input_mut() {
+                    match state {
+                        ElementState::Pressed => {
+                            input.mouse.press_button(button);
+                        }
+                        ElementState::Released => {
+                            input.mouse.release_button(button);
                         }
                     }
                 }
             }
             WinitWindowEvent::MouseWheel { delta, .. } => {
-                if let Some(ref mut engine) = self.engine {
-                    if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
-                        match delta {
-                            winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                                input.mouse.scroll(y * 10.0);
-                            }
-                            winit::event::MouseScrollDelta::PixelDelta(pos) => {
-                                input.mouse.scroll(pos.y as f32);
-                            }
+                if let Some(mut input) = self.input_mut() {
+                    match delta {
+                        winit::event::MouseScrollDelta::LineDelta(_, y) => {
+                            input.mouse.scroll(y * 10.0);
+                        }
+                        winit::event::MouseScrollDelta::PixelDelta(pos) => {
+                            input.mouse.scroll(pos.y as f32);
                         }
                     }
                 }
@@ -156,19 +161,28 @@ impl ApplicationHandler for WindowApp {
         _device_id: DeviceId,
         event: DeviceEvent,
     ) {
-        match event {
-            DeviceEvent::MouseMotion { delta } => {
-                if let Some(ref mut engine) = self.engine {
-                    if let Some(mut input) = engine.world.get_resource_mut::<Input>() {
-                        input.mouse.add_motion_delta(delta.0 as f32, delta.1 as f32);
-                    }
-                }
-            }
-            _ => {}
+        if let (DeviceEvent::MouseMotion { delta }, Some(mut input)) = (event, self.input_mut()) {
+            input.mouse.add_motion_delta(delta.0 as f32, delta.1 as f32);
         }
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // Throttled in the background: sleep until the next frame is due
+        let frame_time = self.engine.as_ref().and_then(|engine| {
+            engine
+                .world
+                .get_resource::<BackgroundState>()
+                .and_then(BackgroundState::frame_time)
+        });
+        if let Some(frame_time) = frame_time {
+            let next_update = self.last_update + frame_time;
+            if Instant::now() < next_update {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(next_update));
+                return;
+            }
+        }
+        self.last_update = Instant::now();
+
         self.update_engine();
 
         if let Some(ref engine) = self.engine {
@@ -182,6 +196,13 @@ impl ApplicationHandler for WindowApp {
         }
     }
 
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // While the event loop is still alive, so the surface goes before the window
+        if let Some(mut engine) = self.engine.take() {
+            engine.shutdown();
+        }
+    }
+
     fn new_events(&mut self, event_loop: &ActiveEventLoop, _cause: StartCause) {
         event_loop.set_control_flow(ControlFlow::Poll);
     }
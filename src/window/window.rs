@@ -1,3 +1,4 @@
+use crate::window::BackgroundPolicy;
 use bevy_ecs::prelude::*;
 use std::sync::Arc;
 use winit::{
@@ -160,6 +161,8 @@ pub struct WindowConfig {
     pub resizable: bool,
     pub vsync: bool,
     pub mode: WindowMode,
+    /// Behavior while the window doesn't have focus.
+    pub background: BackgroundPolicy,
 }
 
 impl WindowConfig {
@@ -171,6 +174,7 @@ impl WindowConfig {
             resizable: true,
             vsync: true,
             mode: WindowMode::Windowed,
+            background: BackgroundPolicy::NONE,
         }
     }
 
@@ -183,6 +187,7 @@ impl WindowConfig {
             resizable: true,
             vsync: true,
             mode: WindowMode::Windowed,
+            background: BackgroundPolicy::NONE,
         }
     }
 
@@ -195,6 +200,7 @@ impl WindowConfig {
             resizable: false,
             vsync: true,
             mode: WindowMode::Fullscreen,
+            background: BackgroundPolicy::NONE,
         }
     }
 
@@ -207,6 +213,7 @@ impl WindowConfig {
             resizable: false,
             vsync: true,
             mode: WindowMode::BorderlessFullscreen,
+            background: BackgroundPolicy::NONE,
         }
     }
 
@@ -233,6 +240,12 @@ impl WindowConfig {
         self.vsync = vsync;
         self
     }
+
+    /// Set what happens while the window is unfocused
+    pub fn with_background_policy(mut self, policy: BackgroundPolicy) -> Self {
+        self.background = policy;
+        self
+    }
 }
 
 impl Default for WindowConfig {
@@ -244,6 +257,7 @@ impl Default for WindowConfig {
             resizable: true,
             vsync: true,
             mode: WindowMode::Windowed,
+            background: BackgroundPolicy::NONE,
         }
     }
 }